@@ -0,0 +1,34 @@
+import { describe, expect, test } from "bun:test";
+import { Database } from "bun:sqlite";
+import { applyKey, exportEncrypted, isValidKey, supportsEncryption } from "./encryption";
+
+const KEY = "ab".repeat(32);
+
+describe("isValidKey", () => {
+  test("accepts 64 lowercase hex characters", () => {
+    expect(isValidKey(KEY)).toBe(true);
+  });
+
+  test("rejects anything that could break out of a pragma", () => {
+    expect(isValidKey("ab".repeat(31))).toBe(false);
+    expect(isValidKey(`${"ab".repeat(31)}'"`)).toBe(false);
+    expect(isValidKey("AB".repeat(32))).toBe(false);
+  });
+});
+
+describe("without SQLCipher", () => {
+  const db = new Database(":memory:");
+
+  test("reports encryption as unsupported", () => {
+    expect(supportsEncryption(db)).toBe(false);
+  });
+
+  test("refuses to key or export the database", () => {
+    expect(() => applyKey(db, KEY)).toThrow("SQLCipher");
+    expect(() => exportEncrypted(db, ":memory:", KEY)).toThrow("SQLCipher");
+  });
+
+  test("checks the key before anything else", () => {
+    expect(() => applyKey(db, "nope")).toThrow("64 hex characters");
+  });
+});
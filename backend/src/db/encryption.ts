@@ -0,0 +1,47 @@
+/**
+ * Database encryption through SQLCipher. The desktop app keeps the key in the
+ * OS keychain and hands it over in HAMBA_DB_KEY; the SQLite build in use must
+ * be SQLCipher (see HAMBA_SQLITE_LIB) for any of this to work.
+ */
+
+import type { Database } from "bun:sqlite";
+
+// Raw 256-bit keys only, so they can go into PRAGMA statements unquoted
+const KEY_PATTERN = /^[0-9a-f]{64}$/;
+
+export function isValidKey(key: string): boolean {
+  return KEY_PATTERN.test(key);
+}
+
+// SQLCipher answers this pragma, plain SQLite ignores it
+export function supportsEncryption(db: Database): boolean {
+  const row = db.prepare("PRAGMA cipher_version").get() as { cipher_version?: string } | null;
+  return Boolean(row?.cipher_version);
+}
+
+function checkKey(db: Database, key: string) {
+  if (!isValidKey(key)) {
+    throw new Error("Database key must be 64 hex characters");
+  }
+  if (!supportsEncryption(db)) {
+    throw new Error("This SQLite build can't encrypt databases, SQLCipher is required");
+  }
+}
+
+// Must run before anything else touches the database
+export function applyKey(db: Database, key: string) {
+  checkKey(db, key);
+  db.run(`PRAGMA key = "x'${key}'"`);
+}
+
+// Writes an encrypted copy of `db` to `path`, which must not exist yet
+export function exportEncrypted(db: Database, path: string, key: string) {
+  checkKey(db, key);
+  db.prepare(`ATTACH DATABASE ? AS encrypted KEY "x'${key}'"`).run(path);
+  try {
+    // One transaction, so rows written meanwhile don't land halfway
+    db.transaction(() => db.run("SELECT sqlcipher_export('encrypted')"))();
+  } finally {
+    db.run("DETACH DATABASE encrypted");
+  }
+}
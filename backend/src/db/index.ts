@@ -1,9 +1,18 @@
 import { Database } from "bun:sqlite";
 import { migrate, getAppliedMigrations } from "./migrate";
+import { applyKey } from "./encryption";
+
+// Encryption needs a SQLCipher build of SQLite in place of the bundled one
+if (process.env.HAMBA_SQLITE_LIB) {
+  Database.setCustomSQLite(process.env.HAMBA_SQLITE_LIB);
+}
 
 // Support DATABASE_PATH env var for Docker deployments
 const databasePath = process.env.DATABASE_PATH || "hamba.db";
 export const db = new Database(databasePath);
+if (process.env.HAMBA_DB_KEY) {
+  applyKey(db, process.env.HAMBA_DB_KEY);
+}
 
 /**
  * Initialize the database using the migration system.
@@ -346,6 +355,24 @@ export const emailQueries = {
     WHERE remind_at IS NOT NULL AND remind_at <= unixepoch() AND is_trashed = 0
   `),
 
+  // Reminders due before a time, soonest first (for the desktop tray)
+  getRemindersDueBefore: db.prepare(`
+    SELECT id, subject, from_name, from_email, remind_at FROM emails
+    WHERE remind_at IS NOT NULL AND remind_at < ? AND is_trashed = 0
+    ORDER BY remind_at ASC
+    LIMIT ?
+  `),
+
+  countRemindersDueBefore: db.prepare(`
+    SELECT COUNT(*) as count FROM emails
+    WHERE remind_at IS NOT NULL AND remind_at < ? AND is_trashed = 0
+  `),
+
+  countUnreadInbox: db.prepare(`
+    SELECT COUNT(*) as count FROM emails
+    WHERE is_read = 0 AND is_archived = 0 AND is_trashed = 0 AND folder = 'inbox'
+  `),
+
   // Get the most recent email for an account (for notifications)
   getLatest: db.prepare(`
     SELECT from_name, from_email, subject, is_important FROM emails
@@ -591,6 +618,15 @@ export const contactQueries = {
       contact_count = contacts.contact_count + 1
   `),
 
+  // Imported contacts haven't been written to yet, so they keep a count of 0
+  // and don't replace a name the user already has
+  importContact: db.prepare(`
+    INSERT INTO contacts (id, account_id, email, name, last_contacted, contact_count)
+    VALUES (?, ?, ?, ?, 0, 0)
+    ON CONFLICT(account_id, email) DO UPDATE SET
+      name = COALESCE(contacts.name, excluded.name)
+  `),
+
   delete: db.prepare("DELETE FROM contacts WHERE id = ?"),
 
   deleteByAccount: db.prepare("DELETE FROM contacts WHERE account_id = ?"),
@@ -631,3 +667,19 @@ export const emailLabelQueries = {
     WHERE email_labels.label_id = ? AND emails.is_trashed = 0
   `),
 };
+
+// Files handed over by the desktop app, see routes/imports.ts
+export const importQueries = {
+  insert: db.prepare(`
+    INSERT INTO imports (id, path, name, kind, size)
+    VALUES (?, ?, ?, ?, ?)
+  `),
+
+  getById: db.prepare("SELECT * FROM imports WHERE id = ?"),
+
+  getRecent: db.prepare(`
+    SELECT * FROM imports ORDER BY created_at DESC, rowid DESC LIMIT ?
+  `),
+
+  delete: db.prepare("DELETE FROM imports WHERE id = ?"),
+};
@@ -0,0 +1,54 @@
+import { describe, expect, test } from "bun:test";
+import { Database } from "bun:sqlite";
+import { compact, repair, verify } from "./maintenance";
+
+function createDb(): Database {
+  const db = new Database(":memory:");
+  db.run("PRAGMA foreign_keys = OFF");
+  db.run("CREATE TABLE accounts (id TEXT PRIMARY KEY)");
+  db.run("CREATE TABLE emails (id TEXT PRIMARY KEY, account_id TEXT REFERENCES accounts(id), body TEXT)");
+  db.run("CREATE INDEX idx_emails_account ON emails(account_id)");
+  db.run("INSERT INTO accounts (id) VALUES ('a')");
+  return db;
+}
+
+describe("compact", () => {
+  test("shrinks the file after rows are deleted", () => {
+    const db = createDb();
+    const insert = db.prepare("INSERT INTO emails (id, account_id, body) VALUES (?, 'a', ?)");
+    for (let i = 0; i < 500; i++) {
+      insert.run(String(i), "x".repeat(2000));
+    }
+    db.run("DELETE FROM emails");
+    const pages = () => (db.prepare("PRAGMA page_count").get() as { page_count: number }).page_count;
+    const before = pages();
+
+    const lines: string[] = [];
+    expect(compact(db, (line) => lines.push(line))).toEqual([]);
+    expect(pages()).toBeLessThan(before);
+    expect(lines.at(-1)).toStartWith("Compacted from");
+  });
+});
+
+describe("verify", () => {
+  test("finds nothing wrong with a healthy database", () => {
+    expect(verify(createDb(), () => {})).toEqual([]);
+  });
+
+  test("reports rows pointing at missing parents", () => {
+    const db = createDb();
+    db.run("INSERT INTO emails (id, account_id) VALUES ('1', 'gone')");
+    const problems = verify(db, () => {});
+    expect(problems).toHaveLength(1);
+    expect(problems[0]).toContain("missing accounts row");
+  });
+});
+
+describe("repair", () => {
+  test("logs each step and checks the result", () => {
+    const lines: string[] = [];
+    expect(repair(createDb(), (line) => lines.push(line))).toEqual([]);
+    expect(lines).toContain("Rebuilding indexes");
+    expect(lines.at(-1)).toBe("No problems found");
+  });
+});
@@ -0,0 +1,105 @@
+/**
+ * Database maintenance the desktop app runs with the server stopped, through
+ * `backend maintenance <task>` (see src/sidecar.ts), so nothing else holds a
+ * lock on the file. Progress goes to stdout one line at a time, which the
+ * app passes on to the frontend.
+ */
+
+import { Database } from "bun:sqlite";
+import { applyKey } from "./encryption";
+
+type Log = (line: string) => void;
+// Resolves with the problems left in the database, if any
+type Task = (db: Database, log: Log) => string[];
+
+// Exit code for a task that ran but found problems it couldn't fix
+export const PROBLEMS_FOUND = 2;
+
+function size(db: Database): number {
+  const { page_count } = db.prepare("PRAGMA page_count").get() as { page_count: number };
+  const { page_size } = db.prepare("PRAGMA page_size").get() as { page_size: number };
+  return page_count * page_size;
+}
+
+function megabytes(bytes: number): string {
+  return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
+
+// Gives the space of deleted rows back to the OS
+export function compact(db: Database, log: Log): string[] {
+  const before = size(db);
+  log("Checkpointing the write-ahead log");
+  db.run("PRAGMA wal_checkpoint(TRUNCATE)");
+  log("Rebuilding the database file");
+  db.run("VACUUM");
+  log(`Compacted from ${megabytes(before)} to ${megabytes(size(db))}`);
+  return [];
+}
+
+export function verify(db: Database, log: Log): string[] {
+  log("Checking the database's structure");
+  const problems = (db.prepare("PRAGMA integrity_check").all() as { integrity_check: string }[])
+    .map((row) => row.integrity_check)
+    .filter((message) => message !== "ok");
+
+  log("Checking references between tables");
+  const orphans = db.prepare("PRAGMA foreign_key_check").all() as {
+    table: string;
+    rowid: number | null;
+    parent: string;
+  }[];
+  problems.push(
+    ...orphans.map((row) => `${row.table} row ${row.rowid} points to a missing ${row.parent} row`)
+  );
+
+  log(problems.length === 0 ? "No problems found" : `Found ${problems.length} problems`);
+  return problems;
+}
+
+// Broken indexes are the usual culprit and are rebuilt from the tables.
+// Damaged table pages can't be fixed this way and are reported again.
+export function repair(db: Database, log: Log): string[] {
+  log("Rebuilding indexes");
+  db.run("REINDEX");
+  log("Rebuilding the database file");
+  db.run("VACUUM");
+  return verify(db, log);
+}
+
+const TASKS: Record<string, Task> = { compact, verify, repair };
+
+/** Runs `task` on the database the server would open, returning the exit code */
+export function runMaintenance(task: string | undefined): number {
+  const run = task ? TASKS[task] : undefined;
+  if (!run) {
+    console.error(`Unknown maintenance task ${task}, expected one of ${Object.keys(TASKS).join(", ")}`);
+    return 1;
+  }
+
+  // Same setup as ./index.ts, without the migrations a damaged file may fail
+  if (process.env.HAMBA_SQLITE_LIB) {
+    Database.setCustomSQLite(process.env.HAMBA_SQLITE_LIB);
+  }
+  let db: Database | undefined;
+  try {
+    db = new Database(process.env.DATABASE_PATH || "hamba.db", { readwrite: true, create: false });
+    if (process.env.HAMBA_DB_KEY) {
+      applyKey(db, process.env.HAMBA_DB_KEY);
+    }
+    const problems = run(db, (line) => console.log(line));
+    for (const problem of problems) {
+      console.log(`Problem: ${problem}`);
+    }
+    return problems.length === 0 ? 0 : PROBLEMS_FOUND;
+  } catch (error) {
+    console.error(error instanceof Error ? error.message : String(error));
+    return 1;
+  } finally {
+    db?.close();
+  }
+}
+
+// CLI entry point, e.g. `bun run src/db/maintenance.ts verify`
+if (import.meta.main) {
+  process.exit(runMaintenance(process.argv[2]));
+}
@@ -0,0 +1,29 @@
+import { Database } from "bun:sqlite";
+
+/**
+ * Migration: Add imports table
+ * Version: 12
+ *
+ * Files the desktop app copied into its import folder, dropped on a window
+ * or picked up from a watched folder, waiting to be read in
+ */
+
+export function up(db: Database): void {
+  db.run(`
+    CREATE TABLE imports (
+      id TEXT PRIMARY KEY,
+      path TEXT NOT NULL,
+      name TEXT NOT NULL,
+      kind TEXT NOT NULL,
+      size INTEGER NOT NULL,
+      status TEXT NOT NULL DEFAULT 'queued',
+      error TEXT,
+      created_at INTEGER DEFAULT (unixepoch())
+    )
+  `);
+  db.run(`CREATE INDEX idx_imports_status ON imports(status)`);
+}
+
+export function down(db: Database): void {
+  db.run(`DROP TABLE IF EXISTS imports`);
+}
@@ -0,0 +1,40 @@
+import { afterEach, describe, expect, test } from "bun:test";
+import { Database } from "bun:sqlite";
+import { mkdtempSync, rmSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { writeSnapshot } from "./snapshot";
+
+let dir: string | undefined;
+
+afterEach(() => {
+  if (dir) rmSync(dir, { recursive: true, force: true });
+  dir = undefined;
+});
+
+describe("writeSnapshot", () => {
+  test("copies every row while the database stays open", () => {
+    dir = mkdtempSync(join(tmpdir(), "hamba-snapshot-"));
+    const db = new Database(join(dir, "hamba.db"));
+    db.run("PRAGMA journal_mode = WAL");
+    db.run("CREATE TABLE emails (id TEXT PRIMARY KEY)");
+    db.run("INSERT INTO emails (id) VALUES ('1'), ('2')");
+
+    const path = join(dir, "snapshot.db");
+    writeSnapshot(db, path, undefined);
+    db.run("INSERT INTO emails (id) VALUES ('3')");
+
+    const copy = new Database(path, { readonly: true });
+    expect(copy.prepare("SELECT count(*) AS count FROM emails").get()).toEqual({ count: 2 });
+    copy.close();
+    db.close();
+  });
+
+  test("refuses to overwrite a file", () => {
+    dir = mkdtempSync(join(tmpdir(), "hamba-snapshot-"));
+    const db = new Database(":memory:");
+    const path = join(dir, "snapshot.db");
+    writeSnapshot(db, path, undefined);
+    expect(() => writeSnapshot(db, path, undefined)).toThrow();
+  });
+});
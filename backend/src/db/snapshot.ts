@@ -0,0 +1,17 @@
+/**
+ * Copies of the live database for the desktop app's backups, so the server
+ * keeps running while one is taken.
+ */
+
+import type { Database } from "bun:sqlite";
+import { exportEncrypted } from "./encryption";
+
+// Writes `db` as it stood at one point in time to `path`, which must not
+// exist yet. An encrypted database is copied encrypted with the same key.
+export function writeSnapshot(db: Database, path: string, key: string | undefined = process.env.HAMBA_DB_KEY) {
+  if (key) {
+    exportEncrypted(db, path, key);
+    return;
+  }
+  db.prepare("VACUUM INTO ?").run(path);
+}
@@ -9,15 +9,30 @@ import { snippetRoutes } from "./routes/snippets";
 import { contactRoutes } from "./routes/contacts";
 import { aiRoutes } from "./routes/ai";
 import { signatureRoutes } from "./routes/signatures";
+import { exportRoutes } from "./routes/export";
+import { backgroundRoutes } from "./routes/background";
+import { controlRoutes } from "./routes/control";
+import { encryptionRoutes } from "./routes/encryption";
+import { backupRoutes } from "./routes/backup";
+import { importRoutes } from "./routes/imports";
 // Database is initialized on import
 import "./db";
 import { emailQueries } from "./db";
 import { addClient, removeClient, subscribeToAccount, notifySyncComplete } from "./services/realtime";
 import { startAllIdle, getIdleStatus } from "./services/imap-idle";
+import { isCanary, isPowerSaving } from "./services/background-jobs";
+import { setHeartbeatInterval } from "./services/heartbeat";
+import { setShutdownHandler } from "./services/shutdown";
 import { startPendingSendProcessor } from "./services/pending-send";
 import { startScheduledSendProcessor } from "./services/scheduled-send";
+import { refreshTrayStatus } from "./services/tray-status";
 import { logger, errorTracking } from "./services/logger";
 import { loggingMiddleware, metricsEndpoints } from "./services/logging-middleware";
+import { desktopAuth } from "./services/desktop-auth";
+import { lanWeb } from "./services/lan-web";
+
+const TLS_CERT = process.env.HAMBA_TLS_CERT;
+const TLS_KEY = process.env.HAMBA_TLS_KEY;
 
 interface WebSocketData {
   accountIds: Set<string>;
@@ -28,8 +43,10 @@ const app = new Elysia()
     origin: "http://localhost:8878",
     credentials: true,
     methods: ["GET", "POST", "PUT", "DELETE", "OPTIONS"],
-    allowedHeaders: ["Content-Type", "Authorization"],
+    allowedHeaders: ["Content-Type", "Authorization", "X-Hamba-Token"],
   }))
+  .use(lanWeb)
+  .use(desktopAuth)
   .use(loggingMiddleware)
   .use(swagger({
     documentation: {
@@ -51,6 +68,12 @@ const app = new Elysia()
         { name: "Contacts", description: "Contact management" },
         { name: "AI", description: "AI-powered email features" },
         { name: "Signatures", description: "Email signature management" },
+        { name: "Export", description: "Data export" },
+        { name: "Background", description: "Pausing background jobs" },
+        { name: "Control", description: "Log level and feature flags at runtime" },
+        { name: "Encryption", description: "Database encryption" },
+        { name: "Backup", description: "Database snapshots for backups" },
+        { name: "Imports", description: "Files handed over by the desktop app" },
         { name: "Realtime", description: "Real-time update status" },
       ],
       components: {
@@ -197,20 +220,33 @@ const app = new Elysia()
     path: "/docs",
     exclude: ["/ws"],
   }))
-  .get("/health", () => ({ status: "ok" }), {
-    detail: {
-      tags: ["Health"],
-      summary: "Health check",
-      description: "Returns the API health status",
-      responses: {
-        200: {
-          description: "API is healthy",
-          content: {
-            "application/json": {
-              schema: {
-                type: "object",
-                properties: {
-                  status: { type: "string", example: "ok" },
+  .get(
+    "/health",
+    () => ({
+      status: "ok",
+      version: process.env.APP_VERSION ?? null,
+      // Lets the desktop app recognize a backend it started on an earlier launch
+      instance: process.env.HAMBA_INSTANCE_ID ?? null,
+      pid: process.pid,
+    }),
+    {
+      detail: {
+        tags: ["Health"],
+        summary: "Health check",
+        description: "Returns the API health status",
+        responses: {
+          200: {
+            description: "API is healthy",
+            content: {
+              "application/json": {
+                schema: {
+                  type: "object",
+                  properties: {
+                    status: { type: "string", example: "ok" },
+                    version: { type: "string", nullable: true, example: "1.4.0" },
+                    instance: { type: "string", nullable: true },
+                    pid: { type: "number" },
+                  },
                 },
               },
             },
@@ -218,7 +254,7 @@ const app = new Elysia()
         },
       },
     },
-  })
+  )
   .use(metricsEndpoints)
   .use(authRoutes)
   .use(emailRoutes)
@@ -228,6 +264,12 @@ const app = new Elysia()
   .use(contactRoutes)
   .use(aiRoutes)
   .use(signatureRoutes)
+  .use(exportRoutes)
+  .use(backgroundRoutes)
+  .use(controlRoutes)
+  .use(encryptionRoutes)
+  .use(backupRoutes)
+  .use(importRoutes)
   // WebSocket for real-time updates
   .ws("/ws", {
     open(ws) {
@@ -285,18 +327,47 @@ const app = new Elysia()
       },
     },
   })
-  .listen(8877);
+  .listen({
+    port: Number(process.env.PORT) || 8877,
+    // The desktop app keeps the backend on loopback unless LAN access is on
+    ...(process.env.HAMBA_HOST ? { hostname: process.env.HAMBA_HOST } : {}),
+    // The desktop app hands over a per-install localhost certificate
+    ...(TLS_CERT && TLS_KEY ? { tls: { cert: Bun.file(TLS_CERT), key: Bun.file(TLS_KEY) } } : {}),
+  });
+
+logger.info("Hamba API started", {
+  port: app.server?.port,
+  url: `${TLS_CERT && TLS_KEY ? "https" : "http"}://localhost:${app.server?.port}`,
+});
+
+// A canary leaves mail alone, see services/background-jobs
+if (!isCanary()) {
+  // Start IMAP IDLE connections for all IMAP accounts, unless the desktop app
+  // started us in battery saver mode
+  if (!isPowerSaving()) {
+    startAllIdle().catch((err) => errorTracking.captureException(err, { context: "startAllIdle" }));
+  }
 
-logger.info("Hamba API started", { port: app.server?.port, url: `http://localhost:${app.server?.port}` });
+  // Start pending send processor for undo send feature
+  startPendingSendProcessor();
 
-// Start IMAP IDLE connections for all IMAP accounts
-startAllIdle().catch((err) => errorTracking.captureException(err, { context: "startAllIdle" }));
+  // Start scheduled send processor for send later feature
+  startScheduledSendProcessor();
+}
 
-// Start pending send processor for undo send feature
-startPendingSendProcessor();
+// The desktop app's watchdog waits for these, see services/heartbeat
+setHeartbeatInterval(Number(process.env.HAMBA_HEARTBEAT_MS));
 
-// Start scheduled send processor for send later feature
-startScheduledSendProcessor();
+// The desktop app stops the backend with SIGTERM (e.g. when swapping in a new
+// binary), or on Windows with POST /control/shutdown; stop accepting
+// connections but let requests in flight finish
+async function drainAndExit(reason: string) {
+  logger.info(`${reason}, draining requests`);
+  await app.server?.stop(false);
+  process.exit(0);
+}
+process.on("SIGTERM", () => drainAndExit("Received SIGTERM"));
+setShutdownHandler(() => drainAndExit("Asked to shut down"));
 
 // Cleanup old trashed emails (30+ days old)
 function cleanupOldTrashedEmails() {
@@ -336,4 +407,9 @@ setInterval(cleanupOldTrashedEmails, 60 * 60 * 1000);
 unsnoozeDueEmails();
 setInterval(unsnoozeDueEmails, 60 * 1000);
 
+// Counts in the desktop tray change as mail is read or reminders come due;
+// only changes are sent
+refreshTrayStatus();
+setInterval(refreshTrayStatus, 30 * 1000);
+
 export type App = typeof app;
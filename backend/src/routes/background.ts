@@ -0,0 +1,51 @@
+import { Elysia, t } from "elysia";
+import {
+  isPaused,
+  isPowerSaving,
+  pauseBackgroundJobs,
+  resumeBackgroundJobs,
+  setPowerSaving,
+} from "../services/background-jobs";
+import { getHeartbeatInterval, setHeartbeatInterval } from "../services/heartbeat";
+
+export const backgroundRoutes = new Elysia({ prefix: "/background", detail: { tags: ["Background"] } })
+  .get("/", () => ({ paused: isPaused(), powerSaving: isPowerSaving(), heartbeatMs: getHeartbeatInterval() }), {
+    detail: {
+      summary: "Get background job status",
+      description: "Whether background jobs (IMAP IDLE, undo send and scheduled send) are paused, and whether battery saver mode is on",
+    },
+  })
+  .post("/pause", async () => {
+    await pauseBackgroundJobs();
+    return { paused: true };
+  }, {
+    detail: {
+      summary: "Pause background jobs",
+      description: "Stops IMAP IDLE and the send processors, e.g. before the machine sleeps",
+    },
+  })
+  .post("/resume", async () => {
+    await resumeBackgroundJobs();
+    return { paused: false };
+  }, {
+    detail: {
+      summary: "Resume background jobs",
+      description: "Restarts everything stopped by /background/pause",
+    },
+  })
+  .post("/power-saver", async ({ body }) => {
+    await setPowerSaving(body.enabled);
+    if (body.heartbeatMs !== undefined) {
+      setHeartbeatInterval(body.heartbeatMs);
+    }
+    return { powerSaving: body.enabled, heartbeatMs: getHeartbeatInterval() };
+  }, {
+    body: t.Object({
+      enabled: t.Boolean(),
+      heartbeatMs: t.Optional(t.Integer({ minimum: 1000 })),
+    }),
+    detail: {
+      summary: "Set battery saver mode",
+      description: "Stops or restarts syncing through IMAP IDLE and changes how often the heartbeat is printed",
+    },
+  });
@@ -0,0 +1,34 @@
+import { Elysia, t } from "elysia";
+import { existsSync } from "node:fs";
+import { db } from "../db";
+import { writeSnapshot } from "../db/snapshot";
+import { isAllowedPath } from "../services/allowed-dirs";
+
+// The desktop app zips the snapshot with the rest of its data directory, so
+// backups no longer need the backend stopped
+export const backupRoutes = new Elysia({ prefix: "/backup", detail: { tags: ["Backup"] } })
+  .post("/snapshot", ({ body, set }) => {
+    if (!isAllowedPath(body.path)) {
+      set.status = 403;
+      return { error: `${body.path} is outside the folders the app allows` };
+    }
+    if (existsSync(body.path)) {
+      set.status = 409;
+      return { error: `${body.path} already exists` };
+    }
+    try {
+      writeSnapshot(db, body.path);
+    } catch (error) {
+      set.status = 500;
+      return { error: (error as Error).message };
+    }
+    return { path: body.path };
+  }, {
+    body: t.Object({
+      path: t.String(),
+    }),
+    detail: {
+      summary: "Snapshot the database",
+      description: "Writes a consistent copy of the database to a new file at `path` without stopping writes. An encrypted database stays encrypted with the same key.",
+    },
+  });
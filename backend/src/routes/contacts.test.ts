@@ -163,3 +163,40 @@ describe("parseEmailAddresses", () => {
     expect(contact2.name).toBe("Jane Smith");
   });
 });
+
+describe("importContacts", () => {
+  test("adds new contacts without counting them as contacted", async () => {
+    const { importContacts } = await import("./contacts");
+
+    const result = importContacts(testAccountId, [{ email: " Imported@Example.com ", name: "Imported User" }]);
+
+    expect(result).toEqual({ imported: 1, errors: [] });
+    const contact = contactQueries.getByEmail.get(testAccountId, "imported@example.com") as any;
+    expect(contact.name).toBe("Imported User");
+    expect(contact.contact_count).toBe(0);
+  });
+
+  test("keeps the name and count of existing contacts", async () => {
+    const { importContacts } = await import("./contacts");
+    const now = Math.floor(Date.now() / 1000);
+    contactQueries.upsert.run(crypto.randomUUID(), testAccountId, "known@example.com", "Known User", now);
+
+    importContacts(testAccountId, [{ email: "known@example.com", name: "Other Name" }]);
+
+    const contact = contactQueries.getByEmail.get(testAccountId, "known@example.com") as any;
+    expect(contact.name).toBe("Known User");
+    expect(contact.contact_count).toBe(1);
+  });
+
+  test("reports invalid addresses by index", async () => {
+    const { importContacts } = await import("./contacts");
+
+    const result = importContacts(testAccountId, [
+      { email: "valid.import@example.com" },
+      { email: "not-an-address" },
+    ]);
+
+    expect(result.imported).toBe(1);
+    expect(result.errors).toEqual([{ index: 1, error: "Not an email address: not-an-address" }]);
+  });
+});
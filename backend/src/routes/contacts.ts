@@ -107,6 +107,43 @@ async function populateContactsFromEmails(accountId: string): Promise<number> {
   return count;
 }
 
+// Most contacts imported per request; the desktop app sends larger files in
+// batches
+const MAX_IMPORT_BATCH = 1000;
+
+interface ImportedContact {
+  email: string;
+  name?: string | null;
+}
+
+// Import a batch of contacts parsed from another app's export. Errors are
+// reported by the contact's index in the batch.
+export function importContacts(
+  accountId: string,
+  contacts: ImportedContact[]
+): { imported: number; errors: Array<{ index: number; error: string }> } {
+  let imported = 0;
+  const errors: Array<{ index: number; error: string }> = [];
+
+  db.transaction(() => {
+    contacts.forEach((contact, index) => {
+      const email = contact.email.trim().toLowerCase();
+      if (!email.includes("@")) {
+        errors.push({ index, error: `Not an email address: ${contact.email}` });
+        return;
+      }
+      try {
+        contactQueries.importContact.run(crypto.randomUUID(), accountId, email, contact.name?.trim() || null);
+        imported++;
+      } catch (error) {
+        errors.push({ index, error: error instanceof Error ? error.message : String(error) });
+      }
+    });
+  })();
+
+  return { imported, errors };
+}
+
 export const contactRoutes = new Elysia({ prefix: "/contacts", detail: { tags: ["Contacts"] } })
   // Get all contacts for an account
   .get("/", ({ query }) => {
@@ -226,6 +263,65 @@ export const contactRoutes = new Elysia({ prefix: "/contacts", detail: { tags: [
     },
   })
 
+  // Import contacts from another app, see the desktop app's importers
+  .post("/import", ({ body }) => {
+    const { accountId, contacts } = body;
+
+    const account = accountQueries.getById.get(accountId);
+    if (!account) {
+      return { success: false, error: "Account not found" };
+    }
+    if (contacts.length > MAX_IMPORT_BATCH) {
+      return { success: false, error: `At most ${MAX_IMPORT_BATCH} contacts can be imported at once` };
+    }
+
+    try {
+      return { success: true, ...importContacts(accountId, contacts) };
+    } catch (error) {
+      console.error("Error importing contacts:", error);
+      return { success: false, error: "Failed to import contacts" };
+    }
+  }, {
+    body: t.Object({
+      accountId: t.String(),
+      contacts: t.Array(t.Object({
+        email: t.String(),
+        name: t.Optional(t.Union([t.String(), t.Null()])),
+      })),
+    }),
+    detail: {
+      summary: "Import contacts",
+      description: "Adds a batch of contacts exported from another app. Existing contacts keep their names and counts.",
+      responses: {
+        200: {
+          description: "Success with the number imported and per-contact errors, or an error",
+          content: {
+            "application/json": {
+              schema: {
+                type: "object",
+                properties: {
+                  success: { type: "boolean" },
+                  imported: { type: "integer", description: "Number of contacts added or updated" },
+                  errors: {
+                    type: "array",
+                    items: {
+                      type: "object",
+                      properties: {
+                        index: { type: "integer", description: "Position of the contact in the batch" },
+                        error: { type: "string" },
+                      },
+                    },
+                  },
+                  error: { type: "string" },
+                },
+              },
+            },
+          },
+        },
+      },
+    },
+  })
+
   // Add or update a contact manually
   .post("/", ({ body }) => {
     const { accountId, email, name } = body;
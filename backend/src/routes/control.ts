@@ -0,0 +1,57 @@
+import { Elysia, t } from "elysia";
+import { configureLogger, getLoggerConfig, logger } from "../services/logger";
+import { listFlags, setFlag } from "../services/flags";
+import { canShutDown, requestShutdown } from "../services/shutdown";
+
+// Lets the desktop app change the log level and feature flags without a
+// restart. It also passes them as LOG_LEVEL and HAMBA_FLAGS on the next start.
+export const controlRoutes = new Elysia({ prefix: "/control", detail: { tags: ["Control"] } })
+  .get("/", () => ({ logLevel: getLoggerConfig().level, flags: listFlags() }), {
+    detail: {
+      summary: "Get runtime settings",
+      description: "The current log level and feature flags",
+    },
+  })
+  .post("/log-level", ({ body }) => {
+    configureLogger({ level: body.level });
+    logger.info("Log level changed", { level: body.level });
+    return { logLevel: body.level };
+  }, {
+    body: t.Object({
+      level: t.Union([t.Literal("debug"), t.Literal("info"), t.Literal("warn"), t.Literal("error")]),
+    }),
+    detail: {
+      summary: "Set log level",
+      description: "Changes which log entries are written, until the backend restarts",
+    },
+  })
+  .post("/flags", ({ body }) => {
+    setFlag(body.name, body.value);
+    logger.info("Feature flag changed", { flag: body.name, value: body.value });
+    return { flags: listFlags() };
+  }, {
+    body: t.Object({
+      name: t.String({ minLength: 1 }),
+      value: t.Union([t.Boolean(), t.Null()]),
+    }),
+    detail: {
+      summary: "Set feature flag",
+      description: "Turns a feature flag on or off, or with null back to its default, until the backend restarts",
+    },
+  })
+  .post("/shutdown", ({ request, set }) => {
+    if (!canShutDown(request.headers.get("x-hamba-token"))) {
+      set.status = 403;
+      return { error: "Only the desktop app can shut the backend down" };
+    }
+    if (!requestShutdown()) {
+      set.status = 503;
+      return { error: "The backend can't shut down yet" };
+    }
+    return { success: true };
+  }, {
+    detail: {
+      summary: "Shut down",
+      description: "Stops accepting connections, lets requests in flight finish and exits, as on SIGTERM. Windows has no signal for the desktop app to send.",
+    },
+  });
@@ -3,6 +3,7 @@ import { emailQueries, accountQueries, attachmentQueries, scheduledEmailQueries,
 import { getProvider } from "../services/providers";
 import { notifySyncComplete, notifyBackgroundSyncProgress } from "../services/realtime";
 import { queueSend, cancelSend, UNDO_WINDOW_SECONDS } from "../services/pending-send";
+import { refreshTrayStatus } from "../services/tray-status";
 import {
   scheduleEmail,
   cancelScheduledEmail,
@@ -672,6 +673,7 @@ export const emailRoutes = new Elysia({ prefix: "/emails", detail: { tags: ["Ema
     }
 
     emailQueries.setReminder.run(remindAt, params.id);
+    refreshTrayStatus();
     return { success: true };
   })
 
@@ -682,6 +684,7 @@ export const emailRoutes = new Elysia({ prefix: "/emails", detail: { tags: ["Ema
     }
 
     emailQueries.clearReminder.run(params.id);
+    refreshTrayStatus();
     return { success: true };
   })
 
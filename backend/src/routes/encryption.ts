@@ -0,0 +1,39 @@
+import { Elysia, t } from "elysia";
+import { existsSync } from "node:fs";
+import { db } from "../db";
+import { exportEncrypted, supportsEncryption } from "../db/encryption";
+import { isAllowedPath } from "../services/allowed-dirs";
+
+export const encryptionRoutes = new Elysia({ prefix: "/encryption", detail: { tags: ["Encryption"] } })
+  .get("/", () => ({ supported: supportsEncryption(db) }), {
+    detail: {
+      summary: "Get encryption support",
+      description: "Whether the SQLite build in use can encrypt the database",
+    },
+  })
+  .post("/export", ({ body, set }) => {
+    if (!isAllowedPath(body.path)) {
+      set.status = 403;
+      return { error: `${body.path} is outside the folders the app allows` };
+    }
+    if (existsSync(body.path)) {
+      set.status = 409;
+      return { error: `${body.path} already exists` };
+    }
+    try {
+      exportEncrypted(db, body.path, body.key);
+    } catch (error) {
+      set.status = 400;
+      return { error: (error as Error).message };
+    }
+    return { path: body.path };
+  }, {
+    body: t.Object({
+      path: t.String(),
+      key: t.String(),
+    }),
+    detail: {
+      summary: "Export an encrypted copy of the database",
+      description: "Writes the whole database, encrypted with `key`, to a new file at `path`. The desktop app then restarts the backend on the copy.",
+    },
+  });
@@ -0,0 +1,34 @@
+import { describe, expect, test } from "bun:test";
+import { csvField, exportStream } from "./export";
+
+describe("csvField", () => {
+  test("leaves plain values alone", () => {
+    expect(csvField("hello")).toBe("hello");
+    expect(csvField(42)).toBe("42");
+  });
+
+  test("writes null as an empty field", () => {
+    expect(csvField(null)).toBe("");
+  });
+
+  test("quotes values with separators, quotes or line breaks", () => {
+    expect(csvField("a,b")).toBe('"a,b"');
+    expect(csvField('say "hi"')).toBe('"say ""hi"""');
+    expect(csvField("line\nbreak")).toBe('"line\nbreak"');
+  });
+});
+
+describe("exportStream", () => {
+  const noSuchAccount = `export-test-${Date.now()}`;
+
+  test("writes an empty JSON array when there is nothing to export", async () => {
+    const text = await new Response(exportStream("json", noSuchAccount)).text();
+    expect(JSON.parse(text)).toEqual([]);
+  });
+
+  test("writes only the header row for an empty CSV export", async () => {
+    const text = await new Response(exportStream("csv", noSuchAccount)).text();
+    expect(text.split("\r\n").filter(Boolean)).toHaveLength(1);
+    expect(text.startsWith("id,account_id,")).toBe(true);
+  });
+});
@@ -0,0 +1,90 @@
+import { Elysia, t } from "elysia";
+import { db } from "../db";
+
+const EXPORT_COLUMNS = [
+  "id",
+  "account_id",
+  "thread_id",
+  "folder",
+  "received_at",
+  "from_name",
+  "from_email",
+  "to_addresses",
+  "cc_addresses",
+  "subject",
+  "labels",
+  "is_read",
+  "is_starred",
+  "body_text",
+] as const;
+
+type ExportRow = Record<(typeof EXPORT_COLUMNS)[number], string | number | null>;
+
+// Quotes fields that contain separators, quotes or line breaks (RFC 4180)
+export function csvField(value: string | number | null): string {
+  if (value === null) return "";
+  const text = String(value);
+  return /[",\r\n]/.test(text) ? `"${text.replace(/"/g, '""')}"` : text;
+}
+
+function exportStatement(accountId?: string) {
+  const columns = EXPORT_COLUMNS.join(", ");
+  return accountId
+    ? db.prepare(`SELECT ${columns} FROM emails WHERE account_id = ? ORDER BY received_at`)
+    : db.prepare(`SELECT ${columns} FROM emails ORDER BY received_at`);
+}
+
+// Streams rows as they are read so large mailboxes never sit in memory. JSON
+// puts one email per line, which lets the desktop app count progress.
+export function exportStream(format: "csv" | "json", accountId?: string): ReadableStream<Uint8Array> {
+  const rows = (accountId ? exportStatement(accountId).iterate(accountId) : exportStatement().iterate()) as IterableIterator<ExportRow>;
+  const encoder = new TextEncoder();
+  let first = true;
+
+  return new ReadableStream({
+    start(controller) {
+      controller.enqueue(encoder.encode(format === "csv" ? EXPORT_COLUMNS.join(",") + "\r\n" : "[\n"));
+    },
+    pull(controller) {
+      const next = rows.next();
+      if (next.done) {
+        if (format === "json") controller.enqueue(encoder.encode(first ? "]\n" : "\n]\n"));
+        controller.close();
+        return;
+      }
+      const row = next.value;
+      const line =
+        format === "csv"
+          ? EXPORT_COLUMNS.map((column) => csvField(row[column])).join(",") + "\r\n"
+          : (first ? "" : ",\n") + JSON.stringify(row);
+      first = false;
+      controller.enqueue(encoder.encode(line));
+    },
+  });
+}
+
+export const exportRoutes = new Elysia({ prefix: "/export", detail: { tags: ["Export"] } })
+  .get("/", ({ query }) => {
+    const format = query.format ?? "json";
+    const total = (
+      query.accountId
+        ? db.prepare("SELECT COUNT(*) AS count FROM emails WHERE account_id = ?").get(query.accountId)
+        : db.prepare("SELECT COUNT(*) AS count FROM emails").get()
+    ) as { count: number };
+
+    return new Response(exportStream(format, query.accountId), {
+      headers: {
+        "Content-Type": format === "csv" ? "text/csv; charset=utf-8" : "application/json; charset=utf-8",
+        "X-Export-Total": String(total.count),
+      },
+    });
+  }, {
+    query: t.Object({
+      format: t.Optional(t.Union([t.Literal("csv"), t.Literal("json")])),
+      accountId: t.Optional(t.String()),
+    }),
+    detail: {
+      summary: "Export emails",
+      description: "Streams all emails (or one account's) as CSV or JSON. The X-Export-Total header holds the number of emails.",
+    },
+  });
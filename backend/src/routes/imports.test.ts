@@ -0,0 +1,51 @@
+import { afterAll, describe, expect, test } from "bun:test";
+import { mkdtempSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { importQueries } from "../db";
+import { importRoutes } from "./imports";
+
+const dir = mkdtempSync(join(tmpdir(), "hamba-import-routes-"));
+const queued: string[] = [];
+
+afterAll(() => {
+  for (const id of queued) importQueries.delete.run(id);
+  rmSync(dir, { recursive: true, force: true });
+});
+
+function post(body: unknown): Promise<Response> {
+  return importRoutes.handle(
+    new Request("http://localhost/imports", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(body),
+    })
+  );
+}
+
+describe("POST /imports", () => {
+  test("queues a file the desktop app copied in", async () => {
+    const path = join(dir, "1-contacts.vcf");
+    writeFileSync(path, "BEGIN:VCARD\nEND:VCARD\n");
+    const response = await post({ path, name: "contacts.vcf", kind: "vcf", size: 22 });
+    expect(response.status).toBe(201);
+    const row = (await response.json()) as { id: string; status: string; kind: string };
+    queued.push(row.id);
+    expect(row).toMatchObject({ status: "queued", kind: "vcf" });
+
+    const list = await importRoutes.handle(new Request("http://localhost/imports?limit=5"));
+    const rows = (await list.json()) as { id: string }[];
+    expect(rows.map((listed) => listed.id)).toContain(row.id);
+  });
+
+  test("refuses what the checks reject", async () => {
+    const response = await post({ path: join(dir, "gone.eml"), name: "gone.eml", kind: "eml", size: 1 });
+    expect(response.status).toBe(404);
+    expect(await response.json()).toHaveProperty("error");
+  });
+
+  test("validates the notice", async () => {
+    const response = await post({ path: join(dir, "x.eml"), kind: "eml" });
+    expect(response.status).toBe(422);
+  });
+});
@@ -0,0 +1,41 @@
+import { Elysia, t } from "elysia";
+import { importQueries } from "../db";
+import { checkImport } from "../services/imports";
+
+// At most this many in GET /imports
+const MAX_LISTED = 100;
+
+// Files the desktop app copied into its import folder, dropped on a window
+// or picked up from a watched folder
+export const importRoutes = new Elysia({ prefix: "/imports", detail: { tags: ["Imports"] } })
+  .get("/", ({ query }) => importQueries.getRecent.all(Math.min(query.limit ?? MAX_LISTED, MAX_LISTED)), {
+    query: t.Object({
+      limit: t.Optional(t.Numeric({ minimum: 1 })),
+    }),
+    detail: {
+      summary: "List imports",
+      description: "The files handed over by the desktop app, newest first",
+    },
+  })
+  .post("/", ({ body, set }) => {
+    const check = checkImport(body);
+    if (!check.ok) {
+      set.status = check.status;
+      return { error: check.error };
+    }
+    const id = crypto.randomUUID();
+    importQueries.insert.run(id, body.path, body.name, body.kind, body.size);
+    set.status = 201;
+    return importQueries.getById.get(id);
+  }, {
+    body: t.Object({
+      path: t.String(),
+      name: t.String({ minLength: 1 }),
+      kind: t.String(),
+      size: t.Integer({ minimum: 0 }),
+    }),
+    detail: {
+      summary: "Queue an import",
+      description: "Queues a file the desktop app copied into its import folder. The file must exist, be inside the folders the app allows and have the size given.",
+    },
+  });
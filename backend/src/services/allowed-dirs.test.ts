@@ -0,0 +1,33 @@
+import { describe, expect, test } from "bun:test";
+import { delimiter } from "node:path";
+import { allowedDirs, isAllowedPath } from "./allowed-dirs";
+
+describe("allowedDirs", () => {
+  test("is null when the desktop app sets no allowlist", () => {
+    expect(allowedDirs(undefined)).toBeNull();
+    expect(allowedDirs("")).toBeNull();
+  });
+
+  test("splits the path list", () => {
+    expect(allowedDirs(["/data", "/tmp"].join(delimiter))).toEqual(["/data", "/tmp"]);
+  });
+});
+
+describe("isAllowedPath", () => {
+  const dirs = ["/data/hamba", "/tmp"];
+
+  test("allows anything without an allowlist", () => {
+    expect(isAllowedPath("/etc/passwd", null)).toBe(true);
+  });
+
+  test("allows paths inside an allowed folder", () => {
+    expect(isAllowedPath("/data/hamba/hamba.db-encrypted", dirs)).toBe(true);
+    expect(isAllowedPath("/tmp/export", dirs)).toBe(true);
+  });
+
+  test("rejects paths outside, including lookalike prefixes and ..", () => {
+    expect(isAllowedPath("/data/hamba-other/x.db", dirs)).toBe(false);
+    expect(isAllowedPath("/data/hamba/../secrets", dirs)).toBe(false);
+    expect(isAllowedPath("/home/user/.ssh/id_rsa", dirs)).toBe(false);
+  });
+});
@@ -0,0 +1,18 @@
+import { delimiter, resolve, sep } from "node:path";
+
+/**
+ * Folders the desktop app lets the backend write to, from HAMBA_ALLOWED_DIRS
+ * (a path list like PATH). Unset outside the desktop app, where any path
+ * is allowed.
+ */
+export function allowedDirs(env: string | undefined = process.env.HAMBA_ALLOWED_DIRS): string[] | null {
+  if (!env) return null;
+  return env.split(delimiter).filter(Boolean).map((dir) => resolve(dir));
+}
+
+/** Whether `path` is inside one of the allowed folders */
+export function isAllowedPath(path: string, dirs: string[] | null = allowedDirs()): boolean {
+  if (dirs === null) return true;
+  const target = resolve(path);
+  return dirs.some((dir) => target === dir || target.startsWith(dir.endsWith(sep) ? dir : dir + sep));
+}
@@ -0,0 +1,75 @@
+/**
+ * Pauses and resumes the backend's background work (IMAP IDLE, the send
+ * processors). The desktop app pauses it before the machine sleeps, since
+ * connections and timers don't survive suspend cleanly, and resumes it after.
+ *
+ * Battery saver mode, which the app turns on when the battery runs low, only
+ * stops syncing through IDLE; mail still goes out on time.
+ */
+
+import { startAllIdle, stopAllIdle } from "./imap-idle";
+import { startPendingSendProcessor, stopPendingSendProcessor } from "./pending-send";
+import { startScheduledSendProcessor, stopScheduledSendProcessor } from "./scheduled-send";
+import { logger, errorTracking } from "./logger";
+
+const jobsLogger = logger.child({ service: "background-jobs" });
+
+// A canary build the desktop app runs next to the real backend, on a copy
+// of its database, only answers requests; it must never send or sync mail
+const canary = process.env.HAMBA_CANARY === "1";
+let paused = canary;
+let powerSaving = process.env.HAMBA_POWER_SAVER === "1";
+
+export function isCanary(): boolean {
+  return canary;
+}
+
+export function isPaused(): boolean {
+  return paused;
+}
+
+export function isPowerSaving(): boolean {
+  return powerSaving;
+}
+
+export async function setPowerSaving(enabled: boolean): Promise<void> {
+  if (powerSaving === enabled) {
+    return;
+  }
+  powerSaving = enabled;
+  if (!paused) {
+    if (enabled) {
+      await stopAllIdle();
+    } else {
+      startAllIdle().catch((err) => errorTracking.captureException(err, { context: "setPowerSaving" }));
+    }
+  }
+  jobsLogger.info(enabled ? "Battery saver on, sync paused" : "Battery saver off, sync resumed");
+}
+
+export async function pauseBackgroundJobs(): Promise<void> {
+  if (paused) {
+    return;
+  }
+  paused = true;
+  stopPendingSendProcessor();
+  stopScheduledSendProcessor();
+  await stopAllIdle();
+  jobsLogger.info("Background jobs paused");
+}
+
+export async function resumeBackgroundJobs(): Promise<void> {
+  if (!paused || canary) {
+    return;
+  }
+  paused = false;
+  startPendingSendProcessor();
+  startScheduledSendProcessor();
+  if (powerSaving) {
+    jobsLogger.info("Background jobs resumed, sync stays paused for battery saver");
+    return;
+  }
+  // IDLE reconnects can take a while with many accounts; don't hold up the caller
+  startAllIdle().catch((err) => errorTracking.captureException(err, { context: "resumeBackgroundJobs" }));
+  jobsLogger.info("Background jobs resumed");
+}
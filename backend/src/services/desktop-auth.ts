@@ -0,0 +1,29 @@
+import { Elysia } from "elysia";
+
+// Set by the desktop app when it spawns the backend as a sidecar, so that only
+// its own webview can talk to the API. When unset (web and dev setups), the
+// API stays open as before.
+const AUTH_TOKEN = process.env.HAMBA_AUTH_TOKEN;
+// Set while LAN access is on; what devices paired by QR code send instead
+const LAN_TOKEN = process.env.HAMBA_LAN_TOKEN;
+
+// Health checks and the OAuth browser redirects can't carry the token
+const PUBLIC_PATHS = [/^\/health$/, /^\/auth\/login(\/[^/]+)?$/, /^\/auth(\/[^/]+)?\/callback$/];
+
+export const desktopAuth = new Elysia({ name: "desktop-auth" }).onRequest(({ request, set }) => {
+  if (!AUTH_TOKEN || request.method === "OPTIONS") {
+    return;
+  }
+
+  const url = new URL(request.url);
+  if (PUBLIC_PATHS.some((path) => path.test(url.pathname))) {
+    return;
+  }
+
+  // Images and WebSockets can't send headers, so accept a query param too
+  const token = request.headers.get("x-hamba-token") ?? url.searchParams.get("token");
+  if (token !== AUTH_TOKEN && !(LAN_TOKEN && token === LAN_TOKEN)) {
+    set.status = 401;
+    return { error: "Unauthorized" };
+  }
+});
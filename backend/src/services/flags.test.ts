@@ -0,0 +1,33 @@
+import { describe, expect, test } from "bun:test";
+import { isFlagEnabled, listFlags, parseFlags, setFlag } from "./flags";
+
+describe("parseFlags", () => {
+  test("reads names with and without values", () => {
+    expect(parseFlags("new-sync=1, prefetch=off,beta")).toEqual(
+      new Map([
+        ["new-sync", true],
+        ["prefetch", false],
+        ["beta", true],
+      ])
+    );
+  });
+
+  test("ignores empty entries", () => {
+    expect(parseFlags(",,")).toEqual(new Map());
+    expect(parseFlags(undefined)).toEqual(new Map());
+  });
+});
+
+describe("setFlag", () => {
+  test("turns flags on, off and forgets them", () => {
+    setFlag("test-flag", true);
+    expect(isFlagEnabled("test-flag")).toBe(true);
+
+    setFlag("test-flag", false);
+    expect(isFlagEnabled("test-flag")).toBe(false);
+    expect(listFlags()["test-flag"]).toBe(false);
+
+    setFlag("test-flag", null);
+    expect("test-flag" in listFlags()).toBe(false);
+  });
+});
@@ -0,0 +1,43 @@
+// Feature flags the desktop app can turn on and off, e.g. when support asks
+// a user to try something. They start out as HAMBA_FLAGS says, a
+// comma-separated list like "new-sync=1,prefetch=0", and can be changed
+// while running through /control/flags.
+
+const flags = new Map<string, boolean>();
+
+// Parse HAMBA_FLAGS; a name without a value turns the flag on
+export function parseFlags(value: string | undefined): Map<string, boolean> {
+  const parsed = new Map<string, boolean>();
+  for (const entry of (value ?? "").split(",")) {
+    const [name, raw] = entry.split("=", 2).map((part) => part.trim());
+    if (!name) continue;
+    parsed.set(name, raw === undefined || ["1", "true", "on", "yes"].includes(raw.toLowerCase()));
+  }
+  return parsed;
+}
+
+for (const [name, value] of parseFlags(process.env.HAMBA_FLAGS)) {
+  flags.set(name, value);
+}
+
+/**
+ * Whether a flag is on; flags nobody set are off
+ */
+export function isFlagEnabled(name: string): boolean {
+  return flags.get(name) ?? false;
+}
+
+/**
+ * Turn a flag on or off, or with null forget it
+ */
+export function setFlag(name: string, value: boolean | null): void {
+  if (value === null) {
+    flags.delete(name);
+  } else {
+    flags.set(name, value);
+  }
+}
+
+export function listFlags(): Record<string, boolean> {
+  return Object.fromEntries(flags);
+}
@@ -0,0 +1,23 @@
+import { afterEach, describe, expect, test } from "bun:test";
+import { getHeartbeatInterval, setHeartbeatInterval } from "./heartbeat";
+
+describe("setHeartbeatInterval", () => {
+  afterEach(() => setHeartbeatInterval(0));
+
+  test("changes the interval", () => {
+    setHeartbeatInterval(5000);
+    expect(getHeartbeatInterval()).toBe(5000);
+
+    setHeartbeatInterval(20000);
+    expect(getHeartbeatInterval()).toBe(20000);
+  });
+
+  test("stops on zero or an unset variable", () => {
+    setHeartbeatInterval(5000);
+    setHeartbeatInterval(0);
+    expect(getHeartbeatInterval()).toBe(0);
+
+    setHeartbeatInterval(Number(undefined));
+    expect(getHeartbeatInterval()).toBe(0);
+  });
+});
@@ -0,0 +1,26 @@
+/**
+ * Prints the line the desktop app's watchdog waits for. It restarts the
+ * backend if these stop arriving, which is what happens when the event loop
+ * is blocked. The app asks for them less often on battery.
+ */
+
+export const HEARTBEAT_TOKEN = "__hamba_heartbeat__";
+
+let timer: ReturnType<typeof setInterval> | null = null;
+let intervalMs = 0;
+
+export function getHeartbeatInterval(): number {
+  return intervalMs;
+}
+
+// Restarts the heartbeat every `ms`, or stops it when `ms` isn't positive
+export function setHeartbeatInterval(ms: number): void {
+  if (timer) {
+    clearInterval(timer);
+    timer = null;
+  }
+  intervalMs = ms > 0 ? ms : 0;
+  if (intervalMs > 0) {
+    timer = setInterval(() => process.stdout.write(`${HEARTBEAT_TOKEN}\n`), intervalMs);
+  }
+}
@@ -0,0 +1,61 @@
+import { afterEach, describe, expect, test } from "bun:test";
+import { mkdirSync, mkdtempSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { checkImport, isImportKind } from "./imports";
+
+let dir: string | undefined;
+
+function scratch(): string {
+  dir = mkdtempSync(join(tmpdir(), "hamba-imports-"));
+  return dir;
+}
+
+afterEach(() => {
+  if (dir) rmSync(dir, { recursive: true, force: true });
+  dir = undefined;
+});
+
+describe("isImportKind", () => {
+  test("knows the kinds the desktop app sends", () => {
+    for (const kind of ["eml", "mbox", "vcf", "ics", "csv"]) {
+      expect(isImportKind(kind)).toBe(true);
+    }
+    expect(isImportKind("exe")).toBe(false);
+    expect(isImportKind("EML")).toBe(false);
+  });
+});
+
+describe("checkImport", () => {
+  test("accepts a file inside the allowed folders", () => {
+    const root = scratch();
+    const path = join(root, "1-inbox.mbox");
+    writeFileSync(path, "From a@b\n");
+    expect(checkImport({ path, name: "inbox.mbox", kind: "mbox", size: 9 }, [root])).toEqual({ ok: true });
+  });
+
+  test("rejects unknown kinds", () => {
+    const root = scratch();
+    const path = join(root, "x.exe");
+    writeFileSync(path, "");
+    expect(checkImport({ path, name: "x.exe", kind: "exe", size: 0 }, [root])).toMatchObject({ ok: false, status: 400 });
+  });
+
+  test("rejects paths outside the allowed folders", () => {
+    const root = scratch();
+    const path = join(root, "x.eml");
+    writeFileSync(path, "");
+    const check = checkImport({ path, name: "x.eml", kind: "eml", size: 0 }, [join(root, "imports")]);
+    expect(check).toMatchObject({ ok: false, status: 403 });
+  });
+
+  test("rejects missing files, folders and size mismatches", () => {
+    const root = scratch();
+    mkdirSync(join(root, "folder.eml"));
+    writeFileSync(join(root, "x.eml"), "abc");
+    const notice = (name: string, size: number) => ({ path: join(root, name), name, kind: "eml", size });
+    expect(checkImport(notice("gone.eml", 0), [root])).toMatchObject({ ok: false, status: 404 });
+    expect(checkImport(notice("folder.eml", 0), [root])).toMatchObject({ ok: false, status: 400 });
+    expect(checkImport(notice("x.eml", 2), [root])).toMatchObject({ ok: false, status: 400 });
+  });
+});
@@ -0,0 +1,48 @@
+import { statSync } from "node:fs";
+import { isAllowedPath } from "./allowed-dirs";
+
+/**
+ * Checks for the files the desktop app hands over through POST /imports.
+ * The app copies each one into its import folder first, so the path must
+ * be inside the folders it allows and the file as big as the app said.
+ */
+
+// Mail (single messages and mailboxes), contacts and calendars, as in the
+// desktop app's imports.rs
+export const IMPORT_KINDS = ["eml", "mbox", "vcf", "ics", "csv"] as const;
+export type ImportKind = (typeof IMPORT_KINDS)[number];
+
+export interface ImportNotice {
+  path: string;
+  name: string;
+  kind: string;
+  size: number;
+}
+
+export type ImportCheck = { ok: true } | { ok: false; status: 400 | 403 | 404; error: string };
+
+export function isImportKind(kind: string): kind is ImportKind {
+  return (IMPORT_KINDS as readonly string[]).includes(kind);
+}
+
+export function checkImport(notice: ImportNotice, dirs?: string[] | null): ImportCheck {
+  if (!isImportKind(notice.kind)) {
+    return { ok: false, status: 400, error: `Only ${IMPORT_KINDS.join(", ")} files can be imported` };
+  }
+  if (!isAllowedPath(notice.path, dirs)) {
+    return { ok: false, status: 403, error: `${notice.path} is outside the folders the app allows` };
+  }
+  let stats;
+  try {
+    stats = statSync(notice.path);
+  } catch {
+    return { ok: false, status: 404, error: `${notice.path} does not exist` };
+  }
+  if (!stats.isFile()) {
+    return { ok: false, status: 400, error: `${notice.path} is not a file` };
+  }
+  if (stats.size !== notice.size) {
+    return { ok: false, status: 400, error: `${notice.path} is ${stats.size} bytes, not ${notice.size}` };
+  }
+  return { ok: true };
+}
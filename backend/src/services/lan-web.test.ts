@@ -0,0 +1,33 @@
+import { afterAll, describe, expect, test } from "bun:test";
+import { mkdirSync, mkdtempSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { webAppFile } from "./lan-web";
+
+describe("webAppFile", () => {
+  const dir = mkdtempSync(join(tmpdir(), "hamba-web-"));
+  mkdirSync(join(dir, "_app"));
+  writeFileSync(join(dir, "index.html"), "<html><head></head></html>");
+  writeFileSync(join(dir, "_app", "start.js"), "");
+
+  afterAll(() => rmSync(dir, { recursive: true, force: true }));
+
+  test("serves index.html at the root", () => {
+    expect(webAppFile("/", dir)).toBe(join(dir, "index.html"));
+  });
+
+  test("serves files that exist", () => {
+    expect(webAppFile("/_app/start.js", dir)).toBe(join(dir, "_app", "start.js"));
+  });
+
+  test("leaves API paths and folders alone", () => {
+    expect(webAppFile("/emails", dir)).toBeNull();
+    expect(webAppFile("/_app", dir)).toBeNull();
+  });
+
+  test("never leaves the folder", () => {
+    expect(webAppFile("/../../etc/passwd", dir)).toBeNull();
+    expect(webAppFile("/%2e%2e/%2e%2e/etc/passwd", dir)).toBeNull();
+    expect(webAppFile("/%E0%A4%A", dir)).toBeNull();
+  });
+});
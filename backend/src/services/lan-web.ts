@@ -0,0 +1,38 @@
+import { Elysia } from "elysia";
+import { join, resolve, sep } from "node:path";
+import { statSync } from "node:fs";
+
+// Set by the desktop app while LAN access is on: its frontend build, served
+// to phones and other devices on the network from the API's own origin
+const WEB_DIR = process.env.HAMBA_WEB_DIR;
+
+// The page talks to the API it was served from rather than localhost
+const URL_SCRIPT = "<script>window.__BACKEND_URL__ = location.origin;</script>";
+
+/** The file in `dir` to answer `pathname` with, or null when there is none */
+export function webAppFile(pathname: string, dir: string): string | null {
+  const root = resolve(dir);
+  try {
+    const target = resolve(join(root, pathname === "/" ? "index.html" : decodeURIComponent(pathname)));
+    if (!target.startsWith(root + sep)) return null;
+    return statSync(target).isFile() ? target : null;
+  } catch {
+    return null;
+  }
+}
+
+// Registered before desktop-auth: the pages and scripts are public, the
+// API calls they make carry the pairing token
+export const lanWeb = new Elysia({ name: "lan-web" }).onRequest(async ({ request }) => {
+  if (!WEB_DIR || request.method !== "GET") return;
+  const file = webAppFile(new URL(request.url).pathname, WEB_DIR);
+  if (!file) return;
+
+  if (file.endsWith(".html")) {
+    const html = await Bun.file(file).text();
+    return new Response(html.replace("<head>", `<head>${URL_SCRIPT}`), {
+      headers: { "Content-Type": "text/html; charset=utf-8" },
+    });
+  }
+  return new Response(Bun.file(file));
+});
@@ -17,6 +17,9 @@ const clients = new Set<ServerWebSocket<WebSocketData>>();
 export function addClient(ws: ServerWebSocket<WebSocketData>) {
   clients.add(ws);
   logger.debug("WebSocket client connected", { totalClients: clients.size });
+  if (lastTrayMessage) {
+    ws.send(lastTrayMessage);
+  }
 }
 
 // Remove a WebSocket client
@@ -89,6 +92,60 @@ export function notifyBackgroundSyncProgress(accountId: string, synced: number,
   }
 }
 
+// Ask the desktop app to show a native notification. `category` is what
+// the user can mute in the app's settings.
+export function notifyDesktop(category: string, title: string, body?: string) {
+  const message = JSON.stringify({ type: "notification", category, title, body });
+
+  for (const client of clients) {
+    try {
+      client.send(message);
+    } catch (e) {
+      clients.delete(client);
+    }
+  }
+}
+
+// Ask the desktop app to show `count` on its icon, e.g. pending items while
+// the window is hidden. Zero clears the badge.
+export function notifyDesktopBadge(count: number) {
+  const message = JSON.stringify({ type: "badge", count });
+
+  for (const client of clients) {
+    try {
+      client.send(message);
+    } catch (e) {
+      clients.delete(client);
+    }
+  }
+}
+
+export interface TrayStatus {
+  // Shown next to the tray icon where the OS supports it (macOS, some Linux panels)
+  title: string;
+  tooltip: string;
+  // Listed at the top of the tray menu; `url` is a `hamba://` link
+  items: { title: string; url: string }[];
+}
+
+// The last status sent, for clients that connect later (e.g. the desktop app
+// after restarting the backend)
+let lastTrayMessage: string | null = null;
+
+// Ask the desktop app to show `status` in its tray icon and menu
+export function notifyDesktopTray(status: TrayStatus) {
+  const message = JSON.stringify({ type: "tray", ...status });
+  lastTrayMessage = message;
+
+  for (const client of clients) {
+    try {
+      client.send(message);
+    } catch (e) {
+      clients.delete(client);
+    }
+  }
+}
+
 export function getClientCount(): number {
   return clients.size;
 }
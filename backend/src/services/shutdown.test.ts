@@ -0,0 +1,35 @@
+import { afterEach, describe, expect, test } from "bun:test";
+import { canShutDown, requestShutdown, setShutdownHandler } from "./shutdown";
+
+describe("canShutDown", () => {
+  const original = process.env.HAMBA_AUTH_TOKEN;
+  afterEach(() => {
+    process.env.HAMBA_AUTH_TOKEN = original;
+  });
+
+  test("takes only the desktop token", () => {
+    process.env.HAMBA_AUTH_TOKEN = "secret";
+    expect(canShutDown("secret")).toBe(true);
+    expect(canShutDown("other")).toBe(false);
+    expect(canShutDown(null)).toBe(false);
+  });
+
+  test("refuses without a desktop token", () => {
+    delete process.env.HAMBA_AUTH_TOKEN;
+    expect(canShutDown(null)).toBe(false);
+    expect(canShutDown("")).toBe(false);
+  });
+});
+
+describe("requestShutdown", () => {
+  test("runs the handler after returning", async () => {
+    let ran = false;
+    setShutdownHandler(async () => {
+      ran = true;
+    });
+    expect(requestShutdown()).toBe(true);
+    expect(ran).toBe(false);
+    await new Promise((resolve) => setTimeout(resolve, 10));
+    expect(ran).toBe(true);
+  });
+});
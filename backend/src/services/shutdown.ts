@@ -0,0 +1,28 @@
+/**
+ * Stops the server the way SIGTERM does, for POST /control/shutdown. The
+ * desktop app has no signal to ask a windowless child with on Windows, so
+ * it asks over HTTP instead.
+ */
+
+let handler: (() => Promise<void>) | null = null;
+
+export function setShutdownHandler(fn: () => Promise<void>): void {
+  handler = fn;
+}
+
+// Only the desktop app's own token counts; LAN devices and setups without
+// the app can't stop the backend
+export function canShutDown(token: string | null): boolean {
+  const authToken = process.env.HAMBA_AUTH_TOKEN;
+  return Boolean(authToken) && token === authToken;
+}
+
+// Waits for the current request to be answered before draining
+export function requestShutdown(): boolean {
+  if (!handler) {
+    return false;
+  }
+  const shutdown = handler;
+  setTimeout(() => void shutdown(), 0);
+  return true;
+}
@@ -0,0 +1,74 @@
+/**
+ * Keeps the desktop app's tray up to date: reminders due today in the title
+ * and the first few of them in the menu, linking to the email.
+ */
+
+import { emailQueries } from "../db";
+import { notifyDesktopTray, type TrayStatus } from "./realtime";
+import { logger } from "./logger";
+
+const trayLogger = logger.child({ service: "tray-status" });
+
+const MAX_ITEMS = 5;
+const MAX_TITLE_LENGTH = 60;
+
+interface DueReminder {
+  id: string;
+  subject: string | null;
+  from_name: string | null;
+  from_email: string | null;
+}
+
+let lastStatus: string | null = null;
+
+function endOfToday(): number {
+  const end = new Date();
+  end.setHours(24, 0, 0, 0);
+  return Math.floor(end.getTime() / 1000);
+}
+
+function itemTitle(reminder: DueReminder): string {
+  const from = reminder.from_name || reminder.from_email || "";
+  const subject = reminder.subject || "(no subject)";
+  const title = from ? `${from}: ${subject}` : subject;
+  return title.length > MAX_TITLE_LENGTH ? `${title.slice(0, MAX_TITLE_LENGTH - 1)}…` : title;
+}
+
+function plural(count: number, word: string): string {
+  return `${count} ${word}${count === 1 ? "" : "s"}`;
+}
+
+export function computeTrayStatus(): TrayStatus {
+  const before = endOfToday();
+  const due = (emailQueries.countRemindersDueBefore.get(before) as { count: number }).count;
+  const unread = (emailQueries.countUnreadInbox.get() as { count: number }).count;
+  const reminders = emailQueries.getRemindersDueBefore.all(before, MAX_ITEMS) as DueReminder[];
+
+  const parts = [];
+  if (due > 0) parts.push(`${plural(due, "reminder")} due today`);
+  if (unread > 0) parts.push(`${unread} unread`);
+
+  return {
+    title: due > 0 ? `${due} due` : "",
+    tooltip: parts.length > 0 ? `Hamba: ${parts.join(", ")}` : "Hamba",
+    items: reminders.map((reminder) => ({
+      title: itemTitle(reminder),
+      url: `hamba://email/${encodeURIComponent(reminder.id)}`,
+    })),
+  };
+}
+
+// Sends the status if it changed since it was last sent
+export function refreshTrayStatus() {
+  try {
+    const status = computeTrayStatus();
+    const serialized = JSON.stringify(status);
+    if (serialized === lastStatus) {
+      return;
+    }
+    lastStatus = serialized;
+    notifyDesktopTray(status);
+  } catch (error) {
+    trayLogger.error("Failed to update tray status", error as Error);
+  }
+}
@@ -0,0 +1,9 @@
+// Entry point of the desktop app's sidecar, see frontend/scripts/build-sidecar.sh.
+// `maintenance <task>` runs a database task while the server is stopped and
+// exits; anything else starts the server as ./index.ts does.
+if (process.argv[2] === "maintenance") {
+  const { runMaintenance } = await import("./db/maintenance");
+  process.exit(runMaintenance(process.argv[3]));
+}
+
+await import("./index");
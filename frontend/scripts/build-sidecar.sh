@@ -32,7 +32,8 @@ echo "Building backend sidecar for $TARGET_TRIPLE..."
 
 cd "$BACKEND_DIR"
 
-# Build standalone executable with bun
-bun build --compile --minify ./src/index.ts --outfile "$OUTPUT_DIR/backend-$TARGET_TRIPLE"
+# Build standalone executable with bun. src/sidecar.ts starts the server, or
+# runs `maintenance <task>` for the app's database maintenance commands.
+bun build --compile --minify ./src/sidecar.ts --outfile "$OUTPUT_DIR/backend-$TARGET_TRIPLE"
 
 echo "Sidecar built: $OUTPUT_DIR/backend-$TARGET_TRIPLE"
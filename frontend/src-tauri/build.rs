@@ -1,3 +1,61 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
 fn main() {
+    embed_backend_checksum();
     tauri_build::build()
 }
+
+// Bakes the primary sidecar's SHA-256 into the binary as `BACKEND_SHA256` so
+// it can be checked before every spawn. Builds without a sidecar (plain
+// `cargo check`, dev) just don't set it.
+fn embed_backend_checksum() {
+    println!("cargo:rerun-if-changed=sidecars.toml");
+    let name = match primary_sidecar() {
+        Ok(name) => name,
+        Err(err) => {
+            println!("cargo:warning=Can't tell the backend from sidecars.toml: {err}");
+            return;
+        }
+    };
+    let target = env::var("TARGET").unwrap_or_default();
+    let extension = if target.contains("windows") {
+        ".exe"
+    } else {
+        ""
+    };
+    let path = format!("binaries/{name}-{target}{extension}");
+    println!("cargo:rerun-if-changed={path}");
+
+    if !Path::new(&path).exists() {
+        return;
+    }
+    match sha256(Path::new(&path)) {
+        Ok(hash) => println!("cargo:rustc-env=BACKEND_SHA256={hash}"),
+        Err(err) => println!("cargo:warning=Failed to hash {path}: {err}"),
+    }
+}
+
+// The app checks sidecars.toml itself; here it only has to find the name
+fn primary_sidecar() -> Result<String, String> {
+    let contents = fs::read_to_string("sidecars.toml").map_err(|err| err.to_string())?;
+    let file: toml::Table = toml::from_str(&contents).map_err(|err| err.to_string())?;
+    file.get("sidecar")
+        .and_then(|sidecars| sidecars.as_array())
+        .into_iter()
+        .flatten()
+        .find(|sidecar| sidecar.get("primary").and_then(|primary| primary.as_bool()) == Some(true))
+        .and_then(|sidecar| sidecar.get("name")?.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "no primary sidecar".to_string())
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
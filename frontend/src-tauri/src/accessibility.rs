@@ -0,0 +1,222 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::settings::SettingsStore;
+
+// Webviews aren't told when these change on every OS, so they're read this
+// often instead
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+const EVENT: &str = "accessibility-changed";
+
+/// What the OS asks for; `false` where it doesn't say
+#[derive(Clone, Copy, Default, PartialEq)]
+struct System {
+    high_contrast: bool,
+    reduced_motion: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessibilityPrefs {
+    /// High contrast on Windows, Increase contrast on macOS, GNOME's high
+    /// contrast on Linux
+    high_contrast: bool,
+    /// Animations turned off on Windows and GNOME, Reduce motion on macOS
+    reduced_motion: bool,
+    /// Whether the frontend should use its forced-colors palette: the
+    /// override if there is one, `high_contrast` otherwise
+    forced_colors: bool,
+    forced_colors_override: Option<bool>,
+}
+
+#[derive(Default)]
+pub struct Accessibility(Mutex<System>);
+
+/// Emits `accessibility-changed` whenever the OS settings change
+pub fn init(app: &AppHandle) {
+    app.manage(Accessibility(Mutex::new(platform::read())));
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Ok(system) = tauri::async_runtime::spawn_blocking(platform::read).await else {
+                continue;
+            };
+            let changed = {
+                let state = app.state::<Accessibility>();
+                let mut current = state.0.lock().unwrap();
+                std::mem::replace(&mut *current, system) != system
+            };
+            if changed {
+                tracing::debug!(
+                    "Accessibility settings changed: high contrast {}, reduced motion {}",
+                    system.high_contrast,
+                    system.reduced_motion
+                );
+                let _ = app.emit(EVENT, prefs(&app));
+            }
+        }
+    });
+}
+
+fn prefs(app: &AppHandle) -> AccessibilityPrefs {
+    let system = app
+        .try_state::<Accessibility>()
+        .map(|state| *state.0.lock().unwrap())
+        .unwrap_or_default();
+    let forced_colors_override = app.state::<SettingsStore>().get().forced_colors_override;
+    AccessibilityPrefs {
+        high_contrast: system.high_contrast,
+        reduced_motion: system.reduced_motion,
+        forced_colors: forced_colors_override.unwrap_or(system.high_contrast),
+        forced_colors_override,
+    }
+}
+
+/// Sets `window.__ACCESSIBILITY__`, for app.html to apply before the first
+/// paint
+pub fn script(app: &AppHandle) -> String {
+    format!(
+        "window.__ACCESSIBILITY__ = {};",
+        serde_json::to_string(&prefs(app)).unwrap_or_else(|_| "null".to_string())
+    )
+}
+
+/// Emits `accessibility-changed` after the override changed
+pub fn apply(app: &AppHandle) -> AccessibilityPrefs {
+    let prefs = prefs(app);
+    let _ = app.emit(EVENT, prefs);
+    prefs
+}
+
+#[tauri::command]
+pub fn get_accessibility_prefs(app: AppHandle) -> AccessibilityPrefs {
+    prefs(&app)
+}
+
+/// Forces the forced-colors palette on or off, or `None` to follow the OS's
+/// high contrast setting again
+#[tauri::command]
+pub fn set_forced_colors_override(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    enabled: Option<bool>,
+) -> Result<AccessibilityPrefs, String> {
+    settings
+        .update(|settings| settings.forced_colors_override = enabled)
+        .map_err(|err| err.to_string())?;
+    Ok(apply(&app))
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+    };
+
+    use super::System;
+
+    pub fn read() -> System {
+        let mut contrast = HIGHCONTRASTW {
+            cbSize: size_of::<HIGHCONTRASTW>() as u32,
+            ..Default::default()
+        };
+        let high_contrast = unsafe {
+            SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                contrast.cbSize,
+                &mut contrast as *mut _ as *mut c_void,
+                0,
+            )
+        } != 0
+            && contrast.dwFlags & HCF_HIGHCONTRASTON != 0;
+
+        let mut animations = 1i32;
+        let read_animations = unsafe {
+            SystemParametersInfoW(
+                SPI_GETCLIENTAREAANIMATION,
+                0,
+                &mut animations as *mut _ as *mut c_void,
+                0,
+            )
+        } != 0;
+        System {
+            high_contrast,
+            reduced_motion: read_animations && animations == 0,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+
+    use super::System;
+
+    pub fn read() -> System {
+        unsafe {
+            let workspace: Option<Retained<AnyObject>> =
+                msg_send![class!(NSWorkspace), sharedWorkspace];
+            let Some(workspace) = workspace else {
+                return System::default();
+            };
+            let high_contrast: bool =
+                msg_send![&workspace, accessibilityDisplayShouldIncreaseContrast];
+            let reduced_motion: bool =
+                msg_send![&workspace, accessibilityDisplayShouldReduceMotion];
+            System {
+                high_contrast,
+                reduced_motion,
+            }
+        }
+    }
+}
+
+// GNOME keeps both as settings; other desktops don't say
+#[cfg(target_os = "linux")]
+mod platform {
+    use gtk::gio;
+    use gtk::prelude::*;
+
+    use super::System;
+
+    const A11Y_SCHEMA: &str = "org.gnome.desktop.a11y.interface";
+    const HIGH_CONTRAST: &str = "high-contrast";
+    const INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+    const ENABLE_ANIMATIONS: &str = "enable-animations";
+
+    pub fn read() -> System {
+        System {
+            high_contrast: boolean(A11Y_SCHEMA, HIGH_CONTRAST).unwrap_or(false),
+            reduced_motion: boolean(INTERFACE_SCHEMA, ENABLE_ANIMATIONS)
+                .is_some_and(|enabled| !enabled),
+        }
+    }
+
+    fn boolean(schema: &str, key: &str) -> Option<bool> {
+        // Reading a schema or key that isn't installed aborts
+        let source = gio::SettingsSchemaSource::default()?;
+        if !source.lookup(schema, true)?.has_key(key) {
+            return None;
+        }
+        Some(gio::Settings::new(schema).boolean(key))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use super::System;
+
+    pub fn read() -> System {
+        System::default()
+    }
+}
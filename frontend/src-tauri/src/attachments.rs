@@ -0,0 +1,355 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeContext, UriSchemeResponder, Wry};
+
+use crate::data_dir;
+use crate::disk;
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+
+/// Served as `attachment://localhost/<id>` on macOS and Linux and
+/// `http://attachment.localhost/<id>` on Windows; `convertFileSrc(id,
+/// "attachment")` in the frontend picks the right one
+pub const SCHEME: &str = "attachment";
+const DIR: &str = "attachments";
+const THUMBNAIL_DIR: &str = "thumbnails";
+const THUMBNAIL_PATH: &str = "thumbnail";
+const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+// Players ask for `bytes=0-` and take whatever comes back, so open-ended
+// ranges are cut short rather than reading a whole video into memory
+const MAX_RANGE: u64 = 4 * 1024 * 1024;
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("pdf", "application/pdf"),
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("webm", "video/webm"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("zip", "application/zip"),
+];
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// A stored file. Its id is the SHA-256 of its contents, so storing the same
+/// file twice keeps one copy.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    id: String,
+    name: String,
+    mime_type: String,
+    size: u64,
+}
+
+fn dir(app: &AppHandle) -> Result<PathBuf, String> {
+    data_dir::data_dir(app)
+        .map(|dir| dir.join(DIR))
+        .ok_or_else(|| "No data directory available".to_string())
+}
+
+fn thumbnail_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(THUMBNAIL_DIR))
+        .map_err(|err| err.to_string())
+}
+
+fn validate_id(id: &str) -> Result<(), String> {
+    if id.len() != 64 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid attachment id {id:?}"));
+    }
+    Ok(())
+}
+
+fn mime_type(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or(DEFAULT_MIME_TYPE)
+}
+
+fn read_meta(dir: &Path, id: &str) -> Result<Attachment, String> {
+    let contents = fs::read_to_string(dir.join(format!("{id}.json")))
+        .map_err(|_| format!("No attachment {id:?}"))?;
+    serde_json::from_str(&contents).map_err(|err| err.to_string())
+}
+
+// Hashes while copying, into a temporary name that only becomes the id once
+// the whole file is in
+fn store(dir: &Path, source: &Path) -> io::Result<(String, u64)> {
+    fs::create_dir_all(dir)?;
+    let mut random = [0u8; 8];
+    getrandom::fill(&mut random).map_err(io::Error::other)?;
+    let partial = dir.join(format!(".partial-{}", hex::encode(random)));
+
+    let result = (|| {
+        let mut input = File::open(source)?;
+        let mut output = File::create(&partial)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut size = 0;
+        loop {
+            let read = input.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            output.write_all(&buffer[..read])?;
+            size += read as u64;
+        }
+        output.sync_all()?;
+        let id = hex::encode(hasher.finalize());
+        fs::rename(&partial, dir.join(&id))?;
+        Ok((id, size))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Copies the file at `path` into the app's attachment store. The frontend
+/// then loads it by id over the `attachment` protocol instead of IPC.
+#[tauri::command]
+pub async fn import_attachment(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: PathBuf,
+) -> Result<Attachment, String> {
+    let path = fs_scope::check(&app, &path, Access::Read).map_err(|err| err.to_string())?;
+    let dir = dir(&app)?;
+    let size = fs::metadata(&path).map_err(|err| err.to_string())?.len();
+    disk::ensure_space(&app, &dir, size)?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or("The path has no file name")?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (id, size) = store(&dir, &path).map_err(|err| err.to_string())?;
+        let attachment = Attachment {
+            mime_type: mime_type(&name).to_string(),
+            id,
+            name,
+            size,
+        };
+        let meta = serde_json::to_string_pretty(&attachment).map_err(|err| err.to_string())?;
+        fs::write(dir.join(format!("{}.json", attachment.id)), meta)
+            .map_err(|err| err.to_string())?;
+        tracing::info!("Stored attachment {} ({} bytes)", attachment.id, size);
+        Ok(attachment)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+#[tauri::command]
+pub fn get_attachment(app: AppHandle, id: String) -> Result<Attachment, String> {
+    validate_id(&id)?;
+    read_meta(&dir(&app)?, &id)
+}
+
+/// Deletes the file and its cached thumbnails
+#[tauri::command]
+pub fn delete_attachment(app: AppHandle, id: String) -> Result<(), String> {
+    validate_id(&id)?;
+    let dir = dir(&app)?;
+    read_meta(&dir, &id)?;
+    fs::remove_file(dir.join(&id)).map_err(|err| err.to_string())?;
+    let _ = fs::remove_file(dir.join(format!("{id}.json")));
+    if let Ok(thumbnails) =
+        thumbnail_dir(&app).and_then(|dir| fs::read_dir(dir).map_err(|err| err.to_string()))
+    {
+        for entry in thumbnails.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&id) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serves `/<id>`, honouring `Range` so video and audio can seek, and
+/// `/<id>/thumbnail?size=<px>`, a PNG made once and cached
+pub fn handle(
+    context: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = context.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = serve(&app, &request).unwrap_or_else(|(status, message)| {
+            Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .body(message.into_bytes())
+                .unwrap_or_default()
+        });
+        responder.respond(response);
+    });
+}
+
+type Failure = (StatusCode, String);
+
+fn not_found(message: impl Into<String>) -> Failure {
+    (StatusCode::NOT_FOUND, message.into())
+}
+
+fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Failure> {
+    let mut parts = request.uri().path().trim_start_matches('/').split('/');
+    let id = parts.next().unwrap_or_default();
+    validate_id(id).map_err(not_found)?;
+    let dir = dir(app).map_err(not_found)?;
+    let attachment = read_meta(&dir, id).map_err(not_found)?;
+
+    match parts.next() {
+        None | Some("") => serve_file(&dir.join(id), &attachment, request),
+        Some(THUMBNAIL_PATH) => {
+            let size = request
+                .uri()
+                .query()
+                .and_then(|query| query.strip_prefix("size="))
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(DEFAULT_THUMBNAIL_SIZE)
+                .clamp(1, MAX_THUMBNAIL_SIZE);
+            let png = thumbnail(app, &dir.join(id), &attachment, size)?;
+            Response::builder()
+                .header(header::CONTENT_TYPE, "image/png")
+                .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+                .body(png)
+                .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+        }
+        Some(_) => Err(not_found("Unknown attachment path")),
+    }
+}
+
+// `bytes=<start>-<end>`, `bytes=<start>-` or `bytes=-<suffix length>`;
+// anything else, multiple ranges included, gets the whole file
+fn parse_range(value: &str, size: u64) -> Option<(u64, u64)> {
+    let range = value.strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (size.saturating_sub(suffix), size.checked_sub(1)?)
+        }
+        (start, "") => {
+            let start: u64 = start.parse().ok()?;
+            (start, (start + MAX_RANGE).min(size).checked_sub(1)?)
+        }
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(size.checked_sub(1)?),
+        ),
+    };
+    (start <= end).then_some((start, end))
+}
+
+fn serve_file(
+    path: &Path,
+    attachment: &Attachment,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, Failure> {
+    let internal = |err: io::Error| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+    let mut file = File::open(path).map_err(|_| not_found("The attachment's file is missing"))?;
+    let size = file.metadata().map_err(internal)?.len();
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, &attachment.mime_type)
+        .header(header::ACCEPT_RANGES, "bytes");
+    let (builder, body) = match range {
+        Some(range) => {
+            let Some((start, end)) = parse_range(range, size) else {
+                return Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+                    .body(Vec::new())
+                    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
+            };
+            file.seek(SeekFrom::Start(start)).map_err(internal)?;
+            let mut body = vec![0; (end - start + 1) as usize];
+            file.read_exact(&mut body).map_err(internal)?;
+            let builder = builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{size}"));
+            (builder, body)
+        }
+        None => {
+            let mut body = Vec::with_capacity(size as usize);
+            file.read_to_end(&mut body).map_err(internal)?;
+            (builder, body)
+        }
+    };
+    builder
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(body)
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+fn thumbnail(
+    app: &AppHandle,
+    path: &Path,
+    attachment: &Attachment,
+    size: u32,
+) -> Result<Vec<u8>, Failure> {
+    let internal = |err: String| (StatusCode::INTERNAL_SERVER_ERROR, err);
+    let cache = thumbnail_dir(app)
+        .map_err(internal)?
+        .join(format!("{}-{size}.png", attachment.id));
+    if let Ok(png) = fs::read(&cache) {
+        return Ok(png);
+    }
+
+    let format = match attachment.mime_type.as_str() {
+        "image/png" => image::ImageFormat::Png,
+        "image/jpeg" => image::ImageFormat::Jpeg,
+        _ => return Err(not_found("No thumbnail for this kind of file")),
+    };
+    let file = File::open(path).map_err(|_| not_found("The attachment's file is missing"))?;
+    let image = image::load(io::BufReader::new(file), format)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()))?;
+    let mut png = Vec::new();
+    image
+        .thumbnail(size, size)
+        .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|err| internal(err.to_string()))?;
+
+    if let Some(parent) = cache.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(err) = fs::write(&cache, &png) {
+        tracing::warn!("Failed to cache thumbnail {}: {err}", cache.display());
+    }
+    Ok(png)
+}
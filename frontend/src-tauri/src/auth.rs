@@ -0,0 +1,340 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+
+const MAX_REASON_LENGTH: usize = 200;
+/// Caps `auth_grace_minutes`, past which asking again is hardly worth it
+pub const MAX_GRACE_MINUTES: u64 = 60;
+
+/// When the user last proved it's them to the OS, for `authenticate` to
+/// skip asking again within `auth_grace_minutes`. Only lives as long as
+/// the process does.
+#[derive(Default)]
+pub struct Auth {
+    last: Mutex<Option<Instant>>,
+    // One prompt at a time; callers waiting on it share its answer
+    prompt: tokio::sync::Mutex<()>,
+}
+
+impl Auth {
+    fn is_fresh(&self, grace: Duration) -> bool {
+        self.last
+            .lock()
+            .unwrap()
+            .is_some_and(|last| last.elapsed() < grace)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Auth::default());
+}
+
+pub fn validate_grace(minutes: u64) -> Result<(), String> {
+    if minutes > MAX_GRACE_MINUTES {
+        return Err(format!(
+            "The grace period can be at most {MAX_GRACE_MINUTES} minutes"
+        ));
+    }
+    Ok(())
+}
+
+/// Makes the next `authenticate` ask again, e.g. once the app locks
+pub fn forget(app: &AppHandle) {
+    if let Some(auth) = app.try_state::<Auth>() {
+        *auth.last.lock().unwrap() = None;
+    }
+}
+
+fn grace(app: &AppHandle) -> Duration {
+    let minutes = app.state::<SettingsStore>().get().auth_grace_minutes;
+    Duration::from_secs(minutes.min(MAX_GRACE_MINUTES) * 60)
+}
+
+/// Asks the user to confirm it's them with Touch ID, Windows Hello or their
+/// account password, e.g. before showing a sensitive screen or exporting
+/// data. `reason` completes "Hamba is trying to ..." on macOS and is shown
+/// as is by Windows; polkit shows its own message. Resolves right away if
+/// they already did within the grace period.
+#[tauri::command]
+pub async fn authenticate(
+    _timer: IpcTimer,
+    app: AppHandle,
+    window: WebviewWindow,
+    auth: State<'_, Auth>,
+    reason: String,
+) -> Result<(), String> {
+    let reason = reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(format!(
+            "The reason must be 1 to {MAX_REASON_LENGTH} characters long"
+        ));
+    }
+    let grace = grace(&app);
+    if auth.is_fresh(grace) {
+        return Ok(());
+    }
+    let _prompt = auth.prompt.lock().await;
+    if auth.is_fresh(grace) {
+        return Ok(());
+    }
+    platform::authenticate(&app, &window, reason).await?;
+    tracing::info!("User authenticated");
+    *auth.last.lock().unwrap() = Some(Instant::now());
+    Ok(())
+}
+
+/// Whether `authenticate` would resolve without asking
+#[tauri::command]
+pub fn is_authenticated(app: AppHandle, auth: State<'_, Auth>) -> bool {
+    auth.is_fresh(grace(&app))
+}
+
+/// Ends the grace period early, e.g. when leaving a sensitive screen
+#[tauri::command]
+pub fn forget_authentication(app: AppHandle) {
+    forget(&app);
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::{AppHandle, WebviewWindow};
+    use tokio::sync::oneshot;
+    use windows::core::{factory, HSTRING};
+    use windows::Security::Credentials::UI::{UserConsentVerificationResult, UserConsentVerifier};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::WinRT::IUserConsentVerifierInterop;
+    use windows_future::IAsyncOperation;
+
+    // Windows Hello takes a face, a fingerprint or the PIN, and the interop
+    // version keeps its dialog in front of the window that asked
+    pub async fn authenticate(
+        _app: &AppHandle,
+        window: &WebviewWindow,
+        reason: &str,
+    ) -> Result<(), String> {
+        // Raw window handles can't cross threads, their address can
+        let hwnd = window.hwnd().map_err(|err| err.to_string())?.0 as isize;
+        let reason = HSTRING::from(reason);
+        let (sender, receiver) = oneshot::channel();
+        window
+            .run_on_main_thread(move || {
+                let _ = sender.send(request(HWND(hwnd as *mut _), &reason));
+            })
+            .map_err(|err| err.to_string())?;
+        let operation = receiver
+            .await
+            .map_err(|_| "Authentication was cancelled".to_string())?
+            .map_err(|err| err.to_string())?;
+        let result = operation.await.map_err(|err| err.to_string())?;
+        match result {
+            UserConsentVerificationResult::Verified => Ok(()),
+            UserConsentVerificationResult::Canceled => {
+                Err("Authentication was cancelled".to_string())
+            }
+            UserConsentVerificationResult::DeviceNotPresent
+            | UserConsentVerificationResult::NotConfiguredForUser => Err(
+                "Set up Windows Hello in Settings > Accounts > Sign-in options first".to_string(),
+            ),
+            UserConsentVerificationResult::DisabledByPolicy => {
+                Err("Windows Hello is turned off by your organization".to_string())
+            }
+            _ => Err("Windows Hello couldn't confirm it's you".to_string()),
+        }
+    }
+
+    fn request(
+        hwnd: HWND,
+        reason: &HSTRING,
+    ) -> windows::core::Result<IAsyncOperation<UserConsentVerificationResult>> {
+        let interop = factory::<UserConsentVerifier, IUserConsentVerifierInterop>()?;
+        unsafe { interop.RequestVerificationForWindowAsync(hwnd, reason) }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, CStr, CString};
+    use std::sync::Mutex;
+
+    use block2::RcBlock;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2::{class, msg_send};
+    use tauri::{AppHandle, WebviewWindow};
+    use tokio::sync::oneshot;
+
+    #[link(name = "LocalAuthentication", kind = "framework")]
+    extern "C" {}
+
+    // LAPolicyDeviceOwnerAuthentication, Touch ID or else the password
+    const DEVICE_OWNER: isize = 2;
+    // LAErrorUserCancel, LAErrorSystemCancel and LAErrorAppCancel
+    const CANCELLED: &[isize] = &[-2, -4, -9];
+
+    type Reply = oneshot::Sender<Result<(), String>>;
+
+    pub async fn authenticate(
+        _app: &AppHandle,
+        _window: &WebviewWindow,
+        reason: &str,
+    ) -> Result<(), String> {
+        let (sender, receiver) = oneshot::channel();
+        unsafe { evaluate(reason, sender)? };
+        receiver
+            .await
+            .map_err(|_| "Authentication was cancelled".to_string())?
+    }
+
+    // The context has to stay alive until the user answers, so the block
+    // holds on to it
+    unsafe fn evaluate(reason: &str, sender: Reply) -> Result<(), String> {
+        unsafe {
+            let context: Retained<AnyObject> = msg_send![class!(LAContext), new];
+            let mut error: *mut AnyObject = std::ptr::null_mut();
+            let available: bool = msg_send![
+                &*context,
+                canEvaluatePolicy: DEVICE_OWNER,
+                error: &mut error as *mut *mut AnyObject
+            ];
+            if !available {
+                return Err(describe(error)
+                    .unwrap_or_else(|| "This Mac has no way to confirm it's you".to_string()));
+            }
+            let reason = ns_string(reason)?;
+            let sender = Mutex::new(Some(sender));
+            let kept = context.clone();
+            let reply = RcBlock::new(move |success: Bool, error: *mut AnyObject| {
+                let _ = &kept;
+                let result = if success.as_bool() {
+                    Ok(())
+                } else if CANCELLED.contains(&code(error)) {
+                    Err("Authentication was cancelled".to_string())
+                } else {
+                    Err(describe(error).unwrap_or_else(|| "Authentication failed".to_string()))
+                };
+                if let Some(sender) = sender.lock().unwrap().take() {
+                    let _ = sender.send(result);
+                }
+            });
+            let _: () = msg_send![
+                &*context,
+                evaluatePolicy: DEVICE_OWNER,
+                localizedReason: &*reason,
+                reply: &*reply
+            ];
+        }
+        Ok(())
+    }
+
+    fn code(error: *mut AnyObject) -> isize {
+        if error.is_null() {
+            return 0;
+        }
+        unsafe { msg_send![&*error, code] }
+    }
+
+    fn describe(error: *mut AnyObject) -> Option<String> {
+        if error.is_null() {
+            return None;
+        }
+        unsafe {
+            let text: Option<Retained<AnyObject>> = msg_send![&*error, localizedDescription];
+            let ptr: *const c_char = msg_send![&*text?, UTF8String];
+            (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        }
+    }
+
+    fn ns_string(text: &str) -> Result<Retained<AnyObject>, String> {
+        let text = CString::new(text.replace('\0', "")).map_err(|err| err.to_string())?;
+        let ptr: *const c_char = text.as_ptr();
+        let string: Option<Retained<AnyObject>> =
+            unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] };
+        string.ok_or_else(|| "Failed to convert the text".to_string())
+    }
+}
+
+// polkit asks through the desktop's authentication agent, for the user's
+// own password as set in the action installed with the app
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+
+    use tauri::{AppHandle, WebviewWindow};
+    use zbus::zvariant::Value;
+
+    // Allow the agent to prompt instead of only checking
+    const ALLOW_USER_INTERACTION: u32 = 1;
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.PolicyKit1.Authority",
+        default_service = "org.freedesktop.PolicyKit1",
+        default_path = "/org/freedesktop/PolicyKit1/Authority"
+    )]
+    trait Authority {
+        fn check_authorization(
+            &self,
+            subject: &(&str, HashMap<&str, Value<'_>>),
+            action_id: &str,
+            details: HashMap<&str, &str>,
+            flags: u32,
+            cancellation_id: &str,
+        ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+    }
+
+    pub async fn authenticate(
+        app: &AppHandle,
+        _window: &WebviewWindow,
+        _reason: &str,
+    ) -> Result<(), String> {
+        let action = format!("{}.authenticate", app.config().identifier);
+        let connection = zbus::Connection::system()
+            .await
+            .map_err(|err| err.to_string())?;
+        let authority = AuthorityProxy::new(&connection)
+            .await
+            .map_err(|err| err.to_string())?;
+        // The app is whoever owns this connection
+        let name = connection
+            .unique_name()
+            .ok_or("Not connected to the system bus")?
+            .to_string();
+        let subject = (
+            "system-bus-name",
+            HashMap::from([("name", Value::from(name.as_str()))]),
+        );
+        let (authorized, _, _) = authority
+            .check_authorization(
+                &subject,
+                &action,
+                HashMap::new(),
+                ALLOW_USER_INTERACTION,
+                "",
+            )
+            .await
+            .map_err(|err| {
+                tracing::warn!("polkit check for {action} failed: {err}");
+                "Couldn't ask for your password. Is a polkit agent running?".to_string()
+            })?;
+        if !authorized {
+            return Err("Authentication was cancelled or failed".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use tauri::{AppHandle, WebviewWindow};
+
+    pub async fn authenticate(
+        _app: &AppHandle,
+        _window: &WebviewWindow,
+        _reason: &str,
+    ) -> Result<(), String> {
+        Err("Authentication is not supported on this platform".to_string())
+    }
+}
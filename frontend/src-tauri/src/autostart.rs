@@ -0,0 +1,200 @@
+use std::env;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::data_dir;
+use crate::profile;
+use crate::safe_mode;
+use crate::settings::SettingsStore;
+use platform::{register, unregister};
+
+/// Passed by the login item so the app starts hidden in the tray
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+/// Re-registers the login item if autostart is on, in case the executable
+/// moved since it was registered (e.g. after an update or reinstall).
+pub fn init(app: &AppHandle) {
+    let settings = app.state::<SettingsStore>().get();
+    if settings.autostart && !safe_mode::is_active(app) {
+        if let Err(err) = register(app, settings.autostart_minimized) {
+            tracing::warn!("Failed to refresh autostart registration: {err}");
+        }
+    }
+}
+
+/// Adds or removes the login item to match `enabled`
+pub fn apply(app: &AppHandle, enabled: bool, minimized: bool) -> Result<(), String> {
+    if enabled {
+        register(app, minimized)
+    } else {
+        unregister(app)
+    }
+}
+
+#[tauri::command]
+pub fn set_autostart(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    enabled: bool,
+    minimized: bool,
+) -> Result<(), String> {
+    apply(&app, enabled, minimized)?;
+
+    settings
+        .update(|settings| {
+            settings.autostart = enabled;
+            settings.autostart_minimized = minimized;
+        })
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+fn executable() -> Result<PathBuf, String> {
+    // An AppImage is mounted somewhere new on every launch, so point the
+    // login item at the image itself rather than the mounted binary.
+    if let Some(appimage) = env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    env::current_exe().map_err(|err| err.to_string())
+}
+
+fn launch_args(app: &AppHandle, minimized: bool) -> Vec<String> {
+    let mut args = data_dir::launch_args(app);
+    args.extend(profile::launch_args(app));
+    if minimized {
+        args.push(MINIMIZED_ARG.to_string());
+    }
+    args
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::{AppHandle, Manager};
+
+    const LABEL: &str = "com.hamba.app";
+
+    fn plist_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let home = app.path().home_dir().map_err(|err| err.to_string())?;
+        Ok(home.join(format!("Library/LaunchAgents/{LABEL}.plist")))
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    pub fn register(app: &AppHandle, minimized: bool) -> Result<(), String> {
+        let exe = super::executable()?;
+        let arguments: String = std::iter::once(exe.to_string_lossy().into_owned())
+            .chain(super::launch_args(app, minimized))
+            .map(|arg| format!("    <string>{}</string>\n", escape(&arg)))
+            .collect();
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{LABEL}</string>
+  <key>ProgramArguments</key>
+  <array>
+{arguments}  </array>
+  <key>RunAtLoad</key>
+  <true/>
+</dict>
+</plist>
+"#
+        );
+
+        let path = plist_path(app)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, plist).map_err(|err| err.to_string())
+    }
+
+    pub fn unregister(app: &AppHandle) -> Result<(), String> {
+        match fs::remove_file(plist_path(app)?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::{AppHandle, Manager};
+
+    fn desktop_file_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let config = app.path().config_dir().map_err(|err| err.to_string())?;
+        Ok(config.join("autostart/hamba.desktop"))
+    }
+
+    pub fn register(app: &AppHandle, minimized: bool) -> Result<(), String> {
+        let exe = super::executable()?;
+        let exec: Vec<String> = std::iter::once(exe.to_string_lossy().into_owned())
+            .chain(super::launch_args(app, minimized))
+            .map(|arg| format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=Hamba\nExec={}\nX-GNOME-Autostart-enabled=true\n",
+            exec.join(" ")
+        );
+
+        let path = desktop_file_path(app)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, entry).map_err(|err| err.to_string())
+    }
+
+    pub fn unregister(app: &AppHandle) -> Result<(), String> {
+        match fs::remove_file(desktop_file_path(app)?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+    const VALUE_NAME: &str = "Hamba";
+
+    pub fn register(app: &AppHandle, minimized: bool) -> Result<(), String> {
+        let exe = super::executable()?;
+        let mut command = format!("\"{}\"", exe.to_string_lossy());
+        for arg in super::launch_args(app, minimized) {
+            command.push_str(&format!(" \"{arg}\""));
+        }
+
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(RUN_KEY)
+            .map_err(|err| err.to_string())?;
+        key.set_value(VALUE_NAME, &command)
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn unregister(_app: &AppHandle) -> Result<(), String> {
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(RUN_KEY)
+            .map_err(|err| err.to_string())?;
+        match key.delete_value(VALUE_NAME) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+use crate::command::{ensure, CommandResult};
+use crate::metrics::IpcTimer;
+use crate::safe_mode;
+use crate::settings::SettingsStore;
+use crate::sidecar::Sidecar;
+
+// What the backend's logger knows, from least to most quiet
+const LOG_LEVELS: &[&str] = &["debug", "info", "warn", "error"];
+// The backend's own default, sent when the level is reset while it runs
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendControls {
+    /// `None` when the backend picks, from hamba.env or its default
+    log_level: Option<String>,
+    flags: BTreeMap<String, bool>,
+}
+
+/// `LOG_LEVEL` and `HAMBA_FLAGS` for the next backend start, as last set
+/// through `set_backend_log_level` and `set_backend_flag`. Safe mode leaves
+/// the flags out.
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    let settings = app.state::<SettingsStore>().get();
+    let mut env = Vec::new();
+    if let Some(level) = settings.backend_log_level {
+        env.push(("LOG_LEVEL".to_string(), level));
+    }
+    if !settings.backend_flags.is_empty() && !safe_mode::is_active(app) {
+        let flags: Vec<String> = settings
+            .backend_flags
+            .iter()
+            .map(|(name, value)| format!("{name}={}", u8::from(*value)))
+            .collect();
+        env.push(("HAMBA_FLAGS".to_string(), flags.join(",")));
+    }
+    env
+}
+
+// Tells the running backend, if there is one. It still gets the change from
+// `env` on its next start if this fails.
+async fn relay(app: &AppHandle, path: &str, body: serde_json::Value) -> bool {
+    let response = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::POST, path)
+        .json(&body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match response {
+        Ok(_) => true,
+        Err(err) => {
+            tracing::info!("The backend will pick up {path} when it next starts: {err}");
+            false
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_backend_controls(app: AppHandle) -> BackendControls {
+    let settings = app.state::<SettingsStore>().get();
+    BackendControls {
+        log_level: settings.backend_log_level,
+        flags: settings.backend_flags,
+    }
+}
+
+/// Sets the backend's log level, or with `None` leaves it to the backend
+/// again, from now and on every start. Resolves with whether the running
+/// backend took it right away.
+#[tauri::command]
+pub async fn set_backend_log_level(
+    _timer: IpcTimer,
+    app: AppHandle,
+    level: Option<String>,
+) -> CommandResult<bool> {
+    let level = level.map(|level| level.trim().to_lowercase());
+    if let Some(level) = &level {
+        ensure!(
+            LOG_LEVELS.contains(&level.as_str()),
+            "level",
+            "The log level must be one of {}",
+            LOG_LEVELS.join(", ")
+        );
+    }
+    app.state::<SettingsStore>()
+        .update(|settings| settings.backend_log_level = level.clone())
+        .map_err(|err| err.to_string())?;
+    tracing::info!("Backend log level set to {level:?}");
+    let live = level.as_deref().unwrap_or(DEFAULT_LOG_LEVEL);
+    Ok(relay(&app, "/control/log-level", json!({ "level": live })).await)
+}
+
+/// Turns a backend feature flag on or off, or with `None` back to the
+/// backend's default, from now and on every start. Resolves with whether the
+/// running backend took it right away.
+#[tauri::command]
+pub async fn set_backend_flag(
+    _timer: IpcTimer,
+    app: AppHandle,
+    name: String,
+    value: Option<bool>,
+) -> CommandResult<bool> {
+    let name = name.trim().to_string();
+    // They're passed on as `name=1,other=0`
+    ensure!(
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')),
+        "name",
+        "Flag names can only have letters, digits, '-', '_' and '.'"
+    );
+    app.state::<SettingsStore>()
+        .update(|settings| match value {
+            Some(value) => {
+                settings.backend_flags.insert(name.clone(), value);
+            }
+            None => {
+                settings.backend_flags.remove(&name);
+            }
+        })
+        .map_err(|err| err.to_string())?;
+    tracing::info!("Backend flag {name} set to {value:?}");
+    Ok(relay(
+        &app,
+        "/control/flags",
+        json!({ "name": name, "value": value }),
+    )
+    .await)
+}
@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::Connector;
+
+use crate::hooks;
+use crate::notifications;
+use crate::sidecar::Sidecar;
+use crate::tls;
+use crate::tray;
+use crate::webhooks;
+
+const EVENT: &str = "backend-event";
+const CONNECTION_EVENT: &str = "backend-connection";
+// Added to the stream once the connection is back, e.g. after the backend
+// restarted, as whatever it sent in between is lost
+const RECONNECTED: &str = "reconnected";
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_BUFFERED: usize = 500;
+
+/// A message from the backend's `/ws` channel as it was sent, numbered in
+/// the order it arrived
+#[derive(Clone, Serialize)]
+pub struct BackendEvent {
+    seq: u64,
+    #[serde(flatten)]
+    message: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replay {
+    events: Vec<BackendEvent>,
+    /// The `seq` to ask from next time
+    latest: u64,
+    /// False when some events since the one asked from were already dropped
+    /// from the buffer, so the window should reload what it shows instead
+    complete: bool,
+    connected: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection {
+    connected: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    buffer: VecDeque<BackendEvent>,
+    latest: u64,
+    connected: bool,
+}
+
+/// The one connection to the backend's realtime channel, shared by every
+/// window and by native notifications. Recent events are kept so a window
+/// opened or reloaded later can catch up on what it missed.
+#[derive(Default)]
+pub struct BackendEvents(Mutex<Inner>);
+
+impl BackendEvents {
+    fn push(&self, message: Map<String, Value>) -> BackendEvent {
+        let mut inner = self.0.lock().unwrap();
+        inner.latest += 1;
+        let event = BackendEvent {
+            seq: inner.latest,
+            message,
+        };
+        if inner.buffer.len() == MAX_BUFFERED {
+            inner.buffer.pop_front();
+        }
+        inner.buffer.push_back(event.clone());
+        event
+    }
+
+    fn set_connected(&self, connected: bool) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        std::mem::replace(&mut inner.connected, connected) != connected
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(BackendEvents::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut delay = MIN_RECONNECT_DELAY;
+        let mut connected_before = false;
+        loop {
+            match listen(&app, connected_before).await {
+                Ok(()) => tracing::debug!("Backend event channel closed"),
+                Err(err) => tracing::debug!("Backend event channel closed: {err}"),
+            }
+            let events = app.state::<BackendEvents>();
+            if events.set_connected(false) {
+                connected_before = true;
+                delay = MIN_RECONNECT_DELAY;
+                let _ = app.emit(CONNECTION_EVENT, Connection { connected: false });
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+}
+
+async fn listen(
+    app: &AppHandle,
+    reconnect: bool,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let sidecar = app.state::<Sidecar>();
+    let connector = match sidecar.tls().map(tls::client_config) {
+        Some(Ok(config)) => Some(Connector::Rustls(Arc::new(config))),
+        Some(Err(err)) => {
+            tracing::warn!("Invalid backend certificate: {err}");
+            None
+        }
+        None => None,
+    };
+    // Without subscribing, the backend sends the messages of every account
+    let (mut socket, _) = tokio_tungstenite::connect_async_tls_with_config(
+        sidecar.websocket_url("/ws"),
+        None,
+        false,
+        connector,
+    )
+    .await?;
+
+    let events = app.state::<BackendEvents>();
+    events.set_connected(true);
+    let _ = app.emit(CONNECTION_EVENT, Connection { connected: true });
+    if reconnect {
+        let mut message = Map::new();
+        message.insert("type".to_string(), Value::from(RECONNECTED));
+        let _ = app.emit(EVENT, events.push(message));
+    }
+
+    while let Some(message) = socket.next().await {
+        let Message::Text(text) = message? else {
+            continue;
+        };
+        let message = match serde_json::from_str::<Value>(&text) {
+            Ok(Value::Object(message)) if message.get("type").is_some_and(Value::is_string) => {
+                message
+            }
+            _ => {
+                tracing::debug!("Ignoring backend message without a type");
+                continue;
+            }
+        };
+        notifications::on_backend_message(app, &message);
+        tray::on_backend_message(app, &message);
+        hooks::on_backend_message(app, &message);
+        webhooks::on_backend_message(app, &message);
+        let _ = app.emit(EVENT, events.push(message));
+    }
+    Ok(())
+}
+
+/// Events after `since`, oldest first, for a window that just loaded or
+/// lost track. `since` 0 gives everything still buffered.
+#[tauri::command]
+pub fn get_backend_events(events: State<'_, BackendEvents>, since: Option<u64>) -> Replay {
+    let since = since.unwrap_or_default();
+    let inner = events.0.lock().unwrap();
+    let oldest = inner
+        .buffer
+        .front()
+        .map_or(inner.latest + 1, |event| event.seq);
+    Replay {
+        events: inner
+            .buffer
+            .iter()
+            .filter(|event| event.seq > since)
+            .cloned()
+            .collect(),
+        latest: inner.latest,
+        complete: since == 0 || since + 1 >= oldest,
+        connected: inner.connected,
+    }
+}
@@ -0,0 +1,155 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{AppHandle, Manager};
+
+use crate::backend_port;
+use crate::data_dir;
+use crate::settings::SettingsStore;
+use crate::sidecar;
+
+// Written next to the database while the backend runs, so the next launch
+// can tell a backend left behind by a crash from an unrelated process
+const LOCK_FILE: &str = "backend.lock";
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How long a killed process gets to go away before the database is opened
+const KILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendLock {
+    pid: u32,
+    instance: String,
+    port: u16,
+}
+
+fn path(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::data_dir(app).map(|dir| dir.join(LOCK_FILE))
+}
+
+/// Records the backend just spawned, see `clean_up`
+pub fn write(app: &AppHandle, pid: u32, port: u16) {
+    let (Some(path), Some(instance)) = (path(app), app.state::<SettingsStore>().get().instance_id)
+    else {
+        return;
+    };
+    let lock = BackendLock {
+        pid,
+        instance,
+        port,
+    };
+    let written = serde_json::to_vec(&lock)
+        .map_err(|err| err.to_string())
+        .and_then(|json| fs::write(&path, json).map_err(|err| err.to_string()));
+    if let Err(err) = written {
+        tracing::warn!("Failed to write {LOCK_FILE}: {err}");
+    }
+}
+
+/// Removes the lock once the backend has exited
+pub fn remove(app: &AppHandle) {
+    if let Some(path) = path(app) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// The process is this profile's backend only if it is still running and was
+// started with our instance id, which the OS may not let us read; then the
+// backend has to report it, and the pid, itself.
+fn is_ours(lock: &BackendLock, client: &reqwest::Client, scheme: &str) -> bool {
+    let pid = Pid::from_u32(lock.pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_environ(UpdateKind::Always),
+    );
+    let Some(process) = system.process(pid) else {
+        return false;
+    };
+    let expected = OsString::from(format!("HAMBA_INSTANCE_ID={}", lock.instance));
+    if !process.environ().is_empty() {
+        return process.environ().contains(&expected);
+    }
+    backend_port::identify(client, scheme, lock.port)
+        .is_some_and(|(instance, pid)| instance == lock.instance && pid == lock.pid)
+}
+
+pub fn is_running(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some()
+}
+
+/// Deals with what a crash left behind before a new backend is started. A
+/// backend that still holds the database is left running when the port it's
+/// on is the one this launch adopts, and is stopped otherwise, asking first
+/// and killing it after `shutdown_timeout_ms`. The lock goes either way.
+/// SQLite rolls back anything half written to the database on its own once
+/// the old backend no longer has it open.
+pub fn clean_up(app: &AppHandle, client: &reqwest::Client, scheme: &str) {
+    let Some(path) = path(app) else {
+        return;
+    };
+    let Ok(contents) = fs::read(&path) else {
+        return;
+    };
+    let lock: BackendLock = match serde_json::from_slice(&contents) {
+        Ok(lock) => lock,
+        Err(err) => {
+            tracing::warn!("Removing unreadable {LOCK_FILE}: {err}");
+            let _ = fs::remove_file(&path);
+            return;
+        }
+    };
+    let settings = app.state::<SettingsStore>().get();
+    if settings.instance_id.as_deref() != Some(&lock.instance) || !is_ours(&lock, client, scheme) {
+        tracing::info!(
+            "Removing the stale {LOCK_FILE}, pid {} is gone or isn't this profile's backend",
+            lock.pid
+        );
+        let _ = fs::remove_file(&path);
+        return;
+    }
+
+    if settings.backend_port == Some(lock.port)
+        && backend_port::can_adopt(client, scheme, lock.port, &lock.instance)
+    {
+        tracing::info!(
+            "The backend from an earlier launch (pid {}) is still running and will be reused",
+            lock.pid
+        );
+        return;
+    }
+
+    tracing::warn!(
+        "Stopping the backend from an earlier launch (pid {}) on port {}, which can't be reused",
+        lock.pid,
+        lock.port
+    );
+    let timeout = Duration::from_millis(settings.shutdown_timeout_ms);
+    let exited = (sidecar::terminate(lock.pid) && wait_for_exit(lock.pid, timeout))
+        || (sidecar::kill(lock.pid) && wait_for_exit(lock.pid, KILL_TIMEOUT));
+    if !exited {
+        tracing::error!("Failed to stop the earlier backend (pid {})", lock.pid);
+        return;
+    }
+    let _ = fs::remove_file(&path);
+}
+
+fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_running(pid) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+    true
+}
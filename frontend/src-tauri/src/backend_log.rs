@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+// How many lines of output are kept around to attach to a crash report
+const RECENT_LINES: usize = 200;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendLogLine<'a> {
+    stream: Stream,
+    line: &'a str,
+}
+
+/// Sink for the sidecar's stdout/stderr. Every line goes to the app log under
+/// the `backend` target and is emitted to the webview as `backend-log`.
+pub struct BackendLog {
+    app: AppHandle,
+    recent: VecDeque<String>,
+}
+
+impl BackendLog {
+    pub fn new(app: &AppHandle) -> Self {
+        Self {
+            app: app.clone(),
+            recent: VecDeque::with_capacity(RECENT_LINES),
+        }
+    }
+
+    /// Takes the lines written since the last call, oldest first
+    pub fn take_recent(&mut self) -> Vec<String> {
+        self.recent.drain(..).collect()
+    }
+
+    pub fn write(&mut self, stream: Stream, bytes: &[u8]) {
+        let text = String::from_utf8_lossy(bytes);
+        let line = text.trim_end_matches(['\r', '\n']);
+
+        match stream {
+            Stream::Stdout => tracing::info!(target: "backend", "{line}"),
+            Stream::Stderr => tracing::warn!(target: "backend", "{line}"),
+        }
+        let _ = self
+            .app
+            .emit("backend-log", BackendLogLine { stream, line });
+
+        if self.recent.len() == RECENT_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line.to_string());
+    }
+}
@@ -0,0 +1,257 @@
+use std::net::TcpListener;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+// How many ports past a taken one to try before letting the OS pick
+const SEARCH_RANGE: u16 = 100;
+// Below this ports are privileged on Unix and often claimed by system services
+const MIN_PORT: u16 = 1024;
+// The token the backend on a port was started with, so a later launch can
+// keep talking to it. Suffixed with the instance id, as profiles share the
+// keychain.
+pub const TOKEN_SECRET_PREFIX: &str = "backend-token-";
+
+/// Where the backend runs for this launch
+pub struct Choice {
+    pub port: u16,
+    pub token: String,
+    /// A backend from an earlier launch already serving `port`, which is
+    /// used instead of starting another
+    pub adopted_pid: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Health {
+    instance: Option<String>,
+    pid: Option<u32>,
+}
+
+/// Settles on the port for the bundled backend: the one from the last
+/// launch if it's free, the backend still running there if it's ours, and
+/// otherwise the next free one, which is remembered unless the port is pinned.
+pub fn choose(app: &AppHandle, client: &reqwest::Client, scheme: &str) -> Choice {
+    let settings = app.state::<SettingsStore>().get();
+    let instance = instance_id(app);
+
+    if let Some(preferred) = settings.backend_port {
+        if is_free(preferred) {
+            return fresh(&instance, preferred);
+        }
+        if let Some((pid, token)) = adopt(client, scheme, preferred, &instance) {
+            tracing::info!("Reusing the backend (pid {pid}) left running on port {preferred}");
+            return Choice {
+                port: preferred,
+                token,
+                adopted_pid: Some(pid),
+            };
+        }
+    }
+
+    let port = match settings.backend_port {
+        Some(preferred) => {
+            let port = next_free(preferred);
+            tracing::warn!("Backend port {preferred} is taken, using {port}");
+            port
+        }
+        None => sidecar::free_port().unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to find a free port, falling back to {}: {err}",
+                sidecar::DEV_PORT
+            );
+            sidecar::DEV_PORT
+        }),
+    };
+    if !settings.pin_backend_port {
+        remember(app, port);
+    }
+    fresh(&instance, port)
+}
+
+// A backend started by this launch, with a token of its own
+fn fresh(instance: &str, port: u16) -> Choice {
+    let token = sidecar::generate_token();
+    if let Err(err) = secrets::set(&format!("{TOKEN_SECRET_PREFIX}{instance}"), &token) {
+        tracing::debug!("Failed to keep the backend token, it can't be reused: {err}");
+    }
+    Choice {
+        port,
+        token,
+        adopted_pid: None,
+    }
+}
+
+fn instance_id(app: &AppHandle) -> String {
+    let store = app.state::<SettingsStore>();
+    if let Some(instance) = store.get().instance_id {
+        return instance;
+    }
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS random number generator unavailable");
+    let instance = hex::encode(bytes);
+    if let Err(err) = store.update(|settings| settings.instance_id = Some(instance.clone())) {
+        tracing::warn!("Failed to save the instance id: {err}");
+    }
+    instance
+}
+
+fn remember(app: &AppHandle, port: u16) {
+    if let Err(err) = app
+        .state::<SettingsStore>()
+        .update(|settings| settings.backend_port = Some(port))
+    {
+        tracing::warn!("Failed to save the backend port: {err}");
+    }
+}
+
+// The ports after `taken`, or one the OS picks once those are all taken too
+fn next_free(taken: u16) -> u16 {
+    let start = taken.saturating_add(1);
+    (start..=start.saturating_add(SEARCH_RANGE))
+        .find(|port| is_free(*port))
+        .or_else(|| sidecar::free_port().ok())
+        .unwrap_or(start)
+}
+
+fn is_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// The instance id and pid the backend on `port` reports about itself, if
+/// something there answers like one
+pub fn identify(client: &reqwest::Client, scheme: &str, port: u16) -> Option<(String, u32)> {
+    tauri::async_runtime::block_on(async {
+        let health: Health = client
+            .get(format!("{scheme}://localhost:{port}/health"))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+        Some((health.instance?, health.pid?))
+    })
+}
+
+/// Whether `choose` would reuse the backend on `port` instead of starting one
+pub fn can_adopt(client: &reqwest::Client, scheme: &str, port: u16, instance: &str) -> bool {
+    adopt(client, scheme, port, instance).is_some()
+}
+
+// Whatever holds the port counts as ours only if it reports our instance id
+// and accepts the token kept from the launch that started it
+fn adopt(
+    client: &reqwest::Client,
+    scheme: &str,
+    port: u16,
+    instance: &str,
+) -> Option<(u32, String)> {
+    let (reported, pid) = identify(client, scheme, port)?;
+    if reported != instance {
+        return None;
+    }
+    let token = secrets::get(&format!("{TOKEN_SECRET_PREFIX}{instance}")).ok()??;
+    let authorized = tauri::async_runtime::block_on(async {
+        client
+            .get(format!("{scheme}://localhost:{port}/health/detailed"))
+            .header(sidecar::TOKEN_HEADER, &token)
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    });
+    authorized.then_some((pid, token))
+}
+
+/// Pins the backend to `port` from the next launch on, or goes back to
+/// remembering whichever port the last launch ended up with for `None`
+#[tauri::command]
+pub fn set_backend_port(
+    settings: State<'_, SettingsStore>,
+    sidecar: State<'_, Sidecar>,
+    port: Option<u16>,
+) -> Result<(), String> {
+    if sidecar.is_external() {
+        return Err("The backend runs outside the app, its port is set there".to_string());
+    }
+    if let Some(port) = port {
+        if port < MIN_PORT {
+            return Err(format!("Pick a port from {MIN_PORT} up"));
+        }
+        if port != sidecar.port() && !is_free(port) {
+            return Err(format!("Port {port} is already in use"));
+        }
+    }
+    settings
+        .update(|settings| {
+            settings.pin_backend_port = port.is_some();
+            if port.is_some() {
+                settings.backend_port = port;
+            }
+        })
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::thread;
+
+    // Answers one request on a port of its own with `body` as JSON
+    fn health(body: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+        port
+    }
+
+    #[test]
+    fn taken_ports_are_skipped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken = listener.local_addr().unwrap().port();
+        assert!(!is_free(taken));
+
+        let port = next_free(taken - 1);
+        assert_ne!(port, taken);
+        assert!(is_free(port));
+    }
+
+    #[test]
+    fn backends_are_identified_by_their_health() {
+        let client = reqwest::Client::new();
+        let port = health(r#"{"status":"ok","instance":"abc","pid":42}"#);
+        assert_eq!(
+            identify(&client, "http", port),
+            Some(("abc".to_string(), 42))
+        );
+
+        let port = health(r#"{"status":"ok"}"#);
+        assert_eq!(identify(&client, "http", port), None);
+
+        let free = sidecar::free_port().unwrap();
+        assert_eq!(identify(&client, "http", free), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::fs_scope::{self, Access};
+use crate::integrity;
+use crate::metrics::IpcTimer;
+use crate::sidecar::{self, Sidecar};
+
+// Time the frontend gets to hold back new requests before the old backend is
+// asked to exit, which then finishes the ones already in flight
+const DRAIN_GRACE: Duration = Duration::from_secs(1);
+
+/// Held for the duration of a swap so two never interleave
+#[derive(Default)]
+pub struct BackendSwap(tokio::sync::Mutex<()>);
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+enum SwapStage {
+    Draining,
+    Starting,
+    Ready,
+    RollingBack { error: String },
+    RolledBack { error: String },
+    Failed { error: String },
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(BackendSwap::default());
+}
+
+/// Replaces the running backend with the binary at `path` without touching
+/// the UI, emitting `backend-swap` at each stage. The new backend runs on the
+/// same port and is only kept if it passes the health check; otherwise the
+/// previous binary is started again. The swap lasts until the app quits.
+#[tauri::command]
+pub async fn swap_backend(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: PathBuf,
+    sha256: String,
+) -> Result<(), String> {
+    if app.state::<Sidecar>().is_external() {
+        return Err("The backend is not run by the app in this mode".to_string());
+    }
+    if !path.is_absolute() || !path.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    let path = fs_scope::check(&app, &path, Access::Read).map_err(|err| err.to_string())?;
+    integrity::verify_file(&path, &sha256)?;
+    swap(&app, path).await
+}
+
+/// Runs the backend at `path` in place of the current one as
+/// `swap_backend` does, for a binary that was already checked
+pub async fn swap(app: &AppHandle, path: PathBuf) -> Result<(), String> {
+    let _guard = app.state::<BackendSwap>().inner().0.lock().await;
+    emit(app, SwapStage::Draining);
+    tokio::time::sleep(DRAIN_GRACE).await;
+
+    tracing::info!("Swapping the backend for {}", path.display());
+    let previous = app.state::<Sidecar>().set_binary(Some(path));
+    emit(app, SwapStage::Starting);
+    if sidecar::restart(app).await {
+        emit(app, SwapStage::Ready);
+        return Ok(());
+    }
+
+    let error = "The new backend did not pass its health check".to_string();
+    tracing::error!("{error}, rolling back");
+    emit(
+        app,
+        SwapStage::RollingBack {
+            error: error.clone(),
+        },
+    );
+    app.state::<Sidecar>().set_binary(previous);
+    if sidecar::restart(app).await {
+        emit(
+            app,
+            SwapStage::RolledBack {
+                error: error.clone(),
+            },
+        );
+        Err(format!("{error}, the previous backend was restored"))
+    } else {
+        let error = format!("{error}, and the previous backend did not come back either");
+        emit(
+            app,
+            SwapStage::Failed {
+                error: error.clone(),
+            },
+        );
+        Err(error)
+    }
+}
+
+fn emit(app: &AppHandle, stage: SwapStage) {
+    let _ = app.emit("backend-swap", stage);
+}
@@ -0,0 +1,387 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::canary;
+use crate::data_dir;
+use crate::disk;
+use crate::fs_scope::{self, Access};
+use crate::hooks;
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar, DATABASE_FILE, DATABASE_SIDE_FILES};
+use crate::trash;
+use crate::webhooks;
+
+const BACKUPS_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "hamba-backup-";
+const RESTORE_TMP_DIR: &str = ".restore";
+// Written by the backend into the backups folder and zipped in place of the
+// live database
+const SNAPSHOT_FILE: &str = "snapshot.db";
+const SNAPSHOT_PATH: &str = "/backup/snapshot";
+// Left out of backups, and left alone when restoring one
+const EXCLUDED: &[&str] = &[
+    BACKUPS_DIR,
+    RESTORE_TMP_DIR,
+    "logs",
+    trash::TRASH_DIR,
+    canary::CANARY_DIR,
+];
+// How often the scheduler wakes up to see whether a backup is due
+const SCHEDULE_TICK: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    path: PathBuf,
+    /// Seconds since the Unix epoch
+    created_at: u64,
+    size: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum BackupEvent {
+    Completed { path: PathBuf, scheduled: bool },
+    Failed { error: String, scheduled: bool },
+}
+
+/// Held for the duration of a backup or restore so they never overlap
+#[derive(Default)]
+pub struct Backups(Mutex<()>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Backups::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULE_TICK).await;
+            if backup_due(&app) {
+                let _ = run(&app, true).await;
+            }
+        }
+    });
+}
+
+fn backup_due(app: &AppHandle) -> bool {
+    let hours = app.state::<SettingsStore>().get().backup_interval_hours;
+    if hours == 0 {
+        return false;
+    }
+    let last = list(app).first().map(|backup| backup.created_at);
+    last.is_none_or(|last| now() >= last + hours * 60 * 60)
+}
+
+/// Creates a backup off the async runtime and emits `backup` with the outcome
+async fn run(app: &AppHandle, scheduled: bool) -> Result<PathBuf, String> {
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || create(&handle))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|result| result);
+
+    let event = match &result {
+        Ok(path) => BackupEvent::Completed {
+            path: path.clone(),
+            scheduled,
+        },
+        Err(error) => {
+            tracing::warn!("Backup failed: {error}");
+            BackupEvent::Failed {
+                error: error.clone(),
+                scheduled,
+            }
+        }
+    };
+    let (hook, webhook) = match &event {
+        BackupEvent::Completed { .. } => ("on-backup-complete", "backup_complete"),
+        BackupEvent::Failed { .. } => ("on-backup-failed", "backup_failed"),
+    };
+    hooks::fire(app, hook, &event);
+    webhooks::fire(app, webhook, &event);
+    let _ = app.emit("backup", event);
+    result
+}
+
+/// Zips the data directory. While the backend runs it snapshots the database
+/// for the archive, so mail keeps syncing during a backup.
+fn create(app: &AppHandle) -> Result<PathBuf, String> {
+    let backups = app.state::<Backups>();
+    let _guard = backups.0.lock().unwrap();
+    let data = data_dir::data_dir(app).ok_or("No data directory available")?;
+    let dir = data.join(BACKUPS_DIR);
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let path = dir.join(format!("{BACKUP_PREFIX}{}.zip", now()));
+    // Compressed, the archive comes out smaller than the data itself. The
+    // snapshot needs room for another copy of the database.
+    let database = data.join(DATABASE_FILE);
+    disk::ensure_space(app, &dir, data_size(&data) + disk::size_of(&database))?;
+
+    // A stopped backend isn't writing, so its files are copied as they are
+    let sidecar = app.state::<Sidecar>();
+    let snapshot = dir.join(SNAPSHOT_FILE);
+    let _ = fs::remove_file(&snapshot);
+    let live = !sidecar.is_stopped() && !sidecar.is_external() && database.exists();
+    if live {
+        take_snapshot(&sidecar, &snapshot)?;
+    }
+    let result = write_zip(&data, &path, live.then_some(snapshot.as_path()));
+    let _ = fs::remove_file(&snapshot);
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&path);
+        return Err(err.to_string());
+    }
+    prune(app);
+    Ok(path)
+}
+
+// Has the backend write a consistent copy of the database to `path`. It
+// goes on serving and writing meanwhile.
+fn take_snapshot(sidecar: &Sidecar, path: &Path) -> Result<(), String> {
+    let request = sidecar
+        .request(reqwest::Method::POST, SNAPSHOT_PATH)
+        .json(&json!({ "path": path }))
+        .send();
+    let response = tauri::async_runtime::block_on(request)
+        .map_err(|err| format!("Could not reach the backend to snapshot the database: {err}"))?;
+    if !response.status().is_success() {
+        let error = tauri::async_runtime::block_on(response.text()).unwrap_or_default();
+        let _ = fs::remove_file(path);
+        return Err(format!(
+            "The backend could not snapshot the database: {error}"
+        ));
+    }
+    Ok(())
+}
+
+// With a `snapshot`, it goes in as the database and the live file's WAL
+// and shared memory are left out
+fn write_zip(data: &Path, path: &Path, snapshot: Option<&Path>) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut pending = vec![data.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(data).unwrap_or(&path);
+            if dir == data && is_excluded(relative) {
+                continue;
+            }
+            // Zip entry names always use forward slashes
+            let name = relative.to_string_lossy().replace('\\', "/");
+            let source = match snapshot {
+                Some(snapshot) if relative == Path::new(DATABASE_FILE) => snapshot,
+                Some(_) if is_side_file(relative) => continue,
+                _ => &path,
+            };
+            if path.is_dir() {
+                zip.add_directory(name, options)?;
+                pending.push(path);
+            } else {
+                zip.start_file(name, options)?;
+                io::copy(&mut File::open(source)?, &mut zip)?;
+            }
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn data_size(data: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(data) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| !is_excluded(path.strip_prefix(data).unwrap_or(path)))
+        .map(|path| disk::size_of(&path))
+        .sum()
+}
+
+fn is_side_file(relative: &Path) -> bool {
+    DATABASE_SIDE_FILES
+        .iter()
+        .any(|suffix| relative == sidecar::with_suffix(Path::new(DATABASE_FILE), suffix))
+}
+
+fn is_excluded(relative: &Path) -> bool {
+    EXCLUDED.iter().any(|name| relative == Path::new(name))
+}
+
+/// Replaces the data directory with the contents of a backup. Everything is
+/// extracted to a scratch folder first, so a broken archive changes nothing.
+fn restore(app: &AppHandle, archive: &Path) -> Result<(), String> {
+    let backups = app.state::<Backups>();
+    let _guard = backups.0.lock().unwrap();
+    let data = data_dir::data_dir(app).ok_or("No data directory available")?;
+    let scratch = data.join(RESTORE_TMP_DIR);
+
+    let needed =
+        uncompressed_size(archive).map_err(|err| format!("Could not read backup: {err}"))?;
+    disk::ensure_space(app, &data, needed)?;
+
+    let _ = fs::remove_dir_all(&scratch);
+    if let Err(err) = extract(archive, &scratch) {
+        let _ = fs::remove_dir_all(&scratch);
+        return Err(format!("Could not read backup: {err}"));
+    }
+
+    // Unlike a backup, this swaps the database file out from under it
+    let was_running = !app.state::<Sidecar>().is_stopped();
+    sidecar::stop(app);
+    let result = swap_in(app, &data, &scratch);
+    let _ = fs::remove_dir_all(&scratch);
+    if was_running {
+        sidecar::start(app);
+    }
+    result.map_err(|err| err.to_string())
+}
+
+fn uncompressed_size(archive: &Path) -> zip::result::ZipResult<u64> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut size = 0;
+    for index in 0..zip.len() {
+        size += zip.by_index_raw(index)?.size();
+    }
+    Ok(size)
+}
+
+fn extract(archive: &Path, into: &Path) -> zip::result::ZipResult<()> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        // Entries that would land outside the target (`../`, absolute paths)
+        // are skipped rather than trusted
+        let Some(name) = file.enclosed_name() else {
+            continue;
+        };
+        let path = into.join(name);
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut File::create(&path)?)?;
+    }
+    Ok(())
+}
+
+// What the backup replaces goes to the trash, so the restore can be undone
+fn swap_in(app: &AppHandle, data: &Path, scratch: &Path) -> io::Result<()> {
+    let mut replaced = Vec::new();
+    for entry in fs::read_dir(data)? {
+        let path = entry?.path();
+        if !is_excluded(path.strip_prefix(data).unwrap_or(&path)) {
+            replaced.push(path);
+        }
+    }
+    trash::move_to_trash(app, "Replaced by restoring a backup", &replaced)?;
+    for entry in fs::read_dir(scratch)? {
+        let path = entry?.path();
+        if let Some(name) = path.file_name() {
+            fs::rename(&path, data.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the oldest backups beyond the number the settings say to keep
+fn prune(app: &AppHandle) {
+    let keep = app.state::<SettingsStore>().get().backup_keep;
+    for backup in list(app).iter().skip(keep.max(1)) {
+        if let Err(err) = fs::remove_file(&backup.path) {
+            tracing::warn!(
+                "Failed to remove old backup {}: {err}",
+                backup.path.display()
+            );
+        }
+    }
+}
+
+/// Backups on disk, newest first
+fn list(app: &AppHandle) -> Vec<BackupInfo> {
+    let Some(dir) = data_dir::data_dir(app).map(|dir| dir.join(BACKUPS_DIR)) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<BackupInfo> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let created_at = name
+                .strip_prefix(BACKUP_PREFIX)?
+                .strip_suffix(".zip")?
+                .parse()
+                .ok()?;
+            Some(BackupInfo {
+                path: entry.path(),
+                created_at,
+                size: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    backups
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[tauri::command]
+pub async fn create_backup(_timer: IpcTimer, app: AppHandle) -> Result<PathBuf, String> {
+    run(&app, false).await
+}
+
+/// Restores one of `list_backups`, or an archive the user picked
+#[tauri::command]
+pub async fn restore_backup(_timer: IpcTimer, app: AppHandle, path: PathBuf) -> Result<(), String> {
+    let path = match list(&app).into_iter().find(|backup| backup.path == path) {
+        Some(backup) => backup.path,
+        None => fs_scope::check(&app, &path, Access::Read).map_err(|err| err.to_string())?,
+    };
+    tauri::async_runtime::spawn_blocking(move || restore(&app, &path))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+#[tauri::command]
+pub fn list_backups(app: AppHandle) -> Vec<BackupInfo> {
+    list(&app)
+}
+
+/// `interval_hours` of 0 turns scheduled backups off
+#[tauri::command]
+pub fn set_backup_schedule(
+    settings: State<'_, SettingsStore>,
+    interval_hours: u64,
+    keep: usize,
+) -> Result<(), String> {
+    settings
+        .update(|settings| {
+            settings.backup_interval_hours = interval_hours;
+            settings.backup_keep = keep;
+        })
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
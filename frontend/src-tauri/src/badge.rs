@@ -0,0 +1,106 @@
+use tauri::{AppHandle, Manager};
+
+use crate::dnd;
+use crate::main_window;
+
+/// Shows `count` on the app icon: the dock badge on macOS, the launcher
+/// count on Linux (Unity and desktops that support its API) and a taskbar
+/// overlay icon on Windows. Zero clears it. Focus mode holds it until it
+/// ends.
+pub fn set(app: &AppHandle, count: u32) -> Result<(), String> {
+    if dnd::hold_badge(app, count) {
+        return Ok(());
+    }
+    let Some(window) = app.get_webview_window(main_window::LABEL) else {
+        return Err("The main window is not open".to_string());
+    };
+
+    #[cfg(windows)]
+    let result = window.set_overlay_icon((count > 0).then(|| overlay::render(count)));
+    #[cfg(not(windows))]
+    let result = window.set_badge_count((count > 0).then_some(count.into()));
+
+    result.map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn set_badge_count(app: AppHandle, count: u32) -> Result<(), String> {
+    set(&app, count)
+}
+
+// Windows has no badge API, only a small overlay image on the taskbar button,
+// so the count is drawn onto a red dot with a tiny bitmap font
+#[cfg(windows)]
+mod overlay {
+    use tauri::image::Image;
+
+    const SIZE: usize = 16;
+    const SCALE: usize = 2;
+    const GLYPH_WIDTH: usize = 3;
+    const GLYPH_HEIGHT: usize = 5;
+    const BACKGROUND: [u8; 4] = [0xe0, 0x24, 0x24, 0xff];
+    const FOREGROUND: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+    // 3x5 glyphs, one row per entry, high bit on the left
+    const DIGITS: [[u8; GLYPH_HEIGHT]; 10] = [
+        [0b111, 0b101, 0b101, 0b101, 0b111],
+        [0b010, 0b110, 0b010, 0b010, 0b111],
+        [0b111, 0b001, 0b111, 0b100, 0b111],
+        [0b111, 0b001, 0b111, 0b001, 0b111],
+        [0b101, 0b101, 0b111, 0b001, 0b001],
+        [0b111, 0b100, 0b111, 0b001, 0b111],
+        [0b111, 0b100, 0b111, 0b101, 0b111],
+        [0b111, 0b001, 0b010, 0b010, 0b010],
+        [0b111, 0b101, 0b111, 0b101, 0b111],
+        [0b111, 0b101, 0b111, 0b001, 0b111],
+    ];
+    const PLUS: [u8; GLYPH_HEIGHT] = [0b000, 0b010, 0b111, 0b010, 0b000];
+
+    /// Two glyphs fit at this size, so anything past 9 shows as "9+"
+    pub fn render(count: u32) -> Image<'static> {
+        let glyphs: &[[u8; GLYPH_HEIGHT]] = if count > 9 {
+            &[DIGITS[9], PLUS]
+        } else {
+            &[DIGITS[count as usize]]
+        };
+
+        let mut rgba = vec![0; SIZE * SIZE * 4];
+        let center = (SIZE as f32 - 1.0) / 2.0;
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (dx, dy) = (x as f32 - center, y as f32 - center);
+                if dx * dx + dy * dy <= center * center + 1.0 {
+                    put(&mut rgba, x, y, BACKGROUND);
+                }
+            }
+        }
+
+        let glyph_width = GLYPH_WIDTH * SCALE;
+        let text_width = glyphs.len() * glyph_width + (glyphs.len() - 1) * SCALE / 2;
+        let left = (SIZE - text_width) / 2;
+        let top = (SIZE - GLYPH_HEIGHT * SCALE) / 2;
+        for (index, glyph) in glyphs.iter().enumerate() {
+            let origin = left + index * (glyph_width + SCALE / 2);
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    for sy in 0..SCALE {
+                        for sx in 0..SCALE {
+                            let x = origin + column * SCALE + sx;
+                            let y = top + row * SCALE + sy;
+                            put(&mut rgba, x, y, FOREGROUND);
+                        }
+                    }
+                }
+            }
+        }
+        Image::new_owned(rgba, SIZE as u32, SIZE as u32)
+    }
+
+    fn put(rgba: &mut [u8], x: usize, y: usize, color: [u8; 4]) {
+        let offset = (y * SIZE + x) * 4;
+        rgba[offset..offset + 4].copy_from_slice(&color);
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+use crate::power;
+use crate::settings::SettingsStore;
+use crate::sidecar::Sidecar;
+use crate::watchdog;
+
+// Heartbeats come this many times less often while saving power; the
+// watchdog waits as many times longer before calling the backend hung
+const SLOW_HEARTBEAT_FACTOR: u32 = 4;
+const PERCENT_RANGE: std::ops::RangeInclusive<u8> = 5..=95;
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatterySaverMode {
+    /// On while running on battery at or below `battery_saver_percent`
+    #[default]
+    Auto,
+    /// Always on, e.g. on a machine that reports no battery level
+    On,
+    Off,
+}
+
+/// Throttles background work while the battery runs low: the backend stops
+/// syncing, prints its heartbeat less often and runs at a lower priority.
+/// Everything goes back once the machine is on mains again.
+#[derive(Default)]
+pub struct BatterySaver {
+    active: AtomicBool,
+    // Keeps turning on and off from overlapping
+    switching: tokio::sync::Mutex<()>,
+}
+
+/// Needs the settings, and comes before the backend is started so it starts
+/// throttled when battery saver is always on
+pub fn init(app: &AppHandle) {
+    let always = app.state::<SettingsStore>().get().battery_saver == BatterySaverMode::On;
+    app.manage(BatterySaver {
+        active: AtomicBool::new(always),
+        ..BatterySaver::default()
+    });
+}
+
+pub fn is_active(app: &AppHandle) -> bool {
+    app.state::<BatterySaver>().active.load(Ordering::Relaxed)
+}
+
+pub fn validate_percent(percent: u8) -> Result<(), String> {
+    if !PERCENT_RANGE.contains(&percent) {
+        return Err(format!(
+            "The battery level must be between {}% and {}%",
+            PERCENT_RANGE.start(),
+            PERCENT_RANGE.end()
+        ));
+    }
+    Ok(())
+}
+
+fn interval(active: bool) -> Duration {
+    if active {
+        watchdog::HEARTBEAT_INTERVAL * SLOW_HEARTBEAT_FACTOR
+    } else {
+        watchdog::HEARTBEAT_INTERVAL
+    }
+}
+
+/// How often the backend is asked to print its heartbeat
+pub fn heartbeat_interval(app: &AppHandle) -> Duration {
+    interval(is_active(app))
+}
+
+/// `HAMBA_POWER_SAVER` for a backend started while saving power, so it
+/// doesn't start syncing only to be told to stop
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    if is_active(app) {
+        vec![("HAMBA_POWER_SAVER".to_string(), "1".to_string())]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Lowers the priority of a backend started while saving power
+pub fn spawned(app: &AppHandle, pid: u32) {
+    if is_active(app) {
+        set_low_priority(pid, true);
+    }
+}
+
+fn wanted(app: &AppHandle) -> bool {
+    let settings = app.state::<SettingsStore>().get();
+    match settings.battery_saver {
+        BatterySaverMode::On => true,
+        BatterySaverMode::Off => false,
+        BatterySaverMode::Auto => {
+            let battery = power::battery(app);
+            battery.on_battery
+                && battery
+                    .percent
+                    .is_some_and(|percent| percent <= settings.battery_saver_percent)
+        }
+    }
+}
+
+/// Turns battery saver on or off to match the power supply and the
+/// settings, after every battery poll and settings change
+pub fn apply(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let saver = app.state::<BatterySaver>();
+        let _switching = saver.switching.lock().await;
+        let active = wanted(&app);
+        if active == is_active(&app) {
+            return;
+        }
+        tracing::info!("Battery saver turned {}", if active { "on" } else { "off" });
+
+        // The watchdog has to wait longer before the heartbeats slow down,
+        // and keep doing so until they're fast again
+        if active {
+            saver.active.store(true, Ordering::Relaxed);
+        }
+        let response = app
+            .state::<Sidecar>()
+            .request(reqwest::Method::POST, "/background/power-saver")
+            .json(&json!({
+                "enabled": active,
+                "heartbeatMs": interval(active).as_millis() as u64,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = response {
+            // A backend started from now on gets it through `env`
+            tracing::warn!("Failed to tell the backend about battery saver: {err}");
+        }
+        if !active {
+            saver.active.store(false, Ordering::Relaxed);
+        }
+
+        if let Some(pid) = app.state::<Sidecar>().pid() {
+            set_low_priority(pid, active);
+        }
+        power::battery_saver_changed(&app, active);
+    });
+}
+
+fn set_low_priority(pid: u32, low: bool) {
+    if let Err(err) = platform::set_low_priority(pid, low) {
+        tracing::info!("Failed to change the backend's priority: {err}");
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::io;
+
+    // Only root may raise a priority again, so a backend lowered once stays
+    // that way until it restarts
+    const LOW_NICE: libc::c_int = 10;
+
+    pub fn set_low_priority(pid: u32, low: bool) -> io::Result<()> {
+        let nice = if low { LOW_NICE } else { 0 };
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io;
+
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+        PROCESS_SET_INFORMATION,
+    };
+
+    pub fn set_low_priority(pid: u32, low: bool) -> io::Result<()> {
+        let class = if low {
+            BELOW_NORMAL_PRIORITY_CLASS
+        } else {
+            NORMAL_PRIORITY_CLASS
+        };
+        let process = unsafe { OpenProcess(PROCESS_SET_INFORMATION, 0, pid) };
+        if process.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let set = unsafe { SetPriorityClass(process, class) };
+        let result = if set == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        unsafe { CloseHandle(process) };
+        result
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::io;
+
+    pub fn set_low_priority(_pid: u32, _low: bool) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
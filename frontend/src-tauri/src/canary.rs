@@ -0,0 +1,413 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+
+use crate::backend_swap;
+use crate::command::{CommandError, CommandResult};
+use crate::data_dir;
+use crate::disk;
+use crate::fs_scope::{self, Access};
+use crate::integrity;
+use crate::metrics::IpcTimer;
+use crate::sidecar::{self, with_suffix, Sidecar, DATABASE_FILE, DATABASE_SIDE_FILES};
+
+/// In the data folder, which the sandbox lets the canary into, and left out
+/// of backups
+pub const CANARY_DIR: &str = "canary";
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_MISMATCHES: usize = 50;
+// Sign-in routes send the browser on to the providers
+const SKIPPED_PREFIXES: &[&str] = &["/auth/login"];
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mismatch {
+    path: String,
+    /// Seconds since the Unix epoch
+    at: u64,
+    primary_status: u16,
+    canary_status: u16,
+    /// e.g. `Differs at /emails/3/subject`
+    difference: String,
+}
+
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryReport {
+    running: bool,
+    binary: Option<PathBuf>,
+    port: Option<u16>,
+    /// Share of reads mirrored, above 0 and up to 1
+    fraction: f64,
+    started_at: Option<u64>,
+    mirrored: u64,
+    matched: u64,
+    mismatched: u64,
+    /// Reads either backend didn't answer
+    failed: u64,
+    /// Newest first, up to `MAX_MISMATCHES`
+    mismatches: Vec<Mismatch>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CanaryChanged {
+    running: bool,
+}
+
+struct Running {
+    binary: PathBuf,
+    port: u16,
+    fraction: f64,
+    child: CommandChild,
+}
+
+/// A second backend build run next to the real one on its own port and a
+/// copy of the database. A share of the frontend's reads is sent to both
+/// and the answers compared, so an upgrade can be tried on real data before
+/// `promote_canary` switches over. The copy isn't synced, so differences
+/// build up the longer the canary runs.
+#[derive(Default)]
+pub struct Canary {
+    running: Mutex<Option<Running>>,
+    report: Mutex<CanaryReport>,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Canary::default());
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn emit(app: &AppHandle, running: bool) {
+    let _ = app.emit("canary-changed", CanaryChanged { running });
+}
+
+// The database as it is right now; a write landing halfway through only
+// shows up as a difference later
+fn copy_data(app: &AppHandle, dir: &Path) -> Result<(), String> {
+    let data = data_dir::data_dir(app).ok_or("No data directory available")?;
+    let database = data.join(DATABASE_FILE);
+    let files: Vec<PathBuf> = std::iter::once(database.clone())
+        .chain(
+            DATABASE_SIDE_FILES
+                .iter()
+                .map(|suffix| with_suffix(&database, suffix)),
+        )
+        .filter(|path| path.is_file())
+        .collect();
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    disk::ensure_space(app, dir, files.iter().map(|path| disk::size_of(path)).sum())?;
+    for file in files {
+        let name = file.file_name().ok_or("Invalid database path")?;
+        fs::copy(&file, dir.join(name)).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+// Output goes to the app log; an exit nobody asked for ends the run
+async fn watch(app: AppHandle, port: u16, mut rx: tauri::async_runtime::Receiver<CommandEvent>) {
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(bytes) | CommandEvent::Stderr(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                tracing::info!(target: "sidecar", "[canary] {}", text.trim_end());
+            }
+            CommandEvent::Terminated(payload) => {
+                let canary = app.state::<Canary>();
+                let mut running = canary.running.lock().unwrap();
+                if running.as_ref().is_some_and(|running| running.port == port) {
+                    tracing::warn!("The canary backend exited (code {:?})", payload.code);
+                    running.take();
+                    canary.report.lock().unwrap().running = false;
+                    emit(&app, false);
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Stops the canary and deletes its copy of the data, returning the binary
+/// it ran
+pub fn stop(app: &AppHandle) -> Option<PathBuf> {
+    let canary = app.try_state::<Canary>()?;
+    let running = canary.running.lock().unwrap().take()?;
+    if let Err(err) = running.child.kill() {
+        tracing::warn!("Failed to kill the canary backend: {err}");
+    }
+    canary.report.lock().unwrap().running = false;
+    if let Some(dir) = data_dir::data_dir(app).map(|dir| dir.join(CANARY_DIR)) {
+        if let Err(err) = fs::remove_dir_all(&dir) {
+            tracing::debug!("Failed to remove {}: {err}", dir.display());
+        }
+    }
+    emit(app, false);
+    tracing::info!("Stopped the canary backend");
+    Some(running.binary)
+}
+
+fn sampled(fraction: f64) -> bool {
+    let mut bytes = [0u8; 4];
+    if getrandom::fill(&mut bytes).is_err() {
+        return false;
+    }
+    (u32::from_le_bytes(bytes) as f64 / u32::MAX as f64) < fraction
+}
+
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+async fn fetch(request: reqwest::RequestBuilder) -> reqwest::Result<Response> {
+    let response = request.timeout(MIRROR_TIMEOUT).send().await?;
+    let status = response.status().as_u16();
+    let body = response.bytes().await?.to_vec();
+    Ok(Response { status, body })
+}
+
+// JSON pointer escaping, RFC 6901
+fn pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn first_difference(primary: &Value, canary: &Value, pointer: &str) -> Option<String> {
+    match (primary, canary) {
+        (Value::Object(primary), Value::Object(canary)) => primary
+            .keys()
+            .chain(canary.keys().filter(|key| !primary.contains_key(*key)))
+            .find_map(|key| {
+                let pointer = format!("{pointer}/{}", pointer_token(key));
+                match (primary.get(key), canary.get(key)) {
+                    (Some(primary), Some(canary)) => first_difference(primary, canary, &pointer),
+                    _ => Some(pointer),
+                }
+            }),
+        (Value::Array(primary), Value::Array(canary)) => primary
+            .iter()
+            .zip(canary)
+            .enumerate()
+            .find_map(|(index, (primary, canary))| {
+                first_difference(primary, canary, &format!("{pointer}/{index}"))
+            })
+            .or_else(|| {
+                (primary.len() != canary.len())
+                    .then(|| format!("{pointer}/{}", primary.len().min(canary.len())))
+            }),
+        _ => (primary != canary).then(|| pointer.to_string()),
+    }
+}
+
+// JSON is compared by value, so key order doesn't count
+fn difference(primary: &Response, canary: &Response) -> Option<String> {
+    if primary.status != canary.status {
+        return Some(format!("Status {} vs {}", primary.status, canary.status));
+    }
+    match (
+        serde_json::from_slice::<Value>(&primary.body),
+        serde_json::from_slice::<Value>(&canary.body),
+    ) {
+        (Ok(primary), Ok(canary)) => first_difference(&primary, &canary, "").map(|pointer| {
+            if pointer.is_empty() {
+                "The bodies differ".to_string()
+            } else {
+                format!("Differs at {pointer}")
+            }
+        }),
+        _ => (primary.body != canary.body).then(|| "The bodies differ".to_string()),
+    }
+}
+
+async fn compare(app: AppHandle, port: u16, path: String) {
+    let sidecar = app.state::<Sidecar>();
+    // At the same time, so both see the data as close to alike as can be
+    let canary =
+        tauri::async_runtime::spawn(fetch(sidecar.request_on(port, reqwest::Method::GET, &path)));
+    let primary = fetch(sidecar.request(reqwest::Method::GET, &path)).await;
+    let Ok(canary) = canary.await else {
+        return;
+    };
+    let state = app.state::<Canary>();
+    let mut report = state.report.lock().unwrap();
+    // Another run by now
+    if report.port != Some(port) {
+        return;
+    }
+    report.mirrored += 1;
+    let (primary, canary) = match (primary, canary) {
+        (Ok(primary), Ok(canary)) => (primary, canary),
+        (Err(err), _) | (_, Err(err)) => {
+            tracing::debug!("Failed to mirror {path}: {err}");
+            report.failed += 1;
+            return;
+        }
+    };
+    let Some(difference) = difference(&primary, &canary) else {
+        report.matched += 1;
+        return;
+    };
+    tracing::debug!("The canary answered {path} differently: {difference}");
+    report.mismatched += 1;
+    report.mismatches.insert(
+        0,
+        Mismatch {
+            path,
+            at: now(),
+            primary_status: primary.status,
+            canary_status: canary.status,
+            difference,
+        },
+    );
+    report.mismatches.truncate(MAX_MISMATCHES);
+}
+
+/// Starts the backend at `path` next to the running one, on a copy of its
+/// database and with mail sending and syncing left off, then mirrors
+/// `fraction` of the frontend's reads to it. Resolves once it is healthy.
+#[tauri::command]
+pub async fn start_canary(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: PathBuf,
+    sha256: String,
+    fraction: f64,
+) -> CommandResult<CanaryReport> {
+    if app.state::<Sidecar>().is_external() {
+        return Err("The backend is not run by the app in this mode".into());
+    }
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        return Err(CommandError::invalid(
+            "fraction",
+            "Pick a share of reads above 0 and up to 1",
+        ));
+    }
+    if !path.is_absolute() || !path.is_file() {
+        return Err(CommandError::invalid(
+            "path",
+            format!("{} is not a file", path.display()),
+        ));
+    }
+    let path = fs_scope::check(&app, &path, Access::Read)?;
+    integrity::verify_file(&path, &sha256).map_err(|err| CommandError::invalid("sha256", err))?;
+    if app.state::<Canary>().running.lock().unwrap().is_some() {
+        return Err("A canary is already running".into());
+    }
+
+    let dir = data_dir::data_dir(&app)
+        .ok_or("No data directory available")?
+        .join(CANARY_DIR);
+    let handle = app.clone();
+    let copy_to = dir.clone();
+    tauri::async_runtime::spawn_blocking(move || copy_data(&handle, &copy_to))
+        .await
+        .map_err(|err| err.to_string())??;
+
+    let port = sidecar::free_port()?;
+    let (rx, child) = sidecar::canary_command(&app, &path, port, &dir)
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    tracing::info!(
+        "Started the canary backend {} on port {port} (pid {})",
+        path.display(),
+        child.pid()
+    );
+    {
+        let canary = app.state::<Canary>();
+        let mut running = canary.running.lock().unwrap();
+        if running.is_some() {
+            let _ = child.kill();
+            return Err("A canary is already running".into());
+        }
+        *running = Some(Running {
+            binary: path.clone(),
+            port,
+            fraction,
+            child,
+        });
+        *canary.report.lock().unwrap() = CanaryReport {
+            running: true,
+            binary: Some(path),
+            port: Some(port),
+            fraction,
+            started_at: Some(now()),
+            ..CanaryReport::default()
+        };
+    }
+    tauri::async_runtime::spawn(watch(app.clone(), port, rx));
+
+    let sidecar = app.state::<Sidecar>();
+    if let Some(health) = sidecar.health_path() {
+        let request = sidecar.request_on(port, reqwest::Method::GET, health);
+        if !sidecar::poll_health(&app, request).await {
+            stop(&app);
+            return Err("The canary backend did not pass its health check".into());
+        }
+    }
+    emit(&app, true);
+    Ok(get_canary_report(app))
+}
+
+/// Sends a read the frontend just made to the canary as well, if one is
+/// running and this read is among the share it gets. Returns right away.
+#[tauri::command]
+pub fn mirror_read(app: AppHandle, path: String) -> CommandResult<()> {
+    if !path.starts_with('/') || path.starts_with("//") {
+        return Err(CommandError::invalid("path", "Paths start with a single /"));
+    }
+    if SKIPPED_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return Ok(());
+    }
+    let port = match &*app.state::<Canary>().running.lock().unwrap() {
+        Some(running) if sampled(running.fraction) => running.port,
+        _ => return Ok(()),
+    };
+    tauri::async_runtime::spawn(compare(app.clone(), port, path));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_canary_report(app: AppHandle) -> CanaryReport {
+    app.state::<Canary>().report.lock().unwrap().clone()
+}
+
+/// Stops the canary, keeping its report until the next one starts
+#[tauri::command]
+pub async fn stop_canary(_timer: IpcTimer, app: AppHandle) -> CanaryReport {
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || stop(&handle)).await;
+    get_canary_report(app)
+}
+
+/// Stops the canary and swaps its build in for the real backend, which
+/// goes on with the real data. Rolls back like `swap_backend` if the new
+/// build fails its health check there.
+#[tauri::command]
+pub async fn promote_canary(_timer: IpcTimer, app: AppHandle) -> CommandResult<()> {
+    let handle = app.clone();
+    let binary = tauri::async_runtime::spawn_blocking(move || stop(&handle))
+        .await
+        .map_err(|err| err.to_string())?
+        .ok_or("No canary is running")?;
+    tracing::info!("Promoting the canary backend {}", binary.display());
+    backend_swap::swap(&app, binary).await?;
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::fs_scope::{self, Access};
+use crate::logging;
+use crate::metrics::IpcTimer;
+use crate::profile::Profile;
+
+const SCREENSHOT_NAME: &str = "screenshot.png";
+const ABOUT_NAME: &str = "about.txt";
+
+/// Screenshots the calling window's page, and nothing of the windows around
+/// it, to a PNG at `path`. With `include_logs` the file is instead a zip of
+/// the screenshot, the most recent logs and the app version, ready to attach
+/// to a support ticket.
+#[tauri::command]
+pub async fn capture_window(
+    _timer: IpcTimer,
+    app: AppHandle,
+    window: WebviewWindow,
+    path: PathBuf,
+    include_logs: Option<bool>,
+) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err("The capture path must be absolute".to_string());
+    }
+    if !path.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    let path = fs_scope::check(&app, &path, Access::Write).map_err(|err| err.to_string())?;
+
+    let (sender, receiver) = oneshot::channel();
+    window
+        .with_webview(move |webview| platform::capture(webview, sender))
+        .map_err(|err| err.to_string())?;
+    let png = receiver
+        .await
+        .map_err(|_| "The capture was cancelled".to_string())??;
+
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if include_logs.unwrap_or(false) {
+            write_bundle(&app, &target, &png)
+        } else {
+            fs::write(&target, &png)
+        }
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())?;
+    tracing::info!("Captured {} to {}", window.label(), path.display());
+    Ok(())
+}
+
+type Done = oneshot::Sender<Result<Vec<u8>, String>>;
+
+fn write_bundle(app: &AppHandle, path: &Path, png: &[u8]) -> io::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(SCREENSHOT_NAME, options)?;
+    zip.write_all(png)?;
+
+    zip.start_file(ABOUT_NAME, options)?;
+    write!(
+        zip,
+        "{} {}\n{} {}\nprofile {}\n",
+        app.package_info().name,
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        app.state::<Profile>().name
+    )?;
+
+    for log in logging::recent_files(app) {
+        let Some(name) = log.file_name() else {
+            continue;
+        };
+        zip.start_file(format!("logs/{}", name.to_string_lossy()), options)?;
+        io::copy(&mut File::open(&log)?, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+// Pixels left to right, top to bottom, as 8-bit RGBA
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(|err| err.to_string())?;
+    Ok(png)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use gtk::cairo::{Format, ImageSurface};
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+
+    use super::{encode_png, Done};
+
+    pub fn capture(webview: PlatformWebview, done: Done) {
+        webview.inner().snapshot(
+            SnapshotRegion::Visible,
+            SnapshotOptions::NONE,
+            None::<&webkit2gtk::gio::Cancellable>,
+            move |result| {
+                let png = result
+                    .map_err(|err| err.to_string())
+                    .and_then(|surface| {
+                        ImageSurface::try_from(surface)
+                            .map_err(|_| "The snapshot is not an image".to_string())
+                    })
+                    .and_then(|surface| to_png(&surface));
+                let _ = done.send(png);
+            },
+        );
+    }
+
+    // Cairo keeps premultiplied native-endian ARGB rows, with padding at the
+    // end of each
+    fn to_png(surface: &ImageSurface) -> Result<Vec<u8>, String> {
+        if !matches!(surface.format(), Format::ARgb32 | Format::Rgb24) {
+            return Err("Unexpected snapshot format".to_string());
+        }
+        let opaque = surface.format() == Format::Rgb24;
+        let (width, height) = (surface.width() as usize, surface.height() as usize);
+        let stride = surface.stride() as usize;
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        surface
+            .with_data(|data| {
+                for row in data.chunks(stride).take(height) {
+                    for pixel in row[..width * 4].chunks_exact(4) {
+                        let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                        let alpha = if opaque { 255 } else { (argb >> 24) as u8 };
+                        let channel = |shift: u32| {
+                            let value = (argb >> shift) as u8;
+                            match alpha {
+                                0 => 0,
+                                255 => value,
+                                _ => (value as u32 * 255 / alpha as u32) as u8,
+                            }
+                        };
+                        rgba.extend_from_slice(&[channel(16), channel(8), channel(0), alpha]);
+                    }
+                }
+            })
+            .map_err(|err| err.to_string())?;
+        encode_png(width as u32, height as u32, &rgba)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webview2_com::CapturePreviewCompletedHandler;
+    use webview2_com::Microsoft::Web::WebView2::Win32::COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG;
+    use windows::Win32::System::Com::{IStream, STREAM_SEEK_SET};
+    use windows::Win32::UI::Shell::SHCreateMemStream;
+
+    use super::Done;
+
+    const READ_CHUNK: u32 = 64 * 1024;
+
+    // WebView2 writes the PNG into a stream, read back once it's done
+    pub fn capture(webview: PlatformWebview, done: Done) {
+        let Some(stream) = (unsafe { SHCreateMemStream(None) }) else {
+            let _ = done.send(Err("Failed to allocate the capture".to_string()));
+            return;
+        };
+        let done = std::sync::Mutex::new(Some(done));
+        let finish = move |result: Result<Vec<u8>, String>| {
+            if let Some(done) = done.lock().unwrap().take() {
+                let _ = done.send(result);
+            }
+        };
+        let finish = std::sync::Arc::new(finish);
+        let completed = finish.clone();
+        let output = stream.clone();
+        let handler = CapturePreviewCompletedHandler::create(Box::new(move |result| {
+            completed(
+                result
+                    .map_err(|err| err.to_string())
+                    .and_then(|()| read_all(&output)),
+            );
+            Ok(())
+        }));
+
+        let result = unsafe {
+            webview.controller().CoreWebView2().and_then(|core| {
+                core.CapturePreview(
+                    COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG,
+                    &stream,
+                    &handler,
+                )
+            })
+        };
+        if let Err(err) = result {
+            finish(Err(err.to_string()));
+        }
+    }
+
+    fn read_all(stream: &IStream) -> Result<Vec<u8>, String> {
+        let mut png = Vec::new();
+        let mut chunk = vec![0u8; READ_CHUNK as usize];
+        unsafe {
+            stream
+                .Seek(0, STREAM_SEEK_SET, None)
+                .map_err(|err| err.to_string())?;
+            loop {
+                let mut read = 0;
+                stream
+                    .Read(chunk.as_mut_ptr().cast(), READ_CHUNK, Some(&mut read))
+                    .ok()
+                    .map_err(|err| err.to_string())?;
+                if read == 0 {
+                    break;
+                }
+                png.extend_from_slice(&chunk[..read as usize]);
+            }
+        }
+        Ok(png)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    use objc2::msg_send;
+    use objc2::runtime::AnyObject;
+    use tauri::webview::PlatformWebview;
+
+    use super::Done;
+
+    // `screencapture` grabs just the one window by its number. The first
+    // capture asks for the Screen Recording permission.
+    pub fn capture(webview: PlatformWebview, done: Done) {
+        let number: isize = unsafe {
+            let window = &*(webview.ns_window() as *const AnyObject);
+            msg_send![window, windowNumber]
+        };
+        std::thread::spawn(move || {
+            let path = std::env::temp_dir().join(format!("hamba-capture-{number}.png"));
+            let result = Command::new("screencapture")
+                .args(["-o", "-x", &format!("-l{number}")])
+                .arg(&path)
+                .status()
+                .map_err(|err| err.to_string())
+                .and_then(|status| {
+                    if status.success() {
+                        std::fs::read(&path).map_err(|err| err.to_string())
+                    } else {
+                        Err(format!("screencapture exited with {status}"))
+                    }
+                });
+            let _ = std::fs::remove_file(&path);
+            let _ = done.send(result);
+        });
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use tauri::webview::PlatformWebview;
+
+    use super::Done;
+
+    pub fn capture(_webview: PlatformWebview, done: Done) {
+        let _ = done.send(Err("Capturing is not supported here".to_string()));
+    }
+}
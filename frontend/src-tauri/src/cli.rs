@@ -0,0 +1,123 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Command line flags. Shell-level ones are applied here; `--open` and any
+/// other arguments are handed to the frontend, `--backend-env` to the backend.
+#[derive(Parser, Debug, Default)]
+#[command(name = "hamba", version, about)]
+pub struct Cli {
+    /// Item to open once the window is ready, e.g. `item:123`
+    #[arg(long, value_name = "ITEM")]
+    pub open: Option<String>,
+    /// Start hidden in the tray
+    #[arg(long)]
+    pub minimized: bool,
+    /// Run only the backend, without any window, until interrupted
+    #[arg(long)]
+    pub headless: bool,
+    /// Backend profile from profiles.toml, e.g. `staging`
+    #[arg(long, env = "HAMBA_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Start with default settings and without autostart, hamba.env, helper
+    /// sidecars or scheduled jobs, and clear the webview caches before the
+    /// window loads. Holding Shift at launch does the same.
+    #[arg(long)]
+    pub safe_mode: bool,
+    /// Write how long each startup stage took to startup-trace.json in the
+    /// log folder
+    #[arg(long)]
+    pub profile_startup: bool,
+    /// Keep all app data in this folder instead of the OS locations
+    #[arg(long, value_name = "PATH")]
+    pub data_dir: Option<PathBuf>,
+    /// Environment variable to set for the backend, can be repeated
+    #[arg(long = "backend-env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    pub backend_env: Vec<(String, String)>,
+    /// Passed on to the frontend as is (including `hamba://` links)
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
+}
+
+/// What the frontend gets from a launch, as `cli-args` or `take_cli_args`
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliArgs {
+    open: Option<String>,
+    args: Vec<String>,
+}
+
+/// The first launch's arguments until the frontend asks for them
+#[derive(Default)]
+pub struct PendingCliArgs(Mutex<Option<CliArgs>>);
+
+impl Cli {
+    fn frontend_args(&self) -> Option<CliArgs> {
+        if self.open.is_none() && self.args.is_empty() {
+            return None;
+        }
+        Some(CliArgs {
+            open: self.open.clone(),
+            args: self.args.clone(),
+        })
+    }
+}
+
+/// Parses the arguments of this or another launch. `--help` and `--version`
+/// exit; anything else that doesn't parse (e.g. the `-psn_` argument macOS
+/// sometimes adds) is skipped rather than keeping the app from starting.
+pub fn parse<I, T>(args: I) -> Cli
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    match Cli::try_parse_from(&args) {
+        Ok(cli) => cli,
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion
+            ) =>
+        {
+            err.exit()
+        }
+        Err(err) => {
+            eprintln!("Ignoring invalid arguments: {err}");
+            Cli::command()
+                .ignore_errors(true)
+                .try_get_matches_from(&args)
+                .and_then(|matches| Cli::from_arg_matches(&matches))
+                .unwrap_or_default()
+        }
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let pending = app.state::<Cli>().frontend_args();
+    app.manage(PendingCliArgs(Mutex::new(pending)));
+}
+
+/// Forwards the arguments of a second launch to the running instance
+pub fn forward(app: &AppHandle, args: Vec<String>) {
+    if let Some(args) = parse(args).frontend_args() {
+        let _ = app.emit("cli-args", args);
+    }
+}
+
+fn parse_env(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {value:?}")),
+    }
+}
+
+#[tauri::command]
+pub fn take_cli_args(pending: State<'_, PendingCliArgs>) -> Option<CliArgs> {
+    pending.0.lock().unwrap().take()
+}
@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+use arboard::ImageData;
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::{AppHandle, Manager, State};
+
+/// The webview only reaches plain text; these cover HTML and images. The
+/// handle is opened on first use and kept, since on X11 copied data is only
+/// served for as long as its owner lives.
+#[derive(Default)]
+pub struct Clipboard(Mutex<Option<arboard::Clipboard>>);
+
+impl Clipboard {
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, String> {
+        let mut clipboard = self.0.lock().unwrap();
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new().map_err(|err| err.to_string())?);
+        }
+        f(clipboard.as_mut().unwrap()).map_err(|err| err.to_string())
+    }
+
+    pub fn write_text(&self, text: String) -> Result<(), String> {
+        self.with(|clipboard| clipboard.set_text(text))
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Clipboard::default());
+}
+
+/// Copies `html` along with a plain-text version for apps that don't take HTML
+#[tauri::command]
+pub fn write_clipboard_html(
+    clipboard: State<'_, Clipboard>,
+    html: String,
+    text: String,
+) -> Result<(), String> {
+    clipboard.with(|clipboard| clipboard.set_html(html, Some(text)))
+}
+
+/// Copies a PNG image, sent as the raw request body
+#[tauri::command]
+pub fn write_clipboard_image(
+    clipboard: State<'_, Clipboard>,
+    request: Request<'_>,
+) -> Result<(), String> {
+    let InvokeBody::Raw(png) = request.body() else {
+        return Err("Expected PNG bytes".to_string());
+    };
+    let image = decode(png)?;
+    clipboard.with(|clipboard| clipboard.set_image(image))
+}
+
+/// The clipboard's image as PNG bytes, empty when it holds no image
+#[tauri::command]
+pub fn read_clipboard_image(clipboard: State<'_, Clipboard>) -> Result<Response, String> {
+    let image = clipboard.with(|clipboard| match clipboard.get_image() {
+        Ok(image) => Ok(Some(image)),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(err) => Err(err),
+    })?;
+    let png = match image {
+        Some(image) => encode(&image)?,
+        None => Vec::new(),
+    };
+    Ok(Response::new(png))
+}
+
+fn decode(png: &[u8]) -> Result<ImageData<'static>, String> {
+    let mut decoder = png::Decoder::new(png);
+    // Palette and low bit depths become 8-bit, so 8-bit RGBA is all that
+    // needs handling after the match below
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 0xff])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer
+            .iter()
+            .flat_map(|&value| [value, value, value, 0xff])
+            .collect(),
+        png::ColorType::Indexed => return Err("Unsupported PNG colour type".to_string()),
+    };
+    Ok(ImageData {
+        width: info.width as usize,
+        height: info.height as usize,
+        bytes: Cow::Owned(rgba),
+    })
+}
+
+fn encode(image: &ImageData) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer
+        .write_image_data(&image.bytes)
+        .map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(png)
+}
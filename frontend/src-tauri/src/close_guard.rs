@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Window, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
+
+use crate::kiosk;
+use crate::main_window;
+use crate::settings::SettingsStore;
+use crate::sidecar;
+
+// A frontend that hasn't answered by then is assumed to have nothing to lose;
+// a hung page should never make the app impossible to quit.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Questions asked of the frontend that are still waiting for an answer
+#[derive(Default)]
+pub struct CloseGuard {
+    next_id: AtomicU32,
+    pending: Mutex<HashMap<u32, oneshot::Sender<bool>>>,
+    // Set while a quit is being confirmed, so repeated clicks don't stack up
+    quitting: AtomicBool,
+}
+
+#[derive(Clone, Serialize)]
+struct CloseRequested {
+    id: u32,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(CloseGuard::default());
+}
+
+/// Checks for unsaved work before the main window closes the app. With
+/// close-to-tray on the window only hides, so there's nothing to check.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    // Kiosk mode keeps the window open
+    if window.label() != main_window::LABEL || kiosk::is_active(window.app_handle()) {
+        return;
+    }
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.state::<SettingsStore>().get().close_to_tray {
+            return;
+        }
+        api.prevent_close();
+        quit(window.app_handle());
+    }
+}
+
+/// Asks the frontend about unsaved changes, confirms with the user if there
+/// are any, then shuts the backend down and exits.
+pub fn quit(app: &AppHandle) {
+    if app
+        .state::<CloseGuard>()
+        .quitting
+        .swap(true, Ordering::SeqCst)
+    {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if has_unsaved_changes(&app).await && !confirm_discard(&app).await {
+            app.state::<CloseGuard>()
+                .quitting
+                .store(false, Ordering::SeqCst);
+            return;
+        }
+        let handle = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle)).await;
+        app.exit(0);
+    });
+}
+
+async fn has_unsaved_changes(app: &AppHandle) -> bool {
+    let guard = app.state::<CloseGuard>();
+    let id = guard.next_id.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = oneshot::channel();
+    guard.pending.lock().unwrap().insert(id, sender);
+
+    if app.emit("close-requested", CloseRequested { id }).is_err() {
+        guard.pending.lock().unwrap().remove(&id);
+        return false;
+    }
+    match tokio::time::timeout(ANSWER_TIMEOUT, receiver).await {
+        Ok(Ok(unsaved)) => unsaved,
+        _ => {
+            tracing::debug!("Frontend did not report unsaved changes in time");
+            guard.pending.lock().unwrap().remove(&id);
+            false
+        }
+    }
+}
+
+async fn confirm_discard(app: &AppHandle) -> bool {
+    main_window::focus(app);
+    let (sender, receiver) = oneshot::channel();
+    app.dialog()
+        .message("You have unsaved changes. Quit anyway and lose them?")
+        .title("Unsaved changes")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Quit".to_string(),
+            "Cancel".to_string(),
+        ))
+        .show(move |confirmed| {
+            let _ = sender.send(confirmed);
+        });
+    receiver.await.unwrap_or(false)
+}
+
+/// The frontend's answer to a `close-requested` event
+#[tauri::command]
+pub fn report_unsaved_changes(guard: State<'_, CloseGuard>, id: u32, unsaved: bool) {
+    if let Some(sender) = guard.pending.lock().unwrap().remove(&id) {
+        let _ = sender.send(unsaved);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{CommandArg, CommandItem, InvokeError};
+use tauri::Runtime;
+
+/// What went wrong, so the frontend can tell failures apart without parsing
+/// the message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// An argument didn't fit the command's schema or failed its checks
+    InvalidInput,
+    /// The calling window lacks the command's scope
+    PermissionDenied,
+    RateLimited,
+    /// The app is locked, see `lock`
+    Locked,
+    NotFound,
+    /// Anything else; the message is the underlying error
+    Failed,
+}
+
+/// What typed commands reject with, as `{ kind, message, field }`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    /// Meant to be shown to the user as is
+    pub message: String,
+    /// The argument, or the key within it, that was invalid
+    pub field: Option<String>,
+}
+
+pub type CommandResult<T> = Result<T, CommandError>;
+
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            field: None,
+        }
+    }
+
+    pub fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: Some(field.into()),
+            ..Self::new(ErrorKind::InvalidInput, message)
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    // Keeps a more specific field set by the check that failed
+    fn in_field(mut self, field: &str) -> Self {
+        self.field.get_or_insert_with(|| field.to_string());
+        self
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// Most of the app's helpers fail with a plain message
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Failed, message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorKind::Failed, message)
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        let kind = match err.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Failed,
+        };
+        Self::new(kind, err.to_string())
+    }
+}
+
+impl From<tauri::Error> for CommandError {
+    fn from(err: tauri::Error) -> Self {
+        Self::new(ErrorKind::Failed, err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::new(ErrorKind::Failed, err.to_string())
+    }
+}
+
+/// Checks an argument beyond what its type already guarantees
+pub trait Validate {
+    fn validate(&self) -> CommandResult<()>;
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self) -> CommandResult<()> {
+        self.as_ref().map_or(Ok(()), Validate::validate)
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> CommandResult<()> {
+        self.iter().try_for_each(Validate::validate)
+    }
+}
+
+/// A command argument that is deserialized and then validated before the
+/// command runs. Either failing rejects the call with `InvalidInput` naming
+/// the argument, instead of Tauri's plain message.
+pub struct Valid<T>(pub T);
+
+impl<'de, T, R> CommandArg<'de, R> for Valid<T>
+where
+    T: Deserialize<'de> + Validate,
+    R: Runtime,
+{
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        let key = command.key;
+        let value =
+            T::deserialize(command).map_err(|err| CommandError::invalid(key, err.to_string()))?;
+        value.validate().map_err(|err| err.in_field(key))?;
+        Ok(Valid(value))
+    }
+}
+
+/// Returns an `InvalidInput` error for `field` from the enclosing function
+/// unless `condition` holds, e.g.
+/// `ensure!(!name.is_empty(), "name", "A name is required")`
+macro_rules! ensure {
+    ($condition:expr, $field:expr, $($message:tt)+) => {
+        if !$condition {
+            let message = format!($($message)+);
+            return Err($crate::command::CommandError::invalid($field, message));
+        }
+    };
+}
+
+pub(crate) use ensure;
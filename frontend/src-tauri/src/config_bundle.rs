@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::command::{CommandError, CommandResult};
+use crate::fs_scope::{self, Access};
+use crate::profile::{self, Profile};
+use crate::settings::{self, SettingsStore};
+use crate::window_state;
+
+// Tells a bundle apart from other JSON files
+const FORMAT: &str = "hamba-config";
+// Bump when a field changes meaning; newer bundles are refused
+const VERSION: u32 = 1;
+
+/// Everything that makes the app feel like the user's own, for moving it to
+/// another machine. It holds no secrets: keychain entries, the app lock and
+/// the database key stay behind, as do settings only their own commands
+/// change, and credentials in the proxy URL are left out.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    format: String,
+    version: u32,
+    app_version: String,
+    /// Seconds since the Unix epoch
+    exported_at: u64,
+    /// The profile it was exported from; its settings are what's in here
+    profile: String,
+    settings: Map<String, Value>,
+    window_state: Value,
+    /// As the frontend keeps them, passed through untouched
+    keybindings: Option<Value>,
+    /// Names of every profile, created empty on import where missing
+    profiles: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImport {
+    /// For the frontend to store again, as it's the one that keeps them
+    keybindings: Option<Value>,
+    profiles_created: Vec<String>,
+    /// Settings this version doesn't know, left out
+    skipped_settings: Vec<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Drops `user:password@` from a proxy URL
+fn without_credentials(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Writes the settings, window positions, `keybindings` and profile names
+/// to `path`
+#[tauri::command]
+pub fn export_config_bundle(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    path: PathBuf,
+    keybindings: Option<Value>,
+) -> CommandResult<()> {
+    let path = fs_scope::check(&app, &path, Access::Write)?;
+    let Value::Object(mut fields) = serde_json::to_value(settings.get())? else {
+        return Err("Settings are not an object".into());
+    };
+    fields.retain(|key, _| !settings::is_read_only(key));
+    if let Some(Value::String(url)) = fields.get_mut("proxyUrl") {
+        *url = without_credentials(url);
+    }
+
+    let bundle = Bundle {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        app_version: app.package_info().version.to_string(),
+        exported_at: now(),
+        profile: app.state::<Profile>().name.clone(),
+        settings: fields,
+        window_state: window_state::export(&app),
+        keybindings,
+        profiles: profile::all(&app),
+    };
+    fs::write(&path, serde_json::to_string_pretty(&bundle)?)?;
+    tracing::info!("Exported the configuration to {}", path.display());
+    Ok(())
+}
+
+/// Applies a bundle from `export_config_bundle` to the current profile:
+/// its settings as `update_settings` would, its window positions from the
+/// next time each window opens, and any profiles missing here
+#[tauri::command]
+pub fn import_config_bundle(
+    app: AppHandle,
+    window: WebviewWindow,
+    settings: State<'_, SettingsStore>,
+    path: PathBuf,
+) -> CommandResult<ConfigImport> {
+    let path = fs_scope::check(&app, &path, Access::Read)?;
+    let text = fs::read_to_string(&path)
+        .map_err(|err| CommandError::invalid("path", format!("{}: {err}", path.display())))?;
+    let bundle: Bundle = serde_json::from_str(&text)
+        .ok()
+        .filter(|bundle: &Bundle| bundle.format == FORMAT)
+        .ok_or_else(|| CommandError::invalid("path", "Not a Hamba configuration file"))?;
+    if bundle.version > VERSION {
+        return Err(CommandError::invalid(
+            "path",
+            format!(
+                "The file is from a newer Hamba ({}), update to import it",
+                bundle.app_version
+            ),
+        ));
+    }
+
+    let known = serde_json::to_value(settings.get())?;
+    let mut patch = Map::new();
+    let mut skipped_settings = Vec::new();
+    for (key, value) in bundle.settings {
+        if settings::is_read_only(&key) {
+            continue;
+        }
+        if known.get(&key).is_some() {
+            patch.insert(key, value);
+        } else {
+            skipped_settings.push(key);
+        }
+    }
+    settings::update_settings(app.clone(), window, settings, Value::Object(patch))?;
+
+    if let Err(err) = window_state::import(&app, bundle.window_state) {
+        tracing::warn!("Failed to import the window positions: {err}");
+    }
+
+    let mut profiles_created = Vec::new();
+    for name in bundle.profiles {
+        if profile::exists(&app, &name) {
+            continue;
+        }
+        match profile::create_profile(app.clone(), name.clone()) {
+            Ok(()) => profiles_created.push(name),
+            Err(err) => tracing::warn!("Failed to create profile {name:?}: {err}"),
+        }
+    }
+
+    tracing::info!(
+        "Imported the configuration exported from {} on {}",
+        bundle.profile,
+        bundle.app_version
+    );
+    Ok(ConfigImport {
+        keybindings: bundle.keybindings,
+        profiles_created,
+        skipped_settings,
+    })
+}
@@ -0,0 +1,92 @@
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::TcpStream;
+
+use crate::sidecar::Sidecar;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+// Public DNS resolvers on ports that are rarely blocked. Any one answering
+// counts as being online; addresses skip DNS so a broken resolver alone
+// doesn't look like an outage.
+const PROBES: &[&str] = &["1.1.1.1:443", "8.8.8.8:443", "9.9.9.9:443"];
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connectivity {
+    /// The internet is reachable
+    online: bool,
+    /// The backend answers `/health`
+    backend: bool,
+}
+
+#[derive(Default)]
+pub struct ConnectivityState(Mutex<Connectivity>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(ConnectivityState::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let current = Connectivity {
+                online: internet_reachable().await,
+                backend: backend_alive(&app).await,
+            };
+
+            let changed = {
+                let state = app.state::<ConnectivityState>();
+                let mut last = state.0.lock().unwrap();
+                let changed = *last != current;
+                *last = current;
+                changed
+            };
+            if changed {
+                tracing::info!(
+                    "Connectivity changed: online={}, backend={}",
+                    current.online,
+                    current.backend
+                );
+                let _ = app.emit("connectivity-changed", current);
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+async fn internet_reachable() -> bool {
+    for probe in PROBES {
+        let Ok(addr) = probe.parse::<SocketAddr>() else {
+            continue;
+        };
+        if let Ok(Ok(_)) = tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+            return true;
+        }
+    }
+    false
+}
+
+async fn backend_alive(app: &AppHandle) -> bool {
+    app.state::<Sidecar>()
+        .request(reqwest::Method::GET, "/health")
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+/// Whether the last check reached the internet
+pub fn is_online(app: &AppHandle) -> bool {
+    app.state::<ConnectivityState>().0.lock().unwrap().online
+}
+
+/// The result of the last check; both are `false` until the first one finishes
+#[tauri::command]
+pub fn get_connectivity(state: State<'_, ConnectivityState>) -> Connectivity {
+    *state.0.lock().unwrap()
+}
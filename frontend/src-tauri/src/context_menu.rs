@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tauri::menu::{
+    CheckMenuItem, IsMenuItem, Menu, MenuItem, MenuItemKind, PredefinedMenuItem, Submenu,
+};
+use tauri::{AppHandle, Manager, State, Window, Wry};
+use tokio::sync::oneshot;
+
+use crate::metrics::IpcTimer;
+
+// Ids of context menu items are `context-menu:<menu>:<item>`, so
+// `menu::on_menu_event` can tell them from the menu bar's and a late click
+// on an old menu can't answer a newer one
+pub const ID_PREFIX: &str = "context-menu:";
+// Windows and macOS show the menu modally and report the click right after
+// it closes. GTK returns at once and never says the menu was dismissed, so
+// there it waits until a click, the next menu or this long.
+#[cfg(any(windows, target_os = "macos"))]
+const DISMISS_WAIT: Duration = Duration::from_millis(250);
+#[cfg(not(any(windows, target_os = "macos")))]
+const DISMISS_WAIT: Duration = Duration::from_secs(120);
+
+/// One entry of a context menu, as sent by the frontend
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Entry {
+    Item {
+        id: String,
+        label: String,
+        #[serde(default = "enabled")]
+        enabled: bool,
+        /// Only shown as a hint; pressing it does nothing while the menu is closed
+        accelerator: Option<String>,
+    },
+    Checkbox {
+        id: String,
+        label: String,
+        checked: bool,
+        #[serde(default = "enabled")]
+        enabled: bool,
+    },
+    Separator,
+    Submenu {
+        label: String,
+        items: Vec<Entry>,
+        #[serde(default = "enabled")]
+        enabled: bool,
+    },
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// The menu that is open, if any, waiting for its answer
+#[derive(Default)]
+pub struct ContextMenus {
+    next: AtomicU64,
+    open: Mutex<Option<(u64, oneshot::Sender<Option<String>>)>>,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(ContextMenus::default());
+}
+
+fn build(app: &AppHandle, menu: u64, entries: &[Entry]) -> tauri::Result<Vec<MenuItemKind<Wry>>> {
+    let id = |item: &str| format!("{ID_PREFIX}{menu}:{item}");
+    entries
+        .iter()
+        .map(|entry| {
+            Ok(match entry {
+                Entry::Item {
+                    id: item,
+                    label,
+                    enabled,
+                    accelerator,
+                } => MenuItem::with_id(app, id(item), label, *enabled, accelerator.as_deref())?
+                    .kind(),
+                Entry::Checkbox {
+                    id: item,
+                    label,
+                    checked,
+                    enabled,
+                } => {
+                    CheckMenuItem::with_id(app, id(item), label, *enabled, *checked, None::<&str>)?
+                        .kind()
+                }
+                Entry::Separator => PredefinedMenuItem::separator(app)?.kind(),
+                Entry::Submenu {
+                    label,
+                    items,
+                    enabled,
+                } => {
+                    let submenu = Submenu::new(app, label, *enabled)?;
+                    for item in build(app, menu, items)? {
+                        submenu.append(&item)?;
+                    }
+                    submenu.kind()
+                }
+            })
+        })
+        .collect()
+}
+
+/// Answers the open menu with the clicked item. Called by
+/// `menu::on_menu_event` for ids starting with `ID_PREFIX`.
+pub fn on_menu_event(app: &AppHandle, id: &str) {
+    let Some((menu, item)) = id
+        .strip_prefix(ID_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+    else {
+        return;
+    };
+    let menus = app.state::<ContextMenus>();
+    let mut open = menus.open.lock().unwrap();
+    if open
+        .as_ref()
+        .is_some_and(|(current, _)| current.to_string() == menu)
+    {
+        if let Some((_, answer)) = open.take() {
+            let _ = answer.send(Some(item.to_string()));
+        }
+    }
+}
+
+/// Shows a native menu built from `items` at the cursor. Resolves with the
+/// id of the clicked item, or `null` when the menu was dismissed.
+#[tauri::command]
+pub async fn show_context_menu(
+    _timer: IpcTimer,
+    app: AppHandle,
+    window: Window,
+    menus: State<'_, ContextMenus>,
+    items: Vec<Entry>,
+) -> Result<Option<String>, String> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+    let id = menus.next.fetch_add(1, Ordering::Relaxed);
+    let menu = Menu::new(&app).map_err(|err| err.to_string())?;
+    for item in build(&app, id, &items).map_err(|err| err.to_string())? {
+        menu.append(&item).map_err(|err| err.to_string())?;
+    }
+
+    // Opening a menu closes the previous one for good
+    let (answer, answered) = oneshot::channel();
+    if let Some((_, previous)) = menus.open.lock().unwrap().replace((id, answer)) {
+        let _ = previous.send(None);
+    }
+
+    // Blocks until the menu closes on Windows and macOS. `menu` is kept
+    // until the answer is in, as GTK is still showing it after this returns.
+    let shown = menu.clone();
+    tauri::async_runtime::spawn_blocking(move || window.popup_menu(&shown))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+
+    let clicked = tokio::time::timeout(DISMISS_WAIT, answered)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .flatten();
+    let mut open = menus.open.lock().unwrap();
+    if open.as_ref().is_some_and(|(current, _)| *current == id) {
+        *open = None;
+    }
+    drop(menu);
+    Ok(clicked)
+}
@@ -0,0 +1,255 @@
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+use crate::proxy;
+use crate::settings::SettingsStore;
+
+const REPORTS_DIR: &str = "crash-reports";
+// Oldest reports are deleted once there are more than this many on disk
+const MAX_REPORTS: usize = 20;
+const SUBMIT_URL: &str = "https://hamba.app/api/crash-reports";
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CrashKind {
+    /// A panic in the Tauri shell itself
+    Shell,
+    /// The backend sidecar exiting with a non-zero code
+    Backend,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    id: String,
+    kind: CrashKind,
+    /// Milliseconds since the Unix epoch
+    created_at: u64,
+    app_version: String,
+    os: String,
+    arch: String,
+    message: String,
+    /// Backtrace for shell panics, the last lines of output for the backend
+    details: Vec<String>,
+    exit_code: Option<i32>,
+    submitted: bool,
+}
+
+impl CrashReport {
+    fn new(app_version: &str, kind: CrashKind, message: String, details: Vec<String>) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut suffix = [0u8; 4];
+        let _ = getrandom::fill(&mut suffix);
+
+        Self {
+            id: format!("{created_at}-{}", hex::encode(suffix)),
+            kind,
+            created_at,
+            app_version: app_version.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            message,
+            details,
+            exit_code: None,
+            submitted: false,
+        }
+    }
+}
+
+pub struct CrashReports {
+    dir: Option<PathBuf>,
+    app_version: String,
+}
+
+impl CrashReports {
+    fn write(&self, report: &CrashReport) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        write_report(dir, report)?;
+        prune(dir);
+        Ok(())
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or("No crash report directory available")?;
+        // Ids come from the webview, so don't let them point outside the dir
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid crash report id {id:?}"));
+        }
+        Ok(dir.join(format!("{id}.json")))
+    }
+
+    fn read(&self, id: &str) -> Result<CrashReport, String> {
+        let contents = fs::read_to_string(self.path(id)?).map_err(|err| err.to_string())?;
+        serde_json::from_str(&contents).map_err(|err| err.to_string())
+    }
+}
+
+/// Installs a panic hook that writes a report to the app data dir before the
+/// default hook runs. Nothing leaves the machine until `submit_crash_report`.
+pub fn init(app: &AppHandle) {
+    let reports = CrashReports {
+        dir: data_dir::data_dir(app).map(|dir| dir.join(REPORTS_DIR)),
+        app_version: app.package_info().version.to_string(),
+    };
+
+    let dir = reports.dir.clone();
+    let app_version = reports.app_version.clone();
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(dir) = &dir {
+            let message = match info.location() {
+                Some(location) => format!("{} at {location}", payload_message(info.payload())),
+                None => payload_message(info.payload()),
+            };
+            let backtrace = Backtrace::force_capture()
+                .to_string()
+                .lines()
+                .map(String::from)
+                .collect();
+            let report = CrashReport::new(&app_version, CrashKind::Shell, message, backtrace);
+            // Logging could itself be what panicked, so write straight to stderr
+            if let Err(err) = write_report(dir, &report) {
+                eprintln!("Failed to write crash report: {err}");
+            }
+        }
+        previous(info);
+    }));
+
+    app.manage(reports);
+}
+
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// Records a backend crash along with its last lines of output, and emits
+/// `crash-report` so the frontend can offer to send it.
+pub fn record_backend_exit(app: &AppHandle, code: Option<i32>, lines: Vec<String>) {
+    let reports = app.state::<CrashReports>();
+    let message = match code {
+        Some(code) => format!("Backend exited with code {code}"),
+        None => "Backend was terminated by a signal".to_string(),
+    };
+    let mut report = CrashReport::new(&reports.app_version, CrashKind::Backend, message, lines);
+    report.exit_code = code;
+
+    if let Err(err) = reports.write(&report) {
+        tracing::warn!("Failed to write crash report: {err}");
+        return;
+    }
+    let _ = app.emit("crash-report", &report);
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(
+        dir.join(format!("{}.json", report.id)),
+        serde_json::to_string_pretty(report)?,
+    )
+}
+
+fn prune(dir: &Path) {
+    let mut paths = report_paths(dir);
+    if paths.len() <= MAX_REPORTS {
+        return;
+    }
+    // Ids start with the creation time, so names sort oldest first
+    paths.sort();
+    for path in &paths[..paths.len() - MAX_REPORTS] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn report_paths(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// All reports on disk, newest first
+#[tauri::command]
+pub fn list_crash_reports(reports: State<'_, CrashReports>) -> Vec<CrashReport> {
+    let Some(dir) = &reports.dir else {
+        return Vec::new();
+    };
+    let mut list: Vec<CrashReport> = report_paths(dir)
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|contents| serde_json::from_str(&contents).ok())
+        .collect();
+    list.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+    list
+}
+
+#[tauri::command]
+pub fn delete_crash_report(reports: State<'_, CrashReports>, id: String) -> Result<(), String> {
+    match fs::remove_file(reports.path(&id)?) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn set_crash_reporting(
+    settings: State<'_, SettingsStore>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings
+        .update(|settings| settings.crash_reporting = enabled)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Sends a report, refusing unless the user has opted in to crash reporting
+#[tauri::command]
+pub async fn submit_crash_report(
+    _timer: IpcTimer,
+    app: AppHandle,
+    reports: State<'_, CrashReports>,
+    settings: State<'_, SettingsStore>,
+    id: String,
+) -> Result<(), String> {
+    if !settings.get().crash_reporting {
+        return Err("Crash reporting is turned off".to_string());
+    }
+
+    let mut report = reports.read(&id)?;
+    proxy::client(&proxy::resolve(&app))?
+        .post(SUBMIT_URL)
+        .json(&report)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+
+    report.submitted = true;
+    reports.write(&report).map_err(|err| err.to_string())
+}
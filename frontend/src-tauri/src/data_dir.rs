@@ -0,0 +1,190 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::cli::Cli;
+use crate::profile::Profile;
+
+const DATA_DIR_FLAG: &str = "--data-dir";
+// An empty marker keeps data in `data/` next to the executable, otherwise
+// its first line is the folder to use (relative to the executable's folder).
+const PORTABLE_MARKER: &str = "portable.txt";
+const PORTABLE_DEFAULT_DIR: &str = "data";
+// Profiles other than the default keep everything under here
+const PROFILES_DIR: &str = "profiles";
+
+/// Where the app keeps its files. With a custom root (from `--data-dir` or
+/// portable mode) everything lives under it instead of the OS locations.
+pub struct DataDir {
+    root: Option<PathBuf>,
+    portable: bool,
+    // `profiles/<name>` for profiles other than the default, appended to
+    // whichever folders are used
+    profile: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataDirInfo {
+    data_dir: Option<PathBuf>,
+    custom: bool,
+    portable: bool,
+    profile: String,
+}
+
+/// Must run before anything else touches the filesystem
+pub fn init(app: &AppHandle) {
+    let (root, portable) = custom_root(app);
+
+    let profile = app.state::<Profile>();
+    let profile = (!profile.is_default()).then(|| Path::new(PROFILES_DIR).join(&profile.name));
+    let state = DataDir {
+        root,
+        portable,
+        profile,
+    };
+
+    if let Some(root) = state
+        .root
+        .as_ref()
+        .map(|root| state.in_profile(root.clone()))
+    {
+        if let Err(err) = fs::create_dir_all(&root) {
+            eprintln!("Failed to create data directory {}: {err}", root.display());
+        }
+    }
+    app.manage(state);
+}
+
+// The folder from `--data-dir` or portable mode, and whether it is the latter
+fn custom_root(app: &AppHandle) -> (Option<PathBuf>, bool) {
+    let flag_dir = app.state::<Cli>().data_dir.as_deref().map(absolute);
+    match flag_dir {
+        Some(dir) => (Some(dir), false),
+        None => match portable_dir() {
+            Some(dir) => (Some(dir), true),
+            None => (None, false),
+        },
+    }
+}
+
+/// The data folder of the default profile, which also holds the others
+/// under `profiles/`. Available before `init`, for `profile::init`.
+pub fn shared_data_dir(app: &AppHandle) -> Option<PathBuf> {
+    match custom_root(app).0 {
+        Some(root) => Some(root),
+        None => app.path().app_data_dir().ok(),
+    }
+}
+
+/// Where profiles other than the default keep their data
+pub fn profiles_dir(app: &AppHandle) -> Option<PathBuf> {
+    shared_data_dir(app).map(|dir| dir.join(PROFILES_DIR))
+}
+
+/// Every folder profile `name` keeps files in, for deleting it
+pub fn profile_dirs(app: &AppHandle, name: &str) -> Vec<PathBuf> {
+    let profile = Path::new(PROFILES_DIR).join(name);
+    let roots = match &app.state::<DataDir>().root {
+        Some(root) => vec![root.clone()],
+        None => [
+            app.path().app_data_dir(),
+            app.path().app_config_dir(),
+            app.path().app_log_dir(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    };
+    let mut dirs: Vec<PathBuf> = roots.into_iter().map(|root| root.join(&profile)).collect();
+    dirs.dedup();
+    dirs
+}
+
+impl DataDir {
+    fn in_profile(&self, dir: PathBuf) -> PathBuf {
+        match &self.profile {
+            Some(profile) => dir.join(profile),
+            None => dir,
+        }
+    }
+}
+
+pub fn config_dir(app: &AppHandle) -> Option<PathBuf> {
+    let state = app.state::<DataDir>();
+    let dir = match &state.root {
+        Some(root) => root.clone(),
+        None => app.path().app_config_dir().ok()?,
+    };
+    Some(state.in_profile(dir))
+}
+
+pub fn data_dir(app: &AppHandle) -> Option<PathBuf> {
+    let state = app.state::<DataDir>();
+    let dir = match &state.root {
+        Some(root) => root.clone(),
+        None => app.path().app_data_dir().ok()?,
+    };
+    Some(state.in_profile(dir))
+}
+
+pub fn log_dir(app: &AppHandle) -> Option<PathBuf> {
+    let state = app.state::<DataDir>();
+    match &state.root {
+        Some(root) => Some(state.in_profile(root.clone()).join("logs")),
+        None => app
+            .path()
+            .app_log_dir()
+            .ok()
+            .map(|dir| state.in_profile(dir)),
+    }
+}
+
+/// Arguments that make another launch (e.g. at login) use the same folder.
+/// Portable mode needs none since the marker is found again on its own.
+pub fn launch_args(app: &AppHandle) -> Vec<String> {
+    let state = app.state::<DataDir>();
+    match &state.root {
+        Some(root) if !state.portable => {
+            vec![
+                DATA_DIR_FLAG.to_string(),
+                root.to_string_lossy().into_owned(),
+            ]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[tauri::command]
+pub fn get_data_dir(app: AppHandle, state: State<'_, DataDir>) -> DataDirInfo {
+    DataDirInfo {
+        data_dir: data_dir(&app),
+        custom: state.root.is_some(),
+        portable: state.portable,
+        profile: app.state::<Profile>().name.clone(),
+    }
+}
+
+fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = env::current_exe().ok()?.parent()?.to_path_buf();
+    let marker = fs::read_to_string(exe_dir.join(PORTABLE_MARKER)).ok()?;
+    let dir = marker.lines().next().map(str::trim).unwrap_or_default();
+    let dir = if dir.is_empty() {
+        PORTABLE_DEFAULT_DIR
+    } else {
+        dir
+    };
+    Some(exe_dir.join(dir))
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    env::current_dir()
+        .map(|cwd| cwd.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
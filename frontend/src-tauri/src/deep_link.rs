@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::main_window;
+
+/// `hamba://` links received before the frontend was ready to listen for
+/// `deep-link` events, i.e. the one(s) the app was launched with.
+#[derive(Default)]
+pub struct PendingDeepLinks(Mutex<Vec<String>>);
+
+pub fn init(app: &AppHandle) {
+    // Installers register the scheme on Windows and macOS; AppImages and dev
+    // builds have to do it themselves.
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    if let Err(err) = app.deep_link().register_all() {
+        tracing::warn!("Failed to register hamba:// scheme: {err}");
+    }
+
+    let launch_urls = app
+        .deep_link()
+        .get_current()
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    app.manage(PendingDeepLinks(Mutex::new(launch_urls)));
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        open(
+            &handle,
+            event.urls().into_iter().map(String::from).collect(),
+        );
+    });
+}
+
+/// Brings the main window up and hands `urls` to the frontend, as if the OS
+/// had opened them
+pub fn open(app: &AppHandle, urls: Vec<String>) {
+    tracing::info!("Opening deep link {urls:?}");
+    main_window::focus(app);
+    let _ = app.emit("deep-link", urls);
+}
+
+#[tauri::command]
+pub fn take_pending_deep_links(pending: State<'_, PendingDeepLinks>) -> Vec<String> {
+    std::mem::take(&mut *pending.0.lock().unwrap())
+}
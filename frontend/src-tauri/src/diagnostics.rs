@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::clipboard::Clipboard;
+use crate::data_dir;
+use crate::disk;
+use crate::logging::{self, Logging};
+use crate::metrics::IpcTimer;
+use crate::sidecar::Sidecar;
+
+const LOG_LINES: usize = 50;
+// A backend that is up answers right away; a hung one shouldn't hold up
+// the report
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What support asks for first when something goes wrong
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    app_version: String,
+    /// As reported by the running backend, `None` when it doesn't answer
+    backend_version: Option<String>,
+    os: String,
+    arch: String,
+    backend_pid: Option<u32>,
+    backend_uptime_secs: Option<u64>,
+    backend_restarts: u32,
+    backend_port: u16,
+    backend_external: bool,
+    data_dir: Option<String>,
+    /// Free space on the disk holding the data folder
+    free_disk_bytes: Option<u64>,
+    recent_logs: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Health {
+    version: Option<String>,
+}
+
+pub async fn collect(app: &AppHandle) -> Diagnostics {
+    let backend_version = backend_version(app).await;
+    let sidecar = app.state::<Sidecar>();
+    let data_dir = data_dir::data_dir(app);
+    Diagnostics {
+        app_version: app.package_info().version.to_string(),
+        backend_version,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        backend_pid: sidecar.pid(),
+        backend_uptime_secs: sidecar.uptime().map(|uptime| uptime.as_secs()),
+        backend_restarts: sidecar.restart_count(),
+        backend_port: sidecar.port(),
+        backend_external: sidecar.is_external(),
+        free_disk_bytes: data_dir.as_deref().and_then(disk::free_space),
+        data_dir: data_dir.map(|dir| dir.to_string_lossy().into_owned()),
+        recent_logs: logging::get_recent_logs(app.state::<Logging>(), Some(LOG_LINES))
+            .unwrap_or_default(),
+    }
+}
+
+async fn backend_version(app: &AppHandle) -> Option<String> {
+    let response = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::GET, "/health")
+        .timeout(HEALTH_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    response.json::<Health>().await.ok()?.version
+}
+
+#[tauri::command]
+pub async fn get_diagnostics(_timer: IpcTimer, app: AppHandle) -> Diagnostics {
+    collect(&app).await
+}
+
+/// Puts the report on the clipboard as pretty-printed JSON, ready to paste
+/// into an issue
+pub async fn copy(app: &AppHandle) -> Result<(), String> {
+    let report = collect(app).await;
+    let text = serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?;
+    app.state::<Clipboard>().write_text(text)
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use sysinfo::{Disk as DiskInfo, Disks};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::data_dir;
+use crate::notifications;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+// Below this the user is warned; the database needs room to grow and SQLite
+// needs room for its journal
+const LOW_SPACE: u64 = 500 * 1024 * 1024;
+// Left free after a backup or export, so finishing one never fills the disk
+const RESERVE: u64 = 100 * 1024 * 1024;
+const NOTIFICATION_CATEGORY: &str = "disk";
+// As sysinfo names them
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "smbfs",
+    "cifs",
+    "smb3",
+    "nfs",
+    "nfs4",
+    "afpfs",
+    "webdav",
+    "fuse.sshfs",
+    "9p",
+];
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskLow {
+    free_bytes: u64,
+    threshold_bytes: u64,
+}
+
+/// Whether the data folder's disk was last seen below `LOW_SPACE`, so the
+/// warning is only given once per dip
+#[derive(Default)]
+pub struct Disk(Mutex<bool>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Disk::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check(&app);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+fn check(app: &AppHandle) {
+    let Some(free) = data_dir::data_dir(app).and_then(|dir| free_space(&dir)) else {
+        return;
+    };
+    let low = free < LOW_SPACE;
+    let was_low = std::mem::replace(&mut *app.state::<Disk>().0.lock().unwrap(), low);
+    if low && !was_low {
+        tracing::warn!("Low disk space: {free} bytes free");
+        let _ = app.emit(
+            "disk-low",
+            DiskLow {
+                free_bytes: free,
+                threshold_bytes: LOW_SPACE,
+            },
+        );
+        notifications::show(
+            app,
+            NOTIFICATION_CATEGORY,
+            "Low disk space",
+            &format!(
+                "Only {} left for Hamba's data. Free up some space to keep mail syncing.",
+                megabytes(free)
+            ),
+            None,
+        );
+    }
+}
+
+/// Free space on the disk `path` is on. The disk mounted deepest along the
+/// path is the one it lives on.
+pub fn free_space(path: &Path) -> Option<u64> {
+    let disks = Disks::new_with_refreshed_list();
+    disk_of(&disks, path).map(|disk| disk.available_space())
+}
+
+/// Whether `path` is on a network share, which the OS doesn't report
+/// changes on that other machines make
+pub fn is_network(path: &Path) -> bool {
+    // UNC paths, as canonicalizing gives them
+    let text = path.to_string_lossy();
+    let unc =
+        text.starts_with(r"\\?\UNC\") || (text.starts_with(r"\\") && !text.starts_with(r"\\?\"));
+    if cfg!(windows) && unc {
+        return true;
+    }
+    let disks = Disks::new_with_refreshed_list();
+    disk_of(&disks, path).is_some_and(|disk| {
+        let file_system = disk.file_system().to_string_lossy().to_ascii_lowercase();
+        NETWORK_FILE_SYSTEMS.contains(&file_system.as_str())
+    })
+}
+
+fn disk_of<'a>(disks: &'a Disks, path: &Path) -> Option<&'a DiskInfo> {
+    disks
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+}
+
+/// Fails when writing `needed` bytes next to `path` would leave less than
+/// `RESERVE` free, emitting `disk-low`. Disks that can't be measured pass.
+pub fn ensure_space(app: &AppHandle, path: &Path, needed: u64) -> Result<(), String> {
+    // The target itself may not exist yet
+    let Some(free) = path
+        .ancestors()
+        .find(|dir| dir.exists())
+        .and_then(free_space)
+    else {
+        return Ok(());
+    };
+    let required = needed.saturating_add(RESERVE);
+    if free >= required {
+        return Ok(());
+    }
+    let _ = app.emit(
+        "disk-low",
+        DiskLow {
+            free_bytes: free,
+            threshold_bytes: required,
+        },
+    );
+    Err(format!(
+        "Not enough disk space: about {} is needed but only {} is free",
+        megabytes(required),
+        megabytes(free)
+    ))
+}
+
+/// Total size of the files under `path`, skipping anything unreadable
+pub fn size_of(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| size_of(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{} MB", bytes / (1024 * 1024))
+}
@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::badge;
+use crate::notifications;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const MAX_FOCUS_MINUTES: u64 = 24 * 60;
+// Held past this, the oldest go
+const MAX_HELD: usize = 50;
+// Shown one by one once quiet time is over; any more are summed up
+const MAX_REPLAYED: usize = 3;
+const SUMMARY_CATEGORY: &str = "summary";
+
+struct Held {
+    category: String,
+    title: String,
+    body: String,
+    account_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// `None` where the OS doesn't say
+    os: Option<bool>,
+    focus_until: Option<SystemTime>,
+    held: VecDeque<Held>,
+    // The last count set during focus mode
+    badge: Option<u32>,
+}
+
+impl Inner {
+    fn focused(&self) -> bool {
+        self.focus_until
+            .is_some_and(|until| until > SystemTime::now())
+    }
+
+    fn quiet(&self) -> bool {
+        self.os == Some(true) || self.focused()
+    }
+
+    fn state(&self) -> DndState {
+        let focus_ends_at = self
+            .focus_until
+            .filter(|_| self.focused())
+            .and_then(|until| until.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as u64);
+        DndState {
+            os_do_not_disturb: self.os,
+            focus_mode: focus_ends_at.is_some(),
+            focus_ends_at,
+            held: self.held.len(),
+        }
+    }
+}
+
+/// The OS's do-not-disturb or Focus state and the app's own focus mode,
+/// along with what was held back during them. Notifications wait until
+/// both are off; badge updates only wait for focus mode.
+#[derive(Default)]
+pub struct Dnd(Mutex<Inner>);
+
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DndState {
+    /// `None` where the OS doesn't say
+    os_do_not_disturb: Option<bool>,
+    focus_mode: bool,
+    /// Milliseconds since the Unix epoch
+    focus_ends_at: Option<u64>,
+    /// Notifications waiting to be shown
+    held: usize,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Dnd::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let os = platform::os_dnd().await;
+            update(&app, |inner| inner.os = os);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+// Emits `dnd-changed` when the state does and shows whatever was held once
+// it's over
+fn update(app: &AppHandle, change: impl FnOnce(&mut Inner)) {
+    let (before, after, held, badge) = {
+        let dnd = app.state::<Dnd>();
+        let mut inner = dnd.0.lock().unwrap();
+        let before = inner.state();
+        change(&mut inner);
+        if !inner.focused() {
+            inner.focus_until = None;
+        }
+        let held: Vec<Held> = if inner.quiet() {
+            Vec::new()
+        } else {
+            inner.held.drain(..).collect()
+        };
+        let badge = if inner.focused() {
+            None
+        } else {
+            inner.badge.take()
+        };
+        (before, inner.state(), held, badge)
+    };
+    if before != after {
+        let _ = app.emit("dnd-changed", after);
+    }
+    if let Some(count) = badge {
+        if let Err(err) = badge::set(app, count) {
+            tracing::debug!("Failed to set the badge count: {err}");
+        }
+    }
+    replay(app, held);
+}
+
+fn replay(app: &AppHandle, held: Vec<Held>) {
+    if held.is_empty() {
+        return;
+    }
+    tracing::info!("Showing {} held notifications", held.len());
+    let skipped = held.len().saturating_sub(MAX_REPLAYED);
+    for notification in held.into_iter().skip(skipped) {
+        notifications::show(
+            app,
+            &notification.category,
+            &notification.title,
+            &notification.body,
+            notification.account_id,
+        );
+    }
+    if skipped > 0 {
+        notifications::show(
+            app,
+            SUMMARY_CATEGORY,
+            &format!("{skipped} more notifications"),
+            "They arrived while notifications were paused.",
+            None,
+        );
+    }
+}
+
+/// Keeps a notification back for later while the OS or the app says not to
+/// disturb. Returns whether it was.
+pub fn hold_notification(
+    app: &AppHandle,
+    category: &str,
+    title: &str,
+    body: &str,
+    account_id: Option<String>,
+) -> bool {
+    let Some(dnd) = app.try_state::<Dnd>() else {
+        return false;
+    };
+    let mut inner = dnd.0.lock().unwrap();
+    if !inner.quiet() {
+        return false;
+    }
+    if inner.held.len() == MAX_HELD {
+        inner.held.pop_front();
+    }
+    inner.held.push_back(Held {
+        category: category.to_string(),
+        title: title.to_string(),
+        body: body.to_string(),
+        account_id,
+    });
+    true
+}
+
+/// Whether the OS or the app says not to disturb right now
+pub fn is_quiet(app: &AppHandle) -> bool {
+    app.try_state::<Dnd>()
+        .is_some_and(|dnd| dnd.0.lock().unwrap().quiet())
+}
+
+/// Keeps the badge as it is during focus mode, remembering `count` for when
+/// it ends. Returns whether it did.
+pub fn hold_badge(app: &AppHandle, count: u32) -> bool {
+    let Some(dnd) = app.try_state::<Dnd>() else {
+        return false;
+    };
+    let mut inner = dnd.0.lock().unwrap();
+    if !inner.focused() {
+        return false;
+    }
+    inner.badge = Some(count);
+    true
+}
+
+#[tauri::command]
+pub fn get_dnd_state(dnd: State<'_, Dnd>) -> DndState {
+    dnd.0.lock().unwrap().state()
+}
+
+/// Holds notifications and badge updates for `minutes`, or until
+/// `stop_focus_mode`. Starting it again moves the end.
+#[tauri::command]
+pub fn start_focus_mode(app: AppHandle, minutes: u64) -> Result<DndState, String> {
+    if minutes == 0 || minutes > MAX_FOCUS_MINUTES {
+        return Err(format!("Focus mode lasts 1 to {MAX_FOCUS_MINUTES} minutes"));
+    }
+    let duration = Duration::from_secs(minutes * 60);
+    update(&app, |inner| {
+        inner.focus_until = Some(SystemTime::now() + duration)
+    });
+    tracing::info!("Focus mode on for {minutes} minutes");
+
+    // A later start or stop makes this a no-op, as the end it checks moved
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(duration).await;
+        update(&handle, |_| {});
+    });
+    Ok(app.state::<Dnd>().0.lock().unwrap().state())
+}
+
+#[tauri::command]
+pub fn stop_focus_mode(app: AppHandle) -> DndState {
+    update(&app, |inner| inner.focus_until = None);
+    app.state::<Dnd>().0.lock().unwrap().state()
+}
+
+// Focus Assist and Windows 11's do not disturb only show up here as quiet
+// time and busy states, which covers presentations and fullscreen games too
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    pub async fn os_dnd() -> Option<bool> {
+        let state = unsafe { SHQueryUserNotificationState() }.ok()?;
+        Some(matches!(
+            state,
+            QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE | QUNS_QUIET_TIME
+        ))
+    }
+}
+
+// There is no public API for Focus. Since macOS 12 the active ones are
+// listed in a file in the user's library; before that it was a preference.
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs;
+    use std::process::Command;
+
+    use serde_json::Value;
+
+    const ASSERTIONS: &str = "Library/DoNotDisturb/DB/Assertions.json";
+
+    pub async fn os_dnd() -> Option<bool> {
+        tauri::async_runtime::spawn_blocking(read)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn read() -> Option<bool> {
+        let home = std::env::var_os("HOME")?;
+        if let Ok(text) = fs::read_to_string(std::path::Path::new(&home).join(ASSERTIONS)) {
+            let value: Value = serde_json::from_str(&text).ok()?;
+            let active = value["data"].as_array().is_some_and(|stores| {
+                stores.iter().any(|store| {
+                    store["storeAssertionRecords"]
+                        .as_array()
+                        .is_some_and(|records| !records.is_empty())
+                })
+            });
+            return Some(active);
+        }
+        let output = Command::new("defaults")
+            .args([
+                "-currentHost",
+                "read",
+                "com.apple.notificationcenterui",
+                "doNotDisturb",
+            ])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim() == "1")
+    }
+}
+
+// KDE's notification server says whether it's inhibited; GNOME Shell keeps
+// do not disturb as a setting instead
+#[cfg(target_os = "linux")]
+mod platform {
+    use gtk::gio;
+    use gtk::prelude::*;
+    use tokio::sync::OnceCell;
+
+    const GNOME_SCHEMA: &str = "org.gnome.desktop.notifications";
+    const SHOW_BANNERS: &str = "show-banners";
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    trait Notifications {
+        #[zbus(property)]
+        fn inhibited(&self) -> zbus::Result<bool>;
+    }
+
+    static SESSION: OnceCell<Option<zbus::Connection>> = OnceCell::const_new();
+
+    pub async fn os_dnd() -> Option<bool> {
+        let connection = SESSION
+            .get_or_init(|| async { zbus::Connection::session().await.ok() })
+            .await;
+        if let Some(connection) = connection {
+            let proxy = NotificationsProxy::builder(connection)
+                .cache_properties(zbus::proxy::CacheProperties::No)
+                .build()
+                .await;
+            if let Ok(inhibited) = async { proxy?.inhibited().await }.await {
+                return Some(inhibited);
+            }
+        }
+        gnome_dnd()
+    }
+
+    fn gnome_dnd() -> Option<bool> {
+        // Reading a schema that isn't installed aborts
+        gio::SettingsSchemaSource::default()?.lookup(GNOME_SCHEMA, true)?;
+        let settings = gio::Settings::new(GNOME_SCHEMA);
+        Some(!settings.boolean(SHOW_BANNERS))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    pub async fn os_dnd() -> Option<bool> {
+        None
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::ipc::{InvokeBody, Request};
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::metrics::IpcTimer;
+
+// Files being dragged out live here until the next drag starts, as the
+// drop target may only copy them once the drop is over
+const DRAG_DIR: &str = "drag";
+const NAME_HEADER: &str = "X-File-Name";
+
+type Done = oneshot::Sender<Result<(), String>>;
+
+/// Starts dragging a file out of the window, to drop on the desktop or into
+/// another app. The file's bytes are the raw request body and its
+/// URI-encoded name the `X-File-Name` header. Call it while the mouse button
+/// is still down, e.g. on `dragstart`.
+#[tauri::command]
+pub async fn start_drag(
+    _timer: IpcTimer,
+    app: AppHandle,
+    window: WebviewWindow,
+    request: Request<'_>,
+) -> Result<(), String> {
+    let InvokeBody::Raw(contents) = request.body() else {
+        return Err("Expected the file's bytes".to_string());
+    };
+    let name = request
+        .headers()
+        .get(NAME_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(decode_name)
+        .ok_or("Missing the file's name")??;
+    let path = write(&app, &name, contents).map_err(|err| err.to_string())?;
+
+    let (sender, receiver) = oneshot::channel();
+    platform::start(&window, path, sender)?;
+    receiver
+        .await
+        .map_err(|_| "The drag was cancelled".to_string())?
+}
+
+fn write(app: &AppHandle, name: &str, contents: &[u8]) -> std::io::Result<PathBuf> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(std::io::Error::other)?
+        .join(DRAG_DIR);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+// Header values are ASCII, so the name comes percent-encoded
+fn decode_name(encoded: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).unwrap_or_default();
+            if let Ok(decoded) = u8::from_str_radix(hex, 16) {
+                bytes.push(decoded);
+                rest = &tail[2..];
+                continue;
+            }
+        }
+        bytes.push(byte);
+        rest = tail;
+    }
+    let name = String::from_utf8(bytes).map_err(|_| "The file name is not UTF-8".to_string())?;
+    // Only a bare name, nothing that could point outside the drag folder
+    let valid = Path::new(&name).file_name().and_then(|file| file.to_str()) == Some(name.as_str());
+    if !valid || name.starts_with('.') {
+        return Err(format!("{name:?} is not a file name"));
+    }
+    Ok(name)
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use tauri::WebviewWindow;
+    use windows::core::HSTRING;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::IDataObject;
+    use windows::Win32::System::Ole::{IDropSource, DROPEFFECT_COPY};
+    use windows::Win32::UI::Shell::{
+        BHID_DataObject, IShellItem, SHCreateItemFromParsingName, SHDoDragDrop,
+    };
+
+    use super::Done;
+
+    // The shell makes both the data object and the drop source, with the
+    // file's icon as the drag image. The drag runs its own message loop
+    // until the drop, so the window keeps painting.
+    pub fn start(window: &WebviewWindow, path: PathBuf, done: Done) -> Result<(), String> {
+        // Raw window handles can't cross threads, their address can
+        let hwnd = window.hwnd().map_err(|err| err.to_string())?.0 as isize;
+        window
+            .run_on_main_thread(move || {
+                let result = drag(HWND(hwnd as *mut _), &path).map_err(|err| err.to_string());
+                let _ = done.send(result);
+            })
+            .map_err(|err| err.to_string())
+    }
+
+    fn drag(hwnd: HWND, path: &Path) -> windows::core::Result<()> {
+        unsafe {
+            let item: IShellItem =
+                SHCreateItemFromParsingName(&HSTRING::from(path.as_os_str()), None)?;
+            let data: IDataObject = item.BindToHandler(None, &BHID_DataObject)?;
+            SHDoDragDrop(Some(hwnd), &data, None::<&IDropSource>, DROPEFFECT_COPY)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, c_void, CString};
+    use std::path::{Path, PathBuf};
+
+    use objc2::encode::{Encode, Encoding};
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use tauri::WebviewWindow;
+
+    use super::Done;
+
+    // NSEventTypeLeftMouseDragged
+    const LEFT_MOUSE_DRAGGED: usize = 6;
+    const ICON_SIZE: f64 = 32.0;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Size {
+        width: f64,
+        height: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Rect {
+        origin: Point,
+        size: Size,
+    }
+
+    unsafe impl Encode for Point {
+        const ENCODING: Encoding = Encoding::Struct("CGPoint", &[f64::ENCODING, f64::ENCODING]);
+    }
+
+    unsafe impl Encode for Size {
+        const ENCODING: Encoding = Encoding::Struct("CGSize", &[f64::ENCODING, f64::ENCODING]);
+    }
+
+    unsafe impl Encode for Rect {
+        const ENCODING: Encoding = Encoding::Struct("CGRect", &[Point::ENCODING, Size::ENCODING]);
+    }
+
+    pub fn start(window: &WebviewWindow, path: PathBuf, done: Done) -> Result<(), String> {
+        window
+            .with_webview(move |webview| {
+                let _ = done.send(unsafe { drag(webview.ns_window(), &path) });
+            })
+            .map_err(|err| err.to_string())
+    }
+
+    // `dragFile:` needs no dragging source of our own and puts the file's
+    // icon under the pointer. The mouse event that started the drag in the
+    // page is long handled, so one is made up at the pointer.
+    unsafe fn drag(ns_window: *mut c_void, path: &Path) -> Result<(), String> {
+        unsafe {
+            let window = &*(ns_window as *const AnyObject);
+            let view: Option<Retained<AnyObject>> = msg_send![window, contentView];
+            let view = view.ok_or("The window has no content view")?;
+            let file = ns_string(&path.to_string_lossy()).ok_or("Invalid file path")?;
+
+            let location: Point = msg_send![window, mouseLocationOutsideOfEventStream];
+            let number: isize = msg_send![window, windowNumber];
+            let uptime: f64 = {
+                let info: Retained<AnyObject> = msg_send![class!(NSProcessInfo), processInfo];
+                msg_send![&*info, systemUptime]
+            };
+            let event: Option<Retained<AnyObject>> = msg_send![
+                class!(NSEvent),
+                mouseEventWithType: LEFT_MOUSE_DRAGGED,
+                location: location,
+                modifierFlags: 0usize,
+                timestamp: uptime,
+                windowNumber: number,
+                context: std::ptr::null_mut::<AnyObject>(),
+                eventNumber: 0isize,
+                clickCount: 1isize,
+                pressure: 1.0f32
+            ];
+            let event = event.ok_or("Could not start the drag")?;
+
+            let at: Point = msg_send![&*view, convertPoint: location, fromView: std::ptr::null_mut::<AnyObject>()];
+            let rect = Rect {
+                origin: Point {
+                    x: at.x - ICON_SIZE / 2.0,
+                    y: at.y - ICON_SIZE / 2.0,
+                },
+                size: Size {
+                    width: ICON_SIZE,
+                    height: ICON_SIZE,
+                },
+            };
+            let started: bool = msg_send![
+                &*view,
+                dragFile: &*file,
+                fromRect: rect,
+                slideBack: true,
+                event: &*event
+            ];
+            if !started {
+                return Err("Could not start the drag".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn ns_string(text: &str) -> Option<Retained<AnyObject>> {
+        let text = CString::new(text.replace('\0', "")).ok()?;
+        let ptr: *const c_char = text.as_ptr();
+        unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::cell::RefCell;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+
+    use gtk::prelude::*;
+    use gtk::{gdk, glib, TargetEntry, TargetFlags, TargetList};
+    use tauri::WebviewWindow;
+
+    use super::Done;
+
+    const URI_LIST: &str = "text/uri-list";
+    const PRIMARY_BUTTON: i32 = 1;
+
+    pub fn start(window: &WebviewWindow, path: PathBuf, done: Done) -> Result<(), String> {
+        window
+            .with_webview(move |webview| {
+                let _ = done.send(drag(webview.inner().upcast(), &path));
+            })
+            .map_err(|err| err.to_string())
+    }
+
+    // The file is offered as a URI to whoever takes the drop. The handlers
+    // come off once the drag ends so WebKit's own drags are left alone.
+    fn drag(view: gtk::Widget, path: &Path) -> Result<(), String> {
+        let uri = glib::filename_to_uri(path, None).map_err(|err| err.to_string())?;
+        let handlers = Rc::new(RefCell::new(Vec::new()));
+
+        let data_get = view.connect_drag_data_get(move |_, _, data, _, _| {
+            data.set_uris(&[uri.as_str()]);
+        });
+        let ended = handlers.clone();
+        let drag_end = view.connect_drag_end(move |view, _| {
+            for handler in ended.borrow_mut().drain(..) {
+                view.disconnect(handler);
+            }
+        });
+        handlers.borrow_mut().extend([data_get, drag_end]);
+
+        let targets = TargetList::new(&[TargetEntry::new(URI_LIST, TargetFlags::OTHER_APP, 0)]);
+        let context = view.drag_begin_with_coordinates(
+            &targets,
+            gdk::DragAction::COPY,
+            PRIMARY_BUTTON,
+            None,
+            -1,
+            -1,
+        );
+        if context.is_none() {
+            for handler in handlers.borrow_mut().drain(..) {
+                view.disconnect(handler);
+            }
+            return Err("Could not start the drag".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use std::path::PathBuf;
+
+    use tauri::WebviewWindow;
+
+    use super::Done;
+
+    pub fn start(_window: &WebviewWindow, _path: PathBuf, _done: Done) -> Result<(), String> {
+        Err("Dragging files out is not supported on this platform".to_string())
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+use crate::safe_mode;
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, with_suffix, Sidecar, DATABASE_FILE, DATABASE_SIDE_FILES};
+
+// Keychain entry holding the database key, see `secrets`
+pub const KEY_SECRET: &str = "database-key";
+const ENCRYPTED_SUFFIX: &str = "encrypted";
+// The unencrypted database is kept under this suffix until the backend has
+// come up on the encrypted copy
+const PLAINTEXT_SUFFIX: &str = "unencrypted";
+
+/// Held for the duration of a migration so two never interleave
+#[derive(Default)]
+pub struct Encryption(tokio::sync::Mutex<()>);
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+enum Stage {
+    Pausing,
+    Encrypting,
+    Restarting,
+    Done,
+    RollingBack { error: String },
+    RolledBack { error: String },
+    Failed { error: String },
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Encryption::default());
+}
+
+/// The key the backend opens the database with, `None` while encryption is
+/// off. Without it an encrypted database can't be read, so a missing key is
+/// logged loudly rather than silently starting on a fresh database.
+pub fn database_key(app: &AppHandle) -> Option<String> {
+    if !app.state::<SettingsStore>().get().encrypt_data {
+        return None;
+    }
+    match secrets::get(KEY_SECRET) {
+        Ok(Some(key)) => Some(key),
+        Ok(None) => {
+            tracing::error!("Database encryption is on but its key is missing from the keychain");
+            None
+        }
+        Err(err) => {
+            tracing::error!("Failed to read the database key: {err}");
+            None
+        }
+    }
+}
+
+// A random 256-bit key, generated once and kept in the keychain from then on
+fn load_or_create_key() -> Result<String, String> {
+    if let Some(key) = secrets::get(KEY_SECRET)? {
+        return Ok(key);
+    }
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|err| err.to_string())?;
+    let key = hex::encode(bytes);
+    secrets::set(KEY_SECRET, &key)?;
+    Ok(key)
+}
+
+/// Encrypts the database with a key stored in the OS keychain and restarts
+/// the backend on it, emitting `encryption-progress` at each stage. If the
+/// backend doesn't come up on the encrypted copy, the original is put back.
+#[tauri::command]
+pub async fn enable_encryption(
+    _timer: IpcTimer,
+    app: AppHandle,
+    encryption: State<'_, Encryption>,
+) -> Result<(), String> {
+    safe_mode::refuse(&app)?;
+    if app.state::<Sidecar>().is_external() {
+        return Err("The backend is not run by the app in this mode".to_string());
+    }
+    let _guard = encryption.0.lock().await;
+    if app.state::<SettingsStore>().get().encrypt_data {
+        return Err("The database is already encrypted".to_string());
+    }
+    let dir = data_dir::data_dir(&app).ok_or("No data directory available")?;
+    let database = dir.join(DATABASE_FILE);
+    let encrypted = with_suffix(&database, ENCRYPTED_SUFFIX);
+    let plaintext = with_suffix(&database, PLAINTEXT_SUFFIX);
+    // Left over from an attempt that was interrupted
+    let _ = fs::remove_file(&encrypted);
+
+    let key = load_or_create_key()?;
+
+    // Nothing should be written between the export and the restart
+    emit(&app, Stage::Pausing);
+    let sidecar = app.state::<Sidecar>();
+    let paused = sidecar
+        .request(reqwest::Method::POST, "/background/pause")
+        .send()
+        .await;
+    if let Err(err) = paused.and_then(|response| response.error_for_status()) {
+        tracing::warn!("Failed to pause background jobs: {err}");
+    }
+
+    emit(&app, Stage::Encrypting);
+    let response = sidecar
+        .request(reqwest::Method::POST, "/encryption/export")
+        .json(&json!({ "path": encrypted, "key": key }))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let error = response.text().await.unwrap_or_default();
+        let _ = fs::remove_file(&encrypted);
+        let _ = sidecar
+            .request(reqwest::Method::POST, "/background/resume")
+            .send()
+            .await;
+        return Err(fail(
+            &app,
+            format!("The backend could not encrypt the database: {error}"),
+        ));
+    }
+
+    emit(&app, Stage::Restarting);
+    stop_backend(&app).await;
+    let swapped = set_enabled(&app, true).and_then(|()| {
+        swap_in(&database, &encrypted, &plaintext).map_err(|err| {
+            let _ = set_enabled(&app, false);
+            err.to_string()
+        })
+    });
+    if let Err(err) = swapped {
+        sidecar::start(&app);
+        return Err(fail(
+            &app,
+            format!("Failed to swap in the encrypted database: {err}"),
+        ));
+    }
+    sidecar::start(&app);
+    if sidecar::wait_until_healthy(&app).await {
+        tracing::info!("Database encryption enabled");
+        remove_database(&plaintext);
+        emit(&app, Stage::Done);
+        return Ok(());
+    }
+
+    let error = "The backend did not start on the encrypted database".to_string();
+    tracing::warn!("{error}, rolling back");
+    emit(
+        &app,
+        Stage::RollingBack {
+            error: error.clone(),
+        },
+    );
+    stop_backend(&app).await;
+    remove_database(&database);
+    let restored = fs::rename(&plaintext, &database).map_err(|err| err.to_string());
+    let restored = restored.and_then(|()| set_enabled(&app, false));
+    sidecar::start(&app);
+    if restored.is_ok() && sidecar::wait_until_healthy(&app).await {
+        emit(
+            &app,
+            Stage::RolledBack {
+                error: error.clone(),
+            },
+        );
+        Err(format!("{error}, the unencrypted database was restored"))
+    } else {
+        Err(fail(
+            &app,
+            format!(
+                "{error}, and restoring the unencrypted database failed. It is at {}",
+                plaintext.display()
+            ),
+        ))
+    }
+}
+
+async fn stop_backend(app: &AppHandle) {
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle)).await;
+}
+
+// The encrypted copy replaces the database, which is kept aside for now
+fn swap_in(database: &Path, encrypted: &Path, plaintext: &Path) -> std::io::Result<()> {
+    let _ = fs::remove_file(plaintext);
+    fs::rename(database, plaintext)?;
+    // A clean shutdown checkpoints the WAL, so these are stale
+    for suffix in DATABASE_SIDE_FILES {
+        let _ = fs::remove_file(with_suffix(database, suffix));
+    }
+    if let Err(err) = fs::rename(encrypted, database) {
+        let _ = fs::rename(plaintext, database);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn remove_database(path: &Path) {
+    let _ = fs::remove_file(path);
+    for suffix in DATABASE_SIDE_FILES {
+        let _ = fs::remove_file(with_suffix(path, suffix));
+    }
+}
+
+fn set_enabled(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    app.state::<SettingsStore>()
+        .update(|settings| settings.encrypt_data = enabled)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+fn fail(app: &AppHandle, error: String) -> String {
+    tracing::error!("{error}");
+    emit(
+        app,
+        Stage::Failed {
+            error: error.clone(),
+        },
+    );
+    error
+}
+
+fn emit(app: &AppHandle, stage: Stage) {
+    let _ = app.emit("encryption-progress", stage);
+}
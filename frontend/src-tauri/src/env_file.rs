@@ -0,0 +1,160 @@
+use std::fs;
+use std::io;
+use std::sync::RwLock;
+
+use tauri::{AppHandle, Manager};
+
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+use crate::safe_mode;
+use crate::sidecar;
+
+const ENV_FILE: &str = "hamba.env";
+// Backend settings a power user may tune. What the app manages itself
+// (port, token, TLS, database) is left out so the file can't break or
+// weaken it.
+const ALLOWED: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "GOOGLE_CLIENT_ID",
+    "GOOGLE_CLIENT_SECRET",
+    "MICROSOFT_CLIENT_ID",
+    "MICROSOFT_CLIENT_SECRET",
+    "MICROSOFT_REDIRECT_URI",
+    "YAHOO_CLIENT_ID",
+    "YAHOO_CLIENT_SECRET",
+    "YAHOO_REDIRECT_URI",
+    "REDIRECT_URI",
+    "LOG_LEVEL",
+    "LOG_FORMAT",
+    "SENTRY_DSN",
+];
+
+/// Variables from `hamba.env` in the config folder, passed to the backend
+/// on every start
+#[derive(Default)]
+pub struct EnvFile(RwLock<Vec<(String, String)>>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(EnvFile(RwLock::new(load(app))));
+}
+
+fn load(app: &AppHandle) -> Vec<(String, String)> {
+    let Some(path) = data_dir::config_dir(app).map(|dir| dir.join(ENV_FILE)) else {
+        return Vec::new();
+    };
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => {
+            tracing::warn!("Failed to read {}: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+// `KEY=value` lines as in a shell or docker env file: `#` comments, an
+// optional `export`, and values optionally in single or double quotes.
+// Lines that don't parse or set a key outside `ALLOWED` are skipped.
+fn parse(contents: &str) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            tracing::warn!("{ENV_FILE}:{}: expected KEY=value", index + 1);
+            continue;
+        };
+        let key = key.trim();
+        if !ALLOWED.contains(&key) {
+            tracing::warn!("{ENV_FILE}:{}: {key} can't be set here", index + 1);
+            continue;
+        }
+        let value = value.trim();
+        let value = match value.as_bytes() {
+            [b'"', .., b'"'] | [b'\'', .., b'\''] => &value[1..value.len() - 1],
+            _ => value,
+        };
+        // The last assignment wins, as in a shell
+        vars.retain(|(existing, _)| existing != key);
+        vars.push((key.to_string(), value.to_string()));
+    }
+    vars
+}
+
+pub fn vars(app: &AppHandle) -> Vec<(String, String)> {
+    app.state::<EnvFile>().0.read().unwrap().clone()
+}
+
+/// Reads `hamba.env` again and restarts the backend with it. Resolves with
+/// the names of the variables that were applied.
+#[tauri::command]
+pub async fn reload_env_and_restart_backend(
+    _timer: IpcTimer,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    safe_mode::refuse(&app)?;
+    let vars = load(&app);
+    let names = vars.iter().map(|(key, _)| key.clone()).collect();
+    *app.state::<EnvFile>().0.write().unwrap() = vars;
+    tracing::info!("Reloaded {ENV_FILE}, restarting the backend");
+    if !sidecar::restart(&app).await {
+        return Err("Backend did not come back up after restarting".to_string());
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_allowed_keys_are_passed_on() {
+        let parsed = parse(
+            "LOG_LEVEL=debug\n\
+             PORT=1\n\
+             DATABASE_PATH=/tmp/other.db\n\
+             BACKEND_TOKEN=guessed\n\
+             log_level=lowercase\n\
+             SENTRY_DSN=https://key@sentry.example.com/1\n",
+        );
+        assert_eq!(
+            parsed,
+            [
+                ("LOG_LEVEL".to_string(), "debug".to_string()),
+                (
+                    "SENTRY_DSN".to_string(),
+                    "https://key@sentry.example.com/1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_are_read_like_a_shell_env_file() {
+        let parsed = parse(
+            "# OAuth\n\
+             \n\
+             export GOOGLE_CLIENT_ID = \"id with spaces\"\n\
+             YAHOO_CLIENT_ID='single'\n\
+             REDIRECT_URI=http://localhost/cb?a=b\n\
+             not a variable\n\
+             LOG_FORMAT=\"\n\
+             LOG_FORMAT=json\n",
+        );
+        assert_eq!(
+            parsed,
+            [
+                ("GOOGLE_CLIENT_ID".to_string(), "id with spaces".to_string()),
+                ("YAHOO_CLIENT_ID".to_string(), "single".to_string()),
+                (
+                    "REDIRECT_URI".to_string(),
+                    "http://localhost/cb?a=b".to_string()
+                ),
+                ("LOG_FORMAT".to_string(), "json".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::data_dir;
+use crate::disk;
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+use crate::sidecar::{Sidecar, DATABASE_FILE};
+
+// Progress events are throttled so a fast export doesn't flood the frontend
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportProgress<'a> {
+    path: &'a Path,
+    /// Emails written so far, out of `total` when the backend said how many
+    exported: u64,
+    total: Option<u64>,
+    bytes: u64,
+    done: bool,
+}
+
+/// Counts the emails in a streamed export. Both formats put one email per
+/// line; CSV fields can contain line breaks too, but only inside quotes.
+struct RecordCounter {
+    format: ExportFormat,
+    in_quotes: bool,
+    lines: u64,
+}
+
+impl RecordCounter {
+    fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            match byte {
+                b'"' if matches!(self.format, ExportFormat::Csv) => {
+                    self.in_quotes = !self.in_quotes
+                }
+                b'\n' if !self.in_quotes => self.lines += 1,
+                _ => {}
+            }
+        }
+    }
+
+    // Without the CSV header row or the JSON array's opening bracket line
+    fn records(&self) -> u64 {
+        self.lines.saturating_sub(1)
+    }
+}
+
+/// Streams an export from the backend into `path`, emitting `export-progress`
+/// as it goes, and reveals the file once it's complete. Nothing is left at
+/// `path` if the export fails halfway.
+#[tauri::command]
+pub async fn export_data(
+    _timer: IpcTimer,
+    app: AppHandle,
+    format: ExportFormat,
+    path: PathBuf,
+    account_id: Option<String>,
+) -> Result<PathBuf, String> {
+    let path = fs_scope::check(&app, &path, Access::Write).map_err(|err| err.to_string())?;
+    // An export holds about as much as the database, often less
+    let estimate = data_dir::data_dir(&app)
+        .map(|dir| disk::size_of(&dir.join(DATABASE_FILE)))
+        .unwrap_or(0);
+    disk::ensure_space(&app, &path, estimate)?;
+
+    let mut query = vec![("format", format.as_str().to_string())];
+    if let Some(account_id) = account_id {
+        query.push(("accountId", account_id));
+    }
+    let mut response = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::GET, "/export")
+        .query(&query)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Backend could not export: {err}"))?;
+    let total = response
+        .headers()
+        .get("X-Export-Total")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+
+    let partial = path.with_extension("partial");
+    let result = async {
+        let mut file = File::create(&partial).map_err(|err| err.to_string())?;
+        let mut counter = RecordCounter {
+            format,
+            in_quotes: false,
+            lines: 0,
+        };
+        let mut bytes = 0;
+        let mut last_progress = Instant::now();
+
+        while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+            file.write_all(&chunk).map_err(|err| err.to_string())?;
+            counter.feed(&chunk);
+            bytes += chunk.len() as u64;
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let _ = app.emit(
+                    "export-progress",
+                    ExportProgress {
+                        path: &path,
+                        exported: counter.records(),
+                        total,
+                        bytes,
+                        done: false,
+                    },
+                );
+            }
+        }
+        file.sync_all().map_err(|err| err.to_string())?;
+        fs::rename(&partial, &path).map_err(|err| err.to_string())?;
+
+        let _ = app.emit(
+            "export-progress",
+            ExportProgress {
+                path: &path,
+                exported: total.unwrap_or_else(|| counter.records()),
+                total,
+                bytes,
+                done: true,
+            },
+        );
+        Ok(())
+    }
+    .await;
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    if let Err(err) = app.opener().reveal_item_in_dir(&path) {
+        tracing::warn!("Failed to reveal export {}: {err}", path.display());
+    }
+    Ok(path)
+}
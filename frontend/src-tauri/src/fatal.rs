@@ -0,0 +1,72 @@
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+
+use crate::logging;
+
+const RETRY: &str = "Retry";
+const OPEN_LOGS: &str = "Open logs";
+const QUIT: &str = "Quit";
+
+// What the user can do about it, in words a screen reader reads out as is
+fn explain(err: &tauri::Error) -> &'static str {
+    match err {
+        tauri::Error::Runtime(_) => {
+            "Hamba couldn't open its window. The system's web view (WebView2 on Windows, \
+             WebKitGTK on Linux) may be missing or damaged; reinstalling it or Hamba \
+             usually fixes this."
+        }
+        tauri::Error::Setup(_) => {
+            "Part of Hamba failed while starting up. Trying again often works; if it \
+             doesn't, the log says what went wrong."
+        }
+        tauri::Error::PluginInitialization(..) => {
+            "A part of Hamba that talks to the system couldn't start. Restarting the \
+             computer or reinstalling Hamba usually fixes this."
+        }
+        _ => "Hamba ran into a problem while starting up.",
+    }
+}
+
+/// For when the app can't be built, which leaves no window to show anything
+/// in: explains the failure in a native dialog, which screen readers announce
+/// like any other, and restarts or quits as the user picks. Headless launches
+/// only get the error on stderr.
+pub fn startup_failed(err: &tauri::Error, headless: bool) -> ! {
+    tracing::error!("Failed to start: {err}");
+    if headless {
+        eprintln!("Hamba failed to start: {err}");
+        std::process::exit(1);
+    }
+
+    let description = format!("{}\n\nError: {err}", explain(err));
+    loop {
+        let buttons = match logging::dir() {
+            Some(_) => MessageButtons::YesNoCancelCustom(
+                RETRY.to_string(),
+                OPEN_LOGS.to_string(),
+                QUIT.to_string(),
+            ),
+            None => MessageButtons::OkCancelCustom(RETRY.to_string(), QUIT.to_string()),
+        };
+        let choice = MessageDialog::new()
+            .set_level(MessageLevel::Error)
+            .set_title("Hamba couldn't start")
+            .set_description(&description)
+            .set_buttons(buttons)
+            .show();
+        match choice {
+            MessageDialogResult::Custom(button) if button == RETRY => {
+                tracing::info!("Restarting after the startup failure");
+                tauri::process::restart(&tauri::Env::default());
+            }
+            // The dialog comes back so the user can still retry afterwards
+            MessageDialogResult::Custom(button) if button == OPEN_LOGS => {
+                if let Some(dir) = logging::dir() {
+                    if let Err(err) = tauri_plugin_opener::open_path(dir, None::<&str>) {
+                        tracing::warn!("Failed to open the log folder: {err}");
+                    }
+                }
+            }
+            _ => std::process::exit(1),
+        }
+    }
+}
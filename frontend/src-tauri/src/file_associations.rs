@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::cli::Cli;
+use crate::fs_scope::{self, Access};
+use crate::main_window;
+
+/// Registered in tauri.conf.json's `fileAssociations` for the installers
+pub const EXTENSION: &str = "hamba";
+
+/// Files opened before the frontend asked for them. `None` once it has, after
+/// which every file is sent straight to it as an `open-file` event.
+pub struct PendingOpenFiles(Mutex<Option<Vec<PathBuf>>>);
+
+pub fn init(app: &AppHandle) {
+    // The installer registers the extension too, but portable and dev builds
+    // only get it this way, for the current user
+    #[cfg(windows)]
+    if let Err(err) = register() {
+        tracing::warn!("Failed to register .{EXTENSION} files: {err}");
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let files = files_in_args(&app.state::<Cli>().args, &cwd);
+    app.manage(PendingOpenFiles(Mutex::new(Some(files))));
+}
+
+/// Opens the files passed to a second launch, which is how Windows and Linux
+/// hand over a double-clicked file while the app is running
+pub fn open_from_args(app: &AppHandle, args: &[String], cwd: &str) {
+    for path in files_in_args(args, Path::new(cwd)) {
+        open(app, path);
+    }
+}
+
+/// Opens the files macOS sends with an `open` Apple Event, both at launch
+/// and while running
+#[cfg(target_os = "macos")]
+pub fn open_from_urls(app: &AppHandle, urls: &[tauri::Url]) {
+    let files = urls
+        .iter()
+        .filter(|url| url.scheme() == "file")
+        .filter_map(|url| url.to_file_path().ok())
+        .filter(|path| is_openable(path));
+    for path in files {
+        open(app, path);
+    }
+}
+
+fn open(app: &AppHandle, path: PathBuf) {
+    tracing::info!("Opening {}", path.display());
+    // macOS can send files before setup has run
+    let Some(pending) = app.try_state::<PendingOpenFiles>() else {
+        return;
+    };
+    if let Some(files) = pending.0.lock().unwrap().as_mut() {
+        files.push(path);
+        return;
+    }
+    // Opening a file with the app grants it, as picking it would
+    fs_scope::grant(app, &path, Access::Read);
+    main_window::focus(app);
+    let _ = app.emit("open-file", path);
+}
+
+fn files_in_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .map(|arg| cwd.join(arg))
+        .filter(|path| is_openable(path))
+        .collect()
+}
+
+fn is_openable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(EXTENSION))
+}
+
+#[tauri::command]
+pub fn take_pending_open_files(
+    app: AppHandle,
+    pending: State<'_, PendingOpenFiles>,
+) -> Vec<PathBuf> {
+    let files = pending.0.lock().unwrap().take().unwrap_or_default();
+    for path in &files {
+        fs_scope::grant(&app, path, Access::Read);
+    }
+    files
+}
+
+/// Points `.hamba` files at this executable for the current user
+#[cfg(windows)]
+pub fn register() -> Result<(), String> {
+    platform::register()
+}
+
+#[cfg(windows)]
+mod platform {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    const PROG_ID: &str = "Hamba.File";
+
+    pub fn register() -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+        let command = format!("\"{}\" \"%1\"", exe.to_string_lossy());
+
+        let (classes, _) = RegKey::predef(HKEY_CURRENT_USER)
+            .create_subkey(r"Software\Classes")
+            .map_err(|err| err.to_string())?;
+        let (open, _) = classes
+            .create_subkey(format!(r"{PROG_ID}\shell\open\command"))
+            .map_err(|err| err.to_string())?;
+        // Already pointing at this executable
+        if open.get_value::<String, _>("").ok().as_deref() == Some(command.as_str()) {
+            return Ok(());
+        }
+        open.set_value("", &command)
+            .map_err(|err| err.to_string())?;
+
+        let (prog_id, _) = classes
+            .create_subkey(PROG_ID)
+            .map_err(|err| err.to_string())?;
+        prog_id
+            .set_value("", &"Hamba File")
+            .map_err(|err| err.to_string())?;
+        let (extension, _) = classes
+            .create_subkey(format!(".{}", super::EXTENSION))
+            .map_err(|err| err.to_string())?;
+        extension
+            .set_value("", &PROG_ID)
+            .map_err(|err| err.to_string())
+    }
+}
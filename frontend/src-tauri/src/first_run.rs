@@ -0,0 +1,355 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::data_dir;
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+use crate::sidecar::{self, with_suffix, Sidecar, DATABASE_FILE, DATABASE_SIDE_FILES};
+
+// Written once the first run is over, and bumped by future data migrations
+const INSTALL_FILE: &str = "install.json";
+const SCHEMA_VERSION: u32 = 1;
+// Every SQLite database starts with this
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+const COPY_CHUNK: usize = 1024 * 1024;
+
+// Relative to the OS data folder: installs from before the app identifier
+// was `com.hamba.app`
+const PREVIOUS_INSTALL_DIRS: &[&str] = &["hamba", "Hamba"];
+// Relative to the home folder: the browser-based version, run from a
+// checkout with `./dev` (Docker) or `bun run dev` in `backend/`
+const BROWSER_DIRS: &[&str] = &["hamba/data", "hamba/backend", ".hamba"];
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Install {
+    schema_version: u32,
+    imported_from: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum LegacyKind {
+    PreviousInstall,
+    Browser,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyData {
+    path: PathBuf,
+    kind: LegacyKind,
+    size: u64,
+    /// Seconds since the Unix epoch
+    modified_at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirstRunInfo {
+    first_run: bool,
+    found: Vec<LegacyData>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "camelCase")]
+enum Stage {
+    Stopping,
+    Copying { copied: u64, total: u64 },
+    Starting,
+    Done,
+    Failed { error: String },
+}
+
+pub struct FirstRun {
+    first_run: AtomicBool,
+    // Held while importing so two imports never interleave
+    importing: tokio::sync::Mutex<()>,
+}
+
+/// Must run before the backend starts, since it creates an empty database
+/// that would make every install look used
+pub fn init(app: &AppHandle) {
+    let first_run = match data_dir::data_dir(app) {
+        Some(dir) => detect(&dir),
+        None => false,
+    };
+    if first_run {
+        tracing::info!("First run of this install");
+    }
+    app.manage(FirstRun {
+        first_run: AtomicBool::new(first_run),
+        importing: tokio::sync::Mutex::new(()),
+    });
+}
+
+// Data from before the install file existed counts as a finished first run
+fn detect(dir: &Path) -> bool {
+    match read_install(dir) {
+        Some(install) => {
+            migrate(dir, install);
+            false
+        }
+        None if dir.join(DATABASE_FILE).exists() => {
+            migrate(dir, Install::default());
+            false
+        }
+        None => true,
+    }
+}
+
+// Brings the data folder up to `SCHEMA_VERSION`. There are no steps yet;
+// a future one goes in a `if install.schema_version < N` block above the
+// write.
+fn migrate(dir: &Path, mut install: Install) {
+    if install.schema_version == SCHEMA_VERSION {
+        return;
+    }
+    tracing::info!(
+        "Migrating data folder from version {} to {SCHEMA_VERSION}",
+        install.schema_version
+    );
+    install.schema_version = SCHEMA_VERSION;
+    if let Err(err) = write_install(dir, &install) {
+        tracing::warn!("Failed to record the data folder version: {err}");
+    }
+}
+
+fn read_install(dir: &Path) -> Option<Install> {
+    let text = fs::read_to_string(dir.join(INSTALL_FILE)).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(install) => Some(install),
+        Err(err) => {
+            tracing::warn!("Ignoring unreadable {INSTALL_FILE}: {err}");
+            Some(Install::default())
+        }
+    }
+}
+
+fn write_install(dir: &Path, install: &Install) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(install).map_err(|err| err.to_string())?;
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    fs::write(dir.join(INSTALL_FILE), text).map_err(|err| err.to_string())
+}
+
+fn finish(app: &AppHandle, imported_from: Option<PathBuf>) -> Result<(), String> {
+    let dir = data_dir::data_dir(app).ok_or("No data directory available")?;
+    write_install(
+        &dir,
+        &Install {
+            schema_version: SCHEMA_VERSION,
+            imported_from,
+        },
+    )?;
+    app.state::<FirstRun>()
+        .first_run
+        .store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Whether this is a fresh install, along with any data from an older
+/// install or the browser-based version that could be brought over
+#[tauri::command]
+pub fn get_first_run(app: AppHandle, first_run: State<'_, FirstRun>) -> FirstRunInfo {
+    let first_run = first_run.first_run.load(Ordering::SeqCst);
+    FirstRunInfo {
+        first_run,
+        found: if first_run { scan(&app) } else { Vec::new() },
+    }
+}
+
+fn scan(app: &AppHandle) -> Vec<LegacyData> {
+    let mut dirs = Vec::new();
+    if let Ok(base) = app.path().data_dir() {
+        dirs.extend(
+            PREVIOUS_INSTALL_DIRS
+                .iter()
+                .map(|dir| (base.join(dir), LegacyKind::PreviousInstall)),
+        );
+    }
+    if let Ok(home) = app.path().home_dir() {
+        dirs.extend(
+            BROWSER_DIRS
+                .iter()
+                .map(|dir| (home.join(dir), LegacyKind::Browser)),
+        );
+    }
+
+    let current =
+        data_dir::data_dir(app).and_then(|dir| dir.join(DATABASE_FILE).canonicalize().ok());
+    let mut found: Vec<LegacyData> = Vec::new();
+    for (dir, kind) in dirs {
+        let path = dir.join(DATABASE_FILE);
+        // Case-insensitive filesystems list the same folder twice
+        let Ok(path) = path.canonicalize() else {
+            continue;
+        };
+        if current.as_ref() == Some(&path)
+            || found.iter().any(|data| data.path == path)
+            || !is_database(&path)
+        {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|age| age.as_secs())
+            .unwrap_or_default();
+        found.push(LegacyData {
+            path,
+            kind,
+            size: metadata.len(),
+            modified_at,
+        });
+    }
+    found
+}
+
+fn is_database(path: &Path) -> bool {
+    let mut header = [0; SQLITE_HEADER.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| header == SQLITE_HEADER)
+}
+
+/// Copies the database at `path` (one from `get_first_run`, or any picked
+/// by the user) over the fresh one and restarts the backend on it, which
+/// brings its schema up to date. Emits `first-run-progress` as it goes.
+#[tauri::command]
+pub async fn import_legacy_data(
+    _timer: IpcTimer,
+    app: AppHandle,
+    first_run: State<'_, FirstRun>,
+    path: PathBuf,
+) -> Result<(), String> {
+    let _guard = first_run.importing.lock().await;
+    if !first_run.first_run.load(Ordering::SeqCst) {
+        return Err("Data can only be imported on the first run".to_string());
+    }
+    if app.state::<Sidecar>().is_external() {
+        return Err("The backend is not run by the app in this mode".to_string());
+    }
+    let path = match scan(&app).into_iter().find(|data| data.path == path) {
+        Some(data) => data.path,
+        None => fs_scope::check(&app, &path, Access::Read).map_err(|err| err.to_string())?,
+    };
+    if !is_database(&path) {
+        return Err(format!("{} is not a Hamba database", path.display()));
+    }
+    let dir = data_dir::data_dir(&app).ok_or("No data directory available")?;
+
+    emit(&app, Stage::Stopping);
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle)).await;
+
+    let handle = app.clone();
+    let source = path.clone();
+    let copied = tauri::async_runtime::spawn_blocking(move || copy(&handle, &source, &dir))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|result| result.map_err(|err| err.to_string()));
+
+    emit(&app, Stage::Starting);
+    sidecar::start(&app);
+    if let Err(err) = copied {
+        return Err(fail(&app, format!("Failed to copy the data: {err}")));
+    }
+    if !sidecar::wait_until_healthy(&app).await {
+        return Err(fail(
+            &app,
+            "The backend did not start on the imported data".to_string(),
+        ));
+    }
+    if let Err(err) = finish(&app, Some(path.clone())) {
+        tracing::warn!("Failed to record the import: {err}");
+    }
+    tracing::info!("Imported data from {}", path.display());
+    emit(&app, Stage::Done);
+    Ok(())
+}
+
+/// Ends the first run without importing anything
+#[tauri::command]
+pub fn skip_first_run(app: AppHandle) -> Result<(), String> {
+    finish(&app, None)
+}
+
+// The database and whichever side files it has (an uncheckpointed WAL holds
+// recent writes), replacing the fresh database. A failed copy leaves the
+// folder without a database, so the backend starts on an empty one again.
+fn copy(app: &AppHandle, source: &Path, dir: &Path) -> io::Result<()> {
+    let mut files = vec![(source.to_path_buf(), dir.join(DATABASE_FILE))];
+    for suffix in DATABASE_SIDE_FILES {
+        let target = with_suffix(&dir.join(DATABASE_FILE), suffix);
+        let _ = fs::remove_file(&target);
+        let side = with_suffix(source, suffix);
+        if side.exists() {
+            files.push((side, target));
+        }
+    }
+    let total = files
+        .iter()
+        .filter_map(|(from, _)| fs::metadata(from).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    let mut copied = 0;
+    let mut buffer = vec![0; COPY_CHUNK];
+    for (from, to) in &files {
+        let result = copy_file(from, to, &mut buffer, |read| {
+            copied += read;
+            emit(app, Stage::Copying { copied, total });
+        });
+        if let Err(err) = result {
+            for (_, to) in &files {
+                let _ = fs::remove_file(to);
+            }
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    buffer: &mut [u8],
+    mut progress: impl FnMut(u64),
+) -> io::Result<()> {
+    let mut reader = File::open(from)?;
+    let mut writer = File::create(to)?;
+    loop {
+        let read = reader.read(buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        progress(read as u64);
+    }
+    writer.sync_all()
+}
+
+fn fail(app: &AppHandle, error: String) -> String {
+    tracing::error!("{error}");
+    emit(
+        app,
+        Stage::Failed {
+            error: error.clone(),
+        },
+    );
+    error
+}
+
+fn emit(app: &AppHandle, stage: Stage) {
+    let _ = app.emit("first-run-progress", stage);
+}
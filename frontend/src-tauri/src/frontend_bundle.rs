@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, State, UriSchemeContext, UriSchemeResponder, WebviewUrl, Wry};
+
+use crate::data_dir;
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+use crate::safe_mode;
+
+/// Release builds load the frontend from `app://localhost` (on Windows
+/// `http://app.localhost`) so a downloaded bundle can stand in for the one
+/// built into the app
+pub const SCHEME: &str = "app";
+const BUNDLES_DIR: &str = "frontend-bundles";
+// The key bundles are signed with, as `tauri signer generate` prints it.
+// Release builds set it; a build without one can't install bundles.
+const PUBLIC_KEY: Option<&str> = option_env!("FRONTEND_BUNDLE_PUBKEY");
+// Holds the version of the bundle in use, if not the built-in one
+const ACTIVE_FILE: &str = "active";
+const MANIFEST_FILE: &str = "bundle.json";
+const INDEX: &str = "index.html";
+// SvelteKit names these after their contents, so they never change
+const IMMUTABLE_PREFIX: &str = "_app/immutable/";
+// Bundles may ship files compressed only, as `<name>.br` or `<name>.gz`.
+// They are decompressed here, as not every webview decodes a
+// `Content-Encoding` on a custom protocol.
+const BROTLI_SUFFIX: &str = ".br";
+const GZIP_SUFFIX: &str = ".gz";
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("css", "text/css"),
+    ("json", "application/json"),
+    ("webmanifest", "application/manifest+json"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("wasm", "application/wasm"),
+    ("txt", "text/plain"),
+];
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// `bundle.json` at the root of a bundle
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: String,
+    /// The app version it was built against. A bundle only loads in that
+    /// version, as the commands it calls may differ in any other.
+    app_version: String,
+}
+
+#[derive(Clone)]
+struct Bundle {
+    version: String,
+    dir: PathBuf,
+}
+
+struct Asset {
+    bytes: Vec<u8>,
+    mime_type: String,
+    etag: String,
+}
+
+/// The downloaded bundle in use, or `None` for the built-in one
+pub struct FrontendBundle {
+    active: RwLock<Option<Bundle>>,
+    // Each file is read, decompressed and hashed once per bundle
+    cache: Mutex<HashMap<String, Arc<Asset>>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendBundleInfo {
+    /// `None` while the bundle built into the app is in use
+    version: Option<String>,
+    app_version: String,
+}
+
+pub fn init(app: &AppHandle) {
+    let active = if safe_mode::is_active(app) {
+        None
+    } else {
+        load_active(app)
+    };
+    if let Some(bundle) = &active {
+        tracing::info!("Using frontend bundle {}", bundle.version);
+    }
+    app.manage(FrontendBundle {
+        active: RwLock::new(active),
+        cache: Mutex::new(HashMap::new()),
+    });
+}
+
+/// Where a window shows `route`. In development that stays the dev server.
+/// A route that would leave the app, see `resolve`, shows the start page.
+pub fn url(route: &str) -> WebviewUrl {
+    let route = route.trim_start_matches('/');
+    if tauri::is_dev() {
+        return WebviewUrl::App(route.into());
+    }
+    match resolve(route) {
+        Some(url) => WebviewUrl::CustomProtocol(url),
+        None => {
+            tracing::warn!("Not loading {route:?}, which resolves outside the app");
+            WebviewUrl::App(PathBuf::new())
+        }
+    }
+}
+
+/// `route` joined onto the app's own origin, `None` if the result lands
+/// anywhere else, e.g. `/\evil.com` on Windows where `\` counts as `/`
+pub fn resolve(route: &str) -> Option<tauri::Url> {
+    let base = if cfg!(windows) {
+        format!("http://{SCHEME}.localhost/")
+    } else {
+        format!("{SCHEME}://localhost/")
+    };
+    join_within(&tauri::Url::parse(&base).ok()?, route)
+}
+
+fn join_within(base: &tauri::Url, route: &str) -> Option<tauri::Url> {
+    let url = base.join(route.trim_start_matches('/')).ok()?;
+    // `origin()` is opaque, and never equal, for custom schemes
+    let same = url.scheme() == base.scheme()
+        && url.host_str() == base.host_str()
+        && url.port() == base.port();
+    same.then_some(url)
+}
+
+fn bundles_dir(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::data_dir(app).map(|dir| dir.join(BUNDLES_DIR))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let contents = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|err| format!("The bundle has no {MANIFEST_FILE}: {err}"))?;
+    serde_json::from_str(&contents).map_err(|err| format!("Invalid {MANIFEST_FILE}: {err}"))
+}
+
+fn load_active(app: &AppHandle) -> Option<Bundle> {
+    let dir = bundles_dir(app)?;
+    let version = fs::read_to_string(dir.join(ACTIVE_FILE)).ok()?;
+    let dir = dir.join(version.trim());
+    let manifest = match read_manifest(&dir) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            tracing::warn!("Ignoring frontend bundle {}: {err}", version.trim());
+            return None;
+        }
+    };
+    let app_version = app.package_info().version.to_string();
+    if manifest.app_version != app_version {
+        tracing::info!(
+            "Frontend bundle {} is for app version {}, not {app_version}",
+            manifest.version,
+            manifest.app_version
+        );
+        return None;
+    }
+    Some(Bundle {
+        version: manifest.version,
+        dir,
+    })
+}
+
+fn validate_version(version: &str) -> Result<(), String> {
+    let valid = !version.is_empty()
+        && !version.starts_with('.')
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'));
+    if !valid {
+        return Err(format!("Invalid bundle version {version:?}"));
+    }
+    Ok(())
+}
+
+fn info(app: &AppHandle, state: &FrontendBundle) -> FrontendBundleInfo {
+    FrontendBundleInfo {
+        version: state
+            .active
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|bundle| bundle.version.clone()),
+        app_version: app.package_info().version.to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_frontend_bundle(app: AppHandle, state: State<'_, FrontendBundle>) -> FrontendBundleInfo {
+    info(&app, &state)
+}
+
+/// Unpacks the zip at `path` as the bundle to use from the next
+/// `reload_frontend_bundle` or launch on. `signature` is the zip's `.sig`
+/// from `tauri signer sign`, made with the key built into the app.
+#[tauri::command]
+pub async fn install_frontend_bundle(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: PathBuf,
+    signature: String,
+) -> Result<String, String> {
+    let public_key = PUBLIC_KEY.ok_or("This build has no key to check frontend bundles with")?;
+    let path = fs_scope::check(&app, &path, Access::Read).map_err(|err| err.to_string())?;
+    let dir = bundles_dir(&app).ok_or("No data directory available")?;
+    let app_version = app.package_info().version.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = fs::read(&path).map_err(|err| err.to_string())?;
+        verify(public_key, &bytes, &signature)?;
+        install(&dir, bytes, &app_version)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+// Both come base64 encoded, and legacy signatures pass, as with the updater
+fn verify(public_key: &str, bytes: &[u8], signature: &str) -> Result<(), String> {
+    let decode = |encoded: &str| {
+        BASE64
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+    };
+    let public_key = decode(public_key)
+        .and_then(|key| PublicKey::decode(&key).ok())
+        .ok_or("The built-in bundle key is invalid")?;
+    let signature = decode(signature)
+        .and_then(|signature| Signature::decode(&signature).ok())
+        .ok_or("Invalid bundle signature")?;
+    public_key
+        .verify(bytes, &signature, true)
+        .map_err(|_| "The bundle isn't signed with the app's key".to_string())
+}
+
+// Unpacks from the bytes that were verified, not the file, which could have
+// changed since
+fn install(dir: &Path, bytes: Vec<u8>, app_version: &str) -> Result<String, String> {
+    let mut random = [0u8; 8];
+    getrandom::fill(&mut random).map_err(|err| err.to_string())?;
+    let partial = dir.join(format!(".partial-{}", hex::encode(random)));
+    let result = (|| {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+        archive.extract(&partial).map_err(|err| err.to_string())?;
+
+        let manifest = read_manifest(&partial)?;
+        validate_version(&manifest.version)?;
+        if manifest.app_version != app_version {
+            return Err(format!(
+                "The bundle is for app version {}, not {app_version}",
+                manifest.app_version
+            ));
+        }
+        if !partial.join(INDEX).exists()
+            && !partial.join(format!("{INDEX}{BROTLI_SUFFIX}")).exists()
+            && !partial.join(format!("{INDEX}{GZIP_SUFFIX}")).exists()
+        {
+            return Err(format!("The bundle has no {INDEX}"));
+        }
+
+        let target = dir.join(&manifest.version);
+        let _ = fs::remove_dir_all(&target);
+        fs::rename(&partial, &target).map_err(|err| err.to_string())?;
+        fs::write(dir.join(ACTIVE_FILE), &manifest.version).map_err(|err| err.to_string())?;
+        Ok(manifest.version)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&partial);
+    }
+    let version = result?;
+
+    // Only the new bundle is kept; the built-in one is always there to go
+    // back to
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() && entry.file_name() != version.as_str() {
+                let _ = fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+    tracing::info!("Installed frontend bundle {version}");
+    Ok(version)
+}
+
+/// Switches every window to whichever bundle is now set to be used and
+/// reloads them
+#[tauri::command]
+pub fn reload_frontend_bundle(
+    app: AppHandle,
+    state: State<'_, FrontendBundle>,
+) -> Result<FrontendBundleInfo, String> {
+    safe_mode::refuse(&app)?;
+    *state.active.write().unwrap() = load_active(&app);
+    state.cache.lock().unwrap().clear();
+    for window in app.webview_windows().values() {
+        let ours = window
+            .url()
+            .map(|url| {
+                url.scheme() == SCHEME || url.host_str() == Some(&format!("{SCHEME}.localhost"))
+            })
+            .unwrap_or(false);
+        if ours {
+            let _ = window.eval("location.reload()");
+        }
+    }
+    Ok(info(&app, &state))
+}
+
+/// Goes back to the bundle built into the app from the next reload on
+#[tauri::command]
+pub fn remove_frontend_bundle(app: AppHandle) -> Result<(), String> {
+    let dir = bundles_dir(&app).ok_or("No data directory available")?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// Serves the frontend with strong ETags, so unchanged files come back as
+/// `304 Not Modified`
+pub fn handle(
+    context: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = context.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = serve(&app, &request).unwrap_or_else(|(status, message)| {
+            Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .body(message.into_bytes())
+                .unwrap_or_default()
+        });
+        responder.respond(response);
+    });
+}
+
+type Failure = (StatusCode, String);
+
+fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Failure> {
+    let mut path = request.uri().path().trim_start_matches('/').to_string();
+    if path.is_empty() || path.ends_with('/') {
+        path.push_str(INDEX);
+    }
+    let asset = asset(app, &path)?;
+
+    let cache_control = if path.starts_with(IMMUTABLE_PREFIX) {
+        "max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    let matches = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == asset.etag));
+    let builder = Response::builder()
+        .header(header::ETAG, &asset.etag)
+        .header(header::CACHE_CONTROL, cache_control);
+    let response = if matches {
+        builder.status(StatusCode::NOT_MODIFIED).body(Vec::new())
+    } else {
+        builder
+            .header(header::CONTENT_TYPE, &asset.mime_type)
+            .body(asset.bytes.clone())
+    };
+    response.map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+fn asset(app: &AppHandle, path: &str) -> Result<Arc<Asset>, Failure> {
+    let state = app.state::<FrontendBundle>();
+    let bundle = state.active.read().unwrap().clone();
+    let key = match &bundle {
+        Some(bundle) => format!("{}:{path}", bundle.version),
+        None => format!(":{path}"),
+    };
+    if let Some(asset) = state.cache.lock().unwrap().get(&key) {
+        return Ok(asset.clone());
+    }
+
+    let (bytes, mime_type) = match &bundle {
+        Some(bundle) => read_from_bundle(&bundle.dir, path)?,
+        None => app
+            .asset_resolver()
+            .get(path.to_string())
+            .map(|asset| (asset.bytes, asset.mime_type))
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No asset {path:?}")))?,
+    };
+    let etag = format!("\"{}\"", hex::encode(Sha256::digest(&bytes)));
+    let asset = Arc::new(Asset {
+        bytes,
+        mime_type,
+        etag,
+    });
+    state.cache.lock().unwrap().insert(key, asset.clone());
+    Ok(asset)
+}
+
+// Falls back the way the built-in protocol does, ending at `index.html`
+// for the single-page app's own routes
+fn read_from_bundle(dir: &Path, path: &str) -> Result<(Vec<u8>, String), Failure> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid path {path:?}")));
+    }
+    let candidates = [
+        path.to_string(),
+        format!("{path}.html"),
+        format!("{path}/{INDEX}"),
+        INDEX.to_string(),
+    ];
+    for candidate in candidates {
+        let internal = |err: io::Error| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+        if let Some(bytes) = read_file(&dir.join(&candidate)).map_err(internal)? {
+            return Ok((bytes, mime_type(&candidate).to_string()));
+        }
+    }
+    Err((StatusCode::NOT_FOUND, format!("No asset {path:?}")))
+}
+
+fn read_file(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let with_suffix = |suffix: &str| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    let mut bytes = Vec::new();
+    if path.is_file() {
+        File::open(path)?.read_to_end(&mut bytes)?;
+    } else if let Ok(file) = File::open(with_suffix(BROTLI_SUFFIX)) {
+        brotli_decompressor::Decompressor::new(file, 4096).read_to_end(&mut bytes)?;
+    } else if let Ok(file) = File::open(with_suffix(GZIP_SUFFIX)) {
+        flate2::read::GzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else {
+        return Ok(None);
+    }
+    Ok(Some(bytes))
+}
+
+fn mime_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or(DEFAULT_MIME_TYPE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A throwaway key and its signature over `b"bundle"`
+    const TEST_KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDk1OEE0ODdEOUQ2NjNGMEEKUldRS1AyYWRmVWlLbFllWlNKQy9VVDBIVkF3L3FBZHYycEY3SVd4VjJZcGpEVjArTWUvMUhaalgK";
+    const TEST_SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVRS1AyYWRmVWlLbFhobERVb0dFbFVEVG5aWlcxYUI5aTZjUUV0azJ1ZkZzQ0dUZUdHR1FuaGRwbExUOUFCc0JWRnk0VTdSek9leWE4ZFZaRStqRWh5dmFrM0ZoQ2dOaXdzPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzAwMDAwMDAwCWZpbGU6YnVuZGxlLnppcApGc2VaN2kwa3hmMXVpZHpjSHFoNHQrOHdnUnBhS0pEU0tpR2JuMHRGbVM4Q0xqMGYwREthT0RhNUhuNDQ4T05TYmtadnRvd3Y1c0lwWFNld2lrVGtCUT09Cg==";
+
+    #[test]
+    fn signed_bundles_verify() {
+        assert!(verify(TEST_KEY, b"bundle", TEST_SIGNATURE).is_ok());
+    }
+
+    #[test]
+    fn changed_or_unsigned_bundles_dont() {
+        assert!(verify(TEST_KEY, b"bundle!", TEST_SIGNATURE).is_err());
+        assert!(verify(TEST_KEY, b"bundle", "").is_err());
+        assert!(verify(TEST_KEY, b"bundle", "not base64").is_err());
+        assert!(verify("", b"bundle", TEST_SIGNATURE).is_err());
+    }
+
+    #[test]
+    fn routes_stay_on_the_app_origin() {
+        for base in ["http://app.localhost/", "app://localhost/"] {
+            let base = tauri::Url::parse(base).unwrap();
+            let url = join_within(&base, "/email/1?x=1").unwrap();
+            assert_eq!(url.path(), "/email/1");
+            assert!(join_within(&base, "https://evil.com/").is_none());
+        }
+        let windows = tauri::Url::parse("http://app.localhost/").unwrap();
+        assert!(join_within(&windows, "/\\\\evil.com/x").is_none());
+    }
+
+    #[test]
+    fn versions() {
+        for version in ["1.2.3", "2024-06-01", "1.0.0-beta.1+build_7"] {
+            assert!(validate_version(version).is_ok(), "{version:?}");
+        }
+        for version in ["", ".", "..", ".hidden", "../1", "1/2", "1\\2", "1 2"] {
+            assert!(validate_version(version).is_err(), "{version:?}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::command::{CommandError, CommandResult, ErrorKind};
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+
+const GRANTS_FILE: &str = "fs_grants.json";
+// Every picked file is a grant, so the oldest go past this
+const MAX_GRANTS: usize = 200;
+
+/// Ordered so that a grant covers any access up to its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Access {
+    Read,
+    /// Reading included
+    Write,
+}
+
+/// A file or folder the user picked, dropped or chose to save to, which the
+/// frontend may then hand to file commands
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsGrant {
+    /// Resolved, with symlinks followed
+    path: PathBuf,
+    /// Covers everything inside it too
+    folder: bool,
+    access: Access,
+    /// Seconds since the Unix epoch
+    granted_at: u64,
+}
+
+impl FsGrant {
+    fn covers(&self, path: &Path, access: Access) -> bool {
+        self.access >= access
+            && (self.path == path || (self.folder && path.starts_with(&self.path)))
+    }
+}
+
+/// The grants, kept in `fs_grants.json` in the config directory until the
+/// user revokes them
+pub struct FsScope {
+    path: Option<PathBuf>,
+    grants: Mutex<Vec<FsGrant>>,
+}
+
+impl FsScope {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let grants = self.grants.lock().unwrap().clone();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&grants)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(GRANTS_FILE));
+    let grants: Vec<FsGrant> = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(grants) => Some(grants),
+            Err(err) => {
+                tracing::warn!("Ignoring an invalid {GRANTS_FILE}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default();
+    app.manage(FsScope {
+        path,
+        grants: Mutex::new(grants),
+    });
+}
+
+// Symlinks and `..` are resolved first, so neither can step outside a grant.
+// A file that doesn't exist yet, like a save target, resolves through its
+// folder.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    if !path.is_absolute() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not an absolute path", path.display()),
+        ));
+    }
+    match fs::canonicalize(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => Ok(fs::canonicalize(parent)?.join(name)),
+                _ => Err(err),
+            }
+        }
+        resolved => resolved,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Lets the frontend use `path` from now on, for files and folders the user
+/// chose through the OS. Granting a path again keeps the wider access.
+pub fn grant(app: &AppHandle, path: &Path, access: Access) -> Option<FsGrant> {
+    let resolved = match resolve(path) {
+        Ok(resolved) => resolved,
+        Err(err) => {
+            tracing::warn!("Not granting access to {}: {err}", path.display());
+            return None;
+        }
+    };
+    let scope = app.state::<FsScope>();
+    let grant = {
+        let mut grants = scope.grants.lock().unwrap();
+        let previous = grants
+            .iter()
+            .position(|grant| grant.path == resolved)
+            .map(|index| grants.remove(index));
+        let grant = FsGrant {
+            folder: resolved.is_dir(),
+            access: previous.map_or(access, |previous| previous.access.max(access)),
+            path: resolved,
+            granted_at: now(),
+        };
+        grants.push(grant.clone());
+        let excess = grants.len().saturating_sub(MAX_GRANTS);
+        grants.drain(..excess);
+        grant
+    };
+    if let Err(err) = scope.save() {
+        tracing::warn!("Failed to save {GRANTS_FILE}: {err}");
+    }
+    Some(grant)
+}
+
+/// The resolved `path`, to use in its place, if a grant allows `access` to
+/// it. Every file command taking a path from the frontend goes through this.
+pub fn check(app: &AppHandle, path: &Path, access: Access) -> CommandResult<PathBuf> {
+    let resolved = resolve(path).map_err(|err| CommandError::invalid("path", err.to_string()))?;
+    let allowed = app
+        .state::<FsScope>()
+        .grants
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|grant| grant.covers(&resolved, access));
+    if !allowed {
+        tracing::warn!(
+            "Refused {access:?} access to {}, which was never granted",
+            resolved.display()
+        );
+        return Err(CommandError::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "Hamba hasn't been given access to {}; pick it in a file dialog first",
+                path.display()
+            ),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Shows a native folder picker and grants `access` to the folder picked.
+/// `None` means the user cancelled.
+#[tauri::command]
+pub async fn request_fs_access(
+    _timer: IpcTimer,
+    app: AppHandle,
+    title: Option<String>,
+    access: Access,
+) -> CommandResult<Option<FsGrant>> {
+    let mut builder = app.dialog().file();
+    if let Some(title) = title {
+        builder = builder.set_title(title);
+    }
+    let picked = tauri::async_runtime::spawn_blocking(move || builder.blocking_pick_folder())
+        .await
+        .map_err(|err| err.to_string())?;
+    let Some(folder) = picked else {
+        return Ok(None);
+    };
+    let folder = folder.into_path().map_err(|err| err.to_string())?;
+    grant(&app, &folder, access)
+        .map(Some)
+        .ok_or_else(|| format!("Couldn't grant access to {}", folder.display()).into())
+}
+
+/// Newest first
+#[tauri::command]
+pub fn list_fs_grants(scope: State<'_, FsScope>) -> Vec<FsGrant> {
+    let mut grants = scope.grants.lock().unwrap().clone();
+    grants.reverse();
+    grants
+}
+
+/// Takes back the grant for exactly `path`, as `list_fs_grants` has it
+#[tauri::command]
+pub fn revoke_fs_grant(scope: State<'_, FsScope>, path: PathBuf) -> CommandResult<Vec<FsGrant>> {
+    {
+        let mut grants = scope.grants.lock().unwrap();
+        let before = grants.len();
+        grants.retain(|grant| grant.path != path);
+        if grants.len() == before {
+            return Err(CommandError::not_found(format!(
+                "Nothing was granted for {}",
+                path.display()
+            )));
+        }
+    }
+    scope.save()?;
+    tracing::info!("Revoked access to {}", path.display());
+    Ok(list_fs_grants(scope))
+}
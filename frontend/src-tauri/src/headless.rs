@@ -0,0 +1,33 @@
+use tauri::{AppHandle, Manager};
+
+use crate::sidecar::{self, Sidecar};
+use crate::startup;
+
+/// Runs just the backend supervisor, without any window, until interrupted.
+/// The backend listens on all interfaces, so it can be reached from other
+/// machines with the printed port and token.
+pub fn run(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let healthy = sidecar::wait_until_healthy(&app).await;
+        startup::finish(&app);
+        if healthy {
+            let sidecar = app.state::<Sidecar>();
+            // Printed rather than logged so scripts can read it from stdout
+            println!("Backend listening on {}", sidecar.url());
+            if let Some(token) = sidecar.token() {
+                println!("Token: {token}");
+            }
+        } else {
+            eprintln!("Backend did not become healthy, still waiting for it");
+        }
+
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {err}");
+            return;
+        }
+        tracing::info!("Interrupted, shutting down");
+        // Stopping the backend happens on exit like it does for the windowed app
+        app.exit(0);
+    });
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+use tokio::sync::Semaphore;
+
+use crate::data_dir;
+use crate::logging;
+use crate::safe_mode;
+use crate::settings::SettingsStore;
+
+const HOOKS_DIR: &str = "hooks";
+// Everything the scripts print goes here, in the log folder
+const LOG_FILE: &str = "hooks.log";
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// A burst of new mail shouldn't start a script per message at once
+const MAX_RUNNING: usize = 4;
+
+/// The events scripts can hook into. A script runs for an event when its
+/// name without the extension is the event's, e.g. `on-item-created.sh`.
+pub const EVENTS: &[&str] = &[
+    "on-backup-complete",
+    "on-backup-failed",
+    "on-item-created",
+    "on-sync-complete",
+];
+
+// All a script gets from the app's environment, so secrets the app was
+// started with or sets for the backend don't leak to it
+const PASSED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+];
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookRun {
+    /// Seconds since the Unix epoch
+    at: u64,
+    /// `None` when it couldn't start or was killed
+    code: Option<i32>,
+    timed_out: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookScript {
+    path: PathBuf,
+    last_run: Option<HookRun>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookEvent {
+    name: &'static str,
+    scripts: Vec<HookScript>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookList {
+    dir: Option<PathBuf>,
+    events: Vec<HookEvent>,
+}
+
+/// Runs the user's scripts in the `hooks` folder when things happen in the
+/// app, with the event as JSON on stdin. Scripts run one after another per
+/// event, with a cleaned up environment and are killed after `TIMEOUT`.
+pub struct Hooks {
+    running: Semaphore,
+    last: Mutex<HashMap<PathBuf, HookRun>>,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Hooks {
+        running: Semaphore::new(MAX_RUNNING),
+        last: Mutex::default(),
+    });
+}
+
+fn dir(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::config_dir(app).map(|dir| dir.join(HOOKS_DIR))
+}
+
+fn scripts(dir: &Path, event: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.file_stem().is_some_and(|stem| stem == event)
+                && platform::is_runnable(path)
+        })
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Runs the scripts for `event`, if any, with `payload` on stdin. Returns
+/// right away; nothing runs in safe mode or with hooks turned off.
+pub fn fire(app: &AppHandle, event: &'static str, payload: &impl Serialize) {
+    debug_assert!(EVENTS.contains(&event));
+    if !app.state::<SettingsStore>().get().hooks || safe_mode::is_active(app) {
+        return;
+    }
+    let Some(dir) = dir(app) else {
+        return;
+    };
+    let input = match serde_json::to_vec(payload) {
+        Ok(input) => input,
+        Err(err) => {
+            tracing::warn!("Failed to serialize the {event} payload: {err}");
+            return;
+        }
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let hooks = app.state::<Hooks>();
+        let Ok(_permit) = hooks.running.acquire().await else {
+            return;
+        };
+        let log = logging::dir().map(|dir| dir.join(LOG_FILE));
+        let handle = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || {
+            for script in scripts(&dir, event) {
+                let run = run(&dir, &script, event, &input, log.as_deref());
+                handle
+                    .state::<Hooks>()
+                    .last
+                    .lock()
+                    .unwrap()
+                    .insert(script, run);
+            }
+        })
+        .await;
+    });
+}
+
+/// Fires `on-item-created` for new mail and `on-sync-complete`, passing the
+/// backend's message as it came
+pub fn on_backend_message(app: &AppHandle, message: &Map<String, Value>) {
+    match message.get("type").and_then(Value::as_str) {
+        Some("new_mail") => fire(app, "on-item-created", message),
+        Some("sync_complete") => fire(app, "on-sync-complete", message),
+        _ => {}
+    }
+}
+
+fn open_log(path: &Path) -> std::io::Result<File> {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        let _ = fs::rename(path, path.with_extension("log.1"));
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn run(dir: &Path, script: &Path, event: &str, input: &[u8], log: Option<&Path>) -> HookRun {
+    let mut run = HookRun {
+        at: now(),
+        code: None,
+        timed_out: false,
+        error: None,
+    };
+    // Output goes straight to the file, so a script that leaves something
+    // running in the background can't hold up anything here
+    let output = match log.map(open_log).transpose() {
+        Ok(Some(mut file)) => {
+            let _ = writeln!(file, "--- {event}: {} at {}", script.display(), run.at);
+            file.try_clone()
+                .map(|copy| (Stdio::from(file), Stdio::from(copy)))
+        }
+        Ok(None) => Ok((Stdio::null(), Stdio::null())),
+        Err(err) => Err(err),
+    };
+    let (stdout, stderr) = output.unwrap_or_else(|err| {
+        tracing::debug!("Not logging hook output: {err}");
+        (Stdio::null(), Stdio::null())
+    });
+
+    let mut command = platform::command(script);
+    command
+        .current_dir(dir)
+        .env_clear()
+        .envs(
+            PASSED_ENV
+                .iter()
+                .filter_map(|name| Some((name, env::var_os(name)?))),
+        )
+        .env("HAMBA_HOOK", event)
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(stderr);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            tracing::warn!("Failed to run hook {}: {err}", script.display());
+            run.error = Some(err.to_string());
+            return run;
+        }
+    };
+    // Written from another thread in case the script never reads it
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = input.to_vec();
+        thread::spawn(move || {
+            let _ = stdin.write_all(&input);
+        });
+    }
+
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                run.code = status.code();
+                break;
+            }
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                run.timed_out = true;
+                break;
+            }
+            Err(err) => {
+                run.error = Some(err.to_string());
+                break;
+            }
+        }
+    }
+
+    if run.timed_out {
+        tracing::warn!(
+            "Hook {} took longer than {TIMEOUT:?} and was killed",
+            script.display()
+        );
+    } else if run.code != Some(0) {
+        tracing::warn!(
+            "Hook {} exited with {:?}, see {LOG_FILE}",
+            script.display(),
+            run.code
+        );
+    } else {
+        tracing::debug!("Hook {} done", script.display());
+    }
+    run
+}
+
+/// Every event with the scripts found for it and how each last went
+#[tauri::command]
+pub fn list_hooks(app: AppHandle, hooks: State<'_, Hooks>) -> HookList {
+    let dir = dir(&app);
+    let last = hooks.last.lock().unwrap();
+    let events = EVENTS
+        .iter()
+        .map(|&name| HookEvent {
+            name,
+            scripts: dir
+                .as_deref()
+                .map(|dir| scripts(dir, name))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|path| HookScript {
+                    last_run: last.get(&path).cloned(),
+                    path,
+                })
+                .collect(),
+        })
+        .collect();
+    HookList { dir, events }
+}
+
+/// Opens the hooks folder, creating it first
+#[tauri::command]
+pub fn open_hooks_folder(app: AppHandle) -> Result<(), String> {
+    let dir = dir(&app).ok_or("No config directory available")?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    // Run directly, so the shebang picks the interpreter
+    pub fn is_runnable(path: &Path) -> bool {
+        path.metadata()
+            .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    pub fn command(script: &Path) -> Command {
+        Command::new(script)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+
+    fn extension(path: &Path) -> String {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default()
+    }
+
+    pub fn is_runnable(path: &Path) -> bool {
+        matches!(extension(path).as_str(), "exe" | "cmd" | "bat" | "ps1")
+    }
+
+    pub fn command(script: &Path) -> Command {
+        let mut command = match extension(script).as_str() {
+            "ps1" => {
+                let mut command = Command::new("powershell");
+                command
+                    .args([
+                        "-NoProfile",
+                        "-NonInteractive",
+                        "-ExecutionPolicy",
+                        "Bypass",
+                        "-File",
+                    ])
+                    .arg(script);
+                command
+            }
+            "cmd" | "bat" => {
+                let mut command = Command::new("cmd");
+                command.arg("/C").arg(script);
+                command
+            }
+            _ => Command::new(script),
+        };
+        command.creation_flags(CREATE_NO_WINDOW);
+        command
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{redirect, Method, Url};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::metrics::IpcTimer;
+use crate::proxy;
+use crate::secrets;
+use crate::settings::SettingsStore;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_REDIRECTS: usize = 5;
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+// Cookies stay with the webview; requests made here neither send nor keep
+// any, and the host is always the URL's
+const FORBIDDEN_HEADERS: &[&str] = &["cookie", "host"];
+const DROPPED_RESPONSE_HEADERS: &[&str] = &["set-cookie", "set-cookie2"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchRequest {
+    url: String,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    /// Headers whose values are read from the keychain, by the key given to
+    /// `secret_set`, so tokens for integrations never reach the frontend.
+    /// The shell's own secrets can't be named.
+    #[serde(default)]
+    secret_headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchResponse {
+    status: u16,
+    /// Lowercased names; repeated headers come once per value
+    headers: Vec<(String, String)>,
+    /// Decoded as UTF-8, with anything invalid replaced
+    body: String,
+    /// Where the request ended up after redirects
+    url: String,
+}
+
+/// Whether `host` matches an entry of `http_allowed_hosts`. `*.example.com`
+/// matches its subdomains but not `example.com` itself.
+fn allowed(allowed_hosts: &[String], host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    allowed_hosts.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+            None => host == pattern,
+        }
+    })
+}
+
+/// Checks an `http_allowed_hosts` entry: a host name, optionally starting
+/// with `*.` for its subdomains
+pub fn validate_host(pattern: &str) -> Result<(), String> {
+    let host = pattern.strip_prefix("*.").unwrap_or(pattern);
+    let valid = host.contains('.')
+        && host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        return Err(format!(
+            "{pattern:?} is not a host like hooks.example.com or *.example.com"
+        ));
+    }
+    Ok(())
+}
+
+fn check_url(allowed_hosts: &[String], url: &Url) -> Result<(), String> {
+    if url.scheme() != "https" {
+        return Err(format!("Only https URLs can be fetched, not {url}"));
+    }
+    let host = url.host_str().ok_or("The URL has no host")?;
+    if !allowed(allowed_hosts, host) {
+        return Err(format!("{host} is not in the hosts allowed in settings"));
+    }
+    Ok(())
+}
+
+fn headers(request: &FetchRequest) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    let secret_values = request
+        .secret_headers
+        .iter()
+        .map(|(name, key)| {
+            let value = secrets::get_frontend(key)?.ok_or_else(|| format!("No secret {key:?}"))?;
+            Ok((name, value))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let plain = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name, value.clone()));
+    for (name, value) in plain.chain(secret_values) {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name {name:?}"))?;
+        if FORBIDDEN_HEADERS.contains(&name.as_str()) {
+            return Err(format!("The {name} header can't be set"));
+        }
+        let mut value =
+            HeaderValue::from_str(&value).map_err(|_| format!("Invalid value for {name}"))?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Makes a request to a third-party service, e.g. a webhook, from Rust so
+/// CORS doesn't apply and credentials can come from the keychain. Only
+/// https URLs on `http_allowed_hosts` can be reached, redirects included.
+#[tauri::command]
+pub async fn http_fetch(
+    _timer: IpcTimer,
+    app: AppHandle,
+    request: FetchRequest,
+) -> Result<FetchResponse, String> {
+    let allowed_hosts = app.state::<SettingsStore>().get().http_allowed_hosts;
+    let url = Url::parse(&request.url).map_err(|err| format!("Invalid URL: {err}"))?;
+    check_url(&allowed_hosts, &url)?;
+    let method = request
+        .method
+        .as_deref()
+        .map(|method| Method::from_bytes(method.to_ascii_uppercase().as_bytes()))
+        .transpose()
+        .map_err(|_| "Invalid method".to_string())?
+        .unwrap_or(Method::GET);
+    let timeout = request
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_TIMEOUT)
+        .min(MAX_TIMEOUT);
+
+    let redirect_hosts = allowed_hosts.clone();
+    let policy = redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("Too many redirects");
+        }
+        match check_url(&redirect_hosts, attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(err) => attempt.error(err),
+        }
+    });
+    let client = proxy::builder(&proxy::resolve(&app))?
+        .redirect(policy)
+        .timeout(timeout)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let mut builder = client.request(method, url).headers(headers(&request)?);
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+    let mut response = builder.send().await.map_err(|err| err.to_string())?;
+
+    let status = response.status().as_u16();
+    let final_url = response.url().to_string();
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !DROPPED_RESPONSE_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+            return Err(format!(
+                "The response is larger than {} MB",
+                MAX_RESPONSE_BYTES / 1024 / 1024
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(FetchResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        url: final_url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(hosts: &[&str]) -> Vec<String> {
+        hosts.iter().map(|host| host.to_string()).collect()
+    }
+
+    #[test]
+    fn exact_hosts_match_case_insensitively() {
+        let hosts = hosts(&["hooks.example.com"]);
+        assert!(allowed(&hosts, "hooks.example.com"));
+        assert!(allowed(&hosts, "HOOKS.Example.com"));
+        assert!(!allowed(&hosts, "example.com"));
+        assert!(!allowed(&hosts, "evilhooks.example.com"));
+        assert!(!allowed(&hosts, "hooks.example.com.evil.net"));
+    }
+
+    #[test]
+    fn wildcards_match_subdomains_only() {
+        let hosts = hosts(&["*.example.com"]);
+        assert!(allowed(&hosts, "hooks.example.com"));
+        assert!(allowed(&hosts, "a.b.example.com"));
+        assert!(!allowed(&hosts, "example.com"));
+        assert!(!allowed(&hosts, ".example.com"));
+        assert!(!allowed(&hosts, "evilexample.com"));
+    }
+
+    #[test]
+    fn nothing_is_allowed_by_default() {
+        assert!(!allowed(&[], "example.com"));
+    }
+
+    #[test]
+    fn host_patterns() {
+        assert!(validate_host("hooks.example.com").is_ok());
+        assert!(validate_host("*.example.com").is_ok());
+        assert!(validate_host("localhost").is_err());
+        assert!(validate_host("*.com.").is_err());
+        assert!(validate_host("hooks.*.com").is_err());
+        assert!(validate_host("https://example.com").is_err());
+    }
+
+    #[test]
+    fn only_https_urls_on_allowed_hosts() {
+        let hosts = hosts(&["example.com"]);
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(check_url(&hosts, &url("https://example.com/hook")).is_ok());
+        assert!(check_url(&hosts, &url("http://example.com/hook")).is_err());
+        assert!(check_url(&hosts, &url("https://other.com/hook")).is_err());
+    }
+}
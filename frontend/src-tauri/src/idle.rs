@@ -0,0 +1,170 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::lock;
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserIdle {
+    idle_seconds: u64,
+}
+
+/// Whether the user has been away past `idle_minutes`
+#[derive(Default)]
+pub struct Idle(Mutex<bool>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Idle::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let settings = app.state::<SettingsStore>().get();
+            let threshold = settings.idle_minutes * 60;
+            // Turned off counts as always active
+            let seconds = if threshold == 0 {
+                Some(0)
+            } else {
+                platform::idle_seconds().await
+            };
+            let Some(seconds) = seconds else {
+                continue;
+            };
+
+            let (went_idle, came_back) = {
+                let idle = app.state::<Idle>();
+                let now_idle = threshold > 0 && seconds >= threshold;
+                let changed = std::mem::replace(&mut *idle.0.lock().unwrap(), now_idle) != now_idle;
+                (changed && now_idle, changed && !now_idle)
+            };
+            if went_idle {
+                tracing::info!("User idle for {seconds}s");
+                let _ = app.emit(
+                    "user-idle",
+                    UserIdle {
+                        idle_seconds: seconds,
+                    },
+                );
+                lock::lock_if_enabled(&app);
+            } else if came_back {
+                let _ = app.emit("user-active", ());
+            }
+        }
+    });
+}
+
+/// Seconds since the last keyboard or mouse input anywhere on the system
+#[tauri::command]
+pub async fn get_idle_seconds(_timer: IpcTimer) -> Result<u64, String> {
+    platform::idle_seconds()
+        .await
+        .ok_or_else(|| "Idle time is not available on this system".to_string())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tokio::sync::OnceCell;
+
+    #[zbus::proxy(
+        interface = "org.gnome.Mutter.IdleMonitor",
+        default_service = "org.gnome.Mutter.IdleMonitor",
+        default_path = "/org/gnome/Mutter/IdleMonitor/Core"
+    )]
+    trait MutterIdleMonitor {
+        /// Milliseconds
+        fn get_idletime(&self) -> zbus::Result<u64>;
+    }
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.ScreenSaver",
+        default_service = "org.freedesktop.ScreenSaver",
+        default_path = "/ScreenSaver"
+    )]
+    trait ScreenSaver {
+        /// Seconds
+        fn get_session_idle_time(&self) -> zbus::Result<u32>;
+    }
+
+    static SESSION: OnceCell<Option<zbus::Connection>> = OnceCell::const_new();
+
+    // GNOME answers through Mutter, KDE and most others through the
+    // freedesktop screensaver; X11 and Wayland alike
+    pub async fn idle_seconds() -> Option<u64> {
+        let connection = SESSION
+            .get_or_init(|| async {
+                zbus::Connection::session()
+                    .await
+                    .inspect_err(|err| tracing::warn!("Not tracking idle time: {err}"))
+                    .ok()
+            })
+            .await
+            .as_ref()?;
+
+        if let Ok(mutter) = MutterIdleMonitorProxy::new(connection).await {
+            if let Ok(millis) = mutter.get_idletime().await {
+                return Some(millis / 1000);
+            }
+        }
+        let screensaver = ScreenSaverProxy::new(connection).await.ok()?;
+        match screensaver.get_session_idle_time().await {
+            Ok(seconds) => Some(seconds.into()),
+            Err(err) => {
+                tracing::debug!("Failed to read the idle time: {err}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub async fn idle_seconds() -> Option<u64> {
+        let mut info = LASTINPUTINFO {
+            cbSize: size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // Both are ticks that wrap after 49 days, so the difference still holds
+        let millis = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Some(u64::from(millis) / 1000)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    // kCGEventSourceStateCombinedSessionState and kCGAnyInputEventType
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event: u32) -> f64;
+    }
+
+    pub async fn idle_seconds() -> Option<u64> {
+        let seconds = unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+        };
+        Some(seconds as u64)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    pub async fn idle_seconds() -> Option<u64> {
+        None
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Cursor, Read};
+
+use super::{row, Importer, Row, Rows};
+
+// A quoted field running longer than this is taken to be missing its
+// closing quote
+const MAX_RECORD_LINES: usize = 100;
+// What 0x80 to 0x9f are in Windows-1252; the rest is Latin-1. The five it
+// leaves undefined stay C1 controls, as browsers decode them.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// Contacts in a CSV file with a header row, read by the column names one
+/// app or another gives them. Names are matched ignoring case.
+pub struct ContactCsv {
+    id: &'static str,
+    name: &'static str,
+    /// Columns that must all be there for `detect` to pick this format
+    signature: &'static [&'static str],
+    emails: &'static [&'static str],
+    full_name: &'static [&'static str],
+    first_name: &'static [&'static str],
+    last_name: &'static [&'static str],
+}
+
+/// Google Contacts' "Google CSV" export
+pub static GOOGLE: ContactCsv = ContactCsv {
+    id: "google-csv",
+    name: "Google Contacts",
+    signature: &["E-mail 1 - Value"],
+    emails: &[
+        "E-mail 1 - Value",
+        "E-mail 2 - Value",
+        "E-mail 3 - Value",
+        "E-mail 4 - Value",
+    ],
+    full_name: &["Name"],
+    first_name: &["Given Name", "First Name"],
+    last_name: &["Family Name", "Last Name"],
+};
+
+/// Outlook's and Outlook.com's CSV export
+pub static OUTLOOK: ContactCsv = ContactCsv {
+    id: "outlook-csv",
+    name: "Outlook",
+    signature: &["First Name", "E-mail Address"],
+    emails: &["E-mail Address", "E-mail 2 Address", "E-mail 3 Address"],
+    full_name: &[],
+    first_name: &["First Name"],
+    last_name: &["Last Name"],
+};
+
+/// Any CSV with an email column, e.g. a spreadsheet
+pub static GENERIC: ContactCsv = ContactCsv {
+    id: "csv",
+    name: "CSV",
+    signature: &[],
+    emails: &["Email", "E-mail", "Email Address", "E-mail Address", "Mail"],
+    full_name: &["Name", "Full Name", "Display Name"],
+    first_name: &["First Name", "Given Name"],
+    last_name: &["Last Name", "Family Name", "Surname"],
+};
+
+// Where each kind of column is in the file
+struct Columns {
+    emails: Vec<usize>,
+    full_name: Option<usize>,
+    first_name: Option<usize>,
+    last_name: Option<usize>,
+}
+
+fn position(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+impl ContactCsv {
+    fn columns(&self, header: &[String]) -> Columns {
+        Columns {
+            emails: self
+                .emails
+                .iter()
+                .filter_map(|name| position(header, &[name]))
+                .collect(),
+            full_name: position(header, self.full_name),
+            first_name: position(header, self.first_name),
+            last_name: position(header, self.last_name),
+        }
+    }
+}
+
+impl Importer for ContactCsv {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        let Some(Ok(header)) = Records::new(head.as_bytes())
+            .next()
+            .map(|(_, record)| record)
+        else {
+            return false;
+        };
+        self.signature
+            .iter()
+            .all(|name| position(&header, &[name]).is_some())
+            && !self.columns(&header).emails.is_empty()
+    }
+
+    fn rows<'a>(&self, mut reader: Box<dyn BufRead + Send + 'a>) -> Result<Rows<'a>, String> {
+        // UTF-16 is decoded up front, which the import size limit keeps
+        // affordable
+        let head = reader.fill_buf().map_err(|err| err.to_string())?;
+        if head.starts_with(&[0xff, 0xfe]) || head.starts_with(&[0xfe, 0xff]) {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|err| err.to_string())?;
+            let text = decode_utf16(&bytes).unwrap_or_default();
+            reader = Box::new(Cursor::new(text.into_bytes()));
+        }
+        let mut records = Records::new(reader);
+        let header = match records.next() {
+            Some((_, Ok(mut header))) => {
+                if let Some(first) = header.first_mut() {
+                    *first = first.trim_start_matches('\u{feff}').to_string();
+                }
+                header
+            }
+            Some((_, Err(err))) => return Err(err),
+            None => return Err("The file is empty".to_string()),
+        };
+        let columns = self.columns(&header);
+        if columns.emails.is_empty() {
+            return Err(format!("The file has no email column for {}", self.name));
+        }
+        Ok(Box::new(records.filter_map(move |(number, record)| {
+            let fields = match record {
+                Ok(fields) => fields,
+                Err(err) => {
+                    return Some(Row {
+                        number,
+                        contacts: Err(err),
+                    })
+                }
+            };
+            if fields.iter().all(|field| field.trim().is_empty()) {
+                return None;
+            }
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+            };
+            let emails: Vec<&str> = columns
+                .emails
+                .iter()
+                .filter_map(|&index| field(Some(index)))
+                .collect();
+            let joined = [field(columns.first_name), field(columns.last_name)]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let name =
+                field(columns.full_name).or(Some(joined.as_str()).filter(|name| !name.is_empty()));
+            Some(row(number, &emails, name))
+        })))
+    }
+}
+
+/// The records of a CSV file, as RFC 4180 has them: quoted fields can hold
+/// commas, line breaks and `""` for a quote. Each comes with the line it
+/// starts on. Fields are decoded one by one, so a file that mixes encodings
+/// still reads.
+struct Records<R> {
+    reader: R,
+    line: u64,
+    // Lines to read again after the record they were part of turned out
+    // to be broken
+    replay: VecDeque<(u64, Vec<u8>)>,
+    done: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            replay: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<(u64, Vec<u8>)>> {
+        if let Some(line) = self.replay.pop_front() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some((self.line, line)))
+    }
+
+    // Fails the record on the first of `lines` and reads the others again
+    // as records of their own, so a stray quote costs one row
+    fn broken(&mut self, mut lines: Vec<(u64, Vec<u8>)>) -> (u64, Result<Vec<String>, String>) {
+        let (start, _) = lines.remove(0);
+        for line in lines.into_iter().rev() {
+            self.replay.push_front(line);
+        }
+        (start, Err("A quoted field is never closed".to_string()))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = (u64, Result<Vec<String>, String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut lines = Vec::new();
+        let mut fields = Vec::new();
+        let mut field = Vec::new();
+        let mut in_quotes = false;
+        loop {
+            let (number, line) = match self.next_line() {
+                Ok(Some(line)) => line,
+                Ok(None) if in_quotes => return Some(self.broken(lines)),
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(err) => {
+                    // Can't tell where the next record starts after this
+                    self.done = true;
+                    let start = lines.first().map_or(self.line + 1, |(number, _)| *number);
+                    return Some((start, Err(err.to_string())));
+                }
+            };
+            let mut bytes = line.iter().copied().peekable();
+            while let Some(byte) = bytes.next() {
+                match byte {
+                    b'"' if in_quotes => {
+                        if bytes.peek() == Some(&b'"') {
+                            bytes.next();
+                            field.push(b'"');
+                        } else {
+                            in_quotes = false;
+                        }
+                    }
+                    b'"' if field.is_empty() => in_quotes = true,
+                    b',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+                    b'\r' | b'\n' if !in_quotes => {}
+                    _ => field.push(byte),
+                }
+            }
+            lines.push((number, line));
+            if !in_quotes {
+                fields.push(field);
+                let start = lines[0].0;
+                return Some((start, Ok(fields.into_iter().map(decode).collect())));
+            }
+            if lines.len() >= MAX_RECORD_LINES {
+                return Some(self.broken(lines));
+            }
+        }
+    }
+}
+
+/// UTF-8 where it's valid and Windows-1252 otherwise, which Excel and older
+/// Outlook versions save CSV in and which any byte decodes as
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|err| {
+        err.as_bytes()
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0x9f => WINDOWS_1252[usize::from(byte - 0x80)],
+                _ => char::from(byte),
+            })
+            .collect()
+    })
+}
+
+/// Text with a UTF-16 byte order mark, as Outlook saves "Unicode" CSV, or
+/// `None` for anything else
+pub fn decode_utf16(bytes: &[u8]) -> Option<String> {
+    let big_endian = match bytes {
+        [0xfe, 0xff, ..] => true,
+        [0xff, 0xfe, ..] => false,
+        _ => return None,
+    };
+    let units = bytes[2..].chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if big_endian {
+            u16::from_be_bytes(pair)
+        } else {
+            u16::from_le_bytes(pair)
+        }
+    });
+    Some(
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(input: &[u8]) -> Vec<(u64, Result<Vec<String>, String>)> {
+        Records::new(input).collect()
+    }
+
+    fn fields(fields: &[&str]) -> Result<Vec<String>, String> {
+        Ok(fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    #[test]
+    fn plain_records() {
+        assert_eq!(
+            records(b"Name,Email\r\nAda,ada@example.com\r\n"),
+            [
+                (1, fields(&["Name", "Email"])),
+                (2, fields(&["Ada", "ada@example.com"])),
+            ]
+        );
+        assert_eq!(records(b"a,,b"), [(1, fields(&["a", "", "b"]))]);
+        assert!(records(b"").is_empty());
+    }
+
+    #[test]
+    fn quoted_fields() {
+        assert_eq!(
+            records(b"\"Lovelace, Ada\",\"say \"\"hi\"\"\"\n\"two\nlines\",x\nnext,y\n"),
+            [
+                (1, fields(&["Lovelace, Ada", "say \"hi\""])),
+                (2, fields(&["two\nlines", "x"])),
+                (4, fields(&["next", "y"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_quotes_only_fail_their_row() {
+        assert_eq!(
+            records(b"a,b\n\"broken,c\nd,e\n"),
+            [
+                (1, fields(&["a", "b"])),
+                (2, Err("A quoted field is never closed".to_string())),
+                (3, fields(&["d", "e"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_fields_stop_at_the_line_limit() {
+        let mut input = b"\"open\n".to_vec();
+        for _ in 0..MAX_RECORD_LINES {
+            input.extend_from_slice(b"x,y\n");
+        }
+        let read = records(&input);
+        assert!(read[0].1.is_err());
+        assert_eq!(read[1], (2, fields(&["x", "y"])));
+        assert_eq!(read.len(), MAX_RECORD_LINES + 1);
+    }
+
+    #[test]
+    fn fields_fall_back_to_windows_1252() {
+        assert_eq!(
+            records(b"Jos\xe9,\x80\x93\n\xc3\xa9t\xc3\xa9,ok\n"),
+            [(1, fields(&["José", "€“"])), (2, fields(&["été", "ok"])),]
+        );
+    }
+
+    #[test]
+    fn utf16_with_a_byte_order_mark() {
+        let mut little = vec![0xff, 0xfe];
+        little.extend("Né,x".encode_utf16().flat_map(u16::to_le_bytes));
+        let mut big = vec![0xfe, 0xff];
+        big.extend("Né,x".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode_utf16(&little).as_deref(), Some("Né,x"));
+        assert_eq!(decode_utf16(&big).as_deref(), Some("Né,x"));
+        assert_eq!(decode_utf16(b"Name,x"), None);
+    }
+}
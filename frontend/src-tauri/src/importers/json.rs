@@ -0,0 +1,234 @@
+use std::io::BufRead;
+
+use serde_json::{Map, Value};
+
+use super::{row, Importer, Row, Rows};
+
+// Keys are matched ignoring case, in this order
+const EMAIL_KEYS: &[&str] = &["email", "e-mail", "emailAddress", "mail"];
+// A list of addresses, either strings or objects like `{ "value": ... }`
+const EMAILS_KEYS: &[&str] = &["emails", "emailAddresses"];
+const ADDRESS_KEYS: &[&str] = &["value", "address", "email"];
+const NAME_KEYS: &[&str] = &["name", "fullName", "displayName"];
+const FIRST_NAME_KEYS: &[&str] = &["firstName", "givenName"];
+const LAST_NAME_KEYS: &[&str] = &["lastName", "familyName", "surname"];
+
+/// Contacts as JSON: an array of objects, an object with such an array under
+/// `contacts`, or one object per line. Arrays are read whole; JSON lines are
+/// read one at a time.
+pub struct Json;
+
+fn get<'a>(object: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|key| {
+        object
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    })
+}
+
+fn text<'a>(object: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a str> {
+    get(object, keys)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn contact_row(number: u64, value: &Value) -> Row {
+    let Some(object) = value.as_object() else {
+        return Row {
+            number,
+            contacts: Err("Expected an object".to_string()),
+        };
+    };
+    let mut emails: Vec<&str> = text(object, EMAIL_KEYS).into_iter().collect();
+    if let Some(Value::Array(list)) = get(object, EMAILS_KEYS) {
+        emails.extend(list.iter().filter_map(|entry| match entry {
+            Value::String(email) => Some(email.as_str()),
+            Value::Object(entry) => text(entry, ADDRESS_KEYS),
+            _ => None,
+        }));
+    }
+    let joined = [text(object, FIRST_NAME_KEYS), text(object, LAST_NAME_KEYS)]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = text(object, NAME_KEYS).or(Some(joined.as_str()).filter(|name| !name.is_empty()));
+    row(number, &emails, name)
+}
+
+impl Importer for Json {
+    fn id(&self) -> &'static str {
+        "json"
+    }
+
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json", "jsonl", "ndjson"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        head.trim_start().starts_with(['[', '{'])
+    }
+
+    fn rows<'a>(&self, mut reader: Box<dyn BufRead + Send + 'a>) -> Result<Rows<'a>, String> {
+        // Peek at the first line to tell an array from JSON lines
+        let mut first = String::new();
+        let mut lines_before = 0;
+        while first.trim().is_empty() {
+            first.clear();
+            if reader
+                .read_line(&mut first)
+                .map_err(|err| err.to_string())?
+                == 0
+            {
+                return Err("The file is empty".to_string());
+            }
+            lines_before += 1;
+        }
+        let first = first.trim_start_matches('\u{feff}').to_string();
+
+        if let Ok(value) = serde_json::from_str::<Value>(&first) {
+            if value.is_object() && value.get("contacts").is_none() {
+                let rest = reader.lines().zip(lines_before + 1..);
+                let rows = std::iter::once(contact_row(lines_before, &value)).chain(
+                    rest.filter_map(|(line, number)| match line {
+                        Ok(line) if line.trim().is_empty() => None,
+                        Ok(line) => Some(match serde_json::from_str(&line) {
+                            Ok(value) => contact_row(number, &value),
+                            Err(err) => Row {
+                                number,
+                                contacts: Err(err.to_string()),
+                            },
+                        }),
+                        Err(err) => Some(Row {
+                            number,
+                            contacts: Err(err.to_string()),
+                        }),
+                    }),
+                );
+                return Ok(Box::new(rows));
+            }
+        }
+
+        let value: Value = serde_json::from_reader(std::io::Read::chain(first.as_bytes(), reader))
+            .map_err(|err| format!("Not a JSON array of contacts: {err}"))?;
+        let items = match value {
+            Value::Array(items) => items,
+            Value::Object(mut object) => match object.remove("contacts") {
+                Some(Value::Array(items)) => items,
+                _ => return Err("Expected an array of contacts".to_string()),
+            },
+            _ => return Err("Expected an array of contacts".to_string()),
+        };
+        Ok(Box::new(
+            items
+                .into_iter()
+                .zip(1..)
+                .map(|(item, number)| contact_row(number, &item)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Parsed = Vec<(u64, Result<Vec<(String, Option<String>)>, String>)>;
+
+    fn read(input: &str) -> Result<Parsed, String> {
+        let rows = Json.rows(Box::new(input.as_bytes()))?;
+        Ok(rows
+            .map(|row| {
+                let contacts = row.contacts.map(|contacts| {
+                    contacts
+                        .into_iter()
+                        .map(|contact| (contact.email, contact.name))
+                        .collect()
+                });
+                (row.number, contacts)
+            })
+            .collect())
+    }
+
+    fn contacts(
+        contacts: &[(&str, Option<&str>)],
+    ) -> Result<Vec<(String, Option<String>)>, String> {
+        Ok(contacts
+            .iter()
+            .map(|(email, name)| (email.to_string(), name.map(str::to_string)))
+            .collect())
+    }
+
+    #[test]
+    fn arrays_of_contacts() {
+        let input = r#"[
+            { "Email": " Ada@Example.com ", "name": "Ada Lovelace" },
+            {
+                "emailAddresses": [{ "value": "grace@example.com" }, "hopper@example.com", 7],
+                "givenName": "Grace",
+                "familyName": "Hopper"
+            },
+            { "name": "No Address" },
+            "not a contact"
+        ]"#;
+        assert_eq!(
+            read(input).unwrap(),
+            [
+                (1, contacts(&[("ada@example.com", Some("Ada Lovelace"))])),
+                (
+                    2,
+                    contacts(&[
+                        ("grace@example.com", Some("Grace Hopper")),
+                        ("hopper@example.com", Some("Grace Hopper")),
+                    ])
+                ),
+                (3, Err("No email address".to_string())),
+                (4, Err("Expected an object".to_string())),
+            ]
+        );
+
+        let wrapped = "{\n  \"contacts\": [{ \"mail\": \"x@example.com\" }]\n}";
+        assert_eq!(
+            read(wrapped).unwrap(),
+            [(1, contacts(&[("x@example.com", None)]))]
+        );
+    }
+
+    #[test]
+    fn json_lines_are_numbered_by_line() {
+        let input = "\n\u{feff}{\"email\":\"a@example.com\",\"fullName\":\"A\"}\n\
+                     \n\
+                     {broken\n\
+                     {\"e-mail\":\"b@example.com\"}\n";
+        let read = read(input).unwrap();
+        assert_eq!(read[0], (2, contacts(&[("a@example.com", Some("A"))])));
+        assert_eq!(read[1].0, 4);
+        assert!(read[1].1.is_err());
+        assert_eq!(read[2], (5, contacts(&[("b@example.com", None)])));
+        assert_eq!(read.len(), 3);
+    }
+
+    #[test]
+    fn files_without_contacts_fail_whole() {
+        assert_eq!(read("").unwrap_err(), "The file is empty");
+        assert_eq!(read(" \n\n").unwrap_err(), "The file is empty");
+        assert_eq!(read("42").unwrap_err(), "Expected an array of contacts");
+        assert_eq!(
+            read(r#"{"contacts": 3}"#).unwrap_err(),
+            "Expected an array of contacts"
+        );
+        assert!(read("[{").is_err());
+    }
+
+    #[test]
+    fn detects_arrays_and_objects() {
+        assert!(Json.detect("  [{\"email\": \"a@b.c\"}]"));
+        assert!(Json.detect("{\"contacts\": []}"));
+        assert!(!Json.detect("Name,Email\n"));
+    }
+}
@@ -0,0 +1,386 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+
+use crate::command::{ensure, CommandError, CommandResult};
+use crate::fs_scope::{self, Access};
+use crate::imports::MAX_IMPORT_SIZE;
+use crate::metrics::IpcTimer;
+use crate::sidecar::Sidecar;
+
+mod csv;
+mod json;
+
+// Contacts per request to the backend, which takes at most 1000
+const BATCH_SIZE: usize = 500;
+// Further errors are only counted, so a file in the wrong format doesn't
+// send thousands of them to the UI
+const MAX_REPORTED_ERRORS: usize = 200;
+// How much of the file `detect` gets to look at
+const HEAD_SIZE: u64 = 64 * 1024;
+
+/// A contact in the form the backend stores it, whatever the file had
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    email: String,
+    name: Option<String>,
+}
+
+impl Contact {
+    /// Trims and lowercases `email`, failing if it isn't an address
+    fn new(email: &str, name: Option<&str>) -> Result<Self, String> {
+        let email = email.trim().to_lowercase();
+        if !email.contains('@') || email.contains(char::is_whitespace) {
+            return Err(format!("Not an email address: {email}"));
+        }
+        Ok(Self {
+            email,
+            name: name
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+        })
+    }
+}
+
+/// One row of the file; a row can hold several addresses for one person
+pub struct Row {
+    /// The line for CSV and JSON lines, the position of the item in a JSON
+    /// array
+    number: u64,
+    contacts: Result<Vec<Contact>, String>,
+}
+
+/// Builds the contacts of one row out of its address and name fields,
+/// keeping the valid addresses if only some of them are
+fn row(number: u64, emails: &[&str], name: Option<&str>) -> Row {
+    let mut contacts = Vec::new();
+    let mut invalid = None;
+    // Google puts several addresses in one field, separated by `:::`
+    for email in emails.iter().flat_map(|email| email.split([':', ';'])) {
+        if email.trim().is_empty() {
+            continue;
+        }
+        match Contact::new(email, name) {
+            Ok(contact) => contacts.push(contact),
+            Err(err) => {
+                invalid.get_or_insert(err);
+            }
+        }
+    }
+    let contacts = if contacts.is_empty() {
+        Err(invalid.unwrap_or_else(|| "No email address".to_string()))
+    } else {
+        Ok(contacts)
+    };
+    Row { number, contacts }
+}
+
+pub type Rows<'a> = Box<dyn Iterator<Item = Row> + Send + 'a>;
+
+/// Reads contacts exported by another app. Importers are tried in the order
+/// of `IMPORTERS` when the user doesn't say which format a file is in.
+pub trait Importer: Sync {
+    /// What the frontend picks the format by, e.g. `google-csv`
+    fn id(&self) -> &'static str;
+    fn name(&self) -> &'static str;
+    fn extensions(&self) -> &'static [&'static str];
+    /// Whether `head`, the start of the file, looks like this format
+    fn detect(&self, head: &str) -> bool;
+    /// Parses the file one row at a time. Fails only if nothing can be read
+    /// at all; rows that can't be read come out as errors.
+    fn rows<'a>(&self, reader: Box<dyn BufRead + Send + 'a>) -> Result<Rows<'a>, String>;
+}
+
+// The more specific formats first, since the generic ones accept them too
+static IMPORTERS: &[&dyn Importer] = &[&csv::GOOGLE, &csv::OUTLOOK, &csv::GENERIC, &json::Json];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImporterInfo {
+    id: &'static str,
+    name: &'static str,
+    extensions: &'static [&'static str],
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    row: u64,
+    message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// The importer used, picked or detected
+    format: &'static str,
+    rows: u64,
+    imported: u64,
+    /// The first `MAX_REPORTED_ERRORS` rows that couldn't be imported
+    errors: Vec<RowError>,
+    /// How many more failed beyond those in `errors`
+    more_errors: u64,
+}
+
+impl ImportReport {
+    fn fail(&mut self, row: u64, message: String) {
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(RowError { row, message });
+        } else {
+            self.more_errors += 1;
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProgress<'a> {
+    path: &'a Path,
+    rows: u64,
+    imported: u64,
+    failed: u64,
+    done: bool,
+}
+
+// What the parsing thread hands over, once per `BATCH_SIZE` contacts
+#[derive(Default)]
+struct Batch {
+    contacts: Vec<Contact>,
+    /// The row each of `contacts` came from
+    rows: Vec<u64>,
+    errors: Vec<RowError>,
+    rows_read: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchRequest<'a> {
+    account_id: &'a str,
+    contacts: &'a [Contact],
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    success: bool,
+    #[serde(default)]
+    imported: u64,
+    #[serde(default)]
+    errors: Vec<BatchError>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchError {
+    index: usize,
+    error: String,
+}
+
+fn find(id: &str) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .copied()
+        .find(|importer| importer.id() == id)
+}
+
+fn detect(path: &Path) -> Result<Option<&'static dyn Importer>, String> {
+    let mut head = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(HEAD_SIZE).read_to_end(&mut head))
+        .map_err(|err| err.to_string())?;
+    let head = csv::decode_utf16(&head)
+        .map(Cow::Owned)
+        .unwrap_or_else(|| String::from_utf8_lossy(&head));
+    let head = head.trim_start_matches('\u{feff}');
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    Ok(IMPORTERS.iter().copied().find(|importer| {
+        importer.extensions().contains(&extension.as_str()) && importer.detect(head)
+    }))
+}
+
+// Runs on a blocking thread, sending batches until the file ends or the
+// receiver is gone
+fn parse(importer: &dyn Importer, path: &Path, batches: mpsc::Sender<Batch>) -> Result<(), String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let mut batch = Batch::default();
+    for row in importer.rows(Box::new(BufReader::new(file)))? {
+        batch.rows_read += 1;
+        match row.contacts {
+            Ok(contacts) => {
+                batch.rows.extend(contacts.iter().map(|_| row.number));
+                batch.contacts.extend(contacts);
+            }
+            Err(message) => batch.errors.push(RowError {
+                row: row.number,
+                message,
+            }),
+        }
+        if batch.contacts.len() >= BATCH_SIZE
+            && batches.blocking_send(std::mem::take(&mut batch)).is_err()
+        {
+            return Ok(());
+        }
+    }
+    let _ = batches.blocking_send(batch);
+    Ok(())
+}
+
+async fn send(
+    app: &AppHandle,
+    account_id: &str,
+    contacts: &[Contact],
+) -> Result<BatchResponse, String> {
+    let response: BatchResponse = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::POST, "/contacts/import")
+        .json(&BatchRequest {
+            account_id,
+            contacts,
+        })
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Backend did not accept the contacts: {err}"))?
+        .json()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !response.success {
+        return Err(response
+            .error
+            .unwrap_or_else(|| "Backend did not accept the contacts".to_string()));
+    }
+    Ok(response)
+}
+
+/// The formats `import_contacts` reads
+#[tauri::command]
+pub fn list_importers() -> Vec<ImporterInfo> {
+    IMPORTERS
+        .iter()
+        .map(|importer| ImporterInfo {
+            id: importer.id(),
+            name: importer.name(),
+            extensions: importer.extensions(),
+        })
+        .collect()
+}
+
+/// Imports the contacts in another app's export into `account_id`, as
+/// `format` or whichever importer recognizes the file. Emits
+/// `contact-import-progress` after every batch the backend takes.
+#[tauri::command]
+pub async fn import_contacts(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: PathBuf,
+    account_id: String,
+    format: Option<String>,
+) -> CommandResult<ImportReport> {
+    ensure!(
+        !account_id.is_empty(),
+        "accountId",
+        "Pick an account to import into"
+    );
+    let path = fs_scope::check(&app, &path, Access::Read)?;
+    let metadata = fs::metadata(&path)
+        .map_err(|err| CommandError::invalid("path", format!("{}: {err}", path.display())))?;
+    ensure!(
+        metadata.is_file(),
+        "path",
+        "{} is not a file",
+        path.display()
+    );
+    ensure!(
+        metadata.len() <= MAX_IMPORT_SIZE,
+        "path",
+        "File is larger than {} MB",
+        MAX_IMPORT_SIZE / 1024 / 1024
+    );
+
+    let importer = match &format {
+        Some(id) => find(id)
+            .ok_or_else(|| CommandError::invalid("format", format!("No importer {id:?}")))?,
+        None => {
+            let detect_path = path.clone();
+            tauri::async_runtime::spawn_blocking(move || detect(&detect_path))
+                .await
+                .map_err(|err| err.to_string())??
+                .ok_or_else(|| {
+                    CommandError::invalid("format", "Couldn't tell which app the file is from")
+                })?
+        }
+    };
+    tracing::info!(
+        "Importing contacts from {} as {}",
+        path.display(),
+        importer.id()
+    );
+
+    let (sender, mut receiver) = mpsc::channel(2);
+    let parse_path = path.clone();
+    let parser = tauri::async_runtime::spawn_blocking(move || parse(importer, &parse_path, sender));
+
+    let mut report = ImportReport {
+        format: importer.id(),
+        rows: 0,
+        imported: 0,
+        errors: Vec::new(),
+        more_errors: 0,
+    };
+    let mut failed = 0;
+    while let Some(batch) = receiver.recv().await {
+        report.rows += batch.rows_read;
+        failed += batch.errors.len() as u64;
+        for error in batch.errors {
+            report.fail(error.row, error.message);
+        }
+        if !batch.contacts.is_empty() {
+            // Dropping `receiver` on error stops the parser
+            let response = send(&app, &account_id, &batch.contacts).await?;
+            report.imported += response.imported;
+            failed += response.errors.len() as u64;
+            for error in response.errors {
+                let row = batch.rows.get(error.index).copied().unwrap_or_default();
+                report.fail(row, error.error);
+            }
+        }
+        let _ = app.emit(
+            "contact-import-progress",
+            ImportProgress {
+                path: &path,
+                rows: report.rows,
+                imported: report.imported,
+                failed,
+                done: false,
+            },
+        );
+    }
+    parser.await.map_err(|err| err.to_string())??;
+
+    let _ = app.emit(
+        "contact-import-progress",
+        ImportProgress {
+            path: &path,
+            rows: report.rows,
+            imported: report.imported,
+            failed,
+            done: true,
+        },
+    );
+    tracing::info!(
+        "Imported {} contacts from {} rows, {failed} failed",
+        report.imported,
+        report.rows
+    );
+    Ok(report)
+}
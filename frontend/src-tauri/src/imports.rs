@@ -0,0 +1,252 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, Window, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder};
+
+use crate::data_dir;
+use crate::fs_scope::{self, Access};
+use crate::main_window;
+use crate::metrics::IpcTimer;
+use crate::sidecar::Sidecar;
+use crate::tabs;
+
+const IMPORTS_DIR: &str = "imports";
+// Queues the copy on the backend, see backend/src/routes/imports.ts
+const IMPORTS_PATH: &str = "/imports";
+// Mail (single messages and mailboxes), contacts and calendars
+const ALLOWED_EXTENSIONS: &[&str] = &["eml", "mbox", "vcf", "ics", "csv"];
+pub const MAX_IMPORT_SIZE: u64 = 500 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct FileFilter {
+    name: String,
+    extensions: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ImportResult {
+    /// Copied into the import directory and handed to the backend
+    Queued {
+        source: PathBuf,
+        path: PathBuf,
+    },
+    Rejected {
+        source: PathBuf,
+        reason: String,
+    },
+}
+
+#[derive(Serialize)]
+struct ImportNotice<'a> {
+    path: &'a Path,
+    name: &'a str,
+    kind: &'a str,
+    size: u64,
+}
+
+fn dialog(
+    app: &AppHandle,
+    title: Option<String>,
+    filters: Vec<FileFilter>,
+) -> FileDialogBuilder<tauri::Wry> {
+    let mut builder = app.dialog().file();
+    if let Some(title) = title {
+        builder = builder.set_title(title);
+    }
+    for filter in &filters {
+        let extensions: Vec<&str> = filter.extensions.iter().map(String::as_str).collect();
+        builder = builder.add_filter(&filter.name, &extensions);
+    }
+    builder
+}
+
+/// Shows a native open dialog and grants read access to what the user picks.
+/// `None` means the user cancelled.
+#[tauri::command]
+pub async fn open_file_dialog(
+    _timer: IpcTimer,
+    app: AppHandle,
+    title: Option<String>,
+    filters: Option<Vec<FileFilter>>,
+    multiple: Option<bool>,
+) -> Result<Option<Vec<PathBuf>>, String> {
+    let builder = dialog(&app, title, filters.unwrap_or_default());
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        if multiple.unwrap_or(false) {
+            builder.blocking_pick_files()
+        } else {
+            builder.blocking_pick_file().map(|file| vec![file])
+        }
+    })
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let Some(files) = picked else {
+        return Ok(None);
+    };
+    let paths = files
+        .into_iter()
+        .map(|file| file.into_path().map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    for path in &paths {
+        fs_scope::grant(&app, path, Access::Read);
+    }
+    Ok(Some(paths))
+}
+
+/// Shows a native save dialog and grants write access to the file chosen.
+/// `None` means the user cancelled.
+#[tauri::command]
+pub async fn save_file_dialog(
+    _timer: IpcTimer,
+    app: AppHandle,
+    title: Option<String>,
+    default_name: Option<String>,
+    filters: Option<Vec<FileFilter>>,
+) -> Result<Option<PathBuf>, String> {
+    let mut builder = dialog(&app, title, filters.unwrap_or_default());
+    if let Some(name) = default_name {
+        builder = builder.set_file_name(name);
+    }
+    let picked = tauri::async_runtime::spawn_blocking(move || builder.blocking_save_file())
+        .await
+        .map_err(|err| err.to_string())?;
+    let Some(file) = picked else {
+        return Ok(None);
+    };
+    let path = file.into_path().map_err(|err| err.to_string())?;
+    fs_scope::grant(&app, &path, Access::Write);
+    Ok(Some(path))
+}
+
+/// Validates, copies and hands each file to the backend, emitting `import`
+/// for every one of them. Used for both picked and dropped files.
+#[tauri::command]
+pub async fn import_files(
+    _timer: IpcTimer,
+    app: AppHandle,
+    paths: Vec<PathBuf>,
+) -> Vec<ImportResult> {
+    import_all(&app, paths).await
+}
+
+async fn import_all(app: &AppHandle, paths: Vec<PathBuf>) -> Vec<ImportResult> {
+    let mut results = Vec::with_capacity(paths.len());
+    for source in paths {
+        let imported = match fs_scope::check(app, &source, Access::Read) {
+            Ok(path) => import(app, &path).await,
+            Err(err) => Err(err.to_string()),
+        };
+        let result = match imported {
+            Ok(path) => ImportResult::Queued { source, path },
+            Err(reason) => {
+                tracing::warn!("Rejected import of {}: {reason}", source.display());
+                ImportResult::Rejected { source, reason }
+            }
+        };
+        let _ = app.emit("import", &result);
+        results.push(result);
+    }
+    results
+}
+
+/// Copies one file into the import directory and hands it to the backend
+pub async fn import(app: &AppHandle, source: &Path) -> Result<PathBuf, String> {
+    let dir = data_dir::data_dir(app)
+        .ok_or("No data directory available")?
+        .join(IMPORTS_DIR);
+    let request = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::POST, IMPORTS_PATH);
+    copy_in(&dir, source, request).await
+}
+
+// `import` with the folder and the request to the backend given, so tests
+// can point it at a backend of their own
+pub(crate) async fn copy_in(
+    dir: &Path,
+    source: &Path,
+    request: reqwest::RequestBuilder,
+) -> Result<PathBuf, String> {
+    let (kind, size) = validate(source)?;
+    let name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("File name is not valid UTF-8")?;
+
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    // Prefixed so importing two files with the same name doesn't clobber one
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("{stamp}-{name}"));
+    fs::copy(source, &path).map_err(|err| err.to_string())?;
+
+    let notice = ImportNotice {
+        path: &path,
+        name,
+        kind: &kind,
+        size,
+    };
+    let response = request
+        .json(&notice)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(err) = response {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Backend did not accept the import: {err}"));
+    }
+    Ok(path)
+}
+
+// Returns the file's kind (its lowercased extension) and size
+fn validate(path: &Path) -> Result<(String, u64), String> {
+    let metadata = fs::metadata(path).map_err(|err| err.to_string())?;
+    if !metadata.is_file() {
+        return Err("Only files can be imported".to_string());
+    }
+    if metadata.len() > MAX_IMPORT_SIZE {
+        return Err(format!(
+            "File is larger than {} MB",
+            MAX_IMPORT_SIZE / 1024 / 1024
+        ));
+    }
+
+    let kind = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .filter(|ext| ALLOWED_EXTENSIONS.contains(&ext.as_str()))
+        .ok_or_else(|| {
+            format!(
+                "Only {} files can be imported",
+                ALLOWED_EXTENSIONS.join(", ")
+            )
+        })?;
+    Ok((kind, metadata.len()))
+}
+
+/// Imports files dropped onto the main window or a tab. Dropping them grants
+/// read access, as picking them would.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let label = window.label();
+    if label != main_window::LABEL && !label.starts_with(tabs::LABEL_PREFIX) {
+        return;
+    }
+    if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+        let app = window.app_handle().clone();
+        let paths = paths.clone();
+        for path in &paths {
+            fs_scope::grant(&app, path, Access::Read);
+        }
+        tauri::async_runtime::spawn(async move {
+            import_all(&app, paths).await;
+        });
+    }
+}
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::sidecar;
+
+// Set by build.rs from the sidecar that was bundled
+const EXPECTED_SHA256: Option<&str> = option_env!("BACKEND_SHA256");
+
+/// Checks the installed backend binary, `name` being the primary entry of
+/// sidecars.toml, against the checksum recorded at build time, so a tampered
+/// or half-updated sidecar is never run.
+pub fn verify_backend(name: &str) -> Result<(), String> {
+    let Some(expected) = EXPECTED_SHA256 else {
+        tracing::debug!("No backend checksum was embedded at build time, skipping check");
+        return Ok(());
+    };
+
+    let path = sidecar::sidecar_path(name)?;
+    let actual =
+        sha256(&path).map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    if actual != expected {
+        return Err(format!(
+            "{} does not match the checksum it was built with (expected {expected}, found {actual})",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Checks any binary against a checksum given as hex
+pub fn verify_file(path: &Path, expected: &str) -> Result<(), String> {
+    let actual = sha256(path).map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "{} does not match its checksum (expected {expected}, found {actual})",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
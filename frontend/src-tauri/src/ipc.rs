@@ -0,0 +1,327 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::command::{CommandError, ErrorKind};
+use crate::{lock, main_window, metrics, quick_capture, quick_open, splash};
+
+/// What a command can reach beyond the app's own state. Commands without a
+/// scope can be called from any window.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    /// Reads or writes files the user didn't pick in that same call
+    Fs,
+    /// The OS keychain, and what is unlocked with it
+    Secrets,
+    /// Starts, stops or replaces processes, the app included
+    Process,
+}
+
+const SCOPES: &[(&str, Scope)] = &[
+    ("open_log_folder", Scope::Fs),
+    ("open_hooks_folder", Scope::Fs),
+    ("create_backup", Scope::Fs),
+    ("restore_backup", Scope::Fs),
+    ("list_backups", Scope::Fs),
+    ("set_backup_schedule", Scope::Fs),
+    ("delete_from_trash", Scope::Fs),
+    ("empty_trash", Scope::Fs),
+    ("delete_profile", Scope::Fs),
+    ("open_file_dialog", Scope::Fs),
+    ("save_file_dialog", Scope::Fs),
+    ("request_fs_access", Scope::Fs),
+    ("list_fs_grants", Scope::Fs),
+    ("import_files", Scope::Fs),
+    ("import_contacts", Scope::Fs),
+    ("export_config_bundle", Scope::Fs),
+    ("import_config_bundle", Scope::Fs),
+    ("add_watch_folder", Scope::Fs),
+    ("update_watch_folder", Scope::Fs),
+    ("remove_watch_folder", Scope::Fs),
+    ("export_data", Scope::Fs),
+    ("start_drag", Scope::Fs),
+    ("import_attachment", Scope::Fs),
+    ("delete_attachment", Scope::Fs),
+    ("install_frontend_bundle", Scope::Fs),
+    ("remove_frontend_bundle", Scope::Fs),
+    ("export_pdf", Scope::Fs),
+    ("capture_window", Scope::Fs),
+    ("import_legacy_data", Scope::Fs),
+    ("delete_crash_report", Scope::Fs),
+    ("share", Scope::Fs),
+    ("get_os_events", Scope::Fs),
+    ("add_os_event", Scope::Fs),
+    ("delete_os_event", Scope::Fs),
+    ("secret_get", Scope::Secrets),
+    ("secret_set", Scope::Secrets),
+    ("secret_delete", Scope::Secrets),
+    ("http_fetch", Scope::Secrets),
+    ("get_metrics_endpoint", Scope::Secrets),
+    ("reset_metrics_token", Scope::Secrets),
+    ("add_webhook", Scope::Secrets),
+    ("update_webhook", Scope::Secrets),
+    ("remove_webhook", Scope::Secrets),
+    ("get_webhook_secret", Scope::Secrets),
+    ("enable_encryption", Scope::Secrets),
+    ("set_app_lock", Scope::Secrets),
+    ("unlock_app", Scope::Secrets),
+    ("enter_kiosk", Scope::Secrets),
+    ("exit_kiosk", Scope::Secrets),
+    ("restart_backend", Scope::Process),
+    ("reload_env_and_restart_backend", Scope::Process),
+    ("set_backend_log_level", Scope::Process),
+    ("set_backend_flag", Scope::Process),
+    ("compact_database", Scope::Process),
+    ("verify_database", Scope::Process),
+    ("repair_database", Scope::Process),
+    ("swap_backend", Scope::Process),
+    ("start_canary", Scope::Process),
+    ("stop_canary", Scope::Process),
+    ("promote_canary", Scope::Process),
+    ("install_update", Scope::Process),
+    ("rollback_update", Scope::Process),
+    ("set_autostart", Scope::Process),
+    ("switch_profile", Scope::Process),
+    ("restore_from_trash", Scope::Process),
+    ("set_lan_access", Scope::Process),
+    ("set_backend_port", Scope::Process),
+    ("schedule_job", Scope::Process),
+    ("cancel_job", Scope::Process),
+];
+
+// Settings `update_settings` only changes for a window with the scope the
+// setting's own command or effect needs, as the patch could otherwise do
+// what that command guards
+const SETTING_SCOPES: &[(&str, Scope)] = &[
+    ("backupIntervalHours", Scope::Fs),
+    ("backupKeep", Scope::Fs),
+    ("trashRetentionDays", Scope::Fs),
+    ("osCalendarRead", Scope::Fs),
+    ("osCalendarWrite", Scope::Fs),
+    ("httpAllowedHosts", Scope::Secrets),
+    ("proxyMode", Scope::Secrets),
+    ("proxyUrl", Scope::Secrets),
+    ("proxyBypass", Scope::Secrets),
+    ("metrics", Scope::Secrets),
+    ("metricsPort", Scope::Secrets),
+    ("idleMinutes", Scope::Secrets),
+    ("lockOnSleep", Scope::Secrets),
+    ("authGraceMinutes", Scope::Secrets),
+    ("crashReporting", Scope::Secrets),
+    ("autostart", Scope::Process),
+    ("autostartMinimized", Scope::Process),
+    ("sandboxSidecars", Scope::Process),
+    ("hooks", Scope::Process),
+    ("shutdownTimeoutMs", Scope::Process),
+    ("healthCheckRetries", Scope::Process),
+    ("healthCheckIntervalMs", Scope::Process),
+    ("backendMemoryLimitMb", Scope::Process),
+    ("updateChannel", Scope::Process),
+    ("pinnedVersion", Scope::Process),
+];
+
+// Calls allowed per window within the period, for commands worth guessing
+// at or expensive to repeat
+const RATE_LIMITS: &[(&str, u32, Duration)] = &[
+    ("unlock_app", 5, Duration::from_secs(60)),
+    ("set_app_lock", 5, Duration::from_secs(60)),
+    ("exit_kiosk", 5, Duration::from_secs(60)),
+    ("secret_get", 60, Duration::from_secs(60)),
+    ("secret_set", 30, Duration::from_secs(60)),
+    ("secret_delete", 30, Duration::from_secs(60)),
+    ("http_fetch", 120, Duration::from_secs(60)),
+    ("enable_encryption", 3, Duration::from_secs(60)),
+    ("restore_backup", 3, Duration::from_secs(60)),
+    ("restore_from_trash", 3, Duration::from_secs(60)),
+    ("swap_backend", 3, Duration::from_secs(60)),
+    ("start_canary", 3, Duration::from_secs(60)),
+    ("promote_canary", 3, Duration::from_secs(60)),
+    ("rollback_update", 3, Duration::from_secs(60)),
+    ("restart_backend", 5, Duration::from_secs(60)),
+    ("reload_env_and_restart_backend", 5, Duration::from_secs(60)),
+    ("switch_profile", 3, Duration::from_secs(60)),
+    ("set_lan_access", 5, Duration::from_secs(60)),
+    ("test_proxy", 10, Duration::from_secs(60)),
+    ("submit_crash_report", 5, Duration::from_secs(60)),
+    ("test_webhook", 10, Duration::from_secs(60)),
+];
+
+pub fn scope(command: &str) -> Option<Scope> {
+    SCOPES
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, scope)| *scope)
+}
+
+/// The scopes a window's frontend may call into. The main window has them
+/// all; the palettes and the splash screen only show data; the lock window
+/// needs the keychain to check the password; windows opened with
+/// `open_window` may work with files but not secrets or processes.
+pub fn permissions(label: &str) -> &'static [Scope] {
+    match label {
+        main_window::LABEL => &[Scope::Fs, Scope::Secrets, Scope::Process],
+        lock::LABEL => &[Scope::Secrets],
+        quick_capture::LABEL | quick_open::LABEL | splash::LABEL => &[],
+        _ => &[Scope::Fs],
+    }
+}
+
+/// Fails unless the window `label` may change the setting `key`, see
+/// `SETTING_SCOPES`
+pub fn check_setting(label: &str, key: &str) -> Result<(), CommandError> {
+    let scope = SETTING_SCOPES
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, scope)| *scope);
+    match scope {
+        Some(scope) if !permissions(label).contains(&scope) => Err(CommandError::new(
+            ErrorKind::PermissionDenied,
+            format!("Changing {key} needs the {scope:?} permission, which the {label:?} window doesn't have"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Whether the window `label` may call `command` at all, before the lock and
+// the rate limits have their say
+fn permitted(label: &str, command: &str) -> Result<(), CommandError> {
+    // The lock window is only there to unlock, so it gets nothing else its
+    // scope would allow
+    if label == lock::LABEL && !lock::allows(command) {
+        return Err(CommandError::new(
+            ErrorKind::PermissionDenied,
+            format!("{command} can't be called from the lock window"),
+        ));
+    }
+    if let Some(scope) = scope(command) {
+        if !permissions(label).contains(&scope) {
+            return Err(CommandError::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "{command} needs the {scope:?} permission, which the {label:?} window doesn't have"
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Recent calls of rate limited commands, per window and command
+#[derive(Default)]
+pub struct Ipc(Mutex<HashMap<(String, &'static str), VecDeque<Instant>>>);
+
+impl Ipc {
+    // Records the call unless it would go over the command's limit
+    fn allow_call(&self, label: &str, command: &str) -> bool {
+        let Some(&(name, limit, period)) = RATE_LIMITS.iter().find(|(name, ..)| *name == command)
+        else {
+            return true;
+        };
+        let now = Instant::now();
+        let mut calls = self.0.lock().unwrap();
+        let recent = calls.entry((label.to_string(), name)).or_default();
+        while recent
+            .front()
+            .is_some_and(|call| now.duration_since(*call) >= period)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= limit as usize {
+            return false;
+        }
+        recent.push_back(now);
+        true
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Ipc::default());
+}
+
+fn check(app: &AppHandle, label: &str, command: &str) -> Result<(), CommandError> {
+    permitted(label, command)?;
+    if lock::is_locked(app) && !lock::allows(command) {
+        return Err(CommandError::new(
+            ErrorKind::Locked,
+            format!("{command} isn't available while Hamba is locked"),
+        ));
+    }
+    if let Some(ipc) = app.try_state::<Ipc>() {
+        if !ipc.allow_call(label, command) {
+            return Err(CommandError::new(
+                ErrorKind::RateLimited,
+                format!("{command} was called too often, try again later"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Wraps the generated command handler so every call is checked against the
+/// calling window's permissions and the rate limits first. Denied calls are
+/// logged and rejected with a `CommandError`, the way a typed command fails.
+pub fn guard<F>(handler: F) -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke<Wry>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let webview = invoke.message.webview();
+        let command = invoke.message.command().to_string();
+        if let Err(reason) = check(webview.app_handle(), webview.label(), &command) {
+            tracing::warn!(target: "ipc", "Denied {command} from {}: {reason}", webview.label());
+            invoke.resolver.reject(reason);
+            return true;
+        }
+        let started = Instant::now();
+        let (handled, timed) = metrics::dispatch(|| handler(invoke));
+        if !timed {
+            metrics::record_ipc(webview.app_handle(), &command, started.elapsed());
+        }
+        handled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_window_can_unlock() {
+        assert!(permitted(lock::LABEL, "unlock_app").is_ok());
+        assert!(permitted(lock::LABEL, "is_app_locked").is_ok());
+    }
+
+    #[test]
+    fn lock_window_gets_nothing_else() {
+        assert!(permitted(lock::LABEL, "secret_get").is_err());
+        assert!(permitted(lock::LABEL, "set_app_lock").is_err());
+        assert!(permitted(lock::LABEL, "restart_backend").is_err());
+    }
+
+    #[test]
+    fn settings_need_the_scope_of_what_they_change() {
+        for key in [
+            "autostart",
+            "sandboxSidecars",
+            "httpAllowedHosts",
+            "proxyUrl",
+            "hooks",
+        ] {
+            assert!(check_setting(main_window::LABEL, key).is_ok(), "{key}");
+            assert!(check_setting("tab-1", key).is_err(), "{key}");
+            assert!(check_setting(quick_open::LABEL, key).is_err(), "{key}");
+        }
+        assert!(check_setting("tab-1", "osCalendarRead").is_ok());
+        assert!(check_setting(quick_capture::LABEL, "osCalendarRead").is_err());
+        assert!(check_setting(quick_capture::LABEL, "fontSize").is_ok());
+    }
+
+    #[test]
+    fn detached_windows_cant_unlock() {
+        assert!(permitted("notes", "unlock_app").is_err());
+        assert!(permitted(main_window::LABEL, "unlock_app").is_ok());
+    }
+}
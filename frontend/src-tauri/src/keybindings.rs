@@ -0,0 +1,276 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_global_shortcut::Shortcut;
+
+use crate::command::{CommandError, CommandResult};
+use crate::kiosk;
+use crate::menu;
+use crate::quick_capture;
+use crate::quick_open;
+use crate::settings::{Settings, SettingsStore};
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeybindingScope {
+    /// Works while a Hamba window has focus
+    Menu,
+    /// Registered with the OS, so it works from any app
+    Global,
+}
+
+struct Action {
+    id: &'static str,
+    label: &'static str,
+    scope: KeybindingScope,
+    default: Option<&'static str>,
+}
+
+const fn menu_action(
+    id: &'static str,
+    label: &'static str,
+    default: Option<&'static str>,
+) -> Action {
+    Action {
+        id,
+        label,
+        scope: KeybindingScope::Menu,
+        default,
+    }
+}
+
+// Everything with a remappable shortcut, in the order the settings list them
+const ACTIONS: &[Action] = &[
+    menu_action(menu::NEW_TAB, "New Tab", Some("CmdOrCtrl+T")),
+    menu_action(menu::EXPORT_DATA, "Export Data", Some("CmdOrCtrl+Shift+E")),
+    menu_action(menu::ZOOM_IN, "Zoom In", Some("CmdOrCtrl+=")),
+    menu_action(menu::ZOOM_OUT, "Zoom Out", Some("CmdOrCtrl+-")),
+    menu_action(menu::ZOOM_RESET, "Actual Size", Some("CmdOrCtrl+0")),
+    menu_action(
+        menu::TOGGLE_DEV_CONSOLE,
+        "Toggle Dev Console",
+        Some("CmdOrCtrl+Alt+I"),
+    ),
+    menu_action(menu::WIDGET_MODE, "Widget Mode", Some("CmdOrCtrl+Shift+M")),
+    menu_action(menu::OPEN_LOGS, "Open Logs", None),
+    menu_action(menu::SAVE_BUG_REPORT, "Save Bug Report", None),
+    menu_action(menu::COPY_DIAGNOSTICS, "Copy Diagnostics", None),
+    menu_action(menu::QUIT, "Quit", Some("CmdOrCtrl+Q")),
+    Action {
+        id: quick_capture::LABEL,
+        label: "Quick Capture",
+        scope: KeybindingScope::Global,
+        default: Some(quick_capture::DEFAULT_SHORTCUT),
+    },
+    Action {
+        id: quick_open::LABEL,
+        label: "Quick Open",
+        scope: KeybindingScope::Global,
+        default: Some(quick_open::DEFAULT_SHORTCUT),
+    },
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keybinding {
+    action: &'static str,
+    label: &'static str,
+    scope: KeybindingScope,
+    /// e.g. `CmdOrCtrl+Shift+E`, `None` when unbound
+    accelerator: Option<String>,
+    default: Option<&'static str>,
+}
+
+/// The menu is built before the settings are loaded, so it only gets the
+/// user's shortcuts once they are
+pub fn init(app: &AppHandle) {
+    if !app.state::<SettingsStore>().get().keybindings.is_empty() {
+        apply(app);
+    }
+}
+
+fn bound(settings: &Settings, action: &Action) -> Option<String> {
+    match action.id {
+        quick_capture::LABEL => settings.quick_capture_shortcut.clone(),
+        quick_open::LABEL => settings.quick_open_shortcut.clone(),
+        id => match settings.keybindings.get(id) {
+            Some(accelerator) => accelerator.clone(),
+            None => action.default.map(str::to_string),
+        },
+    }
+}
+
+/// The shortcut for the menu item `id`, the default until the settings are
+/// loaded
+pub fn accelerator(app: &AppHandle, id: &str) -> Option<String> {
+    let action = ACTIONS.iter().find(|action| action.id == id)?;
+    match app.try_state::<SettingsStore>() {
+        Some(settings) => bound(&settings.get(), action),
+        None => action.default.map(str::to_string),
+    }
+}
+
+fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse::<Shortcut>()
+        .map_err(|err| format!("Invalid shortcut {accelerator:?}: {err}"))
+}
+
+/// Every shortcut in `settings` has to parse and none may be used twice,
+/// as only one of the actions would run
+pub fn validate(settings: &Settings) -> Result<(), String> {
+    if let Some(id) = settings
+        .keybindings
+        .keys()
+        .find(|id| !ACTIONS.iter().any(|action| action.id == id.as_str()))
+    {
+        return Err(format!("No action called {id:?} has a shortcut"));
+    }
+    let mut taken: Vec<(Shortcut, &Action)> = Vec::new();
+    for action in ACTIONS {
+        let Some(accelerator) = bound(settings, action) else {
+            continue;
+        };
+        let shortcut = parse(&accelerator)?;
+        if let Some((_, other)) = taken.iter().find(|(used, _)| *used == shortcut) {
+            return Err(format!(
+                "{accelerator} is already the shortcut for {}",
+                other.label
+            ));
+        }
+        taken.push((shortcut, action));
+    }
+    Ok(())
+}
+
+/// Rebuilds the menu with the current shortcuts. Kiosk mode has no menu
+/// and gets it back on leaving.
+pub fn apply(app: &AppHandle) {
+    if kiosk::is_active(app) {
+        return;
+    }
+    if let Err(err) = menu::build(app).and_then(|menu| app.set_menu(menu)) {
+        tracing::warn!("Failed to apply the menu shortcuts: {err}");
+    }
+}
+
+fn list(settings: &Settings) -> Vec<Keybinding> {
+    ACTIONS
+        .iter()
+        .map(|action| Keybinding {
+            action: action.id,
+            label: action.label,
+            scope: action.scope,
+            accelerator: bound(settings, action),
+            default: action.default,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_keybindings(settings: State<'_, SettingsStore>) -> Vec<Keybinding> {
+    list(&settings.get())
+}
+
+/// Remaps `action` to `accelerator`, or unbinds it with `None`. Refused if
+/// another action has the same shortcut; global ones are also refused when
+/// another app owns them.
+#[tauri::command]
+pub fn set_keybinding(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    action: String,
+    accelerator: Option<String>,
+) -> CommandResult<Vec<Keybinding>> {
+    let found = ACTIONS
+        .iter()
+        .find(|known| known.id == action)
+        .ok_or_else(|| CommandError::invalid("action", format!("No action called {action:?}")))?;
+    let accelerator = accelerator
+        .map(|accelerator| accelerator.trim().to_string())
+        .filter(|accelerator| !accelerator.is_empty());
+
+    let current = settings.get();
+    let mut next = current.clone();
+    match found.id {
+        quick_capture::LABEL => next.quick_capture_shortcut = accelerator.clone(),
+        quick_open::LABEL => next.quick_open_shortcut = accelerator.clone(),
+        id if accelerator.as_deref() == found.default => {
+            next.keybindings.remove(id);
+        }
+        id => {
+            next.keybindings.insert(id.to_string(), accelerator.clone());
+        }
+    }
+    validate(&next).map_err(|err| CommandError::invalid("accelerator", err))?;
+
+    match found.id {
+        quick_capture::LABEL => quick_capture::apply(
+            &app,
+            current.quick_capture_shortcut.as_deref(),
+            accelerator.as_deref(),
+        )?,
+        quick_open::LABEL => quick_open::apply(
+            &app,
+            current.quick_open_shortcut.as_deref(),
+            accelerator.as_deref(),
+        )?,
+        _ => {}
+    }
+    let saved = settings.update(|settings| *settings = next)?;
+    if found.scope == KeybindingScope::Menu {
+        apply(&app);
+    }
+    tracing::info!("Set the shortcut for {action} to {accelerator:?}");
+    Ok(list(&saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(bindings: &[(&str, Option<&str>)]) -> Settings {
+        let mut settings = Settings::default();
+        for (id, accelerator) in bindings {
+            settings
+                .keybindings
+                .insert(id.to_string(), accelerator.map(str::to_string));
+        }
+        settings
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        assert!(validate(&Settings::default()).is_ok());
+    }
+
+    #[test]
+    fn a_shortcut_can_only_run_one_action() {
+        let err = validate(&with(&[(menu::ZOOM_IN, Some("CmdOrCtrl+T"))])).unwrap_err();
+        assert_eq!(err, "CmdOrCtrl+T is already the shortcut for New Tab");
+
+        // Spelled differently, still the same keys
+        let settings = with(&[(menu::OPEN_LOGS, Some("shift+cmdorctrl+e"))]);
+        assert!(validate(&settings).is_err());
+
+        let settings = Settings {
+            quick_open_shortcut: Some("CmdOrCtrl+Q".to_string()),
+            ..Settings::default()
+        };
+        assert!(validate(&settings).is_err());
+    }
+
+    #[test]
+    fn unbinding_frees_a_shortcut() {
+        let settings = with(&[(menu::NEW_TAB, None), (menu::ZOOM_IN, Some("CmdOrCtrl+T"))]);
+        assert!(validate(&settings).is_ok());
+        let new_tab = ACTIONS.iter().find(|a| a.id == menu::NEW_TAB).unwrap();
+        assert_eq!(bound(&settings, new_tab), None);
+    }
+
+    #[test]
+    fn unknown_actions_and_shortcuts_are_rejected() {
+        assert!(validate(&with(&[("launch_rockets", Some("CmdOrCtrl+L"))])).is_err());
+        assert!(validate(&with(&[(menu::OPEN_LOGS, Some("CmdOrCtrl+Nope"))])).is_err());
+        assert!(validate(&with(&[(menu::OPEN_LOGS, Some("CmdOrCtrl+A+B"))])).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow, Window, WindowEvent};
+use tauri_plugin_global_shortcut::GlobalShortcutExt;
+
+use crate::lock;
+use crate::main_window;
+use crate::menu;
+use crate::metrics::IpcTimer;
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::{quick_capture, quick_open, tray, widget};
+
+// Salt and hash of the exit PIN, in the same form as the app lock password
+pub const PIN_SECRET: &str = "kiosk-pin";
+// Slows down guessing the PIN at an unattended screen
+const FAILED_EXIT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct KioskChanged {
+    active: bool,
+}
+
+/// Whether the main window is locked to fullscreen for a wall-mounted
+/// display. The mode is kept in the settings, so a launch after a crash or
+/// power cut goes straight back to it.
+#[derive(Default)]
+pub struct Kiosk(Mutex<bool>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Kiosk::default());
+}
+
+pub fn is_active(app: &AppHandle) -> bool {
+    app.try_state::<Kiosk>()
+        .is_some_and(|kiosk| *kiosk.0.lock().unwrap())
+}
+
+/// Goes back into kiosk mode if the last launch ended in it. Runs once the
+/// main window, tray and global shortcuts are set up, as it takes them away.
+pub fn restore(app: &AppHandle) {
+    if !app.state::<SettingsStore>().get().kiosk {
+        return;
+    }
+    tracing::info!("Restoring kiosk mode");
+    if let Err(err) = set_active(app, true) {
+        tracing::error!("Failed to restore kiosk mode: {err}");
+    }
+}
+
+fn set_active(app: &AppHandle, active: bool) -> Result<(), String> {
+    let window = app
+        .get_webview_window(main_window::LABEL)
+        .ok_or("The main window isn't open")?;
+    if active && widget::is_active(app) {
+        widget::toggle_widget_mode(app.clone())?;
+    }
+    *app.state::<Kiosk>().0.lock().unwrap() = active;
+    let result = if active {
+        enter(app, &window)
+    } else {
+        leave(app, &window)
+    };
+    platform::restart_on_crash(active);
+    let _ = app.emit("kiosk-changed", KioskChanged { active });
+    result.map_err(|err| err.to_string())
+}
+
+// Nothing but the page is left: no frame, menu, tray, dev tools or
+// shortcuts that would open or leave other windows
+fn enter(app: &AppHandle, window: &WebviewWindow) -> tauri::Result<()> {
+    window.set_decorations(false)?;
+    window.set_always_on_top(true)?;
+    window.set_fullscreen(true)?;
+    app.remove_menu()?;
+    if let Some(tray) = app.tray_by_id(tray::ID) {
+        tray.set_visible(false)?;
+    }
+    if let Err(err) = app.global_shortcut().unregister_all() {
+        tracing::warn!("Failed to unregister global shortcuts: {err}");
+    }
+    // Release builds have no dev tools to begin with
+    #[cfg(debug_assertions)]
+    window.close_devtools();
+    window.with_webview(|webview| platform::lock_webview(webview, true))?;
+    window.show()?;
+    window.set_focus()
+}
+
+fn leave(app: &AppHandle, window: &WebviewWindow) -> tauri::Result<()> {
+    let config = main_window::config(app);
+    window.set_fullscreen(false)?;
+    window.set_always_on_top(false)?;
+    window.set_decorations(config.decorations)?;
+    app.set_menu(menu::build(app)?)?;
+    if let Some(tray) = app.tray_by_id(tray::ID) {
+        tray.set_visible(true)?;
+    }
+    quick_capture::init(app);
+    quick_open::init(app);
+    window.with_webview(|webview| platform::lock_webview(webview, false))
+}
+
+/// Keeps the main window in place while in kiosk mode: closing it does
+/// nothing and leaving fullscreen, e.g. with the macOS green button, is
+/// undone.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != main_window::LABEL || !is_active(window.app_handle()) {
+        return;
+    }
+    match event {
+        WindowEvent::CloseRequested { api, .. } => api.prevent_close(),
+        WindowEvent::Resized(_) if !window.is_fullscreen().unwrap_or(true) => {
+            let _ = window.set_fullscreen(true);
+        }
+        _ => {}
+    }
+}
+
+/// Switches the main window to borderless fullscreen without dev tools,
+/// menus or shortcuts. With a `pin`, `exit_kiosk` asks for it.
+#[tauri::command]
+pub fn enter_kiosk(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    pin: Option<String>,
+) -> Result<(), String> {
+    if is_active(&app) {
+        return Err("Kiosk mode is already on".to_string());
+    }
+    match &pin {
+        Some(pin) if pin.is_empty() => return Err("The PIN must not be empty".to_string()),
+        Some(pin) => secrets::set(PIN_SECRET, &lock::hash_password(pin)?)?,
+        None => secrets::delete(PIN_SECRET)?,
+    }
+    set_active(&app, true)?;
+    settings
+        .update(|settings| settings.kiosk = true)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Rejects with "Wrong PIN" when kiosk mode was entered with a different one
+#[tauri::command]
+pub async fn exit_kiosk(
+    _timer: IpcTimer,
+    app: AppHandle,
+    pin: Option<String>,
+) -> Result<(), String> {
+    if !is_active(&app) {
+        return Ok(());
+    }
+    if let Some(stored) = secrets::get(PIN_SECRET)? {
+        let matches = match &pin {
+            Some(pin) => lock::verify_password(&stored, pin)?,
+            None => false,
+        };
+        if !matches {
+            tokio::time::sleep(FAILED_EXIT_DELAY).await;
+            return Err("Wrong PIN".to_string());
+        }
+    }
+    set_active(&app, false)?;
+    secrets::delete(PIN_SECRET)?;
+    app.state::<SettingsStore>()
+        .update(|settings| settings.kiosk = false)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::{SettingsExt, WebInspectorExt, WebViewExt};
+
+    pub fn lock_webview(webview: PlatformWebview, locked: bool) {
+        let view = webview.inner();
+        if let Some(settings) = WebViewExt::settings(&view) {
+            settings.set_enable_developer_extras(!locked && cfg!(debug_assertions));
+        }
+        if locked {
+            if let Some(inspector) = view.inspector() {
+                inspector.close();
+            }
+        }
+    }
+
+    pub fn restart_on_crash(_enabled: bool) {}
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2Settings3;
+    use windows::core::Interface;
+    use windows_sys::Win32::System::Recovery::{
+        RegisterApplicationRestart, UnregisterApplicationRestart,
+    };
+
+    // Also turns off WebView2's own shortcuts, like reload, find and print
+    pub fn lock_webview(webview: PlatformWebview, locked: bool) {
+        let result = unsafe {
+            webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.Settings())
+                .and_then(|settings| {
+                    settings.SetAreDevToolsEnabled(!locked && cfg!(debug_assertions))?;
+                    settings
+                        .cast::<ICoreWebView2Settings3>()?
+                        .SetAreBrowserAcceleratorKeysEnabled(!locked)
+                })
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to change the webview's shortcuts: {err}");
+        }
+    }
+
+    // Windows Error Reporting starts the app again after a crash or hang,
+    // once it has been running for a minute
+    pub fn restart_on_crash(enabled: bool) {
+        let result = unsafe {
+            if enabled {
+                RegisterApplicationRestart(std::ptr::null(), 0)
+            } else {
+                UnregisterApplicationRestart()
+            }
+        };
+        if result < 0 {
+            tracing::warn!("Failed to change restart on crash: {result:#x}");
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use tauri::webview::PlatformWebview;
+
+    pub fn lock_webview(_webview: PlatformWebview, _locked: bool) {}
+
+    // Elsewhere a supervisor like launchd has to relaunch the app, which
+    // then restores kiosk mode on its own
+    pub fn restart_on_crash(_enabled: bool) {}
+}
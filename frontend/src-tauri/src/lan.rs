@@ -0,0 +1,230 @@
+use std::fs;
+use std::io;
+use std::net::{IpAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar};
+
+// The frontend build the backend serves to other devices, copied out of the
+// app's embedded assets
+const WEB_DIR: &str = "lan-web";
+// Never contacted; connecting a UDP socket only picks the interface with the
+// default route, whose address is the one other devices can reach
+const ROUTE_PROBE: &str = "192.0.2.1:80";
+const QR_SIZE: u32 = 240;
+
+/// What a phone on the same network scans to open the app
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanQr {
+    /// The URL with the pairing token, also the QR code's payload
+    url: String,
+    /// The QR code as an SVG document
+    svg: String,
+}
+
+/// The pairing token devices on the LAN authenticate with. It only lives
+/// as long as the process, so a restart means scanning the code again, as
+/// the backend's port changes anyway.
+#[derive(Default)]
+pub struct Lan(Mutex<Option<String>>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Lan::default());
+    if app.state::<SettingsStore>().get().lan_access {
+        if let Err(err) = enable(app) {
+            tracing::warn!("Failed to open LAN access: {err}");
+        }
+    }
+}
+
+// Hands out a fresh token and readies the frontend for the backend to serve
+fn enable(app: &AppHandle) -> Result<(), String> {
+    if app.state::<Sidecar>().is_external() {
+        return Err("LAN access needs the bundled backend".to_string());
+    }
+    export_web_app(app).map_err(|err| format!("Failed to copy the web app: {err}"))?;
+    *app.state::<Lan>().0.lock().unwrap() = Some(generate_token());
+    Ok(())
+}
+
+fn web_dir(app: &AppHandle) -> Option<PathBuf> {
+    Some(app.path().app_cache_dir().ok()?.join(WEB_DIR))
+}
+
+fn export_web_app(app: &AppHandle) -> io::Result<()> {
+    let dir = web_dir(app).ok_or(io::ErrorKind::NotFound)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    let resolver = app.asset_resolver();
+    let paths: Vec<String> = resolver.iter().map(|(path, _)| path.to_string()).collect();
+    for path in paths {
+        // `iter` yields the assets as stored, possibly compressed; `get`
+        // hands them out ready to serve
+        let Some(asset) = resolver.get(path.clone()) else {
+            continue;
+        };
+        let target = dir.join(path.trim_start_matches('/'));
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, asset.bytes())?;
+    }
+    Ok(())
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS random number generator unavailable");
+    hex::encode(bytes)
+}
+
+/// Environment for the backend: listening on every interface, with the
+/// pairing token and the web app to serve, while LAN access is on, and on
+/// loopback only otherwise
+pub fn env(app: &AppHandle) -> Vec<(&'static str, String)> {
+    let token = app.state::<Lan>().0.lock().unwrap().clone();
+    let (Some(token), Some(dir)) = (token, web_dir(app)) else {
+        return vec![("HAMBA_HOST", "127.0.0.1".to_string())];
+    };
+    vec![
+        ("HAMBA_HOST", "0.0.0.0".to_string()),
+        ("HAMBA_LAN_TOKEN", token),
+        ("HAMBA_WEB_DIR", dir.to_string_lossy().into_owned()),
+    ]
+}
+
+/// The address other devices on the network reach this machine at
+pub fn lan_address() -> io::Result<IpAddr> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.connect(ROUTE_PROBE)?;
+    let address = socket.local_addr()?.ip();
+    if address.is_unspecified() || address.is_loopback() {
+        return Err(io::ErrorKind::NotConnected.into());
+    }
+    Ok(address)
+}
+
+/// Opens the backend to other devices on the network, or closes it again.
+/// Closing restarts the backend on loopback with the token gone, which
+/// drops every paired device.
+#[tauri::command]
+pub async fn set_lan_access(_timer: IpcTimer, app: AppHandle, enabled: bool) -> Result<(), String> {
+    if enabled {
+        let backend = app.state::<Sidecar>().backend_path()?;
+        platform::allow_through_firewall(&backend)?;
+        enable(&app)?;
+    } else {
+        *app.state::<Lan>().0.lock().unwrap() = None;
+    }
+    app.state::<SettingsStore>()
+        .update(|settings| settings.lan_access = enabled)
+        .map_err(|err| err.to_string())?;
+    tracing::info!("LAN access {}", if enabled { "on" } else { "off" });
+    if !sidecar::restart(&app).await {
+        return Err("The backend did not come back up".to_string());
+    }
+    Ok(())
+}
+
+/// The URL a phone opens to pair with the app, and a QR code of it. Over
+/// HTTPS the certificate is self-signed, so the phone's browser asks to
+/// trust it the first time.
+#[tauri::command]
+pub fn get_lan_url_qr(lan: State<'_, Lan>, sidecar: State<'_, Sidecar>) -> Result<LanQr, String> {
+    let token = lan.0.lock().unwrap().clone();
+    let token = token.ok_or("LAN access is off")?;
+    let address = lan_address().map_err(|_| "Not connected to a network".to_string())?;
+    // The certificate is made at launch for the address the machine had
+    // then
+    if let Some(tls) = sidecar.tls() {
+        if !tls.names.contains(&address.to_string()) {
+            return Err(format!(
+                "This machine's address changed to {address} since Hamba started; restart Hamba to pair devices"
+            ));
+        }
+    }
+    let scheme = if sidecar.tls().is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let host = match address {
+        IpAddr::V4(address) => address.to_string(),
+        IpAddr::V6(address) => format!("[{address}]"),
+    };
+    let url = format!("{scheme}://{host}:{}/?pair={token}", sidecar.port());
+    let svg = QrCode::new(&url)
+        .map_err(|err| err.to_string())?
+        .render::<svg::Color>()
+        .min_dimensions(QR_SIZE, QR_SIZE)
+        .build();
+    Ok(LanQr { url, svg })
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+
+    const RULE_NAME: &str = "Hamba LAN access";
+
+    // Windows would otherwise ask about the backend the first time it
+    // listens, in a prompt that is easy to dismiss for good. Adding the rule
+    // needs an administrator, so it goes through one elevation prompt, once.
+    pub fn allow_through_firewall(backend: &Path) -> Result<(), String> {
+        let shown = Command::new("netsh")
+            .args(["advfirewall", "firewall", "show", "rule"])
+            .arg(format!("name={RULE_NAME}"))
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(|err| err.to_string())?;
+        if shown.status.success() {
+            return Ok(());
+        }
+
+        let arguments = format!(
+            "advfirewall firewall add rule name=\"{RULE_NAME}\" dir=in action=allow \
+             profile=private program=\"{}\" enable=yes",
+            backend.display()
+        );
+        let script = format!(
+            "$p = Start-Process netsh -Verb RunAs -Wait -PassThru -WindowStyle Hidden \
+             -ArgumentList '{}'; exit $p.ExitCode",
+            arguments.replace('\'', "''")
+        );
+        let status = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .status()
+            .map_err(|err| err.to_string())?;
+        if !status.success() {
+            return Err("Windows Firewall was not allowed to let other devices in".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use std::path::Path;
+
+    // macOS asks about incoming connections by itself the first time the
+    // backend listens; Linux desktops rarely filter incoming traffic, and
+    // changing that needs root
+    pub fn allow_through_firewall(_backend: &Path) -> Result<(), String> {
+        Ok(())
+    }
+}
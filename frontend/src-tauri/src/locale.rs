@@ -0,0 +1,356 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::settings::SettingsStore;
+
+// Only Windows and macOS tell running apps about a new region, and neither
+// in a way that reaches here without a window, so the OS is asked again
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const FALLBACK: &str = "en-US";
+// Regions whose week starts on Sunday or Saturday, from CLDR; everywhere
+// else it starts on Monday
+const SUNDAY_FIRST: &[&str] = &[
+    "AG", "AS", "BD", "BR", "BS", "BT", "BW", "BZ", "CA", "CN", "CO", "DM", "DO", "ET", "GT", "GU",
+    "HK", "HN", "ID", "IL", "IN", "JM", "JP", "KE", "KH", "KR", "LA", "MH", "MM", "MO", "MT", "MX",
+    "MZ", "NI", "NP", "PA", "PE", "PH", "PK", "PR", "PT", "PY", "SA", "SG", "SV", "TH", "TT", "TW",
+    "UM", "US", "VE", "VI", "WS", "YE", "ZA", "ZW",
+];
+const SATURDAY_FIRST: &[&str] = &[
+    "AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY",
+];
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    Dmy,
+    Mdy,
+    Ymd,
+}
+
+/// The language and formatting preferences to show dates and numbers with.
+/// The formatting fields are the OS's, which the user may have changed from
+/// what their locale would use.
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Locale {
+    /// BCP 47 tag to format with, e.g. `de-CH`: the override from the
+    /// settings if there is one, else the OS's
+    locale: String,
+    /// The OS's own tag, whatever the override
+    system_locale: String,
+    /// 0 for Sunday through 6 for Saturday, as `Date.getDay()` counts
+    first_day_of_week: u8,
+    decimal_separator: String,
+    grouping_separator: String,
+    uses_24_hour_clock: bool,
+    date_order: DateOrder,
+}
+
+/// What the OS was set to when last asked
+pub struct SystemLocale(Mutex<Locale>);
+
+pub fn init(app: &AppHandle) {
+    let system = platform::detect();
+    tracing::info!("System locale: {}", system.system_locale);
+    app.manage(SystemLocale(Mutex::new(system)));
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Ok(detected) = tauri::async_runtime::spawn_blocking(platform::detect).await else {
+                continue;
+            };
+            let changed = {
+                let state = app.state::<SystemLocale>();
+                let mut system = state.0.lock().unwrap();
+                let changed = *system != detected;
+                *system = detected;
+                changed
+            };
+            if changed {
+                tracing::info!("System locale changed");
+                apply(&app);
+            }
+        }
+    });
+}
+
+/// The locale with the override from the settings applied
+pub fn current(app: &AppHandle) -> Locale {
+    let mut locale = app.state::<SystemLocale>().0.lock().unwrap().clone();
+    if let Some(tag) = app.state::<SettingsStore>().get().locale_override {
+        locale.locale = tag;
+    }
+    locale
+}
+
+/// The OS's own tag, whatever the override
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn system_tag(app: &AppHandle) -> String {
+    app.state::<SystemLocale>()
+        .0
+        .lock()
+        .unwrap()
+        .system_locale
+        .clone()
+}
+
+/// Tells every window after the OS region or the override changed. The
+/// backend only picks it up on its next start.
+pub fn apply(app: &AppHandle) {
+    let _ = app.emit("locale-changed", current(app));
+}
+
+/// What the backend is told, so it can format what it sends out the same way
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    let locale = current(app);
+    vec![
+        ("HAMBA_LOCALE".to_string(), locale.locale),
+        (
+            "HAMBA_FIRST_DAY_OF_WEEK".to_string(),
+            locale.first_day_of_week.to_string(),
+        ),
+    ]
+}
+
+/// Checks the shape of a BCP 47 tag like `pt-BR` or `zh-Hant-TW`, not that
+/// the language exists
+pub fn validate(tag: &str) -> Result<(), String> {
+    let mut parts = tag.split('-');
+    let language = parts.next().unwrap_or_default();
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| {
+            (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    if !valid {
+        return Err(format!("{tag:?} is not a locale like en-US"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_system_locale(app: AppHandle) -> Locale {
+    current(&app)
+}
+
+// `en_US.UTF-8`, `en_US@rg=...` or `en-US` -> `en-US`
+fn to_tag(name: &str) -> Option<String> {
+    let name = name.split(['.', '@']).next().unwrap_or_default();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    Some(name.replace('_', "-"))
+}
+
+fn first_day_for_region(tag: &str) -> u8 {
+    let region = tag
+        .split('-')
+        .skip(1)
+        .find(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(|region| region.to_ascii_uppercase());
+    match region.as_deref() {
+        Some(region) if SUNDAY_FIRST.contains(&region) => 0,
+        Some(region) if SATURDAY_FIRST.contains(&region) => 6,
+        _ => 1,
+    }
+}
+
+// Works on Unicode (`dd.MM.y`), Windows (`M/d/yyyy`) and strftime
+// (`%d.%m.%Y`) patterns alike
+fn date_order(pattern: &str) -> DateOrder {
+    let position = |field: char| pattern.find(|c: char| c.eq_ignore_ascii_case(&field));
+    match (position('d'), position('m'), position('y')) {
+        (Some(day), Some(month), Some(year)) if year < month && month < day => DateOrder::Ymd,
+        (Some(day), Some(month), _) if month < day => DateOrder::Mdy,
+        _ => DateOrder::Dmy,
+    }
+}
+
+fn fallback(tag: Option<String>) -> Locale {
+    let tag = tag.unwrap_or_else(|| FALLBACK.to_string());
+    Locale {
+        first_day_of_week: first_day_for_region(&tag),
+        locale: tag.clone(),
+        system_locale: tag,
+        decimal_separator: ".".to_string(),
+        grouping_separator: ",".to_string(),
+        uses_24_hour_clock: false,
+        date_order: DateOrder::Mdy,
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::Globalization::{
+        GetLocaleInfoEx, GetUserDefaultLocaleName, LOCALE_IFIRSTDAYOFWEEK, LOCALE_SDECIMAL,
+        LOCALE_SSHORTDATE, LOCALE_STHOUSAND, LOCALE_STIMEFORMAT,
+    };
+
+    use super::{date_order, fallback, to_tag, Locale};
+
+    // LOCALE_NAME_MAX_LENGTH
+    const NAME_LENGTH: usize = 85;
+
+    // A null name is the user's default locale, with their own changes to it
+    fn info(kind: u32) -> Option<String> {
+        let mut buffer = [0u16; 128];
+        let length = unsafe {
+            GetLocaleInfoEx(
+                std::ptr::null(),
+                kind,
+                buffer.as_mut_ptr(),
+                buffer.len() as i32,
+            )
+        };
+        (length > 0).then(|| String::from_utf16_lossy(&buffer[..length as usize - 1]))
+    }
+
+    pub fn detect() -> Locale {
+        let mut name = [0u16; NAME_LENGTH];
+        let length = unsafe { GetUserDefaultLocaleName(name.as_mut_ptr(), NAME_LENGTH as i32) };
+        let tag = (length > 0)
+            .then(|| String::from_utf16_lossy(&name[..length as usize - 1]))
+            .and_then(|name| to_tag(&name));
+        let mut locale = fallback(tag);
+        // Counted from Monday
+        if let Some(day) = info(LOCALE_IFIRSTDAYOFWEEK).and_then(|day| day.parse::<u8>().ok()) {
+            locale.first_day_of_week = (day + 1) % 7;
+        }
+        if let Some(decimal) = info(LOCALE_SDECIMAL) {
+            locale.decimal_separator = decimal;
+        }
+        if let Some(grouping) = info(LOCALE_STHOUSAND) {
+            locale.grouping_separator = grouping;
+        }
+        if let Some(time) = info(LOCALE_STIMEFORMAT) {
+            locale.uses_24_hour_clock = time.contains('H');
+        }
+        if let Some(date) = info(LOCALE_SSHORTDATE) {
+            locale.date_order = date_order(&date);
+        }
+        locale
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, CStr};
+
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+
+    use super::{date_order, fallback, to_tag, Locale};
+
+    pub fn detect() -> Locale {
+        unsafe {
+            // Unlike `currentLocale`, these follow changes while running
+            let current: Retained<AnyObject> =
+                msg_send![class!(NSLocale), autoupdatingCurrentLocale];
+            let calendar: Retained<AnyObject> =
+                msg_send![class!(NSCalendar), autoupdatingCurrentCalendar];
+
+            let tag = to_string(msg_send![&*current, localeIdentifier]).and_then(|id| to_tag(&id));
+            let mut locale = fallback(tag);
+            // Counted from 1 for Sunday
+            let first: usize = msg_send![&*calendar, firstWeekday];
+            locale.first_day_of_week = (first.saturating_sub(1) % 7) as u8;
+            if let Some(decimal) = to_string(msg_send![&*current, decimalSeparator]) {
+                locale.decimal_separator = decimal;
+            }
+            if let Some(grouping) = to_string(msg_send![&*current, groupingSeparator]) {
+                locale.grouping_separator = grouping;
+            }
+            // `j` is the locale's preferred hour, `H` when that is 0-23
+            if let Some(hour) = pattern(&current, c"j") {
+                locale.uses_24_hour_clock = hour.contains('H') || hour.contains('k');
+            }
+            if let Some(date) = pattern(&current, c"yMd") {
+                locale.date_order = date_order(&date);
+            }
+            locale
+        }
+    }
+
+    unsafe fn pattern(locale: &AnyObject, template: &CStr) -> Option<String> {
+        unsafe {
+            let ptr: *const c_char = template.as_ptr();
+            let template: Option<Retained<AnyObject>> =
+                msg_send![class!(NSString), stringWithUTF8String: ptr];
+            to_string(msg_send![
+                class!(NSDateFormatter),
+                dateFormatFromTemplate: &*template?,
+                options: 0usize,
+                locale: locale
+            ])
+        }
+    }
+
+    unsafe fn to_string(text: Option<Retained<AnyObject>>) -> Option<String> {
+        let text = text?;
+        let ptr: *const c_char = unsafe { msg_send![&*text, UTF8String] };
+        (!ptr.is_null()).then(|| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::env;
+    use std::ffi::CStr;
+
+    use super::{date_order, fallback, to_tag, Locale};
+
+    // GTK has already called `setlocale` with the environment by the time
+    // this runs, so `nl_langinfo` answers for the user's locale
+    fn info(item: libc::nl_item) -> Option<String> {
+        let ptr = unsafe { libc::nl_langinfo(item) };
+        if ptr.is_null() {
+            return None;
+        }
+        let value = unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned();
+        (!value.is_empty()).then_some(value)
+    }
+
+    pub fn detect() -> Locale {
+        let tag = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find_map(|value| to_tag(&value));
+        let mut locale = fallback(tag);
+        if let Some(decimal) = info(libc::RADIXCHAR) {
+            locale.decimal_separator = decimal;
+        }
+        if let Some(grouping) = info(libc::THOUSEP) {
+            locale.grouping_separator = grouping;
+        }
+        if let Some(time) = info(libc::T_FMT) {
+            locale.uses_24_hour_clock = ["%H", "%R", "%T", "%k"]
+                .iter()
+                .any(|field| time.contains(field));
+        }
+        if let Some(date) = info(libc::D_FMT) {
+            locale.date_order = date_order(&date);
+        }
+        locale
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use super::{fallback, Locale};
+
+    pub fn detect() -> Locale {
+        fallback(None)
+    }
+}
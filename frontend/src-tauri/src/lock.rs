@@ -0,0 +1,454 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindowBuilder, Window, WindowEvent};
+
+use crate::auth;
+use crate::frontend_bundle;
+use crate::main_window;
+use crate::metrics::IpcTimer;
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::theme;
+use crate::tls;
+
+pub const LABEL: &str = "lock";
+// Salt and hash of the unlock password or PIN, see `hash_password`
+pub const LOCK_SECRET: &str = "app-lock";
+// Slows down guessing the password from the lock screen
+const FAILED_UNLOCK_DELAY: Duration = Duration::from_secs(1);
+const PBKDF2_PREFIX: &str = "pbkdf2-sha256";
+// A PIN only has so many combinations, so each guess is made expensive
+const PBKDF2_ROUNDS: u32 = 200_000;
+const PIN_LENGTH: std::ops::RangeInclusive<usize> = 4..=12;
+// What the lock window may still call while locked
+const ALLOWED_WHILE_LOCKED: &[&str] = &[
+    "is_app_locked",
+    "get_app_lock",
+    "unlock_app",
+    "get_system_theme",
+    "get_accessibility_prefs",
+];
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AppLockChanged {
+    locked: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLock {
+    /// A password or PIN is set
+    enabled: bool,
+    /// It's a PIN, so the lock screen asks for digits
+    pin: bool,
+    locked: bool,
+}
+
+// A window blanked while locked, with the page to load again once unlocked
+struct Covered {
+    label: String,
+    url: tauri::Url,
+    visible: bool,
+}
+
+#[derive(Default)]
+struct LockState {
+    locked: bool,
+    covered: Vec<Covered>,
+}
+
+/// Whether the app is locked until the user enters the password or PIN
+/// again. While it is, every other window is hidden with its page unloaded,
+/// so its script can't reach plugin commands either, and IPC calls outside
+/// `ALLOWED_WHILE_LOCKED` are denied. The lock only lives as long as the
+/// process does.
+#[derive(Default)]
+pub struct Lock(Mutex<LockState>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Lock::default());
+}
+
+pub fn is_locked(app: &AppHandle) -> bool {
+    app.try_state::<Lock>()
+        .is_some_and(|lock| lock.0.lock().unwrap().locked)
+}
+
+/// Whether `command` may run while the app is locked
+pub fn allows(command: &str) -> bool {
+    ALLOWED_WHILE_LOCKED.contains(&command)
+}
+
+/// Locks the app if a password or PIN is set, e.g. once the user has been
+/// away or before the machine sleeps
+pub fn lock_if_enabled(app: &AppHandle) {
+    if !app.state::<SettingsStore>().get().lock_when_idle {
+        return;
+    }
+    match is_configured() {
+        Ok(true) => set_locked(app, true),
+        // Locking without a password to check would lock the user out
+        Ok(false) => tracing::warn!("Locking is on but no password or PIN is set, not locking"),
+        Err(err) => tracing::warn!("Not locking, the keychain is unavailable: {err}"),
+    }
+}
+
+// Only a stored password or PIN counts, whatever the settings say
+fn is_configured() -> Result<bool, String> {
+    Ok(secrets::get(LOCK_SECRET)?.is_some())
+}
+
+fn set_locked(app: &AppHandle, locked: bool) {
+    let changed = {
+        let lock = app.state::<Lock>();
+        let mut state = lock.0.lock().unwrap();
+        std::mem::replace(&mut state.locked, locked) != locked
+    };
+    if locked {
+        auth::forget(app);
+    }
+    if !changed {
+        return;
+    }
+    tracing::info!("App {}", if locked { "locked" } else { "unlocked" });
+    if locked {
+        cover(app);
+    } else {
+        uncover(app);
+    }
+    let _ = app.emit("app-lock-changed", AppLockChanged { locked });
+}
+
+// Window changes have to happen on the main thread, and the lock may come
+// from the idle poller or the power listener
+fn cover(app: &AppHandle) {
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        // Hidden windows keep running their page, so those in the tray are
+        // blanked too. A blank page isn't the app's, so it gets no IPC.
+        let blank = tauri::Url::parse("about:blank").expect("about:blank is a URL");
+        let covered: Vec<Covered> = handle
+            .webview_windows()
+            .into_iter()
+            .filter(|(label, _)| label != LABEL)
+            .filter_map(|(label, window)| {
+                let visible = window.is_visible().unwrap_or(false);
+                let _ = window.hide();
+                let url = window.url().ok()?;
+                if let Err(err) = window.navigate(blank.clone()) {
+                    tracing::warn!("Failed to blank {label} while locked: {err}");
+                }
+                Some(Covered {
+                    label,
+                    url,
+                    visible,
+                })
+            })
+            .collect();
+        handle.state::<Lock>().0.lock().unwrap().covered = covered;
+        if let Err(err) = open_window(&handle) {
+            tracing::warn!("Failed to open the lock window: {err}");
+        }
+    });
+}
+
+fn uncover(app: &AppHandle) {
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        let covered = std::mem::take(&mut handle.state::<Lock>().0.lock().unwrap().covered);
+        if let Some(window) = handle.get_webview_window(LABEL) {
+            let _ = window.destroy();
+        }
+        for covered in covered {
+            let Some(window) = handle.get_webview_window(&covered.label) else {
+                continue;
+            };
+            if let Err(err) = window.navigate(covered.url) {
+                tracing::warn!("Failed to reload {} after unlocking: {err}", covered.label);
+            }
+            if covered.visible {
+                let _ = window.show();
+                if covered.label == main_window::LABEL {
+                    let _ = window.set_focus();
+                }
+            }
+        }
+    });
+}
+
+fn open_window(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        window.show()?;
+        return window.set_focus();
+    }
+    let window = WebviewWindowBuilder::new(app, LABEL, frontend_bundle::url("lock"))
+        .title("Hamba is locked")
+        .inner_size(420.0, 360.0)
+        .resizable(false)
+        .minimizable(false)
+        .maximizable(false)
+        .always_on_top(true)
+        .center()
+        .focused(true)
+        .initialization_script(main_window::initialization_script(app)?)
+        .background_color(theme::background(
+            app.state::<SettingsStore>().get().theme_override,
+        ))
+        .build()?;
+    tls::trust_backend(&window);
+    Ok(())
+}
+
+/// Brings up the lock window instead of whatever was asked for while locked
+pub fn focus(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// The lock window can't be closed while locked
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != LABEL {
+        return;
+    }
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if is_locked(window.app_handle()) {
+            api.prevent_close();
+        }
+    }
+}
+
+fn pbkdf2(salt: &[u8], password: &str, rounds: u32) -> [u8; 32] {
+    let mac =
+        Hmac::<Sha256>::new_from_slice(password.as_bytes()).expect("HMAC takes keys of any length");
+    let mut block = mac.clone();
+    block.update(salt);
+    block.update(&1u32.to_be_bytes());
+    let mut u: [u8; 32] = block.finalize().into_bytes().into();
+    let mut output = u;
+    for _ in 1..rounds {
+        let mut next = mac.clone();
+        next.update(&u);
+        u = next.finalize().into_bytes().into();
+        output
+            .iter_mut()
+            .zip(u)
+            .for_each(|(out, byte)| *out ^= byte);
+    }
+    output
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Salts and hashes `password` into the
+/// `pbkdf2-sha256:<rounds>:<salt>:<hash>` form kept in the keychain
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    getrandom::fill(&mut salt).map_err(|err| err.to_string())?;
+    let hash = pbkdf2(&salt, password, PBKDF2_ROUNDS);
+    Ok(format!(
+        "{PBKDF2_PREFIX}:{PBKDF2_ROUNDS}:{}:{}",
+        hex::encode(salt),
+        hex::encode(hash)
+    ))
+}
+
+/// Whether `password` matches one stored by `hash_password`, or by earlier
+/// versions of it
+pub fn verify_password(stored: &str, password: &str) -> Result<bool, String> {
+    let corrupt = || "The stored password is corrupt".to_string();
+    let parts: Vec<&str> = stored.split(':').collect();
+    let (expected, actual) = match parts[..] {
+        [PBKDF2_PREFIX, rounds, salt, expected] => {
+            let rounds: u32 = rounds.parse().map_err(|_| corrupt())?;
+            let salt = hex::decode(salt).map_err(|_| corrupt())?;
+            (expected, pbkdf2(&salt, password, rounds).to_vec())
+        }
+        _ => return Err(corrupt()),
+    };
+    let expected = hex::decode(expected).map_err(|_| corrupt())?;
+    Ok(constant_time_eq(&expected, &actual))
+}
+
+fn validate_pin(pin: &str) -> Result<(), String> {
+    if !PIN_LENGTH.contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!(
+            "The PIN must be {} to {} digits",
+            PIN_LENGTH.start(),
+            PIN_LENGTH.end()
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn is_app_locked(app: AppHandle) -> bool {
+    is_locked(&app)
+}
+
+#[tauri::command]
+pub fn get_app_lock(app: AppHandle, settings: State<'_, SettingsStore>) -> AppLock {
+    let settings = settings.get();
+    AppLock {
+        enabled: settings.lock_when_idle,
+        pin: settings.app_lock_pin,
+        locked: is_locked(&app),
+    }
+}
+
+/// Locks the app right away, as it would after `idle_minutes` away
+#[tauri::command]
+pub fn lock_app(app: AppHandle) -> Result<(), String> {
+    if !is_configured()? {
+        return Err("Set an app lock password or PIN first".to_string());
+    }
+    set_locked(&app, true);
+    Ok(())
+}
+
+/// Takes the password or PIN, whichever was set
+#[tauri::command]
+pub async fn unlock_app(_timer: IpcTimer, app: AppHandle, password: String) -> Result<(), String> {
+    // The app never locks without one, so a missing password is refused
+    // rather than taken as a match; quitting ends the lock either way
+    let Some(stored) = secrets::get(LOCK_SECRET)? else {
+        tracing::warn!("Refused to unlock, no app lock password or PIN is set");
+        return Err("No app lock password or PIN is set".to_string());
+    };
+    let matches = tauri::async_runtime::spawn_blocking(move || verify_password(&stored, &password))
+        .await
+        .map_err(|err| err.to_string())??;
+    if !matches {
+        tokio::time::sleep(FAILED_UNLOCK_DELAY).await;
+        let pin = app.state::<SettingsStore>().get().app_lock_pin;
+        return Err(if pin { "Wrong PIN" } else { "Wrong password" }.to_string());
+    }
+    set_locked(&app, false);
+    Ok(())
+}
+
+/// Sets the password or, with `pin`, the PIN the lock screen asks for and
+/// turns on locking, or with `None` removes it and turns locking off
+#[tauri::command]
+pub async fn set_app_lock(
+    _timer: IpcTimer,
+    app: AppHandle,
+    password: Option<String>,
+    pin: Option<bool>,
+) -> Result<(), String> {
+    let pin = pin.unwrap_or(false);
+    match &password {
+        Some(password) if password.is_empty() => {
+            return Err("The password must not be empty".to_string());
+        }
+        Some(password) => {
+            if pin {
+                validate_pin(password)?;
+            }
+            let password = password.clone();
+            let hashed = tauri::async_runtime::spawn_blocking(move || hash_password(&password))
+                .await
+                .map_err(|err| err.to_string())??;
+            secrets::set(LOCK_SECRET, &hashed)?;
+        }
+        None => {
+            secrets::delete(LOCK_SECRET)?;
+            set_locked(&app, false);
+        }
+    }
+    app.state::<SettingsStore>()
+        .update(|settings| {
+            settings.lock_when_idle = password.is_some();
+            settings.app_lock_pin = password.is_some() && pin;
+        })
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The RFC 6070 inputs, with PBKDF2-HMAC-SHA256 outputs as RFC 6070 only
+    // lists SHA-1 ones
+    #[test]
+    fn pbkdf2_matches_reference_vectors() {
+        let cases: &[(&str, &[u8], u32, &str)] = &[
+            (
+                "password",
+                b"salt",
+                1,
+                "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            ),
+            (
+                "password",
+                b"salt",
+                2,
+                "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43",
+            ),
+            (
+                "password",
+                b"salt",
+                4096,
+                "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a",
+            ),
+            (
+                "passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1",
+            ),
+            (
+                "pass\0word",
+                b"sa\0lt",
+                4096,
+                "89b69d0516f829893c696226650a86878c029ac13ee276509d5ae58b6466a724",
+            ),
+        ];
+        for (password, salt, rounds, expected) in cases {
+            assert_eq!(hex::encode(pbkdf2(salt, password, *rounds)), *expected);
+        }
+    }
+
+    #[test]
+    fn verify_password_round_trips() {
+        let stored = hash_password("1234").unwrap();
+        assert!(stored.starts_with("pbkdf2-sha256:200000:"));
+        assert!(verify_password(&stored, "1234").unwrap());
+        assert!(!verify_password(&stored, "1235").unwrap());
+    }
+
+    #[test]
+    fn verify_password_reads_cheap_hashes() {
+        let stored = format!(
+            "{PBKDF2_PREFIX}:1:{}:120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            hex::encode(b"salt")
+        );
+        assert!(verify_password(&stored, "password").unwrap());
+        assert!(!verify_password(&stored, "Password").unwrap());
+    }
+
+    #[test]
+    fn verify_password_rejects_corrupt_hashes() {
+        assert!(verify_password("", "x").is_err());
+        assert!(verify_password("pbkdf2-sha256:lots:00:00", "x").is_err());
+        assert!(verify_password("zz:00", "x").is_err());
+    }
+
+    #[test]
+    fn pins_are_digits() {
+        assert!(validate_pin("1234").is_ok());
+        assert!(validate_pin("123").is_err());
+        assert!(validate_pin("12a4").is_err());
+        assert!(validate_pin("1234567890123").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::data_dir;
+
+const LOG_FILE: &str = "hamba.log";
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+// hamba.log.1 is the most recent rotated file, hamba.log.5 the oldest
+const MAX_ROTATED_FILES: u32 = 5;
+const DEFAULT_RECENT_LINES: usize = 200;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Also kept outside the app state, for `fatal` once there is no app
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub struct Logging {
+    dir: Option<PathBuf>,
+    filter: reload::Handle<EnvFilter, Registry>,
+}
+
+/// Installs the global tracing subscriber, writing to stderr and to a log file
+/// in the app log dir that rotates daily or once it grows past 5 MB.
+pub fn init(app: &AppHandle) {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+
+    let dir = data_dir::log_dir(app);
+    let file = dir
+        .as_ref()
+        .map(|dir| RotatingFile::open(dir.join(LOG_FILE)))
+        .transpose();
+    let (file, file_err) = match file {
+        Ok(file) => (file, None),
+        Err(err) => (None, Some(err)),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(io::stderr))
+        .with(file.map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file))))
+        .init();
+
+    if let Some(err) = file_err {
+        tracing::warn!("Logs will not be written to disk: {err}");
+    } else if let Some(dir) = &dir {
+        let _ = DIR.set(dir.clone());
+    }
+
+    app.manage(Logging {
+        dir,
+        filter: handle,
+    });
+}
+
+/// Changes the log level (or any `EnvFilter` directive, e.g. `info,hamba=debug`)
+pub fn set_level(app: &AppHandle, level: &str) {
+    let filter = match EnvFilter::try_new(level) {
+        Ok(filter) => filter,
+        Err(err) => {
+            tracing::warn!("Ignoring invalid log level {level:?}: {err}");
+            return;
+        }
+    };
+    if let Err(err) = app.state::<Logging>().filter.reload(filter) {
+        tracing::warn!("Failed to change log level: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_recent_logs(
+    logging: State<'_, Logging>,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let dir = logging.dir.as_ref().ok_or("No log directory available")?;
+    let contents = fs::read_to_string(dir.join(LOG_FILE)).map_err(|err| err.to_string())?;
+
+    let lines = lines.unwrap_or(DEFAULT_RECENT_LINES);
+    let all: Vec<&str> = contents.lines().collect();
+    Ok(all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// The log file and the one rotated out before it, for bundling with a bug
+/// report
+pub fn recent_files(app: &AppHandle) -> Vec<PathBuf> {
+    let logging = app.state::<Logging>();
+    let Some(dir) = &logging.dir else {
+        return Vec::new();
+    };
+    let current = dir.join(LOG_FILE);
+    let previous = rotated_path(&current, 1);
+    [current, previous]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Where the log file is, once `init` has opened it
+pub fn dir() -> Option<&'static Path> {
+    DIR.get().map(PathBuf::as_path)
+}
+
+#[tauri::command]
+pub fn open_log_folder(app: AppHandle) -> Result<(), String> {
+    open_folder(&app)
+}
+
+pub fn open_folder(app: &AppHandle) -> Result<(), String> {
+    let logging = app.state::<Logging>();
+    let dir = logging.dir.as_ref().ok_or("No log directory available")?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|err| err.to_string())
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    day: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let day = metadata.modified().map(day_of).unwrap_or_else(|_| today());
+
+        Ok(Self {
+            path,
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+
+        *self = Self::open(self.path.clone())?;
+        self.day = today();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let too_big = self.size > 0 && self.size + buf.len() as u64 > MAX_LOG_SIZE;
+        if too_big || self.day != today() {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+// Days since the Unix epoch (UTC), which is all daily rotation needs
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
+fn today() -> u64 {
+    day_of(SystemTime::now())
+}
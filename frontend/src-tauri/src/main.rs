@@ -1,32 +1,487 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-#[cfg(not(debug_assertions))]
-use tauri::Manager;
-#[cfg(not(debug_assertions))]
-use tauri_plugin_shell::ShellExt;
+mod accessibility;
+mod attachments;
+mod auth;
+mod autostart;
+mod backend_control;
+mod backend_events;
+mod backend_lock;
+mod backend_log;
+mod backend_port;
+mod backend_swap;
+mod backup;
+mod badge;
+mod battery_saver;
+mod canary;
+mod capture;
+mod cli;
+mod clipboard;
+mod close_guard;
+mod command;
+mod config_bundle;
+mod connectivity;
+mod context_menu;
+mod crash_report;
+mod data_dir;
+mod deep_link;
+mod diagnostics;
+mod disk;
+mod dnd;
+mod drag;
+mod encryption;
+mod env_file;
+mod export;
+mod fatal;
+mod file_associations;
+mod first_run;
+mod frontend_bundle;
+mod fs_scope;
+mod headless;
+mod hooks;
+mod http_fetch;
+mod idle;
+mod importers;
+mod imports;
+mod integrity;
+mod ipc;
+mod keybindings;
+mod kiosk;
+mod lan;
+mod locale;
+mod lock;
+mod logging;
+mod main_window;
+mod maintenance;
+mod menu;
+mod metrics;
+mod migrations;
+mod monitors;
+mod notifications;
+mod os_calendar;
+mod power;
+mod print;
+mod profile;
+mod proxy;
+mod quick_capture;
+mod quick_open;
+mod recent_items;
+mod reminders;
+mod resources;
+mod rollback;
+mod safe_mode;
+mod sandbox;
+mod scheduler;
+mod secrets;
+mod session;
+mod settings;
+mod share;
+mod sidecar;
+mod sidecar_config;
+mod spellcheck;
+mod splash;
+mod startup;
+mod tabs;
+mod telemetry;
+mod theme;
+mod tls;
+mod trash;
+mod tray;
+mod updater;
+mod watch_folders;
+mod watchdog;
+mod webhooks;
+mod webview_cache;
+mod widget;
+mod window_state;
+mod windows;
+mod zoom;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, RunEvent};
+
+use settings::SettingsStore;
 
 fn main() {
+    sandbox::launch_if_requested();
+    let cli = cli::parse(std::env::args_os());
+    let startup = startup::Startup::new(cli.profile_startup);
+    let headless = cli.headless;
+
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before doing any work
+        .plugin(tauri_plugin_single_instance::init(on_second_instance))
+        .manage(cli)
+        .manage(startup)
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_shell::init())
-        .setup(|_app| {
-            // In release mode, spawn the backend sidecar
-            // In dev mode, run backend separately: cd backend && bun run dev
-            #[cfg(not(debug_assertions))]
-            {
-                let sidecar = _app.shell().sidecar("backend")
-                    .expect("Failed to create sidecar command");
-                let (_rx, child) = sidecar.spawn()
-                    .expect("Failed to spawn backend sidecar");
-                _app.manage(child);
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .invoke_handler(ipc::guard(tauri::generate_handler![
+            sidecar::get_backend_port,
+            sidecar::restart_backend,
+            backend_control::get_backend_controls,
+            backend_control::set_backend_log_level,
+            backend_control::set_backend_flag,
+            backend_port::set_backend_port,
+            diagnostics::get_diagnostics,
+            env_file::reload_env_and_restart_backend,
+            logging::get_recent_logs,
+            logging::open_log_folder,
+            hooks::list_hooks,
+            hooks::open_hooks_folder,
+            tray::set_close_to_tray,
+            updater::check_for_updates,
+            updater::install_update,
+            updater::set_update_channel,
+            rollback::get_previous_version,
+            rollback::rollback_update,
+            window_state::reset_window_state,
+            deep_link::take_pending_deep_links,
+            recent_items::set_recent_items,
+            share::share,
+            drag::start_drag,
+            attachments::import_attachment,
+            attachments::get_attachment,
+            attachments::delete_attachment,
+            frontend_bundle::get_frontend_bundle,
+            frontend_bundle::install_frontend_bundle,
+            frontend_bundle::reload_frontend_bundle,
+            frontend_bundle::remove_frontend_bundle,
+            secrets::secret_get,
+            secrets::secret_set,
+            secrets::secret_delete,
+            http_fetch::http_fetch,
+            metrics::get_metrics_endpoint,
+            metrics::reset_metrics_token,
+            autostart::set_autostart,
+            crash_report::list_crash_reports,
+            crash_report::delete_crash_report,
+            crash_report::set_crash_reporting,
+            crash_report::submit_crash_report,
+            data_dir::get_data_dir,
+            backup::create_backup,
+            backup::restore_backup,
+            backup::list_backups,
+            backup::set_backup_schedule,
+            trash::list_trash,
+            trash::restore_from_trash,
+            trash::delete_from_trash,
+            trash::empty_trash,
+            fs_scope::request_fs_access,
+            fs_scope::list_fs_grants,
+            fs_scope::revoke_fs_grant,
+            imports::open_file_dialog,
+            imports::save_file_dialog,
+            imports::import_files,
+            importers::list_importers,
+            importers::import_contacts,
+            config_bundle::export_config_bundle,
+            config_bundle::import_config_bundle,
+            watch_folders::list_watch_folders,
+            watch_folders::add_watch_folder,
+            watch_folders::update_watch_folder,
+            watch_folders::remove_watch_folder,
+            watch_folders::get_watch_folder_history,
+            watch_folders::clear_watch_folder_history,
+            webhooks::list_webhooks,
+            webhooks::add_webhook,
+            webhooks::update_webhook,
+            webhooks::remove_webhook,
+            webhooks::get_webhook_secret,
+            webhooks::test_webhook,
+            webhooks::get_webhook_deliveries,
+            notifications::set_notifications_enabled,
+            notifications::set_notification_category_muted,
+            backend_events::get_backend_events,
+            connectivity::get_connectivity,
+            quick_capture::hide_quick_capture,
+            quick_capture::set_quick_capture_shortcut,
+            quick_open::hide_quick_open,
+            quick_open::open_quick_open_result,
+            quick_open::set_quick_open_shortcut,
+            keybindings::get_keybindings,
+            keybindings::set_keybinding,
+            windows::open_window,
+            windows::close_window,
+            windows::list_windows,
+            session::report_session_state,
+            session::take_session_restore,
+            tabs::open_tab,
+            tabs::close_tab,
+            tabs::list_tabs,
+            theme::get_system_theme,
+            theme::set_theme_override,
+            accessibility::get_accessibility_prefs,
+            accessibility::set_forced_colors_override,
+            locale::get_system_locale,
+            spellcheck::set_spellcheck,
+            spellcheck::get_dictionary_words,
+            spellcheck::add_dictionary_word,
+            spellcheck::remove_dictionary_word,
+            settings::get_settings,
+            settings::update_settings,
+            close_guard::report_unsaved_changes,
+            cli::take_cli_args,
+            export::export_data,
+            file_associations::take_pending_open_files,
+            resources::get_backend_stats,
+            backend_swap::swap_backend,
+            canary::start_canary,
+            canary::mirror_read,
+            canary::get_canary_report,
+            canary::stop_canary,
+            canary::promote_canary,
+            splash::get_splash_stage,
+            badge::set_badge_count,
+            dnd::get_dnd_state,
+            dnd::start_focus_mode,
+            dnd::stop_focus_mode,
+            power::get_power_state,
+            encryption::enable_encryption,
+            maintenance::compact_database,
+            maintenance::verify_database,
+            maintenance::repair_database,
+            os_calendar::set_os_calendar,
+            os_calendar::list_os_calendars,
+            os_calendar::get_os_events,
+            os_calendar::add_os_event,
+            os_calendar::delete_os_event,
+            os_calendar::open_os_calendar,
+            monitors::list_monitors,
+            monitors::move_to_monitor,
+            zoom::set_zoom,
+            zoom::get_zoom,
+            print::print_current_view,
+            print::export_pdf,
+            clipboard::write_clipboard_html,
+            clipboard::write_clipboard_image,
+            clipboard::read_clipboard_image,
+            first_run::get_first_run,
+            first_run::import_legacy_data,
+            first_run::skip_first_run,
+            telemetry::record_telemetry_event,
+            telemetry::set_telemetry_enabled,
+            telemetry::flush_telemetry,
+            proxy::test_proxy,
+            profile::list_profiles,
+            profile::create_profile,
+            profile::delete_profile,
+            profile::switch_profile,
+            widget::toggle_widget_mode,
+            kiosk::enter_kiosk,
+            kiosk::exit_kiosk,
+            webview_cache::get_cache_size,
+            webview_cache::clear_webview_cache,
+            webview_cache::clear_cookies,
+            context_menu::show_context_menu,
+            capture::capture_window,
+            idle::get_idle_seconds,
+            lock::is_app_locked,
+            lock::get_app_lock,
+            lock::lock_app,
+            lock::unlock_app,
+            lock::set_app_lock,
+            auth::authenticate,
+            auth::is_authenticated,
+            auth::forget_authentication,
+            scheduler::schedule_job,
+            scheduler::cancel_job,
+            scheduler::list_jobs,
+            reminders::schedule_reminder,
+            reminders::cancel_reminder,
+            reminders::list_reminders,
+            lan::set_lan_access,
+            lan::get_lan_url_qr,
+        ]))
+        .register_asynchronous_uri_scheme_protocol(attachments::SCHEME, attachments::handle)
+        .register_asynchronous_uri_scheme_protocol(frontend_bundle::SCHEME, frontend_bundle::handle)
+        .menu(menu::build)
+        .on_menu_event(menu::on_menu_event)
+        .on_window_event(window_state::on_window_event)
+        .on_window_event(widget::on_window_event)
+        .on_window_event(kiosk::on_window_event)
+        .on_window_event(tray::on_window_event)
+        .on_window_event(lock::on_window_event)
+        .on_window_event(close_guard::on_window_event)
+        .on_window_event(imports::on_window_event)
+        .on_window_event(quick_capture::on_window_event)
+        .on_window_event(quick_open::on_window_event)
+        .on_window_event(windows::on_window_event)
+        .on_window_event(tabs::on_window_event)
+        .on_window_event(session::on_window_event)
+        .on_window_event(theme::on_window_event)
+        .on_page_load(splash::on_page_load)
+        .setup(|app| {
+            let result = setup(app);
+            // The backend may already be running when a later step fails
+            if result.is_err() && app.try_state::<sidecar::Sidecar>().is_some() {
+                sidecar::stop(app.handle());
+                sidecar::stop_helpers(app.handle());
+            }
+            result
+        })
+        .build(tauri::generate_context!())
+        .unwrap_or_else(|err| fatal::startup_failed(&err, headless))
+        .run(|app, event| match event {
+            // Anything but `app.exit` asks about unsaved changes first, which
+            // exits again with a code once it's safe to
+            RunEvent::ExitRequested {
+                code: None, api, ..
+            } => {
+                api.prevent_exit();
+                close_guard::quit(app);
             }
+            RunEvent::ExitRequested { .. } => tabs::on_exit_requested(app),
+            RunEvent::Exit => {
+                window_state::save(app);
+                session::on_exit(app);
+                canary::stop(app);
+                sidecar::stop(app);
+                sidecar::stop_helpers(app);
+            }
+            #[cfg(target_os = "macos")]
+            RunEvent::Opened { urls } => file_associations::open_from_urls(app, &urls),
+            _ => {}
+        });
+}
+
+fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    startup::span(app.handle(), "core", || {
+        cli::init(app.handle());
+        ipc::init(app.handle());
+        profile::init(app.handle());
+        data_dir::init(app.handle());
+        file_associations::init(app.handle());
+        logging::init(app.handle());
+        crash_report::init(app.handle());
+        migrations::init(app.handle());
+        safe_mode::init(app.handle());
+        settings::init(app.handle());
+        fs_scope::init(app.handle());
+        battery_saver::init(app.handle());
+        metrics::init(app.handle());
+        keybindings::init(app.handle());
+        frontend_bundle::init(app.handle());
+        locale::init(app.handle());
+        first_run::init(app.handle());
+        logging::set_level(app.handle(), &app.state::<SettingsStore>().get().log_level);
+        env_file::init(app.handle());
+        startup::init(app.handle());
+    });
+    // The dialog saying why quits once dismissed
+    if migrations::failed(app.handle()) {
+        return Ok(());
+    }
+
+    let cli = app.state::<cli::Cli>();
+    let (minimized, headless) = (cli.minimized, cli.headless);
+    if safe_mode::is_active(app.handle()) && !headless {
+        webview_cache::clear_on_disk(app.handle());
+    }
+    // Launched at login to run in the background from the tray
+    startup::span(app.handle(), "splash", || {
+        splash::init(app.handle(), !minimized && !headless)
+    });
 
-            #[cfg(debug_assertions)]
-            {
-                println!("Dev mode: Run backend separately with 'cd backend && bun run dev'");
+    // The backend is spawned and polled as early as it can be, so it
+    // starts up while the rest of the app and the main window do
+    startup::span(app.handle(), "sidecar", || {
+        sidecar::init(app.handle());
+        lan::init(app.handle());
+        sidecar::start(app.handle());
+        sidecar::start_helpers(app.handle());
+    });
+    if !headless {
+        // The window starts hidden and is only shown once the backend
+        // answers, so the first requests from the frontend don't fail.
+        let handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+            if !sidecar::wait_until_healthy(&handle).await {
+                tracing::warn!("Backend did not become healthy, showing the window anyway");
             }
+            startup::mark(&handle, "backend-healthy");
+            splash::backend_ready(&handle);
+        });
+    }
 
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    startup::span(app.handle(), "services", || {
+        updater::init(app.handle());
+        rollback::init(app.handle());
+        window_state::init(app.handle());
+        widget::init(app.handle());
+        kiosk::init(app.handle());
+        zoom::init(app.handle());
+        spellcheck::init(app.handle());
+        clipboard::init(app.handle());
+        context_menu::init(app.handle());
+        deep_link::init(app.handle());
+        autostart::init(app.handle());
+        trash::init(app.handle());
+        backup::init(app.handle());
+        hooks::init(app.handle());
+        webhooks::init(app.handle());
+        scheduler::init(app.handle());
+        watch_folders::init(app.handle());
+        connectivity::init(app.handle());
+        telemetry::init(app.handle());
+        watchdog::init(app.handle());
+        resources::init(app.handle());
+        disk::init(app.handle());
+        dnd::init(app.handle());
+        accessibility::init(app.handle());
+        power::init(app.handle());
+        lock::init(app.handle());
+        idle::init(app.handle());
+        auth::init(app.handle());
+        backend_swap::init(app.handle());
+        canary::init(app.handle());
+        encryption::init(app.handle());
+        maintenance::init(app.handle());
+        close_guard::init(app.handle());
+    });
+
+    if headless {
+        #[cfg(target_os = "macos")]
+        app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+        headless::run(app.handle());
+        return Ok(());
+    }
+
+    notifications::init(app.handle());
+    reminders::init(app.handle());
+    backend_events::init(app.handle());
+
+    startup::span(app.handle(), "main-window", || {
+        main_window::create(app.handle())
+    })?;
+    startup::span(app.handle(), "window-services", || -> tauri::Result<()> {
+        theme::init(app.handle());
+        monitors::init(app.handle());
+        tray::init(app.handle())?;
+        quick_capture::init(app.handle());
+        quick_open::init(app.handle());
+        kiosk::restore(app.handle());
+        tabs::init(app.handle());
+        session::init(app.handle());
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+struct SecondInstance {
+    args: Vec<String>,
+    cwd: String,
+}
+
+// Runs in the already running instance whenever the app is launched again
+fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    main_window::focus(app);
+    cli::forward(app, args.clone());
+    file_associations::open_from_args(app, &args, &cwd);
+    let _ = app.emit("second-instance", SecondInstance { args, cwd });
 }
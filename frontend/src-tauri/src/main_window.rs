@@ -0,0 +1,90 @@
+use tauri::utils::config::WindowConfig;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::accessibility;
+use crate::frontend_bundle;
+use crate::lock;
+use crate::monitors;
+use crate::profile::Profile;
+use crate::proxy;
+use crate::safe_mode;
+use crate::settings::SettingsStore;
+use crate::sidecar::Sidecar;
+use crate::spellcheck;
+use crate::theme;
+use crate::tls;
+use crate::window_state;
+use crate::zoom;
+
+pub const LABEL: &str = "main";
+
+// The main window is created here rather than from tauri.conf.json so the
+// backend URL and auth token can be injected before any frontend code runs.
+pub fn create(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+    let override_theme = app.state::<SettingsStore>().get().theme_override;
+    let mut config = config(app);
+    // Hard to mistake a test profile for the real thing
+    let profile = app.state::<Profile>();
+    if !profile.is_default() {
+        config.title = format!("{} ({})", config.title, profile.name);
+    }
+    if safe_mode::is_active(app) {
+        config.title = format!("{} (Safe Mode)", config.title);
+    }
+    if let WebviewUrl::App(path) = &config.url {
+        config.url = frontend_bundle::url(&path.to_string_lossy());
+    }
+    let window = WebviewWindowBuilder::from_config(app, &config)?
+        .initialization_script(initialization_script(app)?)
+        .background_color(theme::background(override_theme))
+        .build()?;
+    tls::trust_backend(&window);
+    proxy::configure(&window);
+    spellcheck::configure(&window);
+    zoom::restore(&window);
+    window_state::restore(&window);
+    monitors::apply_preferred(&window);
+
+    Ok(window)
+}
+
+/// Sets the backend URL and token globals `api.ts` reads, the theme
+/// override, accessibility preferences, spellchecking and whether this is
+/// safe mode. Every window that loads the frontend needs it.
+pub fn initialization_script(app: &AppHandle) -> tauri::Result<String> {
+    let sidecar = app.state::<Sidecar>();
+    Ok(format!(
+        "window.__BACKEND_URL__ = {}; window.__BACKEND_TOKEN__ = {}; window.__SAFE_MODE__ = {}; {} {} {}",
+        serde_json::to_string(&sidecar.url())?,
+        serde_json::to_string(&sidecar.token())?,
+        safe_mode::is_active(app),
+        theme::script(app),
+        accessibility::script(app),
+        spellcheck::script(app)
+    ))
+}
+
+/// The main window's entry in tauri.conf.json
+pub fn config(app: &AppHandle) -> WindowConfig {
+    app.config()
+        .app
+        .windows
+        .iter()
+        .find(|window| window.label == LABEL)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Brings the main window to the front, restoring it if it was minimized.
+/// While the app is locked the lock window comes up instead.
+pub fn focus(app: &AppHandle) {
+    if lock::is_locked(app) {
+        return lock::focus(app);
+    }
+    let Some(window) = app.get_webview_window(LABEL) else {
+        return;
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::process::CommandEvent;
+
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+use crate::sandbox;
+use crate::sidecar::{self, with_suffix, Sidecar, DATABASE_FILE, DATABASE_SIDE_FILES};
+
+// Exit code of `backend maintenance` when the task ran but problems remain,
+// see backend/src/db/maintenance.ts
+const PROBLEMS_FOUND: i32 = 2;
+// The database as it was before the last repair, in case it made things worse
+const BEFORE_REPAIR_SUFFIX: &str = "before-repair";
+
+/// Held for the duration of a task so two never run at once
+#[derive(Default)]
+pub struct Maintenance(tokio::sync::Mutex<()>);
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Task {
+    Compact,
+    Verify,
+    Repair,
+}
+
+impl Task {
+    fn arg(self) -> &'static str {
+        match self {
+            Task::Compact => "compact",
+            Task::Verify => "verify",
+            Task::Repair => "repair",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    task: Task,
+    line: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    /// No problems were left in the database
+    ok: bool,
+    /// Everything the task printed, also sent as `database-maintenance-progress`
+    output: Vec<String>,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Maintenance::default());
+}
+
+/// Rebuilds the database file to give back the space of deleted mail
+#[tauri::command]
+pub async fn compact_database(
+    _timer: IpcTimer,
+    app: AppHandle,
+    maintenance: State<'_, Maintenance>,
+) -> Result<MaintenanceReport, String> {
+    run(&app, &maintenance, Task::Compact).await
+}
+
+/// Checks the database for damage. `ok` is false when something was found.
+#[tauri::command]
+pub async fn verify_database(
+    _timer: IpcTimer,
+    app: AppHandle,
+    maintenance: State<'_, Maintenance>,
+) -> Result<MaintenanceReport, String> {
+    run(&app, &maintenance, Task::Verify).await
+}
+
+/// Rebuilds the indexes and the file, then checks it again. A copy of the
+/// database from before is kept next to it.
+#[tauri::command]
+pub async fn repair_database(
+    _timer: IpcTimer,
+    app: AppHandle,
+    maintenance: State<'_, Maintenance>,
+) -> Result<MaintenanceReport, String> {
+    run(&app, &maintenance, Task::Repair).await
+}
+
+// The backend is stopped for the task, so it doesn't hold the database
+// open, and started again whatever the outcome
+async fn run(
+    app: &AppHandle,
+    maintenance: &Maintenance,
+    task: Task,
+) -> Result<MaintenanceReport, String> {
+    if app.state::<Sidecar>().is_external() {
+        return Err("The backend is not run by the app in this mode".to_string());
+    }
+    let _guard = maintenance
+        .0
+        .try_lock()
+        .map_err(|_| "Database maintenance is already running".to_string())?;
+
+    progress(app, task, "Stopping the backend".to_string());
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle)).await;
+
+    let prepared = match task {
+        Task::Repair => keep_copy(app),
+        _ => Ok(()),
+    };
+    let result = match prepared {
+        Ok(()) => run_task(app, task).await,
+        Err(err) => Err(err),
+    };
+
+    progress(app, task, "Starting the backend".to_string());
+    sidecar::start(app);
+    let healthy = sidecar::wait_until_healthy(app).await;
+    let report = result?;
+    if !healthy {
+        return Err("The backend did not come back up after maintenance".to_string());
+    }
+    tracing::info!("Database {} finished, ok: {}", task.arg(), report.ok);
+    Ok(report)
+}
+
+async fn run_task(app: &AppHandle, task: Task) -> Result<MaintenanceReport, String> {
+    let (mut rx, child) = sidecar::backend_command(app, &["maintenance", task.arg()])?
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    let _confinement = sandbox::confine(app, child.pid());
+
+    let mut output = Vec::new();
+    let mut code = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(bytes) | CommandEvent::Stderr(bytes) => {
+                let line = String::from_utf8_lossy(&bytes).trim_end().to_string();
+                if line.is_empty() {
+                    continue;
+                }
+                progress(app, task, line.clone());
+                output.push(line);
+            }
+            CommandEvent::Terminated(payload) => code = payload.code,
+            _ => {}
+        }
+    }
+
+    match code {
+        Some(0) => Ok(MaintenanceReport { ok: true, output }),
+        Some(PROBLEMS_FOUND) => Ok(MaintenanceReport { ok: false, output }),
+        _ => Err(format!(
+            "Database {} failed (exit code {code:?}): {}",
+            task.arg(),
+            output.last().map(String::as_str).unwrap_or("no output")
+        )),
+    }
+}
+
+// With the backend stopped the files can simply be copied
+fn keep_copy(app: &AppHandle) -> Result<(), String> {
+    let dir = data_dir::data_dir(app).ok_or("No data directory available")?;
+    let database = dir.join(DATABASE_FILE);
+    let copy = with_suffix(&database, BEFORE_REPAIR_SUFFIX);
+    copy_with_side_files(&database, &copy)
+        .map_err(|err| format!("Failed to keep a copy of the database before repairing it: {err}"))
+}
+
+fn copy_with_side_files(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::copy(from, to)?;
+    for suffix in DATABASE_SIDE_FILES {
+        let (from, to) = (with_suffix(from, suffix), with_suffix(to, suffix));
+        if from.exists() {
+            fs::copy(&from, &to)?;
+        } else {
+            let _ = fs::remove_file(&to);
+        }
+    }
+    Ok(())
+}
+
+fn progress(app: &AppHandle, task: Task, line: String) {
+    let _ = app.emit("database-maintenance-progress", Progress { task, line });
+}
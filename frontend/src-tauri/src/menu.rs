@@ -0,0 +1,157 @@
+use serde::Serialize;
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItem, MenuItemBuilder, SubmenuBuilder};
+use tauri::{AppHandle, Emitter, Wry};
+
+use crate::close_guard;
+use crate::context_menu;
+use crate::diagnostics;
+use crate::keybindings;
+use crate::logging;
+use crate::rollback;
+use crate::tabs;
+use crate::widget;
+use crate::zoom;
+
+// Items the frontend acts on; each is emitted as a `menu` event with its id
+pub const EXPORT_DATA: &str = "export-data";
+pub const TOGGLE_DEV_CONSOLE: &str = "toggle-dev-console";
+pub const SAVE_BUG_REPORT: &str = "save-bug-report";
+pub const OPEN_LOGS: &str = "open-logs";
+// Handled here, on whichever window has focus
+pub const ZOOM_IN: &str = "zoom-in";
+pub const ZOOM_OUT: &str = "zoom-out";
+pub const ZOOM_RESET: &str = "zoom-reset";
+pub const WIDGET_MODE: &str = "widget-mode";
+pub const COPY_DIAGNOSTICS: &str = "copy-diagnostics";
+pub const NEW_TAB: &str = "new-tab";
+// Not the predefined item, which exits without asking about unsaved changes
+pub const QUIT: &str = "quit";
+const ROLLBACK_UPDATE: &str = "rollback-update";
+
+#[derive(Clone, Serialize)]
+struct MenuAction<'a> {
+    id: &'a str,
+}
+
+// With the user's shortcut, if the item has one
+fn item(app: &AppHandle, id: &str, text: &str) -> tauri::Result<MenuItem<Wry>> {
+    let mut item = MenuItemBuilder::with_id(id, text);
+    if let Some(accelerator) = keybindings::accelerator(app, id) {
+        item = item.accelerator(accelerator);
+    }
+    item.build(app)
+}
+
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let new_tab = item(app, NEW_TAB, "New Tab")?;
+    let export_data = item(app, EXPORT_DATA, "Export Data…")?;
+    let toggle_dev_console = item(app, TOGGLE_DEV_CONSOLE, "Toggle Dev Console")?;
+    let open_logs = item(app, OPEN_LOGS, "Open Logs")?;
+    let save_bug_report = item(app, SAVE_BUG_REPORT, "Save Bug Report…")?;
+    let copy_diagnostics = item(app, COPY_DIAGNOSTICS, "Copy Diagnostics")?;
+    let rollback_update = item(app, ROLLBACK_UPDATE, "Roll Back to Previous Version…")?;
+    let zoom_in = item(app, ZOOM_IN, "Zoom In")?;
+    let zoom_out = item(app, ZOOM_OUT, "Zoom Out")?;
+    let zoom_reset = item(app, ZOOM_RESET, "Actual Size")?;
+    let widget_mode = item(app, WIDGET_MODE, "Widget Mode")?;
+    let quit = if cfg!(target_os = "macos") {
+        item(app, QUIT, "Quit Hamba")?
+    } else {
+        item(app, QUIT, "Quit")?
+    };
+
+    let mut file = SubmenuBuilder::new(app, "File")
+        .item(&new_tab)
+        .separator()
+        .item(&export_data)
+        .separator()
+        .close_window();
+    // Quit lives in the app menu on macOS
+    if !cfg!(target_os = "macos") {
+        file = file.separator().item(&quit);
+    }
+
+    let edit = SubmenuBuilder::new(app, "Edit")
+        .undo()
+        .redo()
+        .separator()
+        .cut()
+        .copy()
+        .paste()
+        .select_all()
+        .build()?;
+    let view = SubmenuBuilder::new(app, "View")
+        .item(&zoom_in)
+        .item(&zoom_out)
+        .item(&zoom_reset)
+        .separator()
+        .item(&toggle_dev_console)
+        .separator()
+        .fullscreen()
+        .build()?;
+    let window = SubmenuBuilder::new(app, "Window")
+        .minimize()
+        .maximize()
+        .separator()
+        .item(&widget_mode)
+        .build()?;
+    let help = SubmenuBuilder::new(app, "Help")
+        .item(&open_logs)
+        .item(&save_bug_report)
+        .item(&copy_diagnostics)
+        .separator()
+        .item(&rollback_update)
+        .build()?;
+
+    let mut menu = MenuBuilder::new(app);
+    if cfg!(target_os = "macos") {
+        let app_menu = SubmenuBuilder::new(app, "Hamba")
+            .about(None)
+            .separator()
+            .services()
+            .separator()
+            .hide()
+            .hide_others()
+            .show_all()
+            .separator()
+            .item(&quit)
+            .build()?;
+        menu = menu.item(&app_menu);
+    }
+    menu.items(&[&file.build()?, &edit, &view, &window, &help])
+        .build()
+}
+
+pub fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        OPEN_LOGS => {
+            if let Err(err) = logging::open_folder(app) {
+                tracing::warn!("Failed to open log folder: {err}");
+            }
+        }
+        COPY_DIAGNOSTICS => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = diagnostics::copy(&app).await {
+                    tracing::warn!("Failed to copy diagnostics: {err}");
+                }
+            });
+        }
+        NEW_TAB => tabs::open_default(app),
+        QUIT => close_guard::quit(app),
+        ROLLBACK_UPDATE => rollback::confirm(app),
+        ZOOM_IN => zoom::step(app, Some(1.0)),
+        ZOOM_OUT => zoom::step(app, Some(-1.0)),
+        ZOOM_RESET => zoom::step(app, None),
+        WIDGET_MODE => {
+            if let Err(err) = widget::toggle_widget_mode(app.clone()) {
+                tracing::warn!("Failed to toggle widget mode: {err}");
+            }
+        }
+        id @ (EXPORT_DATA | TOGGLE_DEV_CONSOLE | SAVE_BUG_REPORT) => {
+            let _ = app.emit("menu", MenuAction { id });
+        }
+        id if id.starts_with(context_menu::ID_PREFIX) => context_menu::on_menu_event(app, id),
+        _ => {}
+    }
+}
@@ -0,0 +1,403 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::async_runtime::JoinHandle;
+use tauri::ipc::{CommandArg, CommandItem, InvokeError};
+use tauri::{AppHandle, Manager, Wry};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::command::CommandResult;
+use crate::lock;
+use crate::profile::Profile;
+use crate::resources;
+use crate::safe_mode;
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar};
+use crate::startup;
+
+// Shared by every profile, like the keychain
+pub const TOKEN_SECRET: &str = "metrics-token";
+const MIN_PORT: u16 = 1024;
+const PATH: &str = "/metrics";
+// Upper bounds of the latency buckets, in seconds
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// The port of a server just stopped takes a moment to come free
+const BIND_ATTEMPTS: u32 = 5;
+const BIND_RETRY_DELAY: Duration = Duration::from_millis(200);
+// Accept errors such as running out of file descriptors tend to repeat
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(500);
+
+thread_local! {
+    // Set while a handler runs if the command took an `IpcTimer`
+    static TIMED: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Not cumulative; summed up when rendered
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsEndpoint {
+    enabled: bool,
+    url: String,
+    /// Sent as `Authorization: Bearer <token>`
+    token: String,
+}
+
+/// Runtime numbers in the Prometheus text format on `localhost`, for
+/// scraping into Grafana and the like. Off until the `metrics` setting is
+/// turned on; every scrape needs the token from `get_metrics_endpoint`.
+#[derive(Default)]
+pub struct Metrics {
+    ipc: Mutex<BTreeMap<String, Histogram>>,
+    token: Mutex<Option<String>>,
+    server: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Comes right after the settings, so IPC calls are counted from the first
+pub fn init(app: &AppHandle) {
+    app.manage(Metrics::default());
+    apply(app);
+}
+
+pub fn validate_port(port: u16) -> Result<(), String> {
+    if port < MIN_PORT {
+        return Err(format!("Pick a port from {MIN_PORT} up"));
+    }
+    Ok(())
+}
+
+/// Times an async command until its result is ready. The handler only hands
+/// async commands off, so they take one of these as an argument instead of
+/// being timed by `ipc::guard`; it records the call when the command's future
+/// drops it.
+pub struct IpcTimer {
+    app: AppHandle,
+    command: String,
+    started: Instant,
+}
+
+impl<'de> CommandArg<'de, Wry> for IpcTimer {
+    fn from_command(item: CommandItem<'de, Wry>) -> Result<Self, InvokeError> {
+        TIMED.set(true);
+        Ok(Self {
+            app: item.message.webview().app_handle().clone(),
+            command: item.message.command().to_string(),
+            started: Instant::now(),
+        })
+    }
+}
+
+impl Drop for IpcTimer {
+    fn drop(&mut self) {
+        record_ipc(&self.app, &self.command, self.started.elapsed());
+    }
+}
+
+/// Runs a command handler, returning whether the command times itself
+/// with an `IpcTimer`
+pub fn dispatch(handler: impl FnOnce() -> bool) -> (bool, bool) {
+    TIMED.set(false);
+    let handled = handler();
+    (handled, TIMED.replace(false))
+}
+
+/// Counts a command call that took `elapsed` to return its result
+pub fn record_ipc(app: &AppHandle, command: &str, elapsed: Duration) {
+    let Some(metrics) = app.try_state::<Metrics>() else {
+        return;
+    };
+    let seconds = elapsed.as_secs_f64();
+    let mut ipc = metrics.ipc.lock().unwrap();
+    let histogram = ipc.entry(command.to_string()).or_default();
+    if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+        histogram.buckets[bucket] += 1;
+    }
+    histogram.count += 1;
+    histogram.sum += seconds;
+}
+
+/// Starts, stops or moves the server to match the settings
+pub fn apply(app: &AppHandle) {
+    let metrics = app.state::<Metrics>();
+    let mut server = metrics.server.lock().unwrap();
+    if let Some(running) = server.take() {
+        running.abort();
+    }
+    let settings = app.state::<SettingsStore>().get();
+    if !settings.metrics || safe_mode::is_active(app) {
+        return;
+    }
+    let app = app.clone();
+    *server = Some(tauri::async_runtime::spawn(serve(
+        app,
+        settings.metrics_port,
+    )));
+}
+
+fn token(app: &AppHandle) -> Result<String, String> {
+    let metrics = app.state::<Metrics>();
+    let mut cached = metrics.token.lock().unwrap();
+    if let Some(token) = cached.as_ref() {
+        return Ok(token.clone());
+    }
+    let token = match secrets::get(TOKEN_SECRET)? {
+        Some(token) => token,
+        None => {
+            let token = sidecar::generate_token();
+            secrets::set(TOKEN_SECRET, &token)?;
+            token
+        }
+    };
+    *cached = Some(token.clone());
+    Ok(token)
+}
+
+async fn bind(port: u16) -> std::io::Result<TcpListener> {
+    let mut attempt = 1;
+    loop {
+        match TcpListener::bind(("127.0.0.1", port)).await {
+            Err(err) if err.kind() == std::io::ErrorKind::AddrInUse && attempt < BIND_ATTEMPTS => {
+                attempt += 1;
+                tokio::time::sleep(BIND_RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn serve(app: AppHandle, port: u16) {
+    // Only ever on loopback, LAN access or not
+    let listener = match bind(port).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::warn!("Failed to serve metrics on port {port}: {err}");
+            return;
+        }
+    };
+    tracing::info!("Serving metrics on http://127.0.0.1:{port}{PATH}");
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::debug!("Failed to accept a metrics request: {err}");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = tokio::time::timeout(REQUEST_TIMEOUT, respond(&app, stream)).await {
+                tracing::debug!("Metrics request timed out: {err}");
+            }
+        });
+    }
+}
+
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_SIZE {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn authorized(app: &AppHandle, head: &str) -> bool {
+    let Ok(token) = token(app) else {
+        return false;
+    };
+    head.lines().skip(1).any(|line| {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        name.trim().eq_ignore_ascii_case("authorization")
+            && value.trim().strip_prefix("Bearer ").is_some_and(|given| {
+                lock::constant_time_eq(given.trim().as_bytes(), token.as_bytes())
+            })
+    })
+}
+
+async fn respond(app: &AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    let head = read_head(&mut stream).await?;
+    let mut request = head.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (request.next(), request.next());
+    let path = path.map(|path| path.split('?').next().unwrap_or(path));
+
+    let (status, extra, body) = if path != Some(PATH) {
+        ("404 Not Found", "", String::new())
+    } else if method != Some("GET") {
+        ("405 Method Not Allowed", "Allow: GET\r\n", String::new())
+    } else if !authorized(app, &head) {
+        (
+            "401 Unauthorized",
+            "WWW-Authenticate: Bearer\r\n",
+            String::new(),
+        )
+    } else {
+        ("200 OK", "", render(app))
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n{extra}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+    );
+}
+
+// The app's own resident memory
+fn process_memory() -> Option<u64> {
+    let pid = Pid::from_u32(std::process::id());
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    system.process(pid).map(|process| process.memory())
+}
+
+fn render(app: &AppHandle) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# HELP hamba_build_info The running version and profile\n# TYPE hamba_build_info gauge\nhamba_build_info{{version=\"{}\",profile=\"{}\"}} 1",
+        app.package_info().version,
+        app.state::<Profile>().name
+    );
+    metric(
+        &mut out,
+        "hamba_uptime_seconds",
+        "gauge",
+        "Seconds since the app started",
+        startup::uptime(app).as_secs_f64(),
+    );
+    if let Some(ready) = startup::ready_after(app) {
+        metric(
+            &mut out,
+            "hamba_startup_seconds",
+            "gauge",
+            "Seconds the app took to get ready to use",
+            ready.as_secs_f64(),
+        );
+    }
+    if let Some(memory) = process_memory() {
+        metric(
+            &mut out,
+            "hamba_memory_bytes",
+            "gauge",
+            "Resident memory of the app itself",
+            memory,
+        );
+    }
+
+    // Not there yet for a scrape while the app is still starting
+    if let Some(sidecar) = app.try_state::<Sidecar>() {
+        metric(
+            &mut out,
+            "hamba_backend_up",
+            "gauge",
+            "Whether the backend process is running",
+            u8::from(sidecar.pid().is_some()),
+        );
+        metric(
+            &mut out,
+            "hamba_backend_restarts_total",
+            "counter",
+            "Times the backend was restarted after exiting on its own",
+            sidecar.restart_count(),
+        );
+        if let Some(uptime) = sidecar.uptime() {
+            metric(
+                &mut out,
+                "hamba_backend_uptime_seconds",
+                "gauge",
+                "Seconds since the backend was last started",
+                uptime.as_secs_f64(),
+            );
+        }
+    }
+    if let Some(stats) = resources::latest(app) {
+        metric(
+            &mut out,
+            "hamba_backend_memory_bytes",
+            "gauge",
+            "Resident memory of the backend",
+            stats.memory_bytes,
+        );
+        metric(
+            &mut out,
+            "hamba_backend_cpu_percent",
+            "gauge",
+            "CPU use of the backend, in percent of one core",
+            stats.cpu_percent,
+        );
+    }
+
+    let name = "hamba_ipc_handler_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Time spent handling frontend commands; async ones only count until handed off\n# TYPE {name} histogram"
+    );
+    for (command, histogram) in app.state::<Metrics>().ipc.lock().unwrap().iter() {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{command=\"{command}\",le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{command=\"{command}\",le=\"+Inf\"}} {}\n{name}_sum{{command=\"{command}\"}} {}\n{name}_count{{command=\"{command}\"}} {}",
+            histogram.count, histogram.sum, histogram.count
+        );
+    }
+    out
+}
+
+/// Where to scrape and the token to send, which is created the first time
+#[tauri::command]
+pub fn get_metrics_endpoint(app: AppHandle) -> CommandResult<MetricsEndpoint> {
+    let settings = app.state::<SettingsStore>().get();
+    Ok(MetricsEndpoint {
+        enabled: settings.metrics,
+        url: format!("http://127.0.0.1:{}{PATH}", settings.metrics_port),
+        token: token(&app)?,
+    })
+}
+
+/// Replaces the token, so scrapers with the old one are turned away
+#[tauri::command]
+pub fn reset_metrics_token(app: AppHandle) -> CommandResult<MetricsEndpoint> {
+    secrets::delete(TOKEN_SECRET)?;
+    *app.state::<Metrics>().token.lock().unwrap() = None;
+    tracing::info!("Reset the metrics token");
+    get_metrics_endpoint(app)
+}
@@ -0,0 +1,363 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::cli::Cli;
+use crate::data_dir;
+use crate::sidecar::DATABASE_FILE;
+
+// In the config directory: the app version that last finished migrating
+const RECORD_FILE: &str = "migrations.json";
+// Next to it while a migration runs, listing how to undo what it did so far.
+// Still there at launch means the last attempt never finished.
+const JOURNAL_FILE: &str = "migration-journal.json";
+// Installs from before migrations were recorded
+const UNTRACKED_VERSION: &str = "0.0.0";
+
+/// What a shell upgrade can do. The backend migrates its own database;
+/// these cover the files and OS registrations around it. Not every kind is
+/// in use by a shipped migration at any one time.
+enum Step {
+    /// Moves a file or folder within the data directory. Nothing to move is
+    /// fine; something already at `to` fails the migration.
+    #[allow(dead_code)]
+    Move {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// Renames a top-level key in a JSON file in the config directory
+    #[allow(dead_code)]
+    RenameKey {
+        file: &'static str,
+        from: &'static str,
+        to: &'static str,
+    },
+    /// Points the `hamba://` scheme and `.hamba` files at this copy of the
+    /// app again
+    RegisterProtocols,
+}
+
+struct Migration {
+    /// The app version it ships in. It runs once when upgrading from any
+    /// earlier version to this one or later.
+    version: &'static str,
+    name: &'static str,
+    steps: &'static [Step],
+}
+
+// In the order they run. Add new ones at the end; a shipped one never
+// changes, as installs that ran it won't run it again.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: "0.1.0",
+    name: "Register the app from where it is installed",
+    steps: &[Step::RegisterProtocols],
+}];
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    last_version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Undo {
+    /// Put `to` back at `from`
+    Move { from: PathBuf, to: PathBuf },
+    /// Write `contents` back to `path`
+    Restore { path: PathBuf, contents: String },
+}
+
+/// Whether a migration failed this launch, in which case nothing past the
+/// core of the app is started
+pub struct Migrations {
+    failed: AtomicBool,
+}
+
+/// Runs right after logging is up and before anything reads the files a
+/// migration may move
+pub fn init(app: &AppHandle) {
+    let result = match (data_dir::config_dir(app), data_dir::data_dir(app)) {
+        (Some(config), Some(data)) => run(app, &config, &data),
+        _ => Ok(()),
+    };
+    let failed = result.is_err();
+    app.manage(Migrations {
+        failed: AtomicBool::new(failed),
+    });
+    if let Err(err) = result {
+        report(app, &err);
+    }
+}
+
+pub fn failed(app: &AppHandle) -> bool {
+    app.state::<Migrations>().failed.load(Ordering::Relaxed)
+}
+
+fn version(text: &str) -> Option<(u64, u64, u64)> {
+    // Pre-release and build suffixes don't change which migrations apply
+    let core = text.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+fn run(app: &AppHandle, config: &Path, data: &Path) -> Result<(), String> {
+    let journal = config.join(JOURNAL_FILE);
+    if journal.exists() {
+        tracing::warn!("A migration was interrupted, undoing it");
+        rollback(&journal)?;
+    }
+
+    let current = &app.package_info().version;
+    let current_version = (current.major, current.minor, current.patch);
+    let record_path = config.join(RECORD_FILE);
+    let last = match fs::read_to_string(&record_path) {
+        Ok(text) => {
+            serde_json::from_str::<Record>(&text)
+                .map_err(|err| format!("{RECORD_FILE} is unreadable: {err}"))?
+                .last_version
+        }
+        // Nothing to migrate on a fresh install
+        Err(_) if !data.join(DATABASE_FILE).exists() => current.to_string(),
+        Err(_) => UNTRACKED_VERSION.to_string(),
+    };
+    let last_version = version(&last).ok_or_else(|| format!("Invalid version {last:?}"))?;
+    if last_version > current_version {
+        tracing::warn!("Data was last used by Hamba {last}, newer than {current}");
+        return Ok(());
+    }
+
+    for migration in MIGRATIONS {
+        let Some(version) = version(migration.version) else {
+            return Err(format!(
+                "Invalid version {:?} for {:?}",
+                migration.version, migration.name
+            ));
+        };
+        if version <= last_version || version > current_version {
+            continue;
+        }
+        tracing::info!("Migrating: {}", migration.name);
+        if let Err(err) = apply(app, config, data, &journal, migration.steps) {
+            let undone = rollback(&journal);
+            let err = format!("{}: {err}", migration.name);
+            return Err(match undone {
+                Ok(()) => err,
+                Err(undo_err) => format!("{err}\n\nUndoing it failed too: {undo_err}"),
+            });
+        }
+        let _ = fs::remove_file(&journal);
+        write_record(&record_path, migration.version)?;
+    }
+    if last_version != current_version {
+        write_record(&record_path, &current.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_record(path: &Path, version: &str) -> Result<(), String> {
+    let record = Record {
+        last_version: version.to_string(),
+    };
+    let text = serde_json::to_string_pretty(&record).map_err(|err| err.to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, text).map_err(|err| format!("Failed to save {RECORD_FILE}: {err}"))
+}
+
+// Each undo is on disk before the change it undoes is made
+fn apply(
+    app: &AppHandle,
+    config: &Path,
+    data: &Path,
+    journal: &Path,
+    steps: &[Step],
+) -> Result<(), String> {
+    let mut undo: Vec<Undo> = Vec::new();
+    for step in steps {
+        match step {
+            Step::Move { from, to } => {
+                let (from, to) = (data.join(from), data.join(to));
+                if !from.exists() {
+                    continue;
+                }
+                if to.exists() {
+                    return Err(format!("{} is in the way", to.display()));
+                }
+                note(
+                    journal,
+                    &mut undo,
+                    Undo::Move {
+                        from: from.clone(),
+                        to: to.clone(),
+                    },
+                )?;
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+                }
+                fs::rename(&from, &to).map_err(|err| {
+                    format!(
+                        "Failed to move {} to {}: {err}",
+                        from.display(),
+                        to.display()
+                    )
+                })?;
+            }
+            Step::RenameKey { file, from, to } => {
+                let path = config.join(file);
+                let Ok(contents) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let mut value: Value = serde_json::from_str(&contents)
+                    .map_err(|err| format!("{file} is unreadable: {err}"))?;
+                let Some(object) = value.as_object_mut() else {
+                    continue;
+                };
+                let Some(moved) = object.remove(*from) else {
+                    continue;
+                };
+                object.insert(to.to_string(), moved);
+                note(
+                    journal,
+                    &mut undo,
+                    Undo::Restore {
+                        path: path.clone(),
+                        contents,
+                    },
+                )?;
+                let text = serde_json::to_string_pretty(&value).map_err(|err| err.to_string())?;
+                fs::write(&path, text).map_err(|err| format!("Failed to save {file}: {err}"))?;
+            }
+            Step::RegisterProtocols => platform::register_protocols(app)?,
+        }
+    }
+    Ok(())
+}
+
+fn note(journal: &Path, undo: &mut Vec<Undo>, entry: Undo) -> Result<(), String> {
+    undo.push(entry);
+    let text = serde_json::to_string_pretty(undo).map_err(|err| err.to_string())?;
+    fs::write(journal, text).map_err(|err| format!("Failed to write {JOURNAL_FILE}: {err}"))
+}
+
+fn rollback(journal: &Path) -> Result<(), String> {
+    let entries: Vec<Undo> = match fs::read_to_string(journal) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|err| format!("{JOURNAL_FILE} is unreadable: {err}"))?,
+        Err(_) => return Ok(()),
+    };
+    for entry in entries.into_iter().rev() {
+        match entry {
+            // Only if the move went through before it stopped
+            Undo::Move { from, to } => {
+                if to.exists() && !from.exists() {
+                    fs::rename(&to, &from)
+                        .map_err(|err| format!("Failed to move {} back: {err}", to.display()))?;
+                }
+            }
+            Undo::Restore { path, contents } => {
+                fs::write(&path, contents)
+                    .map_err(|err| format!("Failed to restore {}: {err}", path.display()))?;
+            }
+        }
+    }
+    fs::remove_file(journal).map_err(|err| err.to_string())
+}
+
+// The app can't run on data halfway through an upgrade, so it stops here
+// until the user quits. Everything moved so far is back where it was, so
+// the previous version still works.
+fn report(app: &AppHandle, err: &str) {
+    tracing::error!("Migration failed: {err}");
+    if app.state::<Cli>().headless {
+        app.exit(1);
+        return;
+    }
+    let log_dir = data_dir::log_dir(app)
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+    let handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "Hamba couldn't update its files for this version and can't start. Your data \
+             was left as it was, so the previous version of Hamba still works with it.\n\n\
+             {err}\n\nThe log in {log_dir} has the details."
+        ))
+        .title("Upgrade failed")
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCustom("Quit".to_string()))
+        .show(move |_| handle.exit(1));
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    use crate::file_associations;
+
+    pub fn register_protocols(app: &AppHandle) -> Result<(), String> {
+        app.deep_link()
+            .register_all()
+            .map_err(|err| err.to_string())?;
+        file_associations::register()
+    }
+}
+
+// Launch Services picks up the bundle's schemes and document types once
+// asked to look at it again
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::AppHandle;
+
+    const LSREGISTER: &str = "/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister";
+
+    pub fn register_protocols(_app: &AppHandle) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+        // Not in a bundle when run from cargo
+        let Some(bundle) = exe
+            .ancestors()
+            .find(|dir| dir.extension().is_some_and(|ext| ext == "app"))
+        else {
+            return Ok(());
+        };
+        let status = Command::new(LSREGISTER)
+            .arg("-f")
+            .arg(bundle)
+            .status()
+            .map_err(|err| err.to_string())?;
+        if !status.success() {
+            return Err(format!("lsregister exited with {status}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::AppHandle;
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    pub fn register_protocols(app: &AppHandle) -> Result<(), String> {
+        app.deep_link()
+            .register_all()
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use tauri::AppHandle;
+
+    pub fn register_protocols(_app: &AppHandle) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, LogicalSize, Manager, Monitor, PhysicalPosition, PhysicalSize,
+    WebviewWindow,
+};
+
+use crate::command::{ensure, CommandError, CommandResult};
+use crate::kiosk;
+use crate::settings::SettingsStore;
+use crate::widget;
+
+// No OS sends hotplug events through Tauri, so the layout is compared this
+// often instead
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const PRIMARY: &str = "primary";
+
+/// In physical pixels, as the OS lays them out
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    /// 1-based, left to right and then top to bottom. Stays the same until
+    /// monitors are added, removed or rearranged.
+    number: u32,
+    /// What the OS calls it, e.g. `\\.\DISPLAY2` or `DP-1`
+    name: Option<String>,
+    bounds: Rect,
+    /// Without the taskbar, dock or menu bar
+    work_area: Rect,
+    scale_factor: f64,
+    primary: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Monitors {
+    monitors: Vec<MonitorInfo>,
+    /// The `number` of the monitor the calling window is mostly on
+    current: Option<u32>,
+}
+
+/// Where `move_to_monitor` puts the window within the monitor's work area
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Snap {
+    /// Keeps its size, shrunk to fit if needed
+    #[default]
+    Center,
+    Maximize,
+    /// The left or right half
+    Left,
+    Right,
+}
+
+fn rect(position: PhysicalPosition<i32>, width: u32, height: u32) -> Rect {
+    Rect {
+        x: position.x,
+        y: position.y,
+        width,
+        height,
+    }
+}
+
+// Sorted into the order `number` refers to
+fn sorted(mut monitors: Vec<Monitor>) -> Vec<Monitor> {
+    monitors.sort_by_key(|monitor| (monitor.position().x, monitor.position().y));
+    monitors
+}
+
+fn describe(monitors: &[Monitor], primary: Option<&Monitor>) -> Vec<MonitorInfo> {
+    monitors
+        .iter()
+        .zip(1..)
+        .map(|(monitor, number)| {
+            let area = monitor.work_area();
+            MonitorInfo {
+                number,
+                name: monitor.name().cloned(),
+                bounds: rect(
+                    *monitor.position(),
+                    monitor.size().width,
+                    monitor.size().height,
+                ),
+                work_area: rect(area.position, area.size.width, area.size.height),
+                scale_factor: monitor.scale_factor(),
+                primary: primary.is_some_and(|primary| {
+                    primary.name() == monitor.name() && primary.position() == monitor.position()
+                }),
+            }
+        })
+        .collect()
+}
+
+fn layout(app: &AppHandle) -> Result<(Vec<Monitor>, Vec<MonitorInfo>), String> {
+    let monitors = sorted(app.available_monitors().map_err(|err| err.to_string())?);
+    let primary = app.primary_monitor().map_err(|err| err.to_string())?;
+    let info = describe(&monitors, primary.as_ref());
+    Ok((monitors, info))
+}
+
+/// Emits `monitors-changed` with the new layout whenever a monitor is
+/// connected, disconnected, moved or rescaled
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last = layout(&app).map(|(_, info)| info).unwrap_or_default();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Ok((_, info)) = layout(&app) else {
+                continue;
+            };
+            if info != last {
+                tracing::info!("Monitor layout changed, {} connected", info.len());
+                let _ = app.emit("monitors-changed", &info);
+                last = info;
+            }
+        }
+    });
+}
+
+/// Finds a monitor by `number`, by name (ignoring case) or as `primary`
+fn find(
+    monitors: Vec<Monitor>,
+    info: &[MonitorInfo],
+    wanted: &str,
+) -> Option<(Monitor, MonitorInfo)> {
+    let wanted = wanted.trim();
+    let index = info.iter().position(|monitor| {
+        if wanted.eq_ignore_ascii_case(PRIMARY) {
+            return monitor.primary;
+        }
+        wanted.parse() == Ok(monitor.number)
+            || monitor
+                .name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(wanted))
+    })?;
+    let monitor = monitors.into_iter().nth(index)?;
+    Some((monitor, info[index].clone()))
+}
+
+fn place(window: &WebviewWindow, monitor: &Monitor, snap: Snap) -> tauri::Result<()> {
+    let area = monitor.work_area();
+    let (left, top) = (area.position.x, area.position.y);
+    let (width, height) = (area.size.width, area.size.height);
+
+    window.unmaximize()?;
+    // Keep the size it has on screen where the scale differs
+    let logical = window
+        .inner_size()?
+        .to_logical::<f64>(window.scale_factor()?);
+    window.set_position(PhysicalPosition::new(left, top))?;
+    match snap {
+        Snap::Maximize => window.maximize(),
+        Snap::Left | Snap::Right => {
+            let half = width / 2;
+            let x = if matches!(snap, Snap::Left) {
+                left
+            } else {
+                left + half as i32
+            };
+            window.set_size(PhysicalSize::new(half, height))?;
+            window.set_position(PhysicalPosition::new(x, top))
+        }
+        Snap::Center => {
+            let scale = monitor.scale_factor();
+            let fitted = LogicalSize::new(
+                logical.width.min(width as f64 / scale),
+                logical.height.min(height as f64 / scale),
+            );
+            window.set_size(fitted)?;
+            let outer = window.outer_size()?;
+            window.set_position(PhysicalPosition::new(
+                left + (width.saturating_sub(outer.width) / 2) as i32,
+                top + (height.saturating_sub(outer.height) / 2) as i32,
+            ))
+        }
+    }
+}
+
+/// Moves the main window to `open_on_monitor` when it opens, if that monitor
+/// is connected
+pub fn apply_preferred(window: &WebviewWindow) {
+    let Some(wanted) = window.state::<SettingsStore>().get().open_on_monitor else {
+        return;
+    };
+    let Ok((monitors, info)) = layout(window.app_handle()) else {
+        return;
+    };
+    let Some((monitor, target)) = find(monitors, &info, &wanted) else {
+        tracing::debug!("Monitor {wanted:?} isn't connected, opening where the window was");
+        return;
+    };
+    let current = window.current_monitor().ok().flatten();
+    if current.is_some_and(|current| {
+        current.name() == monitor.name() && current.position() == monitor.position()
+    }) {
+        return;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let snap = if maximized {
+        Snap::Maximize
+    } else {
+        Snap::Center
+    };
+    if let Err(err) = place(window, &monitor, snap) {
+        tracing::warn!("Failed to move to monitor {}: {err}", target.number);
+    }
+}
+
+/// The connected monitors and which one the calling window is on
+#[tauri::command]
+pub fn list_monitors(app: AppHandle, window: WebviewWindow) -> Result<Monitors, String> {
+    let (_, monitors) = layout(&app)?;
+    let current = window
+        .current_monitor()
+        .map_err(|err| err.to_string())?
+        .and_then(|current| {
+            monitors.iter().find(|monitor| {
+                monitor.name.as_ref() == current.name()
+                    && (monitor.bounds.x, monitor.bounds.y)
+                        == (current.position().x, current.position().y)
+            })
+        })
+        .map(|monitor| monitor.number);
+    Ok(Monitors { monitors, current })
+}
+
+/// Moves the calling window to a monitor, by `number`, name or `primary`,
+/// and places it there as `snap` says
+#[tauri::command]
+pub fn move_to_monitor(
+    app: AppHandle,
+    window: WebviewWindow,
+    monitor: String,
+    snap: Option<Snap>,
+) -> CommandResult<MonitorInfo> {
+    ensure!(
+        !monitor.trim().is_empty(),
+        "monitor",
+        "Give a monitor number, name or {PRIMARY:?}"
+    );
+    if widget::is_active(&app) || kiosk::is_active(&app) {
+        return Err("The window can't be moved in widget or kiosk mode".into());
+    }
+    let (monitors, info) = layout(&app)?;
+    let (target, target_info) = find(monitors, &info, &monitor)
+        .ok_or_else(|| CommandError::not_found(format!("No monitor {monitor:?}")))?;
+    window.set_fullscreen(false)?;
+    place(&window, &target, snap.unwrap_or_default())?;
+    Ok(target_info)
+}
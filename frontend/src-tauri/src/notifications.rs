@@ -0,0 +1,214 @@
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::badge;
+use crate::dnd;
+use crate::main_window;
+use crate::settings::SettingsStore;
+
+const MAIL_CATEGORY: &str = "mail";
+
+/// The subset of the backend's `/ws` messages that turn into notifications
+/// or icon badges
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BackendMessage {
+    NewMail {
+        #[serde(rename = "accountId")]
+        account_id: String,
+        email: Option<NewMail>,
+    },
+    /// Sent by `notifyDesktop` on the backend for anything else
+    Notification {
+        category: String,
+        title: String,
+        body: Option<String>,
+    },
+    /// Sent by `notifyDesktopBadge` on the backend
+    Badge { count: u32 },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct NewMail {
+    from: String,
+    subject: String,
+    #[serde(default, rename = "isImportant")]
+    is_important: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationClicked {
+    category: String,
+    account_id: Option<String>,
+}
+
+/// Messages asking for notifications arrive through `backend_events`; macOS
+/// only needs to know which bundle they come from
+pub fn init(_app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    let _ = notify_rust::set_application(&_app.config().identifier);
+}
+
+/// Shows a native notification or updates the badge for each message from
+/// the backend's realtime channel that asks for one
+pub fn on_backend_message(app: &AppHandle, message: &Map<String, Value>) {
+    match BackendMessage::deserialize(message) {
+        Ok(BackendMessage::NewMail {
+            account_id,
+            email: Some(email),
+        }) => {
+            if !email.is_important && app.state::<SettingsStore>().get().notify_important_only {
+                return;
+            }
+            let subject = if email.subject.is_empty() {
+                "(no subject)".to_string()
+            } else {
+                email.subject
+            };
+            show(app, MAIL_CATEGORY, &email.from, &subject, Some(account_id));
+        }
+        Ok(BackendMessage::Notification {
+            category,
+            title,
+            body,
+        }) => show(
+            app,
+            &category,
+            &title,
+            body.as_deref().unwrap_or_default(),
+            None,
+        ),
+        Ok(BackendMessage::Badge { count }) => {
+            if let Err(err) = badge::set(app, count) {
+                tracing::debug!("Failed to set the badge count: {err}");
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_muted(app: &AppHandle, category: &str) -> bool {
+    let settings = app.state::<SettingsStore>().get();
+    !settings.notifications
+        || settings
+            .muted_notification_categories
+            .iter()
+            .any(|muted| muted == category)
+}
+
+fn build(app: &AppHandle, title: &str, body: &str) -> Notification {
+    let mut notification = Notification::new();
+    notification
+        .summary(title)
+        .body(body)
+        .appname(&app.package_info().name);
+    #[cfg(windows)]
+    notification.app_id(&app.config().identifier);
+    notification
+}
+
+/// Shows a native notification unless notifications or `category` are muted.
+/// During do not disturb or focus mode it waits until they're over.
+pub fn show(app: &AppHandle, category: &str, title: &str, body: &str, account_id: Option<String>) {
+    if is_muted(app, category) {
+        return;
+    }
+    if dnd::hold_notification(app, category, title, body, account_id.clone()) {
+        return;
+    }
+
+    let app_handle = app.clone();
+    let clicked = NotificationClicked {
+        category: category.to_string(),
+        account_id,
+    };
+    show_with_actions(
+        build(app, title, body).action("default", "Open"),
+        move |_| {
+            main_window::focus(&app_handle);
+            let _ = app_handle.emit("notification-clicked", clicked);
+        },
+    );
+}
+
+/// Shows a notification with a button per `(id, label)` in `actions`, unless
+/// notifications or `category` are muted. `on_action` gets the id of the
+/// button pressed, `default` for a click on the notification itself. Quiet
+/// time is up to the caller. Returns whether it was shown.
+pub fn show_actionable(
+    app: &AppHandle,
+    category: &str,
+    title: &str,
+    body: &str,
+    actions: &[(&str, &str)],
+    on_action: impl FnOnce(&str) + Send + 'static,
+) -> bool {
+    if is_muted(app, category) {
+        return false;
+    }
+    let mut notification = build(app, title, body);
+    notification.action("default", "Open");
+    for (id, label) in actions {
+        notification.action(id, label);
+    }
+    show_with_actions(&mut notification, on_action)
+}
+
+fn show_with_actions(
+    notification: &mut Notification,
+    on_action: impl FnOnce(&str) + Send + 'static,
+) -> bool {
+    let handle = match notification.show() {
+        Ok(handle) => handle,
+        Err(err) => {
+            tracing::warn!("Failed to show notification: {err}");
+            return false;
+        }
+    };
+
+    // Waiting for a click blocks until the notification goes away, so each
+    // one gets its own thread. On Windows a click also re-launches the app,
+    // which the single-instance handler turns into focusing this one.
+    tauri::async_runtime::spawn_blocking(move || {
+        handle.wait_for_action(|action| {
+            if action != "__closed" {
+                on_action(action);
+            }
+        });
+    });
+    true
+}
+
+#[tauri::command]
+pub fn set_notifications_enabled(
+    settings: State<'_, SettingsStore>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings
+        .update(|settings| settings.notifications = enabled)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn set_notification_category_muted(
+    settings: State<'_, SettingsStore>,
+    category: String,
+    muted: bool,
+) -> Result<(), String> {
+    settings
+        .update(|settings| {
+            let categories = &mut settings.muted_notification_categories;
+            categories.retain(|existing| *existing != category);
+            if muted {
+                categories.push(category);
+            }
+        })
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
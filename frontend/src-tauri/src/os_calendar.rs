@@ -0,0 +1,813 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::metrics::IpcTimer;
+use crate::settings::{Settings, SettingsStore};
+
+/// A calendar the user can pick to read from or write to
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+    id: String,
+    title: String,
+    /// Events can be added to it
+    writable: bool,
+}
+
+/// An event in one of the OS calendars. Times are Unix seconds.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    id: String,
+    calendar_id: String,
+    title: String,
+    start: i64,
+    end: i64,
+    all_day: bool,
+    notes: Option<String>,
+}
+
+/// An event to add, e.g. for a reminder or a snoozed email
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEvent {
+    title: String,
+    start: i64,
+    end: i64,
+    #[serde(default)]
+    all_day: bool,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+fn enabled(app: &AppHandle) -> Result<Settings, String> {
+    let settings = app.state::<SettingsStore>().get();
+    if !settings.os_calendar {
+        return Err("Calendar integration is turned off".to_string());
+    }
+    Ok(settings)
+}
+
+// Calendar stores are slow to open and read, and some block on disk
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+/// Turns the integration on or off. Turning it on asks for access to the
+/// calendars where the OS requires that, and picks the default calendar
+/// for new events if none was chosen yet.
+#[tauri::command]
+pub async fn set_os_calendar(
+    _timer: IpcTimer,
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    enabled: bool,
+) -> Result<Settings, String> {
+    let mut write = settings.get().os_calendar_write;
+    if enabled {
+        platform::request_access().await?;
+        if write.is_none() {
+            let handle = app.clone();
+            write = blocking(move || Ok(platform::default_calendar(&handle))).await?;
+        }
+    }
+    settings
+        .update(|settings| {
+            settings.os_calendar = enabled;
+            settings.os_calendar_write = write;
+        })
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn list_os_calendars(_timer: IpcTimer, app: AppHandle) -> Result<Vec<Calendar>, String> {
+    enabled(&app)?;
+    blocking(move || platform::calendars(&app)).await
+}
+
+/// Events between `from` and `to` in the calendars picked in
+/// `osCalendarRead`
+#[tauri::command]
+pub async fn get_os_events(
+    _timer: IpcTimer,
+    app: AppHandle,
+    from: i64,
+    to: i64,
+) -> Result<Vec<Event>, String> {
+    let settings = enabled(&app)?;
+    blocking(move || platform::events(&app, &settings.os_calendar_read, from, to)).await
+}
+
+/// Adds `event` to the calendar picked in `osCalendarWrite`, resolving with
+/// its id
+#[tauri::command]
+pub async fn add_os_event(
+    _timer: IpcTimer,
+    app: AppHandle,
+    event: NewEvent,
+) -> Result<String, String> {
+    let settings = enabled(&app)?;
+    let calendar = settings
+        .os_calendar_write
+        .ok_or("No calendar was picked for new events")?;
+    if event.title.trim().is_empty() {
+        return Err("The event needs a title".to_string());
+    }
+    if event.end < event.start {
+        return Err("The event ends before it starts".to_string());
+    }
+    blocking(move || platform::add_event(&app, &calendar, &event)).await
+}
+
+/// Deletes an event from the calendar picked in `osCalendarWrite`. Events
+/// elsewhere are left alone, as the app didn't add them.
+#[tauri::command]
+pub async fn delete_os_event(_timer: IpcTimer, app: AppHandle, id: String) -> Result<(), String> {
+    let settings = enabled(&app)?;
+    let calendar = settings
+        .os_calendar_write
+        .ok_or("No calendar was picked for new events")?;
+    blocking(move || platform::delete_event(&app, &calendar, &id)).await
+}
+
+/// Opens the Calendar app on macOS, elsewhere the folder with the .ics
+/// files, so the user can subscribe to `hamba.ics` or add their own
+#[tauri::command]
+pub fn open_os_calendar(app: AppHandle) -> Result<(), String> {
+    let path = platform::location(&app)?;
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, CStr, CString};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use block2::RcBlock;
+    use objc2::rc::{Allocated, Retained};
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2::{class, msg_send, sel};
+    use tauri::AppHandle;
+    use tokio::sync::oneshot;
+
+    use super::{Calendar, Event, NewEvent};
+
+    #[link(name = "EventKit", kind = "framework")]
+    extern "C" {}
+
+    // EKEntityTypeEvent
+    const ENTITY_EVENT: usize = 0;
+    // EKAuthorizationStatusAuthorized, called FullAccess since macOS 14
+    const AUTHORIZED: isize = 3;
+    // EKSpanThisEvent
+    const SPAN_THIS_EVENT: isize = 0;
+    const CALENDAR_APP: &str = "/System/Applications/Calendar.app";
+
+    pub async fn request_access() -> Result<(), String> {
+        let status: isize = unsafe {
+            msg_send![class!(EKEventStore), authorizationStatusForEntityType: ENTITY_EVENT]
+        };
+        if status == AUTHORIZED {
+            return Ok(());
+        }
+        let (sender, receiver) = oneshot::channel();
+        unsafe { ask(sender)? };
+        let granted = receiver
+            .await
+            .map_err(|_| "The calendar access prompt was cancelled".to_string())?;
+        if !granted {
+            return Err(
+                "Hamba isn't allowed to use your calendars. Allow it in System Settings > \
+                 Privacy & Security > Calendars."
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    // The store has to stay alive until the prompt is answered, so the
+    // block holds on to it
+    unsafe fn ask(sender: oneshot::Sender<bool>) -> Result<(), String> {
+        unsafe {
+            let store = store()?;
+            let sender = Mutex::new(Some(sender));
+            let kept = store.clone();
+            let completion = RcBlock::new(move |granted: Bool, _error: *mut AnyObject| {
+                let _ = &kept;
+                if let Some(sender) = sender.lock().unwrap().take() {
+                    let _ = sender.send(granted.as_bool());
+                }
+            });
+            // macOS 14 split access into full and write-only
+            let full: bool = msg_send![
+                &*store,
+                respondsToSelector: sel!(requestFullAccessToEventsWithCompletion:)
+            ];
+            if full {
+                let _: () =
+                    msg_send![&*store, requestFullAccessToEventsWithCompletion: &*completion];
+            } else {
+                let _: () = msg_send![
+                    &*store,
+                    requestAccessToEntityType: ENTITY_EVENT,
+                    completion: &*completion
+                ];
+            }
+        }
+        Ok(())
+    }
+
+    pub fn location(_app: &AppHandle) -> Result<PathBuf, String> {
+        Ok(PathBuf::from(CALENDAR_APP))
+    }
+
+    pub fn default_calendar(_app: &AppHandle) -> Option<String> {
+        unsafe {
+            let store = store().ok()?;
+            let calendar: Option<Retained<AnyObject>> =
+                msg_send![&*store, defaultCalendarForNewEvents];
+            to_string(msg_send![&*calendar?, calendarIdentifier])
+        }
+    }
+
+    pub fn calendars(_app: &AppHandle) -> Result<Vec<Calendar>, String> {
+        unsafe {
+            let store = store()?;
+            let list: Retained<AnyObject> =
+                msg_send![&*store, calendarsForEntityType: ENTITY_EVENT];
+            Ok(items(&list)
+                .iter()
+                .filter_map(|calendar| {
+                    let writable: bool = msg_send![&**calendar, allowsContentModifications];
+                    Some(Calendar {
+                        id: to_string(msg_send![&**calendar, calendarIdentifier])?,
+                        title: to_string(msg_send![&**calendar, title]).unwrap_or_default(),
+                        writable,
+                    })
+                })
+                .collect())
+        }
+    }
+
+    pub fn events(
+        _app: &AppHandle,
+        calendar_ids: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Event>, String> {
+        unsafe {
+            let store = store()?;
+            let calendars: Retained<AnyObject> = msg_send![class!(NSMutableArray), array];
+            for id in calendar_ids {
+                if let Some(calendar) = calendar(&store, id) {
+                    let _: () = msg_send![&*calendars, addObject: &*calendar];
+                }
+            }
+            // No calendars at all would mean every calendar to EventKit
+            let count: usize = msg_send![&*calendars, count];
+            if count == 0 {
+                return Ok(Vec::new());
+            }
+            let predicate: Retained<AnyObject> = msg_send![
+                &*store,
+                predicateForEventsWithStartDate: &*date(from),
+                endDate: &*date(to),
+                calendars: &*calendars
+            ];
+            let found: Retained<AnyObject> =
+                msg_send![&*store, eventsMatchingPredicate: &*predicate];
+            Ok(items(&found)
+                .iter()
+                .filter_map(|event| {
+                    let event = &**event;
+                    let calendar: Option<Retained<AnyObject>> = msg_send![event, calendar];
+                    let all_day: bool = msg_send![event, isAllDay];
+                    Some(Event {
+                        id: to_string(msg_send![event, eventIdentifier])?,
+                        calendar_id: to_string(msg_send![&*calendar?, calendarIdentifier])?,
+                        title: to_string(msg_send![event, title]).unwrap_or_default(),
+                        start: timestamp(msg_send![event, startDate])?,
+                        end: timestamp(msg_send![event, endDate])?,
+                        all_day,
+                        notes: to_string(msg_send![event, notes]),
+                    })
+                })
+                .collect())
+        }
+    }
+
+    pub fn add_event(
+        _app: &AppHandle,
+        calendar_id: &str,
+        new: &NewEvent,
+    ) -> Result<String, String> {
+        unsafe {
+            let store = store()?;
+            let calendar = calendar(&store, calendar_id).ok_or("The calendar no longer exists")?;
+            let event: Retained<AnyObject> =
+                msg_send![class!(EKEvent), eventWithEventStore: &*store];
+            let _: () = msg_send![&*event, setCalendar: &*calendar];
+            let _: () = msg_send![&*event, setTitle: &*ns_string(&new.title)?];
+            let _: () = msg_send![&*event, setStartDate: &*date(new.start)];
+            let _: () = msg_send![&*event, setEndDate: &*date(new.end)];
+            let _: () = msg_send![&*event, setAllDay: new.all_day];
+            if let Some(notes) = &new.notes {
+                let _: () = msg_send![&*event, setNotes: &*ns_string(notes)?];
+            }
+            let mut error: Option<Retained<AnyObject>> = None;
+            let saved: bool = msg_send![
+                &*store,
+                saveEvent: &*event,
+                span: SPAN_THIS_EVENT,
+                commit: true,
+                error: &mut error
+            ];
+            if !saved {
+                return Err(describe(error.as_deref()));
+            }
+            to_string(msg_send![&*event, eventIdentifier])
+                .ok_or_else(|| "The event has no id".to_string())
+        }
+    }
+
+    pub fn delete_event(_app: &AppHandle, calendar_id: &str, id: &str) -> Result<(), String> {
+        unsafe {
+            let store = store()?;
+            let event: Option<Retained<AnyObject>> =
+                msg_send![&*store, eventWithIdentifier: &*ns_string(id)?];
+            let Some(event) = event else {
+                return Ok(());
+            };
+            let calendar: Option<Retained<AnyObject>> = msg_send![&*event, calendar];
+            let calendar =
+                calendar.and_then(|calendar| to_string(msg_send![&*calendar, calendarIdentifier]));
+            if calendar.as_deref() != Some(calendar_id) {
+                return Err("The event isn't in the calendar Hamba adds to".to_string());
+            }
+            let mut error: Option<Retained<AnyObject>> = None;
+            let removed: bool = msg_send![
+                &*store,
+                removeEvent: &*event,
+                span: SPAN_THIS_EVENT,
+                commit: true,
+                error: &mut error
+            ];
+            if !removed {
+                return Err(describe(error.as_deref()));
+            }
+            Ok(())
+        }
+    }
+
+    unsafe fn store() -> Result<Retained<AnyObject>, String> {
+        unsafe {
+            let store: Allocated<AnyObject> = msg_send![class!(EKEventStore), alloc];
+            let store: Option<Retained<AnyObject>> = msg_send![store, init];
+            store.ok_or_else(|| "Failed to open the calendar store".to_string())
+        }
+    }
+
+    unsafe fn calendar(store: &AnyObject, id: &str) -> Option<Retained<AnyObject>> {
+        let id = ns_string(id).ok()?;
+        unsafe { msg_send![store, calendarWithIdentifier: &*id] }
+    }
+
+    unsafe fn items(list: &AnyObject) -> Vec<Retained<AnyObject>> {
+        unsafe {
+            let count: usize = msg_send![list, count];
+            (0..count)
+                .filter_map(|index| {
+                    let item: Option<Retained<AnyObject>> = msg_send![list, objectAtIndex: index];
+                    item
+                })
+                .collect()
+        }
+    }
+
+    unsafe fn date(timestamp: i64) -> Retained<AnyObject> {
+        unsafe { msg_send![class!(NSDate), dateWithTimeIntervalSince1970: timestamp as f64] }
+    }
+
+    unsafe fn timestamp(date: Option<Retained<AnyObject>>) -> Option<i64> {
+        let seconds: f64 = unsafe { msg_send![&*date?, timeIntervalSince1970] };
+        Some(seconds as i64)
+    }
+
+    unsafe fn describe(error: Option<&AnyObject>) -> String {
+        error
+            .and_then(|error| unsafe { to_string(msg_send![error, localizedDescription]) })
+            .unwrap_or_else(|| "The calendar store refused the change".to_string())
+    }
+
+    unsafe fn to_string(text: Option<Retained<AnyObject>>) -> Option<String> {
+        let text = text?;
+        let ptr: *const c_char = unsafe { msg_send![&*text, UTF8String] };
+        (!ptr.is_null()).then(|| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    fn ns_string(text: &str) -> Result<Retained<AnyObject>, String> {
+        let text = CString::new(text.replace('\0', "")).map_err(|err| err.to_string())?;
+        let ptr: *const c_char = text.as_ptr();
+        let string: Option<Retained<AnyObject>> =
+            unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] };
+        string.ok_or_else(|| "Failed to convert the text".to_string())
+    }
+}
+
+// Without a calendar API the desktop apps can all share, each .ics file in
+// the `calendars` folder is a calendar. `hamba.ics` is the app's own, for
+// the user's calendar app to subscribe to; the others are read, e.g.
+// exports dropped there.
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use tauri::AppHandle;
+
+    use super::{Calendar, Event, NewEvent};
+    use crate::data_dir;
+
+    const FOLDER: &str = "calendars";
+    const OWN: &str = "hamba";
+    const OWN_TITLE: &str = "Hamba";
+    const TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+    const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+    const DATE_FORMAT: &str = "%Y%m%d";
+    const DAY: i64 = 24 * 60 * 60;
+    // Lines are folded at 75 bytes, leaving room for the CRLF
+    const LINE_LIMIT: usize = 74;
+
+    // The files are the app's own, so there is nothing to ask for
+    pub async fn request_access() -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn location(app: &AppHandle) -> Result<PathBuf, String> {
+        let folder = folder(app)?;
+        fs::create_dir_all(&folder).map_err(|err| err.to_string())?;
+        Ok(folder)
+    }
+
+    pub fn default_calendar(_app: &AppHandle) -> Option<String> {
+        Some(OWN.to_string())
+    }
+
+    fn folder(app: &AppHandle) -> Result<PathBuf, String> {
+        let dir = data_dir::data_dir(app).ok_or("No data directory available")?;
+        Ok(dir.join(FOLDER))
+    }
+
+    fn path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+        if !is_file_stem(id) {
+            return Err(format!("{id:?} is not a calendar"));
+        }
+        Ok(folder(app)?.join(format!("{id}.ics")))
+    }
+
+    // Ids come from the settings, and must not lead out of the folder
+    pub(super) fn is_file_stem(id: &str) -> bool {
+        !id.is_empty()
+            && !id.contains("..")
+            && !id
+                .chars()
+                .any(|c| matches!(c, '/' | '\\' | ':') || c.is_control())
+    }
+
+    pub fn calendars(app: &AppHandle) -> Result<Vec<Calendar>, String> {
+        let mut calendars = vec![Calendar {
+            id: OWN.to_string(),
+            title: OWN_TITLE.to_string(),
+            writable: true,
+        }];
+        let Ok(entries) = fs::read_dir(folder(app)?) else {
+            return Ok(calendars);
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|ext| ext != "ics") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if id == OWN {
+                continue;
+            }
+            let title = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| calendar_name(&contents))
+                .unwrap_or_else(|| id.to_string());
+            calendars.push(Calendar {
+                id: id.to_string(),
+                title,
+                writable: false,
+            });
+        }
+        Ok(calendars)
+    }
+
+    pub fn events(
+        app: &AppHandle,
+        calendar_ids: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Event>, String> {
+        let mut events = Vec::new();
+        for id in calendar_ids {
+            let path = match path(app, id) {
+                Ok(path) => path,
+                Err(err) => {
+                    tracing::warn!("Skipped a calendar: {err}");
+                    continue;
+                }
+            };
+            let Ok(contents) = fs::read_to_string(path) else {
+                continue;
+            };
+            events.extend(
+                parse(&contents, id)
+                    .into_iter()
+                    .filter(|event| event.start < to && event.end > from),
+            );
+        }
+        Ok(events)
+    }
+
+    pub fn add_event(app: &AppHandle, calendar_id: &str, new: &NewEvent) -> Result<String, String> {
+        let path = own(app, calendar_id)?;
+        let mut events = read(&path);
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).map_err(|err| err.to_string())?;
+        let id = format!("{}@hamba", hex::encode(bytes));
+        events.push(Event {
+            id: id.clone(),
+            calendar_id: OWN.to_string(),
+            title: new.title.clone(),
+            start: new.start,
+            end: new.end,
+            all_day: new.all_day,
+            notes: new.notes.clone(),
+        });
+        write(&path, &events)?;
+        Ok(id)
+    }
+
+    pub fn delete_event(app: &AppHandle, calendar_id: &str, id: &str) -> Result<(), String> {
+        let path = own(app, calendar_id)?;
+        let mut events = read(&path);
+        let count = events.len();
+        events.retain(|event| event.id != id);
+        if events.len() == count {
+            return Ok(());
+        }
+        write(&path, &events)
+    }
+
+    fn own(app: &AppHandle, calendar_id: &str) -> Result<PathBuf, String> {
+        if calendar_id != OWN {
+            return Err("Only the Hamba calendar can be changed here".to_string());
+        }
+        path(app, OWN)
+    }
+
+    fn read(path: &Path) -> Vec<Event> {
+        fs::read_to_string(path)
+            .map(|contents| parse(&contents, OWN))
+            .unwrap_or_default()
+    }
+
+    fn write(path: &Path, events: &[Event]) -> Result<(), String> {
+        let stamp = Utc::now().format(UTC_FORMAT).to_string();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//Hamba//Hamba//EN".to_string(),
+            format!("X-WR-CALNAME:{OWN_TITLE}"),
+        ];
+        for event in events {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", event.id));
+            lines.push(format!("DTSTAMP:{stamp}"));
+            if event.all_day {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", local_date(event.start)));
+                lines.push(format!("DTEND;VALUE=DATE:{}", local_date(event.end)));
+            } else {
+                lines.push(format!("DTSTART:{}", utc_time(event.start)));
+                lines.push(format!("DTEND:{}", utc_time(event.end)));
+            }
+            lines.push(format!("SUMMARY:{}", escape(&event.title)));
+            if let Some(notes) = &event.notes {
+                lines.push(format!("DESCRIPTION:{}", escape(notes)));
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut contents: String = lines
+            .iter()
+            .map(|line| fold(line))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        contents.push_str("\r\n");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, contents).map_err(|err| err.to_string())
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        uid: Option<String>,
+        summary: Option<String>,
+        description: Option<String>,
+        start: Option<(i64, bool)>,
+        end: Option<(i64, bool)>,
+        // Inside a VALARM or similar, whose fields aren't the event's
+        nested: usize,
+    }
+
+    impl Fields {
+        fn into_event(self, calendar_id: &str) -> Option<Event> {
+            let (start, all_day) = self.start?;
+            let end =
+                self.end
+                    .map(|(end, _)| end)
+                    .unwrap_or(if all_day { start + DAY } else { start });
+            Some(Event {
+                id: self.uid?,
+                calendar_id: calendar_id.to_string(),
+                title: self.summary.unwrap_or_default(),
+                start,
+                end,
+                all_day,
+                notes: self.description,
+            })
+        }
+    }
+
+    fn parse(contents: &str, calendar_id: &str) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut current: Option<Fields> = None;
+        for line in unfold(contents) {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = key
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            let is_event = value.eq_ignore_ascii_case("VEVENT");
+            let Some(fields) = current.as_mut() else {
+                if name == "BEGIN" && is_event {
+                    current = Some(Fields::default());
+                }
+                continue;
+            };
+            match name.as_str() {
+                "END" if is_event => {
+                    events.extend(
+                        current
+                            .take()
+                            .and_then(|fields| fields.into_event(calendar_id)),
+                    );
+                }
+                "BEGIN" => fields.nested += 1,
+                "END" => fields.nested = fields.nested.saturating_sub(1),
+                _ if fields.nested > 0 => {}
+                "UID" => fields.uid = Some(value.to_string()),
+                "SUMMARY" => fields.summary = Some(unescape(value)),
+                "DESCRIPTION" => fields.description = Some(unescape(value)),
+                "DTSTART" => fields.start = parse_time(value),
+                "DTEND" => fields.end = parse_time(value),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn calendar_name(contents: &str) -> Option<String> {
+        unfold(contents)
+            .iter()
+            .find_map(|line| line.strip_prefix("X-WR-CALNAME:"))
+            .map(unescape)
+            .filter(|name| !name.is_empty())
+    }
+
+    // Lines of an .ics file, with the folded ones joined back up
+    fn unfold(contents: &str) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for line in contents.lines() {
+            match (
+                line.strip_prefix(|c| c == ' ' || c == '\t'),
+                lines.last_mut(),
+            ) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ => lines.push(line.to_string()),
+            }
+        }
+        lines
+    }
+
+    fn fold(line: &str) -> String {
+        let mut folded = String::with_capacity(line.len());
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > LINE_LIMIT {
+                folded.push_str("\r\n ");
+                width = 1;
+            }
+            folded.push(c);
+            width += c.len_utf8();
+        }
+        folded
+    }
+
+    // A date alone is an all-day event. Times with a TZID are read as local
+    // time, which is right for files from this machine.
+    fn parse_time(value: &str) -> Option<(i64, bool)> {
+        if value.len() == 8 {
+            let date = NaiveDate::parse_from_str(value, DATE_FORMAT).ok()?;
+            return Some((local(date.and_hms_opt(0, 0, 0)?)?, true));
+        }
+        if let Some(utc) = value.strip_suffix('Z') {
+            let time = NaiveDateTime::parse_from_str(utc, TIME_FORMAT).ok()?;
+            return Some((time.and_utc().timestamp(), false));
+        }
+        let time = NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()?;
+        Some((local(time)?, false))
+    }
+
+    fn local(time: NaiveDateTime) -> Option<i64> {
+        Local
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.timestamp())
+    }
+
+    fn local_date(timestamp: i64) -> String {
+        Local
+            .timestamp_opt(timestamp, 0)
+            .single()
+            .map(|time| time.format(DATE_FORMAT).to_string())
+            .unwrap_or_default()
+    }
+
+    fn utc_time(timestamp: i64) -> String {
+        DateTime::from_timestamp(timestamp, 0)
+            .map(|time| time.format(UTC_FORMAT).to_string())
+            .unwrap_or_default()
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace('\r', "")
+            .replace('\n', "\\n")
+    }
+
+    fn unescape(text: &str) -> String {
+        let mut unescaped = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => {}
+            }
+        }
+        unescaped
+    }
+}
+
+#[cfg(all(test, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar_ids_stay_in_the_folder() {
+        assert!(platform::is_file_stem("hamba"));
+        assert!(platform::is_file_stem("Work export 2024"));
+        for id in ["", "..", "../../x", "a/b", "a\\b", "C:x", "a\0b"] {
+            assert!(!platform::is_file_stem(id), "{id:?}");
+        }
+    }
+}
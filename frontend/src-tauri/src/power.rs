@@ -0,0 +1,387 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::battery_saver;
+use crate::lock;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar};
+
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+// The OS only waits a moment before sleeping, so the backend gets this long
+// to pause its jobs and no more
+#[cfg(any(target_os = "linux", windows))]
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerState {
+    suspended: bool,
+    on_battery: bool,
+    /// Charge left, `None` without a battery or when the OS doesn't say
+    battery_percent: Option<u8>,
+    /// Background work is throttled, see `battery_saver`
+    battery_saver: bool,
+}
+
+/// What the OS reports about the power supply
+#[derive(Clone, Copy)]
+pub struct Battery {
+    pub on_battery: bool,
+    pub percent: Option<u8>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+enum PowerEvent {
+    /// The machine is about to sleep; background jobs have been paused
+    #[cfg(any(target_os = "linux", windows))]
+    Suspending,
+    /// Back from sleep and the backend answers again, so data may be stale
+    Resumed,
+    #[serde(rename_all = "camelCase")]
+    PowerSource {
+        on_battery: bool,
+    },
+    BatterySaver {
+        active: bool,
+    },
+}
+
+#[derive(Default)]
+pub struct Power(Mutex<PowerState>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Power::default());
+    platform::watch_sleep(app);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Some(battery) = platform::battery() {
+                let on_battery = battery.on_battery;
+                let changed = {
+                    let power = app.state::<Power>();
+                    let mut state = power.0.lock().unwrap();
+                    let changed = state.on_battery != on_battery;
+                    state.on_battery = on_battery;
+                    state.battery_percent = battery.percent;
+                    changed
+                };
+                if changed {
+                    tracing::info!("Power source changed: on_battery={on_battery}");
+                    emit(&app, PowerEvent::PowerSource { on_battery });
+                }
+                battery_saver::apply(&app);
+            }
+            tokio::time::sleep(BATTERY_POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// Asks the backend to pause its background jobs before the machine sleeps.
+/// Only Linux and Windows say so ahead of time.
+#[cfg(any(target_os = "linux", windows))]
+pub async fn suspending(app: &AppHandle) {
+    tracing::info!("System is going to sleep");
+    app.state::<Power>().0.lock().unwrap().suspended = true;
+    if app.state::<SettingsStore>().get().lock_on_sleep {
+        lock::lock_if_enabled(app);
+    }
+    emit(app, PowerEvent::Suspending);
+
+    let response = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::POST, "/background/pause")
+        .timeout(PAUSE_TIMEOUT)
+        .send()
+        .await;
+    if let Err(err) = response.and_then(|response| response.error_for_status()) {
+        tracing::warn!("Failed to pause background jobs: {err}");
+    }
+}
+
+/// Checks the backend survived the sleep (restarting it if not) and resumes
+/// its background jobs
+pub async fn resumed(app: &AppHandle) {
+    tracing::info!("System resumed from sleep");
+    // Already the case on Linux and Windows; macOS only says so afterwards
+    if app.state::<SettingsStore>().get().lock_on_sleep {
+        lock::lock_if_enabled(app);
+    }
+    if !sidecar::wait_until_healthy(app).await {
+        tracing::warn!("Backend did not answer after resuming, restarting it");
+        sidecar::restart(app).await;
+    }
+
+    let response = app
+        .state::<Sidecar>()
+        .request(reqwest::Method::POST, "/background/resume")
+        .send()
+        .await;
+    if let Err(err) = response.and_then(|response| response.error_for_status()) {
+        tracing::warn!("Failed to resume background jobs: {err}");
+    }
+
+    app.state::<Power>().0.lock().unwrap().suspended = false;
+    emit(app, PowerEvent::Resumed);
+}
+
+fn emit(app: &AppHandle, event: PowerEvent) {
+    let _ = app.emit("power-state", event);
+}
+
+/// The power supply as of the last poll; on mains until one says otherwise
+pub fn battery(app: &AppHandle) -> Battery {
+    let state = *app.state::<Power>().0.lock().unwrap();
+    Battery {
+        on_battery: state.on_battery,
+        percent: state.battery_percent,
+    }
+}
+
+/// Tells the frontend that `battery_saver` turned on or off
+pub fn battery_saver_changed(app: &AppHandle, active: bool) {
+    emit(app, PowerEvent::BatterySaver { active });
+}
+
+#[tauri::command]
+pub fn get_power_state(app: AppHandle, power: State<'_, Power>) -> PowerState {
+    PowerState {
+        battery_saver: battery_saver::is_active(&app),
+        ..*power.0.lock().unwrap()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    use futures_util::StreamExt;
+    use tauri::AppHandle;
+    use zbus::zvariant::OwnedFd;
+
+    use super::Battery;
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.login1.Manager",
+        default_service = "org.freedesktop.login1",
+        default_path = "/org/freedesktop/login1"
+    )]
+    trait Login {
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+        #[zbus(signal)]
+        fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+    }
+
+    /// Follows logind's `PrepareForSleep`, holding a delay inhibitor so the
+    /// backend can pause before the machine actually sleeps
+    pub fn watch_sleep(app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = watch(&app).await {
+                tracing::warn!("Not watching for sleep: {err}");
+            }
+        });
+    }
+
+    async fn watch(app: &AppHandle) -> zbus::Result<()> {
+        let connection = zbus::Connection::system().await?;
+        let login = LoginProxy::new(&connection).await?;
+        let mut signals = login.receive_prepare_for_sleep().await?;
+
+        let mut inhibitor = inhibit(&login).await;
+        while let Some(signal) = signals.next().await {
+            if signal.args()?.start {
+                super::suspending(app).await;
+                // Closing the lock lets the sleep go ahead
+                inhibitor = None;
+            } else {
+                inhibitor = inhibit(&login).await;
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { super::resumed(&app).await });
+            }
+        }
+        drop(inhibitor);
+        Ok(())
+    }
+
+    async fn inhibit(login: &LoginProxy<'_>) -> Option<OwnedFd> {
+        let name = "Hamba";
+        let why = "Pausing background sync";
+        match login.inhibit("sleep", name, why, "delay").await {
+            Ok(fd) => Some(fd),
+            Err(err) => {
+                tracing::debug!("Failed to take a sleep inhibitor: {err}");
+                None
+            }
+        }
+    }
+
+    /// On battery when there is a mains supply and none is online. Machines
+    /// without one (most desktops) are never on battery. The charge is the
+    /// first battery's.
+    pub fn battery() -> Option<Battery> {
+        let mut has_mains = false;
+        let mut mains_online = false;
+        let mut percent = None;
+        for entry in fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+            let path = entry.path();
+            let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+            match kind.trim() {
+                "Mains" => {
+                    has_mains = true;
+                    mains_online |= fs::read_to_string(path.join("online"))
+                        .is_ok_and(|online| online.trim() == "1");
+                }
+                "Battery" if percent.is_none() => {
+                    percent = fs::read_to_string(path.join("capacity"))
+                        .ok()
+                        .and_then(|capacity| capacity.trim().parse().ok());
+                }
+                _ => {}
+            }
+        }
+        Some(Battery {
+            on_battery: has_mains && !mains_online,
+            percent,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    use tauri::AppHandle;
+    use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+
+    use super::Battery;
+    use windows_sys::Win32::System::Power::{
+        GetSystemPowerStatus, PowerRegisterSuspendResumeNotification,
+        DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, SYSTEM_POWER_STATUS,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND,
+    };
+
+    // Called on a system thread; Windows waits for it to return before
+    // suspending, which is what gives the pause request time to finish
+    unsafe extern "system" fn callback(context: *const c_void, kind: u32, _: *const c_void) -> u32 {
+        let app = unsafe { &*(context as *const AppHandle) };
+        match kind {
+            PBT_APMSUSPEND => tauri::async_runtime::block_on(super::suspending(app)),
+            PBT_APMRESUMEAUTOMATIC => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { super::resumed(&app).await });
+            }
+            _ => {}
+        }
+        ERROR_SUCCESS
+    }
+
+    pub fn watch_sleep(app: &AppHandle) {
+        // Both live for the rest of the process, as the registration does
+        let context = Box::into_raw(Box::new(app.clone()));
+        let params = Box::into_raw(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(callback),
+            Context: context.cast(),
+        }));
+        let mut registration = std::ptr::null_mut();
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                params.cast(),
+                &mut registration,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            tracing::warn!("Not watching for sleep: error {result}");
+        }
+    }
+
+    pub fn battery() -> Option<Battery> {
+        let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+            return None;
+        }
+        let on_battery = match status.ACLineStatus {
+            0 => true,
+            1 => false,
+            _ => return None,
+        };
+        Some(Battery {
+            on_battery,
+            // 255 when unknown
+            percent: Some(status.BatteryLifePercent).filter(|percent| *percent <= 100),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+    use std::time::{Duration, Instant, SystemTime};
+
+    use tauri::AppHandle;
+
+    use super::Battery;
+
+    const TICK: Duration = Duration::from_secs(10);
+    // Timer jitter is far below this; only an actual sleep opens such a gap
+    const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+    /// There is no sleep notification without AppKit observers, so resumes
+    /// are detected afterwards: the monotonic clock stops while asleep and the
+    /// wall clock doesn't. Background jobs can't be paused beforehand here.
+    pub fn watch_sleep(app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let (instant, wall) = (Instant::now(), SystemTime::now());
+                tokio::time::sleep(TICK).await;
+                let awake = instant.elapsed();
+                let total = wall.elapsed().unwrap_or_default();
+                if total.saturating_sub(awake) > SLEEP_GAP {
+                    super::resumed(&app).await;
+                }
+            }
+        });
+    }
+
+    // Output looks like `Now drawing from 'Battery Power'` followed by a
+    // line per battery with e.g. `85%; discharging`
+    pub fn battery() -> Option<Battery> {
+        let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        let on_battery = if output.contains("'Battery Power'") {
+            true
+        } else if output.contains("'AC Power'") {
+            false
+        } else {
+            return None;
+        };
+        let percent = output.split_whitespace().find_map(|word| {
+            word.strip_suffix("%;")
+                .and_then(|percent| percent.parse().ok())
+        });
+        Some(Battery {
+            on_battery,
+            percent,
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use tauri::AppHandle;
+
+    pub fn watch_sleep(_app: &AppHandle) {}
+
+    pub fn battery() -> Option<super::Battery> {
+        None
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use tauri::{Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+
+/// Opens the system print dialog for the calling window
+#[tauri::command]
+pub fn print_current_view(window: WebviewWindow) -> Result<(), String> {
+    window.print().map_err(|err| err.to_string())
+}
+
+/// Renders the calling window's current page to a PDF at `path`, without a
+/// dialog. Resolves once the file has been written.
+#[tauri::command]
+pub async fn export_pdf(
+    _timer: IpcTimer,
+    window: WebviewWindow,
+    path: PathBuf,
+) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err("The PDF path must be absolute".to_string());
+    }
+    if !path.parent().is_some_and(|parent| parent.is_dir()) {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    let path = fs_scope::check(window.app_handle(), &path, Access::Write)
+        .map_err(|err| err.to_string())?;
+
+    let (sender, receiver) = oneshot::channel();
+    let target = path.clone();
+    window
+        .with_webview(move |webview| platform::export_pdf(webview, target, sender))
+        .map_err(|err| err.to_string())?;
+    receiver
+        .await
+        .map_err(|_| "The export was cancelled".to_string())??;
+    tracing::info!("Exported PDF to {}", path.display());
+    Ok(())
+}
+
+type Done = oneshot::Sender<Result<(), String>>;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::{PrintOperation, PrintOperationExt};
+
+    use super::Done;
+
+    // GTK's file printer does the work; the settings pick it and the output
+    // file so no dialog is needed
+    pub fn export_pdf(webview: PlatformWebview, path: PathBuf, done: Done) {
+        let uri = match webkit2gtk::glib::filename_to_uri(&path, None) {
+            Ok(uri) => uri,
+            Err(err) => {
+                let _ = done.send(Err(err.to_string()));
+                return;
+            }
+        };
+        let settings = gtk::PrintSettings::new();
+        settings.set_printer("Print to File");
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(&uri));
+
+        let operation = PrintOperation::new(&webview.inner());
+        operation.set_print_settings(&settings);
+        // Either signal may fire, and only the first answer counts
+        let done = Rc::new(RefCell::new(Some(done)));
+        let failed = done.clone();
+        operation.connect_failed(move |_, err| {
+            if let Some(done) = failed.borrow_mut().take() {
+                let _ = done.send(Err(err.to_string()));
+            }
+        });
+        operation.connect_finished(move |_| {
+            if let Some(done) = done.borrow_mut().take() {
+                let _ = done.send(Ok(()));
+            }
+        });
+        operation.print();
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::PathBuf;
+
+    use tauri::webview::PlatformWebview;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2PrintSettings, ICoreWebView2_7,
+    };
+    use webview2_com::PrintToPdfCompletedHandler;
+    use windows_core::{Interface, HSTRING};
+
+    use super::Done;
+
+    pub fn export_pdf(webview: PlatformWebview, path: PathBuf, done: Done) {
+        let done = std::sync::Mutex::new(Some(done));
+        let finish = move |result: Result<(), String>| {
+            if let Some(done) = done.lock().unwrap().take() {
+                let _ = done.send(result);
+            }
+        };
+        let finish = std::sync::Arc::new(finish);
+        let completed = finish.clone();
+        let handler = PrintToPdfCompletedHandler::create(Box::new(move |result, success| {
+            completed(match result {
+                Err(err) => Err(err.to_string()),
+                Ok(()) if !success => Err("WebView2 could not write the PDF".to_string()),
+                Ok(()) => Ok(()),
+            });
+            Ok(())
+        }));
+
+        let path = HSTRING::from(path.as_os_str());
+        let result = unsafe {
+            webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_7>())
+                .and_then(|core| {
+                    core.PrintToPdf(&path, None::<&ICoreWebView2PrintSettings>, &handler)
+                })
+        };
+        if let Err(err) = result {
+            finish(Err(err.to_string()));
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use std::path::PathBuf;
+
+    use tauri::webview::PlatformWebview;
+
+    use super::Done;
+
+    // WKWebView only prints through the dialog, which has its own Save as PDF
+    pub fn export_pdf(_webview: PlatformWebview, _path: PathBuf, done: Done) {
+        let _ = done.send(Err(
+            "Exporting to PDF is not supported here, use Print… and choose Save as PDF".to_string(),
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::cli::Cli;
+use crate::data_dir;
+use crate::trash;
+
+const PROFILES_TOML: &str = include_str!("../profiles.toml");
+pub const DEFAULT: &str = "prod";
+const PROFILE_FLAG: &str = "--profile";
+// In the shared data folder, written by `switch_profile`
+const ACTIVE_FILE: &str = "profile.json";
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Active {
+    /// Used by launches without `--profile`
+    name: Option<String>,
+    /// Set for the relaunch after a switch, so it gets `name` even when the
+    /// app was started with `--profile`
+    switching: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profiles {
+    current: String,
+    profiles: Vec<String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProfileConfig {
+    api_base: Option<String>,
+    backend_env: BTreeMap<String, String>,
+}
+
+/// The profile this instance runs with
+pub struct Profile {
+    pub name: String,
+    /// Backend to use instead of the bundled sidecar
+    pub api_base: Option<String>,
+    pub backend_env: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT
+    }
+}
+
+/// Must run before `data_dir::init`, which keeps each profile's data apart
+pub fn init(app: &AppHandle) {
+    let mut active = read_active(app);
+    let name = match app.state::<Cli>().profile.clone() {
+        Some(name) if !active.switching => name,
+        _ => active.name.clone().unwrap_or_else(|| DEFAULT.to_string()),
+    };
+    if active.switching {
+        active.switching = false;
+        if let Err(err) = write_active(app, &active) {
+            eprintln!("Failed to save the active profile: {err}");
+        }
+    }
+    let name = if is_valid_name(&name) {
+        name
+    } else {
+        eprintln!("Ignoring invalid profile name {name:?}, using {DEFAULT}");
+        DEFAULT.to_string()
+    };
+
+    let mut profiles: BTreeMap<String, ProfileConfig> =
+        toml::from_str(PROFILES_TOML).unwrap_or_else(|err| panic!("Invalid profiles.toml: {err}"));
+    let config = profiles.remove(&name).unwrap_or_default();
+    app.manage(Profile {
+        name,
+        api_base: config
+            .api_base
+            .map(|base| base.trim_end_matches('/').to_string()),
+        backend_env: config.backend_env,
+    });
+}
+
+// Used as a folder name
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Arguments that make another launch (e.g. at login) use the same profile
+pub fn launch_args(app: &AppHandle) -> Vec<String> {
+    let profile = app.state::<Profile>();
+    if profile.is_default() {
+        return Vec::new();
+    }
+    vec![PROFILE_FLAG.to_string(), profile.name.clone()]
+}
+
+fn read_active(app: &AppHandle) -> Active {
+    data_dir::shared_data_dir(app)
+        .and_then(|dir| fs::read_to_string(dir.join(ACTIVE_FILE)).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_active(app: &AppHandle, active: &Active) -> Result<(), String> {
+    let dir = data_dir::shared_data_dir(app).ok_or("No data directory available")?;
+    let text = serde_json::to_string_pretty(active).map_err(|err| err.to_string())?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    fs::write(dir.join(ACTIVE_FILE), text).map_err(|err| err.to_string())
+}
+
+// The default, the current one and every profile that has a data folder
+fn names(app: &AppHandle) -> BTreeSet<String> {
+    let mut names = BTreeSet::from([DEFAULT.to_string(), app.state::<Profile>().name.clone()]);
+    let entries = data_dir::profiles_dir(app).and_then(|dir| fs::read_dir(dir).ok());
+    for entry in entries.into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_valid_name(&name) && entry.path().is_dir() {
+            names.insert(name);
+        }
+    }
+    names
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle, profile: State<'_, Profile>) -> Profiles {
+    Profiles {
+        current: profile.name.clone(),
+        profiles: all(&app),
+    }
+}
+
+/// The names `list_profiles` returns, for `config_bundle`
+pub fn all(app: &AppHandle) -> Vec<String> {
+    names(app).into_iter().collect()
+}
+
+/// Whether a profile named `name` already exists
+pub fn exists(app: &AppHandle, name: &str) -> bool {
+    names(app).contains(name)
+}
+
+/// Creates an empty profile, which starts out like a fresh install once
+/// switched to
+#[tauri::command]
+pub fn create_profile(app: AppHandle, name: String) -> Result<(), String> {
+    if !is_valid_name(&name) {
+        return Err("Profile names may only use lowercase letters, digits and dashes".to_string());
+    }
+    if exists(&app, &name) {
+        return Err(format!("A profile named {name:?} already exists"));
+    }
+    let dir = data_dir::profiles_dir(&app).ok_or("No data directory available")?;
+    fs::create_dir_all(dir.join(&name)).map_err(|err| err.to_string())
+}
+
+/// Moves a profile's folders to the trash, from where `restore_from_trash`
+/// brings it back. Its keychain entries are kept for that. The default and
+/// the current profile can't be deleted.
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, name: String) -> Result<(), String> {
+    if name == DEFAULT {
+        return Err("The default profile can't be deleted".to_string());
+    }
+    if name == app.state::<Profile>().name {
+        return Err("Switch to another profile before deleting this one".to_string());
+    }
+    if !is_valid_name(&name) || !exists(&app, &name) {
+        return Err(format!("No profile named {name:?}"));
+    }
+    trash::move_to_trash(
+        &app,
+        &format!("Profile {name} deleted"),
+        &data_dir::profile_dirs(&app, &name),
+    )
+    .map_err(|err| err.to_string())?;
+    // Launches without `--profile` would otherwise bring it back empty
+    let mut active = read_active(&app);
+    if active.name.as_deref() == Some(name.as_str()) {
+        active.name = None;
+        write_active(&app, &active)?;
+    }
+    tracing::info!("Deleted profile {name}");
+    Ok(())
+}
+
+/// Relaunches the app on `name`, which is also used by later launches
+/// without `--profile`. Everything, the backend included, belongs to one
+/// profile, so a switch is a restart.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, name: String) -> Result<(), String> {
+    if name == app.state::<Profile>().name {
+        return Ok(());
+    }
+    if !names(&app).contains(&name) {
+        return Err(format!("No profile named {name:?}"));
+    }
+    write_active(
+        &app,
+        &Active {
+            name: Some(name.clone()),
+            switching: true,
+        },
+    )?;
+    tracing::info!("Switching to profile {name}");
+    app.request_restart();
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Url, WebviewWindow};
+
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+use crate::sidecar;
+
+// A proxy that can't route back to this machine would cut the frontend off
+// from the backend, so these always skip it
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1"];
+const TEST_URL: &str = "https://hamba.app";
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProxyMode {
+    /// Whatever the OS or the environment the app was started from uses
+    #[default]
+    System,
+    /// `proxy_url` and `proxy_bypass` from the settings
+    Manual,
+    /// Always connect directly
+    Off,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proxy {
+    /// `None` to connect directly
+    pub url: Option<String>,
+    /// Hosts that skip the proxy, in `NO_PROXY` syntax
+    pub bypass: Vec<String>,
+}
+
+/// The proxy the backend and the app's own requests should use right now
+pub fn resolve(app: &AppHandle) -> Proxy {
+    let settings = app.state::<SettingsStore>().get();
+    match settings.proxy_mode {
+        ProxyMode::Off => Proxy::default(),
+        ProxyMode::Manual => Proxy {
+            url: settings.proxy_url.as_deref().map(normalize),
+            bypass: settings.proxy_bypass,
+        },
+        ProxyMode::System => from_env().or_else(platform::system).unwrap_or_default(),
+    }
+}
+
+fn from_env() -> Option<Proxy> {
+    let url = [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ]
+    .iter()
+    .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))?;
+    let bypass = std::env::var("NO_PROXY")
+        .or_else(|_| std::env::var("no_proxy"))
+        .unwrap_or_default();
+    Some(Proxy {
+        url: Some(normalize(&url)),
+        bypass: split_hosts(&bypass, ','),
+    })
+}
+
+fn split_hosts(list: &str, separator: char) -> Vec<String> {
+    list.split(separator)
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// OS settings usually give a bare `host:port`
+fn normalize(url: &str) -> String {
+    let url = url.trim();
+    if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{url}")
+    }
+}
+
+/// Rejects a proxy URL before it is saved, so a typo can't cut the backend
+/// off on the next start
+pub fn validate(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(&normalize(url)).map_err(|err| format!("Invalid proxy URL: {err}"))?;
+    if !SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "Proxy URL must start with one of {}",
+            SCHEMES.join("://, ") + "://"
+        ));
+    }
+    if parsed.host_str().is_none() {
+        return Err("Proxy URL has no host".to_string());
+    }
+    Ok(())
+}
+
+/// Proxy variables for a sidecar. They are always set, empty when there is
+/// no proxy, so ones inherited from the app's environment can't override
+/// the settings.
+pub fn env(proxy: &Proxy) -> Vec<(&'static str, String)> {
+    let url = proxy.url.clone().unwrap_or_default();
+    let no_proxy = LOCAL_HOSTS
+        .iter()
+        .map(|host| host.to_string())
+        .chain(proxy.bypass.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(",");
+    vec![
+        ("HTTP_PROXY", url.clone()),
+        ("http_proxy", url.clone()),
+        ("HTTPS_PROXY", url.clone()),
+        ("https_proxy", url),
+        ("NO_PROXY", no_proxy.clone()),
+        ("no_proxy", no_proxy),
+    ]
+}
+
+/// A client for requests leaving the machine, going through the proxy
+pub fn client(proxy: &Proxy) -> Result<reqwest::Client, String> {
+    builder(proxy)?.build().map_err(|err| err.to_string())
+}
+
+/// `client` for callers that need more options on it
+pub fn builder(proxy: &Proxy) -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder();
+    Ok(match &proxy.url {
+        Some(url) => {
+            let bypass = LOCAL_HOSTS
+                .iter()
+                .map(|host| host.to_string())
+                .chain(proxy.bypass.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(",");
+            let proxy = reqwest::Proxy::all(url)
+                .map_err(|err| format!("Invalid proxy URL: {err}"))?
+                .no_proxy(reqwest::NoProxy::from_string(&bypass));
+            builder.proxy(proxy)
+        }
+        None => builder.no_proxy(),
+    })
+}
+
+/// Points `window`'s webview at the configured proxy. Only WebKitGTK can
+/// change it after the webview is created; WebView2 and WKWebView keep
+/// following the OS proxy, which is what `System` means anyway.
+pub fn configure(window: &WebviewWindow) {
+    let mode = window.state::<SettingsStore>().get().proxy_mode;
+    let proxy = resolve(window.app_handle());
+    let result = window.with_webview(move |webview| platform::configure(webview, mode, proxy));
+    if let Err(err) = result {
+        tracing::warn!("Failed to set the webview proxy: {err}");
+    }
+}
+
+/// Brings everything onto changed proxy settings. The backend only reads
+/// them at startup, so it is restarted.
+pub fn apply(app: &AppHandle) {
+    for window in app.webview_windows().values() {
+        configure(window);
+    }
+    if app.state::<sidecar::Sidecar>().is_external() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if !sidecar::restart(&app).await {
+            tracing::error!("Backend did not come back up after changing the proxy");
+        }
+    });
+}
+
+/// Fetches a page through `url`, or the current proxy without one, and
+/// resolves with how long it took in milliseconds
+#[tauri::command]
+pub async fn test_proxy(
+    _timer: IpcTimer,
+    app: AppHandle,
+    url: Option<String>,
+) -> Result<u64, String> {
+    let proxy = match url {
+        Some(url) => {
+            validate(&url)?;
+            Proxy {
+                url: Some(normalize(&url)),
+                bypass: Vec::new(),
+            }
+        }
+        None => resolve(&app),
+    };
+    let started = Instant::now();
+    client(&proxy)?
+        .get(TEST_URL)
+        .timeout(TEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+    Ok(started.elapsed().as_millis() as u64)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::{
+        NetworkProxyMode, NetworkProxySettings, WebContextExt, WebViewExt, WebsiteDataManagerExt,
+    };
+
+    use super::{split_hosts, Proxy, ProxyMode, LOCAL_HOSTS};
+
+    // GNOME's proxy settings, which most other desktops also write
+    pub fn system() -> Option<Proxy> {
+        if gsettings("org.gnome.system.proxy", "mode")? != "manual" {
+            return None;
+        }
+        let url = ["https", "http", "socks"].iter().find_map(|scheme| {
+            let schema = format!("org.gnome.system.proxy.{scheme}");
+            let host = gsettings(&schema, "host")?;
+            let port = gsettings(&schema, "port")?;
+            let scheme = if *scheme == "socks" { "socks5" } else { "http" };
+            (!host.is_empty() && port != "0").then(|| format!("{scheme}://{host}:{port}"))
+        })?;
+        // A list like ['localhost', '127.0.0.0/8']
+        let ignored = gsettings("org.gnome.system.proxy", "ignore-hosts").unwrap_or_default();
+        let bypass = split_hosts(ignored.trim_matches(['[', ']']), ',')
+            .into_iter()
+            .map(|host| host.trim_matches('\'').to_string())
+            .collect();
+        Some(Proxy {
+            url: Some(url),
+            bypass,
+        })
+    }
+
+    fn gsettings(schema: &str, key: &str) -> Option<String> {
+        let output = Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let value = String::from_utf8_lossy(&output.stdout);
+        Some(value.trim().trim_matches('\'').to_string())
+    }
+
+    // The proxy belongs to the data manager all webviews share, so setting it
+    // again from each window is harmless
+    pub fn configure(webview: PlatformWebview, mode: ProxyMode, proxy: Proxy) {
+        let Some(manager) = webview
+            .inner()
+            .context()
+            .and_then(|context| context.website_data_manager())
+        else {
+            return;
+        };
+        match (mode, &proxy.url) {
+            (ProxyMode::Manual, Some(url)) => {
+                let ignored: Vec<&str> = LOCAL_HOSTS
+                    .iter()
+                    .copied()
+                    .chain(proxy.bypass.iter().map(String::as_str))
+                    .collect();
+                let mut settings = NetworkProxySettings::new(Some(url), &ignored);
+                manager.set_network_proxy_settings(NetworkProxyMode::Custom, Some(&mut settings));
+            }
+            (ProxyMode::Manual | ProxyMode::Off, _) => {
+                manager.set_network_proxy_settings(NetworkProxyMode::NoProxy, None)
+            }
+            (ProxyMode::System, _) => {
+                manager.set_network_proxy_settings(NetworkProxyMode::Default, None)
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    use super::{split_hosts, Proxy, ProxyMode};
+
+    const INTERNET_SETTINGS: &str = r"Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+
+    // The "Use a proxy server" setting. Automatic configuration scripts
+    // aren't read; WebView2 still follows them.
+    pub fn system() -> Option<Proxy> {
+        let key = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(INTERNET_SETTINGS)
+            .ok()?;
+        if key.get_value::<u32, _>("ProxyEnable").ok()? == 0 {
+            return None;
+        }
+        // Either `host:port` for everything or `http=host:port;https=host:port`
+        let server: String = key.get_value("ProxyServer").ok()?;
+        let url = if server.contains('=') {
+            let servers = split_hosts(&server, ';');
+            ["https=", "http="].iter().find_map(|prefix| {
+                servers
+                    .iter()
+                    .find_map(|entry| entry.strip_prefix(prefix))
+                    .map(str::to_string)
+            })?
+        } else {
+            server
+        };
+        // `<local>` stands for plain hostnames, and local hosts skip the
+        // proxy regardless
+        let overrides: String = key.get_value("ProxyOverride").unwrap_or_default();
+        let bypass = split_hosts(&overrides, ';')
+            .into_iter()
+            .filter(|host| host != "<local>")
+            .collect();
+        Some(Proxy {
+            url: Some(super::normalize(&url)),
+            bypass,
+        })
+    }
+
+    pub fn configure(_webview: PlatformWebview, _mode: ProxyMode, _proxy: Proxy) {}
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    use tauri::webview::PlatformWebview;
+
+    use super::{Proxy, ProxyMode};
+
+    // `scutil --proxy` prints the active network service's proxies as
+    // `Key : value` lines, with the exceptions as a nested array
+    pub fn system() -> Option<Proxy> {
+        let output = Command::new("scutil").arg("--proxy").output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut values = std::collections::HashMap::new();
+        let mut bypass = Vec::new();
+        let mut in_exceptions = false;
+        for line in text.lines().map(str::trim) {
+            if line.starts_with("ExceptionsList") {
+                in_exceptions = true;
+                continue;
+            }
+            if line == "}" {
+                in_exceptions = false;
+                continue;
+            }
+            let Some((key, value)) = line.split_once(" : ") else {
+                continue;
+            };
+            if in_exceptions {
+                bypass.push(value.to_string());
+            } else {
+                values.insert(key.to_string(), value.to_string());
+            }
+        }
+        let url = [("HTTPS", "http"), ("HTTP", "http"), ("SOCKS", "socks5")]
+            .iter()
+            .find_map(|(prefix, scheme)| {
+                if values.get(&format!("{prefix}Enable"))? != "1" {
+                    return None;
+                }
+                let host = values.get(&format!("{prefix}Proxy"))?;
+                let port = values.get(&format!("{prefix}Port"))?;
+                Some(format!("{scheme}://{host}:{port}"))
+            })?;
+        Some(Proxy {
+            url: Some(url),
+            bypass,
+        })
+    }
+
+    pub fn configure(_webview: PlatformWebview, _mode: ProxyMode, _proxy: Proxy) {}
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use tauri::webview::PlatformWebview;
+
+    use super::{Proxy, ProxyMode};
+
+    pub fn system() -> Option<Proxy> {
+        None
+    }
+
+    pub fn configure(_webview: PlatformWebview, _mode: ProxyMode, _proxy: Proxy) {}
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, Manager, State, WebviewWindow, WebviewWindowBuilder, Window, WindowEvent,
+};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::frontend_bundle;
+use crate::lock;
+use crate::main_window;
+use crate::proxy;
+use crate::settings::SettingsStore;
+use crate::theme;
+use crate::tls;
+use crate::zoom;
+
+pub const LABEL: &str = "quick-open";
+pub const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+O";
+
+/// The search result picked in the palette, handed to the main window as
+/// `quick-open`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickOpenResult {
+    email_id: String,
+    account_id: String,
+    /// What was typed, so the main window can show the result among the rest
+    query: String,
+}
+
+/// Registers the configured hotkey. A shortcut another app already owns only
+/// logs a warning, the shortcut can still be changed from the settings.
+pub fn init(app: &AppHandle) {
+    if let Some(shortcut) = app.state::<SettingsStore>().get().quick_open_shortcut {
+        if let Err(err) = register(app, &shortcut) {
+            tracing::warn!("Failed to register quick open shortcut {shortcut:?}: {err}");
+        }
+    }
+}
+
+fn register(app: &AppHandle, shortcut: &str) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, |app, _, event| {
+            if event.state == ShortcutState::Pressed {
+                toggle(app);
+            }
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// Shows the palette, or hides it if it's already up. Showing emits
+/// `quick-open-shown` so the page can clear the last search.
+pub fn toggle(app: &AppHandle) {
+    if lock::is_locked(app) {
+        return lock::focus(app);
+    }
+    if let Some(window) = app.get_webview_window(LABEL) {
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+        } else {
+            let _ = window.center();
+            let _ = window.show();
+            let _ = window.set_focus();
+            let _ = window.emit_to(LABEL, "quick-open-shown", ());
+        }
+        return;
+    }
+
+    match create(app) {
+        Ok(window) => {
+            let _ = window.set_focus();
+        }
+        Err(err) => tracing::error!("Failed to open quick open window: {err}"),
+    }
+}
+
+// Created on first use and then only hidden, so later opens are instant
+fn create(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+    let window = WebviewWindowBuilder::new(app, LABEL, frontend_bundle::url("quick-open"))
+        .title("Quick open")
+        .inner_size(640.0, 400.0)
+        .decorations(false)
+        .resizable(false)
+        .minimizable(false)
+        .maximizable(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .initialization_script(main_window::initialization_script(app)?)
+        .background_color(theme::background(
+            app.state::<SettingsStore>().get().theme_override,
+        ))
+        .build()?;
+    tls::trust_backend(&window);
+    proxy::configure(&window);
+    zoom::restore(&window);
+    Ok(window)
+}
+
+/// Closing or clicking away from the palette hides it instead of destroying it
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != LABEL {
+        return;
+    }
+    match event {
+        WindowEvent::CloseRequested { api, .. } => {
+            api.prevent_close();
+            let _ = window.hide();
+        }
+        WindowEvent::Focused(false) => {
+            let _ = window.hide();
+        }
+        _ => {}
+    }
+}
+
+#[tauri::command]
+pub fn hide_quick_open(app: AppHandle) {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.hide();
+    }
+}
+
+/// Hides the palette and opens `result` in the main window
+#[tauri::command]
+pub fn open_quick_open_result(app: AppHandle, result: QuickOpenResult) -> Result<(), String> {
+    hide_quick_open(app.clone());
+    if app.get_webview_window(main_window::LABEL).is_none() {
+        return Err("The main window isn't open".to_string());
+    }
+    main_window::focus(&app);
+    app.emit_to(main_window::LABEL, "quick-open", result)
+        .map_err(|err| err.to_string())
+}
+
+/// Swaps the registered hotkey from `previous` to `shortcut`, leaving the
+/// previous one in place if the new one can't be registered
+pub fn apply(
+    app: &AppHandle,
+    previous: Option<&str>,
+    shortcut: Option<&str>,
+) -> Result<(), String> {
+    if let Some(shortcut) = shortcut {
+        shortcut
+            .parse::<Shortcut>()
+            .map_err(|err| format!("Invalid shortcut {shortcut:?}: {err}"))?;
+    }
+
+    if let Some(previous) = previous {
+        let _ = app.global_shortcut().unregister(previous);
+    }
+    if let Some(shortcut) = shortcut {
+        if let Err(err) = register(app, shortcut) {
+            // Put the old one back so the user isn't left without any
+            if let Some(previous) = previous {
+                let _ = register(app, previous);
+            }
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Changes the hotkey, e.g. `CommandOrControl+Shift+O`. `None` turns it off.
+#[tauri::command]
+pub fn set_quick_open_shortcut(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    shortcut: Option<String>,
+) -> Result<(), String> {
+    let previous = settings.get().quick_open_shortcut;
+    apply(&app, previous.as_deref(), shortcut.as_deref())?;
+
+    settings
+        .update(|settings| settings.quick_open_shortcut = shortcut)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,270 @@
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::command::{ensure, CommandResult, Valid, Validate};
+
+// The dock menu and jump list get unwieldy past this, and Windows trims
+// the jump list to fit the screen anyway
+const MAX_ITEMS: usize = 10;
+const SCHEME: &str = "hamba://";
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecentItemKind {
+    /// Something opened lately, e.g. an email or a search
+    #[default]
+    Recent,
+    /// A fixed shortcut, e.g. composing an email
+    Action,
+}
+
+/// An entry for the dock menu or jump list, opened through the deep link path
+#[derive(Clone, Deserialize)]
+#[cfg_attr(not(any(target_os = "macos", windows)), allow(dead_code))]
+#[serde(rename_all = "camelCase")]
+pub struct RecentItem {
+    title: String,
+    /// A `hamba://` link
+    url: String,
+    #[serde(default)]
+    kind: RecentItemKind,
+}
+
+impl Validate for RecentItem {
+    fn validate(&self) -> CommandResult<()> {
+        ensure!(
+            !self.title.trim().is_empty(),
+            "title",
+            "Recent items need a title"
+        );
+        ensure!(
+            self.url.starts_with(SCHEME),
+            "url",
+            "{:?} is not a {SCHEME} link",
+            self.url
+        );
+        Ok(())
+    }
+}
+
+/// Replaces the recent items and quick actions in the macOS dock menu and
+/// the Windows jump list. Elsewhere this does nothing.
+#[tauri::command]
+pub fn set_recent_items(app: AppHandle, items: Valid<Vec<RecentItem>>) -> CommandResult<()> {
+    let items: Vec<RecentItem> = items.0.into_iter().take(MAX_ITEMS).collect();
+    Ok(platform::set(&app, items)?)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, CString};
+    use std::sync::{Mutex, OnceLock};
+
+    use objc2::rc::{Allocated, Retained};
+    use objc2::runtime::{AnyClass, AnyObject, Imp, Sel};
+    use objc2::{class, msg_send, sel};
+    use tauri::AppHandle;
+
+    use super::RecentItem;
+    use crate::deep_link;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+    static ITEMS: Mutex<Vec<RecentItem>> = Mutex::new(Vec::new());
+
+    type DockMenu = unsafe extern "C-unwind" fn(&AnyObject, Sel, *mut AnyObject) -> *mut AnyObject;
+    type OpenItem = unsafe extern "C-unwind" fn(&AnyObject, Sel, *mut AnyObject);
+
+    pub fn set(app: &AppHandle, items: Vec<RecentItem>) -> Result<(), String> {
+        *ITEMS.lock().unwrap() = items;
+        if APP.set(app.clone()).is_ok() {
+            app.run_on_main_thread(install)
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    // AppKit asks the app delegate for the dock menu each time it opens.
+    // Tauri's delegate has no such method, so it's added to its class, along
+    // with the action the menu items send up the responder chain.
+    fn install() {
+        unsafe {
+            let ns_app: Option<Retained<AnyObject>> =
+                msg_send![class!(NSApplication), sharedApplication];
+            let Some(ns_app) = ns_app else {
+                return;
+            };
+            let delegate: Option<Retained<AnyObject>> = msg_send![&*ns_app, delegate];
+            let Some(delegate) = delegate else {
+                tracing::warn!("No app delegate to add the dock menu to");
+                return;
+            };
+            let class = delegate.class() as *const AnyClass as *mut AnyClass;
+            let dock_menu = std::mem::transmute::<DockMenu, Imp>(dock_menu);
+            let open_item = std::mem::transmute::<OpenItem, Imp>(open_item);
+            let added = objc2::ffi::class_addMethod(
+                class,
+                sel!(applicationDockMenu:),
+                dock_menu,
+                c"@@:@".as_ptr(),
+            )
+            .as_bool()
+                && objc2::ffi::class_addMethod(
+                    class,
+                    sel!(hambaOpenRecentItem:),
+                    open_item,
+                    c"v@:@".as_ptr(),
+                )
+                .as_bool();
+            if !added {
+                tracing::warn!("The app delegate already has a dock menu");
+            }
+        }
+    }
+
+    unsafe extern "C-unwind" fn dock_menu(
+        _this: &AnyObject,
+        _cmd: Sel,
+        _sender: *mut AnyObject,
+    ) -> *mut AnyObject {
+        let items = ITEMS.lock().unwrap().clone();
+        unsafe {
+            let menu: Retained<AnyObject> = msg_send![class!(NSMenu), new];
+            let mut last_kind = None;
+            for (index, item) in items.iter().enumerate() {
+                // Recent items and actions are kept apart by a separator
+                if last_kind.is_some_and(|kind| kind != item.kind) {
+                    let separator: Retained<AnyObject> =
+                        msg_send![class!(NSMenuItem), separatorItem];
+                    let _: () = msg_send![&*menu, addItem: &*separator];
+                }
+                last_kind = Some(item.kind);
+
+                let Some(title) = ns_string(&item.title) else {
+                    continue;
+                };
+                let Some(key) = ns_string("") else {
+                    continue;
+                };
+                let entry: Allocated<AnyObject> = msg_send![class!(NSMenuItem), alloc];
+                let entry: Retained<AnyObject> = msg_send![
+                    entry,
+                    initWithTitle: &*title,
+                    action: sel!(hambaOpenRecentItem:),
+                    keyEquivalent: &*key
+                ];
+                let _: () = msg_send![&*entry, setTag: index as isize];
+                let _: () = msg_send![&*menu, addItem: &*entry];
+            }
+            Retained::autorelease_return(menu)
+        }
+    }
+
+    unsafe extern "C-unwind" fn open_item(_this: &AnyObject, _cmd: Sel, sender: *mut AnyObject) {
+        let Some(sender) = (unsafe { sender.as_ref() }) else {
+            return;
+        };
+        let index: isize = unsafe { msg_send![sender, tag] };
+        let url = usize::try_from(index).ok().and_then(|index| {
+            ITEMS
+                .lock()
+                .unwrap()
+                .get(index)
+                .map(|item| item.url.clone())
+        });
+        if let (Some(app), Some(url)) = (APP.get(), url) {
+            deep_link::open(app, vec![url]);
+        }
+    }
+
+    unsafe fn ns_string(text: &str) -> Option<Retained<AnyObject>> {
+        let text = CString::new(text.replace('\0', "")).ok()?;
+        let ptr: *const c_char = text.as_ptr();
+        unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::AppHandle;
+    use windows::core::{Interface, HSTRING};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+        COINIT_MULTITHREADED,
+    };
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+    };
+
+    use super::{RecentItem, RecentItemKind};
+
+    const RECENT_CATEGORY: &str = "Recent";
+
+    pub fn set(_app: &AppHandle, items: Vec<RecentItem>) -> Result<(), String> {
+        // COM is set up per thread, and the command may run on the UI thread,
+        // which already has it set up differently
+        std::thread::spawn(move || unsafe {
+            let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+            let result = build(&items);
+            if initialized {
+                CoUninitialize();
+            }
+            result
+        })
+        .join()
+        .map_err(|_| "Updating the jump list failed".to_string())?
+        .map_err(|err| err.to_string())
+    }
+
+    // Each entry starts the app again with the link as its argument, which
+    // the single instance plugin hands to the running app as a deep link
+    unsafe fn build(items: &[RecentItem]) -> windows::core::Result<()> {
+        let exe = std::env::current_exe().map_err(|_| windows::core::Error::empty())?;
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut slots = 0;
+        let _removed: IObjectArray = list.BeginList(&mut slots)?;
+
+        let recent: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        let actions: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        for item in items {
+            let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+            link.SetPath(&HSTRING::from(exe.as_os_str()))?;
+            link.SetArguments(&HSTRING::from(format!("\"{}\"", item.url)))?;
+            link.SetIconLocation(&HSTRING::from(exe.as_os_str()), 0)?;
+            link.SetDescription(&HSTRING::from(item.title.as_str()))?;
+            let properties: IPropertyStore = link.cast()?;
+            properties.SetValue(&PKEY_Title, &item.title.as_str().into())?;
+            properties.Commit()?;
+            match item.kind {
+                RecentItemKind::Recent => recent.AddObject(&link)?,
+                RecentItemKind::Action => actions.AddObject(&link)?,
+            }
+        }
+
+        let recent: IObjectArray = recent.cast()?;
+        if recent.GetCount()? > 0 {
+            list.AppendCategory(&HSTRING::from(RECENT_CATEGORY), &recent)?;
+        }
+        let actions: IObjectArray = actions.cast()?;
+        if actions.GetCount()? > 0 {
+            list.AddUserTasks(&actions)?;
+        }
+        list.CommitList()
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    use tauri::AppHandle;
+
+    use super::RecentItem;
+
+    pub fn set(_app: &AppHandle, _items: Vec<RecentItem>) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::command::{ensure, CommandResult, Valid, Validate};
+use crate::data_dir;
+use crate::dnd;
+use crate::main_window;
+use crate::notifications;
+use crate::safe_mode;
+use crate::sidecar::Sidecar;
+
+const REMINDERS_FILE: &str = "reminders.json";
+const CATEGORY: &str = "reminder";
+const SNOOZE_ACTION: &str = "snooze";
+const COMPLETE_ACTION: &str = "complete";
+const SNOOZE_SECS: i64 = 10 * 60;
+// Timers stop while the machine sleeps, so the clock is looked at again at
+// least this often and anything due during sleep fires soon after waking
+const MAX_WAIT: Duration = Duration::from_secs(60);
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_PENDING: usize = 1000;
+
+/// An email to bring back up at `remind_at`, as the frontend sends it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    email_id: String,
+    #[serde(default)]
+    account_id: Option<String>,
+    /// e.g. the subject
+    title: String,
+    /// e.g. the sender
+    #[serde(default)]
+    body: Option<String>,
+    /// Seconds since the Unix epoch, as the backend keeps it
+    remind_at: i64,
+}
+
+impl Validate for Reminder {
+    fn validate(&self) -> CommandResult<()> {
+        ensure!(
+            !self.email_id.is_empty() && !self.email_id.contains(['/', '?', '#']),
+            "emailId",
+            "{:?} is not an email id",
+            self.email_id
+        );
+        ensure!(
+            self.title.chars().count() <= MAX_TITLE_CHARS,
+            "title",
+            "Reminder titles are at most {MAX_TITLE_CHARS} characters"
+        );
+        ensure!(self.remind_at > 0, "remindAt", "Reminders need a time");
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReminderClicked {
+    email_id: String,
+    account_id: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReminderUpdated {
+    email_id: String,
+    /// `None` once completed
+    remind_at: Option<i64>,
+}
+
+/// Reminders the shell fires itself, so they show while the window is
+/// hidden to the tray. Kept in `reminders.json` in the config directory;
+/// ones that came due while the app was closed fire on the next start.
+pub struct Reminders {
+    path: Option<PathBuf>,
+    pending: Mutex<BTreeMap<String, Reminder>>,
+    // Rechecks what's due after a change
+    wake: Notify,
+}
+
+impl Reminders {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let saved: Vec<Reminder> = self.pending.lock().unwrap().values().cloned().collect();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&saved)?)
+    }
+
+    fn insert(&self, reminder: Reminder) -> io::Result<()> {
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(reminder.email_id.clone(), reminder);
+        self.wake.notify_one();
+        self.save()
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(REMINDERS_FILE));
+    let saved: Vec<Reminder> = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                tracing::warn!("Ignoring an invalid {REMINDERS_FILE}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default();
+    app.manage(Reminders {
+        path,
+        pending: Mutex::new(
+            saved
+                .into_iter()
+                .map(|reminder| (reminder.email_id.clone(), reminder))
+                .collect(),
+        ),
+        wake: Notify::new(),
+    });
+
+    if safe_mode::is_active(app) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let reminders = app.state::<Reminders>();
+        loop {
+            let next = fire_due(&app);
+            let wait = next
+                .map(|at| Duration::from_secs(at.saturating_sub(now()).max(0) as u64))
+                .map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT));
+            let _ = tokio::time::timeout(wait, reminders.wake.notified()).await;
+        }
+    });
+}
+
+/// Shows the reminders that are due and returns when the next one is. Quiet
+/// time keeps them waiting until it's over.
+fn fire_due(app: &AppHandle) -> Option<i64> {
+    if dnd::is_quiet(app) {
+        return None;
+    }
+    let reminders = app.state::<Reminders>();
+    let now = now();
+    let (due, next) = {
+        let mut pending = reminders.pending.lock().unwrap();
+        let due: Vec<Reminder> = pending
+            .values()
+            .filter(|reminder| reminder.remind_at <= now)
+            .cloned()
+            .collect();
+        for reminder in &due {
+            pending.remove(&reminder.email_id);
+        }
+        (
+            due,
+            pending.values().map(|reminder| reminder.remind_at).min(),
+        )
+    };
+    if !due.is_empty() {
+        if let Err(err) = reminders.save() {
+            tracing::warn!("Failed to save {REMINDERS_FILE}: {err}");
+        }
+    }
+    for reminder in due {
+        fire(app, reminder);
+    }
+    next
+}
+
+fn fire(app: &AppHandle, reminder: Reminder) {
+    let title = if reminder.title.trim().is_empty() {
+        "(no subject)"
+    } else {
+        &reminder.title
+    };
+    let body = reminder.body.clone().unwrap_or_default();
+    let handle = app.clone();
+    let responded = reminder.clone();
+    let shown = notifications::show_actionable(
+        app,
+        CATEGORY,
+        title,
+        &body,
+        &[
+            (SNOOZE_ACTION, "Snooze 10 Min"),
+            (COMPLETE_ACTION, "Complete"),
+        ],
+        move |action| {
+            let action = action.to_string();
+            tauri::async_runtime::spawn(async move { respond(&handle, responded, &action).await });
+        },
+    );
+    if shown {
+        tracing::info!("Showed the reminder for {}", reminder.email_id);
+    }
+}
+
+async fn respond(app: &AppHandle, mut reminder: Reminder, action: &str) {
+    let path = format!("/emails/{}/reminder", reminder.email_id);
+    let sidecar = app.state::<Sidecar>();
+    let (request, remind_at) = match action {
+        SNOOZE_ACTION => {
+            reminder.remind_at = now() + SNOOZE_SECS;
+            let remind_at = reminder.remind_at;
+            if let Err(err) = app.state::<Reminders>().insert(reminder.clone()) {
+                tracing::warn!("Failed to save {REMINDERS_FILE}: {err}");
+            }
+            let request = sidecar
+                .request(reqwest::Method::POST, &path)
+                .json(&json!({ "remindAt": remind_at }));
+            (request, Some(remind_at))
+        }
+        COMPLETE_ACTION => (sidecar.request(reqwest::Method::DELETE, &path), None),
+        _ => {
+            main_window::focus(app);
+            let _ = app.emit(
+                "reminder-clicked",
+                ReminderClicked {
+                    email_id: reminder.email_id,
+                    account_id: reminder.account_id,
+                },
+            );
+            return;
+        }
+    };
+    if let Err(err) = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        tracing::warn!(
+            "Failed to update the reminder for {} on the backend: {err}",
+            reminder.email_id
+        );
+        return;
+    }
+    let _ = app.emit(
+        "reminder-updated",
+        ReminderUpdated {
+            email_id: reminder.email_id,
+            remind_at,
+        },
+    );
+}
+
+/// Fires a notification for the email at its `remindAt`, replacing any
+/// reminder it had. Saving it on the backend is up to the frontend.
+#[tauri::command]
+pub fn schedule_reminder(
+    reminders: State<'_, Reminders>,
+    reminder: Valid<Reminder>,
+) -> CommandResult<()> {
+    let reminder = reminder.0;
+    {
+        let pending = reminders.pending.lock().unwrap();
+        ensure!(
+            pending.len() < MAX_PENDING || pending.contains_key(&reminder.email_id),
+            "reminder",
+            "At most {MAX_PENDING} reminders can be pending"
+        );
+    }
+    Ok(reminders.insert(reminder)?)
+}
+
+#[tauri::command]
+pub fn cancel_reminder(reminders: State<'_, Reminders>, email_id: String) -> CommandResult<()> {
+    if reminders
+        .pending
+        .lock()
+        .unwrap()
+        .remove(&email_id)
+        .is_none()
+    {
+        return Ok(());
+    }
+    Ok(reminders.save()?)
+}
+
+#[tauri::command]
+pub fn list_reminders(reminders: State<'_, Reminders>) -> Vec<Reminder> {
+    let mut list: Vec<Reminder> = reminders
+        .pending
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect();
+    list.sort_by_key(|reminder| reminder.remind_at);
+    list
+}
@@ -0,0 +1,162 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::settings::SettingsStore;
+use crate::sidecar::Sidecar;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+// CPU spikes are normal while syncing; only a sustained high load (this many
+// samples in a row) is worth a warning
+const CPU_WARN_SAMPLES: u32 = 6;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStats {
+    pub pid: u32,
+    /// Percent of a single core, so it can go past 100 on multi-core machines
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum ResourceWarning {
+    Cpu {
+        percent: f32,
+        threshold: f32,
+    },
+    Memory {
+        bytes: u64,
+        threshold: u64,
+    },
+    /// The hard memory cap was hit and the backend was restarted
+    MemoryLimit {
+        bytes: u64,
+        limit: u64,
+    },
+}
+
+/// The most recent sample, `None` while the backend isn't running
+#[derive(Default)]
+pub struct Resources(Mutex<Option<BackendStats>>);
+
+// Warnings are only emitted when a threshold is first crossed
+#[derive(Default)]
+struct Alarms {
+    high_cpu_samples: u32,
+    cpu: bool,
+    memory: bool,
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Resources::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut system = System::new();
+        let mut alarms = Alarms::default();
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            let stats = sample(&app, &mut system);
+            if let Some(stats) = &stats {
+                check(&app, stats, &mut alarms);
+            } else {
+                alarms = Alarms::default();
+            }
+            *app.state::<Resources>().0.lock().unwrap() = stats;
+        }
+    });
+}
+
+fn sample(app: &AppHandle, system: &mut System) -> Option<BackendStats> {
+    let pid = app.state::<Sidecar>().pid()?;
+    let sys_pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[sys_pid]),
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+    let process = system.process(sys_pid)?;
+    Some(BackendStats {
+        pid,
+        cpu_percent: process.cpu_usage(),
+        memory_bytes: process.memory(),
+    })
+}
+
+fn check(app: &AppHandle, stats: &BackendStats, alarms: &mut Alarms) {
+    let settings = app.state::<SettingsStore>().get();
+
+    if let Some(limit_mb) = settings.backend_memory_limit_mb {
+        let limit = limit_mb * 1024 * 1024;
+        if stats.memory_bytes > limit && app.state::<Sidecar>().kill_child().is_some() {
+            tracing::error!(
+                "Backend (pid {}) uses {} MB, over the {limit_mb} MB limit, restarting it",
+                stats.pid,
+                stats.memory_bytes / 1024 / 1024
+            );
+            warn(
+                app,
+                ResourceWarning::MemoryLimit {
+                    bytes: stats.memory_bytes,
+                    limit,
+                },
+            );
+            return;
+        }
+    }
+
+    let memory_threshold = settings.backend_memory_warn_mb * 1024 * 1024;
+    let high_memory = stats.memory_bytes > memory_threshold;
+    if high_memory && !alarms.memory {
+        tracing::warn!(
+            "Backend uses {} MB of memory",
+            stats.memory_bytes / 1024 / 1024
+        );
+        warn(
+            app,
+            ResourceWarning::Memory {
+                bytes: stats.memory_bytes,
+                threshold: memory_threshold,
+            },
+        );
+    }
+    alarms.memory = high_memory;
+
+    let cpu_threshold = settings.backend_cpu_warn_percent;
+    if stats.cpu_percent > cpu_threshold {
+        alarms.high_cpu_samples += 1;
+    } else {
+        alarms.high_cpu_samples = 0;
+    }
+    let high_cpu = alarms.high_cpu_samples >= CPU_WARN_SAMPLES;
+    if high_cpu && !alarms.cpu {
+        tracing::warn!("Backend has been using {:.0}% CPU", stats.cpu_percent);
+        warn(
+            app,
+            ResourceWarning::Cpu {
+                percent: stats.cpu_percent,
+                threshold: cpu_threshold,
+            },
+        );
+    }
+    alarms.cpu = high_cpu;
+}
+
+fn warn(app: &AppHandle, warning: ResourceWarning) {
+    let _ = app.emit("backend-resource-warning", warning);
+}
+
+/// The most recent sample, for `metrics`
+pub fn latest(app: &AppHandle) -> Option<BackendStats> {
+    app.try_state::<Resources>()?.0.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_backend_stats(resources: State<'_, Resources>) -> Option<BackendStats> {
+    resources.0.lock().unwrap().clone()
+}
@@ -0,0 +1,366 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
+
+use crate::main_window;
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+use crate::{data_dir, sidecar};
+
+// In the shared data folder: `version.json` and a copy of the install as it
+// was before the last update, under `install/`
+const PREVIOUS_DIR: &str = "previous-version";
+const INFO_FILE: &str = "version.json";
+const INSTALL_DIR: &str = "install";
+// Next to the install while swapping: the copy going in and what it replaces
+const STAGING_SUFFIX: &str = "rollback-new";
+const REPLACED_SUFFIX: &str = "rollback-old";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviousVersion {
+    version: String,
+    /// Seconds since the Unix epoch
+    saved_at: u64,
+}
+
+fn previous_dir(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::shared_data_dir(app).map(|dir| dir.join(PREVIOUS_DIR))
+}
+
+fn previous(app: &AppHandle) -> Option<PreviousVersion> {
+    let text = fs::read_to_string(previous_dir(app)?.join(INFO_FILE)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+/// Deletes what a rollback had to leave behind, e.g. the replaced executable
+/// on Windows, which can't go while it is running
+pub fn init(_app: &AppHandle) {
+    if tauri::is_dev() {
+        return;
+    }
+    if let Some(root) = platform::install_root() {
+        tauri::async_runtime::spawn_blocking(move || platform::clean_up(&root));
+    }
+}
+
+/// Copies the running install aside before an update replaces it, so
+/// `rollback_update` can bring it back. Keeps one version only.
+pub fn keep_current(app: &AppHandle) -> Result<(), String> {
+    if tauri::is_dev() {
+        return Ok(());
+    }
+    let root = platform::install_root().ok_or("This kind of install can't be rolled back")?;
+    let dir = previous_dir(app).ok_or("No data directory available")?;
+    let _ = fs::remove_dir_all(&dir);
+    let install = dir.join(INSTALL_DIR);
+    fs::create_dir_all(&install).map_err(|err| err.to_string())?;
+    let name = root.file_name().ok_or("The install has no name")?;
+    copy_tree(&root, &install.join(name)).map_err(|err| {
+        let _ = fs::remove_dir_all(&dir);
+        format!("Failed to copy {}: {err}", root.display())
+    })?;
+
+    // Written last, so a copy that didn't finish is never offered
+    let info = PreviousVersion {
+        version: app.package_info().version.to_string(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let text = serde_json::to_string_pretty(&info).map_err(|err| err.to_string())?;
+    fs::write(dir.join(INFO_FILE), text).map_err(|err| err.to_string())?;
+    tracing::info!("Kept version {} for rollback", info.version);
+    Ok(())
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        // Frameworks in a macOS bundle link their current version
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+    }
+    if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+/// The version `rollback_update` would go back to, if one was kept
+#[tauri::command]
+pub fn get_previous_version(app: AppHandle) -> Option<PreviousVersion> {
+    previous(&app)
+}
+
+/// Puts the version from before the last update back in place, backend
+/// included, and restarts into it. Updates are pinned to that version from
+/// then on, so the one rolled back from isn't offered again until the pin
+/// is cleared.
+#[tauri::command]
+pub async fn rollback_update(_timer: IpcTimer, app: AppHandle) -> Result<(), String> {
+    roll_back(app).await
+}
+
+async fn roll_back(app: AppHandle) -> Result<(), String> {
+    let info = previous(&app).ok_or("No previous version was kept")?;
+    let root = platform::install_root().ok_or("This kind of install can't be rolled back")?;
+    let name = root.file_name().ok_or("The install has no name")?;
+    let saved = previous_dir(&app)
+        .ok_or("No data directory available")?
+        .join(INSTALL_DIR)
+        .join(name);
+    if !saved.exists() {
+        return Err("The previous version is incomplete".to_string());
+    }
+    tracing::info!("Rolling back to version {}", info.version);
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle))
+        .await
+        .map_err(|err| err.to_string())?;
+    let swapped = tauri::async_runtime::spawn_blocking(move || platform::swap(&saved, &root))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|result| {
+            result.map_err(|err| format!("Failed to put the previous version in place: {err}"))
+        });
+    if let Err(err) = swapped {
+        // Leave the app usable on the current version
+        sidecar::start(&app);
+        return Err(err);
+    }
+
+    if let Some(dir) = previous_dir(&app) {
+        let _ = fs::remove_dir_all(dir);
+    }
+    let version = info.version.clone();
+    if let Err(err) = app
+        .state::<SettingsStore>()
+        .update(|settings| settings.pinned_version = Some(version))
+    {
+        tracing::warn!("Failed to pin version {}: {err}", info.version);
+    }
+    app.restart();
+}
+
+/// Asks before rolling back, for the Help menu. Says so if there is nothing
+/// to roll back to.
+pub fn confirm(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        main_window::focus(&app);
+        let Some(info) = previous(&app) else {
+            app.dialog()
+                .message(
+                    "No earlier version is kept on this computer. One is kept each time \
+                     Hamba installs an update.",
+                )
+                .title("Roll Back")
+                .kind(MessageDialogKind::Info)
+                .show(|_| {});
+            return;
+        };
+        let current = app.package_info().version.to_string();
+        let (sender, receiver) = oneshot::channel();
+        app.dialog()
+            .message(format!(
+                "Go back from Hamba {current} to {}? Hamba restarts and stays on {} until \
+                 the pinned version is cleared in settings. Data changed by the newer version \
+                 may not open in the older one.",
+                info.version, info.version
+            ))
+            .title("Roll Back")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Roll Back".to_string(),
+                "Cancel".to_string(),
+            ))
+            .show(move |confirmed| {
+                let _ = sender.send(confirmed);
+            });
+        if !receiver.await.unwrap_or(false) {
+            return;
+        }
+        if let Err(err) = roll_back(app.clone()).await {
+            tracing::warn!("Rollback failed: {err}");
+            app.dialog()
+                .message(err)
+                .title("Rollback failed")
+                .kind(MessageDialogKind::Error)
+                .show(|_| {});
+        }
+    });
+}
+
+// The updater replaces the bundle or the AppImage as a whole, and a running
+// one can be moved aside as a whole too
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::{copy_tree, suffixed, REPLACED_SUFFIX, STAGING_SUFFIX};
+
+    pub fn install_root() -> Option<PathBuf> {
+        if cfg!(target_os = "linux") {
+            return std::env::var_os("APPIMAGE").map(PathBuf::from);
+        }
+        let exe = std::env::current_exe().ok()?;
+        exe.ancestors()
+            .find(|dir| dir.extension().is_some_and(|ext| ext == "app"))
+            .map(Path::to_path_buf)
+    }
+
+    fn remove(path: &Path) -> io::Result<()> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+            Ok(_) => fs::remove_file(path),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn swap(saved: &Path, root: &Path) -> io::Result<()> {
+        let staging = suffixed(root, STAGING_SUFFIX);
+        let replaced = suffixed(root, REPLACED_SUFFIX);
+        remove(&staging)?;
+        remove(&replaced)?;
+        // Copied next to the install first, so the swap is two renames on
+        // the same volume
+        copy_tree(saved, &staging)?;
+        fs::rename(root, &replaced)?;
+        if let Err(err) = fs::rename(&staging, root) {
+            let _ = fs::rename(&replaced, root);
+            return Err(err);
+        }
+        let _ = remove(&replaced);
+        Ok(())
+    }
+
+    pub fn clean_up(root: &Path) {
+        let _ = remove(&suffixed(root, STAGING_SUFFIX));
+        let _ = remove(&suffixed(root, REPLACED_SUFFIX));
+    }
+}
+
+// The install folder holds the app and the backend. Neither can be replaced
+// while running, but both can be renamed, so each file is swapped in place.
+#[cfg(windows)]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::{suffixed, REPLACED_SUFFIX, STAGING_SUFFIX};
+
+    pub fn install_root() -> Option<PathBuf> {
+        std::env::current_exe()
+            .ok()?
+            .parent()
+            .map(Path::to_path_buf)
+    }
+
+    fn files(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                files(&path, found)?;
+            } else {
+                found.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn swap(saved: &Path, root: &Path) -> io::Result<()> {
+        let mut saved_files = Vec::new();
+        files(saved, &mut saved_files)?;
+        // Undone in reverse if a file can't be swapped
+        let mut swapped: Vec<(PathBuf, bool)> = Vec::new();
+        let result = saved_files
+            .iter()
+            .try_for_each(|file| swap_file(saved, root, file, &mut swapped));
+        if result.is_err() {
+            for (target, existed) in swapped.into_iter().rev() {
+                let _ = fs::remove_file(&target);
+                if existed {
+                    let _ = fs::rename(suffixed(&target, REPLACED_SUFFIX), &target);
+                }
+                let _ = fs::remove_file(suffixed(&target, STAGING_SUFFIX));
+            }
+        }
+        result
+    }
+
+    fn swap_file(
+        saved: &Path,
+        root: &Path,
+        file: &Path,
+        swapped: &mut Vec<(PathBuf, bool)>,
+    ) -> io::Result<()> {
+        let relative = file.strip_prefix(saved).map_err(io::Error::other)?;
+        let target = root.join(relative);
+        let staging = suffixed(&target, STAGING_SUFFIX);
+        let replaced = suffixed(&target, REPLACED_SUFFIX);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(file, &staging)?;
+        let _ = fs::remove_file(&replaced);
+        let existed = target.exists();
+        if existed {
+            fs::rename(&target, &replaced)?;
+        }
+        swapped.push((target.clone(), existed));
+        fs::rename(&staging, &target)
+    }
+
+    pub fn clean_up(root: &Path) {
+        let mut found = Vec::new();
+        if files(root, &mut found).is_err() {
+            return;
+        }
+        for file in found {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if name.ends_with(REPLACED_SUFFIX) || name.ends_with(STAGING_SUFFIX) {
+                let _ = fs::remove_file(&file);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    pub fn install_root() -> Option<PathBuf> {
+        None
+    }
+
+    pub fn swap(_saved: &Path, _root: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn clean_up(_root: &Path) {}
+}
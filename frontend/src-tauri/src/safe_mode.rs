@@ -0,0 +1,75 @@
+use tauri::{AppHandle, Manager};
+
+use crate::cli::Cli;
+
+/// Launched with `--safe-mode` or with Shift held down, to get past a bad
+/// configuration: settings are defaults that aren't saved, and autostart,
+/// custom backend variables, helper sidecars and scheduled jobs are all
+/// skipped. Quitting and launching normally undoes it.
+pub struct SafeMode(bool);
+
+/// Runs before settings are loaded, as they depend on it
+pub fn init(app: &AppHandle) {
+    let active = app.state::<Cli>().safe_mode || platform::shift_held();
+    if active {
+        tracing::warn!("Starting in safe mode");
+    }
+    app.manage(SafeMode(active));
+}
+
+pub fn is_active(app: &AppHandle) -> bool {
+    app.try_state::<SafeMode>()
+        .is_some_and(|safe_mode| safe_mode.0)
+}
+
+/// For commands that would change something on disk the defaults in safe
+/// mode don't account for
+pub fn refuse(app: &AppHandle) -> Result<(), String> {
+    if is_active(app) {
+        return Err("Not available in safe mode".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use gtk::gdk;
+
+    pub fn shift_held() -> bool {
+        gdk::Display::default()
+            .and_then(|display| gdk::Keymap::for_display(&display))
+            .is_some_and(|keymap| {
+                keymap.modifier_state() & gdk::ModifierType::SHIFT_MASK.bits() != 0
+            })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_SHIFT};
+
+    pub fn shift_held() -> bool {
+        // The high bit is set while the key is down
+        unsafe { GetAsyncKeyState(VK_SHIFT as i32) as u16 & 0x8000 != 0 }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::{class, msg_send};
+
+    // NSEventModifierFlagShift
+    const SHIFT: usize = 1 << 17;
+
+    pub fn shift_held() -> bool {
+        let flags: usize = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+        flags & SHIFT != 0
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    pub fn shift_held() -> bool {
+        false
+    }
+}
@@ -0,0 +1,322 @@
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::process::Command;
+use tauri_plugin_shell::ShellExt;
+
+use crate::data_dir;
+use crate::settings::SettingsStore;
+
+/// First argument that makes this executable act as the sidecar launcher:
+/// `hamba --sandbox-launch <binary> [args...]` restricts itself and then
+/// execs the binary, which keeps the pid so supervision works unchanged.
+const LAUNCH_ARG: &str = "--sandbox-launch";
+/// Folders the sidecar may write to, in the OS path list format. Enforced by
+/// the macOS sandbox profile and checked by the backend for paths it is sent.
+const ALLOWED_DIRS_ENV: &str = "HAMBA_ALLOWED_DIRS";
+
+/// Runs the launcher and never returns if this process was started as one.
+/// Must be the first thing `main` does.
+pub fn launch_if_requested() {
+    let mut args = env::args_os().skip(1);
+    if args.next().as_deref() != Some(OsStr::new(LAUNCH_ARG)) {
+        return;
+    }
+    let Some(binary) = args.next().map(PathBuf::from) else {
+        eprintln!("{LAUNCH_ARG} needs the binary to run");
+        process::exit(127);
+    };
+    let err = platform::launch(&binary, args.collect());
+    eprintln!("Failed to launch {}: {err}", binary.display());
+    process::exit(127);
+}
+
+/// The command that starts `binary` with reduced privileges: through the
+/// launcher on Linux and macOS, directly on Windows where the process is
+/// confined after it starts (see `confine`). Sandboxing can be turned off in
+/// settings for troubleshooting.
+pub fn command(app: &AppHandle, binary: &Path) -> Command {
+    let allowed = allowed_dirs(app);
+    let allowed = env::join_paths(&allowed).unwrap_or_default();
+    let shell = app.shell();
+    if !app.state::<SettingsStore>().get().sandbox_sidecars {
+        return shell.command(binary).env(ALLOWED_DIRS_ENV, allowed);
+    }
+    let command = match env::current_exe() {
+        Ok(exe) if platform::USES_LAUNCHER => shell.command(exe).arg(LAUNCH_ARG).arg(binary),
+        Ok(_) => shell.command(binary),
+        Err(err) => {
+            tracing::warn!("Starting {} unsandboxed: {err}", binary.display());
+            shell.command(binary)
+        }
+    };
+    command.env(ALLOWED_DIRS_ENV, allowed)
+}
+
+// The data folder (database, imports, certificates) and the temp folder.
+// Canonical, since the macOS sandbox matches on resolved paths.
+fn allowed_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    data_dir::data_dir(app)
+        .into_iter()
+        .chain([env::temp_dir()])
+        .map(|dir| dir.canonicalize().unwrap_or(dir))
+        .collect()
+}
+
+/// Keeps a running process confined; dropping it once the process has
+/// exited releases what confining it took
+pub struct Confinement {
+    _job: Option<platform::Job>,
+}
+
+/// Restricts an already running process where that can only happen after it
+/// started (job objects on Windows). A no-op elsewhere.
+pub fn confine(app: &AppHandle, pid: u32) -> Confinement {
+    if !app.state::<SettingsStore>().get().sandbox_sidecars {
+        return Confinement { _job: None };
+    }
+    match platform::confine(pid) {
+        Ok(job) => Confinement { _job: job },
+        Err(err) => {
+            tracing::warn!("Failed to confine process {pid}: {err}");
+            Confinement { _job: None }
+        }
+    }
+}
+
+// Lowers resource limits that only matter to a compromised process: no
+// core dumps (they would hold the database key and auth token) and a cap on
+// open files. Hard limits are lowered too so they can't be raised again.
+#[cfg(unix)]
+fn restrict_limits() -> std::io::Result<()> {
+    const MAX_OPEN_FILES: libc::rlim_t = 4096;
+    for (resource, max) in [
+        (libc::RLIMIT_CORE, 0),
+        (libc::RLIMIT_NOFILE, MAX_OPEN_FILES),
+    ] {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &mut limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let max = max.min(limit.rlim_max);
+        let limit = libc::rlimit {
+            rlim_cur: limit.rlim_cur.min(max),
+            rlim_max: max,
+        };
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::OsString;
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    pub const USES_LAUNCHER: bool = true;
+
+    pub enum Job {}
+
+    // No-new-privs survives exec, so neither the backend nor anything it
+    // could start gains privileges through setuid binaries or file caps
+    pub fn launch(binary: &Path, args: Vec<OsString>) -> io::Error {
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return io::Error::last_os_error();
+        }
+        if let Err(err) = super::restrict_limits() {
+            return err;
+        }
+        Command::new(binary).args(args).exec()
+    }
+
+    pub fn confine(_pid: u32) -> Result<Option<Job>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::env;
+    use std::ffi::OsString;
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use super::ALLOWED_DIRS_ENV;
+
+    pub const USES_LAUNCHER: bool = true;
+    const SANDBOX_EXEC: &str = "/usr/bin/sandbox-exec";
+
+    pub enum Job {}
+
+    pub fn launch(binary: &Path, args: Vec<OsString>) -> io::Error {
+        if let Err(err) = super::restrict_limits() {
+            return err;
+        }
+        let binary = binary
+            .canonicalize()
+            .unwrap_or_else(|_| binary.to_path_buf());
+        let allowed: Vec<PathBuf> = env::var_os(ALLOWED_DIRS_ENV)
+            .map(|dirs| env::split_paths(&dirs).collect())
+            .unwrap_or_default();
+        Command::new(SANDBOX_EXEC)
+            .arg("-p")
+            .arg(profile(&binary, &allowed))
+            .arg(&binary)
+            .args(args)
+            .exec()
+    }
+
+    // Everything is allowed except writing outside the allowed folders and
+    // running any program other than the sidecar itself
+    fn profile(binary: &Path, allowed: &[PathBuf]) -> String {
+        let mut writable = String::new();
+        for dir in allowed {
+            writable.push_str(&format!(" (subpath \"{}\")", escape(dir)));
+        }
+        format!(
+            "(version 1)\n\
+             (allow default)\n\
+             (deny file-write*)\n\
+             (allow file-write*{writable} (literal \"/dev/null\") (literal \"/dev/tty\"))\n\
+             (deny process-exec)\n\
+             (allow process-exec (literal \"{}\"))\n",
+            escape(binary)
+        )
+    }
+
+    fn escape(path: &Path) -> String {
+        path.to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    }
+
+    pub fn confine(_pid: u32) -> Result<Option<Job>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::OsString;
+    use std::io;
+    use std::path::Path;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicUIRestrictions,
+        JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_BASIC_UI_RESTRICTIONS, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_ACTIVE_PROCESS, JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOB_OBJECT_UILIMIT_DESKTOP,
+        JOB_OBJECT_UILIMIT_DISPLAYSETTINGS, JOB_OBJECT_UILIMIT_EXITWINDOWS,
+        JOB_OBJECT_UILIMIT_GLOBALATOMS, JOB_OBJECT_UILIMIT_HANDLES,
+        JOB_OBJECT_UILIMIT_READCLIPBOARD, JOB_OBJECT_UILIMIT_SYSTEMPARAMETERS,
+        JOB_OBJECT_UILIMIT_WRITECLIPBOARD,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+    };
+
+    pub const USES_LAUNCHER: bool = false;
+
+    /// A job object holding one sidecar. Closing it kills anything still in
+    /// it, so the sidecar can't outlive the app even if the app crashes.
+    pub struct Job(HANDLE);
+
+    // The handle is only closed, from whichever thread drops it
+    unsafe impl Send for Job {}
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub fn launch(_binary: &Path, _args: Vec<OsString>) -> io::Error {
+        io::Error::other("The launcher is not used on Windows")
+    }
+
+    // No child processes, no crash dialogs and no access to other
+    // processes' windows, the clipboard or system settings
+    pub fn confine(pid: u32) -> Result<Option<Job>, String> {
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            let job = Job(job);
+
+            let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_ACTIVE_PROCESS
+                | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION
+                | JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            limits.BasicLimitInformation.ActiveProcessLimit = 1;
+            set(&job, JobObjectExtendedLimitInformation, &limits)?;
+
+            let ui = JOBOBJECT_BASIC_UI_RESTRICTIONS {
+                UIRestrictionsClass: JOB_OBJECT_UILIMIT_DESKTOP
+                    | JOB_OBJECT_UILIMIT_DISPLAYSETTINGS
+                    | JOB_OBJECT_UILIMIT_EXITWINDOWS
+                    | JOB_OBJECT_UILIMIT_GLOBALATOMS
+                    | JOB_OBJECT_UILIMIT_HANDLES
+                    | JOB_OBJECT_UILIMIT_READCLIPBOARD
+                    | JOB_OBJECT_UILIMIT_SYSTEMPARAMETERS
+                    | JOB_OBJECT_UILIMIT_WRITECLIPBOARD,
+            };
+            set(&job, JobObjectBasicUIRestrictions, &ui)?;
+
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+            if process.is_null() {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            let assigned = AssignProcessToJobObject(job.0, process);
+            CloseHandle(process);
+            if assigned == 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            Ok(Some(job))
+        }
+    }
+
+    fn set<T>(job: &Job, class: i32, info: &T) -> Result<(), String> {
+        let info: *const T = info;
+        let set =
+            unsafe { SetInformationJobObject(job.0, class, info.cast(), size_of::<T>() as u32) };
+        if set == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::ffi::OsString;
+    use std::io;
+    use std::path::Path;
+
+    pub const USES_LAUNCHER: bool = false;
+
+    pub enum Job {}
+
+    pub fn launch(_binary: &Path, _args: Vec<OsString>) -> io::Error {
+        io::Error::other("Sandboxing is not supported on this platform")
+    }
+
+    pub fn confine(_pid: u32) -> Result<Option<Job>, String> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeDelta, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::data_dir;
+use crate::safe_mode;
+use crate::sidecar::Sidecar;
+
+const JOBS_FILE: &str = "jobs.json";
+// Jobs fire at whole minutes, so checking more often than this gains nothing
+const TICK: Duration = Duration::from_secs(20);
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+// How far ahead `Cron::next_after` looks before deciding an expression never
+// matches, e.g. `0 0 31 2 *`
+const SEARCH_YEARS: i32 = 5;
+
+/// A backend endpoint to call on a schedule, as the frontend sends it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    /// Cron expression in local time: `minute hour day month weekday`, or
+    /// one of `@hourly`, `@daily`, `@weekly`, `@monthly`. With both day and
+    /// weekday restricted either one matching is enough; a field starting
+    /// with `*`, `*/2` too, counts as unrestricted, as in Vixie cron.
+    pub schedule: String,
+    #[serde(default = "post")]
+    pub method: String,
+    /// Backend path, e.g. `/emails/sync/<accountId>`
+    pub path: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+fn post() -> String {
+    "POST".to_string()
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    #[serde(flatten)]
+    job: Job,
+    /// Seconds since the Unix epoch
+    next_run: Option<i64>,
+    last_run: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobRan {
+    id: String,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Saved {
+    #[serde(flatten)]
+    job: Job,
+    last_run: Option<i64>,
+}
+
+struct Scheduled {
+    job: Job,
+    cron: Cron,
+    next: Option<DateTime<Local>>,
+    last_run: Option<i64>,
+}
+
+/// Jobs run by the shell rather than the backend, so they keep firing while
+/// the window is hidden to the tray. Runs missed while the app was closed
+/// are skipped, not caught up on.
+pub struct Scheduler {
+    path: Option<PathBuf>,
+    jobs: Mutex<HashMap<String, Scheduled>>,
+}
+
+impl Scheduler {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let saved: Vec<Saved> = {
+            let jobs = self.jobs.lock().unwrap();
+            let mut saved: Vec<_> = jobs
+                .values()
+                .map(|scheduled| Saved {
+                    job: scheduled.job.clone(),
+                    last_run: scheduled.last_run,
+                })
+                .collect();
+            saved.sort_by(|a, b| a.job.id.cmp(&b.job.id));
+            saved
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&saved)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(JOBS_FILE));
+    let saved: Vec<Saved> = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid {JOBS_FILE}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    let now = Local::now();
+    let mut jobs = HashMap::new();
+    for saved in saved {
+        match Cron::parse(&saved.job.schedule) {
+            Ok(cron) => {
+                let scheduled = Scheduled {
+                    next: cron.next_after(now),
+                    job: saved.job,
+                    cron,
+                    last_run: saved.last_run,
+                };
+                jobs.insert(scheduled.job.id.clone(), scheduled);
+            }
+            Err(err) => tracing::warn!("Dropping job {}: {err}", saved.job.id),
+        }
+    }
+    app.manage(Scheduler {
+        path,
+        jobs: Mutex::new(jobs),
+    });
+
+    // Jobs can still be looked at and changed in safe mode, they just don't run
+    if safe_mode::is_active(app) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK).await;
+            for job in due(&app) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { run(&app, job).await });
+            }
+        }
+    });
+}
+
+// Jobs whose time has come, already moved on to their next run
+fn due(app: &AppHandle) -> Vec<Job> {
+    let scheduler = app.state::<Scheduler>();
+    let now = Local::now();
+    let due: Vec<Job> = {
+        let mut jobs = scheduler.jobs.lock().unwrap();
+        jobs.values_mut()
+            .filter(|scheduled| scheduled.next.is_some_and(|next| next <= now))
+            .map(|scheduled| {
+                scheduled.next = scheduled.cron.next_after(now);
+                scheduled.last_run = Some(now.timestamp());
+                scheduled.job.clone()
+            })
+            .collect()
+    };
+    if !due.is_empty() {
+        if let Err(err) = scheduler.save() {
+            tracing::warn!("Failed to save {JOBS_FILE}: {err}");
+        }
+    }
+    due
+}
+
+async fn run(app: &AppHandle, job: Job) {
+    tracing::info!(
+        "Running scheduled job {} ({} {})",
+        job.id,
+        job.method,
+        job.path
+    );
+    let method =
+        reqwest::Method::from_bytes(job.method.as_bytes()).unwrap_or(reqwest::Method::POST);
+    let mut request = app.state::<Sidecar>().request(method, &job.path);
+    if let Some(body) = &job.body {
+        request = request.json(body);
+    }
+    let error = match request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(_) => None,
+        Err(err) => {
+            tracing::warn!("Scheduled job {} failed: {err}", job.id);
+            Some(err.to_string())
+        }
+    };
+    let _ = app.emit("scheduled-job", JobRan { id: job.id, error });
+}
+
+/// Adds a job, or replaces the one with the same id, and resolves with when
+/// it runs next
+#[tauri::command]
+pub fn schedule_job(scheduler: State<'_, Scheduler>, job: Job) -> Result<JobInfo, String> {
+    if job.id.trim().is_empty() {
+        return Err("The job needs an id".to_string());
+    }
+    if !job.path.starts_with('/') {
+        return Err("The job path must start with /".to_string());
+    }
+    if !METHODS.contains(&job.method.as_str()) {
+        return Err(format!("Unsupported method {:?}", job.method));
+    }
+    let cron = Cron::parse(&job.schedule)?;
+    let next = cron.next_after(Local::now());
+    if next.is_none() {
+        return Err(format!("{:?} never runs", job.schedule));
+    }
+
+    let info = {
+        let mut jobs = scheduler.jobs.lock().unwrap();
+        let last_run = jobs.get(&job.id).and_then(|scheduled| scheduled.last_run);
+        let info = JobInfo {
+            job: job.clone(),
+            next_run: next.map(|next| next.timestamp()),
+            last_run,
+        };
+        jobs.insert(
+            job.id.clone(),
+            Scheduled {
+                job,
+                cron,
+                next,
+                last_run,
+            },
+        );
+        info
+    };
+    scheduler.save().map_err(|err| err.to_string())?;
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn cancel_job(scheduler: State<'_, Scheduler>, id: String) -> Result<(), String> {
+    if scheduler.jobs.lock().unwrap().remove(&id).is_none() {
+        return Err(format!("No job {id:?}"));
+    }
+    scheduler.save().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn list_jobs(scheduler: State<'_, Scheduler>) -> Vec<JobInfo> {
+    let jobs = scheduler.jobs.lock().unwrap();
+    let mut infos: Vec<_> = jobs
+        .values()
+        .map(|scheduled| JobInfo {
+            job: scheduled.job.clone(),
+            next_run: scheduled.next.map(|next| next.timestamp()),
+            last_run: scheduled.last_run,
+        })
+        .collect();
+    infos.sort_by(|a, b| a.job.id.cmp(&b.job.id));
+    infos
+}
+
+/// A parsed cron expression, each field a bit set of the values it allows
+struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    // Restricting both day and weekday matches either, as in cron. `*/N`
+    // counts as unrestricted, so `0 0 */2 * 1` is odd days that are Mondays.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "{expression:?} is not a cron expression: expected 5 fields"
+            ));
+        };
+        // Sunday is both 0 and 7
+        let weekdays = field(weekday, 0, 7, "weekday")?;
+        Ok(Self {
+            minutes: field(minute, 0, 59, "minute")?,
+            hours: field(hour, 0, 23, "hour")? as u32,
+            days: field(day, 1, 31, "day")? as u32,
+            months: field(month, 1, 12, "month")? as u16,
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & 1 << time.day() != 0;
+        let weekday = self.weekdays & 1 << time.weekday().num_days_from_sunday() != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first matching minute after `after`. Local times skipped by a
+    /// daylight saving change don't match; repeated ones match once.
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let limit = start.year() + SEARCH_YEARS;
+        let mut time = start;
+        while time.year() <= limit {
+            let midnight = time.date().and_hms_opt(0, 0, 0)?;
+            if self.months & 1 << time.month() == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(&time) {
+                time = midnight + TimeDelta::days(1);
+            } else if self.hours & 1 << time.hour() == 0 {
+                time = time.with_minute(0)? + TimeDelta::hours(1);
+            } else if self.minutes & 1 << time.minute() == 0 {
+                time += TimeDelta::minutes(1);
+            } else if let Some(local) = time.and_local_timezone(Local).earliest() {
+                return Some(local);
+            } else {
+                time += TimeDelta::minutes(1);
+            }
+        }
+        None
+    }
+}
+
+// One field of a cron expression: `*`, `5`, `1-5`, `*/15`, `0-30/10` or a
+// comma separated list of those
+fn field(text: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid {name} {text:?} in the schedule");
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    // `5/10` means from 5 to the end in steps of 10
+                    (value, if part.contains('/') { max } else { value })
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Mid-month dates, away from daylight saving changes in any timezone
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn next(expression: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        Cron::parse(expression).unwrap().next_after(after)
+    }
+
+    #[test]
+    fn field_parses_each_form() {
+        assert_eq!(field("*", 0, 3, "minute"), Ok(0b1111));
+        assert_eq!(field("5", 0, 59, "minute"), Ok(1 << 5));
+        assert_eq!(field("1-3", 0, 59, "minute"), Ok(0b1110));
+        assert_eq!(
+            field("*/15", 0, 59, "minute"),
+            Ok(1 | 1 << 15 | 1 << 30 | 1 << 45)
+        );
+        assert_eq!(
+            field("0-30/10", 0, 59, "minute"),
+            Ok(1 | 1 << 10 | 1 << 20 | 1 << 30)
+        );
+        assert_eq!(field("50/5", 0, 59, "minute"), Ok(1 << 50 | 1 << 55));
+        assert_eq!(field("1,3,5", 0, 59, "minute"), Ok(0b101010));
+    }
+
+    #[test]
+    fn field_rejects_bad_values() {
+        for text in ["", "60", "5-1", "*/0", "a", "1-", "-1", "1/x", "1,,2"] {
+            assert!(field(text, 0, 59, "minute").is_err(), "{text:?}");
+        }
+        assert!(field("0", 1, 31, "day").is_err());
+    }
+
+    #[test]
+    fn parse_expands_shorthands() {
+        let hourly = Cron::parse("@hourly").unwrap();
+        assert_eq!(hourly.minutes, 1);
+        assert_eq!(hourly.hours, (1 << 24) - 1);
+        let weekly = Cron::parse(" @weekly ").unwrap();
+        assert_eq!(weekly.weekdays, 1);
+    }
+
+    #[test]
+    fn parse_treats_seven_as_sunday() {
+        assert_eq!(Cron::parse("0 0 * * 7").unwrap().weekdays, 1);
+        assert_eq!(Cron::parse("0 0 * * 5-7").unwrap().weekdays, 0b1100001);
+    }
+
+    #[test]
+    fn parse_needs_five_fields() {
+        assert!(Cron::parse("0 0 * *").is_err());
+        assert!(Cron::parse("0 0 * * * *").is_err());
+        assert!(Cron::parse("@yearly").is_err());
+    }
+
+    #[test]
+    fn starred_steps_count_as_unrestricted() {
+        let cron = Cron::parse("0 0 */2 * 1").unwrap();
+        assert!(cron.any_day);
+        assert!(!cron.any_weekday);
+        assert!(!Cron::parse("0 0 1-31/2 * 1").unwrap().any_day);
+    }
+
+    #[test]
+    fn next_after_skips_to_the_next_minute() {
+        let after = local(2026, 3, 10, 12, 30);
+        assert_eq!(next("* * * * *", after), Some(local(2026, 3, 10, 12, 31)));
+        assert_eq!(
+            next("*/15 * * * *", after),
+            Some(local(2026, 3, 10, 12, 45))
+        );
+        assert_eq!(next("0 9 * * *", after), Some(local(2026, 3, 11, 9, 0)));
+        assert_eq!(next("0 0 1 * *", after), Some(local(2026, 4, 1, 0, 0)));
+    }
+
+    #[test]
+    fn next_after_matches_day_or_weekday() {
+        // 2026-03-10 is a Tuesday
+        let after = local(2026, 3, 10, 12, 0);
+        // The 15th, or any Friday
+        assert_eq!(next("0 0 15 * 5", after), Some(local(2026, 3, 13, 0, 0)));
+        // Odd days that are also Mondays; the 16th is even, the 23rd isn't
+        assert_eq!(next("0 0 */2 * 1", after), Some(local(2026, 3, 23, 0, 0)));
+        // Odd days, or any Monday
+        assert_eq!(
+            next("0 0 1-31/2 * 1", after),
+            Some(local(2026, 3, 11, 0, 0))
+        );
+    }
+
+    #[test]
+    fn next_after_gives_up_on_impossible_dates() {
+        assert_eq!(next("0 0 31 2 *", local(2026, 3, 10, 12, 0)), None);
+        assert_eq!(
+            next("0 0 29 2 *", local(2026, 3, 10, 12, 0)),
+            Some(local(2028, 2, 29, 0, 0))
+        );
+    }
+}
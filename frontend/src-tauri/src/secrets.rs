@@ -0,0 +1,115 @@
+use keyring::{Entry, Error};
+
+use crate::backend_port;
+use crate::encryption;
+use crate::kiosk;
+use crate::lock;
+use crate::metrics;
+use crate::webhooks;
+
+// Every secret is stored under this service name in the OS keychain
+// (Keychain on macOS, Credential Manager on Windows, Secret Service on Linux)
+const SERVICE: &str = "com.hamba.app";
+// Secrets the frontend sets are kept under this prefix, apart from the
+// shell's own
+const FRONTEND_PREFIX: &str = "frontend:";
+
+fn entry(key: &str) -> Result<Entry, String> {
+    if key.is_empty() {
+        return Err("Secret key must not be empty".to_string());
+    }
+    Entry::new(SERVICE, key).map_err(|err| err.to_string())
+}
+
+pub fn get(key: &str) -> Result<Option<String>, String> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    entry(key)?
+        .set_password(value)
+        .map_err(|err| err.to_string())
+}
+
+pub fn delete(key: &str) -> Result<(), String> {
+    match entry(key)?.delete_credential() {
+        Ok(()) | Err(Error::NoEntry) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// Names the shell keeps its own secrets under, which the frontend must never
+// read or overwrite even if the prefix were dropped
+fn is_shell_owned(key: &str) -> bool {
+    [
+        encryption::KEY_SECRET,
+        kiosk::PIN_SECRET,
+        lock::LOCK_SECRET,
+        metrics::TOKEN_SECRET,
+    ]
+    .contains(&key)
+        || [backend_port::TOKEN_SECRET_PREFIX, webhooks::SECRET_PREFIX]
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+/// Where the frontend's `key` is stored
+fn frontend_key(key: &str) -> Result<String, String> {
+    if key.is_empty() {
+        return Err("Secret key must not be empty".to_string());
+    }
+    if is_shell_owned(key) || key.starts_with(FRONTEND_PREFIX) {
+        return Err(format!("{key:?} is reserved for the app"));
+    }
+    Ok(format!("{FRONTEND_PREFIX}{key}"))
+}
+
+/// A secret the frontend set with `secret_set`
+pub fn get_frontend(key: &str) -> Result<Option<String>, String> {
+    get(&frontend_key(key)?)
+}
+
+#[tauri::command]
+pub fn secret_get(key: String) -> Result<Option<String>, String> {
+    get_frontend(&key)
+}
+
+#[tauri::command]
+pub fn secret_set(key: String, value: String) -> Result<(), String> {
+    set(&frontend_key(&key)?, &value)
+}
+
+#[tauri::command]
+pub fn secret_delete(key: String) -> Result<(), String> {
+    delete(&frontend_key(&key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontend_keys_are_prefixed() {
+        assert_eq!(frontend_key("api-key").unwrap(), "frontend:api-key");
+    }
+
+    #[test]
+    fn shell_secrets_are_reserved() {
+        for key in [
+            "database-key",
+            "kiosk-pin",
+            "app-lock",
+            "metrics-token",
+            "webhook-secret-1",
+            "backend-token-canary",
+            "frontend:api-key",
+            "",
+        ] {
+            assert!(frontend_key(key).is_err(), "{key:?} was allowed");
+        }
+    }
+}
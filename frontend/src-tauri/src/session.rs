@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow, Window, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tokio::sync::oneshot;
+
+use crate::command::{CommandError, CommandResult};
+use crate::data_dir;
+use crate::main_window;
+use crate::safe_mode;
+use crate::tabs;
+use crate::windows;
+
+const SESSION_FILE: &str = "session.json";
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+// Scroll positions kept per window, e.g. the page and a list or two
+const MAX_SCROLL_KEYS: usize = 32;
+
+/// A window as its frontend last reported it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowSnapshot {
+    label: String,
+    /// e.g. `/email/123?folder=inbox`
+    route: String,
+    title: Option<String>,
+    /// Scroll offsets by key, `window` for the page itself
+    #[serde(default)]
+    scroll: BTreeMap<String, f64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Snapshot {
+    /// Only set as the app quits, so a snapshot without it was left by a
+    /// crash or a forced shutdown
+    clean: bool,
+    /// Seconds since the Unix epoch
+    saved_at: u64,
+    windows: Vec<WindowSnapshot>,
+}
+
+/// The open windows with their routes and scroll positions, written to
+/// `session.json` in the config directory every `SNAPSHOT_INTERVAL`. When
+/// the last run didn't quit cleanly the next one offers to put it all back.
+pub struct Session {
+    path: Option<PathBuf>,
+    windows: Mutex<BTreeMap<String, WindowSnapshot>>,
+    dirty: AtomicBool,
+    // Off while the user is asked about the last session, which is kept on
+    // disk until then in case this run goes down too
+    recording: AtomicBool,
+    // What windows being restored get once their frontend asks
+    pending: Mutex<HashMap<String, WindowSnapshot>>,
+}
+
+impl Session {
+    fn save(&self, clean: bool) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let snapshot = Snapshot {
+            clean,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            windows: self.windows.lock().unwrap().values().cloned().collect(),
+        };
+        self.dirty.store(false, Ordering::Relaxed);
+        fs::write(path, serde_json::to_string_pretty(&snapshot)?)
+    }
+}
+
+/// Runs once the main window and the tabs are up, so a restore can reach
+/// them
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(SESSION_FILE));
+    let last: Option<Snapshot> = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(snapshot) => Some(snapshot),
+            Err(err) => {
+                tracing::warn!("Ignoring an invalid {SESSION_FILE}: {err}");
+                None
+            }
+        });
+    app.manage(Session {
+        path,
+        windows: Mutex::default(),
+        dirty: AtomicBool::new(false),
+        recording: AtomicBool::new(false),
+        pending: Mutex::default(),
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match last {
+            Some(last)
+                if !last.clean && !last.windows.is_empty() && !safe_mode::is_active(&app) =>
+            {
+                tracing::info!("The last session didn't end cleanly");
+                offer(&app, last.windows).await;
+            }
+            _ => {}
+        }
+        let session = app.state::<Session>();
+        session.recording.store(true, Ordering::Relaxed);
+        loop {
+            if let Err(err) = session.save(false) {
+                tracing::warn!("Failed to save the session: {err}");
+            }
+            tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+            while !session.dirty.load(Ordering::Relaxed) {
+                tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+            }
+        }
+    });
+}
+
+async fn offer(app: &AppHandle, windows: Vec<WindowSnapshot>) {
+    main_window::focus(app);
+    let (sender, receiver) = oneshot::channel();
+    app.dialog()
+        .message(format!(
+            "Hamba didn't quit properly last time. Open the {} it had open again, where \
+             they were?",
+            if windows.len() == 1 {
+                "window".to_string()
+            } else {
+                format!("{} windows", windows.len())
+            }
+        ))
+        .title("Restore Previous Session?")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Restore".to_string(),
+            "Not Now".to_string(),
+        ))
+        .show(move |restore| {
+            let _ = sender.send(restore);
+        });
+    if receiver.await.unwrap_or(false) {
+        restore(app, windows);
+    }
+}
+
+fn restore(app: &AppHandle, windows: Vec<WindowSnapshot>) {
+    let session = app.state::<Session>();
+    for snapshot in windows {
+        let label = snapshot.label.clone();
+        let managed = label == main_window::LABEL || label.starts_with(tabs::LABEL_PREFIX);
+        // The main window and the tabs are open already; other app windows
+        // like quick capture aren't brought back
+        if !managed && windows::validate_label(&label).is_err() {
+            continue;
+        }
+        if !managed && app.get_webview_window(&label).is_none() {
+            if let Err(err) = windows::create(
+                app,
+                &label,
+                &snapshot.route,
+                snapshot.title.clone(),
+                None,
+                None,
+            ) {
+                tracing::warn!("Failed to restore window {label}: {err}");
+                continue;
+            }
+        }
+        session
+            .pending
+            .lock()
+            .unwrap()
+            .insert(label.clone(), snapshot);
+        // Windows already loaded come and get it; new ones ask on start
+        let _ = app.emit_to(label.as_str(), "session-restore", ());
+    }
+    tracing::info!("Restored the previous session");
+}
+
+/// Marks the session as ended cleanly, as the app quits
+pub fn on_exit(app: &AppHandle) {
+    let Some(session) = app.try_state::<Session>() else {
+        return;
+    };
+    if !session.recording.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Err(err) = session.save(true) {
+        tracing::warn!("Failed to save the session: {err}");
+    }
+}
+
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Destroyed) {
+        return;
+    }
+    let Some(session) = window.app_handle().try_state::<Session>() else {
+        return;
+    };
+    if session
+        .windows
+        .lock()
+        .unwrap()
+        .remove(window.label())
+        .is_some()
+    {
+        session.dirty.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Where the calling window is, for the next snapshot. The frontend reports
+/// this on navigation and every so often while scrolled.
+#[tauri::command]
+pub fn report_session_state(
+    window: WebviewWindow,
+    route: String,
+    scroll: BTreeMap<String, f64>,
+) -> CommandResult<()> {
+    windows::validate_route(&route).map_err(|err| CommandError::invalid("route", err))?;
+    if scroll.len() > MAX_SCROLL_KEYS {
+        return Err(CommandError::invalid(
+            "scroll",
+            format!("At most {MAX_SCROLL_KEYS} scroll positions are kept per window"),
+        ));
+    }
+    let app = window.app_handle();
+    let session = app.state::<Session>();
+    let snapshot = WindowSnapshot {
+        label: window.label().to_string(),
+        route,
+        title: window.title().ok(),
+        scroll: scroll
+            .into_iter()
+            .filter(|(_, offset)| offset.is_finite())
+            .collect(),
+    };
+    session
+        .windows
+        .lock()
+        .unwrap()
+        .insert(snapshot.label.clone(), snapshot);
+    session.dirty.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// What the calling window should go back to, once, when the user chose to
+/// restore the last session
+#[tauri::command]
+pub fn take_session_restore(window: WebviewWindow) -> Option<WindowSnapshot> {
+    window
+        .app_handle()
+        .state::<Session>()
+        .pending
+        .lock()
+        .unwrap()
+        .remove(window.label())
+}
@@ -0,0 +1,555 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, State, Theme, WebviewWindow};
+
+use crate::battery_saver::{self, BatterySaverMode};
+use crate::command::{ensure, CommandError, CommandResult};
+use crate::proxy::{self, ProxyMode};
+use crate::updater::{self, UpdateChannel};
+use crate::{
+    accessibility, auth, autostart, data_dir, http_fetch, ipc, keybindings, locale, logging,
+    metrics, quick_capture, quick_open, safe_mode, spellcheck, theme, trash,
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+// Bump alongside a new arm in `migrate` whenever a field is renamed or its
+// meaning changes
+const CURRENT_VERSION: u32 = 1;
+// Settings that can't be patched directly; they change through their own
+// commands
+const READ_ONLY: &[&str] = &[
+    "version",
+    "encryptData",
+    "telemetry",
+    "lockWhenIdle",
+    "appLockPin",
+    "lanAccess",
+    "backendPort",
+    "pinBackendPort",
+    "instanceId",
+    "kiosk",
+    "osCalendar",
+    "backendLogLevel",
+    "backendFlags",
+    "keybindings",
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Format version of settings.json, see `migrate`
+    pub version: u32,
+    /// How long to wait for the backend to exit on its own before killing it
+    pub shutdown_timeout_ms: u64,
+    /// `EnvFilter` directive for the app log, e.g. `info` or `info,backend=debug`
+    pub log_level: String,
+    /// Log level the backend is started with and switched to, see
+    /// `backend_control`. `None` leaves it to the backend.
+    pub backend_log_level: Option<String>,
+    /// Backend feature flags set through `set_backend_flag`
+    pub backend_flags: BTreeMap<String, bool>,
+    /// How many times to poll `/health` at startup before giving up on the backend
+    pub health_check_retries: u32,
+    pub health_check_interval_ms: u64,
+    /// Hide the main window to the tray on close instead of quitting
+    pub close_to_tray: bool,
+    pub update_channel: UpdateChannel,
+    /// Stay on this release instead of following the channel, even if it's
+    /// older than the one running, e.g. to get away from a bad one
+    pub pinned_version: Option<String>,
+    /// Launch at login, optionally straight to the tray
+    pub autostart: bool,
+    pub autostart_minimized: bool,
+    /// The user agreed to send crash reports; off until they opt in
+    pub crash_reporting: bool,
+    /// The user agreed to send anonymous usage events; off until they opt in
+    pub telemetry: bool,
+    /// Hours between scheduled backups, 0 to turn them off
+    pub backup_interval_hours: u64,
+    /// How many backups to keep before the oldest are deleted
+    pub backup_keep: usize,
+    /// Days deleted files stay in the trash before they are purged
+    pub trash_retention_days: u64,
+    /// Show native notifications for backend events, except muted categories
+    pub notifications: bool,
+    pub muted_notification_categories: Vec<String>,
+    /// Global hotkey for the quick capture window, `None` when turned off
+    pub quick_capture_shortcut: Option<String>,
+    /// Global hotkey for the quick open search palette, `None` when turned off
+    pub quick_open_shortcut: Option<String>,
+    /// Menu shortcuts changed from their defaults, by action id, `None` for
+    /// none. Only `set_keybinding` changes them, see `keybindings`.
+    pub keybindings: BTreeMap<String, Option<String>>,
+    /// Light or dark regardless of the OS, `None` to follow it
+    pub theme_override: Option<Theme>,
+    /// Use the forced-colors palette regardless of the OS's high contrast
+    /// setting, `None` to follow it
+    pub forced_colors_override: Option<bool>,
+    /// Mark misspelled words in text fields, see `spellcheck`
+    pub spellcheck: bool,
+    /// Appearance and notification preferences the frontend used to keep in
+    /// localStorage
+    pub font_size: u32,
+    pub accent_color: Option<String>,
+    pub notify_important_only: bool,
+    pub notification_sound: bool,
+    /// Thresholds past which the backend's resource use is reported
+    pub backend_cpu_warn_percent: f32,
+    pub backend_memory_warn_mb: u64,
+    /// Restart the backend when it uses more memory than this, `None` for no cap
+    pub backend_memory_limit_mb: Option<u64>,
+    /// The database is encrypted with the key in the OS keychain. Only
+    /// `enable_encryption` turns this on, as the database has to be migrated.
+    pub encrypt_data: bool,
+    /// Start sidecars with reduced privileges, see `sandbox`. Only worth
+    /// turning off to rule the sandbox out when a sidecar misbehaves.
+    pub sandbox_sidecars: bool,
+    /// Proxy for the backend and the app's own requests, see `proxy`
+    pub proxy_mode: ProxyMode,
+    /// Used when `proxy_mode` is `Manual`, e.g. `http://proxy.corp:3128`
+    pub proxy_url: Option<String>,
+    pub proxy_bypass: Vec<String>,
+    /// Minutes without input before the user counts as away, 0 to never
+    pub idle_minutes: u64,
+    /// Lock the app once the user is away. Only `set_app_lock` changes this,
+    /// as it needs a password to unlock with.
+    pub lock_when_idle: bool,
+    /// The app lock takes a PIN rather than a password; also set by
+    /// `set_app_lock`
+    pub app_lock_pin: bool,
+    /// Lock the app when the machine sleeps too, if a password is set
+    pub lock_on_sleep: bool,
+    /// When to throttle background work to save power, see `battery_saver`
+    pub battery_saver: BatterySaverMode,
+    /// Battery level at or below which `Auto` turns battery saver on
+    pub battery_saver_percent: u8,
+    /// Minutes `authenticate` goes without asking again after the user
+    /// confirmed it's them, 0 to always ask
+    pub auth_grace_minutes: u64,
+    /// Let phones and other devices on the network open the app, see `lan`.
+    /// Only `set_lan_access` changes this, as the backend has to restart.
+    pub lan_access: bool,
+    /// Port the backend listens on, kept from the last launch; `None` lets
+    /// the first launch pick one. Only `set_backend_port` changes this.
+    pub backend_port: Option<u16>,
+    /// Keep `backend_port` even when a launch has to fall back to another
+    pub pin_backend_port: bool,
+    /// Identifies this profile's backend, so one left running by an earlier
+    /// launch can be told apart from other servers on the port
+    pub instance_id: Option<String>,
+    /// The main window is locked to fullscreen, see `kiosk`. Only
+    /// `enter_kiosk` and `exit_kiosk` change this, as leaving may need a PIN.
+    pub kiosk: bool,
+    /// Read and write events in the OS calendars, see `os_calendar`. Only
+    /// `set_os_calendar` changes this, as it may have to ask for access.
+    pub os_calendar: bool,
+    /// Calendars whose events are shown in the app
+    pub os_calendar_read: Vec<String>,
+    /// Calendar new events go to
+    pub os_calendar_write: Option<String>,
+    /// BCP 47 tag to format dates and numbers with instead of the OS's,
+    /// see `locale`
+    pub locale_override: Option<String>,
+    /// Hosts `http_fetch` may reach, e.g. `hooks.slack.com` or
+    /// `*.example.com` for its subdomains
+    pub http_allowed_hosts: Vec<String>,
+    /// Run the scripts in the hooks folder on app events, see `hooks`
+    pub hooks: bool,
+    /// Serve runtime metrics for Prometheus on `metrics_port`, see `metrics`
+    pub metrics: bool,
+    pub metrics_port: u16,
+    /// Monitor the main window opens on when it's connected: its number
+    /// from `list_monitors`, its name or `primary`
+    pub open_on_monitor: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            shutdown_timeout_ms: 5000,
+            log_level: "info".to_string(),
+            backend_log_level: None,
+            backend_flags: BTreeMap::new(),
+            health_check_retries: 50,
+            health_check_interval_ms: 200,
+            close_to_tray: false,
+            update_channel: UpdateChannel::Stable,
+            pinned_version: None,
+            autostart: false,
+            autostart_minimized: true,
+            crash_reporting: false,
+            telemetry: false,
+            backup_interval_hours: 24,
+            backup_keep: 5,
+            trash_retention_days: 30,
+            notifications: true,
+            muted_notification_categories: Vec::new(),
+            quick_capture_shortcut: Some(quick_capture::DEFAULT_SHORTCUT.to_string()),
+            quick_open_shortcut: Some(quick_open::DEFAULT_SHORTCUT.to_string()),
+            keybindings: BTreeMap::new(),
+            theme_override: None,
+            forced_colors_override: None,
+            spellcheck: true,
+            font_size: 14,
+            accent_color: None,
+            notify_important_only: false,
+            notification_sound: true,
+            backend_cpu_warn_percent: 90.0,
+            backend_memory_warn_mb: 1024,
+            backend_memory_limit_mb: None,
+            encrypt_data: false,
+            sandbox_sidecars: true,
+            proxy_mode: ProxyMode::System,
+            proxy_url: None,
+            proxy_bypass: Vec::new(),
+            idle_minutes: 10,
+            lock_when_idle: false,
+            app_lock_pin: false,
+            lock_on_sleep: true,
+            battery_saver: BatterySaverMode::Auto,
+            battery_saver_percent: 20,
+            auth_grace_minutes: 5,
+            lan_access: false,
+            backend_port: None,
+            pin_backend_port: false,
+            instance_id: None,
+            kiosk: false,
+            os_calendar: false,
+            os_calendar_read: Vec::new(),
+            os_calendar_write: None,
+            locale_override: None,
+            http_allowed_hosts: Vec::new(),
+            hooks: true,
+            metrics: false,
+            metrics_port: 9477,
+            open_on_monitor: None,
+        }
+    }
+}
+
+pub struct SettingsStore {
+    app: AppHandle,
+    path: Option<PathBuf>,
+    settings: RwLock<Settings>,
+}
+
+impl SettingsStore {
+    pub fn get(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Applies `change`, writes the result to disk and broadcasts it to every
+    /// window as `settings-changed`, returning the new settings
+    pub fn update(&self, change: impl FnOnce(&mut Settings)) -> io::Result<Settings> {
+        let settings = {
+            let mut settings = self.settings.write().unwrap();
+            change(&mut settings);
+            self.save(&settings)?;
+            settings.clone()
+        };
+        let _ = self.app.emit("settings-changed", &settings);
+        Ok(settings)
+    }
+
+    fn save(&self, settings: &Settings) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(settings)?)
+    }
+}
+
+// Upgrades settings.json as written by older versions, one version at a time
+fn migrate(value: &mut Value) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    let mut version = object
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or_default() as u32;
+
+    while version < CURRENT_VERSION {
+        match version {
+            // Files from before versioning: every field means what it did
+            // then, missing ones are filled in with defaults
+            0 => {}
+            _ => unreachable!("no migration from settings version {version}"),
+        }
+        version += 1;
+    }
+    object.insert("version".to_string(), version.into());
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(SETTINGS_FILE));
+
+    // A missing or unreadable file just means defaults; a broken settings
+    // file should never keep the app from starting.
+    let mut migrated = false;
+    let settings: Settings = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| {
+            let mut value: Value = serde_json::from_str(&contents).ok()?;
+            let before = value.get("version").cloned();
+            migrate(&mut value);
+            migrated = value.get("version") != before.as_ref();
+            match serde_json::from_value(value) {
+                Ok(settings) => Some(settings),
+                Err(err) => {
+                    tracing::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+                    None
+                }
+            }
+        })
+        .unwrap_or_default();
+
+    // Safe mode starts from defaults and leaves the file alone, apart from
+    // what keeps the database, the backend's identity, the app lock and
+    // kiosk mode as they are. The backend log level stays too, as safe mode
+    // is where it's most needed; its flags don't.
+    let (path, settings) = if safe_mode::is_active(app) {
+        let defaults = Settings {
+            encrypt_data: settings.encrypt_data,
+            lock_when_idle: settings.lock_when_idle,
+            app_lock_pin: settings.app_lock_pin,
+            instance_id: settings.instance_id,
+            kiosk: settings.kiosk,
+            backend_log_level: settings.backend_log_level,
+            ..Settings::default()
+        };
+        (None, defaults)
+    } else {
+        (path, settings)
+    };
+
+    let store = SettingsStore {
+        app: app.clone(),
+        path,
+        settings: RwLock::new(settings),
+    };
+    if migrated {
+        if let Err(err) = store.save(&store.get()) {
+            tracing::warn!("Failed to save migrated {SETTINGS_FILE}: {err}");
+        }
+    }
+    app.manage(store);
+}
+
+/// Whether `key` is only changed through its own command, see `READ_ONLY`
+pub fn is_read_only(key: &str) -> bool {
+    READ_ONLY.contains(&key)
+}
+
+#[tauri::command]
+pub fn get_settings(settings: State<'_, SettingsStore>) -> Settings {
+    settings.get()
+}
+
+// Checks the settings that have to make sense before they are saved, naming
+// the one that doesn't
+fn validate(next: &Settings) -> CommandResult<()> {
+    let invalid = |field: &'static str| move |err: String| CommandError::invalid(field, err);
+    if let Some(url) = &next.proxy_url {
+        proxy::validate(url).map_err(invalid("proxyUrl"))?;
+    }
+    if let Some(tag) = &next.locale_override {
+        locale::validate(tag).map_err(invalid("localeOverride"))?;
+    }
+    auth::validate_grace(next.auth_grace_minutes).map_err(invalid("authGraceMinutes"))?;
+    battery_saver::validate_percent(next.battery_saver_percent)
+        .map_err(invalid("batterySaverPercent"))?;
+    metrics::validate_port(next.metrics_port).map_err(invalid("metricsPort"))?;
+    keybindings::validate(next).map_err(invalid("keybindings"))?;
+    trash::validate_retention(next.trash_retention_days).map_err(invalid("trashRetentionDays"))?;
+    if let Some(version) = &next.pinned_version {
+        updater::validate_version(version).map_err(invalid("pinnedVersion"))?;
+    }
+    for host in &next.http_allowed_hosts {
+        http_fetch::validate_host(host).map_err(invalid("httpAllowedHosts"))?;
+    }
+    Ok(())
+}
+
+/// Merges `patch`, a partial settings object with the same camelCase keys
+/// `get_settings` returns, applies whatever changed and saves the result.
+/// Some settings can only be changed from windows with a given scope, see
+/// `ipc::check_setting`.
+#[tauri::command]
+pub fn update_settings(
+    app: AppHandle,
+    window: WebviewWindow,
+    settings: State<'_, SettingsStore>,
+    patch: Value,
+) -> CommandResult<Settings> {
+    let Value::Object(patch) = patch else {
+        return Err(CommandError::invalid(
+            "patch",
+            "Settings patch must be an object",
+        ));
+    };
+    let current = settings.get();
+    let mut merged = serde_json::to_value(&current)?;
+    let Some(fields) = merged.as_object_mut() else {
+        return Err("Settings are not an object".into());
+    };
+    for (key, value) in patch {
+        ensure!(fields.contains_key(&key), key, "Unknown setting {key:?}");
+        ensure!(
+            !READ_ONLY.contains(&key.as_str()),
+            key,
+            "Setting {key:?} can't be changed here"
+        );
+        if fields.get(&key) != Some(&value) {
+            ipc::check_setting(window.label(), &key)?;
+        }
+        fields.insert(key, value);
+    }
+    let next: Settings = serde_json::from_value(merged)
+        .map_err(|err| CommandError::invalid("patch", err.to_string()))?;
+
+    validate(&next)?;
+    // Things registered with the OS can fail, so they go first and nothing
+    // is saved if they do
+    if (next.autostart, next.autostart_minimized)
+        != (current.autostart, current.autostart_minimized)
+    {
+        autostart::apply(&app, next.autostart, next.autostart_minimized)?;
+    }
+    if next.quick_capture_shortcut != current.quick_capture_shortcut {
+        quick_capture::apply(
+            &app,
+            current.quick_capture_shortcut.as_deref(),
+            next.quick_capture_shortcut.as_deref(),
+        )?;
+    }
+    if next.quick_open_shortcut != current.quick_open_shortcut {
+        quick_open::apply(
+            &app,
+            current.quick_open_shortcut.as_deref(),
+            next.quick_open_shortcut.as_deref(),
+        )?;
+    }
+
+    let saved = settings
+        .update(|settings| *settings = next)
+        .map_err(|err| err.to_string())?;
+
+    if saved.log_level != current.log_level {
+        logging::set_level(&app, &saved.log_level);
+    }
+    if saved.theme_override != current.theme_override {
+        theme::apply(&app);
+    }
+    if saved.forced_colors_override != current.forced_colors_override {
+        accessibility::apply(&app);
+    }
+    if saved.spellcheck != current.spellcheck {
+        spellcheck::apply(&app);
+    }
+    if (&saved.proxy_mode, &saved.proxy_url, &saved.proxy_bypass)
+        != (
+            &current.proxy_mode,
+            &current.proxy_url,
+            &current.proxy_bypass,
+        )
+    {
+        proxy::apply(&app);
+    }
+    if saved.locale_override != current.locale_override {
+        locale::apply(&app);
+    }
+    if (saved.battery_saver, saved.battery_saver_percent)
+        != (current.battery_saver, current.battery_saver_percent)
+    {
+        battery_saver::apply(&app);
+    }
+    if (saved.metrics, saved.metrics_port) != (current.metrics, current.metrics_port) {
+        metrics::apply(&app);
+    }
+    if saved.trash_retention_days < current.trash_retention_days {
+        trash::apply(&app);
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::menu;
+
+    fn field(settings: &Settings) -> Option<String> {
+        validate(settings).err().and_then(|err| err.field)
+    }
+
+    #[test]
+    fn unversioned_files_are_brought_up_to_date() {
+        let mut value = json!({ "logLevel": "debug", "metricsPort": 9500 });
+        migrate(&mut value);
+        assert_eq!(value["version"], CURRENT_VERSION);
+        let settings: Settings = serde_json::from_value(value).unwrap();
+        assert_eq!(settings.log_level, "debug");
+        assert_eq!(settings.metrics_port, 9500);
+        assert_eq!(
+            settings.shutdown_timeout_ms,
+            Settings::default().shutdown_timeout_ms
+        );
+    }
+
+    #[test]
+    fn current_files_are_left_as_they_are() {
+        let mut value = json!({ "version": CURRENT_VERSION, "logLevel": "warn" });
+        let before = value.clone();
+        migrate(&mut value);
+        assert_eq!(value, before);
+
+        let mut broken = json!(["not", "settings"]);
+        migrate(&mut broken);
+        assert_eq!(broken, json!(["not", "settings"]));
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(validate(&Settings::default()).is_ok());
+    }
+
+    #[test]
+    fn invalid_settings_name_their_field() {
+        let proxy = Settings {
+            proxy_url: Some("ftp://proxy.example.com".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(field(&proxy).as_deref(), Some("proxyUrl"));
+
+        let port = Settings {
+            metrics_port: 80,
+            ..Settings::default()
+        };
+        assert_eq!(field(&port).as_deref(), Some("metricsPort"));
+
+        let hosts = Settings {
+            http_allowed_hosts: vec!["localhost".to_string()],
+            ..Settings::default()
+        };
+        assert_eq!(field(&hosts).as_deref(), Some("httpAllowedHosts"));
+
+        let mut keybindings = Settings::default();
+        keybindings
+            .keybindings
+            .insert(menu::ZOOM_IN.to_string(), Some("CmdOrCtrl+T".to_string()));
+        assert_eq!(field(&keybindings).as_deref(), Some("keybindings"));
+    }
+}
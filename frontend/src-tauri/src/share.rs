@@ -0,0 +1,369 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::clipboard::Clipboard;
+use crate::fs_scope::{self, Access};
+use crate::metrics::IpcTimer;
+
+/// What to hand to another app. At least one of the fields must be set.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SharePayload {
+    /// Shown as the subject where the target has one
+    title: Option<String>,
+    text: Option<String>,
+    url: Option<String>,
+    /// Absolute paths, e.g. of an export
+    files: Vec<PathBuf>,
+}
+
+impl SharePayload {
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.text.is_none() && self.url.is_none() && self.files.is_empty()
+    }
+
+    // Everything as plain text, for the clipboard
+    fn to_text(&self) -> String {
+        let mut lines: Vec<String> = [&self.title, &self.text, &self.url]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        lines.extend(
+            self.files
+                .iter()
+                .map(|file| file.to_string_lossy().into_owned()),
+        );
+        lines.join("\n")
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShareOutcome {
+    /// The OS share UI was shown
+    Shared,
+    /// There was none to show, so the payload was copied to the clipboard
+    Copied,
+}
+
+/// Opens the macOS share sheet, the Windows Share UI or, on Linux, a new
+/// email through the desktop portal. Where none of those is available the
+/// payload goes to the clipboard instead.
+#[tauri::command]
+pub async fn share(
+    _timer: IpcTimer,
+    app: AppHandle,
+    window: WebviewWindow,
+    mut payload: SharePayload,
+) -> Result<ShareOutcome, String> {
+    if payload.is_empty() {
+        return Err("Nothing to share".to_string());
+    }
+    payload.files = payload
+        .files
+        .iter()
+        .map(|file| fs_scope::check(&app, file, Access::Read).map_err(|err| err.to_string()))
+        .collect::<Result<_, _>>()?;
+    if let Some(file) = payload
+        .files
+        .iter()
+        .find(|file| !file.is_absolute() || !file.is_file())
+    {
+        return Err(format!("{} is not a file", file.display()));
+    }
+
+    match platform::share(&window, &payload).await {
+        Ok(()) => Ok(ShareOutcome::Shared),
+        Err(err) => {
+            tracing::info!("No share UI, copying instead: {err}");
+            app.state::<Clipboard>().write_text(payload.to_text())?;
+            Ok(ShareOutcome::Copied)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::cell::RefCell;
+    use std::ffi::{c_char, c_void, CString};
+    use std::path::Path;
+
+    use objc2::encode::{Encode, Encoding};
+    use objc2::rc::{Allocated, Retained};
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use tauri::WebviewWindow;
+    use tokio::sync::oneshot;
+
+    use super::SharePayload;
+
+    // NSMinYEdge, so the picker hangs off the bottom of the window
+    const PREFERRED_EDGE: usize = 1;
+
+    thread_local! {
+        // AppKit doesn't keep the picker alive while it's shown
+        static PICKER: RefCell<Option<Retained<AnyObject>>> = const { RefCell::new(None) };
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Size {
+        width: f64,
+        height: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Rect {
+        origin: Point,
+        size: Size,
+    }
+
+    unsafe impl Encode for Point {
+        const ENCODING: Encoding = Encoding::Struct("CGPoint", &[f64::ENCODING, f64::ENCODING]);
+    }
+
+    unsafe impl Encode for Size {
+        const ENCODING: Encoding = Encoding::Struct("CGSize", &[f64::ENCODING, f64::ENCODING]);
+    }
+
+    unsafe impl Encode for Rect {
+        const ENCODING: Encoding = Encoding::Struct("CGRect", &[Point::ENCODING, Size::ENCODING]);
+    }
+
+    pub async fn share(window: &WebviewWindow, payload: &SharePayload) -> Result<(), String> {
+        let payload = payload.clone();
+        let (sender, receiver) = oneshot::channel();
+        window
+            .with_webview(move |webview| {
+                let _ = sender.send(unsafe { show(webview.ns_window(), &payload) });
+            })
+            .map_err(|err| err.to_string())?;
+        receiver
+            .await
+            .map_err(|_| "The share sheet was cancelled".to_string())?
+    }
+
+    unsafe fn show(ns_window: *mut c_void, payload: &SharePayload) -> Result<(), String> {
+        unsafe {
+            let window = &*(ns_window as *const AnyObject);
+            let view: Option<Retained<AnyObject>> = msg_send![window, contentView];
+            let view = view.ok_or("The window has no content view")?;
+
+            // The title has no place in the share sheet, only the items do
+            let items: Retained<AnyObject> = msg_send![class!(NSMutableArray), array];
+            if let Some(text) = payload.text.as_deref().and_then(ns_string) {
+                let _: () = msg_send![&*items, addObject: &*text];
+            }
+            if let Some(url) = payload.url.as_deref().and_then(ns_string) {
+                let url: Option<Retained<AnyObject>> =
+                    msg_send![class!(NSURL), URLWithString: &*url];
+                if let Some(url) = url {
+                    let _: () = msg_send![&*items, addObject: &*url];
+                }
+            }
+            for file in &payload.files {
+                if let Some(url) = file_url(file) {
+                    let _: () = msg_send![&*items, addObject: &*url];
+                }
+            }
+            let count: usize = msg_send![&*items, count];
+            if count == 0 {
+                return Err("Nothing the share sheet can take".to_string());
+            }
+
+            let picker: Allocated<AnyObject> = msg_send![class!(NSSharingServicePicker), alloc];
+            let picker: Retained<AnyObject> = msg_send![picker, initWithItems: &*items];
+            let bounds: Rect = msg_send![&*view, bounds];
+            let _: () = msg_send![
+                &*picker,
+                showRelativeToRect: bounds,
+                ofView: &*view,
+                preferredEdge: PREFERRED_EDGE
+            ];
+            PICKER.with(|slot| *slot.borrow_mut() = Some(picker));
+        }
+        Ok(())
+    }
+
+    unsafe fn file_url(path: &Path) -> Option<Retained<AnyObject>> {
+        let path = ns_string(&path.to_string_lossy())?;
+        unsafe { msg_send![class!(NSURL), fileURLWithPath: &*path] }
+    }
+
+    fn ns_string(text: &str) -> Option<Retained<AnyObject>> {
+        let text = CString::new(text.replace('\0', "")).ok()?;
+        let ptr: *const c_char = text.as_ptr();
+        unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::Mutex;
+
+    use tauri::WebviewWindow;
+    use tokio::sync::oneshot;
+    use windows::core::{factory, Interface, HSTRING};
+    use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+    use windows::Foundation::{TypedEventHandler, Uri};
+    use windows::Storage::{IStorageItem, StorageFile};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+    use windows_collections::IIterable;
+
+    use super::SharePayload;
+
+    // The Share UI won't open without a title
+    const DEFAULT_TITLE: &str = "Hamba";
+
+    // The window's manager outlives a share, so the previous request's
+    // handler is taken off before the next one goes on
+    static HANDLER: Mutex<Option<i64>> = Mutex::new(None);
+
+    pub async fn share(window: &WebviewWindow, payload: &SharePayload) -> Result<(), String> {
+        let payload = payload.clone();
+        // Raw window handles can't cross threads, their address can
+        let hwnd = window.hwnd().map_err(|err| err.to_string())?.0 as isize;
+        let (sender, receiver) = oneshot::channel();
+        window
+            .run_on_main_thread(move || {
+                let result = show(HWND(hwnd as *mut _), payload).map_err(|err| err.to_string());
+                let _ = sender.send(result);
+            })
+            .map_err(|err| err.to_string())?;
+        receiver
+            .await
+            .map_err(|_| "The Share UI was cancelled".to_string())?
+    }
+
+    // The Share UI asks for the data once a target is picked, on this thread
+    fn show(hwnd: HWND, payload: SharePayload) -> windows::core::Result<()> {
+        let interop = factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+        let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd)? };
+        if let Some(token) = HANDLER.lock().unwrap().take() {
+            manager.RemoveDataRequested(token)?;
+        }
+        let handler =
+            TypedEventHandler::new(move |_, args: windows::core::Ref<DataRequestedEventArgs>| {
+                let Some(args) = args.as_ref() else {
+                    return Ok(());
+                };
+                fill(args, &payload)
+            });
+        let token = manager.DataRequested(&handler)?;
+        *HANDLER.lock().unwrap() = Some(token);
+        unsafe { interop.ShowShareUIForWindow(hwnd) }
+    }
+
+    fn fill(args: &DataRequestedEventArgs, payload: &SharePayload) -> windows::core::Result<()> {
+        let data = args.Request()?.Data()?;
+        let title = payload.title.as_deref().unwrap_or(DEFAULT_TITLE);
+        data.Properties()?.SetTitle(&HSTRING::from(title))?;
+        if let Some(text) = &payload.text {
+            data.SetText(&HSTRING::from(text.as_str()))?;
+        }
+        if let Some(url) = &payload.url {
+            data.SetWebLink(&Uri::CreateUri(&HSTRING::from(url.as_str()))?)?;
+        }
+        if !payload.files.is_empty() {
+            // Local files resolve right away, so waiting here is fine
+            let files = payload
+                .files
+                .iter()
+                .map(|file| {
+                    StorageFile::GetFileFromPathAsync(&HSTRING::from(file.as_os_str()))?
+                        .join()?
+                        .cast::<IStorageItem>()
+                        .map(Some)
+                })
+                .collect::<windows::core::Result<Vec<_>>>()?;
+            data.SetStorageItemsReadOnly(&IIterable::from(files))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::fs::File;
+
+    use tauri::WebviewWindow;
+    use zbus::zvariant::{Fd, OwnedObjectPath, Value};
+
+    use super::SharePayload;
+
+    // The portals have no general share; an email is the closest that
+    // takes text, links and attachments alike
+    #[zbus::proxy(
+        interface = "org.freedesktop.portal.Email",
+        default_service = "org.freedesktop.portal.Desktop",
+        default_path = "/org/freedesktop/portal/desktop"
+    )]
+    trait Email {
+        fn compose_email(
+            &self,
+            parent_window: &str,
+            options: HashMap<&str, Value<'_>>,
+        ) -> zbus::Result<OwnedObjectPath>;
+    }
+
+    pub async fn share(_window: &WebviewWindow, payload: &SharePayload) -> Result<(), String> {
+        let files = payload
+            .files
+            .iter()
+            .map(File::open)
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|err| err.to_string())?;
+        let body: Vec<&str> = [&payload.text, &payload.url]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+
+        let mut options = HashMap::new();
+        if let Some(title) = &payload.title {
+            options.insert("subject", Value::from(title.as_str()));
+        }
+        if !body.is_empty() {
+            options.insert("body", Value::from(body.join("\n\n")));
+        }
+        if !files.is_empty() {
+            let fds: Vec<Fd> = files.iter().map(Fd::from).collect();
+            options.insert("attachment_fds", Value::from(fds));
+        }
+
+        let connection = zbus::Connection::session()
+            .await
+            .map_err(|err| err.to_string())?;
+        EmailProxy::new(&connection)
+            .await
+            .map_err(|err| err.to_string())?
+            .compose_email("", options)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    use super::SharePayload;
+
+    pub async fn share(_window: &WebviewWindow, _payload: &SharePayload) -> Result<(), String> {
+        Err("Sharing is not supported here".to_string())
+    }
+}
@@ -0,0 +1,961 @@
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+
+use crate::backend_control;
+use crate::backend_lock;
+use crate::backend_log::{BackendLog, Stream};
+use crate::backend_port;
+use crate::battery_saver;
+use crate::cli::Cli;
+use crate::crash_report;
+use crate::data_dir;
+use crate::encryption;
+use crate::env_file;
+use crate::integrity;
+use crate::lan;
+use crate::locale;
+use crate::metrics::IpcTimer;
+use crate::profile::Profile;
+use crate::proxy;
+use crate::safe_mode;
+use crate::sandbox::{self, Confinement};
+use crate::settings::SettingsStore;
+use crate::sidecar_config::{self, SidecarConfig};
+use crate::tls::{self, Tls};
+use crate::watchdog;
+
+pub const TOKEN_HEADER: &str = "X-Hamba-Token";
+pub const DATABASE_FILE: &str = "hamba.db";
+// SQLite keeps these next to the database while it is open
+pub const DATABASE_SIDE_FILES: &[&str] = &["wal", "shm"];
+
+// The port `bun run dev` listens on, used when the backend runs separately
+pub const DEV_PORT: u16 = 8877;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// A backend that stays up this long is considered healthy again and the
+// backoff starts over from the beginning on its next crash.
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+// An adopted backend is polled this often, so a stop notices it exiting soon
+const ADOPTED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Asks the backend to exit where there's no signal to ask with
+#[cfg(not(unix))]
+const SHUTDOWN_PATH: &str = "/control/shutdown";
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum BackendStatus {
+    Starting,
+    Running {
+        pid: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    Restarting {
+        attempt: u32,
+        retry_in_ms: u64,
+        code: Option<i32>,
+    },
+    Stopped,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum RestartStage {
+    Stopping,
+    Starting,
+    Ready,
+    Failed,
+}
+
+#[derive(Default)]
+struct Control {
+    stop_requested: bool,
+    supervising: bool,
+}
+
+/// A supervised child process, the backend or a helper
+#[derive(Default)]
+struct Process {
+    child: Mutex<Option<CommandChild>>,
+    control: Mutex<Control>,
+    // When the process last sent a heartbeat, `None` before its first
+    last_heartbeat: Mutex<Option<Instant>>,
+    confinement: Mutex<Option<Confinement>>,
+    // When the running process was spawned
+    started: Mutex<Option<Instant>>,
+    // Restarts after an unexpected exit since the app started
+    restarts: AtomicU32,
+    // A backend left running by an earlier launch that is used instead of
+    // spawning one, until it goes away
+    adopted: Mutex<Option<u32>>,
+}
+
+impl Process {
+    fn pid(&self) -> Option<u32> {
+        let child = self.child.lock().unwrap().as_ref().map(|child| child.pid());
+        child.or(*self.adopted.lock().unwrap())
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.control.lock().unwrap().stop_requested
+    }
+
+    // Checked by the supervisor between runs. Deciding to exit and clearing
+    // `supervising` happen under one lock so a concurrent `start` either
+    // cancels the stop or spawns a fresh supervisor, never neither.
+    fn supervisor_should_exit(&self) -> bool {
+        let mut control = self.control.lock().unwrap();
+        if control.stop_requested {
+            control.supervising = false;
+        }
+        control.stop_requested
+    }
+
+    // Marks the process as wanted; returns whether a supervisor needs to be
+    // spawned for it
+    fn request_start(&self) -> bool {
+        let mut control = self.control.lock().unwrap();
+        control.stop_requested = false;
+        !std::mem::replace(&mut control.supervising, true)
+    }
+
+    // Asks the process to exit with `ask`, waits up to `timeout` and kills it
+    // after that
+    fn stop(&self, name: &str, timeout: Duration, ask: impl FnOnce(u32) -> bool) {
+        self.control.lock().unwrap().stop_requested = true;
+        let Some(pid) = self.pid() else {
+            return;
+        };
+
+        if ask(pid) {
+            let deadline = Instant::now() + timeout;
+            // The supervisor clears the slot as soon as it sees the process exit
+            while Instant::now() < deadline {
+                if self.pid().is_none() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            tracing::warn!("{name} did not exit within {timeout:?}, killing it");
+        }
+
+        let child = self.child.lock().unwrap().take();
+        if let Some(child) = child {
+            if let Err(err) = child.kill() {
+                tracing::error!("Failed to kill the {name} sidecar: {err}");
+            }
+        } else if let Some(pid) = *self.adopted.lock().unwrap() {
+            if !kill(pid) {
+                tracing::error!("Failed to kill the {name} sidecar (pid {pid})");
+            }
+        }
+    }
+}
+
+pub struct Sidecar {
+    port: u16,
+    // Backend the app uses but doesn't run: the one started by hand in dev
+    // mode, or the profile's `api_base`
+    external: Option<String>,
+    // Shared secret the backend requires on every request; `None` in dev
+    // mode, where the backend is started by hand without one.
+    token: Option<String>,
+    // `None` on platforms without TLS support and in dev mode
+    tls: Option<Tls>,
+    client: reqwest::Client,
+    // The primary entry of sidecars.toml
+    config: SidecarConfig,
+    process: Process,
+    // Binary to run instead of the bundled sidecar, set by `backend_swap`
+    binary: Mutex<Option<PathBuf>>,
+}
+
+/// The non-primary entries of sidecars.toml
+pub struct Helpers(Vec<Helper>);
+
+struct Helper {
+    config: SidecarConfig,
+    // Port handed over in `PORT` for the health check, if it has one
+    port: Option<u16>,
+    process: Process,
+}
+
+// Which process a supervisor looks after
+#[derive(Clone, Copy)]
+enum Target {
+    Backend,
+    Helper(usize),
+}
+
+impl Sidecar {
+    pub fn url(&self) -> String {
+        if let Some(url) = &self.external {
+            return url.clone();
+        }
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        format!("{scheme}://localhost:{}", self.port)
+    }
+
+    /// The backend runs outside the app, which can't start, stop or replace it
+    pub fn is_external(&self) -> bool {
+        self.external.is_some()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// A request to the backend with the auth token already attached
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{path}", self.url()));
+        match &self.token {
+            Some(token) => request.header(TOKEN_HEADER, token),
+            None => request,
+        }
+    }
+
+    /// Like `request`, to a second backend running next to this one on
+    /// `port`, see `canary`
+    pub fn request_on(
+        &self,
+        port: u16,
+        method: reqwest::Method,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let request = self
+            .client
+            .request(method, format!("{scheme}://localhost:{port}{path}"));
+        match &self.token {
+            Some(token) => request.header(TOKEN_HEADER, token),
+            None => request,
+        }
+    }
+
+    /// What the health check asks for, if the backend has one
+    pub fn health_path(&self) -> Option<&str> {
+        self.config.health.as_deref()
+    }
+
+    pub fn tls(&self) -> Option<&Tls> {
+        self.tls.as_ref()
+    }
+
+    /// `ws://` URL for `path`, with the token in the query string since
+    /// WebSocket clients can't always set headers on the upgrade request
+    pub fn websocket_url(&self, path: &str) -> String {
+        // http -> ws, https -> wss
+        let url = format!("{}{path}", self.url().replacen("http", "ws", 1));
+        match &self.token {
+            Some(token) if url.contains('?') => format!("{url}&token={token}"),
+            Some(token) => format!("{url}?token={token}"),
+            None => url,
+        }
+    }
+
+    pub fn binary(&self) -> Option<PathBuf> {
+        self.binary.lock().unwrap().clone()
+    }
+
+    /// Runs `binary` from the next start on, or the bundled sidecar for `None`.
+    /// Returns the binary that was used until now.
+    pub fn set_binary(&self, binary: Option<PathBuf>) -> Option<PathBuf> {
+        std::mem::replace(&mut *self.binary.lock().unwrap(), binary)
+    }
+
+    /// The backend executable the next start runs
+    pub fn backend_path(&self) -> Result<PathBuf, String> {
+        self.binary()
+            .map(Ok)
+            .unwrap_or_else(|| sidecar_path(&self.config.name))
+    }
+
+    /// The running backend's process id
+    pub fn pid(&self) -> Option<u32> {
+        self.process.pid()
+    }
+
+    pub fn last_heartbeat(&self) -> Option<Instant> {
+        *self.process.last_heartbeat.lock().unwrap()
+    }
+
+    /// How long the running backend has been up
+    pub fn uptime(&self) -> Option<Duration> {
+        self.process
+            .started
+            .lock()
+            .unwrap()
+            .map(|started| started.elapsed())
+    }
+
+    /// How often the backend was restarted after exiting on its own
+    pub fn restart_count(&self) -> u32 {
+        self.process.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Kills the backend without stopping supervision, so it gets restarted.
+    /// Returns the pid of the process that was killed.
+    pub fn kill_child(&self) -> Option<u32> {
+        let child = self.process.child.lock().unwrap().take()?;
+        let pid = child.pid();
+        if let Err(err) = child.kill() {
+            tracing::error!("Failed to kill backend sidecar: {err}");
+        }
+        *self.process.last_heartbeat.lock().unwrap() = None;
+        Some(pid)
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.process.is_stopped()
+    }
+}
+
+#[tauri::command]
+pub fn get_backend_port(sidecar: State<'_, Sidecar>) -> u16 {
+    sidecar.port
+}
+
+pub fn init(app: &AppHandle) {
+    let sidecars = sidecar_config::load();
+    let external = match &app.state::<Profile>().api_base {
+        Some(base) => Some(base.clone()),
+        None if cfg!(debug_assertions) => Some(format!("http://localhost:{DEV_PORT}")),
+        None => None,
+    };
+    let tls = if external.is_some() || !tls::SUPPORTED {
+        None
+    } else {
+        tls::load_or_create(app)
+            .inspect_err(|err| {
+                tracing::warn!("Failed to set up TLS, talking to the backend over HTTP: {err}")
+            })
+            .ok()
+    };
+    let mut client = reqwest::Client::builder();
+    if let Some(tls) = &tls {
+        match reqwest::Certificate::from_pem(tls.cert_pem.as_bytes()) {
+            Ok(cert) => client = client.add_root_certificate(cert),
+            Err(err) => tracing::warn!("Invalid backend certificate: {err}"),
+        }
+    }
+    let client = client.build().unwrap_or_default();
+
+    let process = Process::default();
+    let (port, token) = if let Some(url) = &external {
+        let port = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.port_or_known_default())
+            .unwrap_or(DEV_PORT);
+        (port, None)
+    } else {
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        backend_lock::clean_up(app, &client, scheme);
+        let choice = backend_port::choose(app, &client, scheme);
+        *process.adopted.lock().unwrap() = choice.adopted_pid;
+        (choice.port, Some(choice.token))
+    };
+
+    app.manage(Sidecar {
+        port,
+        external,
+        token,
+        client,
+        tls,
+        config: sidecars.primary,
+        process,
+        binary: Mutex::new(None),
+    });
+
+    let helpers = sidecars
+        .helpers
+        .into_iter()
+        .map(|config| Helper {
+            port: config.health.is_some().then(|| free_port().ok()).flatten(),
+            config,
+            process: Process::default(),
+        })
+        .collect();
+    app.manage(Helpers(helpers));
+}
+
+// Binding to port 0 lets the OS pick an unused port; the listener is dropped
+// right away so the backend can bind it instead.
+pub fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("OS random number generator unavailable");
+    hex::encode(bytes)
+}
+
+/// Starts supervising the backend, or resumes an existing supervisor that
+/// has been asked to stop but hasn't exited yet.
+pub fn start(app: &AppHandle) {
+    let sidecar = app.state::<Sidecar>();
+    if sidecar.is_external() {
+        sidecar.process.request_start();
+        if cfg!(debug_assertions) {
+            tracing::info!("Dev mode: Run backend separately with 'cd backend && bun run dev'");
+        } else {
+            tracing::info!("Using the backend at {}", sidecar.url());
+        }
+        return;
+    }
+    spawn_supervisor(app, Target::Backend);
+}
+
+/// Starts every helper from sidecars.toml. Like the backend, they aren't
+/// spawned in dev mode, nor in safe mode.
+pub fn start_helpers(app: &AppHandle) {
+    if cfg!(debug_assertions) || safe_mode::is_active(app) {
+        return;
+    }
+    for index in 0..app.state::<Helpers>().0.len() {
+        spawn_supervisor(app, Target::Helper(index));
+    }
+}
+
+fn spawn_supervisor(app: &AppHandle, target: Target) {
+    if !process(app, target).request_start() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        supervise(app, target).await;
+    });
+}
+
+fn config(app: &AppHandle, target: Target) -> &SidecarConfig {
+    match target {
+        Target::Backend => &app.state::<Sidecar>().inner().config,
+        Target::Helper(index) => &app.state::<Helpers>().inner().0[index].config,
+    }
+}
+
+fn process(app: &AppHandle, target: Target) -> &Process {
+    match target {
+        Target::Backend => &app.state::<Sidecar>().inner().process,
+        Target::Helper(index) => &app.state::<Helpers>().inner().0[index].process,
+    }
+}
+
+// The one supervisor for every sidecar: spawns it, restarts it with backoff
+// as its restart policy asks, and only differs for the backend in what it is
+// handed and reports.
+async fn supervise(app: AppHandle, target: Target) {
+    let config = config(&app, target);
+    let process = process(&app, target);
+    let is_backend = matches!(target, Target::Backend);
+    let mut log = BackendLog::new(&app);
+    let mut attempt = 0;
+
+    while !process.supervisor_should_exit() {
+        if is_backend {
+            emit_status(&app, BackendStatus::Starting);
+        }
+
+        let adopted = *process.adopted.lock().unwrap();
+        if let Some(pid) = adopted {
+            emit_status(&app, BackendStatus::Running { pid });
+            watch_adopted(&app, pid).await;
+            process.adopted.lock().unwrap().take();
+            if !process.is_stopped() {
+                tracing::warn!("The backend from an earlier launch went away, starting a new one");
+            }
+            continue;
+        }
+
+        // Retrying can't fix a bad binary, so stop supervising altogether.
+        // A swapped in binary was already checked before it was chosen.
+        let binary = is_backend
+            .then(|| app.state::<Sidecar>().binary())
+            .flatten();
+        let verified = match (is_backend, &binary) {
+            (true, None) => integrity::verify_backend(&config.name),
+            _ => Ok(()),
+        };
+        if let Err(err) = verified {
+            tracing::error!("Refusing to start the backend: {err}");
+            app.dialog()
+                .message(format!(
+                    "The Hamba backend appears to be damaged or modified and was not started. \
+                     Reinstalling Hamba should fix this.\n\n{err}"
+                ))
+                .title("Backend integrity check failed")
+                .kind(MessageDialogKind::Error)
+                .show(|_| {});
+            process.control.lock().unwrap().stop_requested = true;
+            continue;
+        }
+
+        let started = Instant::now();
+        let spawned = binary
+            .map(Ok)
+            .unwrap_or_else(|| sidecar_path(&config.name))
+            .and_then(|path| {
+                let cmd = sandbox::command(&app, &path)
+                    .args(&config.args)
+                    .envs(config.env.clone());
+                let cmd = match target {
+                    Target::Backend => backend_env(&app, cmd),
+                    Target::Helper(index) => helper_env(&app, index, cmd),
+                };
+                cmd.spawn().map_err(|err| err.to_string())
+            });
+        let code = match spawned {
+            Ok((rx, child)) => {
+                let pid = child.pid();
+                *process.child.lock().unwrap() = Some(child);
+                *process.started.lock().unwrap() = Some(started);
+                *process.confinement.lock().unwrap() = Some(sandbox::confine(&app, pid));
+                if is_backend {
+                    backend_lock::write(&app, pid, app.state::<Sidecar>().port);
+                    battery_saver::spawned(&app, pid);
+                    emit_status(&app, BackendStatus::Running { pid });
+                } else {
+                    tracing::info!("Started the {} sidecar (pid {pid})", config.name);
+                    watch_helper_health(&app, target);
+                }
+
+                let code = if is_backend {
+                    wait_for_exit(rx, process, |stream, bytes| log.write(stream, bytes)).await
+                } else {
+                    wait_for_exit(rx, process, |stream, bytes| {
+                        helper_output(&config.name, stream, bytes)
+                    })
+                    .await
+                };
+                process.child.lock().unwrap().take();
+                process.confinement.lock().unwrap().take();
+                process.started.lock().unwrap().take();
+                *process.last_heartbeat.lock().unwrap() = None;
+                if is_backend {
+                    backend_lock::remove(&app);
+                }
+
+                let lines = log.take_recent();
+                // Exits we asked for (including a kill after the shutdown
+                // timeout) aren't crashes
+                if is_backend && code != Some(0) && !process.is_stopped() {
+                    crash_report::record_backend_exit(&app, code, lines);
+                }
+                code
+            }
+            Err(err) => {
+                tracing::error!("Failed to spawn the {} sidecar: {err}", config.name);
+                None
+            }
+        };
+
+        if process.supervisor_should_exit() {
+            break;
+        }
+        if !config.should_restart(code) {
+            tracing::info!(
+                "The {} sidecar exited (code {code:?}) and is not restarted",
+                config.name
+            );
+            process.control.lock().unwrap().stop_requested = true;
+            continue;
+        }
+
+        if started.elapsed() >= STABLE_UPTIME {
+            attempt = 0;
+        }
+        attempt += 1;
+        process.restarts.fetch_add(1, Ordering::Relaxed);
+
+        let delay = backoff(attempt);
+        tracing::warn!(
+            "The {} sidecar exited (code {code:?}), restarting in {delay:?}",
+            config.name
+        );
+        if is_backend {
+            emit_status(
+                &app,
+                BackendStatus::Restarting {
+                    attempt,
+                    retry_in_ms: delay.as_millis() as u64,
+                    code,
+                },
+            );
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    if is_backend {
+        emit_status(&app, BackendStatus::Stopped);
+    }
+}
+
+pub type SidecarCommand = tauri_plugin_shell::process::Command;
+
+/// A one-off run of the backend executable with `args` and the environment
+/// it is started with, for tasks like database maintenance. The supervised
+/// backend has to be stopped first so the two don't share the database.
+pub fn backend_command(app: &AppHandle, args: &[&str]) -> Result<SidecarCommand, String> {
+    let sidecar = app.state::<Sidecar>();
+    let path = match sidecar.binary() {
+        Some(binary) => binary,
+        None => {
+            integrity::verify_backend(&sidecar.config.name)?;
+            sidecar_path(&sidecar.config.name)?
+        }
+    };
+    let cmd = sandbox::command(app, &path)
+        .args(args)
+        .envs(sidecar.config.env.clone());
+    Ok(backend_env(app, cmd))
+}
+
+/// `binary` set up like the backend, but on `port` and loopback only, with
+/// `data` for its data folder and `HAMBA_CANARY` telling it to leave mail
+/// alone. The supervised backend keeps running, see `canary`.
+pub fn canary_command(app: &AppHandle, binary: &Path, port: u16, data: &Path) -> SidecarCommand {
+    let sidecar = app.state::<Sidecar>();
+    let cmd = sandbox::command(app, binary)
+        .args(&sidecar.config.args)
+        .envs(sidecar.config.env.clone());
+    backend_env(app, cmd)
+        .env("PORT", port.to_string())
+        .env("HAMBA_HOST", "127.0.0.1")
+        .env("HAMBA_DATA_DIR", data)
+        .env("DATABASE_PATH", data.join(DATABASE_FILE))
+        .env("HAMBA_CANARY", "1")
+}
+
+fn backend_env(app: &AppHandle, cmd: SidecarCommand) -> SidecarCommand {
+    let sidecar = app.state::<Sidecar>();
+    let mut cmd = cmd
+        .env("PORT", sidecar.port.to_string())
+        .env(
+            "HAMBA_HEARTBEAT_MS",
+            battery_saver::heartbeat_interval(app)
+                .as_millis()
+                .to_string(),
+        )
+        .env("APP_VERSION", app.package_info().version.to_string());
+    if let Some(instance) = app.state::<SettingsStore>().get().instance_id {
+        cmd = cmd.env("HAMBA_INSTANCE_ID", instance);
+    }
+    // Flags win over what the app set at runtime, then hamba.env, then the
+    // profile, and all of them over the proxy settings. Safe mode keeps
+    // only the flags, as they were given for this launch, and the log level.
+    cmd = cmd.envs(proxy::env(&proxy::resolve(app)));
+    cmd = cmd.envs(lan::env(app));
+    cmd = cmd.envs(locale::env(app));
+    if !safe_mode::is_active(app) {
+        cmd = cmd.envs(app.state::<Profile>().backend_env.clone());
+        cmd = cmd.envs(env_file::vars(app));
+    }
+    cmd = cmd.envs(backend_control::env(app));
+    cmd = cmd.envs(battery_saver::env(app));
+    cmd = cmd.envs(app.state::<Cli>().backend_env.iter().cloned());
+    if let Some(tls) = &sidecar.tls {
+        cmd = cmd
+            .env("HAMBA_TLS_CERT", &tls.cert_path)
+            .env("HAMBA_TLS_KEY", &tls.key_path);
+    }
+    if let Some(dir) = data_dir::data_dir(app) {
+        cmd = cmd
+            .env("HAMBA_DATA_DIR", &dir)
+            .env("DATABASE_PATH", dir.join(DATABASE_FILE));
+    }
+    if let Some(key) = encryption::database_key(app) {
+        cmd = cmd.env("HAMBA_DB_KEY", key);
+    }
+    match &sidecar.token {
+        Some(token) => cmd.env("HAMBA_AUTH_TOKEN", token),
+        None => cmd,
+    }
+}
+
+// Helpers reach the backend the same way the frontend does
+fn helper_env(app: &AppHandle, index: usize, cmd: SidecarCommand) -> SidecarCommand {
+    let sidecar = app.state::<Sidecar>();
+    let mut cmd = cmd
+        .env("HAMBA_BACKEND_URL", sidecar.url())
+        .envs(proxy::env(&proxy::resolve(app)));
+    if let Some(port) = app.state::<Helpers>().0[index].port {
+        cmd = cmd.env("PORT", port.to_string());
+    }
+    match &sidecar.token {
+        Some(token) => cmd.env("HAMBA_AUTH_TOKEN", token),
+        None => cmd,
+    }
+}
+
+fn helper_output(name: &str, stream: Stream, bytes: &[u8]) {
+    let text = String::from_utf8_lossy(bytes);
+    let line = text.trim_end_matches(['\r', '\n']);
+    match stream {
+        Stream::Stdout => tracing::info!(target: "sidecar", "[{name}] {line}"),
+        Stream::Stderr => tracing::warn!(target: "sidecar", "[{name}] {line}"),
+    }
+}
+
+// A helper that never answers its health check is killed, which lets its
+// restart policy decide what happens next
+fn watch_helper_health(app: &AppHandle, target: Target) {
+    let Target::Helper(index) = target else {
+        return;
+    };
+    let helper = &app.state::<Helpers>().inner().0[index];
+    let (Some(path), Some(port)) = (&helper.config.health, helper.port) else {
+        return;
+    };
+    let url = format!("http://localhost:{port}{path}");
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let helper = &app.state::<Helpers>().inner().0[index];
+        if poll_health(&app, reqwest::Client::new().get(&url)).await {
+            tracing::info!("The {} sidecar is healthy", helper.config.name);
+            return;
+        }
+        if helper.process.is_stopped() {
+            return;
+        }
+        tracing::warn!(
+            "The {} sidecar did not answer {url}, killing it",
+            helper.config.name
+        );
+        let child = helper.process.child.lock().unwrap().take();
+        if let Some(child) = child {
+            let _ = child.kill();
+        }
+    });
+}
+
+/// Stops the backend and starts it again on the same port with the same
+/// environment, emitting `backend-restart` as it goes through each stage.
+pub async fn restart(app: &AppHandle) -> bool {
+    let _ = app.emit("backend-restart", RestartStage::Stopping);
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || stop(&handle)).await;
+
+    let _ = app.emit("backend-restart", RestartStage::Starting);
+    start(app);
+
+    let healthy = wait_until_healthy(app).await;
+    let stage = if healthy {
+        RestartStage::Ready
+    } else {
+        RestartStage::Failed
+    };
+    let _ = app.emit("backend-restart", stage);
+    healthy
+}
+
+#[tauri::command]
+pub async fn restart_backend(_timer: IpcTimer, app: AppHandle) -> Result<(), String> {
+    if restart(&app).await {
+        Ok(())
+    } else {
+        Err("Backend did not come back up after restarting".to_string())
+    }
+}
+
+/// Polls the backend's health endpoint from sidecars.toml until it
+/// responds, giving up after the configured number of retries. Without an
+/// endpoint the backend counts as healthy right away.
+pub async fn wait_until_healthy(app: &AppHandle) -> bool {
+    let sidecar = app.state::<Sidecar>();
+    let Some(path) = &sidecar.config.health else {
+        return true;
+    };
+    poll_health(app, sidecar.request(reqwest::Method::GET, path)).await
+}
+
+pub async fn poll_health(app: &AppHandle, request: reqwest::RequestBuilder) -> bool {
+    let settings = app.state::<SettingsStore>().get();
+    for _ in 0..=settings.health_check_retries {
+        let Some(request) = request.try_clone() else {
+            return false;
+        };
+        let response = request.timeout(HEALTH_CHECK_TIMEOUT).send().await;
+        if response.is_ok_and(|response| response.status().is_success()) {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(settings.health_check_interval_ms)).await;
+    }
+
+    false
+}
+
+/// Stops the backend until the next `start`: asks it to exit, gives it the
+/// configured shutdown timeout to do so, and kills it if it is still around
+/// after that. Blocks for up to the shutdown timeout.
+pub fn stop(app: &AppHandle) {
+    let Some(sidecar) = app.try_state::<Sidecar>() else {
+        return;
+    };
+    let timeout = Duration::from_millis(app.state::<SettingsStore>().get().shutdown_timeout_ms);
+    sidecar
+        .process
+        .stop("Backend", timeout, |pid| terminate_backend(&sidecar, pid));
+}
+
+/// Stops every helper, each with the same timeout as the backend
+pub fn stop_helpers(app: &AppHandle) {
+    let Some(helpers) = app.try_state::<Helpers>() else {
+        return;
+    };
+    let timeout = Duration::from_millis(app.state::<SettingsStore>().get().shutdown_timeout_ms);
+    for helper in &helpers.0 {
+        helper.process.stop(&helper.config.name, timeout, terminate);
+    }
+}
+
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+// There is no SIGTERM equivalent for a windowless child on Windows, so
+// helpers are killed straight away and the backend is asked over HTTP.
+#[cfg(not(unix))]
+pub fn terminate(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn terminate_backend(_sidecar: &Sidecar, pid: u32) -> bool {
+    terminate(pid)
+}
+
+#[cfg(not(unix))]
+fn terminate_backend(sidecar: &Sidecar, _pid: u32) -> bool {
+    let request = sidecar
+        .request(reqwest::Method::POST, SHUTDOWN_PATH)
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send();
+    match tauri::async_runtime::block_on(request) {
+        Ok(response) if response.status().is_success() => true,
+        Ok(response) => {
+            tracing::warn!("The backend refused to shut down: {}", response.status());
+            false
+        }
+        Err(err) => {
+            tracing::warn!("Failed to ask the backend to shut down: {err}");
+            false
+        }
+    }
+}
+
+// For processes without a `CommandChild` to kill them through
+pub fn kill(pid: u32) -> bool {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some_and(|process| process.kill())
+}
+
+// Waits for an adopted backend to stop answering its health check, or to
+// exit without one. It isn't a child, so there is no exit to wait for and
+// no output to read heartbeats from.
+async fn watch_adopted(app: &AppHandle, pid: u32) {
+    let sidecar = app.state::<Sidecar>();
+    loop {
+        tokio::time::sleep(ADOPTED_POLL_INTERVAL).await;
+        let alive = match sidecar.health_path() {
+            Some(path) => sidecar
+                .request(reqwest::Method::GET, path)
+                .timeout(HEALTH_CHECK_TIMEOUT)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success()),
+            None => backend_lock::is_running(pid),
+        };
+        if !alive {
+            return;
+        }
+    }
+}
+
+async fn wait_for_exit(
+    mut rx: tauri::async_runtime::Receiver<CommandEvent>,
+    process: &Process,
+    mut output: impl FnMut(Stream, &[u8]),
+) -> Option<i32> {
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(bytes) if is_heartbeat(&bytes) => {
+                *process.last_heartbeat.lock().unwrap() = Some(Instant::now());
+            }
+            CommandEvent::Stdout(bytes) => output(Stream::Stdout, &bytes),
+            CommandEvent::Stderr(bytes) => output(Stream::Stderr, &bytes),
+            CommandEvent::Terminated(payload) => return payload.code,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_heartbeat(bytes: &[u8]) -> bool {
+    bytes.trim_ascii() == watchdog::HEARTBEAT_TOKEN.as_bytes()
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// Where the shell plugin looks for `sidecar(name)`: next to the executable
+pub fn sidecar_path(name: &str) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    let dir = exe.parent().ok_or("Executable has no parent directory")?;
+    Ok(dir.join(format!("{name}{}", std::env::consts::EXE_SUFFIX)))
+}
+
+/// hamba.db -> hamba.db-<suffix>, as SQLite names its own side files
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!("-{suffix}"));
+    PathBuf::from(name)
+}
+
+fn emit_status(app: &AppHandle, status: BackendStatus) {
+    let _ = app.emit("backend-status", status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_from_the_first_attempt() {
+        assert_eq!(backoff(1), INITIAL_BACKOFF);
+        assert_eq!(backoff(2), INITIAL_BACKOFF * 2);
+        assert_eq!(backoff(3), INITIAL_BACKOFF * 4);
+    }
+
+    #[test]
+    fn backoff_stops_growing_at_the_maximum() {
+        assert_eq!(backoff(7), MAX_BACKOFF);
+        assert_eq!(backoff(40), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn backoff_treats_zero_as_the_first_attempt() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+    }
+}
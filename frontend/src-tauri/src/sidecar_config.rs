@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+// Bundled into the binary: the sidecars it describes ship with it anyway
+const SIDECARS_TOML: &str = include_str!("../sidecars.toml");
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    #[default]
+    Always,
+    /// Restart unless the process exited with code 0
+    OnFailure,
+    Never,
+}
+
+/// One `[[sidecar]]` entry of sidecars.toml
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SidecarConfig {
+    pub name: String,
+    #[serde(default)]
+    pub primary: bool,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub health: Option<String>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+}
+
+impl SidecarConfig {
+    /// Whether the supervisor should start the process again after it
+    /// exited with `code`
+    pub fn should_restart(&self, code: Option<i32>) -> bool {
+        match self.restart {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => code != Some(0),
+            RestartPolicy::Never => false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SidecarsFile {
+    sidecar: Vec<SidecarConfig>,
+}
+
+/// The primary sidecar and the helpers, in file order
+pub struct Sidecars {
+    pub primary: SidecarConfig,
+    pub helpers: Vec<SidecarConfig>,
+}
+
+/// Parses the bundled sidecars.toml. A broken file is a build mistake, so
+/// this panics rather than starting without a backend.
+pub fn load() -> Sidecars {
+    parse(SIDECARS_TOML).unwrap_or_else(|err| panic!("Invalid sidecars.toml: {err}"))
+}
+
+fn parse(text: &str) -> Result<Sidecars, String> {
+    let file: SidecarsFile = toml::from_str(text).map_err(|err| err.to_string())?;
+    let (mut primary, helpers): (Vec<_>, Vec<_>) =
+        file.sidecar.into_iter().partition(|config| config.primary);
+    if primary.len() != 1 {
+        return Err(format!(
+            "Expected exactly one primary sidecar, found {}",
+            primary.len()
+        ));
+    }
+    Ok(Sidecars {
+        primary: primary.remove(0),
+        helpers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_file_has_the_backend_as_primary() {
+        let sidecars = load();
+        assert_eq!(sidecars.primary.name, "backend");
+        assert_eq!(sidecars.primary.health.as_deref(), Some("/health"));
+    }
+
+    #[test]
+    fn entries_are_split_into_primary_and_helpers() {
+        let sidecars = parse(
+            r#"
+            [[sidecar]]
+            name = "indexer"
+            args = ["--watch"]
+            env = { RUST_LOG = "info" }
+            health = "/ready"
+            restart = "on-failure"
+
+            [[sidecar]]
+            name = "backend"
+            primary = true
+
+            [[sidecar]]
+            name = "ocr"
+            restart = "never"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sidecars.primary.name, "backend");
+        assert!(sidecars.primary.args.is_empty());
+        assert!(sidecars.primary.restart == RestartPolicy::Always);
+
+        let names: Vec<_> = sidecars.helpers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["indexer", "ocr"]);
+        let indexer = &sidecars.helpers[0];
+        assert_eq!(indexer.args, ["--watch"]);
+        assert_eq!(
+            indexer.env.get("RUST_LOG").map(String::as_str),
+            Some("info")
+        );
+        assert_eq!(indexer.health.as_deref(), Some("/ready"));
+        assert!(indexer.restart == RestartPolicy::OnFailure);
+    }
+
+    #[test]
+    fn there_must_be_one_primary() {
+        let none = parse("[[sidecar]]\nname = \"ocr\"\n");
+        assert!(none.is_err_and(|err| err.contains("found 0")));
+        let two = parse(
+            "[[sidecar]]\nname = \"a\"\nprimary = true\n\
+             [[sidecar]]\nname = \"b\"\nprimary = true\n",
+        );
+        assert!(two.is_err_and(|err| err.contains("found 2")));
+    }
+
+    #[test]
+    fn typos_are_errors() {
+        let misspelled = "[[sidecar]]\nname = \"backend\"\nprimary = true\nhealht = \"/\"\n";
+        assert!(parse(misspelled).is_err());
+        let policy = "[[sidecar]]\nname = \"backend\"\nprimary = true\nrestart = \"sometimes\"\n";
+        assert!(parse(policy).is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn restart_policies() {
+        let mut config = load().primary;
+        assert!(config.should_restart(Some(0)) && config.should_restart(None));
+        config.restart = RestartPolicy::OnFailure;
+        assert!(!config.should_restart(Some(0)));
+        assert!(config.should_restart(Some(1)) && config.should_restart(None));
+        config.restart = RestartPolicy::Never;
+        assert!(!config.should_restart(Some(1)));
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::data_dir;
+use crate::settings::SettingsStore;
+
+const DICTIONARY_FILE: &str = "dictionary.txt";
+const MAX_WORD_LENGTH: usize = 64;
+
+/// Words the spellchecker accepts on top of the OS's dictionaries, kept in
+/// `dictionary.txt` in the data directory one per line
+pub struct Dictionary(Mutex<BTreeSet<String>>);
+
+pub fn init(app: &AppHandle) {
+    let mut words: BTreeSet<String> = dictionary_path(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| parse(&text))
+        .unwrap_or_default();
+    let before = words.len();
+    platform::sync(app, &mut words);
+    if words.len() != before {
+        save(app, &words);
+    }
+    app.manage(Dictionary(Mutex::new(words)));
+}
+
+fn dictionary_path(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::data_dir(app).map(|dir| dir.join(DICTIONARY_FILE))
+}
+
+fn parse(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn save(app: &AppHandle, words: &BTreeSet<String>) {
+    let Some(path) = dictionary_path(app) else {
+        return;
+    };
+    let text: String = words.iter().map(|word| format!("{word}\n")).collect();
+    if let Err(err) = fs::write(&path, text) {
+        tracing::warn!("Failed to save {}: {err}", path.display());
+    }
+}
+
+fn validate(word: &str) -> Result<(), String> {
+    if word.is_empty() || word.chars().count() > MAX_WORD_LENGTH {
+        return Err(format!(
+            "A word must be 1 to {MAX_WORD_LENGTH} characters long"
+        ));
+    }
+    if word.chars().any(char::is_whitespace) {
+        return Err(format!("{word:?} is more than one word"));
+    }
+    Ok(())
+}
+
+/// Sets `spellcheck` on the page as soon as it has a document. Every window
+/// that loads the frontend needs it.
+pub fn script(app: &AppHandle) -> String {
+    let enabled = app.state::<SettingsStore>().get().spellcheck;
+    format!(
+        "window.__SPELLCHECK__ = {enabled}; document.addEventListener(\"DOMContentLoaded\", () => {{ document.documentElement.spellcheck = window.__SPELLCHECK__; }});"
+    )
+}
+
+/// Turns the webview's own spellchecker on or off to match the settings
+pub fn configure(window: &WebviewWindow) {
+    let enabled = window.state::<SettingsStore>().get().spellcheck;
+    let result = window.with_webview(move |webview| platform::configure(webview, enabled));
+    if let Err(err) = result {
+        tracing::warn!("Failed to set spellchecking: {err}");
+    }
+}
+
+/// Applies the saved setting to every window, pages already loaded included
+pub fn apply(app: &AppHandle) {
+    let enabled = app.state::<SettingsStore>().get().spellcheck;
+    for window in app.webview_windows().values() {
+        configure(window);
+        let _ = window.eval(format!(
+            "window.__SPELLCHECK__ = {enabled}; document.documentElement.spellcheck = {enabled};"
+        ));
+    }
+}
+
+#[tauri::command]
+pub fn set_spellcheck(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings
+        .update(|settings| settings.spellcheck = enabled)
+        .map_err(|err| err.to_string())?;
+    apply(&app);
+    Ok(())
+}
+
+/// The custom dictionary, sorted
+#[tauri::command]
+pub fn get_dictionary_words(dictionary: State<'_, Dictionary>) -> Vec<String> {
+    dictionary.0.lock().unwrap().iter().cloned().collect()
+}
+
+/// Stops `word` from being marked as misspelled. On Windows this takes
+/// effect from the next launch.
+#[tauri::command]
+pub fn add_dictionary_word(
+    app: AppHandle,
+    dictionary: State<'_, Dictionary>,
+    word: String,
+) -> Result<(), String> {
+    let word = word.trim().to_string();
+    validate(&word)?;
+    let mut words = dictionary.0.lock().unwrap();
+    if words.insert(word.clone()) {
+        save(&app, &words);
+        platform::learn(&app, &word, &words);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_dictionary_word(
+    app: AppHandle,
+    dictionary: State<'_, Dictionary>,
+    word: String,
+) -> Result<(), String> {
+    let mut words = dictionary.0.lock().unwrap();
+    if words.remove(word.trim()) {
+        save(&app, &words);
+        platform::forget(&app, word.trim(), &words);
+    }
+    Ok(())
+}
+
+// WebView2 has no API for custom words, but Chromium reads them from this
+// file in the browser profile at startup and adds to it from the context
+// menu, so words are merged both ways
+#[cfg(windows)]
+mod platform {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::webview::PlatformWebview;
+    use tauri::{AppHandle, Manager};
+
+    use super::parse;
+
+    // Chromium checks the checksum line when there is one and falls back to
+    // a backup of the file when it doesn't match, so none is written
+    const CHECKSUM_PREFIX: &str = "checksum_v1";
+
+    fn chromium_path(app: &AppHandle) -> Option<PathBuf> {
+        app.path().app_local_data_dir().ok().map(|dir| {
+            dir.join("EBWebView")
+                .join("Default")
+                .join("Custom Dictionary.txt")
+        })
+    }
+
+    fn write(app: &AppHandle, words: &BTreeSet<String>) {
+        let Some(path) = chromium_path(app) else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let text: String = words.iter().map(|word| format!("{word}\n")).collect();
+        if let Err(err) = fs::write(&path, text) {
+            tracing::warn!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    pub fn sync(app: &AppHandle, words: &mut BTreeSet<String>) {
+        if let Some(text) = chromium_path(app).and_then(|path| fs::read_to_string(path).ok()) {
+            words.extend(
+                parse(&text)
+                    .into_iter()
+                    .filter(|word| !word.starts_with(CHECKSUM_PREFIX)),
+            );
+        }
+        write(app, words);
+    }
+
+    pub fn learn(app: &AppHandle, _word: &str, words: &BTreeSet<String>) {
+        write(app, words);
+    }
+
+    pub fn forget(app: &AppHandle, _word: &str, words: &BTreeSet<String>) {
+        write(app, words);
+    }
+
+    // The HTML `spellcheck` attribute is all WebView2 offers
+    pub fn configure(_webview: PlatformWebview, _enabled: bool) {}
+}
+
+// The words go into the user's spelling dictionary, which AppKit shares
+// between apps just as Learn Spelling in the context menu does
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::collections::BTreeSet;
+    use std::ffi::{c_char, CString};
+
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use tauri::webview::PlatformWebview;
+    use tauri::AppHandle;
+
+    fn with_word(word: &str, action: impl FnOnce(&AnyObject, &AnyObject)) {
+        let Ok(word) = CString::new(word) else {
+            return;
+        };
+        unsafe {
+            let checker: Option<Retained<AnyObject>> =
+                msg_send![class!(NSSpellChecker), sharedSpellChecker];
+            let ptr: *const c_char = word.as_ptr();
+            let word: Option<Retained<AnyObject>> =
+                msg_send![class!(NSString), stringWithUTF8String: ptr];
+            if let (Some(checker), Some(word)) = (checker, word) {
+                action(&checker, &word);
+            }
+        }
+    }
+
+    pub fn sync(app: &AppHandle, words: &mut BTreeSet<String>) {
+        for word in words.iter() {
+            learn(app, word, words);
+        }
+    }
+
+    pub fn learn(_app: &AppHandle, word: &str, _words: &BTreeSet<String>) {
+        with_word(word, |checker, word| unsafe {
+            let learned: bool = msg_send![checker, hasLearnedWord: word];
+            if !learned {
+                let _: () = msg_send![checker, learnWord: word];
+            }
+        });
+    }
+
+    pub fn forget(_app: &AppHandle, word: &str, _words: &BTreeSet<String>) {
+        with_word(word, |checker, word| unsafe {
+            let _: () = msg_send![checker, unlearnWord: word];
+        });
+    }
+
+    // The HTML `spellcheck` attribute is enough for WKWebView
+    pub fn configure(_webview: PlatformWebview, _enabled: bool) {}
+}
+
+// WebKitGTK checks spelling through Enchant, which keeps the user's added
+// words in a file per language shared with other GTK apps
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tauri::webview::PlatformWebview;
+    use tauri::{AppHandle, Manager};
+    use webkit2gtk::{WebContextExt, WebViewExt};
+
+    use super::parse;
+    use crate::locale;
+
+    fn enchant_path(app: &AppHandle) -> Option<PathBuf> {
+        let language = locale::system_tag(app).replace('-', "_");
+        app.path()
+            .config_dir()
+            .ok()
+            .map(|dir| dir.join("enchant").join(format!("{language}.dic")))
+    }
+
+    fn edit(app: &AppHandle, change: impl FnOnce(&mut BTreeSet<String>)) {
+        let Some(path) = enchant_path(app) else {
+            return;
+        };
+        let mut words = fs::read_to_string(&path)
+            .map(|text| parse(&text))
+            .unwrap_or_default();
+        change(&mut words);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let text: String = words.iter().map(|word| format!("{word}\n")).collect();
+        if let Err(err) = fs::write(&path, text) {
+            tracing::warn!("Failed to write {}: {err}", path.display());
+        }
+    }
+
+    pub fn sync(app: &AppHandle, words: &mut BTreeSet<String>) {
+        edit(app, |existing| existing.extend(words.iter().cloned()));
+    }
+
+    pub fn learn(app: &AppHandle, word: &str, _words: &BTreeSet<String>) {
+        edit(app, |existing| {
+            existing.insert(word.to_string());
+        });
+    }
+
+    pub fn forget(app: &AppHandle, word: &str, _words: &BTreeSet<String>) {
+        edit(app, |existing| {
+            existing.remove(word);
+        });
+    }
+
+    // Off by default in WebKitGTK, whatever the page asks for. With no
+    // languages set it checks in the user's own.
+    pub fn configure(webview: PlatformWebview, enabled: bool) {
+        if let Some(context) = webview.inner().context() {
+            context.set_spell_checking_enabled(enabled);
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use std::collections::BTreeSet;
+
+    use tauri::webview::PlatformWebview;
+    use tauri::AppHandle;
+
+    pub fn sync(_app: &AppHandle, _words: &mut BTreeSet<String>) {}
+
+    pub fn learn(_app: &AppHandle, _word: &str, _words: &BTreeSet<String>) {}
+
+    pub fn forget(_app: &AppHandle, _word: &str, _words: &BTreeSet<String>) {}
+
+    pub fn configure(_webview: PlatformWebview, _enabled: bool) {}
+}
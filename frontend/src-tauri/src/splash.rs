@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{
+    AppHandle, Emitter, Event, Listener, Manager, State, Webview, WebviewUrl, WebviewWindowBuilder,
+};
+
+use crate::main_window;
+use crate::settings::SettingsStore;
+use crate::startup;
+use crate::theme;
+
+pub const LABEL: &str = "splash";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stage {
+    SpawningBackend,
+    WaitingForHealth,
+    LoadingUi,
+}
+
+/// Startup progress. The main window is shown (and the splash closed) once
+/// the backend is healthy and the frontend has finished loading, whichever
+/// happens last.
+pub struct Splash {
+    stage: Mutex<Stage>,
+    backend_ready: AtomicBool,
+    ui_loaded: AtomicBool,
+    // Later page loads (reloads, navigation) must not show the window again
+    finished: AtomicBool,
+    // Launched to the tray: neither window is shown
+    show_main: bool,
+}
+
+/// Shows the splash window unless the app starts in the background
+pub fn init(app: &AppHandle, show: bool) {
+    app.manage(Splash {
+        stage: Mutex::new(Stage::SpawningBackend),
+        backend_ready: AtomicBool::new(false),
+        ui_loaded: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+        show_main: show,
+    });
+    if !show {
+        return;
+    }
+
+    let created = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("splash.html".into()))
+        .title(&app.package_info().name)
+        .inner_size(360.0, 200.0)
+        .resizable(false)
+        .decorations(false)
+        .skip_taskbar(true)
+        .center()
+        .initialization_script(theme::script(app))
+        .background_color(theme::background(
+            app.state::<SettingsStore>().get().theme_override,
+        ))
+        .build();
+    if let Err(err) = created {
+        tracing::warn!("Failed to create the splash window: {err}");
+    }
+
+    let handle = app.clone();
+    app.listen("backend-status", move |event| {
+        on_backend_status(&handle, event)
+    });
+}
+
+fn set_stage(app: &AppHandle, stage: Stage) {
+    *app.state::<Splash>().stage.lock().unwrap() = stage;
+    let _ = app.emit_to(LABEL, "splash-progress", stage);
+}
+
+// Once the process is up, the next wait is for it to answer health checks
+fn on_backend_status(app: &AppHandle, event: Event) {
+    if app.state::<Splash>().backend_ready.load(Ordering::SeqCst) {
+        return;
+    }
+    let Ok(status) = serde_json::from_str::<serde_json::Value>(event.payload()) else {
+        return;
+    };
+    let stage = match status["status"].as_str() {
+        Some("starting") | Some("restarting") => Stage::SpawningBackend,
+        Some("running") => Stage::WaitingForHealth,
+        _ => return,
+    };
+    set_stage(app, stage);
+}
+
+/// Called once the backend answers (or startup gave up waiting for it)
+pub fn backend_ready(app: &AppHandle) {
+    app.state::<Splash>()
+        .backend_ready
+        .store(true, Ordering::SeqCst);
+    set_stage(app, Stage::LoadingUi);
+    finish_if_ready(app);
+}
+
+/// Tracks when the main window's frontend has loaded
+pub fn on_page_load(webview: &Webview, payload: &PageLoadPayload<'_>) {
+    if webview.label() != main_window::LABEL || payload.event() != PageLoadEvent::Finished {
+        return;
+    }
+    let app = webview.app_handle();
+    startup::mark(app, "ui-loaded");
+    app.state::<Splash>()
+        .ui_loaded
+        .store(true, Ordering::SeqCst);
+    finish_if_ready(app);
+}
+
+fn finish_if_ready(app: &AppHandle) {
+    let splash = app.state::<Splash>();
+    if !splash.backend_ready.load(Ordering::SeqCst)
+        || !splash.ui_loaded.load(Ordering::SeqCst)
+        || splash.finished.swap(true, Ordering::SeqCst)
+    {
+        return;
+    }
+    startup::finish(app);
+    if splash.show_main {
+        if let Some(window) = app.get_webview_window(main_window::LABEL) {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.close();
+    }
+}
+
+#[tauri::command]
+pub fn get_splash_stage(splash: State<'_, Splash>) -> Stage {
+    *splash.stage.lock().unwrap()
+}
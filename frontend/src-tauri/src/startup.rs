@@ -0,0 +1,144 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Event, Listener, Manager};
+
+use crate::data_dir;
+
+const TRACE_FILE: &str = "startup-trace.json";
+
+// An entry in the Chrome trace event format, which chrome://tracing and
+// Perfetto open directly. `X` is a span, `i` a point in time.
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    /// Microseconds since the process started
+    ts: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u128>,
+    pid: u32,
+    tid: u32,
+}
+
+/// Timings of the startup stages for `--profile-startup`, written to
+/// `startup-trace.json` in the log folder once the app is up. Recording
+/// costs nothing when the flag isn't given.
+pub struct Startup {
+    began: Instant,
+    enabled: bool,
+    events: Mutex<Vec<TraceEvent>>,
+    finished: AtomicBool,
+    // How long it took to get ready, kept with or without the flag
+    ready: OnceLock<Duration>,
+}
+
+impl Startup {
+    /// Created first thing in `main`, so the trace covers plugin setup too
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            began: Instant::now(),
+            enabled,
+            events: Mutex::new(Vec::new()),
+            finished: AtomicBool::new(false),
+            ready: OnceLock::new(),
+        }
+    }
+
+    fn record(&self, name: &'static str, start: Duration, dur: Option<Duration>) {
+        if !self.enabled || self.finished.load(Ordering::SeqCst) {
+            return;
+        }
+        self.events.lock().unwrap().push(TraceEvent {
+            name,
+            ph: if dur.is_some() { "X" } else { "i" },
+            ts: start.as_micros(),
+            dur: dur.map(|dur| dur.as_micros()),
+            pid: std::process::id(),
+            tid: 0,
+        });
+    }
+}
+
+/// Marks when the backend process came up, which happens off the setup path
+pub fn init(app: &AppHandle) {
+    if !app.state::<Startup>().enabled {
+        return;
+    }
+    let handle = app.clone();
+    app.listen("backend-status", move |event| {
+        on_backend_status(&handle, event)
+    });
+}
+
+fn on_backend_status(app: &AppHandle, event: Event) {
+    let Ok(status) = serde_json::from_str::<serde_json::Value>(event.payload()) else {
+        return;
+    };
+    if status["status"].as_str() == Some("running") {
+        mark(app, "backend-running");
+    }
+}
+
+/// How long the app took to get ready to use, once it is
+pub fn ready_after(app: &AppHandle) -> Option<Duration> {
+    app.state::<Startup>().ready.get().copied()
+}
+
+/// How long the process has been running
+pub fn uptime(app: &AppHandle) -> Duration {
+    app.state::<Startup>().began.elapsed()
+}
+
+/// Runs `f` as the stage `name`
+pub fn span<T>(app: &AppHandle, name: &'static str, f: impl FnOnce() -> T) -> T {
+    let startup = app.state::<Startup>();
+    let start = startup.began.elapsed();
+    let result = f();
+    startup.record(name, start, Some(startup.began.elapsed() - start));
+    result
+}
+
+/// Records that `name` happened just now
+pub fn mark(app: &AppHandle, name: &'static str) {
+    let startup = app.state::<Startup>();
+    startup.record(name, startup.began.elapsed(), None);
+}
+
+/// Ends the trace once the app is ready to use and writes it out. Later
+/// calls, e.g. from a reload, do nothing.
+pub fn finish(app: &AppHandle) {
+    let startup = app.state::<Startup>();
+    let _ = startup.ready.set(startup.began.elapsed());
+    if !startup.enabled {
+        return;
+    }
+    mark(app, "ready");
+    if startup.finished.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let total = startup.began.elapsed();
+    let events = std::mem::take(&mut *startup.events.lock().unwrap());
+    let Some(dir) = data_dir::log_dir(app) else {
+        return;
+    };
+    let path = dir.join(TRACE_FILE);
+    let written = serde_json::to_string_pretty(&events)
+        .map_err(|err| err.to_string())
+        .and_then(|json| {
+            fs::create_dir_all(&dir)
+                .and_then(|()| fs::write(&path, json))
+                .map_err(|err| err.to_string())
+        });
+    match written {
+        Ok(()) => tracing::info!(
+            "Started in {}ms, trace written to {}",
+            total.as_millis(),
+            path.display()
+        ),
+        Err(err) => tracing::warn!("Failed to write the startup trace: {err}"),
+    }
+}
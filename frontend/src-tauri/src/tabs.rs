@@ -0,0 +1,258 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Window, WindowEvent};
+
+use crate::data_dir;
+use crate::lock;
+use crate::windows;
+
+const SESSION_FILE: &str = "tabs.json";
+/// Tabs are labelled windows, `tab-1`, `tab-2` and so on
+pub const LABEL_PREFIX: &str = "tab-";
+const DEFAULT_ROUTE: &str = "/";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tab {
+    label: String,
+    /// What it shows, e.g. `/email/123`, as of the last save
+    route: String,
+    title: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabInfo {
+    #[serde(flatten)]
+    tab: Tab,
+    focused: bool,
+}
+
+/// The open tabs in the order they were opened, saved to `tabs.json` in the
+/// config directory so the next launch can reopen them
+pub struct Tabs {
+    path: Option<PathBuf>,
+    open: Mutex<Vec<Tab>>,
+    // Windows closing because the app quits stay in the session
+    exiting: AtomicBool,
+}
+
+impl Tabs {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let open = self.open.lock().unwrap();
+        fs::write(path, serde_json::to_string_pretty(&*open)?)
+    }
+}
+
+/// Reopens the tabs of the last session. Runs once the main window exists,
+/// as on macOS tabs join it.
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(SESSION_FILE));
+    let saved: Vec<Tab> = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    app.manage(Tabs {
+        path,
+        open: Mutex::new(Vec::new()),
+        exiting: AtomicBool::new(false),
+    });
+
+    let tabs = app.state::<Tabs>();
+    for tab in saved
+        .into_iter()
+        .filter(|tab| tab.label.starts_with(LABEL_PREFIX))
+    {
+        if let Err(err) = open(app, &tabs, tab) {
+            tracing::warn!("Failed to restore a tab: {err}");
+        }
+    }
+}
+
+fn next_label(open: &[Tab]) -> String {
+    let last = open
+        .iter()
+        .filter_map(|tab| tab.label.strip_prefix(LABEL_PREFIX)?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("{LABEL_PREFIX}{}", last + 1)
+}
+
+fn open(app: &AppHandle, tabs: &Tabs, tab: Tab) -> Result<(), String> {
+    let window = windows::create(app, &tab.label, &tab.route, tab.title.clone(), None, None)?;
+    platform::join(app, &window);
+    tabs.open.lock().unwrap().push(tab);
+    save(app);
+    Ok(())
+}
+
+fn route_of(url: &tauri::Url) -> String {
+    let mut route = url.path().to_string();
+    if let Some(query) = url.query() {
+        route = format!("{route}?{query}");
+    }
+    if let Some(fragment) = url.fragment() {
+        route = format!("{route}#{fragment}");
+    }
+    route
+}
+
+// Single-page navigation doesn't reach Rust, so routes are read back from
+// the windows whenever the session is saved
+fn save(app: &AppHandle) {
+    let tabs = app.state::<Tabs>();
+    // Tabs are blank while locked, see `lock`, so the routes from before
+    // are kept
+    if !lock::is_locked(app) {
+        for tab in tabs.open.lock().unwrap().iter_mut() {
+            if let Some(route) = app
+                .get_webview_window(&tab.label)
+                .and_then(|window| window.url().ok())
+                .map(|url| route_of(&url))
+            {
+                tab.route = route;
+            }
+        }
+    }
+    if let Err(err) = tabs.save() {
+        tracing::warn!("Failed to save tabs: {err}");
+    }
+}
+
+/// Opens `route` in a new tab, the app's start page without one, and
+/// resolves with its label. On macOS it joins the main window's tab bar;
+/// elsewhere each tab is a window of its own.
+#[tauri::command]
+pub fn open_tab(
+    app: AppHandle,
+    tabs: State<'_, Tabs>,
+    route: Option<String>,
+    title: Option<String>,
+) -> Result<String, String> {
+    let label = next_label(&tabs.open.lock().unwrap());
+    let route = route.unwrap_or_else(|| DEFAULT_ROUTE.to_string());
+    open(
+        &app,
+        &tabs,
+        Tab {
+            label: label.clone(),
+            route,
+            title,
+        },
+    )?;
+    Ok(label)
+}
+
+#[tauri::command]
+pub fn close_tab(app: AppHandle, label: String) -> Result<(), String> {
+    if !label.starts_with(LABEL_PREFIX) {
+        return Err(format!("{label:?} is not a tab"));
+    }
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("No tab labelled {label:?}"))?;
+    window.close().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn list_tabs(app: AppHandle, tabs: State<'_, Tabs>) -> Vec<TabInfo> {
+    save(&app);
+    tabs.open
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|tab| TabInfo {
+            focused: app
+                .get_webview_window(&tab.label)
+                .and_then(|window| window.is_focused().ok())
+                .unwrap_or(false),
+            tab: tab.clone(),
+        })
+        .collect()
+}
+
+/// From the menu's New Tab
+pub fn open_default(app: &AppHandle) {
+    let Some(tabs) = app.try_state::<Tabs>() else {
+        return;
+    };
+    if let Err(err) = open_tab(app.clone(), tabs, None, None) {
+        tracing::warn!("Failed to open a tab: {err}");
+    }
+}
+
+/// Saves the session with the routes the tabs are on as the app quits,
+/// before their windows go
+pub fn on_exit_requested(app: &AppHandle) {
+    let Some(tabs) = app.try_state::<Tabs>() else {
+        return;
+    };
+    tabs.exiting.store(true, Ordering::Relaxed);
+    save(app);
+}
+
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if !window.label().starts_with(LABEL_PREFIX) || !matches!(event, WindowEvent::Destroyed) {
+        return;
+    }
+    let app = window.app_handle();
+    let Some(tabs) = app.try_state::<Tabs>() else {
+        return;
+    };
+    if tabs.exiting.load(Ordering::Relaxed) {
+        return;
+    }
+    tabs.open
+        .lock()
+        .unwrap()
+        .retain(|tab| tab.label != window.label());
+    save(app);
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::msg_send;
+    use objc2::runtime::AnyObject;
+    use tauri::{AppHandle, Manager, WebviewWindow};
+
+    use crate::main_window;
+
+    // NSWindowAbove
+    const ORDERED_ABOVE: isize = 1;
+
+    // Whether new windows open as tabs is otherwise up to the user's
+    // "Prefer tabs" system setting
+    pub fn join(app: &AppHandle, window: &WebviewWindow) {
+        let Some(main) = app.get_webview_window(main_window::LABEL) else {
+            return;
+        };
+        let (Ok(main), Ok(tab)) = (main.ns_window(), window.ns_window()) else {
+            return;
+        };
+        let (main, tab) = (main as usize, tab as usize);
+        let _ = app.run_on_main_thread(move || unsafe {
+            let main = &*(main as *const AnyObject);
+            let tab = &*(tab as *const AnyObject);
+            let _: () = msg_send![main, addTabbedWindow: tab, ordered: ORDERED_ABOVE];
+        });
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use tauri::{AppHandle, WebviewWindow};
+
+    pub fn join(_app: &AppHandle, _window: &WebviewWindow) {}
+}
@@ -0,0 +1,289 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::connectivity;
+use crate::data_dir;
+use crate::metrics::IpcTimer;
+use crate::proxy;
+use crate::settings::SettingsStore;
+
+const DATABASE_FILE: &str = "telemetry.db";
+const SUBMIT_URL: &str = "https://hamba.app/api/telemetry";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 100;
+// Oldest events are dropped past this, so a queue that is never sent stays small
+const MAX_QUEUED: i64 = 5000;
+const MAX_NAME_LEN: usize = 64;
+const MAX_PROPERTIES_LEN: usize = 2048;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        properties TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Event {
+    name: String,
+    properties: Value,
+    /// Milliseconds since the Unix epoch
+    created_at: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Batch<'a> {
+    /// Random and unrelated to any account, so events from one install can
+    /// be grouped without knowing whose they are
+    install_id: &'a str,
+    app_version: &'a str,
+    os: &'static str,
+    arch: &'static str,
+    events: Vec<Event>,
+}
+
+/// Usage events queued on disk. Recording never touches the network; batches
+/// only leave the machine while the user has opted in and is online.
+pub struct Telemetry {
+    db: Mutex<Option<Connection>>,
+    // Held while flushing so the same events are never sent twice
+    flushing: tokio::sync::Mutex<()>,
+}
+
+pub fn init(app: &AppHandle) {
+    let db = data_dir::data_dir(app).and_then(|dir| match open(&dir) {
+        Ok(db) => Some(db),
+        Err(err) => {
+            tracing::warn!("Telemetry queue is unavailable: {err}");
+            None
+        }
+    });
+    app.manage(Telemetry {
+        db: Mutex::new(db),
+        flushing: tokio::sync::Mutex::new(()),
+    });
+    record(app, "app.started", Value::Object(Default::default()));
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            if !app.state::<SettingsStore>().get().telemetry || !connectivity::is_online(&app) {
+                continue;
+            }
+            if let Err(err) = flush(&app).await {
+                tracing::debug!("Failed to send telemetry: {err}");
+            }
+        }
+    });
+}
+
+fn open(dir: &Path) -> rusqlite::Result<Connection> {
+    let db = Connection::open(dir.join(DATABASE_FILE))?;
+    db.execute_batch(SCHEMA)?;
+    Ok(db)
+}
+
+/// Queues an event. `properties` must be a small JSON object of anonymous
+/// values such as counts or feature names, never mail content or addresses.
+pub fn record(app: &AppHandle, name: &str, properties: Value) {
+    if let Err(err) = try_record(app, name, properties) {
+        tracing::debug!("Dropped telemetry event {name:?}: {err}");
+    }
+}
+
+fn try_record(app: &AppHandle, name: &str, properties: Value) -> Result<(), String> {
+    let valid_name = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.');
+    if !valid_name {
+        return Err(format!("Invalid event name {name:?}"));
+    }
+    if !properties.is_object() {
+        return Err("Event properties must be an object".to_string());
+    }
+    let properties = properties.to_string();
+    if properties.len() > MAX_PROPERTIES_LEN {
+        return Err("Event properties are too large".to_string());
+    }
+
+    let telemetry = app.state::<Telemetry>();
+    let db = telemetry.db.lock().unwrap();
+    let db = db.as_ref().ok_or("Telemetry queue is unavailable")?;
+    db.execute(
+        "INSERT INTO events (name, properties, created_at) VALUES (?1, ?2, ?3)",
+        params![name, properties, now()],
+    )
+    .and_then(|_| {
+        db.execute(
+            "DELETE FROM events WHERE id <= (SELECT MAX(id) FROM events) - ?1",
+            params![MAX_QUEUED],
+        )
+    })
+    .map(|_| ())
+    .map_err(|err| err.to_string())
+}
+
+// Sends the queue in batches, deleting each once the server has it.
+// Returns how many events were sent.
+async fn flush(app: &AppHandle) -> Result<usize, String> {
+    let telemetry = app.state::<Telemetry>();
+    let _guard = telemetry.flushing.lock().await;
+    let install_id = install_id(&telemetry)?;
+    let app_version = app.package_info().version.to_string();
+    let client = proxy::client(&proxy::resolve(app))?;
+
+    let mut sent = 0;
+    loop {
+        let (last_id, events) = next_batch(&telemetry)?;
+        if events.is_empty() {
+            return Ok(sent);
+        }
+        let count = events.len();
+        let batch = Batch {
+            install_id: &install_id,
+            app_version: &app_version,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            events,
+        };
+        client
+            .post(SUBMIT_URL)
+            .timeout(SUBMIT_TIMEOUT)
+            .json(&batch)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?;
+
+        with_db(&telemetry, |db| {
+            db.execute("DELETE FROM events WHERE id <= ?1", params![last_id])
+                .map(|_| ())
+        })?;
+        sent += count;
+    }
+}
+
+fn next_batch(telemetry: &Telemetry) -> Result<(i64, Vec<Event>), String> {
+    with_db(telemetry, |db| {
+        let mut statement =
+            db.prepare("SELECT id, name, properties, created_at FROM events ORDER BY id LIMIT ?1")?;
+        let rows = statement.query_map(params![BATCH_SIZE], |row| {
+            let properties: String = row.get(2)?;
+            Ok((
+                row.get::<_, i64>(0)?,
+                Event {
+                    name: row.get(1)?,
+                    properties: serde_json::from_str(&properties).unwrap_or(Value::Null),
+                    created_at: row.get(3)?,
+                },
+            ))
+        })?;
+        let mut last_id = 0;
+        let mut events = Vec::new();
+        for row in rows {
+            let (id, event) = row?;
+            last_id = id;
+            events.push(event);
+        }
+        Ok((last_id, events))
+    })
+}
+
+// Created on first use, so an install that never opts in never gets one
+fn install_id(telemetry: &Telemetry) -> Result<String, String> {
+    with_db(telemetry, |db| {
+        let existing = db
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'install_id'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+        let mut bytes = [0u8; 16];
+        let _ = getrandom::fill(&mut bytes);
+        let id = hex::encode(bytes);
+        db.execute(
+            "INSERT INTO meta (key, value) VALUES ('install_id', ?1)",
+            params![id],
+        )?;
+        Ok(id)
+    })
+}
+
+fn with_db<T>(
+    telemetry: &Telemetry,
+    f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let db = telemetry.db.lock().unwrap();
+    let db = db.as_ref().ok_or("Telemetry queue is unavailable")?;
+    f(db).map_err(|err| err.to_string())
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[tauri::command]
+pub fn record_telemetry_event(app: AppHandle, name: String, properties: Option<Value>) {
+    record(
+        &app,
+        &name,
+        properties.unwrap_or_else(|| Value::Object(Default::default())),
+    );
+}
+
+/// Opting out also discards the queue and the install id, so nothing
+/// recorded before is sent if the user opts in again later
+#[tauri::command]
+pub fn set_telemetry_enabled(
+    settings: State<'_, SettingsStore>,
+    telemetry: State<'_, Telemetry>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings
+        .update(|settings| settings.telemetry = enabled)
+        .map_err(|err| err.to_string())?;
+    if !enabled {
+        with_db(&telemetry, |db| {
+            db.execute_batch("DELETE FROM events; DELETE FROM meta;")
+        })?;
+    }
+    Ok(())
+}
+
+/// Sends everything queued now rather than at the next interval. Resolves
+/// with the number of events sent.
+#[tauri::command]
+pub async fn flush_telemetry(_timer: IpcTimer, app: AppHandle) -> Result<usize, String> {
+    if !app.state::<SettingsStore>().get().telemetry {
+        return Err("Telemetry is turned off".to_string());
+    }
+    if !connectivity::is_online(&app) {
+        return Err("Offline, events stay queued until the connection is back".to_string());
+    }
+    flush(&app).await
+}
@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::window::Color;
+use tauri::{AppHandle, Emitter, Manager, State, Theme, Window, WindowEvent};
+
+use crate::main_window;
+use crate::settings::SettingsStore;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeInfo {
+    /// What the app is actually showing
+    theme: Theme,
+    /// What the OS asks for, `None` while an override hides it
+    system: Option<Theme>,
+    #[serde(rename = "override")]
+    override_theme: Option<Theme>,
+}
+
+/// The OS theme as last reported by the main window
+#[derive(Default)]
+pub struct SystemTheme(Mutex<Option<Theme>>);
+
+/// Must run after the main window exists, which is where the OS theme is
+/// read from before any override is applied on top.
+pub fn init(app: &AppHandle) {
+    let override_theme = app.state::<SettingsStore>().get().theme_override;
+    let system = app
+        .get_webview_window(main_window::LABEL)
+        .filter(|_| override_theme.is_none())
+        .and_then(|window| window.theme().ok());
+    app.manage(SystemTheme(Mutex::new(system)));
+    app.set_theme(override_theme);
+}
+
+fn info(app: &AppHandle) -> ThemeInfo {
+    let override_theme = app.state::<SettingsStore>().get().theme_override;
+    let system = *app.state::<SystemTheme>().0.lock().unwrap();
+    ThemeInfo {
+        // The frontend defaults to dark as well
+        theme: override_theme.or(system).unwrap_or(Theme::Dark),
+        system,
+        override_theme,
+    }
+}
+
+/// Window background matching `--bg-primary` in app.css, so nothing flashes
+/// white before the page paints
+pub fn background(theme: Option<Theme>) -> Color {
+    match theme {
+        Some(Theme::Light) => Color(0xff, 0xff, 0xff, 0xff),
+        _ => Color(0x1a, 0x1a, 0x1a, 0xff),
+    }
+}
+
+/// Sets `window.__THEME__` to the override, for app.html to apply before
+/// the first paint
+pub fn script(app: &AppHandle) -> String {
+    let override_theme = app.state::<SettingsStore>().get().theme_override;
+    format!(
+        "window.__THEME__ = {};",
+        serde_json::to_string(&override_theme).unwrap_or_else(|_| "null".to_string())
+    )
+}
+
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != main_window::LABEL {
+        return;
+    }
+    if let WindowEvent::ThemeChanged(theme) = event {
+        let app = window.app_handle();
+        // With an override in place this is just the override being applied
+        if app.state::<SettingsStore>().get().theme_override.is_some() {
+            return;
+        }
+        *app.state::<SystemTheme>().0.lock().unwrap() = Some(*theme);
+        let _ = app.emit("theme-changed", info(app));
+    }
+}
+
+#[tauri::command]
+pub fn get_system_theme(app: AppHandle) -> ThemeInfo {
+    info(&app)
+}
+
+/// Forces light or dark for every window, or `None` to follow the OS again
+#[tauri::command]
+pub fn set_theme_override(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    theme: Option<Theme>,
+) -> Result<ThemeInfo, String> {
+    settings
+        .update(|settings| settings.theme_override = theme)
+        .map_err(|err| err.to_string())?;
+    Ok(apply(&app))
+}
+
+/// Applies the saved override to every window and emits `theme-changed`
+pub fn apply(app: &AppHandle) -> ThemeInfo {
+    let theme = app.state::<SettingsStore>().get().theme_override;
+    app.set_theme(theme);
+    for window in app.webview_windows().values() {
+        let _ = window.set_background_color(Some(background(theme)));
+    }
+
+    let info = info(app);
+    let _ = app.emit("theme-changed", info);
+    info
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rcgen::{CertificateParams, KeyPair};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::data_dir;
+use crate::lan;
+use crate::sidecar::Sidecar;
+
+const TLS_DIR: &str = "tls";
+const CERT_FILE: &str = "localhost.pem";
+const KEY_FILE: &str = "localhost-key.pem";
+// What the certificate covers, one name or address per line
+const NAMES_FILE: &str = "localhost.names";
+const LOCAL_NAMES: &[&str] = &["localhost", "127.0.0.1"];
+const VALIDITY: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+// Certificates are replaced on launch once they are this old, well before
+// they expire, so an app left running for weeks never ends up with a dead one
+const ROTATE_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+
+/// Only webviews that can be told to trust a single self-signed certificate
+/// get TLS. WKWebView has no such hook in Tauri, so macOS stays on plain HTTP.
+pub const SUPPORTED: bool = cfg!(any(target_os = "linux", windows));
+
+/// This install's self-signed `localhost` certificate for the backend
+pub struct Tls {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub cert_pem: String,
+    /// Host names and addresses it's valid for
+    pub names: Vec<String>,
+}
+
+/// Loads the certificate, generating a new one if there is none yet, the
+/// current one is due for rotation or it doesn't cover this machine's LAN
+/// address. That address is always included, so turning LAN access on
+/// doesn't swap out the certificate the webviews trust.
+pub fn load_or_create(app: &AppHandle) -> io::Result<Tls> {
+    let dir = data_dir::data_dir(app)
+        .ok_or_else(|| io::Error::other("No data directory available"))?
+        .join(TLS_DIR);
+    let cert_path = dir.join(CERT_FILE);
+    let key_path = dir.join(KEY_FILE);
+    let names_path = dir.join(NAMES_FILE);
+
+    let mut wanted: Vec<String> = LOCAL_NAMES.iter().map(|name| name.to_string()).collect();
+    if let Ok(address) = lan::lan_address() {
+        wanted.push(address.to_string());
+    }
+    let covered =
+        read_names(&names_path).is_some_and(|names| wanted.iter().all(|name| names.contains(name)));
+    if !is_fresh(&cert_path) || !key_path.is_file() || !covered {
+        tracing::info!("Generating a new certificate for the backend");
+        generate(&dir, &cert_path, &key_path, &wanted)?;
+        fs::write(&names_path, wanted.join("\n"))?;
+    }
+    Ok(Tls {
+        cert_pem: fs::read_to_string(&cert_path)?,
+        cert_path,
+        key_path,
+        names: read_names(&names_path).unwrap_or(wanted),
+    })
+}
+
+fn read_names(path: &Path) -> Option<Vec<String>> {
+    let contents = fs::read_to_string(path).ok()?;
+    Some(contents.lines().map(str::to_string).collect())
+}
+
+fn is_fresh(cert_path: &Path) -> bool {
+    fs::metadata(cert_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ROTATE_AFTER)
+}
+
+// Addresses among `names` become IP address entries
+fn generate(dir: &Path, cert_path: &Path, key_path: &Path, names: &[String]) -> io::Result<()> {
+    let mut params = CertificateParams::new(names.to_vec()).map_err(io::Error::other)?;
+    let now = SystemTime::now();
+    params.not_before = now.into();
+    params.not_after = (now + VALIDITY).into();
+    let key = KeyPair::generate().map_err(io::Error::other)?;
+    let cert = params.self_signed(&key).map_err(io::Error::other)?;
+
+    fs::create_dir_all(dir)?;
+    write_private(key_path, &key.serialize_pem())?;
+    fs::write(cert_path, cert.pem())
+}
+
+// Only this user can read the key
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?
+            .write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    fs::write(path, contents)
+}
+
+/// Client config for connections that trust only this certificate
+pub fn client_config(tls: &Tls) -> Result<rustls::ClientConfig, String> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::CertificateDer;
+
+    let cert =
+        CertificateDer::from_pem_slice(tls.cert_pem.as_bytes()).map_err(|err| err.to_string())?;
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert).map_err(|err| err.to_string())?;
+    let config = rustls::ClientConfig::builder_with_provider(
+        rustls::crypto::ring::default_provider().into(),
+    )
+    .with_safe_default_protocol_versions()
+    .map_err(|err| err.to_string())?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(config)
+}
+
+/// Makes `window`'s webview accept the backend's certificate, if it uses
+/// one. Needed for every window that loads the frontend.
+pub fn trust_backend(window: &WebviewWindow) {
+    let sidecar = window.state::<Sidecar>();
+    let Some(tls) = sidecar.tls() else {
+        return;
+    };
+    let pem = tls.cert_pem.clone();
+    let result = window.with_webview(move |webview| platform::trust(webview, pem));
+    if let Err(err) = result {
+        tracing::warn!("Failed to trust the backend certificate: {err}");
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::gio::TlsCertificate;
+    use webkit2gtk::{WebContextExt, WebViewExt};
+
+    pub fn trust(webview: PlatformWebview, pem: String) {
+        let Some(context) = webview.inner().context() else {
+            return;
+        };
+        match TlsCertificate::from_pem(&pem) {
+            Ok(cert) => context.allow_tls_certificate_for_host(&cert, "localhost"),
+            Err(err) => tracing::warn!("Invalid backend certificate: {err}"),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2_14, COREWEBVIEW2_SERVER_CERTIFICATE_ERROR_ACTION_ALWAYS_ALLOW,
+    };
+    use webview2_com::{take_pwstr, ServerCertificateErrorDetectedEventHandler};
+    use windows_core::{Interface, PWSTR};
+
+    // WebView2 can't be given a trusted certificate up front, so instead the
+    // certificate error is overridden when it is exactly ours
+    pub fn trust(webview: PlatformWebview, pem: String) {
+        let handler =
+            ServerCertificateErrorDetectedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                unsafe {
+                    let mut encoded = PWSTR::null();
+                    args.ServerCertificate()?.ToPemEncoding(&mut encoded)?;
+                    if take_pwstr(encoded).trim() == pem.trim() {
+                        args.SetAction(COREWEBVIEW2_SERVER_CERTIFICATE_ERROR_ACTION_ALWAYS_ALLOW)?;
+                    }
+                }
+                Ok(())
+            }));
+
+        let result = unsafe {
+            webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_14>())
+                .and_then(|core| {
+                    let mut token = 0;
+                    core.add_ServerCertificateErrorDetected(&handler, &mut token)
+                })
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to trust the backend certificate: {err}");
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use tauri::webview::PlatformWebview;
+
+    pub fn trust(_webview: PlatformWebview, _pem: String) {}
+}
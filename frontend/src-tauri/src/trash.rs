@@ -0,0 +1,295 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::command::{CommandError, CommandResult};
+use crate::data_dir;
+use crate::disk;
+use crate::metrics::IpcTimer;
+use crate::settings::SettingsStore;
+use crate::sidecar;
+
+/// In the shared data folder, so backups leave it alone
+pub const TRASH_DIR: &str = "trash";
+const ENTRY_FILE: &str = "entry.json";
+const FILES_DIR: &str = "files";
+const RETENTION_RANGE: std::ops::RangeInclusive<u64> = 1..=365;
+// How often entries past the retention are looked for
+const PURGE_TICK: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// One deletion, with the paths it took in the order they are stored under
+/// `files/`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    id: String,
+    reason: String,
+    /// Seconds since the Unix epoch
+    deleted_at: u64,
+    items: Vec<PathBuf>,
+    #[serde(skip_deserializing)]
+    size: u64,
+}
+
+/// Where files go instead of being deleted by the shell, e.g. the data a
+/// backup restore replaces. Entries stay for `trash_retention_days`, then
+/// are purged for good.
+#[derive(Default)]
+pub struct Trash(Mutex<()>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(Trash::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let handle = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || purge(&handle)).await;
+            tokio::time::sleep(PURGE_TICK).await;
+        }
+    });
+}
+
+pub fn validate_retention(days: u64) -> Result<(), String> {
+    if !RETENTION_RANGE.contains(&days) {
+        return Err(format!(
+            "The trash must keep files between {} and {} days",
+            RETENTION_RANGE.start(),
+            RETENTION_RANGE.end()
+        ));
+    }
+    Ok(())
+}
+
+/// Purges what the new retention no longer covers
+pub fn apply(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || purge(&app));
+}
+
+fn dir(app: &AppHandle) -> Option<PathBuf> {
+    data_dir::shared_data_dir(app).map(|dir| dir.join(TRASH_DIR))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+// A rename where it can be; profiles may have folders on another drive
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(err) = copy_all(from, to) {
+                let _ = remove(to);
+                return Err(err);
+            }
+            remove(from)
+        }
+        result => result,
+    }
+}
+
+/// Moves `paths` to a new trash entry instead of deleting them, returning
+/// its id, or `None` when none of them exist
+pub fn move_to_trash(
+    app: &AppHandle,
+    reason: &str,
+    paths: &[PathBuf],
+) -> io::Result<Option<String>> {
+    let _guard = app.state::<Trash>().inner().0.lock().unwrap();
+    put(app, reason, paths)
+}
+
+fn put(app: &AppHandle, reason: &str, paths: &[PathBuf]) -> io::Result<Option<String>> {
+    let paths: Vec<&PathBuf> = paths.iter().filter(|path| exists(path)).collect();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let trash = dir(app).ok_or_else(|| io::Error::other("No data directory available"))?;
+    let mut bytes = [0u8; 4];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    let deleted_at = now();
+    let id = format!("{deleted_at}-{}", hex::encode(bytes));
+    let entry_dir = trash.join(&id);
+    fs::create_dir_all(entry_dir.join(FILES_DIR))?;
+
+    let mut entry = TrashEntry {
+        id: id.clone(),
+        reason: reason.to_string(),
+        deleted_at,
+        items: Vec::new(),
+        size: 0,
+    };
+    // What was moved before a failure is still written down, so it can be
+    // restored like the rest
+    let mut result = Ok(());
+    for path in paths {
+        let stored = entry_dir
+            .join(FILES_DIR)
+            .join(entry.items.len().to_string());
+        if let Err(err) = move_path(path, &stored) {
+            result = Err(err);
+            break;
+        }
+        entry.items.push(path.clone());
+    }
+    fs::write(
+        entry_dir.join(ENTRY_FILE),
+        serde_json::to_vec_pretty(&entry)?,
+    )?;
+    result?;
+    tracing::info!("Moved {} items to the trash ({reason})", entry.items.len());
+    Ok(Some(id))
+}
+
+fn read_entry(entry_dir: &Path) -> Option<TrashEntry> {
+    let text = fs::read(entry_dir.join(ENTRY_FILE)).ok()?;
+    let mut entry: TrashEntry = serde_json::from_slice(&text).ok()?;
+    entry.size = disk::size_of(&entry_dir.join(FILES_DIR));
+    Some(entry)
+}
+
+/// Entries in the trash, newest first
+fn list(app: &AppHandle) -> Vec<TrashEntry> {
+    let Some(dir) = dir(app) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<TrashEntry> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| read_entry(&entry.path()))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    entries
+}
+
+// Looked up in the listing so an id can't point outside the trash
+fn entry_dir(app: &AppHandle, id: &str) -> CommandResult<PathBuf> {
+    let dir = dir(app).ok_or("No data directory available")?;
+    if !list(app).iter().any(|entry| entry.id == id) {
+        return Err(CommandError::invalid("id", "No such item in the trash"));
+    }
+    Ok(dir.join(id))
+}
+
+fn purge(app: &AppHandle) {
+    let Some(trash) = app.try_state::<Trash>() else {
+        return;
+    };
+    let _guard = trash.0.lock().unwrap();
+    let days = app.state::<SettingsStore>().get().trash_retention_days;
+    let cutoff = now().saturating_sub(days * 24 * 60 * 60);
+    let Some(dir) = dir(app) else {
+        return;
+    };
+    for entry in list(app).iter().filter(|entry| entry.deleted_at < cutoff) {
+        match fs::remove_dir_all(dir.join(&entry.id)) {
+            Ok(()) => tracing::info!("Purged {} from the trash", entry.id),
+            Err(err) => tracing::warn!("Failed to purge {} from the trash: {err}", entry.id),
+        }
+    }
+}
+
+fn restore(app: &AppHandle, id: &str) -> CommandResult<()> {
+    let _guard = app.state::<Trash>().inner().0.lock().unwrap();
+    let entry_dir = entry_dir(app, id)?;
+    let entry = read_entry(&entry_dir).ok_or("The trash entry can't be read")?;
+
+    // The backend can't have its data moved from under it
+    let data = data_dir::data_dir(app);
+    let in_data = entry
+        .items
+        .iter()
+        .any(|path| data.as_deref().is_some_and(|data| path.starts_with(data)));
+    let was_running = in_data && !app.state::<sidecar::Sidecar>().is_stopped();
+    if in_data {
+        sidecar::stop(app);
+    }
+
+    let result = (|| -> io::Result<()> {
+        // Whatever took their place goes to the trash in turn, so this can
+        // be undone too
+        put(app, &format!("Replaced when restoring {id}"), &entry.items)?;
+        for (index, original) in entry.items.iter().enumerate() {
+            if let Some(parent) = original.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            move_path(&entry_dir.join(FILES_DIR).join(index.to_string()), original)?;
+        }
+        fs::remove_dir_all(&entry_dir)
+    })();
+    if was_running {
+        sidecar::start(app);
+    }
+    result?;
+    tracing::info!("Restored {id} from the trash");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_trash(app: AppHandle) -> Vec<TrashEntry> {
+    list(&app)
+}
+
+/// Puts an entry's files back where they were, stopping the backend while
+/// its data is moved. Anything there now goes to the trash first.
+#[tauri::command]
+pub async fn restore_from_trash(_timer: IpcTimer, app: AppHandle, id: String) -> CommandResult<()> {
+    tauri::async_runtime::spawn_blocking(move || restore(&app, &id))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+/// Deletes an entry for good
+#[tauri::command]
+pub fn delete_from_trash(app: AppHandle, id: String) -> CommandResult<()> {
+    let _guard = app.state::<Trash>().inner().0.lock().unwrap();
+    fs::remove_dir_all(entry_dir(&app, &id)?)?;
+    Ok(())
+}
+
+/// Deletes every entry for good
+#[tauri::command]
+pub fn empty_trash(app: AppHandle) -> CommandResult<()> {
+    let _guard = app.state::<Trash>().inner().0.lock().unwrap();
+    let Some(dir) = dir(&app) else {
+        return Ok(());
+    };
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
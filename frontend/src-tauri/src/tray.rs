@@ -0,0 +1,264 @@
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tauri::menu::{IsMenuItem, Menu, MenuEvent, MenuItem, MenuItemKind, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Event, Listener, Manager, State, Window, WindowEvent, Wry};
+
+use crate::close_guard;
+use crate::deep_link;
+use crate::kiosk;
+use crate::main_window;
+use crate::settings::SettingsStore;
+use crate::sidecar::{self, Sidecar};
+use crate::updater;
+
+pub const ID: &str = "main";
+
+const OPEN: &str = "open";
+const TOGGLE_BACKEND: &str = "toggle-backend";
+const RESTART_BACKEND: &str = "restart-backend";
+const CHECK_FOR_UPDATES: &str = "check-for-updates";
+const QUIT: &str = "quit";
+// Followed by the item's index in `Tray::links`
+const STATUS_ITEM: &str = "status-item:";
+const MAX_STATUS_ITEMS: usize = 5;
+const SCHEME: &str = "hamba://";
+const TOOLTIP: &str = "Hamba";
+
+pub struct Tray {
+    menu: Menu<Wry>,
+    toggle_backend: MenuItem<Wry>,
+    /// What the backend's last `tray` message added to the menu. Held while
+    /// the menu changes, so updates don't interleave.
+    status_entries: Mutex<Vec<MenuItemKind<Wry>>>,
+    /// Kept apart so clicks don't wait for the menu to change
+    status_links: Mutex<Vec<String>>,
+}
+
+/// Sent by `notifyDesktopTray` on the backend
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TrayStatus {
+    title: String,
+    tooltip: String,
+    items: Vec<StatusItem>,
+}
+
+#[derive(Deserialize)]
+struct StatusItem {
+    title: String,
+    /// A `hamba://` link
+    url: String,
+}
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let open = MenuItem::with_id(app, OPEN, "Open Hamba", true, None::<&str>)?;
+    let toggle_backend =
+        MenuItem::with_id(app, TOGGLE_BACKEND, "Pause backend", true, None::<&str>)?;
+    let restart_backend =
+        MenuItem::with_id(app, RESTART_BACKEND, "Restart backend", true, None::<&str>)?;
+    let check_for_updates = MenuItem::with_id(
+        app,
+        CHECK_FOR_UPDATES,
+        "Check for updates…",
+        updater::is_configured(app),
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, QUIT, "Quit Hamba", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &open,
+            &PredefinedMenuItem::separator(app)?,
+            &toggle_backend,
+            &restart_backend,
+            &check_for_updates,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(ID)
+        .tooltip(TOOLTIP)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(on_tray_icon_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    app.manage(Tray {
+        menu,
+        toggle_backend,
+        status_entries: Mutex::default(),
+        status_links: Mutex::default(),
+    });
+
+    let handle = app.clone();
+    app.listen("backend-status", move |event| {
+        on_backend_status(&handle, event)
+    });
+    Ok(())
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        OPEN => main_window::focus(app),
+        TOGGLE_BACKEND => toggle_backend(app),
+        RESTART_BACKEND => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                sidecar::restart(&app).await;
+            });
+        }
+        CHECK_FOR_UPDATES => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = updater::check(&app).await {
+                    tracing::warn!("Update check failed: {err}");
+                }
+            });
+        }
+        QUIT => close_guard::quit(app),
+        id => {
+            let Some(index) = id.strip_prefix(STATUS_ITEM) else {
+                return;
+            };
+            let tray = app.state::<Tray>();
+            let link = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| tray.status_links.lock().unwrap().get(index).cloned());
+            if let Some(link) = link {
+                deep_link::open(app, vec![link]);
+            }
+        }
+    }
+}
+
+fn on_tray_icon_event(tray: &TrayIcon, event: TrayIconEvent) {
+    if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    {
+        main_window::focus(tray.app_handle());
+    }
+}
+
+fn toggle_backend(app: &AppHandle) {
+    let app = app.clone();
+    // Stopping waits for the backend to exit, so keep it off the main thread
+    tauri::async_runtime::spawn_blocking(move || {
+        if app.state::<Sidecar>().is_stopped() {
+            sidecar::start(&app);
+        } else {
+            sidecar::stop(&app);
+        }
+    });
+}
+
+// Keeps the pause/resume item in sync however the backend was stopped or started
+fn on_backend_status(app: &AppHandle, event: Event) {
+    let Ok(status) = serde_json::from_str::<serde_json::Value>(event.payload()) else {
+        return;
+    };
+    let label = match status["status"].as_str() {
+        Some("stopped") => {
+            // Nothing updates the counts while the backend is stopped
+            if let Err(err) = show_status(app, TrayStatus::default()) {
+                tracing::debug!("Failed to clear the tray status: {err}");
+            }
+            "Resume backend"
+        }
+        Some("starting") => "Pause backend",
+        _ => return,
+    };
+    let _ = app.state::<Tray>().toggle_backend.set_text(label);
+}
+
+/// Shows the counts and items of the backend's `tray` messages in the tray
+/// icon's title, tooltip and menu
+pub fn on_backend_message(app: &AppHandle, message: &Map<String, Value>) {
+    if message.get("type").and_then(Value::as_str) != Some("tray") {
+        return;
+    }
+    let status = match TrayStatus::deserialize(message) {
+        Ok(status) => status,
+        Err(err) => {
+            tracing::debug!("Ignoring invalid tray status: {err}");
+            return;
+        }
+    };
+    if let Err(err) = show_status(app, status) {
+        tracing::warn!("Failed to update the tray status: {err}");
+    }
+}
+
+fn show_status(app: &AppHandle, status: TrayStatus) -> tauri::Result<()> {
+    let (Some(icon), Some(tray)) = (app.tray_by_id(ID), app.try_state::<Tray>()) else {
+        return Ok(());
+    };
+    let tooltip = if status.tooltip.is_empty() {
+        TOOLTIP
+    } else {
+        &status.tooltip
+    };
+    icon.set_tooltip(Some(tooltip))?;
+    // Only macOS and some Linux panels show a title, next to the icon
+    icon.set_title((!status.title.is_empty()).then_some(&status.title))?;
+
+    let mut entries = tray.status_entries.lock().unwrap();
+    for entry in entries.drain(..) {
+        tray.menu.remove(&entry)?;
+    }
+
+    let items: Vec<StatusItem> = status
+        .items
+        .into_iter()
+        .filter(|item| item.url.starts_with(SCHEME))
+        .take(MAX_STATUS_ITEMS)
+        .collect();
+    for (index, item) in items.iter().enumerate() {
+        let id = format!("{STATUS_ITEM}{index}");
+        entries.push(MenuItem::with_id(app, id, &item.title, true, None::<&str>)?.kind());
+    }
+    if !entries.is_empty() {
+        entries.push(PredefinedMenuItem::separator(app)?.kind());
+    }
+    *tray.status_links.lock().unwrap() = items.into_iter().map(|item| item.url).collect();
+    let items: Vec<&dyn IsMenuItem<Wry>> = entries
+        .iter()
+        .map(|entry| entry as &dyn IsMenuItem<Wry>)
+        .collect();
+    tray.menu.insert_items(&items, 0)
+}
+
+/// Hides the main window instead of closing it when close-to-tray is on, so
+/// the backend keeps running in the background.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != main_window::LABEL {
+        return;
+    }
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.state::<SettingsStore>().get().close_to_tray
+            && !kiosk::is_active(window.app_handle())
+        {
+            api.prevent_close();
+            let _ = window.hide();
+        }
+    }
+}
+
+#[tauri::command]
+pub fn set_close_to_tray(settings: State<'_, SettingsStore>, enabled: bool) -> Result<(), String> {
+    settings
+        .update(|settings| settings.close_to_tray = enabled)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
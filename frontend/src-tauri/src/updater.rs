@@ -0,0 +1,197 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::metrics::IpcTimer;
+use crate::proxy;
+use crate::rollback;
+use crate::settings::SettingsStore;
+use crate::sidecar;
+
+// Where each release's manifest is, for pinning to one
+const RELEASE_ENDPOINT: &str =
+    "https://github.com/djedi/hamba/releases/download/v{version}/latest.json";
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    fn endpoint(self) -> &'static str {
+        match self {
+            Self::Stable => "https://github.com/djedi/hamba/releases/latest/download/latest.json",
+            Self::Beta => "https://github.com/djedi/hamba/releases/download/beta/latest.json",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    version: String,
+    current_version: String,
+    notes: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// The update found by the last check, waiting for `install_update`
+#[derive(Default)]
+pub struct PendingUpdate(Mutex<Option<Update>>);
+
+pub fn init(app: &AppHandle) {
+    app.manage(PendingUpdate::default());
+}
+
+/// Whether the build has a key to check updates with. Without one updates
+/// are off, as nothing could be verified.
+pub fn is_configured(app: &AppHandle) -> bool {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|key| key.as_str())
+        .is_some_and(|key| !key.trim().is_empty())
+}
+
+/// Checks `pinned_version` a release is pinned to: `major.minor.patch`,
+/// optionally with a pre-release suffix
+pub fn validate_version(version: &str) -> Result<(), String> {
+    let (core, pre) = version.split_once('-').unwrap_or((version, ""));
+    let numbers: Vec<&str> = core.split('.').collect();
+    let valid = numbers.len() == 3
+        && numbers
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        && pre
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !valid || version.contains('+') {
+        return Err(format!("{version:?} is not a version like 1.4.2"));
+    }
+    Ok(())
+}
+
+/// Checks the configured channel for a newer release, or for the pinned one
+/// if it isn't the one running, and remembers it so it can be installed
+/// later. Also emits `update-available` when one is found.
+pub async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    if !is_configured(app) {
+        return Err("This build has no update signing key, so updates are off".to_string());
+    }
+    let settings = app.state::<SettingsStore>().get();
+    let endpoint = match &settings.pinned_version {
+        Some(version) => RELEASE_ENDPOINT.replace("{version}", version),
+        None => settings.update_channel.endpoint().to_string(),
+    };
+    let endpoint = Url::parse(&endpoint).map_err(|err| err.to_string())?;
+
+    let mut builder = app.updater_builder();
+    if let Some(url) = proxy::resolve(app).url {
+        builder = builder.proxy(Url::parse(&url).map_err(|err| err.to_string())?);
+    }
+    // A pinned release is installed even when it's older
+    if settings.pinned_version.is_some() {
+        builder = builder.version_comparator(|current, release| release.version != current);
+    }
+    let update = builder
+        .endpoints(vec![endpoint])
+        .and_then(|builder| builder.build())
+        .map_err(|err| err.to_string())?
+        .check()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let info = update.as_ref().map(|update| UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    });
+    if let Some(info) = &info {
+        let _ = app.emit("update-available", info);
+    }
+
+    *app.state::<PendingUpdate>().0.lock().unwrap() = update;
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn check_for_updates(
+    _timer: IpcTimer,
+    app: AppHandle,
+) -> Result<Option<UpdateInfo>, String> {
+    check(&app).await
+}
+
+/// Downloads the pending update (emitting `update-progress`), keeps the
+/// current version for `rollback_update`, stops the backend so its binary
+/// can be replaced, installs, and restarts the app.
+#[tauri::command]
+pub async fn install_update(_timer: IpcTimer, app: AppHandle) -> Result<(), String> {
+    let update = app
+        .state::<PendingUpdate>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("No update available, check for updates first")?;
+
+    let mut downloaded = 0u64;
+    let bytes = update
+        .download(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app.emit("update-progress", DownloadProgress { downloaded, total });
+            },
+            || {},
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+
+    // Installing goes ahead without it; there's just nothing to roll back to
+    let handle = app.clone();
+    let kept = tauri::async_runtime::spawn_blocking(move || rollback::keep_current(&handle))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|result| result);
+    if let Err(err) = kept {
+        tracing::warn!("Failed to keep the current version: {err}");
+    }
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || sidecar::stop(&handle))
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if let Err(err) = update.install(bytes) {
+        // Leave the app usable on the current version
+        sidecar::start(&app);
+        return Err(err.to_string());
+    }
+    app.restart();
+}
+
+#[tauri::command]
+pub fn set_update_channel(
+    settings: State<'_, SettingsStore>,
+    channel: UpdateChannel,
+) -> Result<(), String> {
+    settings
+        .update(|settings| settings.update_channel = channel)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
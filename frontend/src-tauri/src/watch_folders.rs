@@ -0,0 +1,860 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use glob::Pattern;
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::Notify;
+
+use crate::command::{CommandError, CommandResult, Valid, Validate};
+use crate::data_dir;
+use crate::disk;
+use crate::fs_scope::{self, Access};
+use crate::imports;
+use crate::metrics::IpcTimer;
+use crate::safe_mode;
+use crate::sidecar;
+
+const WATCH_FILE: &str = "watch-folders.json";
+// Network shares get no OS events for what other machines write, so they
+// are polled this often instead
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+// A file being written sends a burst of events; one rescan covers it
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// Rescans happen this often regardless, which picks up a drive that came
+// back and anything the OS didn't report
+const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+// How long a file has to stay the same size before it's imported, so one
+// still being copied or downloaded isn't picked up halfway
+const SETTLE: Duration = Duration::from_secs(2);
+const MAX_DEPTH: usize = 8;
+const MAX_HISTORY: usize = 200;
+const DEFAULT_PATTERNS: &[&str] = &["*.eml", "*.mbox", "*.vcf", "*.ics"];
+
+/// What a watched folder imports and what happens to a file after
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Rules {
+    /// Matched against file names, e.g. `*.eml` or `contacts-*.csv`. Only
+    /// the kinds `import_files` takes get past the backend either way.
+    patterns: Vec<String>,
+    /// Looks in subfolders too
+    recursive: bool,
+    /// Deletes the file from the folder once the backend has it
+    delete_after_import: bool,
+    enabled: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            recursive: false,
+            delete_after_import: false,
+            enabled: true,
+        }
+    }
+}
+
+impl Rules {
+    fn compile(&self) -> Result<Vec<Pattern>, String> {
+        if self.patterns.is_empty() {
+            return Err("A watched folder needs at least one pattern".to_string());
+        }
+        self.patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern.trim())
+                    .map_err(|err| format!("Invalid pattern {pattern:?}: {err}"))
+            })
+            .collect()
+    }
+}
+
+impl Validate for Rules {
+    fn validate(&self) -> CommandResult<()> {
+        self.compile()
+            .map(|_| ())
+            .map_err(|err| CommandError::invalid("patterns", err))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFolder {
+    id: String,
+    path: PathBuf,
+    #[serde(flatten)]
+    rules: Rules,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    folder_id: String,
+    source: PathBuf,
+    /// The copy handed to the backend, `None` if it was rejected
+    path: Option<PathBuf>,
+    error: Option<String>,
+    /// Seconds since the Unix epoch
+    imported_at: u64,
+}
+
+// A file whose stamp changes counts as new again, e.g. an export that is
+// overwritten every night
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    modified: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedFolder {
+    #[serde(flatten)]
+    folder: WatchFolder,
+    /// Files already imported, or there when the folder was added
+    #[serde(default)]
+    known: HashMap<PathBuf, Stamp>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    folders: Vec<SavedFolder>,
+    history: VecDeque<HistoryEntry>,
+}
+
+struct Watched {
+    folder: WatchFolder,
+    patterns: Vec<Pattern>,
+    known: HashMap<PathBuf, Stamp>,
+    // New or changed files waiting to settle, with when they were last seen
+    // to change
+    pending: HashMap<PathBuf, (Stamp, Instant)>,
+}
+
+#[derive(Default)]
+struct Inner {
+    folders: Vec<Watched>,
+    /// Newest first
+    history: VecDeque<HistoryEntry>,
+}
+
+// What one scan found, see `Inner::settle`
+#[derive(Default)]
+struct Scan {
+    settled: Vec<(WatchFolder, PathBuf)>,
+    // Folders that couldn't be listed
+    gone: Vec<String>,
+    changed: bool,
+    settling: bool,
+}
+
+impl Inner {
+    // Compares fresh listings, by folder id, with what each folder knew.
+    // Files that have settled come back already recorded as known.
+    fn settle(&mut self, listings: Vec<(String, Option<HashMap<PathBuf, Stamp>>)>) -> Scan {
+        let mut scan = Scan::default();
+        for (id, files) in listings {
+            // A folder that's gone, e.g. an unplugged drive, keeps what it
+            // knew until it's back
+            let Some(files) = files else {
+                scan.gone.push(id);
+                continue;
+            };
+            let Some(watched) = self.folders.iter_mut().find(|w| w.folder.id == id) else {
+                continue;
+            };
+            let before = watched.known.len();
+            watched.known.retain(|path, _| files.contains_key(path));
+            watched.pending.retain(|path, _| files.contains_key(path));
+            scan.changed |= watched.known.len() != before;
+            for (path, stamp) in files {
+                if watched.known.get(&path) == Some(&stamp) {
+                    continue;
+                }
+                match watched.pending.get(&path) {
+                    Some((seen, since)) if *seen == stamp => {
+                        if since.elapsed() >= SETTLE {
+                            watched.pending.remove(&path);
+                            watched.known.insert(path.clone(), stamp);
+                            scan.settled.push((watched.folder.clone(), path));
+                            scan.changed = true;
+                        }
+                    }
+                    _ => {
+                        watched.pending.insert(path, (stamp, Instant::now()));
+                    }
+                }
+            }
+        }
+        scan.settling = self
+            .folders
+            .iter()
+            .any(|watched| !watched.pending.is_empty());
+        scan
+    }
+
+    fn record(&mut self, entry: HistoryEntry) {
+        self.history.push_front(entry);
+        self.history.truncate(MAX_HISTORY);
+    }
+}
+
+// An OS watch on one folder, made with the folder's `recursive` at the time
+struct FolderWatch {
+    recursive: bool,
+    _watcher: Box<dyn Watcher + Send>,
+}
+
+/// Folders whose new files are imported as if dropped on the window, e.g.
+/// where a scanner or another app saves its exports. Files added while the
+/// app was closed are picked up at the next launch.
+pub struct WatchFolders {
+    path: Option<PathBuf>,
+    inner: Mutex<Inner>,
+    // By folder id, for the enabled folders
+    watches: Mutex<HashMap<String, FolderWatch>>,
+    // Rescans after a file event or a change to the folders
+    wake: Notify,
+}
+
+impl WatchFolders {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let saved = {
+            let inner = self.inner.lock().unwrap();
+            Saved {
+                folders: inner
+                    .folders
+                    .iter()
+                    .map(|watched| SavedFolder {
+                        folder: watched.folder.clone(),
+                        known: watched.known.clone(),
+                    })
+                    .collect(),
+                history: inner.history.clone(),
+            }
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&saved)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(WATCH_FILE));
+    let saved: Saved = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str(&contents) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid {WATCH_FILE}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    let mut folders = Vec::new();
+    for saved in saved.folders {
+        match saved.folder.rules.compile() {
+            Ok(patterns) => folders.push(Watched {
+                folder: saved.folder,
+                patterns,
+                known: saved.known,
+                pending: HashMap::new(),
+            }),
+            Err(err) => tracing::warn!("Dropping watched folder {}: {err}", saved.folder.id),
+        }
+    }
+    app.manage(WatchFolders {
+        path,
+        inner: Mutex::new(Inner {
+            folders,
+            history: saved.history,
+        }),
+        watches: Mutex::new(HashMap::new()),
+        wake: Notify::new(),
+    });
+
+    // Folders can still be changed in safe mode, nothing is imported
+    if safe_mode::is_active(app) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        sidecar::wait_until_healthy(&app).await;
+        let watch = app.state::<WatchFolders>();
+        loop {
+            sync_watches(&app);
+            let (settled, settling) = scan(&app).await;
+            for (folder, source) in settled {
+                import(&app, &folder, source).await;
+            }
+            // Files still settling are looked at again once they could have
+            let wait = if settling { SETTLE } else { RESCAN_INTERVAL };
+            if tokio::time::timeout(wait, watch.wake.notified())
+                .await
+                .is_ok()
+            {
+                tokio::time::sleep(DEBOUNCE).await;
+            }
+        }
+    });
+}
+
+// Watches every enabled folder that isn't yet, and stops watching the ones
+// that were disabled, removed or changed
+fn sync_watches(app: &AppHandle) {
+    let watch = app.state::<WatchFolders>();
+    let targets: Vec<(String, PathBuf, bool)> = {
+        let inner = watch.inner.lock().unwrap();
+        inner
+            .folders
+            .iter()
+            .filter(|watched| watched.folder.rules.enabled)
+            .map(|watched| {
+                let folder = &watched.folder;
+                (
+                    folder.id.clone(),
+                    folder.path.clone(),
+                    folder.rules.recursive,
+                )
+            })
+            .collect()
+    };
+    let mut watches = watch.watches.lock().unwrap();
+    watches.retain(|id, existing| {
+        targets
+            .iter()
+            .any(|(target, _, recursive)| target == id && *recursive == existing.recursive)
+    });
+    for (id, path, recursive) in targets {
+        // A folder that's gone is watched again once a rescan finds it
+        if watches.contains_key(&id) || !path.is_dir() {
+            continue;
+        }
+        match start_watch(app, &path, recursive) {
+            Ok(watcher) => {
+                watches.insert(
+                    id,
+                    FolderWatch {
+                        recursive,
+                        _watcher: watcher,
+                    },
+                );
+            }
+            Err(err) => tracing::warn!("Failed to watch {}: {err}", path.display()),
+        }
+    }
+}
+
+fn start_watch(
+    app: &AppHandle,
+    path: &Path,
+    recursive: bool,
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let handle = app.clone();
+    let on_event = move |event: notify::Result<notify::Event>| {
+        // Errors may mean events were lost, so they rescan too
+        let relevant = match event {
+            Ok(event) => !event.kind.is_access(),
+            Err(_) => true,
+        };
+        if relevant {
+            handle.state::<WatchFolders>().wake.notify_one();
+        }
+    };
+    let mut watcher: Box<dyn Watcher + Send> = if disk::is_network(path) {
+        let config = notify::Config::default().with_poll_interval(POLL_INTERVAL);
+        Box::new(PollWatcher::new(on_event, config)?)
+    } else {
+        Box::new(RecommendedWatcher::new(
+            on_event,
+            notify::Config::default(),
+        )?)
+    };
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(path, mode)?;
+    Ok(watcher)
+}
+
+// Lists every matching file of the enabled folders and returns those that
+// have settled since the last scan, already recorded as known, and whether
+// others are still settling
+async fn scan(app: &AppHandle) -> (Vec<(WatchFolder, PathBuf)>, bool) {
+    let targets: Vec<(String, PathBuf, bool, Vec<Pattern>)> = {
+        let watch = app.state::<WatchFolders>();
+        let inner = watch.inner.lock().unwrap();
+        inner
+            .folders
+            .iter()
+            .filter(|watched| watched.folder.rules.enabled)
+            .map(|watched| {
+                let folder = &watched.folder;
+                (
+                    folder.id.clone(),
+                    folder.path.clone(),
+                    folder.rules.recursive,
+                    watched.patterns.clone(),
+                )
+            })
+            .collect()
+    };
+    if targets.is_empty() {
+        return (Vec::new(), false);
+    }
+    let listings = tauri::async_runtime::spawn_blocking(move || {
+        targets
+            .into_iter()
+            .map(|(id, path, recursive, patterns)| {
+                let files = list(&path, recursive, &patterns).ok();
+                (id, files)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+
+    let watch = app.state::<WatchFolders>();
+    let scan = watch.inner.lock().unwrap().settle(listings);
+    // A folder that's gone loses its watch until it's back
+    {
+        let mut watches = watch.watches.lock().unwrap();
+        for id in &scan.gone {
+            watches.remove(id);
+        }
+    }
+    if scan.changed {
+        if let Err(err) = watch.save() {
+            tracing::warn!("Failed to save {WATCH_FILE}: {err}");
+        }
+    }
+    (scan.settled, scan.settling)
+}
+
+fn list(dir: &Path, recursive: bool, patterns: &[Pattern]) -> io::Result<HashMap<PathBuf, Stamp>> {
+    let mut files = HashMap::new();
+    walk(dir, recursive, patterns, 0, &mut files)?;
+    Ok(files)
+}
+
+fn walk(
+    dir: &Path,
+    recursive: bool,
+    patterns: &[Pattern],
+    depth: usize,
+    files: &mut HashMap<PathBuf, Stamp>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        // Hidden files include the partial downloads of most browsers
+        if name.starts_with('.') {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            if recursive && depth < MAX_DEPTH {
+                // One unreadable subfolder doesn't stop the rest
+                let _ = walk(&entry.path(), recursive, patterns, depth + 1, files);
+            }
+            continue;
+        }
+        if !metadata.is_file() || !patterns.iter().any(|pattern| pattern.matches(name)) {
+            continue;
+        }
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+        files.insert(
+            entry.path(),
+            Stamp {
+                size: metadata.len(),
+                modified,
+            },
+        );
+    }
+    Ok(())
+}
+
+async fn import(app: &AppHandle, folder: &WatchFolder, source: PathBuf) {
+    tracing::info!("Importing {} from a watched folder", source.display());
+    let result = imports::import(app, &source).await;
+    let entry = finish(folder, source, result);
+    let _ = app.emit("watch-folder-import", &entry);
+
+    let watch = app.state::<WatchFolders>();
+    watch.inner.lock().unwrap().record(entry);
+    if let Err(err) = watch.save() {
+        tracing::warn!("Failed to save {WATCH_FILE}: {err}");
+    }
+}
+
+// Applies the folder's rules to an imported file and makes its history entry
+fn finish(folder: &WatchFolder, source: PathBuf, result: Result<PathBuf, String>) -> HistoryEntry {
+    let (path, error) = match result {
+        Ok(path) => (Some(path), None),
+        Err(err) => {
+            tracing::warn!("Rejected watched import of {}: {err}", source.display());
+            (None, Some(err))
+        }
+    };
+    if path.is_some() && folder.rules.delete_after_import {
+        if let Err(err) = fs::remove_file(&source) {
+            tracing::warn!("Failed to delete {}: {err}", source.display());
+        }
+    }
+    HistoryEntry {
+        folder_id: folder.id.clone(),
+        source,
+        path,
+        error,
+        imported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    }
+}
+
+#[tauri::command]
+pub fn list_watch_folders(watch: State<'_, WatchFolders>) -> Vec<WatchFolder> {
+    let inner = watch.inner.lock().unwrap();
+    inner.folders.iter().map(|w| w.folder.clone()).collect()
+}
+
+/// Starts watching `path`, or a folder the user picks if there is none.
+/// Files already in it are left alone; only ones added from now on are
+/// imported. `None` means the user cancelled.
+#[tauri::command]
+pub async fn add_watch_folder(
+    _timer: IpcTimer,
+    app: AppHandle,
+    path: Option<PathBuf>,
+    rules: Valid<Option<Rules>>,
+) -> CommandResult<Option<WatchFolder>> {
+    let rules = rules.0.unwrap_or_default();
+    let patterns = rules.compile()?;
+    let path = match path {
+        Some(path) => fs_scope::check(&app, &path, Access::Read)?,
+        None => {
+            let builder = app.dialog().file().set_title("Watch a folder");
+            let picked =
+                tauri::async_runtime::spawn_blocking(move || builder.blocking_pick_folder())
+                    .await
+                    .map_err(|err| err.to_string())?;
+            let Some(folder) = picked else {
+                return Ok(None);
+            };
+            let folder = folder.into_path().map_err(|err| err.to_string())?;
+            fs_scope::grant(&app, &folder, Access::Read);
+            folder
+        }
+    };
+    let path = fs::canonicalize(&path)
+        .map_err(|err| CommandError::invalid("path", format!("{}: {err}", path.display())))?;
+    if !path.is_dir() {
+        return Err(CommandError::invalid(
+            "path",
+            format!("{} is not a folder", path.display()),
+        ));
+    }
+    let known = {
+        let (path, recursive, patterns) = (path.clone(), rules.recursive, patterns.clone());
+        tauri::async_runtime::spawn_blocking(move || list(&path, recursive, &patterns))
+            .await
+            .map_err(|err| err.to_string())?
+            .map_err(|err| err.to_string())?
+    };
+
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|err| err.to_string())?;
+    let folder = WatchFolder {
+        id: hex::encode(bytes),
+        path,
+        rules,
+    };
+    let watch = app.state::<WatchFolders>();
+    {
+        let mut inner = watch.inner.lock().unwrap();
+        if inner.folders.iter().any(|w| w.folder.path == folder.path) {
+            return Err(CommandError::invalid(
+                "path",
+                format!("{} is already watched", folder.path.display()),
+            ));
+        }
+        inner.folders.push(Watched {
+            folder: folder.clone(),
+            patterns,
+            known,
+            pending: HashMap::new(),
+        });
+    }
+    watch.save().map_err(|err| err.to_string())?;
+    watch.wake.notify_one();
+    tracing::info!("Watching {}", folder.path.display());
+    Ok(Some(folder))
+}
+
+/// Replaces a folder's rules. Files that match only now are imported too.
+#[tauri::command]
+pub fn update_watch_folder(
+    watch: State<'_, WatchFolders>,
+    id: String,
+    rules: Valid<Rules>,
+) -> CommandResult<WatchFolder> {
+    let Valid(rules) = rules;
+    let patterns = rules.compile()?;
+    let folder = {
+        let mut inner = watch.inner.lock().unwrap();
+        let watched = inner
+            .folders
+            .iter_mut()
+            .find(|w| w.folder.id == id)
+            .ok_or_else(|| CommandError::not_found(format!("No watched folder {id:?}")))?;
+        watched.folder.rules = rules;
+        watched.patterns = patterns;
+        watched.pending.clear();
+        watched.folder.clone()
+    };
+    watch.save().map_err(|err| err.to_string())?;
+    watch.wake.notify_one();
+    Ok(folder)
+}
+
+/// Stops watching a folder. Its history is kept.
+#[tauri::command]
+pub fn remove_watch_folder(watch: State<'_, WatchFolders>, id: String) -> CommandResult<()> {
+    {
+        let mut inner = watch.inner.lock().unwrap();
+        let before = inner.folders.len();
+        inner.folders.retain(|w| w.folder.id != id);
+        if inner.folders.len() == before {
+            return Err(CommandError::not_found(format!("No watched folder {id:?}")));
+        }
+    }
+    watch.wake.notify_one();
+    Ok(watch.save()?)
+}
+
+/// What was imported from watched folders, newest first, for one folder or
+/// all of them
+#[tauri::command]
+pub fn get_watch_folder_history(
+    watch: State<'_, WatchFolders>,
+    folder_id: Option<String>,
+) -> Vec<HistoryEntry> {
+    let inner = watch.inner.lock().unwrap();
+    inner
+        .history
+        .iter()
+        .filter(|entry| folder_id.as_ref().is_none_or(|id| *id == entry.folder_id))
+        .cloned()
+        .collect()
+}
+
+#[tauri::command]
+pub fn clear_watch_folder_history(watch: State<'_, WatchFolders>) -> Result<(), String> {
+    watch.inner.lock().unwrap().history.clear();
+    watch.save().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    // Answers one request with `status` and hands over its body
+    fn backend(status: &str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), "/imports");
+        let status = status.to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let _ = sender.send(String::from_utf8(body).unwrap());
+            let response =
+                format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+        (url, receiver)
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hamba-watch-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("watched")).unwrap();
+        dir
+    }
+
+    fn watching(dir: &Path, rules: Rules) -> Inner {
+        let folder = WatchFolder {
+            id: "folder".to_string(),
+            path: dir.join("watched"),
+            rules,
+        };
+        Inner {
+            folders: vec![Watched {
+                patterns: folder.rules.compile().unwrap(),
+                folder,
+                known: HashMap::new(),
+                pending: HashMap::new(),
+            }],
+            history: VecDeque::new(),
+        }
+    }
+
+    fn listings(inner: &Inner) -> Vec<(String, Option<HashMap<PathBuf, Stamp>>)> {
+        inner
+            .folders
+            .iter()
+            .map(|watched| {
+                let folder = &watched.folder;
+                let files = list(&folder.path, folder.rules.recursive, &watched.patterns).ok();
+                (folder.id.clone(), files)
+            })
+            .collect()
+    }
+
+    // As if `SETTLE` had passed since the files were first seen
+    fn wait_out_settle(inner: &mut Inner) {
+        for watched in &mut inner.folders {
+            for (_, since) in watched.pending.values_mut() {
+                *since = since.checked_sub(SETTLE).unwrap();
+            }
+        }
+    }
+
+    // The scan -> import -> history steps of the loop in `init`
+    fn run(inner: &mut Inner, dir: &Path, url: &str) -> Scan {
+        let listed = listings(inner);
+        let scan = inner.settle(listed);
+        for (folder, source) in scan.settled.clone() {
+            let request = reqwest::Client::new().post(url);
+            let result = tauri::async_runtime::block_on(imports::copy_in(
+                &dir.join("imports"),
+                &source,
+                request,
+            ));
+            inner.record(finish(&folder, source, result));
+        }
+        scan
+    }
+
+    #[test]
+    fn settled_files_are_imported_and_recorded() {
+        let dir = scratch("import");
+        let source = dir.join("watched").join("message.eml");
+        fs::write(&source, "Subject: hi\r\n\r\nhello").unwrap();
+        fs::write(dir.join("watched").join("notes.txt"), "not matched").unwrap();
+        let mut inner = watching(&dir, Rules::default());
+        let (url, received) = backend("201 Created");
+
+        let scan = run(&mut inner, &dir, &url);
+        assert!(scan.settled.is_empty());
+        assert!(scan.settling);
+
+        wait_out_settle(&mut inner);
+        let scan = run(&mut inner, &dir, &url);
+        assert_eq!(scan.settled.len(), 1);
+        assert!(!scan.settling);
+
+        let notice: serde_json::Value = serde_json::from_str(&received.recv().unwrap()).unwrap();
+        assert_eq!(notice["name"], "message.eml");
+        assert_eq!(notice["kind"], "eml");
+        assert_eq!(notice["size"], 20);
+
+        assert_eq!(inner.history.len(), 1);
+        let entry = &inner.history[0];
+        assert_eq!(entry.folder_id, "folder");
+        assert_eq!(entry.source, source);
+        assert!(entry.error.is_none());
+        assert!(entry.path.as_ref().unwrap().exists());
+        // Kept, as `delete_after_import` is off
+        assert!(source.exists());
+
+        // Known from now on, so not imported again
+        assert!(run(&mut inner, &dir, &url).settled.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejected_imports_are_recorded_with_the_error() {
+        let dir = scratch("rejected");
+        let source = dir.join("watched").join("contacts.vcf");
+        fs::write(&source, "BEGIN:VCARD\nEND:VCARD\n").unwrap();
+        let rules = Rules {
+            delete_after_import: true,
+            ..Rules::default()
+        };
+        let mut inner = watching(&dir, rules);
+        let (url, _received) = backend("404 Not Found");
+
+        run(&mut inner, &dir, &url);
+        wait_out_settle(&mut inner);
+        run(&mut inner, &dir, &url);
+
+        let entry = &inner.history[0];
+        assert!(entry.path.is_none());
+        assert!(entry.error.as_ref().unwrap().contains("did not accept"));
+        // Left in place, and the copy cleaned up
+        assert!(source.exists());
+        assert_eq!(fs::read_dir(dir.join("imports")).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_folders_keep_what_they_knew() {
+        let dir = scratch("gone");
+        fs::write(dir.join("watched").join("a.ics"), "BEGIN:VCALENDAR").unwrap();
+        let mut inner = watching(&dir, Rules::default());
+        inner.settle(listings(&inner));
+        wait_out_settle(&mut inner);
+        inner.settle(listings(&inner));
+        assert_eq!(inner.folders[0].known.len(), 1);
+
+        let scan = inner.settle(vec![("folder".to_string(), None)]);
+        assert_eq!(scan.gone, ["folder"]);
+        assert_eq!(inner.folders[0].known.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::battery_saver;
+use crate::sidecar::Sidecar;
+
+/// Line the backend prints on stdout every `HEARTBEAT_INTERVAL`
+pub const HEARTBEAT_TOKEN: &str = "__hamba_heartbeat__";
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// Missing this many heartbeats in a row means the backend is hung
+const MISSED_HEARTBEATS: u32 = 3;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendHang {
+    pid: u32,
+    silent_for_ms: u64,
+}
+
+/// Kills the backend when it stops sending heartbeats. The supervisor then
+/// restarts it like any other crash, which also files a crash report with
+/// its last lines of output.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            check(&app);
+        }
+    });
+}
+
+fn check(app: &AppHandle) {
+    let sidecar = app.state::<Sidecar>();
+    // Until the first heartbeat there is nothing to miss, which also keeps
+    // older backends without heartbeats from being killed
+    let Some(last) = sidecar.last_heartbeat() else {
+        return;
+    };
+    let silent_for = Instant::now().duration_since(last);
+    if silent_for < battery_saver::heartbeat_interval(app) * MISSED_HEARTBEATS
+        || sidecar.is_stopped()
+    {
+        return;
+    }
+
+    let Some(pid) = sidecar.kill_child() else {
+        return;
+    };
+    tracing::error!("Backend (pid {pid}) sent no heartbeat for {silent_for:?}, killed it as hung");
+    let _ = app.emit(
+        "backend-hang",
+        BackendHang {
+            pid,
+            silent_for_ms: silent_for.as_millis() as u64,
+        },
+    );
+}
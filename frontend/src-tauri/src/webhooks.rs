@@ -0,0 +1,589 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+
+use crate::command::{CommandError, CommandResult, Valid, Validate};
+use crate::data_dir;
+use crate::proxy;
+use crate::safe_mode;
+use crate::secrets;
+
+const WEBHOOKS_FILE: &str = "webhooks.json";
+// Suffixed with the webhook's id
+pub const SECRET_PREFIX: &str = "webhook-secret-";
+/// Matches every event
+const ANY_EVENT: &str = "*";
+/// Sent by `test_webhook`
+const PING_EVENT: &str = "ping";
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 8;
+// Doubled after every failed attempt, up to `MAX_BACKOFF`
+const FIRST_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+// A receiver that's down for long shouldn't fill the disk
+const MAX_QUEUE: usize = 1000;
+const MAX_LOG: usize = 200;
+// Checked at least this often even without new deliveries
+const IDLE_TICK: Duration = Duration::from_secs(60);
+
+/// Where and for which events to deliver
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    url: String,
+    /// Backend event types like `new_mail` or `sync_complete`, the app's
+    /// `backup_complete` and `backup_failed`, or `*` for all of them
+    events: Vec<String>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl WebhookConfig {
+    fn wants(&self, event: &str) -> bool {
+        self.enabled && self.events.iter().any(|e| e == event || e == ANY_EVENT)
+    }
+}
+
+impl Validate for WebhookConfig {
+    fn validate(&self) -> CommandResult<()> {
+        let url = Url::parse(&self.url)
+            .map_err(|err| CommandError::invalid("url", format!("Invalid URL: {err}")))?;
+        let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+        if url.scheme() != "https" && !(url.scheme() == "http" && local) {
+            return Err(CommandError::invalid(
+                "url",
+                "Webhooks need an https:// URL, or http:// to this computer",
+            ));
+        }
+        if self.events.is_empty() {
+            return Err(CommandError::invalid("events", "Pick at least one event"));
+        }
+        for event in &self.events {
+            let valid = event == ANY_EVENT
+                || (!event.is_empty() && event.chars().all(|c| c.is_ascii_lowercase() || c == '_'));
+            if !valid {
+                return Err(CommandError::invalid(
+                    "events",
+                    format!("{event:?} is not an event type like new_mail"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    id: String,
+    #[serde(flatten)]
+    config: WebhookConfig,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    webhook: Webhook,
+    /// The key payloads are signed with, for the receiver to check them
+    secret: String,
+}
+
+// The body is kept as sent, so every attempt carries the same signature
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Delivery {
+    id: String,
+    webhook_id: String,
+    event: String,
+    body: String,
+    attempts: u32,
+    /// Seconds since the Unix epoch
+    next_attempt_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryAttempt {
+    delivery_id: String,
+    webhook_id: String,
+    event: String,
+    attempt: u32,
+    /// Seconds since the Unix epoch
+    at: u64,
+    /// The receiver's HTTP status, `None` if it couldn't be reached
+    status: Option<u16>,
+    error: Option<String>,
+    /// Delivered, or given up on after `MAX_ATTEMPTS`
+    done: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    webhooks: Vec<Webhook>,
+    queue: VecDeque<Delivery>,
+    /// Newest first
+    log: VecDeque<DeliveryAttempt>,
+}
+
+/// Posts events to the user's URLs, signed with HMAC-SHA256. Failed
+/// deliveries are retried with backoff; the queue is saved, so ones pending
+/// at quit go out after the next launch.
+pub struct Webhooks {
+    path: Option<PathBuf>,
+    saved: Mutex<Saved>,
+    wake: Notify,
+}
+
+impl Webhooks {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = serde_json::to_string_pretty(&*self.saved.lock().unwrap())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+
+    fn save_or_warn(&self) {
+        if let Err(err) = self.save() {
+            tracing::warn!("Failed to save {WEBHOOKS_FILE}: {err}");
+        }
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(WEBHOOKS_FILE));
+    let saved = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| match serde_json::from_str(&text) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid {WEBHOOKS_FILE}: {err}");
+                None
+            }
+        })
+        .unwrap_or_default();
+    app.manage(Webhooks {
+        path,
+        saved: Mutex::new(saved),
+        wake: Notify::new(),
+    });
+
+    // Webhooks can still be changed in safe mode, nothing is sent
+    if safe_mode::is_active(app) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait = deliver_due(&app).await;
+            let webhooks = app.state::<Webhooks>();
+            let _ = tokio::time::timeout(wait, webhooks.wake.notified()).await;
+        }
+    });
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn random_id(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    getrandom::fill(&mut bytes).expect("OS random number generator unavailable");
+    hex::encode(bytes)
+}
+
+fn secret_key(id: &str) -> String {
+    format!("{SECRET_PREFIX}{id}")
+}
+
+fn backoff(attempts: u32) -> Duration {
+    FIRST_BACKOFF
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// Queues `event` for every webhook that wants it
+pub fn fire(app: &AppHandle, event: &str, payload: &impl Serialize) {
+    let Some(webhooks) = app.try_state::<Webhooks>() else {
+        return;
+    };
+    match serde_json::to_value(payload) {
+        Ok(data) => enqueue(&webhooks, event, data, |webhook| {
+            webhook.config.wants(event)
+        }),
+        Err(err) => tracing::warn!("Failed to serialize the {event} webhook payload: {err}"),
+    }
+}
+
+fn enqueue(webhooks: &Webhooks, event: &str, data: Value, to: impl Fn(&Webhook) -> bool) {
+    let queued = {
+        let mut saved = webhooks.saved.lock().unwrap();
+        let targets: Vec<String> = saved
+            .webhooks
+            .iter()
+            .filter(|webhook| to(webhook))
+            .map(|webhook| webhook.id.clone())
+            .collect();
+        for webhook_id in &targets {
+            let id = random_id(8);
+            let body = json!({
+                "id": id,
+                "event": event,
+                "occurredAt": now(),
+                "data": data,
+            });
+            saved.queue.push_back(Delivery {
+                id,
+                webhook_id: webhook_id.clone(),
+                event: event.to_string(),
+                body: body.to_string(),
+                attempts: 0,
+                next_attempt_at: 0,
+            });
+        }
+        while saved.queue.len() > MAX_QUEUE {
+            if let Some(dropped) = saved.queue.pop_front() {
+                tracing::warn!("Webhook queue full, dropping delivery {}", dropped.id);
+            }
+        }
+        !targets.is_empty()
+    };
+    if queued {
+        webhooks.save_or_warn();
+        webhooks.wake.notify_one();
+    }
+}
+
+/// Passes every backend message on under its `type`
+pub fn on_backend_message(app: &AppHandle, message: &Map<String, Value>) {
+    if let Some(event) = message.get("type").and_then(Value::as_str) {
+        fire(app, event, message);
+    }
+}
+
+fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Sends one attempt, returning the status when the receiver answered
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    delivery: &Delivery,
+) -> Result<u16, (Option<u16>, String)> {
+    let secret = secrets::get(&secret_key(&delivery.webhook_id))
+        .map_err(|err| (None, err))?
+        .ok_or((None, "The signing secret is missing".to_string()))?;
+    let timestamp = now();
+    let response = client
+        .post(url)
+        .timeout(TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Hamba-Event", &delivery.event)
+        .header("X-Hamba-Delivery", &delivery.id)
+        .header("X-Hamba-Timestamp", timestamp.to_string())
+        .header(
+            "X-Hamba-Signature",
+            format!("sha256={}", sign(&secret, timestamp, &delivery.body)),
+        )
+        .body(delivery.body.clone())
+        .send()
+        .await
+        .map_err(|err| (None, err.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err((
+            Some(status.as_u16()),
+            format!("The receiver answered {status}"),
+        ))
+    }
+}
+
+// Sends what's due and returns how long until the next delivery is
+async fn deliver_due(app: &AppHandle) -> Duration {
+    let webhooks = app.state::<Webhooks>();
+    let (due, urls): (Vec<Delivery>, Vec<(String, String)>) = {
+        let saved = webhooks.saved.lock().unwrap();
+        let now = now();
+        (
+            saved
+                .queue
+                .iter()
+                .filter(|delivery| delivery.next_attempt_at <= now)
+                .cloned()
+                .collect(),
+            saved
+                .webhooks
+                .iter()
+                .map(|webhook| (webhook.id.clone(), webhook.config.url.clone()))
+                .collect(),
+        )
+    };
+
+    if !due.is_empty() {
+        let client = match proxy::client(&proxy::resolve(app)) {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!("Failed to set up webhook delivery: {err}");
+                return IDLE_TICK;
+            }
+        };
+        for delivery in due {
+            // Its webhook was removed since it was queued
+            let Some((_, url)) = urls.iter().find(|(id, _)| *id == delivery.webhook_id) else {
+                continue;
+            };
+            let result = send(&client, url, &delivery).await;
+            record(&webhooks, delivery, result);
+        }
+        webhooks.save_or_warn();
+    }
+
+    let now = now();
+    let saved = webhooks.saved.lock().unwrap();
+    saved
+        .queue
+        .iter()
+        .map(|delivery| Duration::from_secs(delivery.next_attempt_at.saturating_sub(now)))
+        .min()
+        .map_or(IDLE_TICK, |next| next.min(IDLE_TICK))
+}
+
+fn record(webhooks: &Webhooks, delivery: Delivery, result: Result<u16, (Option<u16>, String)>) {
+    let mut saved = webhooks.saved.lock().unwrap();
+    let attempt = delivery.attempts + 1;
+    let (status, error) = match result {
+        Ok(status) => (Some(status), None),
+        Err((status, error)) => (status, Some(error)),
+    };
+    let done = error.is_none() || attempt >= MAX_ATTEMPTS;
+    match &error {
+        None => tracing::debug!("Delivered webhook {} ({})", delivery.id, delivery.event),
+        Some(error) if done => tracing::warn!(
+            "Giving up on webhook {} after {attempt} attempts: {error}",
+            delivery.id
+        ),
+        Some(error) => tracing::info!(
+            "Webhook {} failed, retrying in {:?}: {error}",
+            delivery.id,
+            backoff(attempt)
+        ),
+    }
+
+    if let Some(queued) = saved
+        .queue
+        .iter_mut()
+        .find(|queued| queued.id == delivery.id)
+    {
+        queued.attempts = attempt;
+        queued.next_attempt_at = now() + backoff(attempt).as_secs();
+    }
+    if done {
+        saved.queue.retain(|queued| queued.id != delivery.id);
+    }
+    saved.log.push_front(DeliveryAttempt {
+        delivery_id: delivery.id,
+        webhook_id: delivery.webhook_id,
+        event: delivery.event,
+        attempt,
+        at: now(),
+        status,
+        error,
+        done,
+    });
+    saved.log.truncate(MAX_LOG);
+}
+
+#[tauri::command]
+pub fn list_webhooks(webhooks: State<'_, Webhooks>) -> Vec<Webhook> {
+    webhooks.saved.lock().unwrap().webhooks.clone()
+}
+
+/// Adds a webhook with a fresh signing secret, returned along with it
+#[tauri::command]
+pub fn add_webhook(
+    webhooks: State<'_, Webhooks>,
+    config: Valid<WebhookConfig>,
+) -> CommandResult<CreatedWebhook> {
+    let webhook = Webhook {
+        id: random_id(8),
+        config: config.0,
+    };
+    let secret = random_id(32);
+    secrets::set(&secret_key(&webhook.id), &secret)?;
+    webhooks
+        .saved
+        .lock()
+        .unwrap()
+        .webhooks
+        .push(webhook.clone());
+    webhooks.save()?;
+    tracing::info!("Added webhook {}", webhook.id);
+    Ok(CreatedWebhook { webhook, secret })
+}
+
+#[tauri::command]
+pub fn update_webhook(
+    webhooks: State<'_, Webhooks>,
+    id: String,
+    config: Valid<WebhookConfig>,
+) -> CommandResult<Webhook> {
+    let webhook = {
+        let mut saved = webhooks.saved.lock().unwrap();
+        let webhook = saved
+            .webhooks
+            .iter_mut()
+            .find(|webhook| webhook.id == id)
+            .ok_or_else(|| CommandError::not_found(format!("No webhook {id:?}")))?;
+        webhook.config = config.0;
+        webhook.clone()
+    };
+    webhooks.save()?;
+    Ok(webhook)
+}
+
+/// Removes a webhook, its secret and whatever was still queued for it
+#[tauri::command]
+pub fn remove_webhook(webhooks: State<'_, Webhooks>, id: String) -> CommandResult<()> {
+    {
+        let mut saved = webhooks.saved.lock().unwrap();
+        let before = saved.webhooks.len();
+        saved.webhooks.retain(|webhook| webhook.id != id);
+        if saved.webhooks.len() == before {
+            return Err(CommandError::not_found(format!("No webhook {id:?}")));
+        }
+        saved.queue.retain(|delivery| delivery.webhook_id != id);
+    }
+    secrets::delete(&secret_key(&id))?;
+    Ok(webhooks.save()?)
+}
+
+/// The key a webhook's payloads are signed with
+#[tauri::command]
+pub fn get_webhook_secret(webhooks: State<'_, Webhooks>, id: String) -> CommandResult<String> {
+    if !webhooks
+        .saved
+        .lock()
+        .unwrap()
+        .webhooks
+        .iter()
+        .any(|webhook| webhook.id == id)
+    {
+        return Err(CommandError::not_found(format!("No webhook {id:?}")));
+    }
+    secrets::get(&secret_key(&id))?
+        .ok_or_else(|| CommandError::not_found("The signing secret is missing"))
+}
+
+/// Queues a `ping` event for one webhook, to check the receiver. The
+/// result shows up in `get_webhook_deliveries`.
+#[tauri::command]
+pub fn test_webhook(webhooks: State<'_, Webhooks>, id: String) -> CommandResult<()> {
+    let enabled = webhooks
+        .saved
+        .lock()
+        .unwrap()
+        .webhooks
+        .iter()
+        .find(|webhook| webhook.id == id)
+        .map(|webhook| webhook.config.enabled)
+        .ok_or_else(|| CommandError::not_found(format!("No webhook {id:?}")))?;
+    if !enabled {
+        return Err(CommandError::invalid("id", "The webhook is turned off"));
+    }
+    enqueue(
+        &webhooks,
+        PING_EVENT,
+        json!({ "webhookId": id }),
+        |webhook| webhook.id == id,
+    );
+    Ok(())
+}
+
+/// Recent delivery attempts, newest first, optionally for one webhook
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    webhooks: State<'_, Webhooks>,
+    webhook_id: Option<String>,
+) -> Vec<DeliveryAttempt> {
+    webhooks
+        .saved
+        .lock()
+        .unwrap()
+        .log
+        .iter()
+        .filter(|attempt| {
+            webhook_id
+                .as_ref()
+                .is_none_or(|id| *id == attempt.webhook_id)
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_are_hmac_sha256_of_timestamp_and_body() {
+        let body = r#"{"event":"ping"}"#;
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, body),
+            "aa8efe37b751e71157c508c5ac4acb1e9fe5225db98355dfc00f4b680afbc447"
+        );
+        assert_eq!(
+            sign("", 0, ""),
+            "b849d5a581847b281957065739df36df2463d1977ea8d6e1e4e6cf33fadc68c3"
+        );
+    }
+
+    #[test]
+    fn signatures_cover_every_part() {
+        let signature = sign("secret", 1, "{}");
+        assert_ne!(sign("other", 1, "{}"), signature);
+        assert_ne!(sign("secret", 2, "{}"), signature);
+        assert_ne!(sign("secret", 1, "{ }"), signature);
+        // The separator keeps digits from moving between timestamp and body
+        assert_ne!(sign("secret", 12, "3"), sign("secret", 1, "23"));
+    }
+
+    #[test]
+    fn retries_back_off_up_to_an_hour() {
+        assert_eq!(backoff(1), FIRST_BACKOFF);
+        assert_eq!(backoff(2), FIRST_BACKOFF * 2);
+        assert_eq!(backoff(4), FIRST_BACKOFF * 8);
+        assert_eq!(backoff(MAX_ATTEMPTS), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::disk;
+use crate::metrics::IpcTimer;
+
+/// Where the webview engine keeps its HTTP and script caches. Cookies,
+/// local storage and the backend's data all live elsewhere.
+fn cache_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let path = app.path();
+    if cfg!(windows) {
+        path.app_local_data_dir()
+            .map(|dir| {
+                let profile = dir.join("EBWebView").join("Default");
+                ["Cache", "Code Cache", "GPUCache"]
+                    .iter()
+                    .map(|name| profile.join(name))
+                    .chain([profile.join("Service Worker").join("CacheStorage")])
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else if cfg!(target_os = "macos") {
+        path.app_cache_dir()
+            .map(|dir| vec![dir.join("WebKit")])
+            .unwrap_or_default()
+    } else {
+        // WebKitGTK is given one folder for data and cache alike
+        path.app_local_data_dir()
+            .map(|dir| vec![dir.join("WebKitCache"), dir.join("CacheStorage")])
+            .unwrap_or_default()
+    }
+}
+
+/// Deletes the cache folders outright, for `--safe-mode`. Only safe before
+/// the first webview starts, as the engine keeps them open after that.
+pub fn clear_on_disk(app: &AppHandle) {
+    for dir in cache_dirs(app).into_iter().filter(|dir| dir.exists()) {
+        match fs::remove_dir_all(&dir) {
+            Ok(()) => tracing::info!("Safe mode: cleared {}", dir.display()),
+            Err(err) => tracing::warn!("Safe mode: failed to clear {}: {err}", dir.display()),
+        }
+    }
+}
+
+/// Bytes the webview caches take up on disk
+#[tauri::command]
+pub async fn get_cache_size(_timer: IpcTimer, app: AppHandle) -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        cache_dirs(&app).iter().map(|dir| disk::size_of(dir)).sum()
+    })
+    .await
+    .map_err(|err| err.to_string())
+}
+
+/// Empties the webview's caches, leaving cookies and local storage alone.
+/// Reload the page afterwards to fetch everything fresh.
+#[tauri::command]
+pub async fn clear_webview_cache(_timer: IpcTimer, window: WebviewWindow) -> Result<(), String> {
+    let (sender, receiver) = oneshot::channel();
+    window
+        .with_webview(move |webview| platform::clear_cache(webview, sender))
+        .map_err(|err| err.to_string())?;
+    receiver
+        .await
+        .map_err(|_| "Clearing the cache was cancelled".to_string())??;
+    tracing::info!("Cleared the webview cache");
+    Ok(())
+}
+
+/// Signs the webview out of everywhere by deleting all its cookies.
+/// Resolves with how many there were.
+#[tauri::command]
+pub async fn clear_cookies(_timer: IpcTimer, window: WebviewWindow) -> Result<usize, String> {
+    let cookies = window.cookies().map_err(|err| err.to_string())?;
+    let count = cookies.len();
+    for cookie in cookies {
+        window
+            .delete_cookie(cookie)
+            .map_err(|err| err.to_string())?;
+    }
+    tracing::info!("Cleared {count} cookies");
+    Ok(count)
+}
+
+type Done = oneshot::Sender<Result<(), String>>;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webkit2gtk::{
+        gio, glib, WebContextExt, WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes,
+    };
+
+    use super::Done;
+
+    pub fn clear_cache(webview: PlatformWebview, done: Done) {
+        let Some(manager) = webview
+            .inner()
+            .context()
+            .and_then(|context| context.website_data_manager())
+        else {
+            let _ = done.send(Err("The webview has no data manager".to_string()));
+            return;
+        };
+        let types = WebsiteDataTypes::DISK_CACHE
+            | WebsiteDataTypes::MEMORY_CACHE
+            | WebsiteDataTypes::OFFLINE_APPLICATION_CACHE;
+        // A timespan of 0 means everything, however old
+        manager.clear(
+            types,
+            glib::TimeSpan::from_seconds(0),
+            None::<&gio::Cancellable>,
+            move |result| {
+                let _ = done.send(result.map_err(|err| err.to_string()));
+            },
+        );
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::webview::PlatformWebview;
+    use webview2_com::ClearBrowsingDataCompletedHandler;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2Profile2, ICoreWebView2_13, COREWEBVIEW2_BROWSING_DATA_KINDS,
+        COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE,
+        COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE,
+    };
+    use windows::core::Interface;
+
+    use super::Done;
+
+    pub fn clear_cache(webview: PlatformWebview, done: Done) {
+        let done = std::sync::Mutex::new(Some(done));
+        let finish = move |result: Result<(), String>| {
+            if let Some(done) = done.lock().unwrap().take() {
+                let _ = done.send(result);
+            }
+        };
+        let finish = std::sync::Arc::new(finish);
+        let completed = finish.clone();
+        let handler = ClearBrowsingDataCompletedHandler::create(Box::new(move |result| {
+            completed(result.map_err(|err| err.to_string()));
+            Ok(())
+        }));
+        let kinds = COREWEBVIEW2_BROWSING_DATA_KINDS(
+            COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE.0
+                | COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE.0,
+        );
+        let result = unsafe {
+            webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_13>())
+                .and_then(|core| core.Profile())
+                .and_then(|profile| profile.cast::<ICoreWebView2Profile2>())
+                .and_then(|profile| profile.ClearBrowsingData(kinds, &handler))
+        };
+        if let Err(err) = result {
+            finish(Err(err.to_string()));
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, CString};
+
+    use block2::RcBlock;
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use tauri::webview::PlatformWebview;
+
+    use super::Done;
+
+    // The values of the `WKWebsiteDataType…` constants are their names
+    const TYPES: &[&str] = &[
+        "WKWebsiteDataTypeDiskCache",
+        "WKWebsiteDataTypeMemoryCache",
+        "WKWebsiteDataTypeFetchCache",
+        "WKWebsiteDataTypeOfflineWebApplicationCache",
+    ];
+
+    pub fn clear_cache(webview: PlatformWebview, done: Done) {
+        unsafe {
+            let view = &*(webview.inner() as *const AnyObject);
+            let configuration: Retained<AnyObject> = msg_send![view, configuration];
+            let store: Retained<AnyObject> = msg_send![&*configuration, websiteDataStore];
+            let types: Retained<AnyObject> = msg_send![class!(NSMutableSet), set];
+            for name in TYPES {
+                let Ok(name) = CString::new(*name) else {
+                    continue;
+                };
+                let ptr: *const c_char = name.as_ptr();
+                let name: Option<Retained<AnyObject>> =
+                    msg_send![class!(NSString), stringWithUTF8String: ptr];
+                if let Some(name) = name {
+                    let _: () = msg_send![&*types, addObject: &*name];
+                }
+            }
+            let since: Retained<AnyObject> = msg_send![class!(NSDate), distantPast];
+            let done = std::cell::Cell::new(Some(done));
+            let completed = RcBlock::new(move || {
+                if let Some(done) = done.take() {
+                    let _ = done.send(Ok(()));
+                }
+            });
+            let _: () = msg_send![
+                &*store,
+                removeDataOfTypes: &*types,
+                modifiedSince: &*since,
+                completionHandler: &*completed
+            ];
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use tauri::webview::PlatformWebview;
+
+    use super::Done;
+
+    pub fn clear_cache(_webview: PlatformWebview, done: Done) {
+        let _ = done.send(Err("Clearing the cache is not supported here".to_string()));
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, LogicalSize, Manager, PhysicalPosition, PhysicalSize, WebviewWindow,
+    Window, WindowEvent,
+};
+
+use crate::data_dir;
+use crate::kiosk;
+use crate::main_window;
+
+const WIDGET_FILE: &str = "widget.json";
+// Logical pixels
+const DEFAULT_WIDTH: f64 = 360.0;
+const DEFAULT_HEIGHT: f64 = 520.0;
+const MIN_WIDTH: f64 = 280.0;
+const MIN_HEIGHT: f64 = 320.0;
+// Gap to the screen edge when first placed in the top-right corner
+const MARGIN: i32 = 16;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Placement {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// The main window as it was before becoming a widget, to go back to
+struct Normal {
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WidgetModeChanged {
+    active: bool,
+}
+
+/// The main window's compact, always-on-top mode. Where the widget sits is
+/// kept across restarts; the mode itself isn't, so a launch always opens
+/// the full window.
+pub struct Widget {
+    path: Option<PathBuf>,
+    placement: Mutex<Option<Placement>>,
+    normal: Mutex<Option<Normal>>,
+}
+
+impl Widget {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let Some(placement) = *self.placement.lock().unwrap() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&placement)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(WIDGET_FILE));
+    let placement = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+
+    app.manage(Widget {
+        path,
+        placement: Mutex::new(placement),
+        normal: Mutex::new(None),
+    });
+}
+
+/// Whether the main window is a widget right now. Its geometry then isn't
+/// the one `window_state` should remember.
+pub fn is_active(app: &AppHandle) -> bool {
+    app.state::<Widget>().normal.lock().unwrap().is_some()
+}
+
+fn enter(window: &WebviewWindow) -> tauri::Result<()> {
+    let widget = window.state::<Widget>();
+    let normal = Normal {
+        position: window.outer_position()?,
+        size: window.inner_size()?,
+        maximized: window.is_maximized()?,
+        fullscreen: window.is_fullscreen()?,
+    };
+    if normal.fullscreen {
+        window.set_fullscreen(false)?;
+    }
+    if normal.maximized {
+        window.unmaximize()?;
+    }
+    *widget.normal.lock().unwrap() = Some(normal);
+
+    window.set_decorations(false)?;
+    window.set_always_on_top(true)?;
+    window.set_min_size(Some(LogicalSize::new(MIN_WIDTH, MIN_HEIGHT)))?;
+
+    let saved = *widget.placement.lock().unwrap();
+    match saved.filter(|placement| on_screen(window, placement)) {
+        Some(placement) => {
+            window.set_size(PhysicalSize::new(placement.width, placement.height))?;
+            window.set_position(PhysicalPosition::new(placement.x, placement.y))?;
+        }
+        None => {
+            window.set_size(LogicalSize::new(DEFAULT_WIDTH, DEFAULT_HEIGHT))?;
+            if let Some(monitor) = window.current_monitor()? {
+                let area = monitor.work_area();
+                let width = window.outer_size()?.width as i32;
+                window.set_position(PhysicalPosition::new(
+                    area.position.x + area.size.width as i32 - width - MARGIN,
+                    area.position.y + MARGIN,
+                ))?;
+            }
+        }
+    }
+    window.set_focus()
+}
+
+fn leave(window: &WebviewWindow) -> tauri::Result<()> {
+    let widget = window.state::<Widget>();
+    record(&widget, &window.as_ref().window());
+    if let Err(err) = widget.save() {
+        tracing::warn!("Failed to save the widget position: {err}");
+    }
+    let Some(normal) = widget.normal.lock().unwrap().take() else {
+        return Ok(());
+    };
+
+    let config = main_window::config(window.app_handle());
+    window.set_always_on_top(false)?;
+    window.set_decorations(config.decorations)?;
+    let min_size = config
+        .min_width
+        .zip(config.min_height)
+        .map(|(width, height)| LogicalSize::new(width, height));
+    window.set_min_size(min_size)?;
+    window.set_size(normal.size)?;
+    window.set_position(normal.position)?;
+    if normal.maximized {
+        window.maximize()?;
+    }
+    if normal.fullscreen {
+        window.set_fullscreen(true)?;
+    }
+    Ok(())
+}
+
+// Enough of the widget has to be on a monitor to grab it, otherwise it goes
+// back to the default corner
+fn on_screen(window: &WebviewWindow, placement: &Placement) -> bool {
+    let monitors = window.available_monitors().unwrap_or_default();
+    monitors.iter().any(|monitor| {
+        let area = monitor.work_area();
+        let (left, top) = (area.position.x, area.position.y);
+        let right = left + area.size.width as i32;
+        let bottom = top + area.size.height as i32;
+        placement.x + MARGIN >= left
+            && placement.x + placement.width as i32 - MARGIN <= right
+            && placement.y >= top
+            && placement.y + MARGIN <= bottom
+    })
+}
+
+fn record(widget: &Widget, window: &Window) {
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    *widget.placement.lock().unwrap() = Some(Placement {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    });
+}
+
+/// Follows the widget around while the main window is one
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != main_window::LABEL || !is_active(window.app_handle()) {
+        return;
+    }
+    let widget = window.state::<Widget>();
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => record(&widget, window),
+        WindowEvent::CloseRequested { .. } => {
+            if let Err(err) = widget.save() {
+                tracing::warn!("Failed to save the widget position: {err}");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Turns the main window into a small frameless panel that stays on top of
+/// other windows, or back. Resolves with whether it is a widget now, and
+/// emits `widget-mode-changed` so the frontend can offer a drag handle.
+#[tauri::command]
+pub fn toggle_widget_mode(app: AppHandle) -> Result<bool, String> {
+    if kiosk::is_active(&app) {
+        return Err("Widget mode isn't available in kiosk mode".to_string());
+    }
+    let window = app
+        .get_webview_window(main_window::LABEL)
+        .ok_or("The main window isn't open")?;
+    let active = !is_active(&app);
+    let result = if active {
+        enter(&window)
+    } else {
+        leave(&window)
+    };
+    result.map_err(|err| err.to_string())?;
+    let _ = app.emit("widget-mode-changed", WidgetModeChanged { active });
+    Ok(active)
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, LogicalSize, Manager, Monitor, PhysicalPosition, PhysicalSize, State, WebviewWindow,
+    Window, WindowEvent,
+};
+
+use crate::data_dir;
+use crate::kiosk;
+use crate::main_window;
+use crate::widget;
+
+const STATE_FILE: &str = "window-state.json";
+// This much of the title bar has to be on a monitor for a saved position to
+// be trusted, otherwise the window is re-centered.
+const MIN_VISIBLE: i32 = 100;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Geometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+    monitor: Option<String>,
+}
+
+// The part of a monitor a window may be on
+struct Area {
+    name: Option<String>,
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+}
+
+impl From<&Monitor> for Area {
+    fn from(monitor: &Monitor) -> Self {
+        Self {
+            name: monitor.name().cloned(),
+            position: *monitor.position(),
+            size: *monitor.size(),
+        }
+    }
+}
+
+pub struct WindowState {
+    path: Option<PathBuf>,
+    windows: Mutex<HashMap<String, Geometry>>,
+}
+
+impl WindowState {
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let windows = self.windows.lock().unwrap();
+        fs::write(path, serde_json::to_string_pretty(&*windows)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(STATE_FILE));
+    let windows = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+
+    app.manage(WindowState {
+        path,
+        windows: Mutex::new(windows),
+    });
+}
+
+/// Every window's saved geometry, for `config_bundle`
+pub fn export(app: &AppHandle) -> serde_json::Value {
+    let state = app.state::<WindowState>();
+    let windows = state.windows.lock().unwrap();
+    serde_json::to_value(&*windows).unwrap_or_default()
+}
+
+/// Replaces the saved geometry with `windows` as `export` gave it. It's
+/// applied when each window next opens, and dropped as usual for positions
+/// off this machine's monitors.
+pub fn import(app: &AppHandle, windows: serde_json::Value) -> Result<(), String> {
+    let windows: HashMap<String, Geometry> =
+        serde_json::from_value(windows).map_err(|err| err.to_string())?;
+    let state = app.state::<WindowState>();
+    *state.windows.lock().unwrap() = windows;
+    state.save().map_err(|err| err.to_string())
+}
+
+/// Applies the saved geometry for `window`, if any. Positions that no longer
+/// land on a connected monitor are dropped so the window can't open off-screen.
+pub fn restore(window: &WebviewWindow) {
+    let state = window.state::<WindowState>();
+    let Some(geometry) = state.windows.lock().unwrap().get(window.label()).cloned() else {
+        return;
+    };
+
+    let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+
+    let monitors: Vec<Area> = window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(Area::from)
+        .collect();
+    if is_visible(&geometry, &monitors) {
+        let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    } else {
+        let _ = window.center();
+    }
+
+    if geometry.maximized {
+        let _ = window.maximize();
+    }
+}
+
+fn is_visible(geometry: &Geometry, monitors: &[Area]) -> bool {
+    if let Some(name) = &geometry.monitor {
+        if !monitors
+            .iter()
+            .any(|monitor| monitor.name.as_ref() == Some(name))
+        {
+            return false;
+        }
+    }
+
+    monitors.iter().any(|monitor| {
+        let position = monitor.position;
+        let size = monitor.size;
+        let right = position.x + size.width as i32;
+        let bottom = position.y + size.height as i32;
+
+        geometry.x + geometry.width as i32 - MIN_VISIBLE >= position.x
+            && geometry.x + MIN_VISIBLE <= right
+            && geometry.y >= position.y
+            && geometry.y + MIN_VISIBLE <= bottom
+    })
+}
+
+fn record(window: &Window) {
+    if window.is_minimized().unwrap_or(false)
+        || widget::is_active(window.app_handle())
+        || kiosk::is_active(window.app_handle())
+    {
+        return;
+    }
+    let state = window.state::<WindowState>();
+    let mut windows = state.windows.lock().unwrap();
+    let maximized = window.is_maximized().unwrap_or(false);
+
+    // While maximized, keep the last normal geometry so un-maximizing after
+    // a restart goes back to it rather than to a full-screen-sized window.
+    if maximized {
+        if let Some(geometry) = windows.get_mut(window.label()) {
+            geometry.maximized = true;
+            return;
+        }
+    }
+
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|monitor| monitor.name().cloned());
+
+    windows.insert(
+        window.label().to_string(),
+        Geometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized,
+            monitor,
+        },
+    );
+}
+
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => record(window),
+        WindowEvent::CloseRequested { .. } => {
+            record(window);
+            save(window.app_handle());
+        }
+        _ => {}
+    }
+}
+
+pub fn save(app: &AppHandle) {
+    // Not there when startup stopped early
+    let Some(state) = app.try_state::<WindowState>() else {
+        return;
+    };
+    if let Err(err) = state.save() {
+        tracing::warn!("Failed to save window state: {err}");
+    }
+}
+
+/// Forgets all saved geometry and puts the main window back at its default
+/// size in the middle of the screen, for when it ended up somewhere unreachable.
+#[tauri::command]
+pub fn reset_window_state(app: AppHandle, state: State<'_, WindowState>) -> Result<(), String> {
+    state.windows.lock().unwrap().clear();
+    state.save().map_err(|err| err.to_string())?;
+
+    if let Some(window) = app.get_webview_window(main_window::LABEL) {
+        let config = main_window::config(&app);
+        let _ = window.unmaximize();
+        let _ = window.set_size(LogicalSize::new(config.width, config.height));
+        let _ = window.center();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, x: i32, y: i32, width: u32, height: u32) -> Area {
+        Area {
+            name: Some(name.to_string()),
+            position: PhysicalPosition::new(x, y),
+            size: PhysicalSize::new(width, height),
+        }
+    }
+
+    fn at(x: i32, y: i32, monitor: Option<&str>) -> Geometry {
+        Geometry {
+            x,
+            y,
+            width: 800,
+            height: 600,
+            maximized: false,
+            monitor: monitor.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn positions_on_a_monitor_are_kept() {
+        let monitors = [
+            monitor("left", -1920, 0, 1920, 1080),
+            monitor("main", 0, 0, 2560, 1440),
+        ];
+        assert!(is_visible(&at(100, 100, Some("main")), &monitors));
+        assert!(is_visible(&at(-1000, 200, Some("left")), &monitors));
+        assert!(is_visible(&at(100, 100, None), &monitors));
+    }
+
+    #[test]
+    fn enough_of_the_title_bar_has_to_show() {
+        let monitors = [monitor("main", 0, 0, 1920, 1080)];
+        // Hanging off the right, left and bottom edges
+        assert!(is_visible(&at(1920 - MIN_VISIBLE, 0, None), &monitors));
+        assert!(!is_visible(&at(1920 - MIN_VISIBLE + 1, 0, None), &monitors));
+        assert!(is_visible(&at(MIN_VISIBLE - 800, 0, None), &monitors));
+        assert!(!is_visible(&at(MIN_VISIBLE - 801, 0, None), &monitors));
+        assert!(is_visible(&at(0, 1080 - MIN_VISIBLE, None), &monitors));
+        assert!(!is_visible(&at(0, 1080 - MIN_VISIBLE + 1, None), &monitors));
+        // The title bar above the top can't be grabbed at all
+        assert!(!is_visible(&at(0, -1, None), &monitors));
+    }
+
+    #[test]
+    fn positions_on_a_disconnected_monitor_are_dropped() {
+        let monitors = [monitor("main", 0, 0, 1920, 1080)];
+        assert!(!is_visible(&at(100, 100, Some("projector")), &monitors));
+        assert!(!is_visible(&at(2200, 100, None), &monitors));
+        assert!(!is_visible(&at(0, 0, None), &[]));
+    }
+}
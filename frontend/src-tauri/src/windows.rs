@@ -0,0 +1,200 @@
+use serde::Serialize;
+use tauri::{
+    AppHandle, Emitter, Manager, WebviewWindow, WebviewWindowBuilder, Window, WindowEvent,
+};
+
+use crate::frontend_bundle;
+use crate::lock;
+use crate::main_window;
+use crate::proxy;
+use crate::quick_capture;
+use crate::quick_open;
+use crate::settings::SettingsStore;
+use crate::spellcheck;
+use crate::splash;
+use crate::tabs;
+use crate::theme;
+use crate::tls;
+use crate::window_state;
+use crate::zoom;
+
+const DEFAULT_WIDTH: f64 = 900.0;
+const DEFAULT_HEIGHT: f64 = 700.0;
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+enum WindowLifecycle<'a> {
+    Opened { label: &'a str },
+    Focused { label: &'a str, focused: bool },
+    Closed { label: &'a str },
+}
+
+/// Whether `label` is free for a detached window, not one the app manages
+pub fn validate_label(label: &str) -> Result<(), String> {
+    if label.is_empty()
+        || !label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid window label {label:?}"));
+    }
+    if [
+        main_window::LABEL,
+        quick_capture::LABEL,
+        quick_open::LABEL,
+        lock::LABEL,
+        splash::LABEL,
+    ]
+    .contains(&label)
+    {
+        return Err(format!("The {label:?} window is managed by the app"));
+    }
+    if label.starts_with(tabs::LABEL_PREFIX) {
+        return Err(format!(
+            "Labels starting with {:?} are kept for tabs",
+            tabs::LABEL_PREFIX
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `route` is a path within the app. Anything that resolves
+/// elsewhere would load an arbitrary site with access to the backend token.
+pub fn validate_route(route: &str) -> Result<(), String> {
+    let lower = route.to_ascii_lowercase();
+    let valid = route.starts_with('/')
+        && !route.starts_with("//")
+        // Windows and the WHATWG parser read `\` as `/`
+        && !route.contains('\\')
+        && !lower.contains("%2f")
+        && !lower.contains("%5c")
+        && !route.chars().any(char::is_control)
+        && frontend_bundle::resolve(route).is_some();
+    if !valid {
+        return Err(format!(
+            "Route must be an app path like /inbox, got {route:?}"
+        ));
+    }
+    Ok(())
+}
+
+/// Opens a detached window showing `route` (e.g. `/email/123`) of the
+/// frontend, or focuses it if a window with this label is already open.
+/// Every window talks to the same backend as the main one.
+#[tauri::command]
+pub fn open_window(
+    app: AppHandle,
+    label: String,
+    route: String,
+    title: Option<String>,
+    width: Option<f64>,
+    height: Option<f64>,
+) -> Result<(), String> {
+    validate_label(&label)?;
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    create(&app, &label, &route, title, width, height)?;
+    Ok(())
+}
+
+/// Builds a window showing `route` with everything a frontend window needs
+pub fn create(
+    app: &AppHandle,
+    label: &str,
+    route: &str,
+    title: Option<String>,
+    width: Option<f64>,
+    height: Option<f64>,
+) -> Result<WebviewWindow, String> {
+    validate_route(route)?;
+    let script = main_window::initialization_script(app).map_err(|err| err.to_string())?;
+    let window = WebviewWindowBuilder::new(app, label, frontend_bundle::url(route))
+        .title(title.unwrap_or_else(|| app.package_info().name.clone()))
+        .inner_size(
+            width.unwrap_or(DEFAULT_WIDTH),
+            height.unwrap_or(DEFAULT_HEIGHT),
+        )
+        .initialization_script(script)
+        .background_color(theme::background(
+            app.state::<SettingsStore>().get().theme_override,
+        ))
+        .build()
+        .map_err(|err| err.to_string())?;
+    tls::trust_backend(&window);
+    proxy::configure(&window);
+    spellcheck::configure(&window);
+    zoom::restore(&window);
+    window_state::restore(&window);
+
+    let _ = app.emit("window-lifecycle", WindowLifecycle::Opened { label });
+    Ok(window)
+}
+
+#[tauri::command]
+pub fn close_window(app: AppHandle, label: String) -> Result<(), String> {
+    validate_label(&label)?;
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("No window labelled {label:?}"))?;
+    window.close().map_err(|err| err.to_string())
+}
+
+/// Labels of all open windows, including the main one
+#[tauri::command]
+pub fn list_windows(app: AppHandle) -> Vec<String> {
+    app.webview_windows().into_keys().collect()
+}
+
+/// Emits `window-lifecycle` as windows gain or lose focus and close, so every
+/// window can keep track of the others
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let label = window.label();
+    let lifecycle = match event {
+        WindowEvent::Focused(focused) => WindowLifecycle::Focused {
+            label,
+            focused: *focused,
+        },
+        WindowEvent::Destroyed => WindowLifecycle::Closed { label },
+        _ => return,
+    };
+    let _ = window.app_handle().emit("window-lifecycle", lifecycle);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_routes_are_valid() {
+        for route in [
+            "/",
+            "/inbox",
+            "/email/123?thread=1#reply",
+            "/search?q=a%20b",
+        ] {
+            assert!(validate_route(route).is_ok(), "{route:?}");
+        }
+    }
+
+    #[test]
+    fn routes_leaving_the_app_are_not() {
+        for route in [
+            "",
+            "inbox",
+            "https://evil.com",
+            "//evil.com/x",
+            "/\\evil.com/x",
+            "/\\\\evil.com",
+            "/%2f%2fevil.com",
+            "/%5C%5Cevil.com",
+            "/inbox\n",
+        ] {
+            assert!(validate_route(route).is_err(), "{route:?}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::data_dir;
+
+const ZOOM_FILE: &str = "zoom.json";
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+const STEP: f64 = 0.1;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ZoomChanged {
+    factor: f64,
+}
+
+/// Zoom factor per window label, kept across restarts
+pub struct Zoom {
+    path: Option<PathBuf>,
+    levels: Mutex<HashMap<String, f64>>,
+}
+
+impl Zoom {
+    fn get(&self, label: &str) -> f64 {
+        self.levels
+            .lock()
+            .unwrap()
+            .get(label)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let levels = self.levels.lock().unwrap();
+        fs::write(path, serde_json::to_string_pretty(&*levels)?)
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let path = data_dir::config_dir(app).map(|dir| dir.join(ZOOM_FILE));
+    let levels = path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+
+    app.manage(Zoom {
+        path,
+        levels: Mutex::new(levels),
+    });
+}
+
+/// Applies the saved zoom factor for `window`, if it has one
+pub fn restore(window: &WebviewWindow) {
+    let factor = window.state::<Zoom>().get(window.label());
+    if factor != 1.0 {
+        let _ = window.set_zoom(factor);
+    }
+}
+
+/// Zooms `window` to `factor`, clamped to a usable range, and remembers it.
+/// Returns the factor that was applied.
+pub fn set(window: &WebviewWindow, factor: f64) -> Result<f64, String> {
+    if !factor.is_finite() {
+        return Err("Zoom factor must be a number".to_string());
+    }
+    // Rounded so repeated steps don't drift to 1.0999999
+    let factor = (factor.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0;
+    window.set_zoom(factor).map_err(|err| err.to_string())?;
+
+    let zoom = window.state::<Zoom>();
+    zoom.levels
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string(), factor);
+    if let Err(err) = zoom.save() {
+        tracing::warn!("Failed to save zoom levels: {err}");
+    }
+    let _ = window.emit_to(window.label(), "zoom-changed", ZoomChanged { factor });
+    Ok(factor)
+}
+
+/// Zooms the focused window in or out by one step, or back to 100% for
+/// `None`. Used by the View menu accelerators.
+pub fn step(app: &AppHandle, direction: Option<f64>) {
+    let Some(window) = app
+        .webview_windows()
+        .into_values()
+        .find(|window| window.is_focused().unwrap_or(false))
+    else {
+        return;
+    };
+    let factor = match direction {
+        Some(direction) => app.state::<Zoom>().get(window.label()) + direction * STEP,
+        None => 1.0,
+    };
+    if let Err(err) = set(&window, factor) {
+        tracing::warn!("Failed to zoom {}: {err}", window.label());
+    }
+}
+
+#[tauri::command]
+pub fn set_zoom(window: WebviewWindow, factor: f64) -> Result<f64, String> {
+    set(&window, factor)
+}
+
+#[tauri::command]
+pub fn get_zoom(window: WebviewWindow, zoom: State<'_, Zoom>) -> f64 {
+    zoom.get(window.label())
+}
@@ -8,6 +8,16 @@ declare global {
 		// interface PageState {}
 		// interface Platform {}
 	}
+
+	interface Window {
+		// Injected by the desktop app, which runs the backend on a dynamic port
+		__BACKEND_URL__?: string;
+		// Required by the desktop app's backend on every request
+		__BACKEND_TOKEN__?: string;
+		__THEME__?: "light" | "dark" | null;
+		// Set when the desktop app was started in safe mode
+		__SAFE_MODE__?: boolean;
+	}
 }
 
 export {};
@@ -1,4 +1,31 @@
-const API_URL = import.meta.env.VITE_API_URL ?? "http://localhost:8877";
+import { mirrorRead } from "$lib/desktopCanary";
+
+export const API_URL = window.__BACKEND_URL__ ?? import.meta.env.VITE_API_URL ?? "http://localhost:8877";
+const PAIR_TOKEN_KEY = "lan.pairToken";
+const BACKEND_TOKEN = window.__BACKEND_TOKEN__ ?? pairingToken();
+
+// A phone paired with the desktop app opens `/?pair=<token>` from its QR
+// code; the token is kept so reloads stay paired
+function pairingToken(): string | undefined {
+  if (typeof localStorage === "undefined") return undefined;
+  const url = new URL(window.location.href);
+  const token = url.searchParams.get("pair");
+  if (token) {
+    localStorage.setItem(PAIR_TOKEN_KEY, token);
+    url.searchParams.delete("pair");
+    history.replaceState(history.state, "", url);
+  }
+  return localStorage.getItem(PAIR_TOKEN_KEY) ?? undefined;
+}
+
+// For URLs loaded without fetch (images, WebSockets), which can't send the token header
+export function apiUrl(path: string): string {
+  if (!BACKEND_TOKEN) {
+    return `${API_URL}${path}`;
+  }
+  const separator = path.includes("?") ? "&" : "?";
+  return `${API_URL}${path}${separator}token=${encodeURIComponent(BACKEND_TOKEN)}`;
+}
 
 export class AuthError extends Error {
   needsReauth: boolean;
@@ -16,6 +43,7 @@ async function request<T>(path: string, options?: RequestInit): Promise<T> {
     credentials: "include",
     headers: {
       "Content-Type": "application/json",
+      ...(BACKEND_TOKEN && { "X-Hamba-Token": BACKEND_TOKEN }),
       ...options?.headers,
     },
   });
@@ -23,6 +51,10 @@ async function request<T>(path: string, options?: RequestInit): Promise<T> {
   if (!res.ok) {
     throw new Error(`API error: ${res.status}`);
   }
+  // Compared against a canary backend, if the desktop app runs one
+  if ((options?.method ?? "GET") === "GET") {
+    mirrorRead(path);
+  }
 
   const data = await res.json();
 
@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/accessibility.rs. The desktop app reads the
+// OS's high contrast and reduced motion settings, which webviews don't all
+// expose through `prefers-contrast` and `prefers-reduced-motion`.
+
+/** Mirrors `AccessibilityPrefs` in src-tauri/src/accessibility.rs */
+export interface AccessibilityPrefs {
+  highContrast: boolean;
+  reducedMotion: boolean;
+  /** Whether to use the forced-colors palette: the override if set, `highContrast` otherwise */
+  forcedColors: boolean;
+  forcedColorsOverride: boolean | null;
+}
+
+export function getAccessibilityPrefs(): Promise<AccessibilityPrefs> {
+  return invoke<AccessibilityPrefs>("get_accessibility_prefs");
+}
+
+/** `null` follows the OS's high contrast setting again */
+export function setForcedColorsOverride(enabled: boolean | null): Promise<AccessibilityPrefs> {
+  return invoke<AccessibilityPrefs>("set_forced_colors_override", { enabled });
+}
+
+/** Emitted when the OS settings or the override change */
+export function onAccessibilityChanged(handler: (prefs: AccessibilityPrefs) => void): Promise<UnlistenFn> {
+  return listen<AccessibilityPrefs>("accessibility-changed", (event) => handler(event.payload));
+}
+
+/** Sets the `data-forced-colors` and `data-reduced-motion` attributes app.css styles by */
+export function applyAccessibilityPrefs(prefs: AccessibilityPrefs) {
+  document.documentElement.toggleAttribute("data-forced-colors", prefs.forcedColors);
+  document.documentElement.toggleAttribute("data-reduced-motion", prefs.reducedMotion);
+}
@@ -0,0 +1,37 @@
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/attachments.rs. Files are copied into the data
+// directory once and then loaded through the `attachment` protocol, so
+// `<img>` and `<video>` read them straight from disk instead of over IPC.
+
+/** Mirrors `Attachment` in src-tauri/src/attachments.rs */
+export interface Attachment {
+  /** SHA-256 of the contents */
+  id: string;
+  name: string;
+  mimeType: string;
+  size: number;
+}
+
+/** Copies the file at `path`, e.g. from the file dialog, into the store */
+export function importAttachment(path: string): Promise<Attachment> {
+  return invoke<Attachment>("import_attachment", { path });
+}
+
+export function getAttachment(id: string): Promise<Attachment> {
+  return invoke<Attachment>("get_attachment", { id });
+}
+
+export function deleteAttachment(id: string): Promise<void> {
+  return invoke("delete_attachment", { id });
+}
+
+/** For `src`; supports range requests, so video and audio can seek */
+export function attachmentUrl(id: string): string {
+  return convertFileSrc(id, "attachment");
+}
+
+/** A PNG at most `size` pixels on its longer side; PNG and JPEG only */
+export function attachmentThumbnailUrl(id: string, size = 256): string {
+  return `${convertFileSrc(`${id}/thumbnail`, "attachment")}?size=${size}`;
+}
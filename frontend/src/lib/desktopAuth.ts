@@ -0,0 +1,23 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/auth.rs. Gate sensitive screens and exports
+// behind `authenticate`; within `authGraceMinutes` of the last success it
+// resolves without asking again.
+
+/**
+ * Asks for Touch ID, Windows Hello or the account password. On macOS
+ * `reason` completes "Hamba is trying to ...", e.g. "export your mail".
+ * Rejects if the user cancels or the OS has no way to ask.
+ */
+export function authenticate(reason: string): Promise<void> {
+  return invoke("authenticate", { reason });
+}
+
+export function isAuthenticated(): Promise<boolean> {
+  return invoke<boolean>("is_authenticated");
+}
+
+/** Makes the next `authenticate` ask again */
+export function forgetAuthentication(): Promise<void> {
+  return invoke("forget_authentication");
+}
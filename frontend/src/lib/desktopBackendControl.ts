@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/backend_control.rs. Changes reach the running
+// backend right away when they can and are passed to it on every start.
+
+export type BackendLogLevel = "debug" | "info" | "warn" | "error";
+
+/** Mirrors `BackendControls` in src-tauri/src/backend_control.rs */
+export interface BackendControls {
+  /** `null` when the backend picks its own */
+  logLevel: BackendLogLevel | null;
+  flags: Record<string, boolean>;
+}
+
+export function getBackendControls(): Promise<BackendControls> {
+  return invoke<BackendControls>("get_backend_controls");
+}
+
+/** Resolves with whether the running backend took it without a restart */
+export function setBackendLogLevel(level: BackendLogLevel | null): Promise<boolean> {
+  return invoke<boolean>("set_backend_log_level", { level });
+}
+
+/** `null` forgets the flag. Resolves like `setBackendLogLevel`. */
+export function setBackendFlag(name: string, value: boolean | null): Promise<boolean> {
+  return invoke<boolean>("set_backend_flag", { name, value });
+}
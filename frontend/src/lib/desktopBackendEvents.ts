@@ -0,0 +1,43 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/backend_events.rs. The desktop app keeps the one
+// connection to the backend's `/ws` channel and forwards each message to every
+// window as `backend-event`, numbered so a reloaded window can catch up.
+
+/** The backend's realtime messages, see backend/src/services/realtime.ts */
+export type BackendMessage =
+  | { type: "new_mail"; accountId: string; email?: { from: string; subject: string; isImportant: boolean } }
+  | { type: "sync_complete"; accountId: string; count: number }
+  | { type: "background_sync_progress"; accountId: string; synced: number; total: number; percentage: number }
+  | { type: "notification"; category: string; title: string; body?: string }
+  | { type: "badge"; count: number }
+  /** Shown in the tray icon and menu by src-tauri/src/tray.rs */
+  | { type: "tray"; title: string; tooltip: string; items: { title: string; url: string }[] }
+  /** Added by the desktop app when the connection is back, e.g. after a backend restart */
+  | { type: "reconnected" };
+
+/** Mirrors `BackendEvent` in src-tauri/src/backend_events.rs */
+export type BackendEvent = BackendMessage & { seq: number };
+
+/** Mirrors `Replay` in src-tauri/src/backend_events.rs */
+export interface BackendEventReplay {
+  events: BackendEvent[];
+  latest: number;
+  /** `false` when events were missed for good and the window should reload its data */
+  complete: boolean;
+  connected: boolean;
+}
+
+/** Buffered events after `since`; 0 gives all of them */
+export function getBackendEvents(since = 0): Promise<BackendEventReplay> {
+  return invoke<BackendEventReplay>("get_backend_events", { since });
+}
+
+export function onBackendEvent(handler: (event: BackendEvent) => void): Promise<UnlistenFn> {
+  return listen<BackendEvent>("backend-event", (event) => handler(event.payload));
+}
+
+export function onBackendConnection(handler: (connected: boolean) => void): Promise<UnlistenFn> {
+  return listen<{ connected: boolean }>("backend-connection", (event) => handler(event.payload.connected));
+}
@@ -0,0 +1,70 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/canary.rs. A canary is a second backend build
+// run next to the real one on a copy of the data; while it runs, a share of
+// the reads made through api.ts is compared against it.
+
+/** Mirrors `Mismatch` in src-tauri/src/canary.rs */
+export interface CanaryMismatch {
+  path: string;
+  /** Seconds since the Unix epoch */
+  at: number;
+  primaryStatus: number;
+  canaryStatus: number;
+  /** e.g. "Differs at /emails/3/subject" */
+  difference: string;
+}
+
+/** Mirrors `CanaryReport` in src-tauri/src/canary.rs */
+export interface CanaryReport {
+  running: boolean;
+  binary: string | null;
+  port: number | null;
+  fraction: number;
+  startedAt: number | null;
+  mirrored: number;
+  matched: number;
+  mismatched: number;
+  failed: number;
+  /** Newest first */
+  mismatches: CanaryMismatch[];
+}
+
+let running = false;
+
+/** `fraction` of reads, above 0 and up to 1, go to the canary as well */
+export function startCanary(path: string, sha256: string, fraction: number): Promise<CanaryReport> {
+  return invoke<CanaryReport>("start_canary", { path, sha256, fraction });
+}
+
+export function getCanaryReport(): Promise<CanaryReport> {
+  return invoke<CanaryReport>("get_canary_report");
+}
+
+export function stopCanary(): Promise<CanaryReport> {
+  return invoke<CanaryReport>("stop_canary");
+}
+
+/** Swaps the canary's build in for the real backend */
+export function promoteCanary(): Promise<void> {
+  return invoke("promote_canary");
+}
+
+/** Keeps track of whether a canary runs, so reads are only handed to the
+ * shell while one does */
+export function trackCanary(): Promise<UnlistenFn> {
+  getCanaryReport()
+    .then((report) => (running = report.running))
+    .catch(() => {});
+  return listen<{ running: boolean }>("canary-changed", (event) => {
+    running = event.payload.running;
+  });
+}
+
+/** Called by api.ts after each successful read; the shell picks which to
+ * mirror */
+export function mirrorRead(path: string): void {
+  if (!running) return;
+  invoke("mirror_read", { path }).catch(() => {});
+}
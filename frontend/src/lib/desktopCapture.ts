@@ -0,0 +1,23 @@
+import { invoke } from "@tauri-apps/api/core";
+import { showToast } from "$lib/stores";
+import { errorMessage } from "$lib/desktopErrors";
+
+/**
+ * Asks where to save a screenshot of this window bundled with recent logs,
+ * for attaching to a support ticket
+ */
+export async function saveBugReport(): Promise<void> {
+  const path = await invoke<string | null>("save_file_dialog", {
+    title: "Save bug report",
+    defaultName: "hamba-bug-report.zip",
+    filters: [{ name: "Zip archive", extensions: ["zip"] }],
+  });
+  if (!path) return;
+
+  try {
+    await invoke("capture_window", { path, includeLogs: true });
+    showToast("Bug report saved", "success");
+  } catch (e) {
+    showToast(`Failed to save the bug report: ${errorMessage(e)}`, "error");
+  }
+}
@@ -0,0 +1,21 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for the clipboard commands in src-tauri/src/clipboard.rs, which
+// handle the formats the webview's own clipboard access doesn't
+
+/** Copies rich text, with `text` for apps that only take plain text */
+export function copyHtml(html: string, text: string): Promise<void> {
+  return invoke("write_clipboard_html", { html, text });
+}
+
+/** Copies a PNG image */
+export async function copyImage(png: Blob): Promise<void> {
+  await invoke("write_clipboard_image", new Uint8Array(await png.arrayBuffer()));
+}
+
+/** The clipboard's image as a PNG file, or null when it holds none */
+export async function pasteImage(filename = "pasted-image.png"): Promise<File | null> {
+  const png = await invoke<ArrayBuffer>("read_clipboard_image");
+  if (png.byteLength === 0) return null;
+  return new File([png], filename, { type: "image/png" });
+}
@@ -0,0 +1,59 @@
+import { invoke } from "@tauri-apps/api/core";
+import { loadCustomBindings } from "$lib/keyboardShortcuts";
+import { refreshKeyMaps } from "$lib/keyboard";
+
+// Wrappers for src-tauri/src/config_bundle.rs. Keybindings live in
+// localStorage, so they're handed over from here and put back here.
+
+const KEYBINDINGS_KEY = "settings.keyboardShortcuts";
+
+/** Mirrors `ConfigImport` in src-tauri/src/config_bundle.rs */
+export interface ConfigImport {
+  keybindings: Record<string, string> | null;
+  profilesCreated: string[];
+  /** Settings from a newer version that this one doesn't have */
+  skippedSettings: string[];
+}
+
+function storedKeybindings(): Record<string, string> | null {
+  try {
+    const stored = localStorage.getItem(KEYBINDINGS_KEY);
+    return stored ? JSON.parse(stored) : null;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Asks where to save the configuration and writes it there. Resolves with
+ * `false` if the user cancelled.
+ */
+export async function exportConfigBundle(): Promise<boolean> {
+  const path = await invoke<string | null>("save_file_dialog", {
+    title: "Export configuration",
+    defaultName: "hamba-config.json",
+    filters: [{ name: "JSON", extensions: ["json"] }],
+  });
+  if (!path) return false;
+  await invoke("export_config_bundle", { path, keybindings: storedKeybindings() });
+  return true;
+}
+
+/**
+ * Asks for a file from `exportConfigBundle` and applies it to the current
+ * profile. Resolves with `null` if the user cancelled.
+ */
+export async function importConfigBundle(): Promise<ConfigImport | null> {
+  const paths = await invoke<string[] | null>("open_file_dialog", {
+    title: "Import configuration",
+    filters: [{ name: "JSON", extensions: ["json"] }],
+  });
+  if (!paths?.[0]) return null;
+  const result = await invoke<ConfigImport>("import_config_bundle", { path: paths[0] });
+  if (result.keybindings) {
+    localStorage.setItem(KEYBINDINGS_KEY, JSON.stringify(result.keybindings));
+    loadCustomBindings();
+    refreshKeyMaps();
+  }
+  return result;
+}
@@ -0,0 +1,16 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/** Mirrors `Entry` in src-tauri/src/context_menu.rs */
+export type ContextMenuEntry =
+  | { type: "item"; id: string; label: string; enabled?: boolean; accelerator?: string }
+  | { type: "checkbox"; id: string; label: string; checked: boolean; enabled?: boolean }
+  | { type: "separator" }
+  | { type: "submenu"; label: string; items: ContextMenuEntry[]; enabled?: boolean };
+
+/**
+ * Shows a native menu at the cursor and resolves with the id of the item
+ * picked, or null when it was dismissed
+ */
+export function showContextMenu(items: ContextMenuEntry[]): Promise<string | null> {
+  return invoke<string | null>("show_context_menu", { items });
+}
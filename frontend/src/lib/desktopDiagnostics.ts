@@ -0,0 +1,25 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for the diagnostics report in src-tauri/src/diagnostics.rs. The
+// Help menu's "Copy Diagnostics" puts the same report on the clipboard.
+
+/** Mirrors `Diagnostics` in src-tauri/src/diagnostics.rs */
+export interface Diagnostics {
+  appVersion: string;
+  /** `null` when the backend didn't answer */
+  backendVersion: string | null;
+  os: string;
+  arch: string;
+  backendPid: number | null;
+  backendUptimeSecs: number | null;
+  backendRestarts: number;
+  backendPort: number;
+  backendExternal: boolean;
+  dataDir: string | null;
+  freeDiskBytes: number | null;
+  recentLogs: string[];
+}
+
+export function getDiagnostics(): Promise<Diagnostics> {
+  return invoke<Diagnostics>("get_diagnostics");
+}
@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/dnd.rs. Native notifications wait while the OS
+// says not to disturb or focus mode is on, and show once both are over.
+// Focus mode also keeps the icon badge as it was until it ends.
+
+/** Mirrors `DndState` in src-tauri/src/dnd.rs */
+export interface DndState {
+  /** `null` where the OS doesn't say */
+  osDoNotDisturb: boolean | null;
+  focusMode: boolean;
+  /** Milliseconds since the Unix epoch */
+  focusEndsAt: number | null;
+  /** Notifications waiting to be shown */
+  held: number;
+}
+
+export function getDndState(): Promise<DndState> {
+  return invoke<DndState>("get_dnd_state");
+}
+
+/** Starting it again while on moves the end */
+export function startFocusMode(minutes: number): Promise<DndState> {
+  return invoke<DndState>("start_focus_mode", { minutes });
+}
+
+export function stopFocusMode(): Promise<DndState> {
+  return invoke<DndState>("stop_focus_mode");
+}
+
+export function onDndChanged(handler: (state: DndState) => void): Promise<UnlistenFn> {
+  return listen<DndState>("dnd-changed", (event) => handler(event.payload));
+}
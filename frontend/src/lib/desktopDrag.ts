@@ -0,0 +1,15 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for src-tauri/src/drag.rs
+
+/**
+ * Drags a file named `name` out of the window, onto the desktop or into
+ * another app. Call it from `dragstart` (and `preventDefault()` the page's
+ * own drag) while the mouse button is still down.
+ */
+export async function startDrag(name: string, contents: Blob | string): Promise<void> {
+  const blob = typeof contents === "string" ? new Blob([contents]) : contents;
+  await invoke("start_drag", new Uint8Array(await blob.arrayBuffer()), {
+    headers: { "X-File-Name": encodeURIComponent(name) },
+  });
+}
@@ -0,0 +1,31 @@
+// Errors from src-tauri/src/command.rs. Typed commands and the IPC guard
+// reject with a `CommandError`; the rest still reject with a plain message,
+// so `errorMessage` handles both.
+
+/** Mirrors `ErrorKind` in src-tauri/src/command.rs */
+export type CommandErrorKind = "invalidInput" | "permissionDenied" | "rateLimited" | "notFound" | "failed";
+
+/** Mirrors `CommandError` in src-tauri/src/command.rs */
+export interface CommandError {
+  kind: CommandErrorKind;
+  /** Meant to be shown to the user as is */
+  message: string;
+  /** The argument, or the setting or key within it, that was invalid */
+  field: string | null;
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    typeof (error as CommandError).kind === "string" &&
+    typeof (error as CommandError).message === "string"
+  );
+}
+
+/** What to show for an error thrown by `invoke`, whichever shape it has */
+export function errorMessage(error: unknown): string {
+  if (isCommandError(error)) return error.message;
+  if (error instanceof Error) return error.message;
+  return String(error);
+}
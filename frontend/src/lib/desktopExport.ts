@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+import { showToast } from "$lib/stores";
+import { errorMessage } from "$lib/desktopErrors";
+
+/**
+ * Asks where to save an export of all mail and writes it there. The format
+ * follows the chosen file's extension.
+ */
+export async function exportData(): Promise<void> {
+  const path = await invoke<string | null>("save_file_dialog", {
+    title: "Export data",
+    defaultName: "hamba-export.json",
+    filters: [
+      { name: "JSON", extensions: ["json"] },
+      { name: "CSV", extensions: ["csv"] },
+    ],
+  });
+  if (!path) return;
+
+  const format = path.toLowerCase().endsWith(".csv") ? "csv" : "json";
+  try {
+    await invoke("export_data", { format, path });
+    showToast("Export complete", "success");
+  } catch (e) {
+    showToast(`Export failed: ${errorMessage(e)}`, "error");
+  }
+}
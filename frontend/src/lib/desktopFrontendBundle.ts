@@ -0,0 +1,38 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/frontend_bundle.rs. Release builds load the
+// frontend through the `app` protocol, which can serve a downloaded bundle in
+// place of the built-in one, so frontend-only fixes ship without a full
+// update.
+
+/** Mirrors `FrontendBundleInfo` in src-tauri/src/frontend_bundle.rs */
+export interface FrontendBundleInfo {
+  /** `null` while the bundle built into the app is in use */
+  version: string | null;
+  appVersion: string;
+}
+
+export function getFrontendBundle(): Promise<FrontendBundleInfo> {
+  return invoke<FrontendBundleInfo>("get_frontend_bundle");
+}
+
+/**
+ * Unpacks a downloaded bundle zip. It needs a `bundle.json` with its
+ * `version` and the `appVersion` it was built for, and only loads in that
+ * app version. Takes effect on `reloadFrontendBundle` or the next launch.
+ * `signature` is the zip's `.sig` from `tauri signer sign`, made with the
+ * key built into the app.
+ */
+export function installFrontendBundle(path: string, signature: string): Promise<string> {
+  return invoke<string>("install_frontend_bundle", { path, signature });
+}
+
+/** Switches to the installed bundle and reloads every window */
+export function reloadFrontendBundle(): Promise<FrontendBundleInfo> {
+  return invoke<FrontendBundleInfo>("reload_frontend_bundle");
+}
+
+/** Goes back to the built-in bundle from the next reload on */
+export function removeFrontendBundle(): Promise<void> {
+  return invoke("remove_frontend_bundle");
+}
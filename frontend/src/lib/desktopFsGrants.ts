@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/fs_scope.rs. File commands only take paths the
+// user granted: files picked in open and save dialogs, files dropped on a
+// window and folders picked with `requestFsAccess`.
+
+export type FsAccess = "read" | "write";
+
+/** Mirrors `FsGrant` in src-tauri/src/fs_scope.rs */
+export interface FsGrant {
+  /** Resolved, with symlinks followed */
+  path: string;
+  /** Covers everything inside it too */
+  folder: boolean;
+  /** `write` includes reading */
+  access: FsAccess;
+  /** Seconds since the Unix epoch */
+  grantedAt: number;
+}
+
+/** Asks the user for a folder to grant `access` to. Resolves with `null` if they cancelled. */
+export function requestFsAccess(access: FsAccess, title?: string): Promise<FsGrant | null> {
+  return invoke<FsGrant | null>("request_fs_access", { title: title ?? null, access });
+}
+
+/** Newest first */
+export function listFsGrants(): Promise<FsGrant[]> {
+  return invoke<FsGrant[]>("list_fs_grants");
+}
+
+/** Resolves with the grants left */
+export function revokeFsGrant(path: string): Promise<FsGrant[]> {
+  return invoke<FsGrant[]>("revoke_fs_grant", { path });
+}
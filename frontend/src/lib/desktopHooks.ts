@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/hooks.rs. Scripts dropped into the hooks
+// folder run on app events with the event as JSON on stdin.
+
+/** Mirrors `HookRun` in src-tauri/src/hooks.rs */
+export interface HookRun {
+  /** Seconds since the Unix epoch */
+  at: number;
+  /** `null` when it couldn't start or was killed */
+  code: number | null;
+  timedOut: boolean;
+  error: string | null;
+}
+
+/** Mirrors `HookEvent` in src-tauri/src/hooks.rs */
+export interface HookEvent {
+  /** e.g. "on-item-created", which is also what the script is named */
+  name: string;
+  scripts: { path: string; lastRun: HookRun | null }[];
+}
+
+/** Mirrors `HookList` in src-tauri/src/hooks.rs */
+export interface HookList {
+  dir: string | null;
+  events: HookEvent[];
+}
+
+export function listHooks(): Promise<HookList> {
+  return invoke<HookList>("list_hooks");
+}
+
+export function openHooksFolder(): Promise<void> {
+  return invoke("open_hooks_folder");
+}
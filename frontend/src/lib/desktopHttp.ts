@@ -0,0 +1,31 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for src-tauri/src/http_fetch.rs. Requests to integrations and
+// webhooks go through Rust, so CORS doesn't get in the way and tokens can stay
+// in the keychain. Only https hosts listed in `httpAllowedHosts` in the
+// desktop settings can be reached; cookies are never sent or kept.
+
+/** Mirrors `FetchRequest` in src-tauri/src/http_fetch.rs */
+export interface HttpRequest {
+  url: string;
+  /** `GET` if left out */
+  method?: string;
+  headers?: Record<string, string>;
+  /** Header name to the key of a secret set with `secretSet` */
+  secretHeaders?: Record<string, string>;
+  body?: string;
+  timeoutMs?: number;
+}
+
+/** Mirrors `FetchResponse` in src-tauri/src/http_fetch.rs */
+export interface HttpResponse {
+  status: number;
+  headers: [string, string][];
+  body: string;
+  /** After redirects */
+  url: string;
+}
+
+export function httpFetch(request: HttpRequest): Promise<HttpResponse> {
+  return invoke<HttpResponse>("http_fetch", { request });
+}
@@ -0,0 +1,9 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for the idle command in src-tauri/src/idle.rs. The app lock it
+// triggers is in desktopLock.ts.
+
+/** Seconds since the last keyboard or mouse input anywhere on the system */
+export function getIdleSeconds(): Promise<number> {
+  return invoke<number>("get_idle_seconds");
+}
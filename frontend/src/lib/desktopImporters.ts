@@ -0,0 +1,68 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/importers. Contacts exported from other apps
+// are parsed in Rust and sent to the backend in batches.
+
+/** Mirrors `ImporterInfo` in src-tauri/src/importers/mod.rs */
+export interface Importer {
+  /** Passed as `format` to `importContacts`, e.g. `google-csv` */
+  id: string;
+  name: string;
+  extensions: string[];
+}
+
+/** Mirrors `RowError` in src-tauri/src/importers/mod.rs */
+export interface ImportRowError {
+  /** The line in a CSV or JSON lines file, the item number in a JSON array */
+  row: number;
+  message: string;
+}
+
+/** Mirrors `ImportReport` in src-tauri/src/importers/mod.rs */
+export interface ImportReport {
+  format: string;
+  rows: number;
+  imported: number;
+  errors: ImportRowError[];
+  /** Failed rows beyond the ones listed in `errors` */
+  moreErrors: number;
+}
+
+/** Mirrors `ImportProgress` in src-tauri/src/importers/mod.rs */
+export interface ContactImportProgress {
+  path: string;
+  rows: number;
+  imported: number;
+  failed: number;
+  done: boolean;
+}
+
+export function listImporters(): Promise<Importer[]> {
+  return invoke<Importer[]>("list_importers");
+}
+
+/**
+ * Asks for a contacts export and imports it into `accountId`. `format` is
+ * detected from the file when left out. Resolves with `null` if the user
+ * cancelled.
+ */
+export async function importContacts(
+  accountId: string,
+  importers: Importer[],
+  format?: string,
+): Promise<ImportReport | null> {
+  const picked = importers.filter((importer) => !format || importer.id === format);
+  const paths = await invoke<string[] | null>("open_file_dialog", {
+    title: "Import contacts",
+    filters: [{ name: "Contacts", extensions: [...new Set(picked.flatMap((importer) => importer.extensions))] }],
+  });
+  if (!paths?.[0]) return null;
+  return invoke<ImportReport>("import_contacts", { path: paths[0], accountId, format: format ?? null });
+}
+
+export function onContactImportProgress(
+  handler: (progress: ContactImportProgress) => void,
+): Promise<UnlistenFn> {
+  return listen<ContactImportProgress>("contact-import-progress", (event) => handler(event.payload));
+}
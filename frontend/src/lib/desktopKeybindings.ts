@@ -0,0 +1,25 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/keybindings.rs. Menu and global shortcuts are
+// kept by the shell; the in-app ones stay in keyboardShortcuts.ts.
+
+/** Mirrors `Keybinding` in src-tauri/src/keybindings.rs */
+export interface Keybinding {
+  action: string;
+  label: string;
+  /** `global` ones work from any app */
+  scope: "menu" | "global";
+  /** e.g. "CmdOrCtrl+Shift+E", `null` when unbound */
+  accelerator: string | null;
+  default: string | null;
+}
+
+export function getKeybindings(): Promise<Keybinding[]> {
+  return invoke<Keybinding[]>("get_keybindings");
+}
+
+/** Rejects when another action already has the shortcut. Resolves with
+ * the updated list. */
+export function setKeybinding(action: string, accelerator: string | null): Promise<Keybinding[]> {
+  return invoke<Keybinding[]>("set_keybinding", { action, accelerator });
+}
@@ -0,0 +1,20 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/kiosk.rs: borderless fullscreen without menus,
+// shortcuts or dev tools, for wall-mounted dashboards. The mode survives
+// restarts until exited.
+
+/** With a `pin`, exiting asks for it */
+export function enterKiosk(pin: string | null): Promise<void> {
+  return invoke("enter_kiosk", { pin });
+}
+
+/** Rejects with "Wrong PIN" when it doesn't match */
+export function exitKiosk(pin: string | null): Promise<void> {
+  return invoke("exit_kiosk", { pin });
+}
+
+export function onKioskChanged(handler: (active: boolean) => void): Promise<UnlistenFn> {
+  return listen<{ active: boolean }>("kiosk-changed", (event) => handler(event.payload.active));
+}
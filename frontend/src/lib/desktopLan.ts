@@ -0,0 +1,20 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for LAN access in src-tauri/src/lan.rs, which lets a phone on the
+// same network open the app by scanning a QR code
+
+/** Mirrors `LanQr` in src-tauri/src/lan.rs */
+export interface LanQr {
+  url: string;
+  /** SVG document of a QR code for `url` */
+  svg: string;
+}
+
+/** Restarts the backend; turning it off unpairs every device */
+export function setLanAccess(enabled: boolean): Promise<void> {
+  return invoke("set_lan_access", { enabled });
+}
+
+export function getLanUrlQr(): Promise<LanQr> {
+  return invoke<LanQr>("get_lan_url_qr");
+}
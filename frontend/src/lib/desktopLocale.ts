@@ -0,0 +1,28 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/locale.rs. The override is `localeOverride` in
+// the desktop settings; the backend gets the same locale when it next starts.
+
+/** Mirrors `Locale` in src-tauri/src/locale.rs */
+export interface SystemLocale {
+  /** BCP 47 tag to pass to `Intl`, with the override applied */
+  locale: string;
+  /** What the OS itself is set to */
+  systemLocale: string;
+  /** 0 for Sunday through 6 for Saturday, as `Date.getDay()` counts */
+  firstDayOfWeek: number;
+  decimalSeparator: string;
+  groupingSeparator: string;
+  uses24HourClock: boolean;
+  dateOrder: "dmy" | "mdy" | "ymd";
+}
+
+export function getSystemLocale(): Promise<SystemLocale> {
+  return invoke<SystemLocale>("get_system_locale");
+}
+
+/** After the OS region or the override changed */
+export function onLocaleChanged(handler: (locale: SystemLocale) => void): Promise<UnlistenFn> {
+  return listen<SystemLocale>("locale-changed", (event) => handler(event.payload));
+}
@@ -0,0 +1,39 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/lock.rs. While the app is locked every other
+// window is hidden behind a lock window, and commands other than these are
+// rejected with a `locked` CommandError.
+
+/** Mirrors `AppLock` in src-tauri/src/lock.rs */
+export interface AppLock {
+  /** A password or PIN is set */
+  enabled: boolean;
+  /** It's a PIN, so ask for digits */
+  pin: boolean;
+  locked: boolean;
+}
+
+export function isAppLocked(): Promise<boolean> {
+  return invoke<boolean>("is_app_locked");
+}
+
+export function getAppLock(): Promise<AppLock> {
+  return invoke<AppLock>("get_app_lock");
+}
+
+export function lockApp(): Promise<void> {
+  return invoke("lock_app");
+}
+
+/** Takes the password or PIN; rejects with "Wrong password" or "Wrong PIN" */
+export function unlockApp(password: string): Promise<void> {
+  return invoke("unlock_app", { password });
+}
+
+/**
+ * Sets the unlock password, or with `pin` a PIN of 4 to 12 digits, and turns
+ * on locking; null turns it off
+ */
+export function setAppLock(password: string | null, pin = false): Promise<void> {
+  return invoke("set_app_lock", { password, pin });
+}
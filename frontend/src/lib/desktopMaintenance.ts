@@ -0,0 +1,37 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/maintenance.rs. The backend is stopped while a
+// task runs and started again afterwards, so requests fail in the meantime.
+
+export type MaintenanceTask = "compact" | "verify" | "repair";
+
+/** Mirrors `MaintenanceReport` in src-tauri/src/maintenance.rs */
+export interface MaintenanceReport {
+  /** No problems were left in the database */
+  ok: boolean;
+  output: string[];
+}
+
+export function compactDatabase(): Promise<MaintenanceReport> {
+  return invoke<MaintenanceReport>("compact_database");
+}
+
+/** `ok` is false when the database is damaged */
+export function verifyDatabase(): Promise<MaintenanceReport> {
+  return invoke<MaintenanceReport>("verify_database");
+}
+
+/** Keeps a copy of the database from before, next to it */
+export function repairDatabase(): Promise<MaintenanceReport> {
+  return invoke<MaintenanceReport>("repair_database");
+}
+
+/** Each line of output as the task prints it */
+export function onMaintenanceProgress(
+  handler: (task: MaintenanceTask, line: string) => void
+): Promise<UnlistenFn> {
+  return listen<{ task: MaintenanceTask; line: string }>("database-maintenance-progress", (event) =>
+    handler(event.payload.task, event.payload.line)
+  );
+}
@@ -0,0 +1,21 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/metrics.rs. With the `metrics` setting on, the
+// shell serves Prometheus metrics on localhost for self-hosters to scrape.
+
+/** Mirrors `MetricsEndpoint` in src-tauri/src/metrics.rs */
+export interface MetricsEndpoint {
+  enabled: boolean;
+  url: string;
+  /** Sent by the scraper as `Authorization: Bearer <token>` */
+  token: string;
+}
+
+export function getMetricsEndpoint(): Promise<MetricsEndpoint> {
+  return invoke<MetricsEndpoint>("get_metrics_endpoint");
+}
+
+/** Replaces the token; scrapers need the new one from then on */
+export function resetMetricsToken(): Promise<MetricsEndpoint> {
+  return invoke<MetricsEndpoint>("reset_metrics_token");
+}
@@ -0,0 +1,47 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// Wrappers for src-tauri/src/monitors.rs. Monitors are picked by their
+// `number`, their name or "primary"; `openOnMonitor` in desktopSettings.ts
+// picks the one the main window opens on.
+
+/** Physical pixels */
+export interface MonitorRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** Mirrors `MonitorInfo` in src-tauri/src/monitors.rs */
+export interface MonitorInfo {
+  /** 1-based, left to right and then top to bottom */
+  number: number;
+  name: string | null;
+  bounds: MonitorRect;
+  workArea: MonitorRect;
+  scaleFactor: number;
+  primary: boolean;
+}
+
+export interface Monitors {
+  monitors: MonitorInfo[];
+  /** The `number` of the monitor this window is on */
+  current: number | null;
+}
+
+export type Snap = "center" | "maximize" | "left" | "right";
+
+export function listMonitors(): Promise<Monitors> {
+  return invoke<Monitors>("list_monitors");
+}
+
+/** Moves this window to the monitor and places it as `snap` says, centered by default */
+export function moveToMonitor(monitor: string | number, snap?: Snap): Promise<MonitorInfo> {
+  return invoke<MonitorInfo>("move_to_monitor", { monitor: String(monitor), snap: snap ?? null });
+}
+
+/** Called with the new layout when a monitor is connected, disconnected, moved or rescaled */
+export function onMonitorsChanged(handler: (monitors: MonitorInfo[]) => void): Promise<UnlistenFn> {
+  return listen<MonitorInfo[]>("monitors-changed", (event) => handler(event.payload));
+}
@@ -0,0 +1,62 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { DesktopSettings } from "$lib/desktopSettings";
+
+// Wrappers for src-tauri/src/os_calendar.rs: EventKit on macOS, and on
+// Windows and Linux a folder of .ics files where `hamba.ics` is the app's
+// own calendar for the user's calendar app to subscribe to. Everything is
+// off until `setOsCalendar(true)`. Times are Unix seconds.
+
+/** Mirrors `Calendar` in src-tauri/src/os_calendar.rs */
+export interface OsCalendar {
+  id: string;
+  title: string;
+  /** Events can be added to it */
+  writable: boolean;
+}
+
+/** Mirrors `Event` in src-tauri/src/os_calendar.rs */
+export interface OsEvent {
+  id: string;
+  calendarId: string;
+  title: string;
+  start: number;
+  end: number;
+  allDay: boolean;
+  notes: string | null;
+}
+
+export interface NewOsEvent {
+  title: string;
+  start: number;
+  end: number;
+  allDay?: boolean;
+  notes?: string;
+}
+
+/** Asks for calendar access when turning on, where the OS needs that */
+export function setOsCalendar(enabled: boolean): Promise<DesktopSettings> {
+  return invoke<DesktopSettings>("set_os_calendar", { enabled });
+}
+
+export function listOsCalendars(): Promise<OsCalendar[]> {
+  return invoke<OsCalendar[]>("list_os_calendars");
+}
+
+/** Events in the calendars picked in `osCalendarRead` */
+export function getOsEvents(from: number, to: number): Promise<OsEvent[]> {
+  return invoke<OsEvent[]>("get_os_events", { from, to });
+}
+
+/** Adds to the calendar picked in `osCalendarWrite`, resolving with the event's id */
+export function addOsEvent(event: NewOsEvent): Promise<string> {
+  return invoke<string>("add_os_event", { event });
+}
+
+export function deleteOsEvent(id: string): Promise<void> {
+  return invoke("delete_os_event", { id });
+}
+
+/** The Calendar app on macOS, the folder with the .ics files elsewhere */
+export function openOsCalendar(): Promise<void> {
+  return invoke("open_os_calendar");
+}
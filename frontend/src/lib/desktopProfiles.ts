@@ -0,0 +1,28 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for the profile commands in src-tauri/src/profile.rs. Each
+// profile has its own data folder and backend.
+
+/** Mirrors `Profiles` in src-tauri/src/profile.rs */
+export interface Profiles {
+  current: string;
+  profiles: string[];
+}
+
+export function listProfiles(): Promise<Profiles> {
+  return invoke<Profiles>("list_profiles");
+}
+
+export function createProfile(name: string): Promise<void> {
+  return invoke("create_profile", { name });
+}
+
+/** Moves the profile to the trash, see desktopTrash.ts */
+export function deleteProfile(name: string): Promise<void> {
+  return invoke("delete_profile", { name });
+}
+
+/** Relaunches the app on `name`, so this never resolves when it switches */
+export function switchProfile(name: string): Promise<void> {
+  return invoke("switch_profile", { name });
+}
@@ -0,0 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for src-tauri/src/recent_items.rs: the macOS dock menu and the
+// Windows jump list. Picking an entry arrives as a `deep-link` event with
+// its url, the same as opening the link from a browser.
+
+/** Mirrors `RecentItem` in src-tauri/src/recent_items.rs */
+export interface RecentItem {
+  title: string;
+  /** A `hamba://` link */
+  url: string;
+  /** Recent items are listed first, actions after them; defaults to `recent` */
+  kind?: "recent" | "action";
+}
+
+/** Replaces every entry; only the first 10 are shown */
+export function setRecentItems(items: RecentItem[]): Promise<void> {
+  return invoke("set_recent_items", { items });
+}
@@ -0,0 +1,68 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { Email } from "./api";
+
+// Wrappers for src-tauri/src/reminders.rs. The shell fires reminders as
+// native notifications with Snooze and Complete buttons, so they show while
+// the window is hidden to the tray. The backend still keeps the reminder.
+
+/** Mirrors `Reminder` in src-tauri/src/reminders.rs */
+export interface DesktopReminder {
+  emailId: string;
+  accountId: string | null;
+  title: string;
+  body: string | null;
+  /** Seconds since the Unix epoch */
+  remindAt: number;
+}
+
+/** Payload of the `reminder-clicked` event */
+export interface ReminderClicked {
+  emailId: string;
+  accountId: string | null;
+}
+
+/** Payload of the `reminder-updated` event, sent after Snooze or Complete */
+export interface ReminderUpdated {
+  emailId: string;
+  /** `null` once completed */
+  remindAt: number | null;
+}
+
+const inDesktopShell = typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
+
+/** Replaces any reminder the email had */
+export function scheduleReminder(reminder: DesktopReminder): Promise<void> {
+  return invoke("schedule_reminder", { reminder });
+}
+
+export function cancelReminder(emailId: string): Promise<void> {
+  return invoke("cancel_reminder", { emailId });
+}
+
+export function listReminders(): Promise<DesktopReminder[]> {
+  return invoke<DesktopReminder[]>("list_reminders");
+}
+
+/** Has the shell fire the reminder just set on `email`; does nothing in a browser */
+export function scheduleEmailReminder(
+  email: Pick<Email, "id" | "subject"> & {
+    account_id: string | null;
+    from_name?: string;
+    from_email?: string;
+  },
+  remindAt: number,
+): void {
+  if (!inDesktopShell) return;
+  scheduleReminder({
+    emailId: email.id,
+    accountId: email.account_id,
+    title: email.subject.slice(0, 200),
+    body: email.from_name || email.from_email || null,
+    remindAt,
+  }).catch(() => {});
+}
+
+export function cancelEmailReminder(emailId: string): void {
+  if (!inDesktopShell) return;
+  cancelReminder(emailId).catch(() => {});
+}
@@ -0,0 +1,9 @@
+// Wrapper for src-tauri/src/safe_mode.rs: a launch with `--safe-mode` or
+// Shift held down, for getting past a bad configuration. Settings are
+// defaults that aren't saved, and autostart, hamba.env, helper sidecars and
+// scheduled jobs are skipped until the app is started normally again.
+
+/** Set before any frontend code runs, so it can be checked synchronously */
+export function isSafeMode(): boolean {
+  return window.__SAFE_MODE__ === true;
+}
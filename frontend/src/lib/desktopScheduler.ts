@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for the job scheduler in src-tauri/src/scheduler.rs. Jobs call a
+// backend endpoint on a cron schedule and keep running while the window is
+// hidden to the tray.
+
+/** Mirrors `Job` in src-tauri/src/scheduler.rs */
+export interface ScheduledJob {
+  id: string;
+  /** `minute hour day month weekday` in local time, or `@hourly`, `@daily`, `@weekly`, `@monthly` */
+  schedule: string;
+  method?: "GET" | "POST" | "PUT" | "PATCH" | "DELETE";
+  /** Backend path, e.g. `/emails/sync/<accountId>` */
+  path: string;
+  body?: unknown;
+}
+
+/** Mirrors `JobInfo`; times are seconds since the Unix epoch */
+export interface ScheduledJobInfo extends ScheduledJob {
+  nextRun: number | null;
+  lastRun: number | null;
+}
+
+/** Adds the job, or replaces the one with the same id */
+export function scheduleJob(job: ScheduledJob): Promise<ScheduledJobInfo> {
+  return invoke<ScheduledJobInfo>("schedule_job", { job });
+}
+
+export function cancelJob(id: string): Promise<void> {
+  return invoke("cancel_job", { id });
+}
+
+export function listJobs(): Promise<ScheduledJobInfo[]> {
+  return invoke<ScheduledJobInfo[]>("list_jobs");
+}
@@ -0,0 +1,72 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { goto } from "$app/navigation";
+
+// Wrappers for src-tauri/src/session.rs. Each window reports its route and
+// scroll positions so the app can offer to put them back after a crash.
+
+/** Mirrors `WindowSnapshot` in src-tauri/src/session.rs */
+export interface WindowSnapshot {
+  label: string;
+  route: string;
+  title: string | null;
+  /** Offsets by `data-scroll-key`, `window` for the page itself */
+  scroll: Record<string, number>;
+}
+
+// Cheap enough to check this often; only changes are sent
+const REPORT_INTERVAL_MS = 2000;
+
+function currentRoute(): string {
+  return window.location.pathname + window.location.search + window.location.hash;
+}
+
+/** Elements marked `data-scroll-key="..."` have their scroll kept too */
+function currentScroll(): Record<string, number> {
+  const scroll: Record<string, number> = { window: window.scrollY };
+  document.querySelectorAll<HTMLElement>("[data-scroll-key]").forEach((element) => {
+    scroll[element.dataset.scrollKey!] = element.scrollTop;
+  });
+  return scroll;
+}
+
+async function restore(): Promise<void> {
+  const snapshot = await invoke<WindowSnapshot | null>("take_session_restore");
+  if (!snapshot) return;
+  if (snapshot.route !== currentRoute()) await goto(snapshot.route);
+  // Lists render after navigation, so give them a frame before scrolling
+  requestAnimationFrame(() => {
+    for (const [key, offset] of Object.entries(snapshot.scroll)) {
+      if (key === "window") {
+        window.scrollTo(0, offset);
+        continue;
+      }
+      const element = document.querySelector<HTMLElement>(
+        `[data-scroll-key="${CSS.escape(key)}"]`,
+      );
+      if (element) element.scrollTop = offset;
+    }
+  });
+}
+
+/** Reports this window's state from now on and applies a session restore
+ * when the user picks one. Returns a function that stops it. */
+export function trackSession(): () => void {
+  let last = "";
+  const report = () => {
+    const state = { route: currentRoute(), scroll: currentScroll() };
+    const serialized = JSON.stringify(state);
+    if (serialized === last) return;
+    last = serialized;
+    invoke("report_session_state", state).catch(() => {});
+  };
+  report();
+  const timer = setInterval(report, REPORT_INTERVAL_MS);
+  const stopListener = listen("session-restore", () => restore().catch(() => {}));
+  restore().catch(() => {});
+
+  return () => {
+    clearInterval(timer);
+    stopListener.then((unlisten) => unlisten());
+  };
+}
@@ -0,0 +1,60 @@
+import { describe, it, expect, vi, beforeEach, afterEach } from "vitest";
+
+const { invoke } = vi.hoisted(() => ({ invoke: vi.fn() }));
+vi.mock("@tauri-apps/api/core", () => ({ invoke }));
+
+import { migrateLocalSettings } from "./desktopSettings";
+
+describe("desktopSettings module", () => {
+  let mockLocalStorage: Record<string, string>;
+
+  beforeEach(() => {
+    mockLocalStorage = {};
+    vi.spyOn(Storage.prototype, "getItem").mockImplementation(
+      (key: string) => mockLocalStorage[key] ?? null
+    );
+    vi.spyOn(Storage.prototype, "setItem").mockImplementation(
+      (key: string, value: string) => {
+        mockLocalStorage[key] = value;
+      }
+    );
+    invoke.mockReset();
+    invoke.mockResolvedValue({});
+  });
+
+  afterEach(() => {
+    vi.restoreAllMocks();
+  });
+
+  describe("migrateLocalSettings", () => {
+    it("copies saved preferences into the desktop store once", async () => {
+      mockLocalStorage["settings.theme"] = "light";
+      mockLocalStorage["settings.fontSize"] = "16";
+      mockLocalStorage["settings.sound"] = "false";
+
+      await migrateLocalSettings();
+
+      expect(invoke).toHaveBeenCalledWith("update_settings", {
+        patch: { themeOverride: "light", fontSize: 16, notificationSound: false },
+      });
+      expect(mockLocalStorage["settings.migratedToDesktop"]).toBe("true");
+    });
+
+    it("only reads the desktop store after migrating", async () => {
+      mockLocalStorage["settings.migratedToDesktop"] = "true";
+      mockLocalStorage["settings.theme"] = "light";
+
+      await migrateLocalSettings();
+
+      expect(invoke).toHaveBeenCalledTimes(1);
+      expect(invoke).toHaveBeenCalledWith("get_settings");
+    });
+
+    it("does not mark the migration done when saving fails", async () => {
+      invoke.mockRejectedValue(new Error("nope"));
+
+      await expect(migrateLocalSettings()).rejects.toThrow();
+      expect(mockLocalStorage["settings.migratedToDesktop"]).toBeUndefined();
+    });
+  });
+});
@@ -0,0 +1,109 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Mirrors `Settings` in src-tauri/src/settings.rs. Only the fields the
+// frontend reads or writes are listed.
+export interface DesktopSettings {
+  themeOverride: "light" | "dark" | null;
+  /** Changed through `setForcedColorsOverride` in desktopAccessibility.ts */
+  forcedColorsOverride: boolean | null;
+  /** Changed through `setSpellcheck` in desktopSpellcheck.ts */
+  spellcheck: boolean;
+  fontSize: number;
+  accentColor: string | null;
+  notifications: boolean;
+  notifyImportantOnly: boolean;
+  notificationSound: boolean;
+  idleMinutes: number;
+  /** Read-only here; changed through `setAppLock` in desktopLock.ts */
+  lockWhenIdle: boolean;
+  /** Read-only here; set along with `lockWhenIdle` */
+  appLockPin: boolean;
+  lockOnSleep: boolean;
+  /** When the backend's background work is throttled to save power */
+  batterySaver: "auto" | "on" | "off";
+  /** Battery level at or below which "auto" turns battery saver on, 5 to 95 */
+  batterySaverPercent: number;
+  /** Minutes `authenticate` in desktopAuth.ts goes without asking again */
+  authGraceMinutes: number;
+  /** Read-only here; changed through `setLanAccess` in desktopLan.ts */
+  lanAccess: boolean;
+  /** Read-only here; changed through `setBackendPort` */
+  backendPort: number | null;
+  pinBackendPort: boolean;
+  /** Read-only here; changed through `enterKiosk` and `exitKiosk` in desktopKiosk.ts */
+  kiosk: boolean;
+  /** Read-only here; changed through `setOsCalendar` in desktopOsCalendar.ts */
+  osCalendar: boolean;
+  /** Read-only here; changed through `setBackendLogLevel` in desktopBackendControl.ts */
+  backendLogLevel: string | null;
+  /** Read-only here; changed through `setBackendFlag` in desktopBackendControl.ts */
+  backendFlags: Record<string, boolean>;
+  osCalendarRead: string[];
+  osCalendarWrite: string | null;
+  /** BCP 47 tag like "de-CH" used instead of the OS's, see desktopLocale.ts */
+  localeOverride: string | null;
+  /** Hosts `httpFetch` in desktopHttp.ts may reach, like "*.example.com" */
+  httpAllowedHosts: string[];
+  /** Release updates stay on, older or newer, instead of following the channel */
+  pinnedVersion: string | null;
+  /** Run the scripts in the hooks folder on app events, see desktopHooks.ts */
+  hooks: boolean;
+  /** Days deleted files stay in the trash, 1 to 365, see desktopTrash.ts */
+  trashRetentionDays: number;
+  /** Serve Prometheus metrics on `metricsPort`, see desktopMetrics.ts */
+  metrics: boolean;
+  metricsPort: number;
+  /** Monitor the main window opens on, see desktopMonitors.ts */
+  openOnMonitor: string | null;
+}
+
+/**
+ * Pins the backend to `port` from the next launch on. `null` unpins it, after
+ * which each launch keeps whichever port it ended up with.
+ */
+export function setBackendPort(port: number | null): Promise<void> {
+  return invoke("set_backend_port", { port });
+}
+
+const MIGRATED_KEY = "settings.migratedToDesktop";
+
+export function isDesktop(): boolean {
+  return typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
+}
+
+export function getDesktopSettings(): Promise<DesktopSettings> {
+  return invoke<DesktopSettings>("get_settings");
+}
+
+/** Saves the given fields and resolves with the full settings afterwards */
+export function updateDesktopSettings(patch: Partial<DesktopSettings>): Promise<DesktopSettings> {
+  return invoke<DesktopSettings>("update_settings", { patch });
+}
+
+/**
+ * Copies preferences saved in localStorage by the web build into the desktop
+ * settings store the first time it runs.
+ */
+export async function migrateLocalSettings(): Promise<DesktopSettings> {
+  if (localStorage.getItem(MIGRATED_KEY) === "true") {
+    return getDesktopSettings();
+  }
+
+  const patch: Partial<DesktopSettings> = {};
+  const theme = localStorage.getItem("settings.theme");
+  if (theme === "light" || theme === "dark") patch.themeOverride = theme;
+  const fontSize = Number(localStorage.getItem("settings.fontSize"));
+  if (fontSize) patch.fontSize = fontSize;
+  const accentColor = localStorage.getItem("settings.accentColor");
+  if (accentColor) patch.accentColor = accentColor;
+  const notifications = localStorage.getItem("settings.notifications");
+  if (notifications !== null) patch.notifications = notifications === "true";
+  const importantOnly = localStorage.getItem("settings.notifyImportantOnly");
+  if (importantOnly !== null) patch.notifyImportantOnly = importantOnly === "true";
+  const sound = localStorage.getItem("settings.sound");
+  if (sound !== null) patch.notificationSound = sound !== "false";
+
+  const settings = await updateDesktopSettings(patch);
+  localStorage.setItem(MIGRATED_KEY, "true");
+  return settings;
+}
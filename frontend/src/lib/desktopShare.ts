@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrapper for src-tauri/src/share.rs: the macOS share sheet, the Windows
+// Share UI and, on Linux, a new email through the desktop portal.
+
+/** Mirrors `SharePayload` in src-tauri/src/share.rs */
+export interface SharePayload {
+  /** Used as the subject where the target has one */
+  title?: string;
+  text?: string;
+  url?: string;
+  /** Absolute paths, e.g. of an export */
+  files?: string[];
+}
+
+/**
+ * `copied` means there was no share UI, so the payload went to the clipboard
+ * as text instead; worth telling the user.
+ */
+export type ShareOutcome = "shared" | "copied";
+
+export function share(payload: SharePayload): Promise<ShareOutcome> {
+  return invoke<ShareOutcome>("share", { payload });
+}
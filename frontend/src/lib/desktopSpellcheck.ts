@@ -0,0 +1,22 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/spellcheck.rs. Whether spellchecking is on is
+// `spellcheck` in the desktop settings.
+
+export function setSpellcheck(enabled: boolean): Promise<void> {
+  return invoke("set_spellcheck", { enabled });
+}
+
+/** Words added to the custom dictionary, sorted */
+export function getDictionaryWords(): Promise<string[]> {
+  return invoke<string[]>("get_dictionary_words");
+}
+
+/** Rejects when `word` is empty, too long or has spaces in it */
+export function addDictionaryWord(word: string): Promise<void> {
+  return invoke("add_dictionary_word", { word });
+}
+
+export function removeDictionaryWord(word: string): Promise<void> {
+  return invoke("remove_dictionary_word", { word });
+}
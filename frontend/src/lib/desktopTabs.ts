@@ -0,0 +1,28 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/tabs.rs. Tabs are windows labelled `tab-N`
+// that the app reopens on the next launch; on macOS they share the main
+// window's tab bar.
+
+/** Mirrors `TabInfo` in src-tauri/src/tabs.rs */
+export interface Tab {
+  label: string;
+  /** App path the tab is on, e.g. `/email/123` */
+  route: string;
+  title: string | null;
+  focused: boolean;
+}
+
+/** Resolves with the new tab's label */
+export function openTab(route?: string, title?: string): Promise<string> {
+  return invoke<string>("open_tab", { route: route ?? null, title: title ?? null });
+}
+
+export function closeTab(label: string): Promise<void> {
+  return invoke("close_tab", { label });
+}
+
+/** In the order they were opened */
+export function listTabs(): Promise<Tab[]> {
+  return invoke<Tab[]>("list_tabs");
+}
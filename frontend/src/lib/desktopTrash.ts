@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/trash.rs. Files the app would delete, like the
+// data a backup restore replaces or a deleted profile, go here first and are
+// purged after `trashRetentionDays`.
+
+/** Mirrors `TrashEntry` in src-tauri/src/trash.rs */
+export interface TrashEntry {
+  id: string;
+  /** What deleted it, e.g. "Profile work deleted" */
+  reason: string;
+  /** Seconds since the Unix epoch */
+  deletedAt: number;
+  /** Where each item was, and goes back to */
+  items: string[];
+  size: number;
+}
+
+/** Newest first */
+export function listTrash(): Promise<TrashEntry[]> {
+  return invoke<TrashEntry[]>("list_trash");
+}
+
+/** Puts the files back; whatever is in their place now goes to the trash */
+export function restoreFromTrash(id: string): Promise<void> {
+  return invoke("restore_from_trash", { id });
+}
+
+export function deleteFromTrash(id: string): Promise<void> {
+  return invoke("delete_from_trash", { id });
+}
+
+export function emptyTrash(): Promise<void> {
+  return invoke("empty_trash");
+}
@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/rollback.rs. Each update keeps the version it
+// replaces, app and backend, so a bad release can be undone without
+// reinstalling. The Help menu offers the same.
+
+/** Mirrors `PreviousVersion` in src-tauri/src/rollback.rs */
+export interface PreviousVersion {
+  version: string;
+  /** Seconds since the Unix epoch */
+  savedAt: number;
+}
+
+export function getPreviousVersion(): Promise<PreviousVersion | null> {
+  return invoke<PreviousVersion | null>("get_previous_version");
+}
+
+/**
+ * Restarts into the previous version and pins updates to it, see
+ * `pinnedVersion` in desktopSettings.ts. Only resolves if it failed.
+ */
+export function rollbackUpdate(): Promise<void> {
+  return invoke("rollback_update");
+}
@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/watch_folders.rs. New files in a watched folder
+// are imported as if dropped on the window, and each one emits
+// `watch-folder-import` with its history entry.
+
+/** Mirrors `Rules` in src-tauri/src/watch_folders.rs */
+export interface WatchFolderRules {
+  /** Matched against file names, e.g. `*.eml` */
+  patterns: string[];
+  recursive: boolean;
+  deleteAfterImport: boolean;
+  enabled: boolean;
+}
+
+/** Mirrors `WatchFolder` in src-tauri/src/watch_folders.rs */
+export interface WatchFolder extends WatchFolderRules {
+  id: string;
+  path: string;
+}
+
+/** Mirrors `HistoryEntry` in src-tauri/src/watch_folders.rs */
+export interface WatchFolderImport {
+  folderId: string;
+  source: string;
+  /** `null` if the import was rejected, see `error` */
+  path: string | null;
+  error: string | null;
+  /** Seconds since the Unix epoch */
+  importedAt: number;
+}
+
+export function listWatchFolders(): Promise<WatchFolder[]> {
+  return invoke<WatchFolder[]>("list_watch_folders");
+}
+
+/**
+ * Watches `path`, or a folder the user picks when it's left out. Files
+ * already there are not imported. Resolves with `null` if the user cancelled.
+ */
+export function addWatchFolder(path?: string, rules?: Partial<WatchFolderRules>): Promise<WatchFolder | null> {
+  return invoke<WatchFolder | null>("add_watch_folder", { path: path ?? null, rules: rules ?? null });
+}
+
+export function updateWatchFolder(id: string, rules: WatchFolderRules): Promise<WatchFolder> {
+  return invoke<WatchFolder>("update_watch_folder", { id, rules });
+}
+
+export function removeWatchFolder(id: string): Promise<void> {
+  return invoke("remove_watch_folder", { id });
+}
+
+/** Newest first, for one folder or all of them */
+export function getWatchFolderHistory(folderId?: string): Promise<WatchFolderImport[]> {
+  return invoke<WatchFolderImport[]>("get_watch_folder_history", { folderId: folderId ?? null });
+}
+
+export function clearWatchFolderHistory(): Promise<void> {
+  return invoke("clear_watch_folder_history");
+}
@@ -0,0 +1,64 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/webhooks.rs. Events are POSTed as JSON to the
+// configured URLs, signed with HMAC-SHA256 in `X-Hamba-Signature` over
+// `<X-Hamba-Timestamp>.<body>`, and retried with backoff until they go through.
+
+/** Mirrors `WebhookConfig` in src-tauri/src/webhooks.rs */
+export interface WebhookConfig {
+  url: string;
+  /** Backend event types like "new_mail", "backup_complete", or "*" for all */
+  events: string[];
+  enabled: boolean;
+}
+
+/** Mirrors `Webhook` in src-tauri/src/webhooks.rs */
+export interface Webhook extends WebhookConfig {
+  id: string;
+}
+
+/** Mirrors `DeliveryAttempt` in src-tauri/src/webhooks.rs */
+export interface WebhookDelivery {
+  deliveryId: string;
+  webhookId: string;
+  event: string;
+  attempt: number;
+  /** Seconds since the Unix epoch */
+  at: number;
+  /** `null` when the receiver couldn't be reached */
+  status: number | null;
+  error: string | null;
+  /** Delivered, or given up on */
+  done: boolean;
+}
+
+export function listWebhooks(): Promise<Webhook[]> {
+  return invoke<Webhook[]>("list_webhooks");
+}
+
+/** Resolves with the webhook and the secret its payloads are signed with */
+export function addWebhook(config: WebhookConfig): Promise<Webhook & { secret: string }> {
+  return invoke<Webhook & { secret: string }>("add_webhook", { config });
+}
+
+export function updateWebhook(id: string, config: WebhookConfig): Promise<Webhook> {
+  return invoke<Webhook>("update_webhook", { id, config });
+}
+
+export function removeWebhook(id: string): Promise<void> {
+  return invoke("remove_webhook", { id });
+}
+
+export function getWebhookSecret(id: string): Promise<string> {
+  return invoke<string>("get_webhook_secret", { id });
+}
+
+/** Queues a "ping" event for the webhook; see `getWebhookDeliveries` for how it went */
+export function testWebhook(id: string): Promise<void> {
+  return invoke("test_webhook", { id });
+}
+
+/** Newest first, for one webhook or all of them */
+export function getWebhookDeliveries(webhookId?: string): Promise<WebhookDelivery[]> {
+  return invoke<WebhookDelivery[]>("get_webhook_deliveries", { webhookId: webhookId ?? null });
+}
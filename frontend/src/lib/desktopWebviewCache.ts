@@ -0,0 +1,20 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Wrappers for src-tauri/src/webview_cache.rs. These only touch the
+// webview's own data; mail and settings in the backend stay as they are.
+// Launching with `--safe-mode` clears the cache before the window loads.
+
+/** Bytes the webview caches take up on disk */
+export function getCacheSize(): Promise<number> {
+  return invoke<number>("get_cache_size");
+}
+
+/** Leaves cookies and local storage alone; reload afterwards */
+export function clearWebviewCache(): Promise<void> {
+  return invoke("clear_webview_cache");
+}
+
+/** Resolves with how many cookies were deleted */
+export function clearCookies(): Promise<number> {
+  return invoke<number>("clear_cookies");
+}
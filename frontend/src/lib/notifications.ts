@@ -93,6 +93,9 @@ export function shouldNotify(isImportant: boolean = false): boolean {
 
   if (!settings.enabled) return false;
 
+  // The desktop app shows native notifications itself
+  if (typeof window !== "undefined" && "__TAURI_INTERNALS__" in window) return false;
+
   // Check browser permission
   if (typeof Notification === "undefined" || Notification.permission !== "granted") {
     return false;
@@ -3,6 +3,8 @@
  */
 
 import { writable, get } from "svelte/store";
+import { apiUrl } from "./api";
+import { getBackendEvents, onBackendConnection, onBackendEvent, type BackendEvent } from "./desktopBackendEvents";
 
 type MessageHandler = (data: any) => void;
 
@@ -25,9 +27,70 @@ let currentReconnectDelay = INITIAL_RECONNECT_DELAY;
 // Subscriptions to re-subscribe after reconnect
 const activeSubscriptions = new Set<string>();
 
-const WS_URL = import.meta.env.VITE_WS_URL ?? "ws://localhost:8877/ws";
+const WS_URL = import.meta.env.VITE_WS_URL ?? apiUrl("/ws").replace(/^http/, "ws");
+
+// In the desktop app the shell holds the connection and forwards messages to
+// every window, see src-tauri/src/backend_events.rs
+const inDesktopShell = typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
+// Per window, so a reload picks up where it left off
+const LAST_EVENT_KEY = "hamba:lastBackendEvent";
+let stopDesktop: (() => void) | null = null;
+let lastSeq = 0;
+
+function deliver(event: BackendEvent): void {
+  if (event.seq <= lastSeq) return;
+  lastSeq = event.seq;
+  sessionStorage.setItem(LAST_EVENT_KEY, String(lastSeq));
+  handlers.forEach(handler => handler(event));
+}
+
+function connectDesktop(): void {
+  if (stopDesktop) return;
+  connectionState.set("connecting");
+
+  let stopped = false;
+  // Live events wait until the missed ones are delivered, so they stay in order
+  let early: BackendEvent[] | null = [];
+  const listeners = [
+    onBackendEvent(event => (early ? early.push(event) : deliver(event))),
+    onBackendConnection(connected => connectionState.set(connected ? "connected" : "reconnecting")),
+  ];
+  stopDesktop = () => {
+    stopped = true;
+    listeners.forEach(listener => listener.then(unlisten => unlisten()));
+  };
+
+  const since = Number(sessionStorage.getItem(LAST_EVENT_KEY) ?? 0);
+  Promise.all(listeners)
+    .then(() => getBackendEvents(since))
+    .then(replay => {
+      if (stopped) return;
+      connectionState.set(replay.connected ? "connected" : "reconnecting");
+      if (since === 0) {
+        // A new window loads everything fresh anyway
+        lastSeq = replay.latest;
+      } else {
+        if (!replay.complete) {
+          handlers.forEach(handler => handler({ type: "reconnected" }));
+        }
+        replay.events.forEach(deliver);
+      }
+      const pending = early ?? [];
+      early = null;
+      pending.forEach(deliver);
+    })
+    .catch(e => {
+      console.error("[Realtime] Failed to catch up on backend events:", e);
+      early?.forEach(deliver);
+      early = null;
+    });
+}
 
 export function connect(): void {
+  if (inDesktopShell) {
+    connectDesktop();
+    return;
+  }
   if (ws?.readyState === WebSocket.OPEN) {
     return;
   }
@@ -105,6 +168,10 @@ function scheduleReconnect(): void {
 }
 
 export function disconnect(): void {
+  if (stopDesktop) {
+    stopDesktop();
+    stopDesktop = null;
+  }
   if (reconnectTimeout) {
     clearTimeout(reconnectTimeout);
     reconnectTimeout = null;
@@ -135,6 +202,9 @@ export function unsubscribe(accountId: string): void {
  * Manually trigger a reconnection attempt (resets backoff)
  */
 export function reconnectNow(): void {
+  // The shell reconnects on its own and replays what came in meanwhile
+  if (inDesktopShell) return;
+
   // Clear any pending reconnect
   if (reconnectTimeout) {
     clearTimeout(reconnectTimeout);
@@ -161,5 +231,6 @@ export function onMessage(handler: MessageHandler): () => void {
 }
 
 export function isConnected(): boolean {
+  if (inDesktopShell) return get(connectionState) === "connected";
   return ws?.readyState === WebSocket.OPEN;
 }
@@ -1,6 +1,7 @@
 import { writable, derived, get } from "svelte/store";
 import type { Account, Email, Draft, Label, ScheduledEmail, Snippet } from "./api";
 import { api } from "./api";
+import { cancelEmailReminder, scheduleEmailReminder } from "./desktopReminders";
 
 // Current state
 export const accounts = writable<Account[]>([]);
@@ -445,6 +446,10 @@ export const emailActions = {
     );
 
     api.setReminder(emailId, remindAt).then(() => {
+      scheduleEmailReminder(
+        email ?? { id: emailId, account_id: get(selectedAccountId), subject: "Email reminder" },
+        remindAt,
+      );
       const reminderDate = new Date(remindAt);
       const timeStr = reminderDate.toLocaleString(undefined, {
         month: "short",
@@ -490,6 +495,7 @@ export const emailActions = {
     }
 
     api.clearReminder(emailId).then(() => {
+      cancelEmailReminder(emailId);
       showToast("Reminder cleared", "success", previousRemindAt ? {
         action: {
           label: "Undo",
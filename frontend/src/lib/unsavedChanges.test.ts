@@ -0,0 +1,18 @@
+import { describe, it, expect } from "vitest";
+import { registerUnsavedCheck, hasUnsavedChanges } from "./unsavedChanges";
+
+describe("unsavedChanges module", () => {
+  it("reports nothing unsaved without checks", () => {
+    expect(hasUnsavedChanges()).toBe(false);
+  });
+
+  it("reports unsaved changes while any check says so", () => {
+    const clean = registerUnsavedCheck(() => false);
+    const dirty = registerUnsavedCheck(() => true);
+    expect(hasUnsavedChanges()).toBe(true);
+
+    dirty();
+    expect(hasUnsavedChanges()).toBe(false);
+    clean();
+  });
+});
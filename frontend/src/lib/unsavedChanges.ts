@@ -0,0 +1,32 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+type UnsavedCheck = () => boolean;
+
+const checks = new Set<UnsavedCheck>();
+
+/**
+ * Registers a check the desktop app runs before quitting. Returns a function
+ * that removes it again.
+ */
+export function registerUnsavedCheck(check: UnsavedCheck): () => void {
+  checks.add(check);
+  return () => checks.delete(check);
+}
+
+export function hasUnsavedChanges(): boolean {
+  for (const check of checks) {
+    if (check()) return true;
+  }
+  return false;
+}
+
+/** Answers the desktop app's `close-requested` question */
+export function listenForCloseRequests(): Promise<UnlistenFn> {
+  return listen<{ id: number }>("close-requested", (event) => {
+    invoke("report_unsaved_changes", {
+      id: event.payload.id,
+      unsaved: hasUnsavedChanges(),
+    }).catch(() => {});
+  });
+}